@@ -13,6 +13,8 @@ where maturin
 echo Building Rust core...
 cd rust_core
 maturin develop --release
+echo Regenerating cesarops_core.pyi stubs...
+python scripts\gen_stubs.py
 
 echo Done!
 pause
\ No newline at end of file
@@ -3,5 +3,7 @@ call conda activate cesarops
 cd rust_core
 echo Building Rust extension...
 maturin develop --release
+echo Regenerating cesarops_core.pyi stubs...
+python scripts\gen_stubs.py
 cd ..
 echo Build complete!
\ No newline at end of file
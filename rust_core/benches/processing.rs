@@ -0,0 +1,146 @@
+// Benchmarks for the sonar processing pipeline on synthetic surveys
+// benches/processing.rs
+//
+// Run with: cargo bench --no-default-features
+
+use cesarops_core::contours::generate_contours;
+use cesarops_core::dedup::{reconcile, DedupConfig};
+use cesarops_core::detection::{detect_targets, DetectionConfig};
+use cesarops_core::gridding::{grid_soundings, GridConfig, GridMethod};
+use cesarops_core::parsers::nmea0183::parse_nmea0183;
+use cesarops_core::parsers::ParseMode;
+use cesarops_core::survey::{Ping, Sounding};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// Lawnmower survey over a sloping lake bed with a little depth noise
+fn synthetic_soundings(n: usize, seed: u64) -> Vec<Sounding> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let per_line = 200;
+    (0..n)
+        .map(|i| {
+            let line = (i / per_line) as f64;
+            let along = (i % per_line) as f64;
+            let along = if (i / per_line) % 2 == 0 { along } else { per_line as f64 - along };
+            let lat = 43.0 + line * 0.00005;
+            let lon = -87.0 + along * 0.00003;
+            let depth = 3.0 + along * 0.05 + line * 0.02 + rng.gen::<f64>() * 0.2;
+            Sounding::new(i as f64, lat, lon, depth)
+        })
+        .collect()
+}
+
+/// NMEA GGA log where `corruption` is the fraction of sentences with bad checksums
+fn synthetic_nmea(n: usize, corruption: f64, seed: u64) -> String {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut text = String::with_capacity(n * 80);
+    for i in 0..n {
+        let secs = i % 60;
+        let mins = (i / 60) % 60;
+        let body = format!(
+            "GPGGA,12{:02}{:02},4300.{:04},N,08700.{:04},W,1,08,0.9,176.0,M,-34.0,M,,",
+            mins,
+            secs,
+            i % 10_000,
+            (i * 3) % 10_000
+        );
+        let mut checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+        if rng.gen::<f64>() < corruption {
+            checksum ^= 0x5A;
+        }
+        text.push_str(&format!("${}*{:02X}\n", body, checksum));
+    }
+    text
+}
+
+/// Down-looking pings with a bottom echo and occasional fish arches
+fn synthetic_pings(n: usize, samples: usize, seed: u64) -> Vec<Ping> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..n)
+        .map(|i| {
+            let mut data: Vec<u8> = (0..samples).map(|_| rng.gen_range(0..40)).collect();
+            let bottom = samples * 3 / 4;
+            data[bottom..].iter_mut().for_each(|v| *v = 220);
+            if i % 50 < 4 {
+                data[samples / 3..samples / 3 + 5].iter_mut().for_each(|v| *v = 200);
+            }
+            Ping {
+                timestamp: i as f64,
+                lat: 43.0 + i as f64 * 1e-5,
+                lon: -87.0,
+                depth_m: 15.0,
+                range_m: 20.0,
+                samples: data,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+fn bench_gridding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gridding");
+    for &n in &SIZES {
+        let soundings = synthetic_soundings(n, 1);
+        group.throughput(Throughput::Elements(n as u64));
+        for (name, method) in [
+            ("nearest", GridMethod::Nearest),
+            ("idw", GridMethod::InverseDistance { power: 2.0 }),
+            ("tin", GridMethod::Triangulation),
+        ] {
+            if name == "tin" && n > 10_000 {
+                continue;
+            }
+            let config = GridConfig {
+                method,
+                ..Default::default()
+            };
+            group.bench_with_input(BenchmarkId::new(name, n), &soundings, |b, s| {
+                b.iter(|| grid_soundings(black_box(s), &config))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_contours(c: &mut Criterion) {
+    let grid = grid_soundings(&synthetic_soundings(100_000, 2), &GridConfig::default()).unwrap();
+    c.bench_function("contours_1m", |b| b.iter(|| generate_contours(black_box(&grid), 1.0)));
+}
+
+fn bench_dedup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dedup");
+    for &n in &SIZES {
+        let soundings = synthetic_soundings(n, 3);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &soundings, |b, s| {
+            b.iter(|| reconcile(black_box(s), &DedupConfig::default()))
+        });
+    }
+    group.finish();
+}
+
+fn bench_nmea(c: &mut Criterion) {
+    let mut group = c.benchmark_group("nmea0183");
+    for corruption in [0.0, 0.01, 0.2] {
+        let text = synthetic_nmea(100_000, corruption, 4);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::new("corruption", corruption), &text, |b, t| {
+            b.iter(|| parse_nmea0183(black_box(t), ParseMode::Lenient))
+        });
+    }
+    group.finish();
+}
+
+fn bench_detection(c: &mut Criterion) {
+    let pings = synthetic_pings(5_000, 1_000, 5);
+    let refs: Vec<&Ping> = pings.iter().collect();
+    c.bench_function("detect_targets_5k", |b| {
+        b.iter(|| detect_targets(black_box(&refs), &DetectionConfig::default()))
+    });
+}
+
+criterion_group!(benches, bench_gridding, bench_contours, bench_dedup, bench_nmea, bench_detection);
+criterion_main!(benches);
@@ -0,0 +1,309 @@
+# Generated by scripts/gen_stubs.py from the built extension; do not edit by hand
+
+from typing import Any
+
+
+class ParseError(OSError):
+    """A recording is malformed or truncated"""
+
+
+class PingBatches:
+    """Iterator yielding lists of up to `batch_size` ping dicts"""
+
+    def __iter__(self) -> PingBatches: ...
+
+    def __next__(self) -> Any: ...
+
+    def _repr_html_(self) -> str:
+        """Progress table for notebooks; the batches themselves are not read ahead"""
+
+    @property
+    def warnings(self) -> Any:
+        """Anomalies seen so far as `(kind, line, timestamp, message)` tuples"""
+
+
+class Recording:
+    """Whole recording held in memory; shows a mini-map and summary in notebooks"""
+
+    def __repr__(self) -> str: ...
+
+    def __len__(self) -> int: ...
+
+    def pings(self, channel_id: Any | None = None) -> Any:
+        """Pings of one channel, or all pings, as a list of dicts"""
+
+    def records_where(self, depth_lt: Any | None = None, depth_gt: Any | None = None, time_from: Any | None = None, time_to: Any | None = None, bbox: Any | None = None) -> Any:
+        """Indices into `pings()` of soundings within every given bound
+
+        Only positioned pings with a depth are considered. `bbox` is
+        `(min_lat, min_lon, max_lat, max_lon)` and is answered from an R-tree
+        built on first use.
+        """
+
+    def shallowest(self, n: Any) -> Any:
+        """Indices into `pings()` of the `n` shallowest soundings, shallowest first"""
+
+    def samples(self, channel_id: Any) -> Any:
+        """One channel's samples as a matrix that numpy can wrap without copying
+
+        The matrix is built on the first call per channel; later calls and
+        every buffer exported from them share it.
+        """
+
+    def save_ssf(self, path: Any, compress: bool = False) -> Any:
+        """Archive the loaded pings as a compact SSF file, readable by `load`
+
+        `compress=True` also LZ-compresses each column (SSF version 3).
+        """
+
+    def save_arrow(self, path: Any, batch_size: int = 10000) -> Any:
+        """Write the loaded pings as an Arrow IPC stream of `batch_size`-row record batches
+
+        Open it with `pyarrow.ipc.open_stream` to get columns without per-ping objects.
+        """
+
+    def with_nmea2000(self, path: Any, max_gap_s: float = 2.0, mode: str = 'lenient') -> Any:
+        """Copy with attitude, water temperature, water speed and engine readings from an NMEA 2000 candump log
+
+        Each ping takes the nearest reading no more than `max_gap_s` away; the
+        values appear in `pings()` as `pitch_deg`, `roll_deg`, `water_temp_c`,
+        `stw_knots`, `engine_rpm`, `battery_volts` and `air_temp_c`.
+        """
+
+    def estimate_current(self, window_s: float = 60.0) -> Any:
+        """Current set and drift along the track, e.g. after `with_nmea2000`
+
+        Compares ground velocity (`cog_deg`, `sog_knots`) with water velocity
+        (`heading_deg`, `stw_knots`) averaged over `window_s`. Returns a dict
+        of equal-length lists (`time`, `lat`, `lon`, `set_deg`, `drift_knots`);
+        pings without speed through water are left out.
+        """
+
+    def anonymized(self, positions: str = 'relocate', times: str = 'zero', offset_east_m: float = 0.0, offset_north_m: float = 0.0, rotate_deg: float = 0.0, jitter_m: float = 0.0, time_shift_s: Any | None = None, seed: Any | None = None) -> Any:
+        """Copy safe to share: positions and times redacted, channel names replaced
+
+        `positions` is `"keep"`, `"relocate"` (centroid moved to 0°N 0°E) or
+        `"fuzz"` (shifted, rotated and jittered by the given amounts);
+        `times` is `"keep"`, `"zero"` or `"shift"` by `time_shift_s`. The
+        jitter seed is random unless `seed` is given. Save the result with
+        `save_ssf` or export it as usual.
+        """
+
+    def quicklook(self, width: int = 480, height: int = 240) -> Any:
+        """Track mini-map, sonar strip and depth profile in one image"""
+
+    def waterfall(self, channel_id: Any, width: int = 512) -> Any:
+        """Waterfall of one channel, one row per ping, with stretched levels"""
+
+    def plot_data(self) -> Any:
+        """Track arrays ready for any plotting library
+
+        Returns a dict with float64 arrays `time`, `lon`, `lat` and `depth_m`
+        (NaN where unknown) of one channel's positioned pings, plus `extent`
+        as `(west, east, south, north)` or None without positions. Plot
+        `lon` as x and `lat` as y.
+        """
+
+    def _repr_html_(self) -> str:
+        """Summary card with a track mini-map, shown by notebooks"""
+
+    def _repr_png_(self) -> bytes:
+        """Default-size `quicklook` as PNG, for frontends that do not render HTML"""
+
+    @property
+    def summary(self) -> Any:
+        """Overview as a dict: format, counts, time span, bounds and depth range"""
+
+
+class SampleBlock:
+    """One channel's samples as a read-only `(pings, samples)` uint8 matrix
+
+    Exposes the buffer protocol, so `numpy.asarray(block)` and
+    `memoryview(block)` share this memory rather than copying it. Pings with
+    fewer samples are zero-padded to the longest.
+    """
+
+    def __repr__(self) -> str: ...
+
+    def __len__(self) -> int: ...
+
+    @property
+    def timestamps(self) -> Any:
+        """Ping times, one per row"""
+
+    @property
+    def shape(self) -> Any:
+        """`(pings, samples)`"""
+
+    @property
+    def range_m(self) -> Any:
+        """Range covered by each row's samples; padding lies beyond it"""
+
+
+class SonarImage:
+    """Rendered RGB image; displays inline in notebooks"""
+
+    def __repr__(self) -> str: ...
+
+    def plot_data(self) -> Any:
+        """Pixels and axes ready for any plotting library
+
+        Returns a dict with `image`, a `(height, width, 3)` uint8 array whose
+        row 0 is the top; `extent` as `(left, right, bottom, top)` in data
+        units; `origin` (always `"upper"`); and `xlabel`/`ylabel` naming the
+        units. With matplotlib this is `imshow(d["image"], extent=d["extent"],
+        origin=d["origin"])`.
+        """
+
+    def to_png(self) -> Any:
+        """PNG file contents as bytes"""
+
+    def save(self, path: Any) -> Any:
+        """Write the image to `path` as a PNG file"""
+
+    def _repr_png_(self) -> bytes:
+        """Same as `to_png`, so notebooks display the image inline"""
+
+    @property
+    def width(self) -> Any:
+        """Width in pixels"""
+
+    @property
+    def height(self) -> Any:
+        """Height in pixels"""
+
+
+class UnsupportedFormatError(OSError):
+    """A file is not in any recognized sonar format, or the format cannot do what was asked"""
+
+
+def catalog_directory(directory: Any, db: Any | None = None, pattern: str = '*', recursive: bool = True) -> Any:
+    """Hash and summarize every recording under `directory` as a list of dicts
+
+    With `db`, the catalog is also saved there as an SQLite database for
+    [`query_catalog`] or any SQLite client.
+    """
+
+
+def depth_profile(path: Any, resample: str = '1s', max_gap: str = '5s') -> Any:
+    """Depth and water temperature of a recording resampled for plotting
+
+    `resample` and `max_gap` are intervals such as `"1s"`, `"500ms"` or
+    `"2min"`. Returns a dict of equal-length lists (`time`, `depth_m`,
+    `temperature_c`, `gap`); values are NaN, and `gap` set, where no
+    readings lie within `max_gap` of each other.
+    """
+
+
+def detect_sessions(directory: Any, pattern: str = '*', recursive: bool = True, max_gap_s: float = 1800.0, max_jump_m: float = 1000.0) -> Any:
+    """Group the recordings under `directory` into outings
+
+    Consecutive files join one session when the next starts within
+    `max_gap_s` of the previous file's end and within `max_jump_m` of its
+    last position. Returns a list of dicts with `files`, in time order, and
+    `summary`, the combined summary. Unreadable files are left out.
+    """
+
+
+def field_stats(path: Any, field: Any, bins: int = 32, filter: Any | None = None) -> Any:
+    """Count, min, max, mean, stddev, percentiles and histogram of one ping field
+
+    The recording is streamed in fixed memory rather than loaded; unset
+    values (e.g. a missing `sog_knots`) are skipped. `p5`, `median` and `p95`
+    are exact up to a few thousand values and P² estimates beyond that. The histogram is returned as
+    `(edges, counts)` with `bins` equal-width bins, at most 65,536; more
+    raises ValueError.
+    """
+
+
+def grid_depths(path: Any, cell_size_m: Any, statistic: str = 'median', filter: Any | None = None, transducer_offset_m: Any | None = None, draft_m: float = 0.0) -> Any:
+    """Bin a recording's soundings into `cell_size_m` cells in one call
+
+    Returns `(grid, transform)`: a 2-D float64 array, north row first, with
+    NaN in empty cells, and its GDAL-style geotransform
+    `(west, dlon, 0, north, 0, -dlat)` in degrees. `transducer_offset_m` is the
+    transducer's `(starboard, forward, down)` offset from the GPS antenna and
+    `draft_m` its depth below the waterline; with either set, soundings are
+    moved to the transducer and depths are below the waterline.
+    """
+
+
+def load(path: Any, filter: Any | None = None, warn: bool = True, mode: str = 'lenient') -> Any:
+    """Read a whole recording into memory for interactive use
+
+    Reader anomalies are raised as `UserWarning` unless `warn` is false.
+    `mode="strict"` raises `ParseError` on the first malformed record instead
+    of skipping it; files in no known format raise `UnsupportedFormatError`.
+    """
+
+
+def parse_batches(path: Any, batch_size: int = 1000, warn: bool = True, filter: Any | None = None, mode: str = 'lenient') -> Any:
+    """Open a recording and iterate over its pings `batch_size` at a time
+
+    Skipped input and clock regressions are collected on the iterator's
+    `warnings` attribute and, unless `warn` is false, raised as `UserWarning`.
+    `filter` is an expression such as `"depth_m < 3 && channel_id == 4"`;
+    pings that do not match are dropped before conversion to dicts. `mode`
+    is `"lenient"` (skip malformed input) or `"strict"` (raise on it).
+    """
+
+
+def process_directory(directory: Any, pattern: str = '*', recursive: bool = True, track_decimation: int = 10) -> Any:
+    """Parse and summarize every recording under `directory`
+
+    Returns a dict with `summaries`, one dict per file (`error` is set for
+    files that could not be read), and `track`, every `track_decimation`-th
+    positioned ping across all files as time-ordered `(timestamp, latitude,
+    longitude)` tuples.
+    """
+
+
+def query_catalog(db: Any, time_from: Any | None = None, time_to: Any | None = None, polygon: Any | None = None, channel_id: Any | None = None, include_failed: bool = False) -> Any:
+    """Entries of a saved catalog matching every given condition
+
+    Recordings overlapping `time_from..time_to` whose bounding box meets
+    `polygon`, a list of `(lat, lon)` vertices, and that contain
+    `channel_id`. Unreadable files are left out unless `include_failed`.
+    """
+
+
+def range_segments(path: Any, channel_id: Any, filter: Any | None = None) -> Any:
+    """Range-setting changes of one channel as a list of segment dicts
+
+    Each dict has `start` and `end` ping indices within the channel (end
+    exclusive), `start_time`, `end_time` and the `range_m` its waterfall rows
+    are scaled to.
+    """
+
+
+def resample(path: Any, interval_ms: Any, aggregation: str = 'mean', filter: Any | None = None) -> Any:
+    """Resample a recording's navigation fields every `interval_ms`
+
+    Returns a dict of equal-length lists (`time`, `lat`, `lon`, `depth_m`,
+    `heading_deg`, `cog_deg`, `sog_knots`, `count`, `gap`). Empty bins are
+    kept with `gap` set and NaN values so the time base stays uniform.
+    """
+
+
+def run_pipeline(path: Any, mode: str = 'lenient') -> Any:
+    """Run the processing pipeline described by a TOML file
+
+    Paths in the file are relative to it. Returns a dict with `recordings`
+    (one summary dict per file read), `sounding_count`, `unreduced_count`
+    and `written`, the paths of the products written. An invalid pipeline
+    file raises `ParseError`.
+    """
+
+
+def set_log_level(level: Any | None = None) -> Any:
+    """Forward the reader's tracing diagnostics to Python's `logging`
+
+    Events at `level` (`"error"`, `"warning"`, `"info"`, `"debug"` or
+    `"trace"`) and above go to loggers named after their Rust module, such
+    as `cesarops_core.parsers.registry`, when the call that produced them
+    returns. `None` or `"off"` stops forwarding.
+    """
+
+
+def validate(path: Any) -> Any:
+    """Walk a recording and return its validation report as a dict"""
@@ -0,0 +1,12 @@
+#![no_main]
+
+use cesarops_core::parsers::nmea2000::{decode_frames, parse_candump};
+use cesarops_core::parsers::ParseMode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    if let Ok(frames) = parse_candump(&text, ParseMode::Lenient) {
+        let _ = decode_frames(&frames);
+    }
+});
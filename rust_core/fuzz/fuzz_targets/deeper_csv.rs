@@ -0,0 +1,10 @@
+#![no_main]
+
+use cesarops_core::parsers::deeper::parse_deeper_csv;
+use cesarops_core::parsers::ParseMode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let _ = parse_deeper_csv(&text, ParseMode::Lenient);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use cesarops_core::parsers::gpx::parse_points;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let _ = parse_points(&text, "trkpt");
+    let _ = parse_points(&text, "rtept");
+});
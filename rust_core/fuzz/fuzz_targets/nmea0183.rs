@@ -0,0 +1,11 @@
+#![no_main]
+
+use cesarops_core::parsers::nmea0183::parse_nmea0183;
+use cesarops_core::parsers::ParseMode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let _ = parse_nmea0183(&text, ParseMode::Lenient);
+    let _ = parse_nmea0183(&text, ParseMode::Strict);
+});
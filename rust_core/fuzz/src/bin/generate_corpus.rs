@@ -0,0 +1,69 @@
+// Writes seed inputs for each fuzz target into corpus/<target>/
+//
+// Run from the fuzz directory: cargo run --bin generate_corpus
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+fn nmea_sentence(body: &str) -> String {
+    let checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+    format!("${}*{:02X}\r\n", body, checksum)
+}
+
+fn write_seeds(target: &str, seeds: &[(&str, String)]) -> io::Result<()> {
+    let dir = Path::new("corpus").join(target);
+    fs::create_dir_all(&dir)?;
+    for (name, contents) in seeds {
+        fs::write(dir.join(name), contents)?;
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let rmc = nmea_sentence("GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W");
+    let gga = nmea_sentence("GPGGA,123520,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,");
+    let hdt = nmea_sentence("HCHDT,84.5,T");
+    let dbt = nmea_sentence("SDDBT,10.0,f,3.05,M,1.6,F");
+    let mtw = nmea_sentence("YXMTW,17.5,C");
+    write_seeds(
+        "nmea0183",
+        &[
+            ("rmc_gga", format!("{}{}", rmc, gga)),
+            ("heading_depth_temp", format!("{}{}{}{}", hdt, gga, dbt, mtw)),
+            ("bad_checksum", "$GPGGA,123520,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*00\r\n".to_string()),
+            ("truncated", rmc[..rmc.len() / 2].to_string()),
+        ],
+    )?;
+
+    write_seeds(
+        "candump",
+        &[
+            ("position_rapid", "(1600000000.500000) can0 09F80123#C0E6EE19A0C8FECB\n".to_string()),
+            ("heading", "(1600000000.600000) can0 09F11223#00C8000000FF7FFC\n".to_string()),
+            ("interactive", "(1600000000.700000) can0 09F50B23 [8] 00 2C 01 00 00 FF 7F FF\n".to_string()),
+        ],
+    )?;
+
+    write_seeds(
+        "deeper_csv",
+        &[
+            ("comma", "latitude,longitude,depth,time\n43.1,-87.2,3.5,1717243200\n".to_string()),
+            ("semicolon", "Lat;Lon;Depth (m);Date\n43.1;-87.2;3.5;2024-06-01 12:00:00\n".to_string()),
+        ],
+    )?;
+
+    write_seeds(
+        "gpx",
+        &[
+            (
+                "track",
+                "<gpx><trk><trkseg><trkpt lat=\"43.1\" lon=\"-87.2\"><ele>176</ele><time>2024-06-01T12:00:00Z</time></trkpt></trkseg></trk></gpx>"
+                    .to_string(),
+            ),
+            ("route", "<gpx><rte><rtept lat='43.1' lon='-87.2'/><rtept lat='43.2' lon='-87.3'/></rte></gpx>".to_string()),
+        ],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+"""Generate cesarops_core.pyi from the built extension module.
+
+pyo3 records each function's signature (names, defaults, keyword-only
+markers) in ``__text_signature__`` and its doc comment in ``__doc__``, so the
+stub is derived from the compiled module rather than written by hand.  Run
+after ``maturin develop``; ``--check`` exits non-zero when the committed stub
+no longer matches the module.
+
+    python scripts/gen_stubs.py [--check] [--output PATH]
+"""
+
+import argparse
+import importlib
+import inspect
+import os
+import sys
+
+MODULE = "cesarops_core"
+DEFAULT_OUTPUT = os.path.join(os.path.dirname(os.path.dirname(os.path.abspath(__file__))), MODULE + ".pyi")
+
+# Return types pyo3 does not record; everything else is Any. `__iter__` returns its own class.
+RETURNS = {
+    "__iter__": None,
+    "__len__": "int",
+    "__repr__": "str",
+    "__str__": "str",
+    "__next__": "Any",
+    "_repr_html_": "str",
+    "_repr_png_": "bytes",
+}
+
+# Signature pyo3 reports for classes without a #[new] constructor
+NO_CONSTRUCTOR = "($type, *args, **kwargs)"
+
+
+def annotate(param):
+    """Annotate a parameter from its default value"""
+    if param.kind is param.VAR_POSITIONAL or param.kind is param.VAR_KEYWORD:
+        return "Any"
+    default = param.default
+    if default is param.empty:
+        return "Any"
+    if default is None:
+        return "Any | None"
+    return type(default).__name__
+
+
+def render_params(text_signature):
+    """Render a pyo3 text signature as a typed parameter list"""
+    text = text_signature.replace("$self", "self").replace("$type", "cls")
+    parameters = inspect.signature(_parse(text)).parameters.values()
+    parts = []
+    positional_only = False
+    keyword_only = False
+    for param in parameters:
+        if param.name in ("self", "cls"):
+            parts.append(param.name)
+            continue
+        if param.kind is param.POSITIONAL_ONLY:
+            positional_only = True
+            parts.append(_render(param))
+            continue
+        if positional_only:
+            parts.append("/")
+            positional_only = False
+        if param.kind is param.KEYWORD_ONLY and not keyword_only:
+            parts.append("*")
+        if param.kind in (param.KEYWORD_ONLY, param.VAR_POSITIONAL):
+            keyword_only = True
+        parts.append(_render(param))
+    if positional_only:
+        parts.append("/")
+    return ", ".join(parts)
+
+
+def _render(param):
+    """Render one parameter with its annotation and default"""
+    prefix = {param.VAR_POSITIONAL: "*", param.VAR_KEYWORD: "**"}.get(param.kind, "")
+    item = f"{prefix}{param.name}: {annotate(param)}"
+    if param.default is not param.empty:
+        item += f" = {param.default!r}"
+    return item
+
+
+def _parse(text):
+    """Build a throwaway function whose signature matches a text signature"""
+    namespace = {}
+    exec(f"def _f{text}: pass", {}, namespace)
+    return namespace["_f"]
+
+
+def docstring(obj, indent):
+    """Render an object's doc comment as a stub docstring, or nothing"""
+    doc = inspect.cleandoc(getattr(obj, "__doc__", None) or "")
+    if not doc:
+        return []
+    doc = doc.replace("\\", "\\\\").replace('"""', '\\"\\"\\"')
+    lines = doc.splitlines()
+    pad = " " * indent
+    if len(lines) == 1:
+        return [f'{pad}"""{lines[0]}"""']
+    return [f'{pad}"""{lines[0]}'] + [f"{pad}{line}" if line else "" for line in lines[1:]] + [f'{pad}"""']
+
+
+def render_function(name, func, indent, owner=None):
+    """Render one function or method with its signature and docstring"""
+    pad = " " * indent
+    params = render_params(func.__text_signature__ or "($self)")
+    returns = owner.__name__ if name == "__iter__" and owner else RETURNS.get(name, "Any")
+    # Slot wrappers carry CPython's generic "Return len(self)." docs, not ours
+    body = [] if type(func).__name__ == "wrapper_descriptor" else docstring(func, indent + 4)
+    if not body:
+        return [f"{pad}def {name}({params}) -> {returns}: ..."]
+    return [f"{pad}def {name}({params}) -> {returns}:"] + body
+
+
+def render_class(name, cls):
+    """Render an exported class: constructor, methods, dunders and getters"""
+    lines = [f"class {name}:"]
+    lines += docstring(cls, 4)
+    members = []
+    new = cls.__dict__.get("__new__")
+    signature = getattr(new, "__text_signature__", None)
+    if signature and signature != NO_CONSTRUCTOR:
+        init = signature.replace("$type", "$self", 1)
+        members.append(render_function("__init__", _Signed(init), 4, cls))
+    for attr, value in cls.__dict__.items():
+        if attr in ("__new__", "__doc__", "__module__"):
+            continue
+        kind = type(value).__name__
+        if kind == "getset_descriptor":
+            members.append(["    @property", f"    def {attr}(self) -> Any:"] + (docstring(value, 8) or ["        ..."]))
+        elif kind in ("method_descriptor", "wrapper_descriptor"):
+            if kind == "wrapper_descriptor" and attr not in RETURNS:
+                continue
+            members.append(render_function(attr, value, 4, cls))
+        elif kind in ("classmethod_descriptor", "builtin_function_or_method"):
+            members.append(["    @staticmethod"] + render_function(attr, value, 4))
+    if not members and len(lines) == 1:
+        lines.append("    ...")
+    for member in members:
+        lines.append("")
+        lines += member
+    return lines
+
+
+class _Signed:
+    """A stand-in callable carrying a rewritten text signature"""
+
+    def __init__(self, text_signature):
+        self.__text_signature__ = text_signature
+        self.__doc__ = None
+
+
+def render_exception(name, exc):
+    """Render a create_exception! type under its Python base class"""
+    base = exc.__bases__[0].__name__
+    return [f"class {name}({base}):"] + (docstring(exc, 4) or ["    ..."])
+
+
+def generate(module):
+    """Render the stub for every public item of the module"""
+    lines = ["# Generated by scripts/gen_stubs.py from the built extension; do not edit by hand", ""]
+    lines += docstring(module, 0)
+    lines += ["from typing import Any", ""]
+    for name in sorted(vars(module)):
+        if name.startswith("__"):
+            continue
+        value = getattr(module, name)
+        if inspect.isclass(value) and issubclass(value, BaseException):
+            block = render_exception(name, value)
+        elif inspect.isclass(value):
+            block = render_class(name, value)
+        elif callable(value):
+            block = render_function(name, value, 0)
+        else:
+            block = [f"{name}: {type(value).__name__}"]
+        lines += ["", *block, ""]
+    text = "\n".join(lines)
+    while "\n\n\n\n" in text:
+        text = text.replace("\n\n\n\n", "\n\n\n")
+    return text.rstrip("\n") + "\n"
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--output", default=DEFAULT_OUTPUT, help="stub file to write")
+    parser.add_argument("--check", action="store_true", help="fail if the stub is out of date instead of writing it")
+    args = parser.parse_args()
+
+    stub = generate(importlib.import_module(MODULE))
+    if args.check:
+        try:
+            with open(args.output, encoding="utf-8") as f:
+                current = f.read()
+        except FileNotFoundError:
+            current = None
+        if current != stub:
+            print(f"{args.output} is out of date; run scripts/gen_stubs.py", file=sys.stderr)
+            return 1
+        return 0
+    with open(args.output, "w", encoding="utf-8", newline="\n") as f:
+        f.write(stub)
+    print(f"wrote {args.output}")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,302 @@
+// Position and time redaction for sharing recordings
+// src/anonymize.rs
+//
+// Pings carry no device identifiers; the free-text channel names a unit
+// records are the only ones, and `anonymize_channels` replaces them.
+
+use crate::geo::{local_offset_m, offset_position};
+use crate::parsers::ChannelInfo;
+use crate::survey::{Ping, Sounding};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// How recorded positions are redacted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PositionRedaction {
+    Keep,
+    /// Move the track so its centroid sits at 0°N 0°E, keeping its shape
+    Relocate,
+    /// Shift, rotate about the centroid and jitter each position;
+    /// headings and courses rotate with the track
+    Fuzz {
+        offset_east_m: f64,
+        offset_north_m: f64,
+        rotate_deg: f64,
+        jitter_m: f64,
+    },
+}
+
+/// How timestamps are redacted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeRedaction {
+    Keep,
+    /// Start the recording at t = 0, keeping relative timing
+    ZeroBased,
+    Shift(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnonymizeConfig {
+    pub positions: PositionRedaction,
+    pub times: TimeRedaction,
+    /// Seed for the jitter; random by default, since anyone who knows the
+    /// seed can regenerate and subtract the jitter. Fix it only so repeated
+    /// exports of one file match, and keep it private
+    pub seed: u64,
+}
+
+impl Default for AnonymizeConfig {
+    fn default() -> Self {
+        Self {
+            positions: PositionRedaction::Relocate,
+            times: TimeRedaction::ZeroBased,
+            seed: rand::random(),
+        }
+    }
+}
+
+/// Records carrying a position, heading and timestamp that can be redacted
+trait Redactable {
+    fn nav_mut(&mut self) -> (&mut f64, &mut f64, &mut f64, &mut f64);
+
+    /// Course over ground, which would reveal a rotation if left as recorded
+    fn course_mut(&mut self) -> Option<&mut f64> {
+        None
+    }
+}
+
+impl Redactable for Sounding {
+    fn nav_mut(&mut self) -> (&mut f64, &mut f64, &mut f64, &mut f64) {
+        (&mut self.lat, &mut self.lon, &mut self.heading_deg, &mut self.timestamp)
+    }
+}
+
+impl Redactable for Ping {
+    fn nav_mut(&mut self) -> (&mut f64, &mut f64, &mut f64, &mut f64) {
+        (&mut self.lat, &mut self.lon, &mut self.heading_deg, &mut self.timestamp)
+    }
+
+    fn course_mut(&mut self) -> Option<&mut f64> {
+        self.cog_deg.as_mut()
+    }
+}
+
+/// Redacted copy of soundings
+pub fn anonymize_soundings(soundings: &[Sounding], config: &AnonymizeConfig) -> Vec<Sounding> {
+    let mut out = soundings.to_vec();
+    redact(&mut out, config);
+    out
+}
+
+/// Redacted copy of pings; sample data is kept intact
+pub fn anonymize_pings(pings: &[Ping], config: &AnonymizeConfig) -> Vec<Ping> {
+    let mut out = pings.to_vec();
+    redact(&mut out, config);
+    out
+}
+
+/// Redact pings and the soundings derived from them in place, moving both the same way
+///
+/// The centroid and start time come from the pings (or the soundings when
+/// there are none), so the products of one export still line up.
+pub fn anonymize_survey(pings: &mut [Ping], soundings: &mut [Sounding], config: &AnonymizeConfig) {
+    let Some(anchor) = anchor(pings).or_else(|| anchor(soundings)) else {
+        return;
+    };
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    redact_from(pings, anchor, config, &mut rng);
+    redact_from(soundings, anchor, config, &mut rng);
+}
+
+/// Channel table with the unit-assigned names replaced by `"channel <id>"`
+pub fn anonymize_channels(channels: &[ChannelInfo]) -> Vec<ChannelInfo> {
+    channels
+        .iter()
+        .map(|c| ChannelInfo {
+            name: format!("channel {}", c.channel_id),
+            ..c.clone()
+        })
+        .collect()
+}
+
+/// Centroid latitude, longitude and earliest timestamp of the records
+fn anchor<T: Redactable>(records: &mut [T]) -> Option<(f64, f64, f64)> {
+    if records.is_empty() {
+        return None;
+    }
+    let n = records.len() as f64;
+    let (mut lat_sum, mut lon_sum, mut start) = (0.0, 0.0, f64::INFINITY);
+    for record in records.iter_mut() {
+        let (lat, lon, _, timestamp) = record.nav_mut();
+        lat_sum += *lat;
+        lon_sum += *lon;
+        start = start.min(*timestamp);
+    }
+    Some((lat_sum / n, lon_sum / n, start))
+}
+
+fn redact<T: Redactable>(records: &mut [T], config: &AnonymizeConfig) {
+    if let Some(anchor) = anchor(records) {
+        redact_from(records, anchor, config, &mut StdRng::seed_from_u64(config.seed));
+    }
+}
+
+fn redact_from<T: Redactable>(
+    records: &mut [T],
+    (center_lat, center_lon, start): (f64, f64, f64),
+    config: &AnonymizeConfig,
+    rng: &mut StdRng,
+) {
+    for record in records.iter_mut() {
+        let (lat, lon, heading, timestamp) = record.nav_mut();
+        let (east, north) = local_offset_m(center_lat, center_lon, *lat, *lon);
+        let mut rotation = None;
+
+        match config.positions {
+            PositionRedaction::Keep => {}
+            PositionRedaction::Relocate => {
+                (*lat, *lon) = offset_position(0.0, 0.0, east, north);
+            }
+            PositionRedaction::Fuzz {
+                offset_east_m,
+                offset_north_m,
+                rotate_deg,
+                jitter_m,
+            } => {
+                // Clockwise rotation so headings rotate by the same amount
+                let (sin_r, cos_r) = rotate_deg.to_radians().sin_cos();
+                let mut x = east * cos_r + north * sin_r + offset_east_m;
+                let mut y = north * cos_r - east * sin_r + offset_north_m;
+                if jitter_m > 0.0 {
+                    let r = jitter_m * rng.gen::<f64>().sqrt();
+                    let theta = rng.gen::<f64>() * std::f64::consts::TAU;
+                    x += r * theta.cos();
+                    y += r * theta.sin();
+                }
+                (*lat, *lon) = offset_position(center_lat, center_lon, x, y);
+                *heading = (*heading + rotate_deg).rem_euclid(360.0);
+                rotation = Some(rotate_deg);
+            }
+        }
+
+        match config.times {
+            TimeRedaction::Keep => {}
+            TimeRedaction::ZeroBased => *timestamp -= start,
+            TimeRedaction::Shift(seconds) => *timestamp += seconds,
+        }
+        if let (Some(rotate_deg), Some(course)) = (rotation, record.course_mut()) {
+            *course = (*course + rotate_deg).rem_euclid(360.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::ChannelKind;
+
+    fn track() -> Vec<Ping> {
+        (0..20)
+            .map(|i| {
+                let (lat, lon) = offset_position(44.6488, -63.5752, i as f64 * 3.0, (i * i) as f64 * 0.5);
+                Ping {
+                    timestamp: 1_700_000_000.0 + i as f64,
+                    lat,
+                    lon,
+                    heading_deg: 30.0,
+                    cog_deg: Some(35.0 + i as f64),
+                    depth_m: 4.0,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn distance(a: &Ping, b: &Ping) -> f64 {
+        let (east, north) = local_offset_m(a.lat, a.lon, b.lat, b.lon);
+        east.hypot(north)
+    }
+
+    fn fuzz(rotate_deg: f64, jitter_m: f64, seed: u64) -> AnonymizeConfig {
+        AnonymizeConfig {
+            positions: PositionRedaction::Fuzz {
+                offset_east_m: 500.0,
+                offset_north_m: -200.0,
+                rotate_deg,
+                jitter_m,
+            },
+            times: TimeRedaction::Keep,
+            seed,
+        }
+    }
+
+    #[test]
+    fn relocate_keeps_the_track_shape() {
+        let pings = track();
+        let moved = anonymize_pings(&pings, &AnonymizeConfig::default());
+        for (a, b) in [(0, 1), (0, 19), (7, 13)] {
+            let (before, after) = (distance(&pings[a], &pings[b]), distance(&moved[a], &moved[b]));
+            assert!((before - after).abs() < 0.01, "{} vs {}", before, after);
+        }
+        let center_lat = moved.iter().map(|p| p.lat).sum::<f64>() / moved.len() as f64;
+        assert!(center_lat.abs() < 1e-6 && moved.iter().all(|p| p.lon.abs() < 0.01));
+        assert_eq!(moved[0].timestamp, 0.0);
+        assert_eq!(moved[19].timestamp, 19.0);
+        assert_eq!(moved[3].cog_deg, pings[3].cog_deg);
+    }
+
+    #[test]
+    fn jitter_depends_only_on_the_seed() {
+        let pings = track();
+        let a = anonymize_pings(&pings, &fuzz(0.0, 10.0, 7));
+        assert_eq!(a, anonymize_pings(&pings, &fuzz(0.0, 10.0, 7)));
+        assert_ne!(a, anonymize_pings(&pings, &fuzz(0.0, 10.0, 8)));
+        let plain = anonymize_pings(&pings, &fuzz(0.0, 0.0, 7));
+        for (jittered, exact) in a.iter().zip(&plain) {
+            assert!(distance(jittered, exact) <= 10.0 + 1e-6);
+        }
+        assert_ne!(AnonymizeConfig::default().seed, AnonymizeConfig::default().seed);
+    }
+
+    #[test]
+    fn heading_and_course_rotate_with_the_track() {
+        let pings = track();
+        let rotated = anonymize_pings(&pings, &fuzz(90.0, 0.0, 1));
+        for (before, after) in pings.iter().zip(&rotated) {
+            assert!((after.heading_deg - 120.0).abs() < 1e-9);
+            assert!((after.cog_deg.unwrap() - (before.cog_deg.unwrap() + 90.0)).abs() < 1e-9);
+        }
+        // The bearing between fixes turns by the same angle as the course
+        let bearing = |a: &Ping, b: &Ping| {
+            let (east, north) = local_offset_m(a.lat, a.lon, b.lat, b.lon);
+            east.atan2(north).to_degrees().rem_euclid(360.0)
+        };
+        let turn = bearing(&rotated[4], &rotated[5]) - bearing(&pings[4], &pings[5]);
+        assert!((turn.rem_euclid(360.0) - 90.0).abs() < 0.01, "{}", turn);
+    }
+
+    #[test]
+    fn pings_and_soundings_move_together() {
+        let mut pings = track();
+        let mut soundings: Vec<Sounding> = pings.iter().map(Ping::to_sounding).collect();
+        anonymize_survey(&mut pings, &mut soundings[5..], &fuzz(45.0, 0.0, 3));
+        for (p, s) in pings[5..].iter().zip(&soundings[5..]) {
+            assert!((p.lat - s.lat).abs() < 1e-12 && (p.lon - s.lon).abs() < 1e-12);
+            assert_eq!((p.timestamp, p.heading_deg), (s.timestamp, s.heading_deg));
+        }
+    }
+
+    #[test]
+    fn channel_names_are_replaced() {
+        let channels = [ChannelInfo {
+            channel_id: 4,
+            name: "GT56UHD-TM sn 4711".to_string(),
+            kind: ChannelKind::SideScanPort,
+            frequency_khz: Some(455.0),
+            beam_angle_deg: None,
+        }];
+        let renamed = anonymize_channels(&channels);
+        assert_eq!(renamed[0].name, "channel 4");
+        assert_eq!((renamed[0].kind, renamed[0].frequency_khz), (ChannelKind::SideScanPort, Some(455.0)));
+    }
+}
@@ -0,0 +1,501 @@
+// Batch processing of recordings found in a directory tree
+// src/batch.rs
+
+use crate::geo::distance_m;
+use crate::parsers::multipart::{is_continuation, logical_path};
+use crate::parsers::{open_with, FormatRegistry};
+use crate::survey::Ping;
+use rayon::prelude::*;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::SyncSender;
+
+/// Overview of one recording
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordingSummary {
+    pub path: PathBuf,
+    pub format: String,
+    pub ping_count: usize,
+    pub channels: Vec<u16>,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// (min_lat, min_lon, max_lat, max_lon) of positioned pings
+    pub bbox: Option<(f64, f64, f64, f64)>,
+    pub min_depth_m: Option<f64>,
+    pub max_depth_m: Option<f64>,
+    /// Set when the file could not be read; other fields are then empty
+    pub error: Option<String>,
+}
+
+impl RecordingSummary {
+    pub fn from_pings(path: &Path, format: &str, pings: &[Ping]) -> Self {
+        let mut summary = Self {
+            path: path.to_path_buf(),
+            format: format.to_string(),
+            ping_count: pings.len(),
+            start_time: f64::NAN,
+            end_time: f64::NAN,
+            ..Default::default()
+        };
+        let mut channels = BTreeSet::new();
+
+        for p in pings {
+            channels.insert(p.channel_id);
+            summary.start_time = summary.start_time.min(p.timestamp);
+            summary.end_time = summary.end_time.max(p.timestamp);
+            if p.lat != 0.0 || p.lon != 0.0 {
+                summary.bbox = Some(match summary.bbox {
+                    None => (p.lat, p.lon, p.lat, p.lon),
+                    Some((a, b, c, d)) => (a.min(p.lat), b.min(p.lon), c.max(p.lat), d.max(p.lon)),
+                });
+            }
+            if p.depth_m > 0.0 {
+                summary.min_depth_m = Some(summary.min_depth_m.map_or(p.depth_m, |d| d.min(p.depth_m)));
+                summary.max_depth_m = Some(summary.max_depth_m.map_or(p.depth_m, |d| d.max(p.depth_m)));
+            }
+        }
+        summary.channels = channels.into_iter().collect();
+        summary
+    }
+
+    pub fn duration_s(&self) -> f64 {
+        self.end_time - self.start_time
+    }
+}
+
+/// (timestamp, lat, lon)
+pub type TrackPoint = (f64, f64, f64);
+
+/// Options for [`process_directory`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchOptions {
+    /// File name pattern with `*` and `?` wildcards, matched case-insensitively
+    pub pattern: String,
+    pub recursive: bool,
+    pub parallel: bool,
+    /// Keep every n-th positioned ping in the aggregate track
+    pub track_decimation: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            pattern: "*".to_string(),
+            recursive: true,
+            parallel: true,
+            track_decimation: 10,
+        }
+    }
+}
+
+/// Per-file summaries plus one time-ordered track across all files
+#[derive(Debug, Clone, Default)]
+pub struct BatchResult {
+    pub summaries: Vec<RecordingSummary>,
+    pub track: Vec<TrackPoint>,
+}
+
+impl BatchResult {
+    pub fn failed(&self) -> impl Iterator<Item = &RecordingSummary> {
+        self.summaries.iter().filter(|s| s.error.is_some())
+    }
+}
+
+/// Limits for joining consecutive recordings into one outing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionConfig {
+    /// Longest pause between one file's end and the next file's start
+    pub max_gap_s: f64,
+    /// Largest position jump across that pause
+    pub max_jump_m: f64,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            max_gap_s: 1800.0,
+            max_jump_m: 1000.0,
+        }
+    }
+}
+
+/// One outing recorded as one or more files (e.g. split by power cycles)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    /// Member files in time order
+    pub files: Vec<PathBuf>,
+    /// Combined summary; `path` is the first file
+    pub summary: RecordingSummary,
+}
+
+impl Session {
+    /// Read every member file as one time-ordered logical recording
+    pub fn read_pings(&self, registry: &FormatRegistry) -> io::Result<Vec<Ping>> {
+        let mut pings = Vec::new();
+        for path in &self.files {
+            pings.extend(read_recording(path, registry)?.1);
+        }
+        pings.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+        Ok(pings)
+    }
+}
+
+/// Group the recordings under `dir` into outings by time and position continuity
+///
+/// Files that cannot be read are left out.
+pub fn detect_sessions(
+    dir: &Path,
+    registry: &FormatRegistry,
+    options: &BatchOptions,
+    config: &SessionConfig,
+) -> io::Result<Vec<Session>> {
+    let files = discover_files(dir, &options.pattern, options.recursive)?;
+    let endpoints = |path: &PathBuf| {
+        let (summary, pings) = summarize_file(path, registry);
+        let mut positioned = pings.iter().filter(|p| p.lat != 0.0 || p.lon != 0.0);
+        let first = positioned.next().map(|p| (p.lat, p.lon));
+        let last = positioned.next_back().map(|p| (p.lat, p.lon)).or(first);
+        (summary, first, last)
+    };
+    let mut parts: Vec<_> = if options.parallel {
+        files.par_iter().map(endpoints).collect()
+    } else {
+        files.iter().map(endpoints).collect()
+    };
+    parts.retain(|(s, _, _)| s.error.is_none() && s.ping_count > 0);
+    parts.sort_by(|a, b| a.0.start_time.total_cmp(&b.0.start_time));
+
+    let mut groups: Vec<Vec<RecordingSummary>> = Vec::new();
+    let mut last_end: Option<(f64, Option<(f64, f64)>)> = None;
+    for (summary, first, last) in parts {
+        let continues = last_end.is_some_and(|(end, end_pos)| {
+            summary.start_time - end <= config.max_gap_s
+                && match (end_pos, first) {
+                    (Some(a), Some(b)) => distance_m(a.0, a.1, b.0, b.1) <= config.max_jump_m,
+                    _ => true,
+                }
+        });
+        let end = (summary.end_time, last);
+        match groups.last_mut() {
+            Some(group) if continues => group.push(summary),
+            _ => groups.push(vec![summary]),
+        }
+        last_end = Some(end);
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|group| Session {
+            files: group.iter().map(|s| s.path.clone()).collect(),
+            summary: merge_summaries(&group),
+        })
+        .collect())
+}
+
+fn merge_summaries(parts: &[RecordingSummary]) -> RecordingSummary {
+    let mut merged = parts[0].clone();
+    let mut channels: BTreeSet<u16> = merged.channels.iter().copied().collect();
+    for s in &parts[1..] {
+        merged.ping_count += s.ping_count;
+        channels.extend(&s.channels);
+        merged.start_time = merged.start_time.min(s.start_time);
+        merged.end_time = merged.end_time.max(s.end_time);
+        merged.bbox = match (merged.bbox, s.bbox) {
+            (Some((a, b, c, d)), Some((e, f, g, h))) => Some((a.min(e), b.min(f), c.max(g), d.max(h))),
+            (bbox, None) | (None, bbox) => bbox,
+        };
+        merged.min_depth_m = merged.min_depth_m.into_iter().chain(s.min_depth_m).reduce(f64::min);
+        merged.max_depth_m = merged.max_depth_m.into_iter().chain(s.max_depth_m).reduce(f64::max);
+    }
+    merged.channels = channels.into_iter().collect();
+    merged
+}
+
+/// Find, parse and summarize every recording under `dir` that matches the pattern
+///
+/// Files that fail to open or parse are reported with `error` set rather
+/// than aborting the batch.
+pub fn process_directory(dir: &Path, registry: &FormatRegistry, options: &BatchOptions) -> io::Result<BatchResult> {
+    let files = discover_files(dir, &options.pattern, options.recursive)?;
+    let process = |path: &PathBuf| process_file(path.as_path(), registry, options.track_decimation);
+
+    let results: Vec<(RecordingSummary, Vec<TrackPoint>)> = if options.parallel {
+        files.par_iter().map(process).collect()
+    } else {
+        files.iter().map(process).collect()
+    };
+
+    let mut result = BatchResult::default();
+    for (summary, track) in results {
+        result.summaries.push(summary);
+        result.track.extend(track);
+    }
+    result.track.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(result)
+}
+
+fn process_file(path: &Path, registry: &FormatRegistry, decimation: usize) -> (RecordingSummary, Vec<TrackPoint>) {
+    let (summary, pings) = summarize_file(path, registry);
+    let track = pings
+        .iter()
+        .filter(|p| p.lat != 0.0 || p.lon != 0.0)
+        .step_by(decimation.max(1))
+        .map(|p| (p.timestamp, p.lat, p.lon))
+        .collect();
+    (summary, track)
+}
+
+/// Parse one recording, returning its summary and pings
+///
+/// On failure the summary carries the error and no pings are returned.
+pub fn summarize_file(path: &Path, registry: &FormatRegistry) -> (RecordingSummary, Vec<Ping>) {
+    match read_recording(path, registry) {
+        Ok((format, pings)) => (RecordingSummary::from_pings(path, &format, &pings), pings),
+        Err(err) => (
+            RecordingSummary {
+                path: path.to_path_buf(),
+                error: Some(err.to_string()),
+                ..Default::default()
+            },
+            Vec::new(),
+        ),
+    }
+}
+
+/// Detect the format of `path` and read all of its pings
+pub fn read_recording(path: &Path, registry: &FormatRegistry) -> io::Result<(String, Vec<Ping>)> {
+    let parser = registry
+        .detect(path)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "unrecognized sonar format"))?;
+    let pings = open_with(parser, path, registry.mode())?.collect::<io::Result<Vec<Ping>>>()?;
+    Ok((parser.name().to_string(), pings))
+}
+
+/// Stream the pings of `path` into a bounded channel as they are parsed
+///
+/// `send` blocks while the channel is full, so a slow consumer throttles
+/// the reader instead of pings piling up in memory. Returns the number of
+/// pings sent, or a `BrokenPipe` error as soon as the receiver is dropped.
+pub fn parse_into(path: &Path, registry: &FormatRegistry, sender: &SyncSender<Ping>) -> io::Result<usize> {
+    let mut sent = 0;
+    for ping in registry.open(path)? {
+        sender.send(ping?).map_err(|_| {
+            io::Error::new(io::ErrorKind::BrokenPipe, format!("ping receiver dropped after {sent} pings"))
+        })?;
+        sent += 1;
+    }
+    Ok(sent)
+}
+
+/// Files under `dir` whose names match `pattern`, sorted by path
+///
+/// Later parts of a split recording are left out; the first part stands for
+/// the whole and is matched by its name without the part number.
+pub fn discover_files(dir: &Path, pattern: &str, recursive: bool) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    let pattern = pattern.to_lowercase();
+
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if recursive {
+                    pending.push(path);
+                }
+            } else if !is_continuation(&path) {
+                // Split recordings match by their joined name, so `*.csv` finds `track.csv.001`
+                let logical = logical_path(&path);
+                if let Some(name) = logical.file_name().and_then(|n| n.to_str()) {
+                    if wildcard_match(&pattern, &name.to_lowercase()) {
+                        found.push(path);
+                    }
+                }
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Glob-style match supporting `*` (any run) and `?` (any one character)
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star_pi, star_ti)) = star {
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            star = Some((star_pi, star_ti + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch_dir;
+
+    /// CSV recording of one ping per second from `start`, heading north from `lat`
+    fn write_csv(path: &Path, start: i64, count: usize, lat: f64, depth_m: f64) {
+        let mut csv = String::from("Time,Depth (m),Latitude,Longitude\n");
+        for i in 0..count {
+            let (time, depth, lat) = (start + i as i64, depth_m + i as f64 * 0.1, lat + i as f64 * 1e-5);
+            csv.push_str(&format!("{},{},{},-63.5\n", time, depth, lat));
+        }
+        fs::write(path, csv).unwrap();
+    }
+
+    #[test]
+    fn sessions_split_on_time_gaps_and_position_jumps() {
+        let dir = scratch_dir("batch", "sessions");
+        let t0 = 1_700_000_000;
+        write_csv(&dir.join("a.csv"), t0, 20, 44.6, 3.0);
+        // Restarted 30 s later where the first file left off
+        write_csv(&dir.join("b.csv"), t0 + 50, 20, 44.6002, 5.0);
+        // Soon after, but 5 km away
+        write_csv(&dir.join("c.csv"), t0 + 100, 20, 44.65, 4.0);
+        // Same spot, two hours later
+        write_csv(&dir.join("d.csv"), t0 + 7300, 20, 44.65, 4.0);
+        fs::write(dir.join("notes.csv"), "not a recording").unwrap();
+
+        let options = BatchOptions {
+            parallel: false,
+            ..Default::default()
+        };
+        let config = SessionConfig {
+            max_gap_s: 600.0,
+            max_jump_m: 500.0,
+        };
+        let sessions = detect_sessions(&dir, &FormatRegistry::default(), &options, &config).unwrap();
+        let files: Vec<Vec<PathBuf>> = sessions.iter().map(|s| s.files.clone()).collect();
+        assert_eq!(
+            files,
+            vec![vec![dir.join("a.csv"), dir.join("b.csv")], vec![dir.join("c.csv")], vec![dir.join("d.csv")]]
+        );
+
+        let merged = &sessions[0].summary;
+        assert_eq!(merged.path, dir.join("a.csv"));
+        assert_eq!(merged.ping_count, 40);
+        assert_eq!((merged.start_time, merged.end_time), ((t0) as f64, (t0 + 69) as f64));
+        assert_eq!(merged.min_depth_m, Some(3.0));
+        assert!((merged.max_depth_m.unwrap() - 6.9).abs() < 1e-9);
+        let (min_lat, _, max_lat, _) = merged.bbox.unwrap();
+        assert!((min_lat - 44.6).abs() < 1e-9 && (max_lat - 44.60039).abs() < 1e-9);
+
+        let pings = sessions[0].read_pings(&FormatRegistry::default()).unwrap();
+        assert_eq!(pings.len(), 40);
+        assert!(pings.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn directory_batch_reports_bad_files_and_merges_tracks() {
+        let dir = scratch_dir("batch", "directory");
+        let t0 = 1_700_000_000;
+        // Sorted first by name but recorded after `b.csv`
+        write_csv(&dir.join("a.csv"), t0 + 100, 25, 44.7, 2.0);
+        write_csv(&dir.join("b.csv"), t0, 25, 44.6, 3.0);
+        fs::write(dir.join("broken.csv"), "Time,Depth (m)\n1700000000,3.5\n").unwrap();
+
+        let options = BatchOptions {
+            pattern: "*.csv".into(),
+            track_decimation: 10,
+            ..Default::default()
+        };
+        let result = process_directory(&dir, &FormatRegistry::default(), &options).unwrap();
+        let names: Vec<&str> = result.summaries.iter().map(|s| s.path.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["a.csv", "b.csv", "broken.csv"]);
+
+        let good = &result.summaries[1];
+        assert_eq!(good.ping_count, 25);
+        assert_eq!((good.start_time, good.end_time), (t0 as f64, (t0 + 24) as f64));
+        assert_eq!(good.min_depth_m, Some(3.0));
+        assert!(good.error.is_none());
+
+        let failed: Vec<_> = result.failed().collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].path, dir.join("broken.csv"));
+        assert_eq!(failed[0].ping_count, 0);
+        assert!(failed[0].error.is_some());
+
+        // Every tenth fix of each file, interleaved by time across files
+        let times: Vec<i64> = result.track.iter().map(|p| p.0 as i64 - t0).collect();
+        assert_eq!(times, [0, 10, 20, 100, 110, 120]);
+        assert!((result.track[3].1 - 44.7).abs() < 1e-9);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn streaming_parse_blocks_on_a_full_channel() {
+        let dir = scratch_dir("batch", "stream_full");
+        let path = dir.join("track.csv");
+        write_csv(&path, 1_700_000_000, 12, 44.6, 3.0);
+
+        let (sender, receiver) = std::sync::mpsc::sync_channel(2);
+        let reader = std::thread::spawn(move || parse_into(&path, &FormatRegistry::default(), &sender));
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        // The reader stalls with the channel full instead of running ahead
+        assert!(!reader.is_finished());
+
+        let pings: Vec<Ping> = receiver.iter().collect();
+        assert_eq!(reader.join().unwrap().unwrap(), 12);
+        assert_eq!(pings.len(), 12);
+        assert_eq!(pings.last().unwrap().timestamp, 1_700_000_011.0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn streaming_parse_fails_when_the_receiver_goes_away() {
+        let dir = scratch_dir("batch", "stream_dropped");
+        let path = dir.join("track.csv");
+        write_csv(&path, 1_700_000_000, 12, 44.6, 3.0);
+
+        let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+        let reader = std::thread::spawn(move || parse_into(&path, &FormatRegistry::default(), &sender));
+        let first = receiver.recv().unwrap();
+        drop(receiver);
+        let err = reader.join().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(first.timestamp, 1_700_000_000.0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn merged_summaries_combine_channels_and_missing_fields() {
+        let part = |start, end, channels: Vec<u16>, bbox, depth| RecordingSummary {
+            ping_count: 10,
+            channels,
+            start_time: start,
+            end_time: end,
+            bbox,
+            min_depth_m: depth,
+            max_depth_m: depth,
+            ..Default::default()
+        };
+        let merged = merge_summaries(&[
+            part(100.0, 200.0, vec![0, 2], None, None),
+            part(50.0, 150.0, vec![1, 2], Some((1.0, 2.0, 3.0, 4.0)), Some(7.0)),
+            part(300.0, 400.0, vec![], Some((0.5, 2.5, 2.0, 5.0)), Some(2.0)),
+        ]);
+        assert_eq!(merged.ping_count, 30);
+        assert_eq!(merged.channels, vec![0, 1, 2]);
+        assert_eq!((merged.start_time, merged.end_time), (50.0, 400.0));
+        assert_eq!(merged.bbox, Some((0.5, 2.0, 3.0, 5.0)));
+        assert_eq!((merged.min_depth_m, merged.max_depth_m), (Some(2.0), Some(7.0)));
+    }
+}
@@ -0,0 +1,186 @@
+// Plausibility bounds for decoded navigation and depth fields
+// src/bounds.rs
+
+use crate::parsers::warnings::{report, ParseWarning, WarningKind};
+use crate::survey::Ping;
+
+/// Latitude outside its range
+pub const FLAG_LAT: u8 = 1 << 0;
+/// Longitude outside its range
+pub const FLAG_LON: u8 = 1 << 1;
+/// Depth outside its range
+pub const FLAG_DEPTH: u8 = 1 << 2;
+/// Speed over ground above the limit
+pub const FLAG_SPEED: u8 = 1 << 3;
+
+/// What to do with a value outside its bounds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BoundsAction {
+    /// Set the field's flag bit and keep the value
+    #[default]
+    Flag,
+    /// Set the flag bit and clear the value (position to 0/0, depth to 0, speed to None)
+    Clear,
+}
+
+/// Accepted ranges for decoded fields, inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundsConfig {
+    pub lat: (f64, f64),
+    pub lon: (f64, f64),
+    pub depth_m: (f64, f64),
+    pub temperature_c: (f64, f64),
+    pub max_speed_knots: f64,
+    pub action: BoundsAction,
+}
+
+impl Default for BoundsConfig {
+    fn default() -> Self {
+        Self {
+            lat: (-90.0, 90.0),
+            lon: (-180.0, 180.0),
+            depth_m: (0.0, 1000.0),
+            temperature_c: (-5.0, 45.0),
+            max_speed_knots: 80.0,
+            action: BoundsAction::Flag,
+        }
+    }
+}
+
+impl BoundsConfig {
+    /// Flag (and possibly clear) implausible fields of a ping, returning the flags set
+    ///
+    /// Each implausible field is also reported as a suspicious value warning.
+    pub fn check(&self, ping: &mut Ping) -> u8 {
+        let within = |v: f64, (lo, hi): (f64, f64)| (lo..=hi).contains(&v);
+        let mut flags = 0;
+        if !within(ping.lat, self.lat) {
+            flags |= FLAG_LAT;
+        }
+        if !within(ping.lon, self.lon) {
+            flags |= FLAG_LON;
+        }
+        if !within(ping.depth_m, self.depth_m) {
+            flags |= FLAG_DEPTH;
+        }
+        if ping.sog_knots.is_some_and(|s| !within(s, (0.0, self.max_speed_knots))) {
+            flags |= FLAG_SPEED;
+        }
+        if flags == 0 {
+            return 0;
+        }
+
+        for (flag, field, value) in [
+            (FLAG_LAT, "latitude", ping.lat),
+            (FLAG_LON, "longitude", ping.lon),
+            (FLAG_DEPTH, "depth", ping.depth_m),
+            (FLAG_SPEED, "speed", ping.sog_knots.unwrap_or(f64::NAN)),
+        ] {
+            if flags & flag != 0 {
+                report(ParseWarning {
+                    kind: WarningKind::SuspiciousValue,
+                    line: None,
+                    timestamp: Some(ping.timestamp),
+                    message: format!("channel {} implausible {} {}", ping.channel_id, field, value),
+                });
+            }
+        }
+        if self.action == BoundsAction::Clear {
+            if flags & (FLAG_LAT | FLAG_LON) != 0 {
+                ping.lat = 0.0;
+                ping.lon = 0.0;
+            }
+            if flags & FLAG_DEPTH != 0 {
+                ping.depth_m = 0.0;
+            }
+            if flags & FLAG_SPEED != 0 {
+                ping.sog_knots = None;
+            }
+        }
+        ping.flags |= flags;
+        flags
+    }
+
+    /// Check every ping, returning how many had an implausible field
+    pub fn apply(&self, pings: &mut [Ping]) -> usize {
+        pings.iter_mut().map(|p| self.check(p)).filter(|&flags| flags != 0).count()
+    }
+
+    /// Whether a water temperature reading is plausible
+    pub fn temperature_ok(&self, temperature_c: f64) -> bool {
+        (self.temperature_c.0..=self.temperature_c.1).contains(&temperature_c)
+    }
+
+    /// Drop implausible `(timestamp, temperature)` readings, returning how many were removed
+    pub fn filter_temperatures(&self, readings: &mut Vec<(f64, f64)>) -> usize {
+        let before = readings.len();
+        readings.retain(|&(timestamp, c)| {
+            let ok = self.temperature_ok(c);
+            if !ok {
+                report(ParseWarning {
+                    kind: WarningKind::SuspiciousValue,
+                    line: None,
+                    timestamp: Some(timestamp),
+                    message: format!("implausible temperature {} C", c),
+                });
+            }
+            ok
+        });
+        before - readings.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::warnings::collect_warnings;
+
+    fn ping(lat: f64, depth_m: f64, sog_knots: Option<f64>) -> Ping {
+        Ping {
+            timestamp: 10.0,
+            lat,
+            lon: -63.5,
+            depth_m,
+            sog_knots,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn flags_each_implausible_field() {
+        let bounds = BoundsConfig::default();
+        let mut pings = vec![ping(44.6, 5.0, Some(4.0)), ping(91.0, 5.0, None), ping(44.6, -2.0, Some(120.0))];
+        let (count, warnings) = collect_warnings(|| bounds.apply(&mut pings));
+        assert_eq!(count, 2);
+        let flags: Vec<u8> = pings.iter().map(|p| p.flags).collect();
+        assert_eq!(flags, vec![0, FLAG_LAT, FLAG_DEPTH | FLAG_SPEED]);
+        // Flagging keeps the values
+        assert_eq!((pings[1].lat, pings[2].depth_m, pings[2].sog_knots), (91.0, -2.0, Some(120.0)));
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings.iter().all(|w| w.kind == WarningKind::SuspiciousValue));
+        assert_eq!(warnings[0].message, "channel 0 implausible latitude 91");
+    }
+
+    #[test]
+    fn clear_action_removes_the_values() {
+        let bounds = BoundsConfig {
+            action: BoundsAction::Clear,
+            depth_m: (0.5, 100.0),
+            ..Default::default()
+        };
+        let mut bad = ping(-95.0, 0.2, Some(90.0));
+        let (flags, _) = collect_warnings(|| bounds.check(&mut bad));
+        assert_eq!(flags, FLAG_LAT | FLAG_DEPTH | FLAG_SPEED);
+        assert_eq!((bad.lat, bad.lon, bad.depth_m, bad.sog_knots), (0.0, 0.0, 0.0, None));
+        assert_eq!(bad.flags, flags);
+    }
+
+    #[test]
+    fn drops_implausible_temperatures() {
+        let bounds = BoundsConfig::default();
+        let mut readings = vec![(0.0, 12.0), (1.0, 99.0), (2.0, -5.0)];
+        let (removed, warnings) = collect_warnings(|| bounds.filter_temperatures(&mut readings));
+        assert_eq!((removed, readings), (1, vec![(0.0, 12.0), (2.0, -5.0)]));
+        assert_eq!(warnings[0].timestamp, Some(1.0));
+    }
+}
@@ -0,0 +1,541 @@
+// Recording library catalog: content hashes, fingerprints and duplicates
+// src/catalog.rs
+
+use crate::batch::{discover_files, summarize_file, BatchOptions, RecordingSummary};
+use crate::geo::polygon_intersects_bbox;
+use crate::parsers::FormatRegistry;
+use crate::sqlite::{OwnedValue, SqliteReader, SqliteWriter, Value};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Read};
+use std::path::{Path, PathBuf};
+
+/// "SSCT" in the SQLite header marks catalog databases
+const CATALOG_APPLICATION_ID: u32 = 0x5353_4354;
+const CATALOG_TABLE: &str = "recordings";
+const CATALOG_SQL: &str = "CREATE TABLE recordings (path TEXT NOT NULL, format TEXT, size INTEGER, \
+    sha256 TEXT, ping_count INTEGER, channels TEXT, start_time REAL, end_time REAL, min_lat REAL, \
+    min_lon REAL, max_lat REAL, max_lon REAL, min_depth_m REAL, max_depth_m REAL, error TEXT)";
+const CATALOG_COLUMNS: usize = 15;
+
+/// One catalogued file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogEntry {
+    pub summary: RecordingSummary,
+    pub size: u64,
+    /// Lowercase hex SHA-256 of the file contents
+    pub sha256: String,
+}
+
+impl CatalogEntry {
+    pub fn fingerprint(&self) -> Option<Fingerprint> {
+        Fingerprint::of(&self.summary)
+    }
+}
+
+/// Content-independent identity of a session: when and where it was recorded
+///
+/// Values are rounded (1 s, about 1 m) so re-exports of the same session
+/// compare equal even when the files differ byte for byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fingerprint {
+    pub start_time: i64,
+    pub duration_s: i64,
+    /// (min_lat, min_lon, max_lat, max_lon) in 1e-5 degrees
+    pub bbox: Option<(i64, i64, i64, i64)>,
+}
+
+impl Fingerprint {
+    /// None for unreadable or empty recordings
+    pub fn of(summary: &RecordingSummary) -> Option<Self> {
+        if summary.error.is_some() || summary.ping_count == 0 {
+            return None;
+        }
+        let q = |v: f64| (v * 1e5).round() as i64;
+        Some(Self {
+            start_time: summary.start_time.round() as i64,
+            duration_s: summary.duration_s().round() as i64,
+            bbox: summary.bbox.map(|(a, b, c, d)| (q(a), q(b), q(c), q(d))),
+        })
+    }
+}
+
+/// Filter over catalog entries; unset fields match everything
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CatalogQuery {
+    /// Recordings overlapping this time window
+    pub time_from: Option<f64>,
+    pub time_to: Option<f64>,
+    /// Recordings whose bounding box meets this `(lat, lon)` polygon
+    pub polygon: Option<Vec<(f64, f64)>>,
+    pub channel_id: Option<u16>,
+    pub include_failed: bool,
+}
+
+impl CatalogQuery {
+    pub fn matches(&self, entry: &CatalogEntry) -> bool {
+        let s = &entry.summary;
+        if s.error.is_some() {
+            return self.include_failed;
+        }
+        self.time_from.is_none_or(|t| s.end_time >= t)
+            && self.time_to.is_none_or(|t| s.start_time <= t)
+            && self.channel_id.is_none_or(|c| s.channels.contains(&c))
+            && self
+                .polygon
+                .as_ref()
+                .is_none_or(|poly| s.bbox.is_some_and(|bbox| polygon_intersects_bbox(poly, bbox)))
+    }
+}
+
+/// Entries matching the query, in catalog order
+pub fn query_catalog<'a>(entries: &'a [CatalogEntry], query: &CatalogQuery) -> Vec<&'a CatalogEntry> {
+    entries.iter().filter(|e| query.matches(e)).collect()
+}
+
+/// Save entries as an SQLite database with one `recordings` row per file
+///
+/// Any SQLite client can query the file. `channels` holds comma-separated
+/// channel ids, and times and bounds are NULL for unreadable or unpositioned
+/// recordings. An index on `(start_time, end_time)` serves time windows.
+pub fn write_catalog_db<P: AsRef<Path>>(path: P, entries: &[CatalogEntry]) -> io::Result<()> {
+    let mut db = SqliteWriter::new(BufWriter::new(File::create(path)?));
+    db.set_application_id(CATALOG_APPLICATION_ID);
+    let table = db.create_table(CATALOG_TABLE, CATALOG_SQL);
+    db.create_index(
+        "recordings_time",
+        table,
+        "CREATE INDEX recordings_time ON recordings (start_time, end_time)",
+        &[6, 7],
+    );
+    let real = |v: f64| if v.is_finite() { Value::Real(v) } else { Value::Null };
+    let optional = |v: Option<f64>| v.map_or(Value::Null, real);
+    for entry in entries {
+        let s = &entry.summary;
+        let path = s.path.to_string_lossy();
+        let channels = s.channels.iter().map(u16::to_string).collect::<Vec<_>>().join(",");
+        let bbox = s.bbox.map(|(a, b, c, d)| [a, b, c, d]);
+        let corner = |i: usize| optional(bbox.map(|b| b[i]));
+        db.insert(
+            table,
+            &[
+                Value::Text(&path),
+                Value::Text(&s.format),
+                Value::Integer(entry.size as i64),
+                Value::Text(&entry.sha256),
+                Value::Integer(s.ping_count as i64),
+                Value::Text(&channels),
+                real(s.start_time),
+                real(s.end_time),
+                corner(0),
+                corner(1),
+                corner(2),
+                corner(3),
+                optional(s.min_depth_m),
+                optional(s.max_depth_m),
+                s.error.as_deref().map_or(Value::Null, Value::Text),
+            ],
+        )?;
+    }
+    db.finish()?.into_inner().map_err(|err| err.into_error())?;
+    Ok(())
+}
+
+/// Entries saved by [`write_catalog_db`], in file order
+///
+/// Rows added or edited with other SQLite tools are read too, as long as
+/// the `recordings` columns keep their order.
+pub fn read_catalog_db<P: AsRef<Path>>(path: P) -> io::Result<Vec<CatalogEntry>> {
+    let reader = SqliteReader::open(path)?;
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let rows = reader.rows(CATALOG_TABLE).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => invalid("not a catalog database: no recordings table".to_string()),
+        _ => err,
+    })?;
+    rows.into_iter()
+        .map(|(rowid, row)| {
+            if row.len() < CATALOG_COLUMNS {
+                return Err(invalid(format!("recordings row {} has {} columns", rowid, row.len())));
+            }
+            // REAL columns may hold integers: SQLite stores whole floats that way
+            let real = |i: usize| match row[i] {
+                OwnedValue::Real(v) => Some(v),
+                OwnedValue::Integer(v) => Some(v as f64),
+                _ => None,
+            };
+            let integer = |i: usize| match row[i] {
+                OwnedValue::Integer(v) => v.max(0) as u64,
+                OwnedValue::Real(v) => v.max(0.0) as u64,
+                _ => 0,
+            };
+            let text = |i: usize| match &row[i] {
+                OwnedValue::Text(v) => Some(v.clone()),
+                _ => None,
+            };
+            let channels = text(5)
+                .unwrap_or_default()
+                .split(',')
+                .filter(|c| !c.trim().is_empty())
+                .map(|c| c.trim().parse())
+                .collect::<Result<Vec<u16>, _>>()
+                .map_err(|_| invalid(format!("recordings row {} has invalid channels", rowid)))?;
+            let bbox = match (real(8), real(9), real(10), real(11)) {
+                (Some(a), Some(b), Some(c), Some(d)) => Some((a, b, c, d)),
+                _ => None,
+            };
+            Ok(CatalogEntry {
+                summary: RecordingSummary {
+                    path: PathBuf::from(text(0).unwrap_or_default()),
+                    format: text(1).unwrap_or_default(),
+                    ping_count: integer(4) as usize,
+                    channels,
+                    start_time: real(6).unwrap_or(f64::NAN),
+                    end_time: real(7).unwrap_or(f64::NAN),
+                    bbox,
+                    min_depth_m: real(12),
+                    max_depth_m: real(13),
+                    error: text(14),
+                },
+                size: integer(2),
+                sha256: text(3).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Why files were grouped as duplicates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKind {
+    /// Byte-identical copies (same SHA-256)
+    Identical,
+    /// Different bytes but the same session fingerprint
+    SameSession,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    pub paths: Vec<PathBuf>,
+}
+
+/// Hash and summarize every matching recording under `dir`
+pub fn catalog_directory(dir: &Path, registry: &FormatRegistry, options: &BatchOptions) -> io::Result<Vec<CatalogEntry>> {
+    let files = discover_files(dir, &options.pattern, options.recursive)?;
+    let entry = |path: &PathBuf| -> io::Result<CatalogEntry> {
+        let (sha256, size) = sha256_file(path)?;
+        Ok(CatalogEntry {
+            summary: summarize_file(path, registry).0,
+            size,
+            sha256,
+        })
+    };
+    if options.parallel {
+        files.par_iter().map(entry).collect()
+    } else {
+        files.iter().map(entry).collect()
+    }
+}
+
+/// Groups of duplicate files; identical copies are not repeated as same-session groups
+pub fn find_duplicates(entries: &[CatalogEntry]) -> Vec<DuplicateGroup> {
+    let mut by_hash: BTreeMap<&str, Vec<&CatalogEntry>> = BTreeMap::new();
+    for entry in entries {
+        by_hash.entry(&entry.sha256).or_default().push(entry);
+    }
+
+    let mut groups = Vec::new();
+    // One representative per distinct content takes part in fingerprint matching
+    let mut by_fingerprint: BTreeMap<Fingerprint, Vec<&CatalogEntry>> = BTreeMap::new();
+    for copies in by_hash.values() {
+        if copies.len() > 1 {
+            groups.push(DuplicateGroup {
+                kind: DuplicateKind::Identical,
+                paths: copies.iter().map(|e| e.summary.path.clone()).collect(),
+            });
+        }
+        if let Some(fingerprint) = copies[0].fingerprint() {
+            by_fingerprint.entry(fingerprint).or_default().push(copies[0]);
+        }
+    }
+    for same in by_fingerprint.into_values().filter(|g| g.len() > 1) {
+        groups.push(DuplicateGroup {
+            kind: DuplicateKind::SameSession,
+            paths: same.iter().map(|e| e.summary.path.clone()).collect(),
+        });
+    }
+    groups
+}
+
+/// SHA-256 of a file as lowercase hex, plus its size in bytes
+pub fn sha256_file<P: AsRef<Path>>(path: P) -> io::Result<(String, u64)> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let size = hasher.length;
+    Ok((hex(&hasher.finish()), size))
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Streaming SHA-256 (FIPS 180-4)
+#[derive(Debug, Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    block_len: usize,
+    length: u64,
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Self {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+            ],
+            block: [0; 64],
+            block_len: 0,
+            length: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        while !data.is_empty() {
+            let n = (64 - self.block_len).min(data.len());
+            self.block[self.block_len..self.block_len + n].copy_from_slice(&data[..n]);
+            self.block_len += n;
+            data = &data[n..];
+            if self.block_len == 64 {
+                let block = self.block;
+                self.compress(&block);
+                self.block_len = 0;
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> [u8; 32] {
+        let bit_len = self.length.wrapping_mul(8);
+        let mut padding = vec![0x80u8];
+        padding.resize(1 + (119 - self.block_len) % 64, 0);
+        padding.extend_from_slice(&bit_len.to_be_bytes());
+        let length = self.length;
+        self.update(&padding);
+        self.length = length;
+
+        let mut digest = [0u8; 32];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut w = [0u32; 64];
+        for (i, chunk) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (s, v) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch;
+
+    fn entry(path: &str, start: f64, bbox: Option<(f64, f64, f64, f64)>, channels: &[u16]) -> CatalogEntry {
+        CatalogEntry {
+            summary: RecordingSummary {
+                path: PathBuf::from(path),
+                format: "deeper_csv".to_string(),
+                ping_count: 120,
+                channels: channels.to_vec(),
+                start_time: start,
+                end_time: start + 600.0,
+                bbox,
+                min_depth_m: Some(1.5),
+                max_depth_m: Some(18.25),
+                error: None,
+            },
+            size: 4096,
+            sha256: hex(&[start as u8; 32]),
+        }
+    }
+
+    fn entries() -> Vec<CatalogEntry> {
+        let failed = CatalogEntry {
+            summary: RecordingSummary {
+                path: PathBuf::from("cards/broken.sl2"),
+                start_time: f64::NAN,
+                end_time: f64::NAN,
+                error: Some("unrecognized sonar format".to_string()),
+                ..Default::default()
+            },
+            size: 10,
+            sha256: hex(&[0; 32]),
+        };
+        vec![
+            // 2024-06-10 and 2024-07-02
+            entry(
+                "cards/june.sl2",
+                1_718_000_000.0,
+                Some((44.5, -63.6, 44.6, -63.5)),
+                &[0, 2],
+            ),
+            entry(
+                "cards/july.sl2",
+                1_719_900_000.0,
+                Some((45.0, -64.0, 45.1, -63.9)),
+                &[0],
+            ),
+            entry("cards/no_gps.sl2", 1_718_100_000.0, None, &[]),
+            failed,
+        ]
+    }
+
+    #[test]
+    fn sha256_matches_known_digests() {
+        assert_eq!(
+            hex(&Sha256::new().finish()),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let mut hasher = Sha256::new();
+        hasher.update(b"ab");
+        hasher.update(b"c");
+        assert_eq!(
+            hex(&hasher.finish()),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let mut hasher = Sha256::new();
+        hasher.update(&[b'a'; 1_000_000]);
+        assert_eq!(
+            hex(&hasher.finish()),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+
+    #[test]
+    fn database_round_trips_entries() {
+        let path = scratch("catalog", "round_trip.db");
+        let input = entries();
+        write_catalog_db(&path, &input).unwrap();
+        let output = read_catalog_db(&path).unwrap();
+        assert_eq!(output.len(), input.len());
+        assert_eq!(output[..3], input[..3]);
+        // NaN times of the failed file come back as NaN
+        let failed = &output[3].summary;
+        assert!(failed.start_time.is_nan() && failed.end_time.is_nan());
+        assert_eq!(failed.error.as_deref(), Some("unrecognized sonar format"));
+        assert_eq!(failed.bbox, None);
+        assert_eq!(
+            SqliteReader::open(&path).unwrap().application_id(),
+            CATALOG_APPLICATION_ID
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn queries_run_over_a_saved_catalog() {
+        let path = scratch("catalog", "query.db");
+        write_catalog_db(&path, &entries()).unwrap();
+        let entries = read_catalog_db(&path).unwrap();
+        let names = |query: &CatalogQuery| -> Vec<String> {
+            query_catalog(&entries, query)
+                .iter()
+                .map(|e| e.summary.path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        // All recordings intersecting a polygon in June 2024
+        let june = CatalogQuery {
+            time_from: Some(1_717_200_000.0),
+            time_to: Some(1_719_791_999.0),
+            polygon: Some(vec![(44.55, -63.7), (44.7, -63.55), (44.4, -63.4)]),
+            ..Default::default()
+        };
+        assert_eq!(names(&june), ["june.sl2"]);
+        let june_anywhere = CatalogQuery {
+            polygon: None,
+            ..june.clone()
+        };
+        assert_eq!(names(&june_anywhere), ["june.sl2", "no_gps.sl2"]);
+        let side_scan = CatalogQuery {
+            channel_id: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(names(&side_scan), ["june.sl2"]);
+        let everything = CatalogQuery {
+            include_failed: true,
+            ..Default::default()
+        };
+        assert_eq!(names(&everything).len(), 4);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_databases_that_are_not_catalogs() {
+        let path = scratch("catalog", "other.db");
+        let mut db = SqliteWriter::new(BufWriter::new(File::create(&path).unwrap()));
+        let table = db.create_table("tiles", "CREATE TABLE tiles (data BLOB)");
+        db.insert(table, &[Value::Blob(b"png")]).unwrap();
+        db.finish().unwrap();
+        assert_eq!(read_catalog_db(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        std::fs::write(&path, b"path,format\n").unwrap();
+        assert_eq!(read_catalog_db(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read_catalog_db(&path).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,207 @@
+// Bottom hardness and vegetation classification from first/second echoes
+// src/classification.rs
+
+use crate::gridding::{grid_soundings, DepthGrid, GridConfig};
+use crate::survey::{Ping, Sounding};
+use crate::timezone::TimeZone;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Echo windows and thresholds for bottom classification
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassificationConfig {
+    /// Length of the integration window after each bottom echo
+    pub window_m: f64,
+    /// Minimum intensity treated as vegetation above the bottom
+    pub vegetation_threshold: u8,
+    /// Tallest vegetation searched for above the bottom
+    pub max_vegetation_m: f64,
+}
+
+impl Default for ClassificationConfig {
+    fn default() -> Self {
+        Self {
+            window_m: 0.5,
+            vegetation_threshold: 90,
+            max_vegetation_m: 3.0,
+        }
+    }
+}
+
+/// Per-ping bottom classification
+///
+/// `e1` is the normalized echo energy of the first bottom return (roughness),
+/// `e2` the energy of the second (bottom-surface-bottom) return, which tracks
+/// hardness. Energies are mean squared intensity scaled to 0..1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BottomClass {
+    pub timestamp: f64,
+    pub lat: f64,
+    pub lon: f64,
+    pub depth_m: f64,
+    pub e1: f64,
+    /// None when the ping range does not reach twice the depth
+    pub e2: Option<f64>,
+    pub vegetation_height_m: f64,
+}
+
+/// Value of a classification used when rasterizing maps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BottomField {
+    Roughness,
+    Hardness,
+    Vegetation,
+}
+
+impl BottomClass {
+    pub fn value(&self, field: BottomField) -> Option<f64> {
+        match field {
+            BottomField::Roughness => Some(self.e1),
+            BottomField::Hardness => self.e2,
+            BottomField::Vegetation => Some(self.vegetation_height_m),
+        }
+    }
+}
+
+/// Classify the bottom under a single down-looking ping
+pub fn classify_ping(ping: &Ping, config: &ClassificationConfig) -> Option<BottomClass> {
+    let spacing = ping.sample_spacing_m();
+    if spacing <= 0.0 || ping.depth_m <= 0.0 {
+        return None;
+    }
+    let bottom = (ping.depth_m / spacing) as usize;
+    if bottom >= ping.samples.len() {
+        return None;
+    }
+    let window = ((config.window_m / spacing).ceil() as usize).max(1);
+
+    let e1 = echo_energy(&ping.samples, bottom, window)?;
+    let e2 = echo_energy(&ping.samples, 2 * bottom, window);
+
+    // Walk up from the bottom while returns stay above the vegetation threshold
+    let max_steps = (config.max_vegetation_m / spacing) as usize;
+    let mut top = bottom;
+    while top > 0 && bottom - top < max_steps && ping.samples[top - 1] >= config.vegetation_threshold {
+        top -= 1;
+    }
+
+    Some(BottomClass {
+        timestamp: ping.timestamp,
+        lat: ping.lat,
+        lon: ping.lon,
+        depth_m: ping.depth_m,
+        e1,
+        e2,
+        vegetation_height_m: (bottom - top) as f64 * spacing,
+    })
+}
+
+/// Classify every ping that has a usable bottom echo
+pub fn classify_pings(pings: &[&Ping], config: &ClassificationConfig) -> Vec<BottomClass> {
+    pings.iter().filter_map(|p| classify_ping(p, config)).collect()
+}
+
+fn echo_energy(samples: &[u8], start: usize, len: usize) -> Option<f64> {
+    if start + len > samples.len() {
+        return None;
+    }
+    let sum: f64 = samples[start..start + len]
+        .iter()
+        .map(|&v| (v as f64 / 255.0).powi(2))
+        .sum();
+    Some(sum / len as f64)
+}
+
+/// Rasterize one classification value onto a grid using the bathymetric gridder
+pub fn classification_grid(classes: &[BottomClass], field: BottomField, config: &GridConfig) -> Option<DepthGrid> {
+    let points: Vec<Sounding> = classes
+        .iter()
+        .filter_map(|c| c.value(field).map(|v| Sounding::new(c.timestamp, c.lat, c.lon, v)))
+        .collect();
+    grid_soundings(&points, config)
+}
+
+/// Write classifications as CSV columns alongside position and depth
+pub fn write_classification_csv<P: AsRef<Path>>(path: P, classes: &[BottomClass]) -> io::Result<()> {
+    write_classification_csv_with_zone(path, classes, &TimeZone::Utc)
+}
+
+/// CSV whose trailing `time` column is local time in `zone`; `timestamp` stays UTC epoch seconds
+pub fn write_classification_csv_with_zone<P: AsRef<Path>>(
+    path: P,
+    classes: &[BottomClass],
+    zone: &TimeZone,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "timestamp,latitude,longitude,depth_m,e1_roughness,e2_hardness,vegetation_m,time"
+    )?;
+    for c in classes {
+        let e2 = c.e2.map(|v| format!("{:.4}", v)).unwrap_or_default();
+        writeln!(
+            out,
+            "{:.3},{:.7},{:.7},{:.2},{:.4},{},{:.2},{}",
+            c.timestamp,
+            c.lat,
+            c.lon,
+            c.depth_m,
+            c.e1,
+            e2,
+            c.vegetation_height_m,
+            zone.format_time(c.timestamp).unwrap_or_default()
+        )?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 0.1 m samples over `range_m` with the bottom at 5 m
+    fn ping(range_m: f64) -> Ping {
+        let mut samples = vec![10u8; (range_m * 10.0) as usize];
+        samples[40..50].fill(120);
+        samples[50..55].fill(255);
+        if let Some(second) = samples.get_mut(100..105) {
+            second.fill(51);
+        }
+        Ping {
+            timestamp: 1.0,
+            depth_m: 5.0,
+            range_m,
+            samples,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn measures_both_echoes_and_vegetation() {
+        let class = classify_ping(&ping(20.0), &ClassificationConfig::default()).unwrap();
+        assert!((class.e1 - 1.0).abs() < 1e-12);
+        assert!((class.e2.unwrap() - 0.04).abs() < 1e-12);
+        assert!((class.vegetation_height_m - 1.0).abs() < 1e-9);
+        assert_eq!(class.value(BottomField::Hardness), class.e2);
+
+        let sparse = ClassificationConfig {
+            vegetation_threshold: 121,
+            ..Default::default()
+        };
+        assert_eq!(classify_ping(&ping(20.0), &sparse).unwrap().vegetation_height_m, 0.0);
+    }
+
+    #[test]
+    fn second_echo_needs_twice_the_depth_in_range() {
+        let class = classify_ping(&ping(8.0), &ClassificationConfig::default()).unwrap();
+        assert_eq!(class.e2, None);
+        assert_eq!(class.value(BottomField::Hardness), None);
+
+        let mut no_bottom = ping(20.0);
+        no_bottom.depth_m = 0.0;
+        assert_eq!(classify_ping(&no_bottom, &ClassificationConfig::default()), None);
+        no_bottom.depth_m = 25.0;
+        assert_eq!(classify_ping(&no_bottom, &ClassificationConfig::default()), None);
+    }
+}
@@ -0,0 +1,237 @@
+// Isobath generation from gridded bathymetry (marching squares)
+// src/contours.rs
+
+use crate::geo::offset_position;
+use crate::gridding::DepthGrid;
+use std::collections::HashMap;
+
+/// Single contour polyline at a fixed depth, as (lat, lon) vertices
+#[derive(Debug, Clone)]
+pub struct Contour {
+    pub depth_m: f64,
+    pub points: Vec<(f64, f64)>,
+    pub closed: bool,
+}
+
+/// Grid edge identifier: (row, col, vertical) where the edge starts at node (row, col)
+type EdgeKey = (usize, usize, bool);
+
+/// Generate contours every `interval_m` across the grid's depth range
+pub fn generate_contours(grid: &DepthGrid, interval_m: f64) -> Vec<Contour> {
+    let (min_depth, max_depth) = match grid.depth_range() {
+        Some(range) if interval_m > 0.0 => range,
+        _ => return Vec::new(),
+    };
+
+    let mut levels = Vec::new();
+    let mut level = (min_depth / interval_m).ceil() * interval_m;
+    while level <= max_depth {
+        levels.push(level);
+        level += interval_m;
+    }
+
+    levels
+        .into_iter()
+        .flat_map(|level| contours_at(grid, level))
+        .collect()
+}
+
+/// Trace all contour lines at a single depth level
+pub fn contours_at(grid: &DepthGrid, level: f64) -> Vec<Contour> {
+    let mut segments: Vec<(EdgeKey, EdgeKey)> = Vec::new();
+
+    for row in 0..grid.rows.saturating_sub(1) {
+        for col in 0..grid.cols.saturating_sub(1) {
+            let tl = grid.get(row, col);
+            let tr = grid.get(row, col + 1);
+            let br = grid.get(row + 1, col + 1);
+            let bl = grid.get(row + 1, col);
+            if !(tl.is_finite() && tr.is_finite() && br.is_finite() && bl.is_finite()) {
+                continue;
+            }
+
+            let top: EdgeKey = (row, col, false);
+            let right: EdgeKey = (row, col + 1, true);
+            let bottom: EdgeKey = (row + 1, col, false);
+            let left: EdgeKey = (row, col, true);
+
+            let case = (tl >= level) as u8
+                | ((tr >= level) as u8) << 1
+                | ((br >= level) as u8) << 2
+                | ((bl >= level) as u8) << 3;
+
+            match case {
+                0 | 15 => {}
+                1 | 14 => segments.push((left, top)),
+                2 | 13 => segments.push((top, right)),
+                3 | 12 => segments.push((left, right)),
+                4 | 11 => segments.push((right, bottom)),
+                6 | 9 => segments.push((top, bottom)),
+                7 | 8 => segments.push((left, bottom)),
+                5 | 10 => {
+                    // Saddle: resolve using the cell center value
+                    let center = (tl + tr + br + bl) / 4.0;
+                    let center_high = center >= level;
+                    if (case == 5) == center_high {
+                        segments.push((left, bottom));
+                        segments.push((top, right));
+                    } else {
+                        segments.push((left, top));
+                        segments.push((right, bottom));
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    join_segments(segments)
+        .into_iter()
+        .map(|(edges, closed)| Contour {
+            depth_m: level,
+            points: edges.iter().map(|&edge| edge_point(grid, edge, level)).collect(),
+            closed,
+        })
+        .collect()
+}
+
+/// Interpolated crossing point of the level along a grid edge
+fn edge_point(grid: &DepthGrid, (row, col, vertical): EdgeKey, level: f64) -> (f64, f64) {
+    let (row2, col2) = if vertical { (row + 1, col) } else { (row, col + 1) };
+    let v1 = grid.get(row, col);
+    let v2 = grid.get(row2, col2);
+    let t = if (v2 - v1).abs() < 1e-12 { 0.5 } else { (level - v1) / (v2 - v1) };
+
+    let (x1, y1) = grid.cell_center_m(row, col);
+    let (x2, y2) = grid.cell_center_m(row2, col2);
+    let east = x1 + t * (x2 - x1);
+    let north = y1 + t * (y2 - y1);
+
+    offset_position(grid.min_lat, grid.min_lon, east, north)
+}
+
+/// Chain segments sharing edges into polylines, flagging closed rings
+fn join_segments(segments: Vec<(EdgeKey, EdgeKey)>) -> Vec<(Vec<EdgeKey>, bool)> {
+    let mut by_edge: HashMap<EdgeKey, Vec<usize>> = HashMap::new();
+    for (i, (a, b)) in segments.iter().enumerate() {
+        by_edge.entry(*a).or_default().push(i);
+        by_edge.entry(*b).or_default().push(i);
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut lines = Vec::new();
+
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let (a, b) = segments[start];
+        let mut line = vec![a, b];
+
+        // Extend forward from the tail, then backward from the head
+        for forward in [true, false] {
+            loop {
+                let end = if forward { *line.last().unwrap() } else { line[0] };
+                let next = by_edge[&end].iter().copied().find(|&i| !used[i]);
+                let Some(i) = next else { break };
+                used[i] = true;
+                let (p, q) = segments[i];
+                let other = if p == end { q } else { p };
+                if forward {
+                    line.push(other);
+                } else {
+                    line.insert(0, other);
+                }
+            }
+        }
+
+        let closed = line.len() > 2 && line.first() == line.last();
+        lines.push((line, closed));
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::local_offset_m;
+
+    fn grid(rows: usize, cols: usize, values: Vec<f64>) -> DepthGrid {
+        DepthGrid {
+            min_lat: 44.0,
+            min_lon: -63.0,
+            cell_size_m: 10.0,
+            rows,
+            cols,
+            values,
+        }
+    }
+
+    /// Local (east, north) of every vertex
+    fn local(contour: &Contour) -> Vec<(f64, f64)> {
+        contour.points.iter().map(|&(lat, lon)| local_offset_m(44.0, -63.0, lat, lon)).collect()
+    }
+
+    #[test]
+    fn a_pit_gives_one_closed_ring() {
+        // 10 m deep in the centre of a 5 x 5 grid, shoaling 2 m per cell
+        let values = (0..25)
+            .map(|i| {
+                let (row, col) = ((i / 5) as f64 - 2.0, (i % 5) as f64 - 2.0);
+                10.0 - 2.0 * row.hypot(col)
+            })
+            .collect();
+        let contours = contours_at(&grid(5, 5, values), 7.0);
+        assert_eq!(contours.len(), 1);
+        assert!(contours[0].closed);
+        // The ring lies 1.5 cells from the centre node at (25, 25) m
+        for (east, north) in local(&contours[0]) {
+            let r = (east - 25.0).hypot(north - 25.0);
+            assert!((13.0..17.5).contains(&r), "{}", r);
+        }
+    }
+
+    #[test]
+    fn saddles_are_resolved_by_the_cell_centre() {
+        // Where the line through the west edge crossing at (5, 10) m goes next
+        let partner = |values: Vec<f64>| {
+            let contours = contours_at(&grid(2, 2, values), 5.0);
+            assert_eq!(contours.len(), 2);
+            assert!(contours.iter().all(|c| !c.closed && c.points.len() == 2));
+            let near = |p: (f64, f64), q: (f64, f64)| (p.0 - q.0).abs() < 1e-6 && (p.1 - q.1).abs() < 1e-6;
+            contours
+                .iter()
+                .map(local)
+                .find_map(|p| match p[..] {
+                    [a, b] if near(a, (5.0, 10.0)) => Some(b),
+                    [a, b] if near(b, (5.0, 10.0)) => Some(a),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        // Deep centre joins the deep north-west and south-east corners: the line cuts off the south-west
+        let (east, north) = partner(vec![10.0, 0.0, 0.0, 10.0]);
+        assert!((east - 10.0).abs() < 1e-6 && (north - 5.0).abs() < 1e-6);
+        // Shallow centre separates them: the line cuts off the north-west corner
+        let (east, north) = partner(vec![10.0, 0.0, 0.0, 6.0]);
+        assert!((east - 10.0).abs() < 1e-6 && (north - 15.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn levels_step_through_the_depth_range() {
+        // East-west ramp from 0 to 10 m
+        let values = (0..12).map(|i| (i % 4) as f64 * 10.0 / 3.0).collect();
+        let contours = generate_contours(&grid(3, 4, values), 2.5);
+        let depths: Vec<f64> = contours.iter().map(|c| c.depth_m).collect();
+        // 10 m lies on the deepest column itself; 0 m has nothing shallower to separate
+        assert_eq!(depths, vec![2.5, 5.0, 7.5, 10.0]);
+        for contour in &contours {
+            let east = contour.depth_m * 3.0 + 5.0;
+            assert!(local(contour).iter().all(|p| (p.0 - east).abs() < 1e-6));
+        }
+        assert!(generate_contours(&grid(3, 4, vec![f64::NAN; 12]), 2.5).is_empty());
+        assert!(generate_contours(&grid(3, 4, vec![1.0; 12]), 0.0).is_empty());
+    }
+}
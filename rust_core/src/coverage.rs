@@ -0,0 +1,292 @@
+// Swath coverage rasterization and gap analysis for survey planning
+// src/coverage.rs
+
+use crate::geo::{local_offset_m, offset_position, point_in_polygon, vessel_to_local, METERS_PER_DEGREE_LAT};
+use crate::gridding::footprint_diameter_m;
+use crate::imaging::png::{write_png, PngColor};
+use crate::imaging::GeoRaster;
+use crate::parsers::{ChannelInfo, ChannelKind};
+use crate::survey::Ping;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const COVERED: [u8; 3] = [40, 180, 80];
+const GAP: [u8; 3] = [220, 50, 40];
+const OUTSIDE: [u8; 3] = [0, 0, 0];
+
+/// Raster and swath settings for coverage analysis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoverageConfig {
+    pub cell_size_m: f64,
+    /// Consecutive pings further apart in time are not joined into a swath
+    pub max_gap_s: f64,
+    /// Beam angle for down-looking channels without one in their channel info
+    pub default_beam_angle_deg: f64,
+}
+
+impl Default for CoverageConfig {
+    fn default() -> Self {
+        Self {
+            cell_size_m: 5.0,
+            max_gap_s: 5.0,
+            default_beam_angle_deg: 20.0,
+        }
+    }
+}
+
+/// Covered cells (value 255) of the survey area; the mask marks cells inside the area
+#[derive(Debug, Clone)]
+pub struct CoverageMap {
+    pub raster: GeoRaster,
+    pub cell_size_m: f64,
+    pub area_cells: usize,
+    pub covered_cells: usize,
+}
+
+impl CoverageMap {
+    pub fn percent_covered(&self) -> f64 {
+        if self.area_cells == 0 {
+            0.0
+        } else {
+            100.0 * self.covered_cells as f64 / self.area_cells as f64
+        }
+    }
+
+    /// Unsurveyed cells inside the area as a raster (value 255 where a gap remains)
+    pub fn gaps(&self) -> GeoRaster {
+        let r = &self.raster;
+        let mut gaps = GeoRaster::new(r.min_lat, r.min_lon, r.max_lat, r.max_lon, r.width, r.height);
+        for (i, (&inside, &value)) in r.mask.iter().zip(&r.values).enumerate() {
+            if inside && value == 0 {
+                gaps.set(i / r.width, i % r.width, 255);
+            }
+        }
+        gaps
+    }
+
+    /// Gap area in square meters
+    pub fn gap_area_m2(&self) -> f64 {
+        (self.area_cells - self.covered_cells) as f64 * self.cell_size_m.powi(2)
+    }
+
+    /// Coverage map as a PNG: covered cells green, gaps red, outside the area black
+    pub fn write_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let rgb: Vec<u8> = self
+            .raster
+            .mask
+            .iter()
+            .zip(&self.raster.values)
+            .flat_map(|(&inside, &value)| match (inside, value) {
+                (false, _) => OUTSIDE,
+                (true, 0) => GAP,
+                _ => COVERED,
+            })
+            .collect();
+        let mut out = BufWriter::new(File::create(path)?);
+        write_png(&mut out, self.raster.width, self.raster.height, PngColor::Rgb, &rgb)?;
+        out.flush()
+    }
+}
+
+/// Across-track extent `(port_m, starboard_m)` insonified by one ping
+///
+/// Side-scan channels cover their side out to the ping range; other channels
+/// cover the beam footprint at the recorded depth.
+pub fn swath_extent_m(ping: &Ping, kind: ChannelKind, beam_angle_deg: f64) -> (f64, f64) {
+    match kind {
+        ChannelKind::SideScanPort => (ping.range_m, 0.0),
+        ChannelKind::SideScanStarboard => (0.0, ping.range_m),
+        _ => {
+            let half = footprint_diameter_m(ping.depth_m, beam_angle_deg) / 2.0;
+            (half, half)
+        }
+    }
+}
+
+/// Rasterize swath footprints and measure coverage of an area
+///
+/// The area is `polygon` (`(lat, lon)` vertices) when given, otherwise the
+/// bounding box of the swaths. Returns None when no ping has a swath.
+pub fn coverage_map(
+    pings: &[Ping],
+    channels: &[ChannelInfo],
+    polygon: Option<&[(f64, f64)]>,
+    config: &CoverageConfig,
+) -> Option<CoverageMap> {
+    let swaths = swath_quads(pings, channels, config);
+
+    let corners: Vec<(f64, f64)> = match polygon {
+        Some(poly) if !poly.is_empty() => poly.to_vec(),
+        _ => swaths.iter().flatten().copied().collect(),
+    };
+    let first = corners.first()?;
+    let (min_lat, min_lon, max_lat, max_lon) = corners.iter().fold(
+        (first.0, first.1, first.0, first.1),
+        |(a, b, c, d), &(lat, lon)| (a.min(lat), b.min(lon), c.max(lat), d.max(lon)),
+    );
+    let cell = config.cell_size_m;
+    let (east, north) = local_offset_m(min_lat, min_lon, max_lat, max_lon);
+    let cols = ((east / cell).ceil() as usize).max(1);
+    let rows = ((north / cell).ceil() as usize).max(1);
+    let (top_lat, right_lon) = offset_position(min_lat, min_lon, cols as f64 * cell, rows as f64 * cell);
+    let mut raster = GeoRaster::new(min_lat, min_lon, top_lat, right_lon, cols, rows);
+
+    let north_m = |row: usize| ((rows - row) as f64 - 0.5) * cell;
+    let lon_scale = local_offset_m(min_lat, min_lon, min_lat, min_lon + 1.0).0;
+    let to_local = |&(lat, lon): &(f64, f64)| ((lat - min_lat) * METERS_PER_DEGREE_LAT, (lon - min_lon) * lon_scale);
+
+    let mut area_cells = 0;
+    for row in 0..rows {
+        for col in 0..cols {
+            let (lat, lon) = offset_position(min_lat, min_lon, (col as f64 + 0.5) * cell, north_m(row));
+            if polygon.is_none_or(|p| p.is_empty() || point_in_polygon(lat, lon, p)) {
+                raster.set(row, col, 0);
+                area_cells += 1;
+            }
+        }
+    }
+
+    let mut covered_cells = 0;
+    for quad in &swaths {
+        let local: Vec<(f64, f64)> = quad.iter().map(to_local).collect();
+        let (lo_n, hi_n) = local.iter().fold((f64::MAX, f64::MIN), |(a, b), p| (a.min(p.0), b.max(p.0)));
+        let (lo_e, hi_e) = local.iter().fold((f64::MAX, f64::MIN), |(a, b), p| (a.min(p.1), b.max(p.1)));
+        let col_range = (lo_e / cell).floor().max(0.0) as usize..((hi_e / cell).ceil().max(0.0) as usize).min(cols);
+        let row_range = rows.saturating_sub((hi_n / cell).ceil().max(0.0) as usize)
+            ..rows.saturating_sub((lo_n / cell).floor().max(0.0) as usize);
+        for row in row_range {
+            for col in col_range.clone() {
+                let idx = row * cols + col;
+                let center = (north_m(row), (col as f64 + 0.5) * cell);
+                if raster.mask[idx] && raster.values[idx] == 0 && point_in_polygon(center.0, center.1, &local) {
+                    raster.values[idx] = 255;
+                    covered_cells += 1;
+                }
+            }
+        }
+    }
+
+    Some(CoverageMap {
+        raster,
+        cell_size_m: cell,
+        area_cells,
+        covered_cells,
+    })
+}
+
+/// Swath quadrilaterals `(lat, lon)` between consecutive pings of each channel
+fn swath_quads(pings: &[Ping], channels: &[ChannelInfo], config: &CoverageConfig) -> Vec<[(f64, f64); 4]> {
+    let mut by_channel: BTreeMap<u16, Vec<&Ping>> = BTreeMap::new();
+    for p in pings {
+        by_channel.entry(p.channel_id).or_default().push(p);
+    }
+
+    let mut quads = Vec::new();
+    for (channel_id, mut selected) in by_channel {
+        let info = channels.iter().find(|c| c.channel_id == channel_id);
+        let kind = info.map_or(ChannelKind::Traditional, |c| c.kind);
+        let beam = info.and_then(|c| c.beam_angle_deg).unwrap_or(config.default_beam_angle_deg);
+        selected.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+
+        let edges = |p: &Ping| {
+            let (port, starboard) = swath_extent_m(p, kind, beam);
+            let heading = p.cog_deg.unwrap_or(p.heading_deg);
+            let (pe, pn) = vessel_to_local(-port, 0.0, heading);
+            let (se, sn) = vessel_to_local(starboard, 0.0, heading);
+            (offset_position(p.lat, p.lon, pe, pn), offset_position(p.lat, p.lon, se, sn))
+        };
+        for pair in selected.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if b.timestamp - a.timestamp > config.max_gap_s {
+                continue;
+            }
+            let (a_port, a_stbd) = edges(a);
+            let (b_port, b_stbd) = edges(b);
+            if a_port != a_stbd || b_port != b_stbd {
+                quads.push([a_port, a_stbd, b_stbd, b_port]);
+            }
+        }
+    }
+    quads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: (f64, f64) = (44.0, -63.0);
+
+    fn local(east: f64, north: f64) -> (f64, f64) {
+        offset_position(ORIGIN.0, ORIGIN.1, east, north)
+    }
+
+    fn starboard_channel() -> ChannelInfo {
+        ChannelInfo {
+            channel_id: 2,
+            name: "starboard".to_string(),
+            kind: ChannelKind::SideScanStarboard,
+            frequency_khz: None,
+            beam_angle_deg: None,
+        }
+    }
+
+    /// Heading north up the west edge of the square, scanning 50 m to starboard
+    fn west_edge_pass() -> Vec<Ping> {
+        (0..=100)
+            .map(|i| {
+                let (lat, lon) = local(0.0, i as f64);
+                Ping {
+                    timestamp: i as f64,
+                    channel_id: 2,
+                    lat,
+                    lon,
+                    range_m: 50.0,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_pass_covers_half_of_a_square() {
+        let square = [local(0.0, 0.0), local(100.0, 0.0), local(100.0, 100.0), local(0.0, 100.0)];
+        let map = coverage_map(&west_edge_pass(), &[starboard_channel()], Some(&square), &CoverageConfig::default()).unwrap();
+        assert_eq!(map.area_cells, 400);
+        assert!((map.percent_covered() - 50.0).abs() <= 2.5, "{}", map.percent_covered());
+        assert!((map.gap_area_m2() - 5000.0).abs() <= 250.0);
+
+        // Gaps lie on the unscanned east half only
+        let gaps = map.gaps();
+        let gap_cols: Vec<usize> = (0..gaps.values.len()).filter(|&i| gaps.values[i] == 255).map(|i| i % gaps.width).collect();
+        assert_eq!(gap_cols.len(), map.area_cells - map.covered_cells);
+        assert!(gap_cols.iter().all(|&col| col >= 10));
+    }
+
+    #[test]
+    fn pauses_break_the_swath() {
+        // Ten seconds without pings leaves a 10 m strip unjoined
+        let mut pings = west_edge_pass();
+        pings.drain(41..50);
+        let config = CoverageConfig::default();
+        let whole = coverage_map(&west_edge_pass(), &[starboard_channel()], None, &config).unwrap();
+        let split = coverage_map(&pings, &[starboard_channel()], None, &config).unwrap();
+        assert_eq!(whole.area_cells, split.area_cells);
+        assert!(split.covered_cells < whole.covered_cells);
+        assert!(coverage_map(&pings[..1], &[starboard_channel()], None, &config).is_none());
+    }
+
+    #[test]
+    fn down_looking_swath_is_the_beam_footprint() {
+        let ping = Ping {
+            depth_m: 10.0,
+            range_m: 40.0,
+            ..Default::default()
+        };
+        let (port, starboard) = swath_extent_m(&ping, ChannelKind::Traditional, 20.0);
+        assert!((port - 10.0 * 10f64.to_radians().tan()).abs() < 1e-12);
+        assert_eq!(port, starboard);
+        assert_eq!(swath_extent_m(&ping, ChannelKind::SideScanPort, 20.0), (40.0, 0.0));
+    }
+}
@@ -0,0 +1,271 @@
+// Coordinate reference systems for projected exports
+// src/crs.rs
+
+use std::f64::consts::{FRAC_PI_4, PI};
+use std::io;
+
+/// WGS84 semi-major axis in meters
+const WGS84_A: f64 = 6378137.0;
+/// WGS84 flattening
+const WGS84_F: f64 = 1.0 / 298.257223563;
+const UTM_SCALE: f64 = 0.9996;
+const UTM_FALSE_EASTING: f64 = 500000.0;
+const UTM_FALSE_NORTHING_SOUTH: f64 = 10000000.0;
+/// Latitude limit of the square Web Mercator world
+const WEB_MERCATOR_MAX_LAT: f64 = 85.051_128_779_806_59;
+
+const WGS84_GEOGCS: &str = "GEOGCS[\"GCS_WGS_1984\",DATUM[\"D_WGS_1984\",SPHEROID[\"WGS_1984\",6378137.0,298.257223563]],PRIMEM[\"Greenwich\",0.0],UNIT[\"Degree\",0.0174532925199433]]";
+
+/// Output coordinate system; positions are always WGS84 internally
+///
+/// Projections are computed in pure Rust. Supported are geographic WGS84
+/// (EPSG:4326), Web Mercator (EPSG:3857) and WGS84 UTM zones
+/// (EPSG:32601–32660 north, 32701–32760 south). State plane, NAD83 UTM
+/// (269xx) and other national grids are not; deliver in the WGS84 UTM zone
+/// from [`Crs::utm_for`] and reproject downstream if one is required.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Crs {
+    #[default]
+    Wgs84,
+    WebMercator,
+    Utm {
+        zone: u8,
+        north: bool,
+    },
+}
+
+impl Crs {
+    pub fn from_epsg(code: u32) -> io::Result<Self> {
+        match code {
+            4326 => Ok(Self::Wgs84),
+            3857 | 900913 => Ok(Self::WebMercator),
+            32601..=32660 => Ok(Self::Utm {
+                zone: (code - 32600) as u8,
+                north: true,
+            }),
+            32701..=32760 => Ok(Self::Utm {
+                zone: (code - 32700) as u8,
+                north: false,
+            }),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "unsupported CRS EPSG:{} (supported: 4326, 3857, WGS84 UTM 326xx/327xx; state plane is not)",
+                    code
+                ),
+            )),
+        }
+    }
+
+    /// Parse `"EPSG:32617"` or a bare code such as `"3857"`
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let text = text.trim();
+        let code = text
+            .get(..5)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("epsg:"))
+            .map_or(text, |_| &text[5..]);
+        let code = code
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid CRS '{}'", text)))?;
+        Self::from_epsg(code)
+    }
+
+    /// UTM zone containing a position (without the Norway/Svalbard exceptions)
+    pub fn utm_for(lat: f64, lon: f64) -> Self {
+        let zone = (((lon + 180.0) / 6.0).floor().rem_euclid(60.0) as u8) + 1;
+        Self::Utm {
+            zone,
+            north: lat >= 0.0,
+        }
+    }
+
+    pub fn epsg(&self) -> u32 {
+        match *self {
+            Self::Wgs84 => 4326,
+            Self::WebMercator => 3857,
+            Self::Utm { zone, north: true } => 32600 + zone as u32,
+            Self::Utm { zone, north: false } => 32700 + zone as u32,
+        }
+    }
+
+    /// Whether coordinates are degrees rather than meters
+    pub fn is_geographic(&self) -> bool {
+        *self == Self::Wgs84
+    }
+
+    /// Decimal places that keep about a centimeter of precision
+    pub fn decimals(&self) -> usize {
+        if self.is_geographic() {
+            7
+        } else {
+            2
+        }
+    }
+
+    /// `(x, y)`: lon/lat for geographic, easting/northing in meters otherwise
+    pub fn project(&self, lat: f64, lon: f64) -> (f64, f64) {
+        match *self {
+            Self::Wgs84 => (lon, lat),
+            Self::WebMercator => {
+                let lat = lat.clamp(-WEB_MERCATOR_MAX_LAT, WEB_MERCATOR_MAX_LAT).to_radians();
+                (WGS84_A * lon.to_radians(), WGS84_A * (FRAC_PI_4 + lat / 2.0).tan().ln())
+            }
+            Self::Utm { zone, north } => utm_forward(lat, lon, zone, north),
+        }
+    }
+
+    /// Inverse of [`Crs::project`], returning `(lat, lon)`
+    pub fn unproject(&self, x: f64, y: f64) -> (f64, f64) {
+        match *self {
+            Self::Wgs84 => (y, x),
+            Self::WebMercator => {
+                let lat = 2.0 * (y / WGS84_A).exp().atan() - PI / 2.0;
+                (lat.to_degrees(), (x / WGS84_A).to_degrees())
+            }
+            Self::Utm { zone, north } => utm_inverse(x, y, zone, north),
+        }
+    }
+
+    /// ESRI-flavoured WKT, as written to shapefile `.prj` files
+    pub fn wkt(&self) -> String {
+        match *self {
+            Self::Wgs84 => WGS84_GEOGCS.to_string(),
+            Self::WebMercator => format!(
+                "PROJCS[\"WGS_1984_Web_Mercator_Auxiliary_Sphere\",{},PROJECTION[\"Mercator_Auxiliary_Sphere\"],PARAMETER[\"False_Easting\",0.0],PARAMETER[\"False_Northing\",0.0],PARAMETER[\"Central_Meridian\",0.0],PARAMETER[\"Standard_Parallel_1\",0.0],PARAMETER[\"Auxiliary_Sphere_Type\",0.0],UNIT[\"Meter\",1.0]]",
+                WGS84_GEOGCS
+            ),
+            Self::Utm { zone, north } => format!(
+                "PROJCS[\"WGS_1984_UTM_Zone_{}{}\",{},PROJECTION[\"Transverse_Mercator\"],PARAMETER[\"False_Easting\",{:.1}],PARAMETER[\"False_Northing\",{:.1}],PARAMETER[\"Central_Meridian\",{:.1}],PARAMETER[\"Scale_Factor\",{}],PARAMETER[\"Latitude_Of_Origin\",0.0],UNIT[\"Meter\",1.0]]",
+                zone,
+                if north { 'N' } else { 'S' },
+                WGS84_GEOGCS,
+                UTM_FALSE_EASTING,
+                if north { 0.0 } else { UTM_FALSE_NORTHING_SOUTH },
+                central_meridian(zone),
+                UTM_SCALE
+            ),
+        }
+    }
+
+    /// OGC URN used by the legacy GeoJSON `crs` member
+    pub fn urn(&self) -> String {
+        format!("urn:ogc:def:crs:EPSG::{}", self.epsg())
+    }
+}
+
+fn central_meridian(zone: u8) -> f64 {
+    zone as f64 * 6.0 - 183.0
+}
+
+/// Transverse Mercator series (Snyder, USGS PP 1395, eqs. 8-9 to 8-15)
+fn utm_forward(lat: f64, lon: f64, zone: u8, north: bool) -> (f64, f64) {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let ep2 = e2 / (1.0 - e2);
+    let phi = lat.to_radians();
+    let (sin, cos, tan) = (phi.sin(), phi.cos(), phi.tan());
+
+    let n = WGS84_A / (1.0 - e2 * sin * sin).sqrt();
+    let t = tan * tan;
+    let c = ep2 * cos * cos;
+    let a = cos * (lon - central_meridian(zone)).to_radians();
+    let m = meridian_arc(phi, e2);
+
+    let x = UTM_SCALE
+        * n
+        * (a + (1.0 - t + c) * a.powi(3) / 6.0 + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0);
+    let y = UTM_SCALE
+        * (m + n
+            * tan
+            * (a * a / 2.0
+                + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+                + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0));
+    let false_northing = if north { 0.0 } else { UTM_FALSE_NORTHING_SOUTH };
+    (x + UTM_FALSE_EASTING, y + false_northing)
+}
+
+/// Inverse series (Snyder eqs. 8-17 to 8-25) via the footpoint latitude
+fn utm_inverse(x: f64, y: f64, zone: u8, north: bool) -> (f64, f64) {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let ep2 = e2 / (1.0 - e2);
+    let false_northing = if north { 0.0 } else { UTM_FALSE_NORTHING_SOUTH };
+
+    let m = (y - false_northing) / UTM_SCALE;
+    let mu = m / (WGS84_A * (1.0 - e2 / 4.0 - 3.0 * e2 * e2 / 64.0 - 5.0 * e2.powi(3) / 256.0));
+    let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+    let phi1 = mu
+        + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+        + (21.0 * e1 * e1 / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+        + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+        + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+
+    let (sin, cos, tan) = (phi1.sin(), phi1.cos(), phi1.tan());
+    let c1 = ep2 * cos * cos;
+    let t1 = tan * tan;
+    let n1 = WGS84_A / (1.0 - e2 * sin * sin).sqrt();
+    let r1 = WGS84_A * (1.0 - e2) / (1.0 - e2 * sin * sin).powf(1.5);
+    let d = (x - UTM_FALSE_EASTING) / (n1 * UTM_SCALE);
+
+    let lat = phi1
+        - (n1 * tan / r1)
+            * (d * d / 2.0 - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1 * c1 - 9.0 * ep2) * d.powi(4) / 24.0
+                + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1 * t1 - 252.0 * ep2 - 3.0 * c1 * c1) * d.powi(6) / 720.0);
+    let lon = (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+        + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1 * c1 + 8.0 * ep2 + 24.0 * t1 * t1) * d.powi(5) / 120.0)
+        / cos;
+    (lat.to_degrees(), central_meridian(zone) + lon.to_degrees())
+}
+
+/// Distance along the meridian from the equator to latitude `phi`
+fn meridian_arc(phi: f64, e2: f64) -> f64 {
+    let (e4, e6) = (e2 * e2, e2.powi(3));
+    WGS84_A
+        * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+            - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+            + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+            - (35.0 * e6 / 3072.0) * (6.0 * phi).sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_supported_codes_only() {
+        assert_eq!(Crs::parse("EPSG:4326").unwrap(), Crs::Wgs84);
+        assert_eq!(Crs::parse(" epsg:900913").unwrap(), Crs::WebMercator);
+        assert_eq!(Crs::parse("32617").unwrap(), Crs::Utm { zone: 17, north: true });
+        assert_eq!(Crs::parse("EPSG:32760").unwrap(), Crs::Utm { zone: 60, north: false });
+        for code in ["EPSG:2263", "26917", "32661", "EPSG:", "utm17"] {
+            let err = Crs::parse(code).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", code);
+        }
+        assert!(Crs::from_epsg(2263)
+            .unwrap_err()
+            .to_string()
+            .contains("state plane is not"));
+        for crs in [Crs::Wgs84, Crs::WebMercator, Crs::Utm { zone: 33, north: false }] {
+            assert_eq!(Crs::from_epsg(crs.epsg()).unwrap(), crs);
+        }
+    }
+
+    #[test]
+    fn projections_round_trip() {
+        let (x, y) = Crs::Utm { zone: 31, north: true }.project(0.0, 3.0);
+        assert!((x - UTM_FALSE_EASTING).abs() < 1e-6 && y.abs() < 1e-6);
+        let (_, y) = Crs::WebMercator.project(0.0, 0.0);
+        assert!(y.abs() < 1e-9);
+
+        for (lat, lon) in [(45.5, -83.2), (-33.9, 151.2), (0.1, 0.1), (71.0, 25.7)] {
+            for crs in [Crs::Wgs84, Crs::WebMercator, Crs::utm_for(lat, lon)] {
+                let (x, y) = crs.project(lat, lon);
+                let (back_lat, back_lon) = crs.unproject(x, y);
+                assert!(
+                    (back_lat - lat).abs() < 1e-7 && (back_lon - lon).abs() < 1e-7,
+                    "{:?}",
+                    crs
+                );
+            }
+        }
+        assert!(Crs::utm_for(45.5, -83.2).wkt().contains("WGS_1984_UTM_Zone_17N"));
+    }
+}
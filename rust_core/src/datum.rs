@@ -0,0 +1,184 @@
+// Vertical reference model: transducer, waterline and chart datum depths
+// src/datum.rs
+
+use crate::parsers::parse_timestamp;
+use crate::survey::{Sounding, VerticalReference};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Water level above chart datum over time, e.g. from a tide gauge or prediction
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TideTable {
+    /// (timestamp, level_m) sorted by time
+    readings: Vec<(f64, f64)>,
+    /// Longest gap between readings that is still interpolated across
+    pub max_gap_s: f64,
+}
+
+impl TideTable {
+    pub fn new(mut readings: Vec<(f64, f64)>) -> Self {
+        readings.retain(|(t, level)| t.is_finite() && level.is_finite());
+        readings.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            readings,
+            max_gap_s: 3.0 * 3600.0,
+        }
+    }
+
+    /// Load `time level_m` lines separated by whitespace or commas
+    ///
+    /// Times are epoch seconds or ISO 8601 (UTC). Blank lines, `#` comments
+    /// and a header line that does not parse are skipped.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut readings = Vec::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split([',', '\t', ';']).map(str::trim).collect();
+            let (time, level) = match fields[..] {
+                [time, level, ..] => (time, level),
+                _ => match line.rsplit_once(char::is_whitespace) {
+                    Some((time, level)) => (time.trim(), level),
+                    None => (line, ""),
+                },
+            };
+            match (parse_timestamp(time), level.parse::<f64>()) {
+                (Some(t), Ok(level)) => readings.push((t, level)),
+                _ if line_no == 0 => {}
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("bad tide reading on line {}", line_no + 1),
+                    ))
+                }
+            }
+        }
+        if readings.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "tide file has no readings"));
+        }
+        Ok(Self::new(readings))
+    }
+
+    pub fn len(&self) -> usize {
+        self.readings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+
+    /// Interpolated level at `t`; None outside the table or across a long gap
+    pub fn level_at(&self, t: f64) -> Option<f64> {
+        let idx = self.readings.partition_point(|r| r.0 <= t);
+        let a = *self.readings.get(idx.checked_sub(1)?)?;
+        if a.0 == t {
+            return Some(a.1);
+        }
+        let b = *self.readings.get(idx)?;
+        (b.0 - a.0 <= self.max_gap_s).then(|| a.1 + (b.1 - a.1) * (t - a.0) / (b.0 - a.0))
+    }
+}
+
+/// Offsets between the vertical references of one survey
+///
+/// Depth below the waterline is transducer depth plus `draft_m`; depth
+/// below chart datum is waterline depth minus the tide level at that time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerticalModel {
+    pub draft_m: f64,
+    pub tide: Option<TideTable>,
+}
+
+impl VerticalModel {
+    /// Amount added to a transducer depth to express it in `reference`
+    fn offset(&self, reference: VerticalReference, timestamp: f64) -> Option<f64> {
+        match reference {
+            VerticalReference::Transducer => Some(0.0),
+            VerticalReference::Waterline => Some(self.draft_m),
+            VerticalReference::ChartDatum => Some(self.draft_m - self.tide.as_ref()?.level_at(timestamp)?),
+        }
+    }
+
+    /// Re-express a sounding's depth in `target`
+    ///
+    /// None when chart datum is involved and no tide is known at the
+    /// sounding's time.
+    pub fn convert(&self, sounding: &Sounding, target: VerticalReference) -> Option<Sounding> {
+        if sounding.reference == target {
+            return Some(*sounding);
+        }
+        let from = self.offset(sounding.reference, sounding.timestamp)?;
+        let to = self.offset(target, sounding.timestamp)?;
+        Some(Sounding {
+            depth_m: sounding.depth_m - from + to,
+            reference: target,
+            ..*sounding
+        })
+    }
+
+    /// Convert every sounding, dropping those that cannot be reduced
+    ///
+    /// Returns the number of soundings dropped.
+    pub fn reduce_all(&self, soundings: &mut Vec<Sounding>, target: VerticalReference) -> usize {
+        let before = soundings.len();
+        *soundings = soundings.iter().filter_map(|s| self.convert(s, target)).collect();
+        before - soundings.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch;
+
+    fn model() -> VerticalModel {
+        VerticalModel {
+            draft_m: 0.5,
+            tide: Some(TideTable::new(vec![(3600.0, 2.0), (0.0, 1.0), (20_000.0, 0.0)])),
+        }
+    }
+
+    fn raw(timestamp: f64, depth_m: f64) -> Sounding {
+        Sounding::new(timestamp, 44.0, -63.0, depth_m)
+    }
+
+    #[test]
+    fn tide_is_subtracted_to_reach_chart_datum() {
+        let model = model();
+        // Half way through the rising hour the water is 1.5 m above datum
+        let reduced = model.convert(&raw(1800.0, 10.0), VerticalReference::ChartDatum).unwrap();
+        assert_eq!((reduced.depth_m, reduced.reference), (9.0, VerticalReference::ChartDatum));
+        let waterline = model.convert(&raw(1800.0, 10.0), VerticalReference::Waterline).unwrap();
+        assert_eq!(waterline.depth_m, 10.5);
+        let back = model.convert(&reduced, VerticalReference::Transducer).unwrap();
+        assert!((back.depth_m - 10.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn soundings_without_a_tide_are_dropped() {
+        let model = model();
+        assert_eq!(model.tide.as_ref().unwrap().level_at(-1.0), None);
+        // The last two readings are further apart than the interpolation limit
+        assert_eq!(model.tide.as_ref().unwrap().level_at(10_000.0), None);
+        let mut soundings = vec![raw(0.0, 5.0), raw(10_000.0, 5.0), raw(3600.0, 5.0)];
+        assert_eq!(model.reduce_all(&mut soundings, VerticalReference::ChartDatum), 1);
+        let depths: Vec<f64> = soundings.iter().map(|s| s.depth_m).collect();
+        assert_eq!(depths, vec![4.5, 3.5]);
+    }
+
+    #[test]
+    fn loads_epoch_and_iso_readings() {
+        let path = scratch("datum", "tide.csv");
+        fs::write(&path, "time,level\n# gauge 1\n1700000000,1.25\n2023-11-14T23:13:20Z\t1.5\n").unwrap();
+        let table = TideTable::load(&path).unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.level_at(1_700_000_000.0), Some(1.25));
+        fs::write(&path, "1700000000,1.25\nlater,2\n").unwrap();
+        assert_eq!(TideTable::load(&path).unwrap_err().to_string(), "bad tide reading on line 2");
+        fs::remove_file(path).unwrap();
+    }
+}
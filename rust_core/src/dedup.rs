@@ -0,0 +1,178 @@
+// Reconciliation of overlapping passes into one depth per cell
+// src/dedup.rs
+
+use crate::geo::{local_offset_m, offset_position};
+use crate::survey::{Sounding, VerticalReference};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DedupConfig {
+    pub cell_size_m: f64,
+    /// Time gap that separates one pass over a cell from the next
+    pub pass_gap_s: f64,
+    /// Standard deviation above which a cell is flagged as conflicting
+    pub conflict_std_m: f64,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            cell_size_m: 2.0,
+            pass_gap_s: 60.0,
+            conflict_std_m: 0.5,
+        }
+    }
+}
+
+/// One reconciled cell: median depth plus spread statistics
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconciledSounding {
+    pub lat: f64,
+    pub lon: f64,
+    pub timestamp: f64,
+    pub depth_m: f64,
+    pub variance: f64,
+    pub count: usize,
+    pub passes: usize,
+    pub conflict: bool,
+    /// Vertical reference of the cell's soundings
+    pub reference: VerticalReference,
+}
+
+impl ReconciledSounding {
+    pub fn to_sounding(&self) -> Sounding {
+        Sounding {
+            reference: self.reference,
+            ..Sounding::new(self.timestamp, self.lat, self.lon, self.depth_m)
+        }
+    }
+}
+
+/// Group sounding indices into square cells anchored at the data's south-west corner
+pub fn bin_soundings(soundings: &[Sounding], cell_size_m: f64) -> BTreeMap<(i64, i64), Vec<usize>> {
+    match south_west(soundings) {
+        Some(anchor) => bin_soundings_at(soundings, anchor, cell_size_m),
+        None => BTreeMap::new(),
+    }
+}
+
+/// Group sounding indices into (row, col) cells anchored at a fixed (lat, lon)
+pub fn bin_soundings_at(
+    soundings: &[Sounding],
+    (min_lat, min_lon): (f64, f64),
+    cell_size_m: f64,
+) -> BTreeMap<(i64, i64), Vec<usize>> {
+    let mut cells: BTreeMap<(i64, i64), Vec<usize>> = BTreeMap::new();
+    for (i, s) in soundings.iter().enumerate() {
+        let (east, north) = local_offset_m(min_lat, min_lon, s.lat, s.lon);
+        let key = ((north / cell_size_m).floor() as i64, (east / cell_size_m).floor() as i64);
+        cells.entry(key).or_default().push(i);
+    }
+    cells
+}
+
+pub(crate) fn south_west(soundings: &[Sounding]) -> Option<(f64, f64)> {
+    let first = soundings.first()?;
+    let min_lat = soundings.iter().map(|s| s.lat).fold(first.lat, f64::min);
+    let min_lon = soundings.iter().map(|s| s.lon).fold(first.lon, f64::min);
+    Some((min_lat, min_lon))
+}
+
+/// Median of a non-empty slice (sorted in place)
+pub(crate) fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Collapse repeated passes into one sounding per cell, positioned at the cell center
+pub fn reconcile(soundings: &[Sounding], config: &DedupConfig) -> Vec<ReconciledSounding> {
+    let Some((min_lat, min_lon)) = south_west(soundings) else {
+        return Vec::new();
+    };
+
+    bin_soundings(soundings, config.cell_size_m)
+        .into_iter()
+        .map(|((row, col), indices)| {
+            let mut depths: Vec<f64> = indices.iter().map(|&i| soundings[i].depth_m).collect();
+            let mut times: Vec<f64> = indices.iter().map(|&i| soundings[i].timestamp).collect();
+            times.sort_by(|a, b| a.total_cmp(b));
+
+            let passes = 1 + times.windows(2).filter(|w| w[1] - w[0] > config.pass_gap_s).count();
+            let count = depths.len() as f64;
+            let mean = depths.iter().sum::<f64>() / count;
+            let variance = depths.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / count;
+            let depth_m = median(&mut depths);
+
+            let (lat, lon) = offset_position(
+                min_lat,
+                min_lon,
+                (col as f64 + 0.5) * config.cell_size_m,
+                (row as f64 + 0.5) * config.cell_size_m,
+            );
+
+            ReconciledSounding {
+                lat,
+                lon,
+                timestamp: times[0],
+                depth_m,
+                variance,
+                count: indices.len(),
+                passes,
+                conflict: passes > 1 && variance.sqrt() > config.conflict_std_m,
+                reference: soundings[indices[0]].reference,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: (f64, f64) = (44.0, -63.0);
+
+    fn at(timestamp: f64, east: f64, north: f64, depth_m: f64) -> Sounding {
+        let (lat, lon) = offset_position(ORIGIN.0, ORIGIN.1, east, north);
+        Sounding {
+            reference: VerticalReference::Waterline,
+            ..Sounding::new(timestamp, lat, lon, depth_m)
+        }
+    }
+
+    #[test]
+    fn repeated_passes_collapse_to_a_median() {
+        let soundings = [
+            at(0.0, 0.0, 0.0, 5.0),
+            at(1.0, 0.5, 0.5, 5.2),
+            at(2.0, 11.0, 0.5, 3.0),
+            // Second pass over the first cell, well after the pass gap
+            at(200.0, 1.0, 1.0, 6.5),
+        ];
+        let cells = reconcile(&soundings, &DedupConfig::default());
+        assert_eq!(cells.len(), 2);
+
+        let first = &cells[0];
+        assert_eq!((first.count, first.passes, first.depth_m, first.timestamp), (3, 2, 5.2, 0.0));
+        assert!((first.variance - 0.4422).abs() < 1e-4);
+        assert!(first.conflict);
+        let (east, north) = local_offset_m(ORIGIN.0, ORIGIN.1, first.lat, first.lon);
+        assert!((east - 1.0).abs() < 1e-6 && (north - 1.0).abs() < 1e-6);
+        assert_eq!(first.to_sounding().reference, VerticalReference::Waterline);
+
+        let second = &cells[1];
+        assert_eq!((second.count, second.passes, second.variance, second.conflict), (1, 1, 0.0, false));
+    }
+
+    #[test]
+    fn agreeing_passes_are_not_conflicts() {
+        let soundings = [at(0.0, 0.0, 0.0, 5.0), at(100.0, 0.5, 0.5, 5.4)];
+        let cells = reconcile(&soundings, &DedupConfig::default());
+        assert_eq!((cells[0].passes, cells[0].conflict), (2, false));
+        assert!(reconcile(&[], &DedupConfig::default()).is_empty());
+    }
+}
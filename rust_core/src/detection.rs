@@ -0,0 +1,248 @@
+// Suspended target (fish) detection in the water column
+// src/detection.rs
+
+use crate::survey::{Ping, Waypoint};
+
+/// Detection thresholds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectionConfig {
+    /// Minimum sample intensity counted as echo
+    pub threshold: u8,
+    /// Ignore returns this close to the transducer (ringdown, surface clutter)
+    pub surface_blank_m: f64,
+    /// Ignore returns this close above the bottom
+    pub bottom_margin_m: f64,
+    /// Smallest blob, in samples, reported as a target
+    pub min_samples: usize,
+}
+
+impl Default for DetectionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 140,
+            surface_blank_m: 1.0,
+            bottom_margin_m: 0.5,
+            min_samples: 6,
+        }
+    }
+}
+
+/// Echo cluster found between the surface and the bottom
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub timestamp: f64,
+    pub lat: f64,
+    pub lon: f64,
+    /// Intensity-weighted depth of the cluster
+    pub depth_m: f64,
+    /// Vertical extent of the cluster
+    pub height_m: f64,
+    /// Number of consecutive pings the cluster spans
+    pub ping_count: usize,
+    pub sample_count: usize,
+    pub peak_intensity: u8,
+    pub bottom_depth_m: f64,
+}
+
+impl Target {
+    pub fn to_waypoint(&self, name: &str) -> Waypoint {
+        Waypoint {
+            name: name.to_string(),
+            timestamp: self.timestamp,
+            lat: self.lat,
+            lon: self.lon,
+            depth_m: Some(self.depth_m),
+            comment: format!(
+                "target {:.1} m tall over {:.1} m bottom, {} pings",
+                self.height_m, self.bottom_depth_m, self.ping_count
+            ),
+        }
+    }
+}
+
+/// Find suspended echo clusters in a single channel's pings (recording order)
+///
+/// Samples above the threshold inside the water column window are grouped
+/// into 8-connected blobs across adjacent pings; each blob large enough
+/// becomes a target positioned at its strongest ping.
+pub fn detect_targets(pings: &[&Ping], config: &DetectionConfig) -> Vec<Target> {
+    let cols = pings.iter().map(|p| p.samples.len()).max().unwrap_or(0);
+    if cols == 0 {
+        return Vec::new();
+    }
+
+    // Echo mask over (ping, sample), limited to the water column window
+    let mut mask = vec![false; pings.len() * cols];
+    for (row, ping) in pings.iter().enumerate() {
+        let spacing = ping.sample_spacing_m();
+        if spacing <= 0.0 {
+            continue;
+        }
+        let start = (config.surface_blank_m / spacing).ceil() as usize;
+        let end = ((ping.depth_m - config.bottom_margin_m) / spacing).floor().max(0.0) as usize;
+        for i in start..end.min(ping.samples.len()) {
+            mask[row * cols + i] = ping.samples[i] >= config.threshold;
+        }
+    }
+
+    let mut visited = vec![false; mask.len()];
+    let mut targets = Vec::new();
+    let mut stack = Vec::new();
+
+    for seed in 0..mask.len() {
+        if !mask[seed] || visited[seed] {
+            continue;
+        }
+        visited[seed] = true;
+        stack.push(seed);
+        let mut blob = Vec::new();
+
+        while let Some(idx) = stack.pop() {
+            blob.push(idx);
+            let (row, col) = (idx / cols, idx % cols);
+            for dr in -1i64..=1 {
+                for dc in -1i64..=1 {
+                    let r = row as i64 + dr;
+                    let c = col as i64 + dc;
+                    if r < 0 || c < 0 || r >= pings.len() as i64 || c >= cols as i64 {
+                        continue;
+                    }
+                    let n = r as usize * cols + c as usize;
+                    if mask[n] && !visited[n] {
+                        visited[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+
+        if blob.len() >= config.min_samples {
+            targets.push(summarize_blob(pings, cols, &blob));
+        }
+    }
+
+    targets.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    targets
+}
+
+fn summarize_blob(pings: &[&Ping], cols: usize, blob: &[usize]) -> Target {
+    let mut weight_sum = 0.0;
+    let mut depth_sum = 0.0;
+    let (mut min_depth, mut max_depth) = (f64::INFINITY, f64::NEG_INFINITY);
+    let (mut first_row, mut last_row) = (usize::MAX, 0);
+    let (mut peak, mut peak_row) = (0u8, blob[0] / cols);
+
+    for &idx in blob {
+        let (row, col) = (idx / cols, idx % cols);
+        let ping = pings[row];
+        let value = ping.samples[col];
+        let depth = (col as f64 + 0.5) * ping.sample_spacing_m();
+
+        weight_sum += value as f64;
+        depth_sum += value as f64 * depth;
+        min_depth = min_depth.min(depth);
+        max_depth = max_depth.max(depth);
+        first_row = first_row.min(row);
+        last_row = last_row.max(row);
+        if value > peak {
+            peak = value;
+            peak_row = row;
+        }
+    }
+
+    let at = pings[peak_row];
+    Target {
+        timestamp: at.timestamp,
+        lat: at.lat,
+        lon: at.lon,
+        // A zero threshold admits blobs of zero samples, which carry no weight
+        depth_m: if weight_sum > 0.0 { depth_sum / weight_sum } else { (min_depth + max_depth) / 2.0 },
+        height_m: max_depth - min_depth + at.sample_spacing_m(),
+        ping_count: last_row - first_row + 1,
+        sample_count: blob.len(),
+        peak_intensity: peak,
+        bottom_depth_m: at.depth_m,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::gpx::write_waypoints_to;
+
+    /// 20 pings of 100 samples over 10 m, bottom at 8 m, with a 3x5 echo
+    /// centred at 4.05 m on pings 6..9
+    fn column() -> Vec<Ping> {
+        (0..20)
+            .map(|i| {
+                let mut samples = vec![20u8; 100];
+                if (6..9).contains(&i) {
+                    for (offset, s) in samples[38..43].iter_mut().enumerate() {
+                        *s = if i == 7 && offset == 2 { 250 } else { 200 };
+                    }
+                }
+                Ping {
+                    timestamp: i as f64,
+                    lat: 44.0 + i as f64 * 1e-5,
+                    lon: -63.0,
+                    depth_m: 8.0,
+                    range_m: 10.0,
+                    samples,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_a_suspended_blob() {
+        let pings = column();
+        let refs: Vec<&Ping> = pings.iter().collect();
+        let targets = detect_targets(&refs, &DetectionConfig::default());
+        assert_eq!(targets.len(), 1);
+        let t = &targets[0];
+        assert_eq!((t.timestamp, t.ping_count, t.sample_count, t.peak_intensity), (7.0, 3, 15, 250));
+        assert!((t.depth_m - 4.05).abs() < 0.01, "{}", t.depth_m);
+        assert!((t.height_m - 0.5).abs() < 1e-9);
+        assert_eq!(t.bottom_depth_m, 8.0);
+
+        let mut gpx = Vec::new();
+        write_waypoints_to(&mut gpx, &[t.to_waypoint("fish 1")]).unwrap();
+        let gpx = String::from_utf8(gpx).unwrap();
+        assert!(gpx.contains("<name>fish 1</name>") && gpx.contains("<ele>-4.05</ele>"));
+    }
+
+    #[test]
+    fn ignores_echoes_outside_the_water_column() {
+        let mut pings = column();
+        for p in &mut pings {
+            // Ringdown at the surface and a hard bottom return
+            p.samples[..8].fill(255);
+            p.samples[78..].fill(255);
+        }
+        let refs: Vec<&Ping> = pings.iter().collect();
+        assert_eq!(detect_targets(&refs, &DetectionConfig::default()).len(), 1);
+        let small = DetectionConfig {
+            min_samples: 16,
+            ..Default::default()
+        };
+        assert!(detect_targets(&refs, &small).is_empty());
+    }
+
+    #[test]
+    fn zero_threshold_over_silent_samples_gives_a_finite_depth() {
+        let mut pings = column();
+        for p in &mut pings {
+            p.samples.fill(0);
+        }
+        let refs: Vec<&Ping> = pings.iter().collect();
+        let config = DetectionConfig {
+            threshold: 0,
+            ..Default::default()
+        };
+        let targets = detect_targets(&refs, &config);
+        assert_eq!(targets.len(), 1);
+        assert!(targets[0].depth_m.is_finite());
+        assert_eq!(targets[0].ping_count, 20);
+    }
+}
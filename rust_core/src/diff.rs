@@ -0,0 +1,133 @@
+// Comparison of two recordings over the same area
+// src/diff.rs
+
+use crate::dedup::{bin_soundings_at, median, south_west};
+use crate::survey::{Ping, Sounding};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffConfig {
+    /// Cell size used to decide whether both recordings cover the same spot
+    pub cell_size_m: f64,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self { cell_size_m: 5.0 }
+    }
+}
+
+/// Coverage, depth and channel differences between recordings `a` and `b`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordingDiff {
+    pub cells_a_only: usize,
+    pub cells_b_only: usize,
+    pub cells_shared: usize,
+    /// Mean of (b - a) median depth over shared cells; positive means b is deeper
+    pub mean_depth_diff_m: f64,
+    pub rms_depth_diff_m: f64,
+    pub max_abs_depth_diff_m: f64,
+    pub channels_a_only: Vec<u16>,
+    pub channels_b_only: Vec<u16>,
+    pub channels_shared: Vec<u16>,
+    pub time_span_a: (f64, f64),
+    pub time_span_b: (f64, f64),
+}
+
+/// Align two recordings by position and report where and how they differ
+pub fn diff_recordings(a: &[Ping], b: &[Ping], config: &DiffConfig) -> RecordingDiff {
+    let soundings_a: Vec<Sounding> = a.iter().map(Ping::to_sounding).collect();
+    let soundings_b: Vec<Sounding> = b.iter().map(Ping::to_sounding).collect();
+
+    let mut diff = RecordingDiff {
+        time_span_a: time_span(&soundings_a),
+        time_span_b: time_span(&soundings_b),
+        ..Default::default()
+    };
+
+    let channels_a: BTreeSet<u16> = a.iter().map(|p| p.channel_id).collect();
+    let channels_b: BTreeSet<u16> = b.iter().map(|p| p.channel_id).collect();
+    diff.channels_a_only = channels_a.difference(&channels_b).copied().collect();
+    diff.channels_b_only = channels_b.difference(&channels_a).copied().collect();
+    diff.channels_shared = channels_a.intersection(&channels_b).copied().collect();
+
+    // Bin both recordings on a common anchor so cells line up
+    let combined: Vec<Sounding> = soundings_a.iter().chain(&soundings_b).copied().collect();
+    let Some(anchor) = south_west(&combined) else {
+        return diff;
+    };
+    let cells_a = bin_soundings_at(&soundings_a, anchor, config.cell_size_m);
+    let cells_b = bin_soundings_at(&soundings_b, anchor, config.cell_size_m);
+
+    let mut diffs = Vec::new();
+    for (key, indices_a) in &cells_a {
+        match cells_b.get(key) {
+            Some(indices_b) => {
+                let mut depths_a: Vec<f64> = indices_a.iter().map(|&i| soundings_a[i].depth_m).collect();
+                let mut depths_b: Vec<f64> = indices_b.iter().map(|&i| soundings_b[i].depth_m).collect();
+                diffs.push(median(&mut depths_b) - median(&mut depths_a));
+            }
+            None => diff.cells_a_only += 1,
+        }
+    }
+    diff.cells_shared = diffs.len();
+    diff.cells_b_only = cells_b.len() - diffs.len();
+
+    if !diffs.is_empty() {
+        let n = diffs.len() as f64;
+        diff.mean_depth_diff_m = diffs.iter().sum::<f64>() / n;
+        diff.rms_depth_diff_m = (diffs.iter().map(|d| d * d).sum::<f64>() / n).sqrt();
+        diff.max_abs_depth_diff_m = diffs.iter().fold(0.0, |m, d| m.max(d.abs()));
+    }
+    diff
+}
+
+fn time_span(soundings: &[Sounding]) -> (f64, f64) {
+    soundings.iter().fold((f64::NAN, f64::NAN), |(lo, hi), s| {
+        (lo.min(s.timestamp), hi.max(s.timestamp))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::offset_position;
+
+    /// One ping per 10 m cell along a line heading east
+    fn line(cells: std::ops::Range<usize>, depth_m: f64, channel_id: u16, start: f64) -> Vec<Ping> {
+        cells
+            .enumerate()
+            .map(|(i, cell)| {
+                let (lat, lon) = offset_position(44.0, -63.0, cell as f64 * 10.0 + 5.0, 5.0);
+                Ping {
+                    timestamp: start + i as f64,
+                    channel_id,
+                    lat,
+                    lon,
+                    depth_m: depth_m + cell as f64 * 0.1,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_coverage_depth_and_channel_differences() {
+        let mut a = line(0..6, 4.0, 0, 100.0);
+        // A second channel at the west edge anchors the cells, so the lines run through cell centres
+        let mut edge = line(0..1, 4.0, 1, 50.0);
+        (edge[0].lat, edge[0].lon) = offset_position(44.0, -63.0, 0.0, 5.0);
+        a.extend(edge);
+        let b = line(3..9, 4.5, 0, 500.0);
+        let config = DiffConfig { cell_size_m: 10.0 };
+
+        let diff = diff_recordings(&a, &b, &config);
+        assert_eq!((diff.cells_a_only, diff.cells_shared, diff.cells_b_only), (3, 3, 3));
+        assert!((diff.mean_depth_diff_m - 0.5).abs() < 1e-9);
+        assert!((diff.rms_depth_diff_m - 0.5).abs() < 1e-9);
+        assert!((diff.max_abs_depth_diff_m - 0.5).abs() < 1e-9);
+        assert_eq!((diff.channels_a_only.clone(), diff.channels_shared.clone()), (vec![1], vec![0]));
+        assert!(diff.channels_b_only.is_empty());
+        assert_eq!((diff.time_span_a, diff.time_span_b), ((50.0, 105.0), (500.0, 505.0)));
+    }
+}
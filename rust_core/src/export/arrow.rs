@@ -0,0 +1,455 @@
+// Arrow IPC streaming export of pings
+// src/export/arrow.rs
+//
+// Hand-written writer for the Arrow IPC streaming format (the `.arrows`
+// file pyarrow and Arrow.jl open with `ipc.open_stream`), so consumers get
+// columnar batches without the arrow crates in this build. A stream is a
+// schema message, one record batch message per `write_batch` call and an
+// end-of-stream marker; each message is a flatbuffer header followed by a
+// body of 8-byte aligned buffers. Only what pings need is implemented:
+// little-endian, uncompressed, no dictionaries.
+//
+// Columns: timestamp, channel_id (uint16), lat, lon, heading_deg, depth_m,
+// range_m, then nullable cog_deg, sog_knots, stw_knots, frequency_khz, then
+// flags (uint8) and samples (binary).
+
+use crate::survey::Ping;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Marks the start of every message since format version 0.15
+const CONTINUATION: [u8; 4] = [0xFF; 4];
+/// `MetadataVersion.V5`
+const METADATA_V5: i16 = 4;
+/// `MessageHeader` union tags
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+/// `Type` union tags
+const TYPE_INT: u8 = 2;
+const TYPE_FLOAT: u8 = 3;
+const TYPE_BINARY: u8 = 4;
+/// `Precision.DOUBLE`
+const PRECISION_DOUBLE: i16 = 2;
+
+#[derive(Debug, Clone, Copy)]
+enum Column {
+    Float(fn(&Ping) -> f64),
+    OptionalFloat(fn(&Ping) -> Option<f64>),
+    UInt16(fn(&Ping) -> u16),
+    UInt8(fn(&Ping) -> u8),
+    Samples,
+}
+
+const COLUMNS: [(&str, Column); 13] = [
+    ("timestamp", Column::Float(|p| p.timestamp)),
+    ("channel_id", Column::UInt16(|p| p.channel_id)),
+    ("lat", Column::Float(|p| p.lat)),
+    ("lon", Column::Float(|p| p.lon)),
+    ("heading_deg", Column::Float(|p| p.heading_deg)),
+    ("depth_m", Column::Float(|p| p.depth_m)),
+    ("range_m", Column::Float(|p| p.range_m)),
+    ("cog_deg", Column::OptionalFloat(|p| p.cog_deg)),
+    ("sog_knots", Column::OptionalFloat(|p| p.sog_knots)),
+    ("stw_knots", Column::OptionalFloat(|p| p.stw_knots)),
+    ("frequency_khz", Column::OptionalFloat(|p| p.acoustics.frequency_khz)),
+    ("flags", Column::UInt8(|p| p.flags)),
+    ("samples", Column::Samples),
+];
+
+/// Writes pings to any byte sink (file, socket, pipe) as an Arrow IPC stream
+///
+/// The schema goes out on creation; call [`finish`](Self::finish) to write
+/// the end-of-stream marker. Readers see each `write_batch` as one record batch.
+pub struct ArrowStreamWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> ArrowStreamWriter<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        let fields = COLUMNS.iter().map(|&(name, column)| field(name, column)).collect();
+        let schema = Fb::Table(vec![(1, Fb::Tables(fields))]);
+        write_message(&mut out, HEADER_SCHEMA, schema, &[])?;
+        Ok(Self { out })
+    }
+
+    /// Write one record batch; an empty slice writes nothing
+    pub fn write_batch(&mut self, pings: &[Ping]) -> io::Result<()> {
+        if pings.is_empty() {
+            return Ok(());
+        }
+        let mut body = Body::default();
+        for &(_, column) in &COLUMNS {
+            body.column(pings, column)?;
+        }
+        let batch = Fb::Table(vec![
+            (0, Fb::I64(pings.len() as i64)),
+            (1, Fb::Pairs(body.nodes)),
+            (2, Fb::Pairs(body.buffers)),
+        ]);
+        write_message(&mut self.out, HEADER_RECORD_BATCH, batch, &body.data)
+    }
+
+    /// Write the end-of-stream marker and hand back the sink
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.write_all(&CONTINUATION)?;
+        self.out.write_all(&0i32.to_le_bytes())?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Write pings to an Arrow IPC stream file in batches of `batch_size` rows
+pub fn write_arrow<P: AsRef<Path>>(path: P, pings: &[Ping], batch_size: usize) -> io::Result<()> {
+    let mut writer = ArrowStreamWriter::new(BufWriter::new(File::create(path)?))?;
+    for batch in pings.chunks(batch_size.max(1)) {
+        writer.write_batch(batch)?;
+    }
+    writer.finish().map(drop)
+}
+
+fn field(name: &str, column: Column) -> Fb {
+    let (type_tag, kind) = match column {
+        Column::Float(_) | Column::OptionalFloat(_) => (TYPE_FLOAT, Fb::Table(vec![(0, Fb::I16(PRECISION_DOUBLE))])),
+        Column::UInt16(_) => (TYPE_INT, Fb::Table(vec![(0, Fb::I32(16)), (1, Fb::Bool(false))])),
+        Column::UInt8(_) => (TYPE_INT, Fb::Table(vec![(0, Fb::I32(8)), (1, Fb::Bool(false))])),
+        Column::Samples => (TYPE_BINARY, Fb::Table(Vec::new())),
+    };
+    Fb::Table(vec![
+        (0, Fb::Str(name.to_string())),
+        (1, Fb::Bool(matches!(column, Column::OptionalFloat(_)))),
+        (2, Fb::U8(type_tag)),
+        (3, kind),
+        // Readers insist on the children vector even for primitive types
+        (5, Fb::Tables(Vec::new())),
+    ])
+}
+
+/// Record batch body with its field nodes and buffer locations
+#[derive(Default)]
+struct Body {
+    data: Vec<u8>,
+    /// `(length, null_count)` per column
+    nodes: Vec<(i64, i64)>,
+    /// `(offset, length)` within `data` per buffer
+    buffers: Vec<(i64, i64)>,
+}
+
+impl Body {
+    fn buffer(&mut self, bytes: &[u8]) {
+        self.buffers.push((self.data.len() as i64, bytes.len() as i64));
+        self.data.extend_from_slice(bytes);
+        pad_to(&mut self.data, 8);
+    }
+
+    fn column(&mut self, pings: &[Ping], column: Column) -> io::Result<()> {
+        let mut nulls = 0;
+        match column {
+            Column::Float(get) => {
+                self.buffer(&[]);
+                self.buffer(&pings.iter().flat_map(|p| get(p).to_le_bytes()).collect::<Vec<u8>>());
+            }
+            Column::OptionalFloat(get) => {
+                let mut validity = vec![0u8; pings.len().div_ceil(8)];
+                for (i, p) in pings.iter().enumerate() {
+                    if get(p).is_some() {
+                        validity[i / 8] |= 1 << (i % 8);
+                    } else {
+                        nulls += 1;
+                    }
+                }
+                self.buffer(if nulls == 0 { &[] } else { &validity });
+                self.buffer(&pings.iter().flat_map(|p| get(p).unwrap_or(0.0).to_le_bytes()).collect::<Vec<u8>>());
+            }
+            Column::UInt16(get) => {
+                self.buffer(&[]);
+                self.buffer(&pings.iter().flat_map(|p| get(p).to_le_bytes()).collect::<Vec<u8>>());
+            }
+            Column::UInt8(get) => {
+                self.buffer(&[]);
+                self.buffer(&pings.iter().map(get).collect::<Vec<u8>>());
+            }
+            Column::Samples => {
+                let mut offsets = Vec::with_capacity(4 * (pings.len() + 1));
+                let mut end = 0i32;
+                offsets.extend_from_slice(&end.to_le_bytes());
+                for p in pings {
+                    end = i32::try_from(p.samples.len())
+                        .ok()
+                        .and_then(|n| end.checked_add(n))
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Arrow batch has over 2 GiB of samples"))?;
+                    offsets.extend_from_slice(&end.to_le_bytes());
+                }
+                self.buffer(&[]);
+                self.buffer(&offsets);
+                self.buffer(&pings.iter().flat_map(|p| p.samples.iter().copied()).collect::<Vec<u8>>());
+            }
+        }
+        self.nodes.push((pings.len() as i64, nulls));
+        Ok(())
+    }
+}
+
+fn write_message<W: Write>(out: &mut W, header_type: u8, header: Fb, body: &[u8]) -> io::Result<()> {
+    let message = Fb::Table(vec![
+        (0, Fb::I16(METADATA_V5)),
+        (1, Fb::U8(header_type)),
+        (2, header),
+        (3, Fb::I64(body.len() as i64)),
+    ]);
+    let mut metadata = flatbuffer(&message);
+    // The 8-byte prefix plus padded metadata keeps the body 8-byte aligned
+    pad_to(&mut metadata, 8);
+    out.write_all(&CONTINUATION)?;
+    out.write_all(&(metadata.len() as i32).to_le_bytes())?;
+    out.write_all(&metadata)?;
+    out.write_all(body)
+}
+
+/// Flatbuffer value tree
+///
+/// Serialized front to back: each table's vtable comes just before it and
+/// its strings, vectors and subtables after it, so every unsigned offset
+/// points forward as the format requires.
+enum Fb {
+    U8(u8),
+    Bool(bool),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Str(String),
+    /// Fields as `(field id, value)`; absent ids take the schema default
+    Table(Vec<(u16, Fb)>),
+    Tables(Vec<Fb>),
+    /// Vector of structs made of two `long`s (`FieldNode`, `Buffer`)
+    Pairs(Vec<(i64, i64)>),
+}
+
+impl Fb {
+    /// Bytes the value takes inside its table; references take a 4-byte offset
+    fn inline_size(&self) -> usize {
+        match self {
+            Fb::U8(_) | Fb::Bool(_) => 1,
+            Fb::I16(_) => 2,
+            Fb::I64(_) => 8,
+            _ => 4,
+        }
+    }
+}
+
+fn flatbuffer(root: &Fb) -> Vec<u8> {
+    let mut buf = vec![0u8; 4];
+    let pos = write_object(&mut buf, root);
+    buf[..4].copy_from_slice(&(pos as u32).to_le_bytes());
+    buf
+}
+
+/// Append a string, vector or table and return its position
+fn write_object(buf: &mut Vec<u8>, value: &Fb) -> usize {
+    match value {
+        Fb::Str(s) => {
+            pad_to(buf, 4);
+            let pos = buf.len();
+            buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            buf.extend_from_slice(s.as_bytes());
+            buf.push(0);
+            pos
+        }
+        Fb::Pairs(pairs) => {
+            // The length sits just before 8-byte aligned elements
+            buf.resize((buf.len() + 4).next_multiple_of(8) - 4, 0);
+            let pos = buf.len();
+            buf.extend_from_slice(&(pairs.len() as u32).to_le_bytes());
+            for &(a, b) in pairs {
+                buf.extend_from_slice(&a.to_le_bytes());
+                buf.extend_from_slice(&b.to_le_bytes());
+            }
+            pos
+        }
+        Fb::Tables(tables) => {
+            pad_to(buf, 4);
+            let pos = buf.len();
+            buf.extend_from_slice(&(tables.len() as u32).to_le_bytes());
+            let first_slot = buf.len();
+            buf.resize(first_slot + 4 * tables.len(), 0);
+            for (k, table) in tables.iter().enumerate() {
+                let target = write_object(buf, table);
+                patch_offset(buf, first_slot + 4 * k, target);
+            }
+            pos
+        }
+        Fb::Table(fields) => {
+            pad_to(buf, 2);
+            let vtable = buf.len();
+            let slot_count = fields.iter().map(|&(id, _)| id as usize + 1).max().unwrap_or(0);
+            buf.resize(vtable + 4 + 2 * slot_count, 0);
+            pad_to(buf, 4);
+            let table = buf.len();
+            buf.extend_from_slice(&((table - vtable) as i32).to_le_bytes());
+
+            let mut children = Vec::new();
+            for (id, value) in fields {
+                pad_to(buf, value.inline_size());
+                let entry = vtable + 4 + 2 * *id as usize;
+                let offset = (buf.len() - table) as u16;
+                buf[entry..entry + 2].copy_from_slice(&offset.to_le_bytes());
+                match *value {
+                    Fb::U8(v) => buf.push(v),
+                    Fb::Bool(v) => buf.push(v as u8),
+                    Fb::I16(v) => buf.extend_from_slice(&v.to_le_bytes()),
+                    Fb::I32(v) => buf.extend_from_slice(&v.to_le_bytes()),
+                    Fb::I64(v) => buf.extend_from_slice(&v.to_le_bytes()),
+                    _ => {
+                        children.push((buf.len(), value));
+                        buf.extend_from_slice(&[0; 4]);
+                    }
+                }
+            }
+            let table_size = (buf.len() - table) as u16;
+            buf[vtable..vtable + 2].copy_from_slice(&((4 + 2 * slot_count) as u16).to_le_bytes());
+            buf[vtable + 2..vtable + 4].copy_from_slice(&table_size.to_le_bytes());
+
+            for (slot, child) in children {
+                let target = write_object(buf, child);
+                patch_offset(buf, slot, target);
+            }
+            table
+        }
+        Fb::U8(_) | Fb::Bool(_) | Fb::I16(_) | Fb::I32(_) | Fb::I64(_) => unreachable!("scalars are stored inline"),
+    }
+}
+
+fn patch_offset(buf: &mut [u8], slot: usize, target: usize) {
+    buf[slot..slot + 4].copy_from_slice(&((target - slot) as u32).to_le_bytes());
+}
+
+fn pad_to(buf: &mut Vec<u8>, align: usize) {
+    buf.resize(buf.len().next_multiple_of(align), 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Messages of a stream as `(metadata, body)`, checking the framing on the way
+    fn messages(stream: &[u8]) -> Vec<(&[u8], &[u8])> {
+        let mut messages = Vec::new();
+        let mut pos = 0;
+        loop {
+            assert_eq!(stream[pos..pos + 4], CONTINUATION);
+            let len = i32::from_le_bytes(stream[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            if len == 0 {
+                assert_eq!(pos + 8, stream.len());
+                return messages;
+            }
+            assert_eq!(len % 8, 0);
+            let metadata = &stream[pos + 8..pos + 8 + len];
+            let body_len = table_field(metadata, root(metadata), 3, 8).map_or(0, |at| read_i64(metadata, at)) as usize;
+            let body_at = pos + 8 + len;
+            messages.push((metadata, &stream[body_at..body_at + body_len]));
+            pos = body_at + body_len;
+        }
+    }
+
+    fn root(buf: &[u8]) -> usize {
+        u32::from_le_bytes(buf[..4].try_into().unwrap()) as usize
+    }
+
+    /// Position of a table field, checking it is aligned to `align`
+    fn table_field(buf: &[u8], table: usize, id: usize, align: usize) -> Option<usize> {
+        let vtable = (table as i64 - i32::from_le_bytes(buf[table..table + 4].try_into().unwrap()) as i64) as usize;
+        let vtable_len = u16::from_le_bytes(buf[vtable..vtable + 2].try_into().unwrap()) as usize;
+        if 4 + 2 * id >= vtable_len {
+            return None;
+        }
+        let entry = vtable + 4 + 2 * id;
+        let offset = u16::from_le_bytes(buf[entry..entry + 2].try_into().unwrap()) as usize;
+        (offset != 0).then(|| {
+            assert_eq!((table + offset) % align, 0);
+            table + offset
+        })
+    }
+
+    fn follow(buf: &[u8], at: usize) -> usize {
+        at + u32::from_le_bytes(buf[at..at + 4].try_into().unwrap()) as usize
+    }
+
+    fn read_i64(buf: &[u8], at: usize) -> i64 {
+        i64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+    }
+
+    fn pings() -> Vec<Ping> {
+        (0..20)
+            .map(|i| Ping {
+                timestamp: i as f64,
+                channel_id: i % 2,
+                cog_deg: (i % 3 == 0).then_some(90.0),
+                samples: vec![i as u8; i as usize],
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn schema_lists_every_column() {
+        let stream = ArrowStreamWriter::new(Vec::new()).unwrap().finish().unwrap();
+        let messages = messages(&stream);
+        assert_eq!(messages.len(), 1);
+        let (meta, body) = messages[0];
+        assert!(body.is_empty());
+        let message = root(meta);
+        assert_eq!(meta[table_field(meta, message, 1, 1).unwrap()], HEADER_SCHEMA);
+        let schema = follow(meta, table_field(meta, message, 2, 4).unwrap());
+        let fields = follow(meta, table_field(meta, schema, 1, 4).unwrap());
+        let count = u32::from_le_bytes(meta[fields..fields + 4].try_into().unwrap()) as usize;
+        assert_eq!(count, COLUMNS.len());
+        for (k, &(name, column)) in COLUMNS.iter().enumerate() {
+            let field = follow(meta, fields + 4 + 4 * k);
+            let text = follow(meta, table_field(meta, field, 0, 4).unwrap());
+            let len = u32::from_le_bytes(meta[text..text + 4].try_into().unwrap()) as usize;
+            assert_eq!(&meta[text + 4..text + 4 + len], name.as_bytes());
+            assert_eq!(meta[text + 4 + len], 0);
+            let nullable = table_field(meta, field, 1, 1).is_some_and(|at| meta[at] == 1);
+            assert_eq!(nullable, matches!(column, Column::OptionalFloat(_)));
+        }
+    }
+
+    #[test]
+    fn batches_locate_aligned_buffers_in_the_body() {
+        let mut writer = ArrowStreamWriter::new(Vec::new()).unwrap();
+        let pings = pings();
+        writer.write_batch(&pings[..12]).unwrap();
+        writer.write_batch(&[]).unwrap();
+        writer.write_batch(&pings[12..]).unwrap();
+        let stream = writer.finish().unwrap();
+        let messages = messages(&stream);
+        assert_eq!(messages.len(), 3);
+
+        let (meta, body) = messages[1];
+        let batch = follow(meta, table_field(meta, root(meta), 2, 4).unwrap());
+        assert_eq!(read_i64(meta, table_field(meta, batch, 0, 8).unwrap()), 12);
+        let pairs = |id| {
+            let at = follow(meta, table_field(meta, batch, id, 4).unwrap());
+            let n = u32::from_le_bytes(meta[at..at + 4].try_into().unwrap()) as usize;
+            assert_eq!((at + 4) % 8, 0);
+            (0..n)
+                .map(|k| (read_i64(meta, at + 4 + 16 * k), read_i64(meta, at + 12 + 16 * k)))
+                .collect::<Vec<_>>()
+        };
+        let nodes = pairs(1);
+        let buffers = pairs(2);
+        assert_eq!(nodes.len(), COLUMNS.len());
+        // cog_deg is set on rows 0, 3, 6 and 9
+        assert_eq!(nodes[7], (12, 8));
+        assert_eq!(buffers.len(), 2 * COLUMNS.len() + 1);
+        assert!(buffers.iter().all(|&(offset, len)| offset % 8 == 0 && (offset + len) as usize <= body.len()));
+
+        let (offset, len) = buffers[14];
+        assert_eq!(&body[offset as usize..(offset + len) as usize], &[0b0100_1001, 0b0010]);
+        let (offset, _) = buffers[1];
+        assert_eq!(read_i64(body, offset as usize + 8 * 5), 5f64.to_bits() as i64);
+        let (offset, len) = buffers[26];
+        assert_eq!(len, (0..12).sum::<i64>());
+        assert_eq!(&body[offset as usize + 1..offset as usize + 3], &[2, 2]);
+    }
+}
@@ -0,0 +1,332 @@
+// Chart layer export for loading processed maps back onto chartplotters
+// src/export/chart.rs
+
+use super::gpx::escape_xml;
+use crate::contours::Contour;
+use crate::imaging::palette::Palette;
+use crate::imaging::png::{crc32_update, write_png, PngColor};
+use crate::imaging::GeoRaster;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Processed map content: an optional mosaic or depth raster plus contours
+#[derive(Debug, Clone)]
+pub struct ChartLayer {
+    pub name: String,
+    pub raster: Option<GeoRaster>,
+    /// Colors applied to raster values
+    pub palette: Palette,
+    pub contours: Vec<Contour>,
+}
+
+/// A chart layer output format
+///
+/// Implement this for additional chartplotter formats; the pipeline and
+/// callers only depend on the trait.
+pub trait ChartLayerExporter {
+    /// Short identifier such as `"kmz"`
+    fn name(&self) -> &str;
+
+    /// File extension without the dot
+    fn extension(&self) -> &str;
+
+    fn write_to(&self, out: &mut dyn Write, layer: &ChartLayer) -> io::Result<()>;
+}
+
+/// Write a layer to a file with the given exporter
+pub fn write_chart_layer<P: AsRef<Path>>(path: P, exporter: &dyn ChartLayerExporter, layer: &ChartLayer) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    exporter.write_to(&mut out, layer)?;
+    out.flush()
+}
+
+/// KMZ with the raster as ground overlay tiles and contours as line placemarks
+///
+/// Tile size and count default to the Garmin Custom Maps limits; rasters
+/// that would need more tiles are downsampled to fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KmzExporter {
+    pub max_tile_px: usize,
+    pub max_tiles: usize,
+    /// Overlay stacking order on the device; custom maps above 50 draw over the base chart
+    pub draw_order: u32,
+}
+
+impl Default for KmzExporter {
+    fn default() -> Self {
+        Self {
+            max_tile_px: 1024,
+            max_tiles: 100,
+            draw_order: 50,
+        }
+    }
+}
+
+impl ChartLayerExporter for KmzExporter {
+    fn name(&self) -> &str {
+        "kmz"
+    }
+
+    fn extension(&self) -> &str {
+        "kmz"
+    }
+
+    fn write_to(&self, out: &mut dyn Write, layer: &ChartLayer) -> io::Result<()> {
+        let mut kml = Vec::new();
+        let mut entries = Vec::new();
+        writeln!(kml, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>")?;
+        writeln!(kml, "<name>{}</name>", escape_xml(&layer.name))?;
+
+        if let Some(raster) = &layer.raster {
+            for tile in self.tiles(raster) {
+                let href = format!("files/tile_{}_{}.png", tile.row, tile.col);
+                writeln!(
+                    kml,
+                    "<GroundOverlay><name>{} {},{}</name><drawOrder>{}</drawOrder><Icon><href>{}</href></Icon>",
+                    escape_xml(&layer.name),
+                    tile.row,
+                    tile.col,
+                    self.draw_order,
+                    href
+                )?;
+                writeln!(
+                    kml,
+                    "<LatLonBox><north>{:.7}</north><south>{:.7}</south><east>{:.7}</east><west>{:.7}</west></LatLonBox></GroundOverlay>",
+                    tile.north, tile.south, tile.east, tile.west
+                )?;
+                let rgba: Vec<u8> = tile
+                    .pixels
+                    .iter()
+                    .flat_map(|px| match px {
+                        Some(v) => {
+                            let [r, g, b] = layer.palette.color(*v);
+                            [r, g, b, 255]
+                        }
+                        None => [0, 0, 0, 0],
+                    })
+                    .collect();
+                let mut png = Vec::new();
+                write_png(&mut png, tile.width, tile.height, PngColor::Rgba, &rgba)?;
+                entries.push((href, png));
+            }
+        }
+
+        if !layer.contours.is_empty() {
+            writeln!(kml, "<Folder><name>Contours</name>")?;
+            for contour in &layer.contours {
+                write!(
+                    kml,
+                    "<Placemark><name>{:.1} m</name><LineString><tessellate>1</tessellate><coordinates>",
+                    contour.depth_m
+                )?;
+                for (lat, lon) in &contour.points {
+                    write!(kml, "{:.7},{:.7},0 ", lon, lat)?;
+                }
+                writeln!(kml, "</coordinates></LineString></Placemark>")?;
+            }
+            writeln!(kml, "</Folder>")?;
+        }
+        writeln!(kml, "</Document>\n</kml>")?;
+
+        entries.insert(0, ("doc.kml".to_string(), kml));
+        write_stored_zip(out, &entries)
+    }
+}
+
+struct OverlayTile {
+    row: usize,
+    col: usize,
+    north: f64,
+    south: f64,
+    east: f64,
+    west: f64,
+    width: usize,
+    height: usize,
+    pixels: Vec<Option<u8>>,
+}
+
+impl KmzExporter {
+    /// Split the raster into tiles, downsampling by the smallest factor that fits the tile budget
+    fn tiles(&self, raster: &GeoRaster) -> Vec<OverlayTile> {
+        let size = self.max_tile_px.max(1);
+        let mut factor = 1;
+        while raster.width.div_ceil(factor).div_ceil(size) * raster.height.div_ceil(factor).div_ceil(size)
+            > self.max_tiles.max(1)
+        {
+            factor += 1;
+        }
+        let (out_w, out_h) = (raster.width.div_ceil(factor), raster.height.div_ceil(factor));
+        let lat_span = raster.max_lat - raster.min_lat;
+        let lon_span = raster.max_lon - raster.min_lon;
+
+        let mut tiles = Vec::new();
+        for row in 0..out_h.div_ceil(size) {
+            for col in 0..out_w.div_ceil(size) {
+                let (y0, y1) = (row * size, ((row + 1) * size).min(out_h));
+                let (x0, x1) = (col * size, ((col + 1) * size).min(out_w));
+                let mut pixels = Vec::with_capacity((y1 - y0) * (x1 - x0));
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        let idx = (y * factor) * raster.width + x * factor;
+                        pixels.push(raster.mask[idx].then_some(raster.values[idx]));
+                    }
+                }
+                let src = |p: usize, limit: usize| (p * factor).min(limit) as f64 / limit as f64;
+                tiles.push(OverlayTile {
+                    row,
+                    col,
+                    north: raster.max_lat - src(y0, raster.height) * lat_span,
+                    south: raster.max_lat - src(y1, raster.height) * lat_span,
+                    west: raster.min_lon + src(x0, raster.width) * lon_span,
+                    east: raster.min_lon + src(x1, raster.width) * lon_span,
+                    width: x1 - x0,
+                    height: y1 - y0,
+                    pixels,
+                });
+            }
+        }
+        tiles
+    }
+}
+
+/// Write files into a ZIP archive without compression
+fn write_stored_zip(out: &mut dyn Write, entries: &[(String, Vec<u8>)]) -> io::Result<()> {
+    let mut central = Vec::new();
+    let mut offset = 0u32;
+    for (name, data) in entries {
+        let crc = crc32_update(0xFFFF_FFFF, data) ^ 0xFFFF_FFFF;
+        let size = u32::try_from(data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "ZIP entry larger than 4 GiB"))?;
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        header.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]); // version, flags, stored, time, date (1980-01-01)
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+        out.write_all(&header)?;
+        out.write_all(data)?;
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&[20, 0]);
+        central.extend_from_slice(&header[4..28]);
+        central.extend_from_slice(&[0; 12]); // extra, comment, disk, internal and external attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+        offset += header.len() as u32 + size;
+    }
+
+    let mut end = Vec::with_capacity(22);
+    end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    end.extend_from_slice(&[0; 4]);
+    end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    end.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    end.extend_from_slice(&offset.to_le_bytes());
+    end.extend_from_slice(&[0, 0]);
+    out.write_all(&central)?;
+    out.write_all(&end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (name, data) of each stored entry, checking CRCs and the central directory
+    fn read_zip(bytes: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]) as usize;
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        while u32_at(pos) == 0x0403_4b50 {
+            let (size, name_len) = (u32_at(pos + 18) as usize, u16_at(pos + 26));
+            let name = String::from_utf8(bytes[pos + 30..pos + 30 + name_len].to_vec()).unwrap();
+            let data = bytes[pos + 30 + name_len..pos + 30 + name_len + size].to_vec();
+            assert_eq!(crc32_update(0xFFFF_FFFF, &data) ^ 0xFFFF_FFFF, u32_at(pos + 14));
+            entries.push((name, data));
+            pos += 30 + name_len + size;
+        }
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(end), 0x0605_4b50);
+        assert_eq!((u16_at(end + 10), u32_at(end + 16) as usize), (entries.len(), pos));
+        assert_eq!(u32_at(pos), 0x0201_4b50);
+        entries
+    }
+
+    /// Size and first RGBA pixel of a PNG written with stored deflate blocks
+    fn png_first_pixel(png: &[u8]) -> ((u32, u32), [u8; 4]) {
+        let be = |i: usize| u32::from_be_bytes(png[i..i + 4].try_into().unwrap());
+        assert_eq!(&png[12..16], b"IHDR");
+        ((be(16), be(20)), png[49..53].try_into().unwrap())
+    }
+
+    fn layer() -> ChartLayer {
+        let mut raster = GeoRaster::new(44.0, -64.0, 45.0, -63.0, 6, 4);
+        for y in 0..4 {
+            for x in 0..6 {
+                raster.values[y * 6 + x] = (x * 40) as u8;
+                raster.mask[y * 6 + x] = (x, y) != (0, 0);
+            }
+        }
+        ChartLayer {
+            name: "Cove & Point".to_string(),
+            raster: Some(raster),
+            palette: Palette::default(),
+            contours: vec![Contour {
+                depth_m: 2.5,
+                points: vec![(44.5, -63.5), (44.6, -63.4)],
+                closed: false,
+            }],
+        }
+    }
+
+    #[test]
+    fn kmz_holds_tiles_and_contours() {
+        let exporter = KmzExporter {
+            max_tile_px: 4,
+            ..Default::default()
+        };
+        let layer = layer();
+        let mut out = Vec::new();
+        exporter.write_to(&mut out, &layer).unwrap();
+        let entries = read_zip(&out);
+        let names: Vec<&str> = entries.iter().map(|e| e.0.as_str()).collect();
+        assert_eq!(names, ["doc.kml", "files/tile_0_0.png", "files/tile_0_1.png"]);
+
+        let kml = String::from_utf8(entries[0].1.clone()).unwrap();
+        assert!(kml.contains("<name>Cove &amp; Point</name>"));
+        assert!(kml.contains(
+            "<GroundOverlay><name>Cove &amp; Point 0,1</name><drawOrder>50</drawOrder>\
+             <Icon><href>files/tile_0_1.png</href></Icon>\n<LatLonBox><north>45.0000000</north>\
+             <south>44.0000000</south><east>-63.0000000</east><west>-63.3333333</west></LatLonBox>"
+        ));
+        assert!(kml.contains("<Placemark><name>2.5 m</name><LineString><tessellate>1</tessellate>\
+                              <coordinates>-63.5000000,44.5000000,0 -63.4000000,44.6000000,0 </coordinates>"));
+
+        // Masked cells are transparent; the rest take the palette color
+        assert_eq!(png_first_pixel(&entries[1].1), ((4, 4), [0, 0, 0, 0]));
+        let [r, g, b] = layer.palette.color(160);
+        assert_eq!(png_first_pixel(&entries[2].1), ((2, 4), [r, g, b, 255]));
+    }
+
+    #[test]
+    fn kmz_downsamples_rasters_over_the_tile_budget() {
+        let exporter = KmzExporter {
+            max_tile_px: 4,
+            max_tiles: 1,
+            draw_order: 60,
+        };
+        let mut out = Vec::new();
+        exporter.write_to(&mut out, &layer()).unwrap();
+        let entries = read_zip(&out);
+        assert_eq!(entries.len(), 2);
+        // Every other cell of the 6 x 4 raster
+        assert_eq!(png_first_pixel(&entries[1].1).0, (3, 2));
+        let kml = String::from_utf8(entries[0].1.clone()).unwrap();
+        assert!(kml.contains("<drawOrder>60</drawOrder>"));
+        assert!(kml.contains("<east>-63.0000000</east><west>-64.0000000</west>"));
+    }
+}
@@ -0,0 +1,161 @@
+// DXF export of contours and track for CAD
+// src/export/dxf.rs
+
+use crate::contours::Contour;
+use crate::crs::Crs;
+use crate::geo::local_offset_m;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const CONTOUR_LAYER: &str = "CONTOURS";
+const TRACK_LAYER: &str = "TRACK";
+
+/// Coordinate space written to the drawing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DxfCoordinates {
+    /// X = longitude, Y = latitude in degrees
+    Geographic,
+    /// X = meters east, Y = meters north of the origin
+    LocalMeters { origin_lat: f64, origin_lon: f64 },
+    /// X/Y in a projected CRS such as a UTM zone
+    Projected(Crs),
+}
+
+/// Write contours and the vessel track as an AutoCAD R12 ASCII DXF
+///
+/// Contours go on layer `CONTOURS` as polylines at elevation `-depth_m`;
+/// the track (`(lat, lon)` points) goes on layer `TRACK` at elevation 0.
+pub fn write_dxf<P: AsRef<Path>>(
+    path: P,
+    contours: &[Contour],
+    track: &[(f64, f64)],
+    coordinates: DxfCoordinates,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_dxf_to(&mut out, contours, track, coordinates)?;
+    out.flush()
+}
+
+pub fn write_dxf_to<W: Write>(
+    out: &mut W,
+    contours: &[Contour],
+    track: &[(f64, f64)],
+    coordinates: DxfCoordinates,
+) -> io::Result<()> {
+    let project = |(lat, lon): (f64, f64)| match coordinates {
+        DxfCoordinates::Geographic => (lon, lat),
+        DxfCoordinates::LocalMeters { origin_lat, origin_lon } => local_offset_m(origin_lat, origin_lon, lat, lon),
+        DxfCoordinates::Projected(crs) => crs.project(lat, lon),
+    };
+
+    pair(out, 0, "SECTION")?;
+    pair(out, 2, "TABLES")?;
+    pair(out, 0, "TABLE")?;
+    pair(out, 2, "LAYER")?;
+    pair(out, 70, 2)?;
+    // Layer colors are ACI indices: 5 = blue, 1 = red
+    for (name, color) in [(CONTOUR_LAYER, 5), (TRACK_LAYER, 1)] {
+        pair(out, 0, "LAYER")?;
+        pair(out, 2, name)?;
+        pair(out, 70, 0)?;
+        pair(out, 62, color)?;
+        pair(out, 6, "CONTINUOUS")?;
+    }
+    pair(out, 0, "ENDTAB")?;
+    pair(out, 0, "ENDSEC")?;
+
+    pair(out, 0, "SECTION")?;
+    pair(out, 2, "ENTITIES")?;
+    for contour in contours {
+        let points: Vec<(f64, f64)> = contour.points.iter().map(|&p| project(p)).collect();
+        write_polyline(out, CONTOUR_LAYER, &points, -contour.depth_m, contour.closed)?;
+    }
+    if track.len() >= 2 {
+        let points: Vec<(f64, f64)> = track.iter().map(|&p| project(p)).collect();
+        write_polyline(out, TRACK_LAYER, &points, 0.0, false)?;
+    }
+    pair(out, 0, "ENDSEC")?;
+    pair(out, 0, "EOF")
+}
+
+fn write_polyline<W: Write>(out: &mut W, layer: &str, points: &[(f64, f64)], elevation: f64, closed: bool) -> io::Result<()> {
+    pair(out, 0, "POLYLINE")?;
+    pair(out, 8, layer)?;
+    pair(out, 66, 1)?; // vertices follow
+    pair(out, 10, 0.0)?;
+    pair(out, 20, 0.0)?;
+    pair(out, 30, elevation)?;
+    pair(out, 70, if closed { 1 } else { 0 })?;
+    for &(x, y) in points {
+        pair(out, 0, "VERTEX")?;
+        pair(out, 8, layer)?;
+        writeln!(out, " 10\n{:.7}\n 20\n{:.7}\n 30\n{:.3}", x, y, elevation)?;
+    }
+    pair(out, 0, "SEQEND")?;
+    pair(out, 8, layer)
+}
+
+/// One group code / value pair
+fn pair<W: Write, V: std::fmt::Display>(out: &mut W, code: u16, value: V) -> io::Result<()> {
+    writeln!(out, "{:>3}\n{}", code, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(text: &str) -> Vec<(u16, &str)> {
+        let lines: Vec<&str> = text.lines().collect();
+        lines.chunks(2).map(|p| (p[0].trim().parse().unwrap(), p[1])).collect()
+    }
+
+    #[test]
+    fn writes_layers_and_polylines_with_depth_elevations() {
+        let contour = Contour {
+            depth_m: 3.0,
+            points: vec![(44.6, -63.5), (44.61, -63.49), (44.6, -63.48)],
+            closed: true,
+        };
+        let mut out = Vec::new();
+        // A single fix is not a track
+        write_dxf_to(&mut out, &[contour], &[(44.6, -63.5)], DxfCoordinates::Geographic).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("  0\nSECTION\n  2\nTABLES\n"));
+        assert!(text.contains("  0\nLAYER\n  2\nCONTOURS\n 70\n0\n 62\n5\n  6\nCONTINUOUS\n"));
+        assert!(text.contains(
+            "  0\nPOLYLINE\n  8\nCONTOURS\n 66\n1\n 10\n0\n 20\n0\n 30\n-3\n 70\n1\n\
+             \x20 0\nVERTEX\n  8\nCONTOURS\n 10\n-63.5000000\n 20\n44.6000000\n 30\n-3.000\n"
+        ));
+        assert!(text.ends_with("  0\nSEQEND\n  8\nCONTOURS\n  0\nENDSEC\n  0\nEOF\n"));
+        let pairs = pairs(&text);
+        assert_eq!(pairs.iter().filter(|&&p| p == (0, "VERTEX")).count(), 3);
+        assert!(!pairs.contains(&(8, TRACK_LAYER)));
+    }
+
+    #[test]
+    fn local_meters_put_the_track_around_the_origin() {
+        let mut out = Vec::new();
+        let track = [(44.6, -63.5), (44.601, -63.5)];
+        let coordinates = DxfCoordinates::LocalMeters {
+            origin_lat: 44.6,
+            origin_lon: -63.5,
+        };
+        write_dxf_to(&mut out, &[], &track, coordinates).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let pairs = pairs(&text);
+        let track_start = pairs.iter().position(|p| *p == (0, "POLYLINE")).unwrap();
+        assert_eq!(pairs[track_start + 1], (8, TRACK_LAYER));
+        assert_eq!(pairs[track_start + 6], (70, "0"));
+
+        let coords: Vec<f64> = pairs
+            .iter()
+            .filter(|p| p.0 == 10 || p.0 == 20)
+            .skip(2)
+            .map(|p| p.1.parse().unwrap())
+            .collect();
+        assert_eq!(coords.len(), 4);
+        assert_eq!((coords[0], coords[1], coords[2]), (0.0, 0.0, 0.0));
+        assert!((coords[3] - 111.2).abs() < 0.5, "{}", coords[3]);
+    }
+}
@@ -0,0 +1,124 @@
+// GeoJSON export
+// src/export/geojson.rs
+
+use crate::contours::Contour;
+use crate::crs::Crs;
+use crate::survey::{Sounding, VerticalReference};
+use crate::track::{simplify_track, Simplify, TrackVertex};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Write contours as a FeatureCollection of LineStrings with a `depth_m` property
+pub fn write_contours<P: AsRef<Path>>(path: P, contours: &[Contour]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_contours_to(&mut out, contours)?;
+    out.flush()
+}
+
+pub fn write_contours_to<W: Write>(out: &mut W, contours: &[Contour]) -> io::Result<()> {
+    write_contours_with_crs_to(out, contours, Crs::Wgs84)
+}
+
+/// Contours with coordinates in `crs`
+///
+/// RFC 7946 GeoJSON is WGS84 only; for any other CRS the legacy (2008)
+/// `crs` member names it so GIS tools read the projected coordinates.
+pub fn write_contours_with_crs<P: AsRef<Path>>(path: P, contours: &[Contour], crs: Crs) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_contours_with_crs_to(&mut out, contours, crs)?;
+    out.flush()
+}
+
+pub fn write_contours_with_crs_to<W: Write>(out: &mut W, contours: &[Contour], crs: Crs) -> io::Result<()> {
+    writeln!(out, "{{\"type\":\"FeatureCollection\",{}\"features\":[", crs_member(crs))?;
+    for (i, contour) in contours.iter().enumerate() {
+        let separator = if i + 1 < contours.len() { "," } else { "" };
+        writeln!(
+            out,
+            "{{\"type\":\"Feature\",\"properties\":{{\"depth_m\":{}}},\"geometry\":{{\"type\":\"LineString\",\"coordinates\":[{}]}}}}{}",
+            contour.depth_m,
+            coordinates(&contour.points, crs),
+            separator
+        )?;
+    }
+    writeln!(out, "]}}")
+}
+
+/// Write soundings as a simplified LineString feature
+///
+/// Coordinates carry elevation (`-depth_m`); the `min_depth_m` and
+/// `max_depth_m` properties list the depth range folded into each vertex,
+/// and `vertical_reference` names what the depths are measured from.
+pub fn write_track<P: AsRef<Path>>(path: P, name: &str, track: &[Sounding], simplify: &Simplify) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_track_to(&mut out, name, track, simplify)?;
+    out.flush()
+}
+
+pub fn write_track_to<W: Write>(out: &mut W, name: &str, track: &[Sounding], simplify: &Simplify) -> io::Result<()> {
+    write_track_with_crs_to(out, name, track, simplify, Crs::Wgs84)
+}
+
+/// Track feature with coordinates in `crs`, see [`write_contours_with_crs`]
+pub fn write_track_with_crs<P: AsRef<Path>>(
+    path: P,
+    name: &str,
+    track: &[Sounding],
+    simplify: &Simplify,
+    crs: Crs,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_track_with_crs_to(&mut out, name, track, simplify, crs)?;
+    out.flush()
+}
+
+pub fn write_track_with_crs_to<W: Write>(
+    out: &mut W,
+    name: &str,
+    track: &[Sounding],
+    simplify: &Simplify,
+    crs: Crs,
+) -> io::Result<()> {
+    let vertices = simplify_track(track, simplify);
+    let decimals = crs.decimals();
+    writeln!(
+        out,
+        "{{\"type\":\"Feature\",{}\"properties\":{{\"name\":\"{}\",\"vertical_reference\":\"{}\",\"min_depth_m\":[{}],\"max_depth_m\":[{}]}},\"geometry\":{{\"type\":\"LineString\",\"coordinates\":[{}]}}}}",
+        crs_member(crs),
+        name.replace('\\', "\\\\").replace('"', "\\\""),
+        track.first().map_or(VerticalReference::Transducer, |s| s.reference).name(),
+        join(&vertices, |v| format!("{:.3}", v.min_depth_m)),
+        join(&vertices, |v| format!("{:.3}", v.max_depth_m)),
+        join(&vertices, |v| {
+            let (x, y) = crs.project(v.lat, v.lon);
+            format!("[{:.*},{:.*},{:.3}]", decimals, x, decimals, y, -v.depth_m)
+        })
+    )
+}
+
+/// GeoJSON coordinate list in x,y (lon,lat) order
+fn coordinates(points: &[(f64, f64)], crs: Crs) -> String {
+    let decimals = crs.decimals();
+    points
+        .iter()
+        .map(|&(lat, lon)| {
+            let (x, y) = crs.project(lat, lon);
+            format!("[{:.*},{:.*}]", decimals, x, decimals, y)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Named `crs` member plus trailing comma; empty for WGS84
+fn crs_member(crs: Crs) -> String {
+    if crs.is_geographic() {
+        String::new()
+    } else {
+        format!("\"crs\":{{\"type\":\"name\",\"properties\":{{\"name\":\"{}\"}}}},", crs.urn())
+    }
+}
+
+fn join<F: Fn(&TrackVertex) -> String>(vertices: &[TrackVertex], f: F) -> String {
+    vertices.iter().map(f).collect::<Vec<_>>().join(",")
+}
@@ -0,0 +1,158 @@
+// GPX export
+// src/export/gpx.rs
+
+use crate::metadata::SurveyMetadata;
+use crate::survey::{Sounding, Waypoint};
+use crate::track::{simplify_track, Simplify};
+use chrono::{DateTime, SecondsFormat};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const GPX_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\" creator=\"SonarSniffer\" xmlns=\"http://www.topografix.com/GPX/1/1\">";
+
+/// Write waypoints as GPX 1.1 `<wpt>` elements (depth goes into `<ele>` as negative elevation)
+pub fn write_waypoints<P: AsRef<Path>>(path: P, waypoints: &[Waypoint]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_waypoints_to(&mut out, waypoints)?;
+    out.flush()
+}
+
+pub fn write_waypoints_to<W: Write>(out: &mut W, waypoints: &[Waypoint]) -> io::Result<()> {
+    write_waypoints_with_metadata_to(out, waypoints, &SurveyMetadata::default())
+}
+
+/// Write waypoints with the survey metadata in the GPX `<metadata>` element
+pub fn write_waypoints_with_metadata<P: AsRef<Path>>(
+    path: P,
+    waypoints: &[Waypoint],
+    metadata: &SurveyMetadata,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_waypoints_with_metadata_to(&mut out, waypoints, metadata)?;
+    out.flush()
+}
+
+pub fn write_waypoints_with_metadata_to<W: Write>(
+    out: &mut W,
+    waypoints: &[Waypoint],
+    metadata: &SurveyMetadata,
+) -> io::Result<()> {
+    writeln!(out, "{}", GPX_HEADER)?;
+    write_metadata_to(out, metadata)?;
+    for wpt in waypoints {
+        writeln!(out, "<wpt lat=\"{:.7}\" lon=\"{:.7}\">", wpt.lat, wpt.lon)?;
+        if let Some(depth) = wpt.depth_m {
+            writeln!(out, "<ele>{:.2}</ele>", -depth)?;
+        }
+        if let Some(time) = format_time(wpt.timestamp) {
+            writeln!(out, "<time>{}</time>", time)?;
+        }
+        writeln!(out, "<name>{}</name>", escape_xml(&wpt.name))?;
+        if !wpt.comment.is_empty() {
+            writeln!(out, "<cmt>{}</cmt>", escape_xml(&wpt.comment))?;
+        }
+        writeln!(out, "</wpt>")?;
+    }
+    writeln!(out, "</gpx>")
+}
+
+/// Write soundings as a single-segment GPX track, simplified before writing
+///
+/// Each point's elevation is the shoalest depth folded into it, so shoals on
+/// dropped points are not lost.
+pub fn write_track<P: AsRef<Path>>(path: P, name: &str, track: &[Sounding], simplify: &Simplify) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_track_to(&mut out, name, track, simplify)?;
+    out.flush()
+}
+
+pub fn write_track_to<W: Write>(out: &mut W, name: &str, track: &[Sounding], simplify: &Simplify) -> io::Result<()> {
+    writeln!(out, "{}", GPX_HEADER)?;
+    writeln!(out, "<trk><name>{}</name><trkseg>", escape_xml(name))?;
+    for v in simplify_track(track, simplify) {
+        write!(out, "<trkpt lat=\"{:.7}\" lon=\"{:.7}\"><ele>{:.2}</ele>", v.lat, v.lon, -v.min_depth_m)?;
+        if let Some(time) = format_time(v.timestamp) {
+            write!(out, "<time>{}</time>", time)?;
+        }
+        writeln!(out, "</trkpt>")?;
+    }
+    writeln!(out, "</trkseg></trk>")?;
+    writeln!(out, "</gpx>")
+}
+
+/// GPX `<metadata>` element (project as name, description, operator as author); nothing when empty
+pub fn write_metadata_to<W: Write>(out: &mut W, metadata: &SurveyMetadata) -> io::Result<()> {
+    if metadata.is_empty() {
+        return Ok(());
+    }
+    write!(out, "<metadata>")?;
+    if !metadata.project.is_empty() {
+        write!(out, "<name>{}</name>", escape_xml(&metadata.project))?;
+    }
+    let description = metadata.description();
+    if !description.is_empty() {
+        write!(out, "<desc>{}</desc>", escape_xml(&description))?;
+    }
+    if !metadata.operator.is_empty() {
+        write!(out, "<author><name>{}</name></author>", escape_xml(&metadata.operator))?;
+    }
+    writeln!(out, "</metadata>")
+}
+
+/// ISO 8601 UTC timestamp for unix seconds
+pub(crate) fn format_time(timestamp: f64) -> Option<String> {
+    let secs = timestamp.floor();
+    let nanos = ((timestamp - secs) * 1e9) as u32;
+    DateTime::from_timestamp(secs as i64, nanos).map(|t| t.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_points_carry_the_shoalest_folded_depth() {
+        // Straight line east; the 2 m shoal sits on a collinear point that is dropped
+        let track: Vec<Sounding> = [10.0, 9.0, 2.0, 9.0, 10.0]
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| Sounding::new(1_700_000_000.0 + i as f64, 45.0, -83.0 + i as f64 * 1e-4, d))
+            .collect();
+        let mut buf = Vec::new();
+        write_track_to(&mut buf, "run <1>", &track, &Simplify::Tolerance { meters: 1.0 }).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("<trk><name>run &lt;1&gt;</name>"));
+        assert_eq!(text.matches("<trkpt").count(), 2);
+        assert!(text.contains("<ele>-2.00</ele>"), "{}", text);
+        assert!(text.contains("<time>2023-11-14T22:13:20.000Z</time>"));
+    }
+
+    #[test]
+    fn waypoint_files_carry_survey_metadata() {
+        let metadata = SurveyMetadata {
+            vessel: "Osprey".to_string(),
+            operator: "J. Doe".to_string(),
+            project: "Cove & Point".to_string(),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        write_waypoints_with_metadata_to(&mut buf, &[], &metadata).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains(
+            "<metadata><name>Cove &amp; Point</name><desc>Vessel: Osprey; Operator: J. Doe; Project: Cove &amp; Point\
+             </desc><author><name>J. Doe</name></author></metadata>\n"
+        ));
+
+        let mut buf = Vec::new();
+        write_waypoints_to(&mut buf, &[]).unwrap();
+        assert!(!String::from_utf8(buf).unwrap().contains("<metadata>"));
+    }
+}
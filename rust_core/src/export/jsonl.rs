@@ -0,0 +1,153 @@
+// JSON Lines streaming export
+// src/export/jsonl.rs
+
+use super::gpx::format_time;
+use crate::survey::{Ping, Sounding};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Write one JSON object per ping; `samples` is included only when asked for
+pub fn write_pings_jsonl<P: AsRef<Path>>(path: P, pings: &[Ping], include_samples: bool) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_pings_jsonl_to(&mut out, pings.iter(), include_samples)?;
+    out.flush()
+}
+
+/// Stream pings as they arrive; each line is written with a single call, so
+/// passing an unbuffered writer such as `io::stdout().lock()` keeps pipes live
+pub fn write_pings_jsonl_to<'a, W: Write, I: IntoIterator<Item = &'a Ping>>(
+    out: &mut W,
+    pings: I,
+    include_samples: bool,
+) -> io::Result<usize> {
+    let mut count = 0;
+    for p in pings {
+        let mut line = format!(
+            "{{\"timestamp\":{},\"time\":{},\"channel_id\":{},\"lat\":{},\"lon\":{},\"heading_deg\":{},\"depth_m\":{},\"range_m\":{},\"cog_deg\":{},\"sog_knots\":{},\"stw_knots\":{},\"frequency_khz\":{},\"chirp_khz\":{},\"pulse_length_us\":{},\"range_setting_m\":{},\"gain_pct\":{},\"flags\":{}",
+            number(p.timestamp),
+            time(p.timestamp),
+            p.channel_id,
+            number(p.lat),
+            number(p.lon),
+            number(p.heading_deg),
+            number(p.depth_m),
+            number(p.range_m),
+            number(p.cog_deg.unwrap_or(f64::NAN)),
+            number(p.sog_knots.unwrap_or(f64::NAN)),
+            number(p.stw_knots.unwrap_or(f64::NAN)),
+            number(p.acoustics.frequency_khz.unwrap_or(f64::NAN)),
+            p.acoustics
+                .chirp_khz
+                .map_or("null".to_string(), |(start, end)| format!("[{},{}]", number(start), number(end))),
+            number(p.acoustics.pulse_length_us.unwrap_or(f64::NAN)),
+            number(p.acoustics.range_setting_m.unwrap_or(f64::NAN)),
+            number(p.acoustics.gain_pct.unwrap_or(f64::NAN)),
+            p.flags
+        );
+        if include_samples {
+            let samples: Vec<String> = p.samples.iter().map(u8::to_string).collect();
+            line.push_str(&format!(",\"samples\":[{}]", samples.join(",")));
+        }
+        line.push_str("}\n");
+        out.write_all(line.as_bytes())?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Write one JSON object per sounding
+pub fn write_soundings_jsonl<P: AsRef<Path>>(path: P, soundings: &[Sounding]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_soundings_jsonl_to(&mut out, soundings.iter())?;
+    out.flush()
+}
+
+pub fn write_soundings_jsonl_to<'a, W: Write, I: IntoIterator<Item = &'a Sounding>>(out: &mut W, soundings: I) -> io::Result<usize> {
+    let mut count = 0;
+    for s in soundings {
+        let line = format!(
+            "{{\"timestamp\":{},\"time\":{},\"lat\":{},\"lon\":{},\"depth_m\":{},\"heading_deg\":{},\"reference\":\"{}\"}}\n",
+            number(s.timestamp),
+            time(s.timestamp),
+            number(s.lat),
+            number(s.lon),
+            number(s.depth_m),
+            number(s.heading_deg),
+            s.reference.name()
+        );
+        out.write_all(line.as_bytes())?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// JSON number, or `null` for NaN and infinities which JSON cannot represent
+fn number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn time(timestamp: f64) -> String {
+    match format_time(timestamp) {
+        Some(t) => format!("\"{}\"", t),
+        None => "null".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::survey::{Acoustics, VerticalReference};
+
+    #[test]
+    fn ping_lines_use_null_for_missing_values() {
+        let ping = Ping {
+            timestamp: 1_700_000_000.5,
+            channel_id: 2,
+            lat: 44.6,
+            lon: -63.5,
+            heading_deg: f64::NAN,
+            depth_m: 4.25,
+            range_m: 30.0,
+            sog_knots: Some(3.5),
+            acoustics: Acoustics {
+                frequency_khz: Some(455.0),
+                chirp_khz: Some((420.0, 490.0)),
+                ..Default::default()
+            },
+            flags: 1,
+            samples: vec![0, 128, 255],
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        assert_eq!(write_pings_jsonl_to(&mut out, [&ping, &ping], true).unwrap(), 2);
+        let text = String::from_utf8(out).unwrap();
+        let expected = "{\"timestamp\":1700000000.5,\"time\":\"2023-11-14T22:13:20.500Z\",\"channel_id\":2,\
+                        \"lat\":44.6,\"lon\":-63.5,\"heading_deg\":null,\"depth_m\":4.25,\"range_m\":30,\
+                        \"cog_deg\":null,\"sog_knots\":3.5,\"stw_knots\":null,\"frequency_khz\":455,\
+                        \"chirp_khz\":[420,490],\"pulse_length_us\":null,\"range_setting_m\":null,\
+                        \"gain_pct\":null,\"flags\":1,\"samples\":[0,128,255]}\n";
+        assert_eq!(text, expected.repeat(2));
+
+        let mut out = Vec::new();
+        write_pings_jsonl_to(&mut out, [&ping], false).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected.replace(",\"samples\":[0,128,255]", ""));
+    }
+
+    #[test]
+    fn sounding_lines_name_the_vertical_reference() {
+        let mut sounding = Sounding::new(1_700_000_000.0, 44.6, -63.5, 7.5);
+        sounding.reference = VerticalReference::ChartDatum;
+        let mut out = Vec::new();
+        assert_eq!(write_soundings_jsonl_to(&mut out, [&sounding]).unwrap(), 1);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"timestamp\":1700000000,\"time\":\"2023-11-14T22:13:20.000Z\",\"lat\":44.6,\"lon\":-63.5,\
+             \"depth_m\":7.5,\"heading_deg\":0,\"reference\":\"chart_datum\"}\n"
+        );
+    }
+}
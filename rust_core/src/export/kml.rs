@@ -0,0 +1,107 @@
+// KML export
+// src/export/kml.rs
+
+use super::gpx::escape_xml;
+use crate::contours::Contour;
+use crate::survey::Sounding;
+use crate::timezone::TimeZone;
+use crate::track::{simplify_track, Simplify, TrackVertex};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const KML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>";
+const KML_FOOTER: &str = "</Document>\n</kml>";
+
+/// Write contours as LineString placemarks named by depth
+pub fn write_contours<P: AsRef<Path>>(path: P, contours: &[Contour]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_contours_to(&mut out, contours)?;
+    out.flush()
+}
+
+pub fn write_contours_to<W: Write>(out: &mut W, contours: &[Contour]) -> io::Result<()> {
+    writeln!(out, "{}", KML_HEADER)?;
+    writeln!(out, "<name>Depth contours</name>")?;
+    for contour in contours {
+        writeln!(out, "<Placemark><name>{:.1} m</name>", contour.depth_m)?;
+        writeln!(out, "<LineString><tessellate>1</tessellate><coordinates>")?;
+        for (lat, lon) in &contour.points {
+            writeln!(out, "{:.7},{:.7},0", lon, lat)?;
+        }
+        writeln!(out, "</coordinates></LineString></Placemark>")?;
+    }
+    writeln!(out, "{}", KML_FOOTER)
+}
+
+/// Write soundings as a simplified track LineString clamped to the surface
+pub fn write_track<P: AsRef<Path>>(path: P, name: &str, track: &[Sounding], simplify: &Simplify) -> io::Result<()> {
+    write_track_with_zone(path, name, track, simplify, &TimeZone::Utc)
+}
+
+/// Track whose time span is written in local time of `zone`
+pub fn write_track_with_zone<P: AsRef<Path>>(
+    path: P,
+    name: &str,
+    track: &[Sounding],
+    simplify: &Simplify,
+    zone: &TimeZone,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_track_to(&mut out, name, track, simplify, zone)?;
+    out.flush()
+}
+
+/// The placemark gets a `TimeSpan` from the first to the last timed sounding,
+/// and `ExtendedData` with each vertex's folded `min_depth_m` and `max_depth_m`
+pub fn write_track_to<W: Write>(
+    out: &mut W,
+    name: &str,
+    track: &[Sounding],
+    simplify: &Simplify,
+    zone: &TimeZone,
+) -> io::Result<()> {
+    writeln!(out, "{}", KML_HEADER)?;
+    writeln!(out, "<Placemark><name>{}</name>", escape_xml(name))?;
+    let times = track.iter().map(|s| s.timestamp).filter(|&t| t > 0.0);
+    let span = times.clone().reduce(f64::min).zip(times.reduce(f64::max));
+    if let Some((begin, end)) = span.and_then(|(b, e)| zone.format_time(b).zip(zone.format_time(e))) {
+        writeln!(out, "<TimeSpan><begin>{}</begin><end>{}</end></TimeSpan>", begin, end)?;
+    }
+    let vertices = simplify_track(track, simplify);
+    // Depth range folded into each vertex, space separated in coordinate order
+    let depths = |f: fn(&TrackVertex) -> f64| vertices.iter().map(|v| format!("{:.3}", f(v))).collect::<Vec<_>>().join(" ");
+    writeln!(
+        out,
+        "<ExtendedData><Data name=\"min_depth_m\"><value>{}</value></Data><Data name=\"max_depth_m\"><value>{}</value></Data></ExtendedData>",
+        depths(|v| v.min_depth_m),
+        depths(|v| v.max_depth_m)
+    )?;
+    writeln!(out, "<LineString><tessellate>1</tessellate><coordinates>")?;
+    for v in &vertices {
+        writeln!(out, "{:.7},{:.7},0", v.lon, v.lat)?;
+    }
+    writeln!(out, "</coordinates></LineString></Placemark>")?;
+    writeln!(out, "{}", KML_FOOTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_extended_data_keeps_folded_depth_ranges() {
+        let track: Vec<Sounding> = [10.0, 9.0, 2.0, 9.0, 10.0]
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| Sounding::new(1_700_000_000.0 + i as f64, 45.0, -83.0 + i as f64 * 1e-4, d))
+            .collect();
+        let mut buf = Vec::new();
+        write_track_to(&mut buf, "run", &track, &Simplify::MaxPoints(2), &TimeZone::Utc).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("<Data name=\"min_depth_m\"><value>2.000 9.000</value></Data>"), "{}", text);
+        assert!(text.contains("<Data name=\"max_depth_m\"><value>10.000 10.000</value></Data>"));
+        assert!(text.contains("-83.0000000,45.0000000,0\n-82.9996000,45.0000000,0\n"));
+        assert!(text.find("</ExtendedData>") < text.find("<LineString>"));
+    }
+}
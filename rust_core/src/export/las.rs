@@ -0,0 +1,250 @@
+// LAS 1.2 point cloud export
+// src/export/las.rs
+
+use crate::crs::Crs;
+use crate::survey::Sounding;
+use chrono::{Datelike, Utc};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const HEADER_SIZE: u16 = 227;
+const POINT_RECORD_LENGTH: u16 = 20;
+const XY_SCALE: f64 = 1e-7;
+/// Millimeter resolution for projected coordinates
+const XY_SCALE_PROJECTED: f64 = 1e-3;
+const Z_SCALE: f64 = 1e-3;
+
+/// ASPRS class 2 (ground) — the lake/sea bed
+const CLASS_GROUND: u8 = 2;
+
+/// GeoTIFF key directory: geographic (model type 2, GeographicTypeGeoKey)
+/// or projected (model type 1, ProjectedCSTypeGeoKey)
+fn geo_keys(crs: Crs) -> [u16; 12] {
+    let code = crs.epsg() as u16;
+    if crs.is_geographic() {
+        [1, 1, 0, 2, 1024, 0, 1, 2, 2048, 0, 1, code]
+    } else {
+        [1, 1, 0, 2, 1024, 0, 1, 1, 3072, 0, 1, code]
+    }
+}
+
+/// Write soundings as LAS 1.2 point format 0 (X=lon, Y=lat, Z=elevation, i.e. -depth)
+///
+/// Each point's user data byte holds its vertical reference code
+/// (0 transducer, 1 waterline, 2 chart datum).
+pub fn write_las<P: AsRef<Path>>(path: P, soundings: &[Sounding]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_las_to(&mut out, soundings)?;
+    out.flush()
+}
+
+pub fn write_las_to<W: Write>(out: &mut W, soundings: &[Sounding]) -> io::Result<()> {
+    write_las_with_crs_to(out, soundings, Crs::Wgs84)
+}
+
+/// Write soundings as LAS with X/Y in `crs`, declared in the GeoKey VLR
+pub fn write_las_with_crs<P: AsRef<Path>>(path: P, soundings: &[Sounding], crs: Crs) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_las_with_crs_to(&mut out, soundings, crs)?;
+    out.flush()
+}
+
+pub fn write_las_with_crs_to<W: Write>(out: &mut W, soundings: &[Sounding], crs: Crs) -> io::Result<()> {
+    let count = u32::try_from(soundings.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many points for LAS 1.2"))?;
+
+    let (mut min_x, mut max_x) = (f64::INFINITY, f64::NEG_INFINITY);
+    let (mut min_y, mut max_y) = (f64::INFINITY, f64::NEG_INFINITY);
+    let (mut min_z, mut max_z) = (f64::INFINITY, f64::NEG_INFINITY);
+    let points: Vec<(f64, f64)> = soundings.iter().map(|s| crs.project(s.lat, s.lon)).collect();
+    for (s, &(x, y)) in soundings.iter().zip(&points) {
+        min_x = min_x.min(x);
+        max_x = max_x.max(x);
+        min_y = min_y.min(y);
+        max_y = max_y.max(y);
+        min_z = min_z.min(-s.depth_m);
+        max_z = max_z.max(-s.depth_m);
+    }
+    if soundings.is_empty() {
+        (min_x, max_x, min_y, max_y, min_z, max_z) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    }
+    let (offset_x, offset_y, offset_z) = (min_x.floor(), min_y.floor(), 0.0);
+
+    let xy_scale = if crs.is_geographic() { XY_SCALE } else { XY_SCALE_PROJECTED };
+
+    let vlr_data: Vec<u8> = geo_keys(crs).iter().flat_map(|k| k.to_le_bytes()).collect();
+    let point_offset = HEADER_SIZE as u32 + 54 + vlr_data.len() as u32;
+    let today = Utc::now();
+
+    // Public header block
+    out.write_all(b"LASF")?;
+    out.write_all(&0u16.to_le_bytes())?; // file source id
+    out.write_all(&0u16.to_le_bytes())?; // global encoding
+    out.write_all(&[0u8; 16])?; // project GUID
+    out.write_all(&[1, 2])?; // version 1.2
+    out.write_all(&fixed_ascii::<32>("OTHER"))?;
+    out.write_all(&fixed_ascii::<32>("SonarSniffer"))?;
+    out.write_all(&(today.ordinal() as u16).to_le_bytes())?;
+    out.write_all(&(today.year() as u16).to_le_bytes())?;
+    out.write_all(&HEADER_SIZE.to_le_bytes())?;
+    out.write_all(&point_offset.to_le_bytes())?;
+    out.write_all(&1u32.to_le_bytes())?; // number of VLRs
+    out.write_all(&[0])?; // point data format 0
+    out.write_all(&POINT_RECORD_LENGTH.to_le_bytes())?;
+    out.write_all(&count.to_le_bytes())?;
+    out.write_all(&count.to_le_bytes())?; // all points are first returns
+    out.write_all(&[0u8; 16])?;
+    for value in [xy_scale, xy_scale, Z_SCALE, offset_x, offset_y, offset_z] {
+        out.write_all(&value.to_le_bytes())?;
+    }
+    for value in [max_x, min_x, max_y, min_y, max_z, min_z] {
+        out.write_all(&value.to_le_bytes())?;
+    }
+
+    // GeoKeyDirectoryTag VLR
+    out.write_all(&0u16.to_le_bytes())?; // reserved
+    out.write_all(&fixed_ascii::<16>("LASF_Projection"))?;
+    out.write_all(&34735u16.to_le_bytes())?;
+    out.write_all(&(vlr_data.len() as u16).to_le_bytes())?;
+    out.write_all(&fixed_ascii::<32>("GeoKeyDirectoryTag"))?;
+    out.write_all(&vlr_data)?;
+
+    for (s, &(x, y)) in soundings.iter().zip(&points) {
+        let x = ((x - offset_x) / xy_scale).round() as i32;
+        let y = ((y - offset_y) / xy_scale).round() as i32;
+        let z = ((-s.depth_m - offset_z) / Z_SCALE).round() as i32;
+        out.write_all(&x.to_le_bytes())?;
+        out.write_all(&y.to_le_bytes())?;
+        out.write_all(&z.to_le_bytes())?;
+        out.write_all(&0u16.to_le_bytes())?; // intensity
+        out.write_all(&[0b0000_1001])?; // return 1 of 1
+        out.write_all(&[CLASS_GROUND])?;
+        out.write_all(&[0, s.reference.code()])?; // scan angle, user data
+        out.write_all(&0u16.to_le_bytes())?; // point source id
+    }
+
+    Ok(())
+}
+
+/// NUL-padded fixed-width ASCII field
+fn fixed_ascii<const N: usize>(text: &str) -> [u8; N] {
+    let mut field = [0u8; N];
+    let bytes = text.as_bytes();
+    let len = bytes.len().min(N);
+    field[..len].copy_from_slice(&bytes[..len]);
+    field
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::survey::VerticalReference;
+
+    fn u16_at(b: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(b[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(b: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(b[at..at + 4].try_into().unwrap())
+    }
+
+    fn i32_at(b: &[u8], at: usize) -> i32 {
+        i32::from_le_bytes(b[at..at + 4].try_into().unwrap())
+    }
+
+    fn f64_at(b: &[u8], at: usize) -> f64 {
+        f64::from_le_bytes(b[at..at + 8].try_into().unwrap())
+    }
+
+    /// Decode point format 0 records back to `(x, y, z, user data)`
+    fn read_points(b: &[u8]) -> Vec<(f64, f64, f64, u8)> {
+        let offset = u32_at(b, 96) as usize;
+        let count = u32_at(b, 107) as usize;
+        let scale = [f64_at(b, 131), f64_at(b, 139), f64_at(b, 147)];
+        let origin = [f64_at(b, 155), f64_at(b, 163), f64_at(b, 171)];
+        (0..count)
+            .map(|i| {
+                let p = offset + i * POINT_RECORD_LENGTH as usize;
+                let x = i32_at(b, p) as f64 * scale[0] + origin[0];
+                let y = i32_at(b, p + 4) as f64 * scale[1] + origin[1];
+                let z = i32_at(b, p + 8) as f64 * scale[2] + origin[2];
+                (x, y, z, b[p + 17])
+            })
+            .collect()
+    }
+
+    fn soundings() -> Vec<Sounding> {
+        let mut chart = Sounding::new(2.0, 44.6, -63.55, 12.345);
+        chart.reference = VerticalReference::ChartDatum;
+        vec![
+            Sounding::new(0.0, 44.5, -63.5, 3.2),
+            Sounding::new(1.0, 44.5001, -63.5002, 4.75),
+            chart,
+        ]
+    }
+
+    #[test]
+    fn header_describes_points() {
+        let mut buf = Vec::new();
+        write_las_to(&mut buf, &soundings()).unwrap();
+        assert_eq!(&buf[..4], b"LASF");
+        assert_eq!(&buf[24..26], &[1, 2]);
+        assert_eq!(u16_at(&buf, 94), HEADER_SIZE);
+        assert_eq!(u32_at(&buf, 100), 1);
+        assert_eq!(buf[104], 0);
+        assert_eq!(u16_at(&buf, 105), POINT_RECORD_LENGTH);
+        assert_eq!(u32_at(&buf, 107), 3);
+        let offset = u32_at(&buf, 96) as usize;
+        assert_eq!(buf.len(), offset + 3 * POINT_RECORD_LENGTH as usize);
+        // Bounds: max x, min x, max y, min y, max z, min z
+        assert_eq!(f64_at(&buf, 179), -63.5);
+        assert_eq!(f64_at(&buf, 187), -63.55);
+        assert_eq!(f64_at(&buf, 195), 44.6);
+        assert_eq!(f64_at(&buf, 203), 44.5);
+        assert_eq!(f64_at(&buf, 211), -3.2);
+        assert_eq!(f64_at(&buf, 219), -12.345);
+        // GeoKey VLR declares EPSG:4326 as a geographic type
+        let vlr = HEADER_SIZE as usize;
+        assert_eq!(&buf[vlr + 2..vlr + 17], b"LASF_Projection");
+        assert_eq!(u16_at(&buf, vlr + 18), 34735);
+        assert_eq!(u16_at(&buf, vlr + 54 + 22), 4326);
+    }
+
+    #[test]
+    fn points_round_trip_within_scale() {
+        let input = soundings();
+        let mut buf = Vec::new();
+        write_las_to(&mut buf, &input).unwrap();
+        let points = read_points(&buf);
+        assert_eq!(points.len(), input.len());
+        for (s, &(x, y, z, user)) in input.iter().zip(&points) {
+            assert!((x - s.lon).abs() < 1e-7 && (y - s.lat).abs() < 1e-7);
+            assert!((z + s.depth_m).abs() < 1e-3);
+            assert_eq!(user, s.reference.code());
+        }
+    }
+
+    #[test]
+    fn projected_points_are_meters_in_the_declared_zone() {
+        let input = soundings();
+        let crs = Crs::utm_for(44.5, -63.5);
+        let mut buf = Vec::new();
+        write_las_with_crs_to(&mut buf, &input, crs).unwrap();
+        assert_eq!(f64_at(&buf, 131), XY_SCALE_PROJECTED);
+        assert_eq!(u16_at(&buf, HEADER_SIZE as usize + 54 + 16), 3072);
+        assert_eq!(u16_at(&buf, HEADER_SIZE as usize + 54 + 22) as u32, crs.epsg());
+        for (s, &(x, y, _, _)) in input.iter().zip(&read_points(&buf)) {
+            let (lat, lon) = crs.unproject(x, y);
+            assert!((lat - s.lat).abs() < 1e-7 && (lon - s.lon).abs() < 1e-7);
+        }
+    }
+
+    #[test]
+    fn empty_input_writes_a_valid_header() {
+        let mut buf = Vec::new();
+        write_las_to(&mut buf, &[]).unwrap();
+        assert_eq!(u32_at(&buf, 107), 0);
+        assert_eq!(buf.len(), u32_at(&buf, 96) as usize);
+    }
+}
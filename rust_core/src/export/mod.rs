@@ -0,0 +1,17 @@
+// File exporters for survey products
+// src/export/mod.rs
+
+pub mod arrow;
+pub mod chart;
+pub mod dxf;
+pub mod geojson;
+pub mod gpx;
+pub mod jsonl;
+pub mod kml;
+pub mod las;
+pub mod protobuf;
+pub mod report;
+pub mod segy;
+pub mod shapefile;
+pub mod ssf;
+pub mod xyz;
@@ -0,0 +1,452 @@
+// Protocol Buffers encoding of pings and soundings
+// src/export/protobuf.rs
+//
+// Hand-written codec for the messages in proto/sonar.proto, so services
+// using any protobuf runtime can exchange data with this crate.
+
+use crate::survey::{Ping, Sounding, VerticalReference};
+use std::io::{self, Read, Write};
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_FIXED32: u8 = 5;
+
+/// Encode a `Ping` message
+pub fn encode_ping(ping: &Ping) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(64 + ping.samples.len());
+    put_double(&mut buf, 1, ping.timestamp);
+    if ping.channel_id != 0 {
+        put_key(&mut buf, 2, WIRE_VARINT);
+        put_varint(&mut buf, ping.channel_id as u64);
+    }
+    put_double(&mut buf, 3, ping.lat);
+    put_double(&mut buf, 4, ping.lon);
+    put_double(&mut buf, 5, ping.heading_deg);
+    put_double(&mut buf, 6, ping.depth_m);
+    put_double(&mut buf, 7, ping.range_m);
+    if !ping.samples.is_empty() {
+        put_bytes(&mut buf, 8, &ping.samples);
+    }
+    let a = &ping.acoustics;
+    for (field, value) in [
+        (9, ping.cog_deg),
+        (10, ping.sog_knots),
+        (12, ping.stw_knots),
+        (13, a.frequency_khz),
+        (14, a.chirp_khz.map(|c| c.0)),
+        (15, a.chirp_khz.map(|c| c.1)),
+        (16, a.pulse_length_us),
+        (17, a.range_setting_m),
+        (18, a.gain_pct),
+    ] {
+        if let Some(v) = value {
+            put_key(&mut buf, field, WIRE_FIXED64);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    if ping.flags != 0 {
+        put_key(&mut buf, 11, WIRE_VARINT);
+        put_varint(&mut buf, ping.flags as u64);
+    }
+    buf
+}
+
+pub fn decode_ping(data: &[u8]) -> io::Result<Ping> {
+    let mut ping = Ping::default();
+    let mut chirp = (None, None);
+    for field in Fields(data) {
+        match field? {
+            (1, Value::Fixed64(v)) => ping.timestamp = f64::from_bits(v),
+            (2, Value::Varint(v)) => {
+                ping.channel_id = u16::try_from(v).map_err(|_| invalid("channel_id out of range"))?
+            }
+            (3, Value::Fixed64(v)) => ping.lat = f64::from_bits(v),
+            (4, Value::Fixed64(v)) => ping.lon = f64::from_bits(v),
+            (5, Value::Fixed64(v)) => ping.heading_deg = f64::from_bits(v),
+            (6, Value::Fixed64(v)) => ping.depth_m = f64::from_bits(v),
+            (7, Value::Fixed64(v)) => ping.range_m = f64::from_bits(v),
+            (8, Value::Bytes(b)) => ping.samples = b.to_vec(),
+            (9, Value::Fixed64(v)) => ping.cog_deg = Some(f64::from_bits(v)),
+            (10, Value::Fixed64(v)) => ping.sog_knots = Some(f64::from_bits(v)),
+            (11, Value::Varint(v)) => ping.flags = v as u8,
+            (12, Value::Fixed64(v)) => ping.stw_knots = Some(f64::from_bits(v)),
+            (13, Value::Fixed64(v)) => ping.acoustics.frequency_khz = Some(f64::from_bits(v)),
+            (14, Value::Fixed64(v)) => chirp.0 = Some(f64::from_bits(v)),
+            (15, Value::Fixed64(v)) => chirp.1 = Some(f64::from_bits(v)),
+            (16, Value::Fixed64(v)) => ping.acoustics.pulse_length_us = Some(f64::from_bits(v)),
+            (17, Value::Fixed64(v)) => ping.acoustics.range_setting_m = Some(f64::from_bits(v)),
+            (18, Value::Fixed64(v)) => ping.acoustics.gain_pct = Some(f64::from_bits(v)),
+            _ => {} // unknown fields are skipped for forward compatibility
+        }
+    }
+    if let (Some(start), Some(end)) = chirp {
+        ping.acoustics.chirp_khz = Some((start, end));
+    }
+    Ok(ping)
+}
+
+/// Encode a `Sounding` message
+pub fn encode_sounding(sounding: &Sounding) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(45);
+    put_double(&mut buf, 1, sounding.timestamp);
+    put_double(&mut buf, 2, sounding.lat);
+    put_double(&mut buf, 3, sounding.lon);
+    put_double(&mut buf, 4, sounding.depth_m);
+    put_double(&mut buf, 5, sounding.heading_deg);
+    if sounding.reference.code() != 0 {
+        put_key(&mut buf, 6, WIRE_VARINT);
+        put_varint(&mut buf, sounding.reference.code() as u64);
+    }
+    buf
+}
+
+pub fn decode_sounding(data: &[u8]) -> io::Result<Sounding> {
+    let mut sounding = Sounding::default();
+    for field in Fields(data) {
+        match field? {
+            (1, Value::Fixed64(v)) => sounding.timestamp = f64::from_bits(v),
+            (2, Value::Fixed64(v)) => sounding.lat = f64::from_bits(v),
+            (3, Value::Fixed64(v)) => sounding.lon = f64::from_bits(v),
+            (4, Value::Fixed64(v)) => sounding.depth_m = f64::from_bits(v),
+            (5, Value::Fixed64(v)) => sounding.heading_deg = f64::from_bits(v),
+            (6, Value::Varint(v)) => {
+                sounding.reference =
+                    VerticalReference::from_code(v).ok_or_else(|| invalid("unknown vertical reference"))?
+            }
+            _ => {}
+        }
+    }
+    Ok(sounding)
+}
+
+/// Encode a `PingBatch` message
+pub fn encode_ping_batch(pings: &[Ping]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for ping in pings {
+        put_bytes(&mut buf, 1, &encode_ping(ping));
+    }
+    buf
+}
+
+pub fn decode_ping_batch(data: &[u8]) -> io::Result<Vec<Ping>> {
+    let mut pings = Vec::new();
+    for field in Fields(data) {
+        if let (1, Value::Bytes(b)) = field? {
+            pings.push(decode_ping(b)?);
+        }
+    }
+    Ok(pings)
+}
+
+/// Write a message prefixed with its varint length (the `writeDelimitedTo` framing)
+pub fn write_delimited<W: Write>(out: &mut W, message: &[u8]) -> io::Result<()> {
+    let mut prefix = Vec::with_capacity(10);
+    put_varint(&mut prefix, message.len() as u64);
+    out.write_all(&prefix)?;
+    out.write_all(message)
+}
+
+/// Read one length-prefixed message; `None` at a clean end of stream
+pub fn read_delimited<R: Read>(input: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8];
+        if input.read(&mut byte)? == 0 {
+            return if shift == 0 {
+                Ok(None)
+            } else {
+                Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated length prefix"))
+            };
+        }
+        len |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            // Grow with the data rather than trusting the prefix for the allocation
+            let mut message = Vec::new();
+            input.take(len).read_to_end(&mut message)?;
+            if (message.len() as u64) < len {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated message"));
+            }
+            return Ok(Some(message));
+        }
+    }
+    Err(invalid("length prefix too long"))
+}
+
+fn put_key(buf: &mut Vec<u8>, field: u32, wire_type: u8) {
+    put_varint(buf, ((field as u64) << 3) | wire_type as u64);
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// proto3 omits fields holding their default value
+fn put_double(buf: &mut Vec<u8>, field: u32, value: f64) {
+    if value.to_bits() != 0 {
+        put_key(buf, field, WIRE_FIXED64);
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_key(buf, field, WIRE_LEN);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32,
+}
+
+/// Iterator over `(field number, value)` pairs of an encoded message
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for (i, &byte) in self.0.iter().enumerate().take(10) {
+            value |= ((byte & 0x7f) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                self.0 = &self.0[i + 1..];
+                return Ok(value);
+            }
+        }
+        Err(invalid("truncated or overlong varint"))
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.0.len() {
+            return Err(invalid("field runs past end of message"));
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn field(&mut self) -> io::Result<(u32, Value<'a>)> {
+        let key = self.varint()?;
+        let field = u32::try_from(key >> 3).map_err(|_| invalid("field number out of range"))?;
+        let value = match (key & 7) as u8 {
+            WIRE_VARINT => Value::Varint(self.varint()?),
+            WIRE_FIXED64 => Value::Fixed64(u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes"))),
+            WIRE_LEN => {
+                let len = usize::try_from(self.varint()?).map_err(|_| invalid("length out of range"))?;
+                Value::Bytes(self.take(len)?)
+            }
+            WIRE_FIXED32 => {
+                self.take(4)?;
+                Value::Fixed32
+            }
+            other => return Err(invalid(&format!("unsupported wire type {}", other))),
+        };
+        Ok((field, value))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = io::Result<(u32, Value<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let result = self.field();
+        if result.is_err() {
+            // Stop after the first error rather than reparsing garbage
+            self.0 = &[];
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::survey::{Acoustics, Sensors};
+
+    fn full_ping() -> Ping {
+        Ping {
+            timestamp: 1_700_000_000.25,
+            channel_id: 3,
+            lat: 44.5,
+            lon: -63.5,
+            heading_deg: 271.5,
+            depth_m: 12.3,
+            range_m: 40.0,
+            samples: (0..=255).collect(),
+            cog_deg: Some(270.0),
+            sog_knots: Some(4.2),
+            stw_knots: Some(3.9),
+            acoustics: Acoustics {
+                frequency_khz: Some(200.0),
+                chirp_khz: Some((150.0, 240.0)),
+                pulse_length_us: Some(350.0),
+                range_setting_m: Some(50.0),
+                gain_pct: Some(65.0),
+            },
+            sensors: Sensors::default(),
+            flags: 0b101,
+        }
+    }
+
+    #[test]
+    fn ping_round_trips() {
+        let ping = full_ping();
+        assert_eq!(decode_ping(&encode_ping(&ping)).unwrap(), ping);
+        // Defaults are omitted entirely, as proto3 does
+        assert!(encode_ping(&Ping::default()).is_empty());
+        assert_eq!(decode_ping(&[]).unwrap(), Ping::default());
+    }
+
+    #[test]
+    fn sounding_round_trips() {
+        let mut sounding = Sounding::new(1_700_000_000.0, 44.5, -63.5, 7.25);
+        sounding.heading_deg = 90.0;
+        for reference in [VerticalReference::Transducer, VerticalReference::ChartDatum] {
+            sounding.reference = reference;
+            assert_eq!(decode_sounding(&encode_sounding(&sounding)).unwrap(), sounding);
+        }
+    }
+
+    #[test]
+    fn encoding_matches_the_wire_format() {
+        let ping = Ping {
+            channel_id: 300,
+            samples: vec![7, 8],
+            ..Ping::default()
+        };
+        // field 2 varint 300, field 8 length-delimited [7, 8]
+        assert_eq!(encode_ping(&ping), [0x10, 0xac, 0x02, 0x42, 0x02, 7, 8]);
+        let sounding = Sounding::new(0.0, 0.0, 0.0, 1.0);
+        let mut expected = vec![0x21];
+        expected.extend_from_slice(&1.0f64.to_le_bytes());
+        assert_eq!(encode_sounding(&sounding), expected);
+    }
+
+    #[test]
+    fn acoustics_use_their_own_fields() {
+        let ping = Ping {
+            acoustics: Acoustics {
+                frequency_khz: Some(455.0),
+                gain_pct: Some(40.0),
+                ..Default::default()
+            },
+            ..Ping::default()
+        };
+        // field 13 and field 18 as fixed64 doubles
+        let mut expected = vec![0x69];
+        expected.extend_from_slice(&455.0f64.to_le_bytes());
+        expected.extend_from_slice(&[0x91, 0x01]);
+        expected.extend_from_slice(&40.0f64.to_le_bytes());
+        assert_eq!(encode_ping(&ping), expected);
+
+        // A sweep needs both ends; a lone start frequency is dropped
+        let mut lone_start = vec![0x71];
+        lone_start.extend_from_slice(&150.0f64.to_le_bytes());
+        assert_eq!(decode_ping(&lone_start).unwrap().acoustics.chirp_khz, None);
+    }
+
+    #[test]
+    fn skips_unknown_fields() {
+        let mut data = encode_ping(&full_ping());
+        put_key(&mut data, 99, WIRE_VARINT);
+        put_varint(&mut data, 12345);
+        put_bytes(&mut data, 100, b"future");
+        put_key(&mut data, 101, WIRE_FIXED32);
+        data.extend_from_slice(&[1, 2, 3, 4]);
+        put_key(&mut data, 102, WIRE_FIXED64);
+        data.extend_from_slice(&[0; 8]);
+        assert_eq!(decode_ping(&data).unwrap(), full_ping());
+    }
+
+    #[test]
+    fn batch_and_delimited_stream_round_trip() {
+        let mut second = full_ping();
+        second.channel_id = 0;
+        second.samples.clear();
+        let pings = vec![full_ping(), second, Ping::default()];
+        assert_eq!(decode_ping_batch(&encode_ping_batch(&pings)).unwrap(), pings);
+
+        let mut stream = Vec::new();
+        for ping in &pings {
+            write_delimited(&mut stream, &encode_ping(ping)).unwrap();
+        }
+        let mut input = &stream[..];
+        let mut decoded = Vec::new();
+        while let Some(message) = read_delimited(&mut input).unwrap() {
+            decoded.push(decode_ping(&message).unwrap());
+        }
+        assert_eq!(decoded, pings);
+    }
+
+    #[test]
+    fn rejects_malformed_messages() {
+        let data = encode_ping(&full_ping());
+        for cut in [1, 5, data.len() - 1] {
+            assert_eq!(
+                decode_ping(&data[..cut]).unwrap_err().kind(),
+                io::ErrorKind::InvalidData
+            );
+        }
+        let cases: [&[u8]; 6] = [
+            // truncated varint
+            &[0x10, 0x80],
+            // channel 65536 out of range
+            &[0x10, 0x80, 0x80, 0x04],
+            // wire type 3 (groups)
+            &[0x0b],
+            // bytes past the end
+            &[0x42, 0x05, 1, 2],
+            // overlong varint
+            &[0x10, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            // short fixed64
+            &[0x11, 0, 0, 0],
+        ];
+        for data in cases {
+            assert!(decode_ping(data).is_err(), "{:02x?} accepted", data);
+        }
+        assert!(decode_sounding(&[0x30, 0x09]).is_err());
+        assert!(decode_ping_batch(&[0x0a, 0x02, 0x10, 0x80]).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_streams() {
+        let mut stream = Vec::new();
+        write_delimited(&mut stream, &encode_ping(&full_ping())).unwrap();
+        let mut input = &stream[..stream.len() - 1];
+        assert_eq!(
+            read_delimited(&mut input).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        let mut input: &[u8] = &[0x80];
+        assert_eq!(
+            read_delimited(&mut input).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        // A huge length prefix fails on the missing data instead of allocating it
+        let mut input: &[u8] = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f, 1];
+        assert_eq!(
+            read_delimited(&mut input).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        let mut input: &[u8] = &[0xff; 11];
+        assert_eq!(
+            read_delimited(&mut input).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        let mut empty: &[u8] = &[];
+        assert!(read_delimited(&mut empty).unwrap().is_none());
+    }
+}
@@ -0,0 +1,378 @@
+// Self-contained HTML summary report of one recording
+// src/export/report.rs
+
+use super::gpx::escape_xml;
+use crate::batch::{read_recording, RecordingSummary};
+use crate::expr::Field;
+use crate::geo::distance_m;
+use crate::imaging::palette::ImagingSettings;
+use crate::imaging::png::{write_png, PngColor};
+use crate::imaging::quicklook::{render_depth_profile, render_track_map};
+use crate::imaging::waterfall::{channel_pings, render_waterfall};
+use crate::parsers::FormatRegistry;
+use crate::stats::field_stats;
+use crate::survey::Ping;
+use crate::timezone::TimeZone;
+use crate::validate::{validate, ValidationReport};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:4px 8px;text-align:left}img{image-rendering:pixelated;border:1px solid #ccc;margin:4px}.ok{color:#080}.bad{color:#b00}";
+
+/// Image sizes in pixels and the zone times are shown in
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportConfig {
+    pub map_size: usize,
+    pub profile_width: usize,
+    pub profile_height: usize,
+    /// Width of each channel's waterfall snippet
+    pub waterfall_width: usize,
+    /// Pings shown per snippet, evenly spaced over the recording
+    pub waterfall_rows: usize,
+    pub time_zone: TimeZone,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            map_size: 320,
+            profile_width: 640,
+            profile_height: 160,
+            waterfall_width: 256,
+            waterfall_rows: 320,
+            time_zone: TimeZone::Utc,
+        }
+    }
+}
+
+/// Read and validate a recording and write its report to `out_path`
+///
+/// The page embeds its images as data URIs so it can be mailed or archived
+/// as one file; print it from a browser for a PDF.
+pub fn write_report<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    out_path: Q,
+    registry: &FormatRegistry,
+    config: &ReportConfig,
+    settings: &ImagingSettings,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let (format, pings) = read_recording(path, registry)?;
+    let summary = RecordingSummary::from_pings(path, &format, &pings);
+    let validation = validate(path, registry);
+    let mut out = BufWriter::new(File::create(out_path)?);
+    write_report_to(&mut out, &summary, &pings, &validation, config, settings)?;
+    out.flush()
+}
+
+pub fn write_report_to<W: Write>(
+    out: &mut W,
+    summary: &RecordingSummary,
+    pings: &[Ping],
+    validation: &ValidationReport,
+    config: &ReportConfig,
+    settings: &ImagingSettings,
+) -> io::Result<()> {
+    let name = summary.path.file_name().unwrap_or_default().to_string_lossy();
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(
+        out,
+        "<html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head><body>",
+        escape_xml(&name),
+        STYLE
+    )?;
+    writeln!(out, "<h1>{}</h1>", escape_xml(&name))?;
+
+    writeln!(out, "<h2>Summary</h2><table>")?;
+    for (label, value) in summary_rows(summary, pings, &config.time_zone) {
+        writeln!(out, "<tr><th>{}</th><td>{}</td></tr>", label, escape_xml(&value))?;
+    }
+    writeln!(out, "</table>")?;
+
+    writeln!(out, "<h2>Track and depth</h2>")?;
+    image(out, "Track", &render_track_map(pings, config.map_size).to_png()?)?;
+    image(
+        out,
+        "Depth profile",
+        &render_depth_profile(pings, config.profile_width, config.profile_height).to_png()?,
+    )?;
+
+    let with_samples: Vec<u16> = summary
+        .channels
+        .iter()
+        .copied()
+        .filter(|&c| pings.iter().any(|p| p.channel_id == c && !p.samples.is_empty()))
+        .collect();
+    if !with_samples.is_empty() {
+        writeln!(out, "<h2>Waterfalls</h2>")?;
+        for channel in with_samples {
+            let selected = channel_pings(pings, channel);
+            let step = selected.len().div_ceil(config.waterfall_rows.max(1)).max(1);
+            let snippet: Vec<&Ping> = selected.into_iter().step_by(step).collect();
+            let waterfall = render_waterfall(&snippet, config.waterfall_width.max(1), &settings.levels(channel));
+            let mut png = Vec::new();
+            write_png(
+                &mut png,
+                waterfall.width,
+                waterfall.height,
+                PngColor::Rgb,
+                &settings.palette.colorize(&waterfall.pixels),
+            )?;
+            image(out, &format!("Channel {}", channel), &png)?;
+        }
+    }
+
+    write_validation(out, validation, &config.time_zone)?;
+    writeln!(out, "</body></html>")
+}
+
+/// Compact `<div>` with the track mini-map beside the summary table, e.g. for notebook display
+///
+/// Times are shown in UTC.
+pub fn summary_card(summary: &RecordingSummary, pings: &[Ping], map_size: usize) -> io::Result<String> {
+    let mut out = Vec::new();
+    let name = summary.path.file_name().unwrap_or_default().to_string_lossy();
+    writeln!(out, "<div style=\"display:flex;gap:1em;align-items:flex-start\">")?;
+    image(&mut out, "Track", &render_track_map(pings, map_size).to_png()?)?;
+    writeln!(out, "<table><tr><th colspan=\"2\">{}</th></tr>", escape_xml(&name))?;
+    for (label, value) in summary_rows(summary, pings, &TimeZone::Utc) {
+        writeln!(out, "<tr><th>{}</th><td>{}</td></tr>", label, escape_xml(&value))?;
+    }
+    writeln!(out, "</table></div>")?;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+fn summary_rows(summary: &RecordingSummary, pings: &[Ping], zone: &TimeZone) -> Vec<(&'static str, String)> {
+    let time = |t: f64| zone.format_time(t).unwrap_or_else(|| "-".to_string());
+    let mut rows = vec![
+        ("Format", summary.format.clone()),
+        ("Pings", summary.ping_count.to_string()),
+        (
+            "Channels",
+            summary
+                .channels
+                .iter()
+                .map(u16::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        ("Start", time(summary.start_time)),
+        ("End", time(summary.end_time)),
+        (
+            "Duration",
+            format!("{:.0} s", (summary.end_time - summary.start_time).max(0.0)),
+        ),
+        ("Track length", format!("{:.0} m", track_length_m(pings))),
+    ];
+    if let Some((min_lat, min_lon, max_lat, max_lon)) = summary.bbox {
+        rows.push((
+            "Bounds",
+            format!("{:.6}, {:.6} to {:.6}, {:.6}", min_lat, min_lon, max_lat, max_lon),
+        ));
+    }
+    let depths = field_stats(pings.iter().filter(|p| p.depth_m > 0.0), Field::DepthM, 1);
+    if depths.count > 0 {
+        rows.push((
+            "Depth",
+            format!(
+                "{:.2} to {:.2} m, mean {:.2} m (sd {:.2})",
+                depths.min, depths.max, depths.mean, depths.stddev
+            ),
+        ));
+    }
+    rows
+}
+
+/// Distance along positioned pings, one channel's worth
+fn track_length_m(pings: &[Ping]) -> f64 {
+    let Some(channel) = pings.iter().map(|p| p.channel_id).min() else {
+        return 0.0;
+    };
+    let track: Vec<&Ping> = pings
+        .iter()
+        .filter(|p| p.channel_id == channel && (p.lat != 0.0 || p.lon != 0.0))
+        .collect();
+    track
+        .windows(2)
+        .map(|w| distance_m(w[0].lat, w[0].lon, w[1].lat, w[1].lon))
+        .sum()
+}
+
+fn write_validation<W: Write>(out: &mut W, report: &ValidationReport, zone: &TimeZone) -> io::Result<()> {
+    let (class, verdict) = if report.is_valid() {
+        ("ok", "passed")
+    } else {
+        ("bad", "issues found")
+    };
+    writeln!(
+        out,
+        "<h2>QC</h2><p class=\"{}\">Validation {}</p><table>",
+        class, verdict
+    )?;
+    for (label, value) in [
+        ("Skipped inputs", report.skipped_inputs),
+        ("Clock regressions", report.clock_regressions),
+        ("Invalid positions", report.invalid_positions),
+        ("Suspicious values", report.suspicious_values),
+        ("Unpositioned", report.unpositioned),
+    ] {
+        writeln!(out, "<tr><th>{}</th><td>{}</td></tr>", label, value)?;
+    }
+    writeln!(out, "</table>")?;
+    if let Some(err) = &report.error {
+        writeln!(out, "<p class=\"bad\">{}</p>", escape_xml(err))?;
+    }
+    if !report.issues.is_empty() {
+        writeln!(out, "<ul>")?;
+        for issue in &report.issues {
+            let at = match (issue.line, issue.timestamp.and_then(|t| zone.format_time(t))) {
+                (Some(line), _) => format!("line {}: ", line),
+                (None, Some(time)) => format!("{}: ", time),
+                (None, None) => String::new(),
+            };
+            writeln!(
+                out,
+                "<li>{}{} ({})</li>",
+                escape_xml(&at),
+                escape_xml(&issue.message),
+                issue.kind.name()
+            )?;
+        }
+        writeln!(out, "</ul>")?;
+    }
+    Ok(())
+}
+
+fn image<W: Write>(out: &mut W, alt: &str, png: &[u8]) -> io::Result<()> {
+    writeln!(
+        out,
+        "<img alt=\"{}\" title=\"{}\" src=\"data:image/png;base64,{}\">",
+        escape_xml(alt),
+        escape_xml(alt),
+        base64(png)
+    )
+}
+
+fn base64(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::warnings::{ParseWarning, WarningKind};
+    use std::path::PathBuf;
+
+    fn pings() -> Vec<Ping> {
+        let mut pings: Vec<Ping> = (0..4)
+            .map(|i| Ping {
+                timestamp: 1_700_000_000.0 + i as f64 * 30.0,
+                lat: 44.6 + i as f64 * 0.001,
+                lon: -63.5,
+                depth_m: 2.0 + i as f64 * 2.0,
+                ..Default::default()
+            })
+            .collect();
+        pings.push(Ping {
+            channel_id: 1,
+            timestamp: 1_700_000_000.0,
+            range_m: 10.0,
+            samples: vec![90; 16],
+            ..Default::default()
+        });
+        pings
+    }
+
+    fn summary(pings: &[Ping]) -> RecordingSummary {
+        RecordingSummary::from_pings(&PathBuf::from("/cards/run <2>.csv"), "deeper-csv", pings)
+    }
+
+    #[test]
+    fn report_lists_summary_images_and_qc() {
+        let pings = pings();
+        let validation = ValidationReport {
+            records: 5,
+            clock_regressions: 1,
+            issues: vec![ParseWarning {
+                kind: WarningKind::ClockRegression,
+                line: None,
+                timestamp: Some(1_700_000_060.0),
+                message: "channel 0 clock went back 2.000 s".to_string(),
+            }],
+            ..Default::default()
+        };
+        let config = ReportConfig {
+            time_zone: TimeZone::Fixed(3600),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_report_to(&mut out, &summary(&pings), &pings, &validation, &config, &ImagingSettings::default()).unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(html.contains("<title>run &lt;2&gt;.csv</title>"));
+        for row in [
+            "<tr><th>Format</th><td>deeper-csv</td></tr>",
+            "<tr><th>Pings</th><td>5</td></tr>",
+            "<tr><th>Channels</th><td>0, 1</td></tr>",
+            "<tr><th>Start</th><td>2023-11-14T23:13:20.000+01:00</td></tr>",
+            "<tr><th>Duration</th><td>90 s</td></tr>",
+            "<tr><th>Track length</th><td>334 m</td></tr>",
+            "<tr><th>Bounds</th><td>44.600000, -63.500000 to 44.603000, -63.500000</td></tr>",
+            "<tr><th>Depth</th><td>2.00 to 8.00 m, mean 5.00 m",
+        ] {
+            assert!(html.contains(row), "missing {}", row);
+        }
+        // Track, depth profile and a waterfall only for the channel with samples
+        assert_eq!(html.matches("src=\"data:image/png;base64,iVBORw0KGgo").count(), 3);
+        assert!(html.contains("alt=\"Channel 1\"") && !html.contains("alt=\"Channel 0\""));
+        assert!(html.contains("<p class=\"bad\">Validation issues found</p>"));
+        assert!(html.contains("<tr><th>Clock regressions</th><td>1</td></tr>"));
+        assert!(html.contains(
+            "<li>2023-11-14T23:14:20.000+01:00: channel 0 clock went back 2.000 s (clock_regression)</li>"
+        ));
+        assert!(html.ends_with("</body></html>\n"));
+    }
+
+    #[test]
+    fn summary_card_shows_the_map_beside_utc_rows() {
+        let pings = pings();
+        let card = summary_card(&summary(&pings), &pings, 64).unwrap();
+        assert!(card.starts_with("<div style=\"display:flex;gap:1em;align-items:flex-start\">\n<img alt=\"Track\""));
+        assert_eq!(card.matches("<img ").count(), 1);
+        assert!(card.contains("<table><tr><th colspan=\"2\">run &lt;2&gt;.csv</th></tr>"));
+        assert!(card.contains("<tr><th>End</th><td>2023-11-14T22:14:50.000Z</td></tr>"));
+        assert!(card.ends_with("</table></div>\n"));
+
+        // The embedded map is the requested size
+        let start = card.find("base64,").unwrap() + 7;
+        let encoded = &card[start..start + 32];
+        let header = base64(&render_track_map(&pings, 64).to_png().unwrap()[..24]);
+        assert_eq!(encoded, &header[..32]);
+    }
+
+    #[test]
+    fn base64_pads_partial_groups() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(&[0xFB, 0xFF]), "+/8=");
+        assert_eq!(base64(b""), "");
+    }
+}
@@ -0,0 +1,243 @@
+// SEG-Y (rev 1) export of single-channel sonar returns
+// src/export/segy.rs
+
+use crate::survey::Ping;
+use chrono::{DateTime, Datelike, Timelike};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Nominal sound speed used to convert sample spacing into two-way time
+pub const SOUND_SPEED_M_S: f64 = 1500.0;
+
+/// Coordinates are stored as arc-seconds divided by this factor
+const COORD_SCALAR: i16 = -100;
+/// Depths are stored in centimeters
+const ELEVATION_SCALAR: i16 = -100;
+
+/// Write one channel's pings as SEG-Y traces (IEEE float samples, navigation in trace headers)
+///
+/// Traces are padded or truncated to the longest ping so the file uses a
+/// fixed trace length. Returns the number of traces written.
+pub fn write_segy<P: AsRef<Path>>(path: P, pings: &[&Ping]) -> io::Result<usize> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let count = write_segy_to(&mut out, pings)?;
+    out.flush()?;
+    Ok(count)
+}
+
+pub fn write_segy_to<W: Write>(out: &mut W, pings: &[&Ping]) -> io::Result<usize> {
+    let first = pings
+        .first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no pings to export"))?;
+    let samples = pings.iter().map(|p| p.samples.len()).max().unwrap_or(0);
+    let samples_u16 = u16::try_from(samples)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many samples per trace for SEG-Y"))?;
+    let interval_us = (2.0 * first.sample_spacing_m() / SOUND_SPEED_M_S * 1e6).round() as u16;
+
+    out.write_all(&textual_header(first, samples, interval_us))?;
+
+    let mut binary = [0u8; 400];
+    put_u16(&mut binary, 12, 1); // traces per ensemble
+    put_u16(&mut binary, 16, interval_us);
+    put_u16(&mut binary, 20, samples_u16);
+    put_u16(&mut binary, 24, 5); // 4-byte IEEE float
+    put_u16(&mut binary, 54, 1); // meters
+    put_u16(&mut binary, 300, 0x0100); // SEG-Y revision 1
+    put_u16(&mut binary, 302, 1); // fixed length traces
+    out.write_all(&binary)?;
+
+    for (i, ping) in pings.iter().enumerate() {
+        let seq = i as u32 + 1;
+        let mut header = [0u8; 240];
+        put_u32(&mut header, 0, seq);
+        put_u32(&mut header, 4, seq);
+        put_u32(&mut header, 8, seq);
+        put_u32(&mut header, 12, 1);
+        put_u16(&mut header, 28, 1); // seismic data
+        put_i32(&mut header, 60, (ping.depth_m * 100.0).round() as i32);
+        put_i16(&mut header, 68, ELEVATION_SCALAR);
+        put_i16(&mut header, 70, COORD_SCALAR);
+        let x = arc_seconds(ping.lon);
+        let y = arc_seconds(ping.lat);
+        put_i32(&mut header, 72, x);
+        put_i32(&mut header, 76, y);
+        put_i32(&mut header, 80, x);
+        put_i32(&mut header, 84, y);
+        put_u16(&mut header, 88, 2); // coordinate units: seconds of arc
+        put_u16(&mut header, 114, samples_u16);
+        put_u16(&mut header, 116, interval_us);
+        if let Some(time) = DateTime::from_timestamp(ping.timestamp.floor() as i64, 0) {
+            put_u16(&mut header, 156, time.year() as u16);
+            put_u16(&mut header, 158, time.ordinal() as u16);
+            put_u16(&mut header, 160, time.hour() as u16);
+            put_u16(&mut header, 162, time.minute() as u16);
+            put_u16(&mut header, 164, time.second() as u16);
+            put_u16(&mut header, 166, 4); // UTC
+        }
+        out.write_all(&header)?;
+
+        for k in 0..samples {
+            let value = ping.samples.get(k).copied().unwrap_or(0) as f32;
+            out.write_all(&value.to_be_bytes())?;
+        }
+    }
+
+    Ok(pings.len())
+}
+
+fn arc_seconds(degrees: f64) -> i32 {
+    (degrees * 3600.0 * -(COORD_SCALAR as f64)).round() as i32
+}
+
+fn put_u16(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+fn put_i16(buf: &mut [u8], offset: usize, value: i16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+fn put_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+}
+
+fn put_i32(buf: &mut [u8], offset: usize, value: i32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+}
+
+/// 40 EBCDIC card images describing the export
+fn textual_header(first: &Ping, samples: usize, interval_us: u16) -> Vec<u8> {
+    let lines = [
+        "SONARSNIFFER SEG-Y EXPORT OF RECREATIONAL SONAR RETURNS".to_string(),
+        format!("CHANNEL {}", first.channel_id),
+        format!("SAMPLES PER TRACE {}  SAMPLE INTERVAL {} US", samples, interval_us),
+        format!("TWO-WAY TIME FROM RANGE AT {} M/S", SOUND_SPEED_M_S),
+        "SAMPLE FORMAT 5 (IEEE FLOAT), 8-BIT INTENSITY VALUES".to_string(),
+        "SOURCE X/Y: LON/LAT IN ARC-SECONDS, SCALAR -100".to_string(),
+        "WATER DEPTH AT SOURCE IN CM (BYTES 61-64)".to_string(),
+    ];
+
+    let mut text = Vec::with_capacity(3200);
+    for card in 0..40 {
+        let body = match card {
+            39 => "END TEXTUAL HEADER".to_string(),
+            _ => lines.get(card).cloned().unwrap_or_default(),
+        };
+        let line = format!("C{:2} {:<76}", card + 1, body);
+        text.extend(line.bytes().take(80).map(ascii_to_ebcdic));
+    }
+    text
+}
+
+/// ASCII to EBCDIC (code page 037) for the characters used in card images
+fn ascii_to_ebcdic(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => 0xF0 + (c - b'0'),
+        b'A'..=b'I' => 0xC1 + (c - b'A'),
+        b'J'..=b'R' => 0xD1 + (c - b'J'),
+        b'S'..=b'Z' => 0xE2 + (c - b'S'),
+        b'a'..=b'z' => ascii_to_ebcdic(c.to_ascii_uppercase()),
+        b'.' => 0x4B,
+        b'(' => 0x4D,
+        b')' => 0x5D,
+        b',' => 0x6B,
+        b'-' => 0x60,
+        b'/' => 0x61,
+        b':' => 0x7A,
+        _ => 0x40,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(b: &[u8], at: usize) -> u16 {
+        u16::from_be_bytes([b[at], b[at + 1]])
+    }
+
+    fn i32_at(b: &[u8], at: usize) -> i32 {
+        i32::from_be_bytes(b[at..at + 4].try_into().unwrap())
+    }
+
+    fn ping(timestamp: f64, samples: Vec<u8>) -> Ping {
+        Ping {
+            timestamp,
+            channel_id: 2,
+            lat: 44.6488,
+            lon: -63.5752,
+            depth_m: 12.34,
+            range_m: 30.0,
+            samples,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn headers_describe_fixed_length_float_traces() {
+        let long = ping(1_700_000_000.0, (0..200).map(|i| i as u8).collect());
+        let short = ping(1_700_000_001.5, vec![9; 50]);
+        let mut buf = Vec::new();
+        assert_eq!(write_segy_to(&mut buf, &[&long, &short]).unwrap(), 2);
+        assert_eq!(buf.len(), 3600 + 2 * (240 + 4 * 200));
+
+        let text: Vec<u8> = "C 1 SONARSNIFFER".bytes().map(ascii_to_ebcdic).collect();
+        assert_eq!(&buf[..text.len()], &text[..]);
+        assert!(buf[..3200].iter().all(|&c| c != b' '), "textual header must be EBCDIC");
+
+        let binary = &buf[3200..3600];
+        // 30 m over 200 samples is 0.15 m, i.e. 200 us two-way at 1500 m/s
+        assert_eq!(u16_at(binary, 16), 200);
+        assert_eq!(u16_at(binary, 20), 200);
+        assert_eq!(u16_at(binary, 24), 5);
+        assert_eq!(u16_at(binary, 300), 0x0100);
+        assert_eq!(u16_at(binary, 302), 1);
+    }
+
+    #[test]
+    fn traces_round_trip_navigation_and_samples() {
+        let long = ping(1_700_000_000.0, (0..200).map(|i| i as u8).collect());
+        let short = ping(1_700_000_001.5, vec![9; 50]);
+        let mut buf = Vec::new();
+        write_segy_to(&mut buf, &[&long, &short]).unwrap();
+
+        let trace_len = 240 + 4 * 200;
+        for (i, p) in [&long, &short].into_iter().enumerate() {
+            let header = &buf[3600 + i * trace_len..3600 + i * trace_len + 240];
+            assert_eq!(i32_at(header, 0), i as i32 + 1);
+            assert_eq!(i32_at(header, 60), 1234);
+            let scalar = i16::from_be_bytes([header[70], header[71]]) as f64;
+            assert_eq!(scalar, -100.0);
+            let lon = i32_at(header, 72) as f64 / -scalar / 3600.0;
+            let lat = i32_at(header, 76) as f64 / -scalar / 3600.0;
+            assert!((lon - p.lon).abs() < 1e-6 && (lat - p.lat).abs() < 1e-6);
+            assert_eq!(u16_at(header, 156), 2023);
+            assert_eq!(u16_at(header, 158), 318);
+            assert_eq!((u16_at(header, 160), u16_at(header, 162)), (22, 13));
+            assert_eq!(u16_at(header, 164), 20 + i as u16);
+
+            let samples: Vec<f32> = buf[3600 + i * trace_len + 240..3600 + (i + 1) * trace_len]
+                .chunks(4)
+                .map(|b| f32::from_be_bytes(b.try_into().unwrap()))
+                .collect();
+            for (k, &value) in samples.iter().enumerate() {
+                assert_eq!(value, p.samples.get(k).copied().unwrap_or(0) as f32);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_empty_and_oversized_input() {
+        let mut buf = Vec::new();
+        assert_eq!(
+            write_segy_to(&mut buf, &[]).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        let huge = ping(0.0, vec![0; 70_000]);
+        assert_eq!(
+            write_segy_to(&mut buf, &[&huge]).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
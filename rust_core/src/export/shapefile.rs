@@ -0,0 +1,410 @@
+// ESRI Shapefile export of soundings and tracks
+// src/export/shapefile.rs
+
+use super::gpx::format_time;
+use crate::crs::Crs;
+use crate::survey::Sounding;
+use chrono::{Datelike, Utc};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const SHAPE_POINT: i32 = 1;
+const SHAPE_POLYLINE: i32 = 3;
+const HEADER_BYTES: usize = 100;
+
+/// dBASE column: name (max 10 chars), type (`C` or `N`), width and decimals
+struct Field {
+    name: &'static str,
+    kind: u8,
+    width: u8,
+    decimals: u8,
+}
+
+const SOUNDING_FIELDS: [Field; 6] = [
+    Field { name: "TIME", kind: b'C', width: 24, decimals: 0 },
+    Field { name: "LAT", kind: b'N', width: 12, decimals: 7 },
+    Field { name: "LON", kind: b'N', width: 12, decimals: 7 },
+    Field { name: "DEPTH_M", kind: b'N', width: 10, decimals: 3 },
+    Field { name: "HEADING", kind: b'N', width: 7, decimals: 2 },
+    Field { name: "VREF", kind: b'C', width: 12, decimals: 0 },
+];
+
+const TRACK_FIELDS: [Field; 4] = [
+    Field { name: "NAME", kind: b'C', width: 32, decimals: 0 },
+    Field { name: "START", kind: b'C', width: 24, decimals: 0 },
+    Field { name: "END", kind: b'C', width: 24, decimals: 0 },
+    Field { name: "POINTS", kind: b'N', width: 10, decimals: 0 },
+];
+
+/// Write soundings as a point shapefile (`.shp`, `.shx`, `.dbf`, `.prj` next to `base`)
+///
+/// Attributes mirror the CSV columns: time, lat, lon, depth and heading,
+/// plus `VREF`, the vertical reference of the depth.
+pub fn write_soundings_shp<P: AsRef<Path>>(base: P, soundings: &[Sounding]) -> io::Result<()> {
+    write_soundings_shp_with_crs(base, soundings, Crs::Wgs84)
+}
+
+/// Point shapefile with geometry in `crs`; the `LAT`/`LON` attributes stay in WGS84
+pub fn write_soundings_shp_with_crs<P: AsRef<Path>>(base: P, soundings: &[Sounding], crs: Crs) -> io::Result<()> {
+    let points: Vec<(f64, f64)> = soundings.iter().map(|s| crs.project(s.lat, s.lon)).collect();
+    let shapes: Vec<Vec<u8>> = points
+        .iter()
+        .map(|(x, y)| {
+            let mut content = SHAPE_POINT.to_le_bytes().to_vec();
+            content.extend_from_slice(&x.to_le_bytes());
+            content.extend_from_slice(&y.to_le_bytes());
+            content
+        })
+        .collect();
+    let rows: Vec<Vec<String>> = soundings
+        .iter()
+        .map(|s| {
+            vec![
+                format_time(s.timestamp).unwrap_or_default(),
+                format!("{:.7}", s.lat),
+                format!("{:.7}", s.lon),
+                format!("{:.3}", s.depth_m),
+                format!("{:.2}", s.heading_deg),
+                s.reference.name().to_string(),
+            ]
+        })
+        .collect();
+    write_shapefile(base.as_ref(), crs, SHAPE_POINT, bbox(&points), &shapes, &SOUNDING_FIELDS, &rows)
+}
+
+/// Write the soundings' path as a single-polyline shapefile
+pub fn write_track_shp<P: AsRef<Path>>(base: P, name: &str, track: &[Sounding]) -> io::Result<()> {
+    write_track_shp_with_crs(base, name, track, Crs::Wgs84)
+}
+
+/// Single-polyline shapefile with geometry in `crs`
+pub fn write_track_shp_with_crs<P: AsRef<Path>>(base: P, name: &str, track: &[Sounding], crs: Crs) -> io::Result<()> {
+    if track.len() < 2 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "a track needs at least two points"));
+    }
+    let count = u32::try_from(track.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many track points"))?;
+    let points: Vec<(f64, f64)> = track.iter().map(|s| crs.project(s.lat, s.lon)).collect();
+    let (min_x, min_y, max_x, max_y) = bbox(&points);
+
+    let mut content = SHAPE_POLYLINE.to_le_bytes().to_vec();
+    for v in [min_x, min_y, max_x, max_y] {
+        content.extend_from_slice(&v.to_le_bytes());
+    }
+    content.extend_from_slice(&1i32.to_le_bytes()); // parts
+    content.extend_from_slice(&count.to_le_bytes());
+    content.extend_from_slice(&0i32.to_le_bytes()); // first part starts at point 0
+    for (x, y) in &points {
+        content.extend_from_slice(&x.to_le_bytes());
+        content.extend_from_slice(&y.to_le_bytes());
+    }
+
+    let row = vec![
+        name.to_string(),
+        format_time(track[0].timestamp).unwrap_or_default(),
+        format_time(track[track.len() - 1].timestamp).unwrap_or_default(),
+        track.len().to_string(),
+    ];
+    write_shapefile(base.as_ref(), crs, SHAPE_POLYLINE, (min_x, min_y, max_x, max_y), &[content], &TRACK_FIELDS, &[row])
+}
+
+/// (min_x, min_y, max_x, max_y) of projected points
+fn bbox(points: &[(f64, f64)]) -> (f64, f64, f64, f64) {
+    if points.is_empty() {
+        return (0.0, 0.0, 0.0, 0.0);
+    }
+    points.iter().fold(
+        (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        |(a, b, c, d), &(x, y)| (a.min(x), b.min(y), c.max(x), d.max(y)),
+    )
+}
+
+fn write_shapefile(
+    base: &Path,
+    crs: Crs,
+    shape_type: i32,
+    bbox: (f64, f64, f64, f64),
+    shapes: &[Vec<u8>],
+    fields: &[Field],
+    rows: &[Vec<String>],
+) -> io::Result<()> {
+    let shp_len = HEADER_BYTES + shapes.iter().map(|s| 8 + s.len()).sum::<usize>();
+    let shx_len = HEADER_BYTES + 8 * shapes.len();
+    if shp_len / 2 > i32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "too much data for a shapefile"));
+    }
+
+    let mut shp = BufWriter::new(File::create(base.with_extension("shp"))?);
+    let mut shx = BufWriter::new(File::create(base.with_extension("shx"))?);
+    write_header(&mut shp, shp_len, shape_type, bbox)?;
+    write_header(&mut shx, shx_len, shape_type, bbox)?;
+
+    // Offsets and lengths are in 16-bit words
+    let mut offset = HEADER_BYTES / 2;
+    for (i, content) in shapes.iter().enumerate() {
+        let words = content.len() / 2;
+        shp.write_all(&(i as i32 + 1).to_be_bytes())?;
+        shp.write_all(&(words as i32).to_be_bytes())?;
+        shp.write_all(content)?;
+        shx.write_all(&(offset as i32).to_be_bytes())?;
+        shx.write_all(&(words as i32).to_be_bytes())?;
+        offset += 4 + words;
+    }
+    shp.flush()?;
+    shx.flush()?;
+
+    let mut dbf = BufWriter::new(File::create(base.with_extension("dbf"))?);
+    write_dbf(&mut dbf, fields, rows)?;
+    dbf.flush()?;
+
+    fs::write(base.with_extension("prj"), crs.wkt())
+}
+
+fn write_header<W: Write>(out: &mut W, file_bytes: usize, shape_type: i32, bbox: (f64, f64, f64, f64)) -> io::Result<()> {
+    out.write_all(&9994i32.to_be_bytes())?;
+    out.write_all(&[0u8; 20])?;
+    out.write_all(&((file_bytes / 2) as i32).to_be_bytes())?;
+    out.write_all(&1000i32.to_le_bytes())?;
+    out.write_all(&shape_type.to_le_bytes())?;
+    for v in [bbox.0, bbox.1, bbox.2, bbox.3, 0.0, 0.0, 0.0, 0.0] {
+        out.write_all(&v.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// dBASE III attribute table
+fn write_dbf<W: Write>(out: &mut W, fields: &[Field], rows: &[Vec<String>]) -> io::Result<()> {
+    let header_len = 32 + 32 * fields.len() + 1;
+    let record_len = 1 + fields.iter().map(|f| f.width as usize).sum::<usize>();
+    let today = Utc::now();
+
+    out.write_all(&[0x03, (today.year() - 1900) as u8, today.month() as u8, today.day() as u8])?;
+    out.write_all(&(rows.len() as u32).to_le_bytes())?;
+    out.write_all(&(header_len as u16).to_le_bytes())?;
+    out.write_all(&(record_len as u16).to_le_bytes())?;
+    out.write_all(&[0u8; 20])?;
+
+    for field in fields {
+        let mut name = [0u8; 11];
+        name[..field.name.len()].copy_from_slice(field.name.as_bytes());
+        out.write_all(&name)?;
+        out.write_all(&[field.kind])?;
+        out.write_all(&[0u8; 4])?;
+        out.write_all(&[field.width, field.decimals])?;
+        out.write_all(&[0u8; 14])?;
+    }
+    out.write_all(&[0x0D])?;
+
+    for row in rows {
+        out.write_all(b" ")?; // not deleted
+        for (field, value) in fields.iter().zip(row) {
+            let width = field.width as usize;
+            let value: String = value.chars().filter(char::is_ascii).take(width).collect();
+            let cell = if field.kind == b'N' {
+                format!("{:>width$}", value, width = width)
+            } else {
+                format!("{:<width$}", value, width = width)
+            };
+            out.write_all(cell.as_bytes())?;
+        }
+    }
+    out.write_all(&[0x1A])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::survey::VerticalReference;
+    use crate::testutil::scratch;
+
+    fn be_i32(b: &[u8], at: usize) -> i32 {
+        i32::from_be_bytes(b[at..at + 4].try_into().unwrap())
+    }
+
+    fn le_i32(b: &[u8], at: usize) -> i32 {
+        i32::from_le_bytes(b[at..at + 4].try_into().unwrap())
+    }
+
+    fn le_f64(b: &[u8], at: usize) -> f64 {
+        f64::from_le_bytes(b[at..at + 8].try_into().unwrap())
+    }
+
+    fn remove(base: &Path) {
+        for ext in ["shp", "shx", "dbf", "prj"] {
+            let _ = fs::remove_file(base.with_extension(ext));
+        }
+    }
+
+    /// Record contents of a `.shp`, checked against its header and `.shx` index
+    fn read_shapes(base: &Path) -> (i32, [f64; 4], Vec<Vec<u8>>) {
+        let shp = fs::read(base.with_extension("shp")).unwrap();
+        let shx = fs::read(base.with_extension("shx")).unwrap();
+        for file in [&shp, &shx] {
+            assert_eq!(be_i32(file, 0), 9994);
+            assert_eq!(be_i32(file, 24) as usize * 2, file.len());
+            assert_eq!(le_i32(file, 28), 1000);
+        }
+        let bbox = [le_f64(&shp, 36), le_f64(&shp, 44), le_f64(&shp, 52), le_f64(&shp, 60)];
+        let mut shapes = Vec::new();
+        let mut at = HEADER_BYTES;
+        while at < shp.len() {
+            let index = HEADER_BYTES + 8 * shapes.len();
+            assert_eq!(be_i32(&shp, at), shapes.len() as i32 + 1);
+            assert_eq!(be_i32(&shx, index) as usize * 2, at);
+            let len = be_i32(&shp, at + 4) as usize * 2;
+            assert_eq!(be_i32(&shx, index + 4) as usize * 2, len);
+            shapes.push(shp[at + 8..at + 8 + len].to_vec());
+            at += 8 + len;
+        }
+        assert_eq!(shx.len(), HEADER_BYTES + 8 * shapes.len());
+        (le_i32(&shp, 32), bbox, shapes)
+    }
+
+    /// Field names and trimmed cell text of a `.dbf`
+    fn read_dbf(base: &Path) -> (Vec<String>, Vec<Vec<String>>) {
+        let dbf = fs::read(base.with_extension("dbf")).unwrap();
+        assert_eq!(dbf[0], 0x03);
+        let count = u32::from_le_bytes(dbf[4..8].try_into().unwrap()) as usize;
+        let header_len = u16::from_le_bytes([dbf[8], dbf[9]]) as usize;
+        let record_len = u16::from_le_bytes([dbf[10], dbf[11]]) as usize;
+        assert_eq!(dbf[header_len - 1], 0x0D);
+        assert_eq!(dbf.len(), header_len + count * record_len + 1);
+        assert_eq!(dbf[dbf.len() - 1], 0x1A);
+
+        let (mut names, mut widths) = (Vec::new(), Vec::new());
+        for field in dbf[32..header_len - 1].chunks(32) {
+            let name = field[..11].split(|&b| b == 0).next().unwrap();
+            names.push(String::from_utf8(name.to_vec()).unwrap());
+            widths.push(field[16] as usize);
+        }
+        assert_eq!(1 + widths.iter().sum::<usize>(), record_len);
+        let rows = (0..count)
+            .map(|i| {
+                let record = &dbf[header_len + i * record_len..][..record_len];
+                assert_eq!(record[0], b' ');
+                let mut at = 1;
+                widths
+                    .iter()
+                    .map(|&w| {
+                        at += w;
+                        String::from_utf8(record[at - w..at].to_vec())
+                            .unwrap()
+                            .trim()
+                            .to_string()
+                    })
+                    .collect()
+            })
+            .collect();
+        (names, rows)
+    }
+
+    fn soundings() -> Vec<Sounding> {
+        let mut datum = Sounding::new(1_700_000_010.0, 44.6, -63.55, 12.345);
+        datum.reference = VerticalReference::ChartDatum;
+        datum.heading_deg = 271.5;
+        vec![Sounding::new(1_700_000_000.0, 44.5, -63.5, 3.2), datum]
+    }
+
+    #[test]
+    fn points_and_attributes_round_trip() {
+        let base = scratch("shp", "points");
+        let input = soundings();
+        write_soundings_shp(&base, &input).unwrap();
+
+        let (shape_type, bbox, shapes) = read_shapes(&base);
+        assert_eq!(shape_type, SHAPE_POINT);
+        assert_eq!(bbox, [-63.55, 44.5, -63.5, 44.6]);
+        assert_eq!(shapes.len(), 2);
+        for (shape, s) in shapes.iter().zip(&input) {
+            assert_eq!(shape.len(), 20);
+            assert_eq!(le_i32(shape, 0), SHAPE_POINT);
+            assert_eq!((le_f64(shape, 4), le_f64(shape, 12)), (s.lon, s.lat));
+        }
+
+        let (names, rows) = read_dbf(&base);
+        assert_eq!(names, ["TIME", "LAT", "LON", "DEPTH_M", "HEADING", "VREF"]);
+        assert_eq!(
+            rows[1][1..],
+            ["44.6000000", "-63.5500000", "12.345", "271.50", "chart_datum"]
+        );
+        assert_eq!(rows[0][5], "transducer");
+        assert_eq!(rows[0][0], format_time(1_700_000_000.0).unwrap());
+        assert!(fs::read_to_string(base.with_extension("prj"))
+            .unwrap()
+            .starts_with("GEOGCS["));
+        remove(&base);
+    }
+
+    #[test]
+    fn projected_geometry_keeps_wgs84_attributes() {
+        let base = scratch("shp", "utm");
+        let input = soundings();
+        let crs = Crs::utm_for(44.5, -63.5);
+        write_soundings_shp_with_crs(&base, &input, crs).unwrap();
+
+        let (_, _, shapes) = read_shapes(&base);
+        for (shape, s) in shapes.iter().zip(&input) {
+            let (lat, lon) = crs.unproject(le_f64(shape, 4), le_f64(shape, 12));
+            assert!((lat - s.lat).abs() < 1e-7 && (lon - s.lon).abs() < 1e-7);
+        }
+        let (_, rows) = read_dbf(&base);
+        assert_eq!(rows[0][1..3], ["44.5000000", "-63.5000000"]);
+        assert_eq!(fs::read_to_string(base.with_extension("prj")).unwrap(), crs.wkt());
+        remove(&base);
+    }
+
+    #[test]
+    fn track_is_one_polyline() {
+        let base = scratch("shp", "track");
+        let input = soundings();
+        write_track_shp(&base, "Morning run, très long name that overflows", &input).unwrap();
+
+        let (shape_type, bbox, shapes) = read_shapes(&base);
+        assert_eq!(shape_type, SHAPE_POLYLINE);
+        let [shape] = &shapes[..] else {
+            panic!("expected one shape")
+        };
+        assert_eq!(le_i32(shape, 0), SHAPE_POLYLINE);
+        assert_eq!(
+            [
+                le_f64(shape, 4),
+                le_f64(shape, 12),
+                le_f64(shape, 20),
+                le_f64(shape, 28)
+            ],
+            bbox
+        );
+        assert_eq!((le_i32(shape, 36), le_i32(shape, 40), le_i32(shape, 44)), (1, 2, 0));
+        let points: Vec<_> = (0..2)
+            .map(|i| (le_f64(shape, 48 + 16 * i), le_f64(shape, 56 + 16 * i)))
+            .collect();
+        assert_eq!(points, [(-63.5, 44.5), (-63.55, 44.6)]);
+
+        let (names, rows) = read_dbf(&base);
+        assert_eq!(names, ["NAME", "START", "END", "POINTS"]);
+        // Non-ASCII is dropped and long text truncated to the column width
+        assert_eq!(rows[0][0], "Morning run, trs long name that");
+        assert_eq!(rows[0][3], "2");
+        remove(&base);
+    }
+
+    #[test]
+    fn rejects_degenerate_tracks() {
+        let base = scratch("shp", "short");
+        let err = write_track_shp(&base, "t", &soundings()[..1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!base.with_extension("shp").exists());
+    }
+
+    #[test]
+    fn empty_point_file_is_valid() {
+        let base = scratch("shp", "empty");
+        write_soundings_shp(&base, &[]).unwrap();
+        let (_, bbox, shapes) = read_shapes(&base);
+        assert!(shapes.is_empty());
+        assert_eq!(bbox, [0.0; 4]);
+        assert!(read_dbf(&base).1.is_empty());
+        remove(&base);
+    }
+}
@@ -0,0 +1,771 @@
+// SSF: compact column-wise archive of pings
+// src/export/ssf.rs
+//
+// Layout: magic, a little-endian u32 format version, channel table, ping
+// count, then one length-prefixed column per field. Samples are delta-coded
+// within each ping. Optional fields share a per-ping presence mask and store
+// only present values. Columns are byte-aligned so a general-purpose
+// compressor (zstd, xz) applied to the whole file shrinks it further.
+//
+// Float columns are stored losslessly as zigzag varint deltas:
+// - version 1: of consecutive IEEE-754 bit patterns
+// - version 2: a leading byte gives the encoding. 0..=9 means every value
+//   is a decimal with that many places, stored as deltas of the scaled
+//   integers (so GPS positions and depths parsed from text stay small);
+//   255 means bit-pattern deltas as in version 1.
+//
+// Version 3 adds block compression: each column starts with a codec byte,
+// 0 for stored bytes or 1 for a varint raw length followed by LZ77
+// sequences (varint literal count, literals, varint offset, varint match
+// length minus 4, ending after the final literals). A column is only
+// compressed when that makes it smaller. The LZ stage is dependency-free
+// and favours speed over ratio; it is no substitute for zstd or xz on the
+// whole file, but removes the need for one to get compact archives.
+//
+// Readers accept versions 1 to 3. `write_ssf` produces version 2 and
+// `write_ssf_compressed` version 3.
+
+use crate::parsers::{ChannelInfo, ChannelKind};
+use crate::survey::Ping;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+pub const MAGIC: &[u8; 4] = b"SSF\x1a";
+const VERSION: u32 = 2;
+/// Version written with compressed columns
+const COMPRESSED_VERSION: u32 = 3;
+/// Float column tag for IEEE-754 bit-pattern deltas
+const RAW_FLOATS: u8 = 255;
+/// Most decimal places a quantized float column can use
+const MAX_DECIMALS: u8 = 9;
+
+/// Optional ping fields in presence-mask bit order
+const OPTIONAL_FIELDS: usize = 9;
+
+/// Column codec tags of version 3
+const STORED: u8 = 0;
+const LZ: u8 = 1;
+/// Shortest back-reference the LZ stage emits
+const MIN_MATCH: usize = 4;
+const HASH_BITS: u32 = 14;
+/// Farthest back-reference the LZ stage looks for
+const MAX_OFFSET: usize = 1 << 16;
+
+/// Write pings and their channel table to an SSF file
+pub fn write_ssf<P: AsRef<Path>>(path: P, channels: &[ChannelInfo], pings: &[Ping]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_ssf_to(&mut out, channels, pings)?;
+    out.flush()
+}
+
+pub fn write_ssf_to<W: Write>(out: &mut W, channels: &[ChannelInfo], pings: &[Ping]) -> io::Result<()> {
+    write_columns(out, channels, pings, VERSION)
+}
+
+/// Like [`write_ssf`], but with LZ-compressed columns (format version 3)
+pub fn write_ssf_compressed<P: AsRef<Path>>(path: P, channels: &[ChannelInfo], pings: &[Ping]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_ssf_compressed_to(&mut out, channels, pings)?;
+    out.flush()
+}
+
+pub fn write_ssf_compressed_to<W: Write>(out: &mut W, channels: &[ChannelInfo], pings: &[Ping]) -> io::Result<()> {
+    write_columns(out, channels, pings, COMPRESSED_VERSION)
+}
+
+fn write_columns<W: Write>(out: &mut W, channels: &[ChannelInfo], pings: &[Ping], version: u32) -> io::Result<()> {
+    out.write_all(MAGIC)?;
+    out.write_all(&version.to_le_bytes())?;
+
+    let mut table = Vec::new();
+    put_varint(&mut table, channels.len() as u64);
+    for c in channels {
+        put_varint(&mut table, c.channel_id as u64);
+        table.push(kind_code(c.kind));
+        put_varint(&mut table, c.name.len() as u64);
+        table.extend_from_slice(c.name.as_bytes());
+        for v in [c.frequency_khz, c.beam_angle_deg] {
+            table.extend_from_slice(&v.unwrap_or(f64::NAN).to_bits().to_le_bytes());
+        }
+    }
+    put_column(out, &table, version)?;
+    out.write_all(&(pings.len() as u64).to_le_bytes())?;
+
+    let mut channel_ids = Vec::new();
+    let mut previous = 0i64;
+    for p in pings {
+        put_varint(&mut channel_ids, zigzag(p.channel_id as i64 - previous));
+        previous = p.channel_id as i64;
+    }
+    put_column(out, &channel_ids, version)?;
+    for field in [
+        |p: &Ping| p.timestamp,
+        |p: &Ping| p.lat,
+        |p: &Ping| p.lon,
+        |p: &Ping| p.heading_deg,
+        |p: &Ping| p.depth_m,
+        |p: &Ping| p.range_m,
+    ] {
+        put_column(out, &float_column(pings.iter().map(field)), version)?;
+    }
+    put_column(out, &pings.iter().map(|p| p.flags).collect::<Vec<u8>>(), version)?;
+
+    let optionals: Vec<[Option<f64>; OPTIONAL_FIELDS]> = pings.iter().map(optional_values).collect();
+    let mut masks = Vec::new();
+    for values in &optionals {
+        let mask = values
+            .iter()
+            .enumerate()
+            .fold(0u64, |mask, (bit, v)| mask | (v.is_some() as u64) << bit);
+        put_varint(&mut masks, mask);
+    }
+    put_column(out, &masks, version)?;
+    for field in 0..OPTIONAL_FIELDS {
+        put_column(out, &float_column(optionals.iter().filter_map(|v| v[field])), version)?;
+    }
+
+    let mut lengths = Vec::new();
+    let mut samples = Vec::new();
+    for p in pings {
+        put_varint(&mut lengths, p.samples.len() as u64);
+        let mut last = 0u8;
+        for &v in &p.samples {
+            samples.push(v.wrapping_sub(last));
+            last = v;
+        }
+    }
+    put_column(out, &lengths, version)?;
+    put_column(out, &samples, version)
+}
+
+/// Read an SSF file back into its channel table and pings
+pub fn read_ssf<P: AsRef<Path>>(path: P) -> io::Result<(Vec<ChannelInfo>, Vec<Ping>)> {
+    read_ssf_from(&mut BufReader::new(File::open(path)?))
+}
+
+pub fn read_ssf_from<R: Read + ?Sized>(input: &mut R) -> io::Result<(Vec<ChannelInfo>, Vec<Ping>)> {
+    let mut header = [0u8; 8];
+    input.read_exact(&mut header)?;
+    if &header[..4] != MAGIC {
+        return Err(invalid("not an SSF file"));
+    }
+    let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    if !(1..=COMPRESSED_VERSION).contains(&version) {
+        return Err(invalid("unsupported SSF version"));
+    }
+
+    let table = read_column(input, version)?;
+    let mut cursor = Cursor::new(&table);
+    let mut channels = Vec::new();
+    for _ in 0..cursor.varint()? {
+        let channel_id = u16::try_from(cursor.varint()?).map_err(|_| invalid("SSF channel id out of range"))?;
+        let kind = kind_from_code(cursor.byte()?);
+        let len = cursor.varint()? as usize;
+        let name =
+            String::from_utf8(cursor.bytes(len)?.to_vec()).map_err(|_| invalid("SSF channel name is not UTF-8"))?;
+        let mut optional = || cursor.f64().map(|v| (!v.is_nan()).then_some(v));
+        let frequency_khz = optional()?;
+        let beam_angle_deg = optional()?;
+        channels.push(ChannelInfo {
+            channel_id,
+            name,
+            kind,
+            frequency_khz,
+            beam_angle_deg,
+        });
+    }
+
+    let mut count = [0u8; 8];
+    input.read_exact(&mut count)?;
+    let count = usize::try_from(u64::from_le_bytes(count)).map_err(|_| invalid("SSF ping count out of range"))?;
+    let mut pings = Vec::new();
+
+    let column = read_column(input, version)?;
+    let mut cursor = Cursor::new(&column);
+    let mut previous = 0i64;
+    for _ in 0..count {
+        previous += unzigzag(cursor.varint()?);
+        let channel_id = u16::try_from(previous).map_err(|_| invalid("SSF channel id out of range"))?;
+        pings.push(Ping {
+            channel_id,
+            ..Default::default()
+        });
+    }
+    let setters: [fn(&mut Ping, f64); 6] = [
+        |p, v| p.timestamp = v,
+        |p, v| p.lat = v,
+        |p, v| p.lon = v,
+        |p, v| p.heading_deg = v,
+        |p, v| p.depth_m = v,
+        |p, v| p.range_m = v,
+    ];
+    for set in setters {
+        let values = read_floats(input, count, version)?;
+        for (p, v) in pings.iter_mut().zip(values) {
+            set(p, v);
+        }
+    }
+    let flags = read_column(input, version)?;
+    if flags.len() != count {
+        return Err(invalid("SSF flags column has the wrong length"));
+    }
+    for (p, f) in pings.iter_mut().zip(flags) {
+        p.flags = f;
+    }
+
+    let column = read_column(input, version)?;
+    let mut cursor = Cursor::new(&column);
+    let mut masks = Vec::with_capacity(count);
+    for _ in 0..count {
+        masks.push(cursor.varint()?);
+    }
+    let mut optionals = vec![[None; OPTIONAL_FIELDS]; count];
+    for field in 0..OPTIONAL_FIELDS {
+        let present = masks.iter().filter(|m| *m & (1 << field) != 0).count();
+        let mut values = read_floats(input, present, version)?.into_iter();
+        for (mask, slot) in masks.iter().zip(optionals.iter_mut()) {
+            if mask & (1 << field) != 0 {
+                slot[field] = values.next();
+            }
+        }
+    }
+    for (p, values) in pings.iter_mut().zip(optionals) {
+        set_optional_values(p, values);
+    }
+
+    let column = read_column(input, version)?;
+    let mut cursor = Cursor::new(&column);
+    let mut lengths = Vec::with_capacity(count);
+    for _ in 0..count {
+        lengths.push(cursor.varint()? as usize);
+    }
+    let samples = read_column(input, version)?;
+    if lengths.iter().try_fold(0usize, |sum, &n| sum.checked_add(n)) != Some(samples.len()) {
+        return Err(invalid("SSF sample column has the wrong length"));
+    }
+    let mut offset = 0;
+    for (p, len) in pings.iter_mut().zip(lengths) {
+        let mut last = 0u8;
+        p.samples = samples[offset..offset + len]
+            .iter()
+            .map(|&d| {
+                last = last.wrapping_add(d);
+                last
+            })
+            .collect();
+        offset += len;
+    }
+    Ok((channels, pings))
+}
+
+fn optional_values(p: &Ping) -> [Option<f64>; OPTIONAL_FIELDS] {
+    let a = &p.acoustics;
+    [
+        p.cog_deg,
+        p.sog_knots,
+        p.stw_knots,
+        a.frequency_khz,
+        a.chirp_khz.map(|c| c.0),
+        a.chirp_khz.map(|c| c.1),
+        a.pulse_length_us,
+        a.range_setting_m,
+        a.gain_pct,
+    ]
+}
+
+fn set_optional_values(p: &mut Ping, v: [Option<f64>; OPTIONAL_FIELDS]) {
+    p.cog_deg = v[0];
+    p.sog_knots = v[1];
+    p.stw_knots = v[2];
+    p.acoustics.frequency_khz = v[3];
+    p.acoustics.chirp_khz = v[4].zip(v[5]);
+    p.acoustics.pulse_length_us = v[6];
+    p.acoustics.range_setting_m = v[7];
+    p.acoustics.gain_pct = v[8];
+}
+
+fn kind_code(kind: ChannelKind) -> u8 {
+    match kind {
+        ChannelKind::Traditional => 0,
+        ChannelKind::DownScan => 1,
+        ChannelKind::SideScanPort => 2,
+        ChannelKind::SideScanStarboard => 3,
+        ChannelKind::Other => 255,
+    }
+}
+
+fn kind_from_code(code: u8) -> ChannelKind {
+    match code {
+        0 => ChannelKind::Traditional,
+        1 => ChannelKind::DownScan,
+        2 => ChannelKind::SideScanPort,
+        3 => ChannelKind::SideScanStarboard,
+        _ => ChannelKind::Other,
+    }
+}
+
+/// Tagged float column: scaled decimal deltas when lossless, bit-pattern deltas otherwise
+fn float_column<I: Iterator<Item = f64>>(values: I) -> Vec<u8> {
+    let values: Vec<f64> = values.collect();
+    let decimals = values
+        .iter()
+        .try_fold(0, |most, &v| decimal_places(v).map(|d| d.max(most)));
+    let scaled = decimals.and_then(|d| {
+        let scale = 10f64.powi(d as i32);
+        let scaled: Option<Vec<i64>> = values.iter().map(|&v| quantize(v, scale)).collect();
+        scaled.map(|q| (d, q))
+    });
+
+    let mut buf = Vec::new();
+    match scaled {
+        Some((d, scaled)) => {
+            buf.push(d);
+            let mut previous = 0i64;
+            for q in scaled {
+                put_varint(&mut buf, zigzag(q.wrapping_sub(previous)));
+                previous = q;
+            }
+        }
+        None => {
+            buf.push(RAW_FLOATS);
+            let mut previous = 0u64;
+            for v in values {
+                let bits = v.to_bits();
+                put_varint(&mut buf, zigzag(bits.wrapping_sub(previous) as i64));
+                previous = bits;
+            }
+        }
+    }
+    buf
+}
+
+/// Fewest decimal places that reproduce `v` exactly, if there are at most [`MAX_DECIMALS`]
+fn decimal_places(v: f64) -> Option<u8> {
+    (0..=MAX_DECIMALS).find(|&d| quantize(v, 10f64.powi(d as i32)).is_some())
+}
+
+/// `v * scale` as an integer, when dividing it back by `scale` restores `v` bit for bit
+fn quantize(v: f64, scale: f64) -> Option<i64> {
+    const EXACT_INTEGERS: f64 = (1u64 << 53) as f64;
+    let q = (v * scale).round();
+    (q.abs() < EXACT_INTEGERS && (q as i64 as f64 / scale).to_bits() == v.to_bits()).then_some(q as i64)
+}
+
+fn read_floats<R: Read + ?Sized>(input: &mut R, count: usize, version: u32) -> io::Result<Vec<f64>> {
+    let column = read_column(input, version)?;
+    let mut cursor = Cursor::new(&column);
+    let tag = if version >= 2 { cursor.byte()? } else { RAW_FLOATS };
+    let mut values = Vec::with_capacity(count.min(column.len()));
+    if tag == RAW_FLOATS {
+        let mut previous = 0u64;
+        for _ in 0..count {
+            previous = previous.wrapping_add(unzigzag(cursor.varint()?) as u64);
+            values.push(f64::from_bits(previous));
+        }
+    } else if tag <= MAX_DECIMALS {
+        let scale = 10f64.powi(tag as i32);
+        let mut previous = 0i64;
+        for _ in 0..count {
+            previous = previous.wrapping_add(unzigzag(cursor.varint()?));
+            values.push(previous as f64 / scale);
+        }
+    } else {
+        return Err(invalid("unknown SSF float encoding"));
+    }
+    Ok(values)
+}
+
+fn put_column<W: Write>(out: &mut W, column: &[u8], version: u32) -> io::Result<()> {
+    if version < COMPRESSED_VERSION {
+        out.write_all(&(column.len() as u64).to_le_bytes())?;
+        return out.write_all(column);
+    }
+    let mut compressed = vec![LZ];
+    put_varint(&mut compressed, column.len() as u64);
+    lz_compress(column, &mut compressed);
+    if compressed.len() > column.len() {
+        compressed.clear();
+        compressed.push(STORED);
+        compressed.extend_from_slice(column);
+    }
+    out.write_all(&(compressed.len() as u64).to_le_bytes())?;
+    out.write_all(&compressed)
+}
+
+fn read_column<R: Read + ?Sized>(input: &mut R, version: u32) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 8];
+    input.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    let mut column = Vec::new();
+    input.take(len).read_to_end(&mut column)?;
+    if column.len() as u64 != len {
+        return Err(invalid("truncated SSF column"));
+    }
+    if version < COMPRESSED_VERSION {
+        return Ok(column);
+    }
+    match column.first() {
+        Some(&STORED) => Ok(column.split_off(1)),
+        Some(&LZ) => {
+            let mut cursor = Cursor::new(&column[1..]);
+            let raw_len = cursor.varint()?;
+            lz_decompress(&mut cursor, raw_len)
+        }
+        _ => Err(invalid("unknown SSF column codec")),
+    }
+}
+
+/// Append greedy LZ77 sequences for `data`, finding matches through a hash of the next 4 bytes
+fn lz_compress(data: &[u8], out: &mut Vec<u8>) {
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let mut literal_start = 0;
+    let mut i = 0;
+    while i + MIN_MATCH <= data.len() {
+        let key = u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        let slot = (key.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+        let candidate = std::mem::replace(&mut table[slot], i);
+        if candidate == usize::MAX || i - candidate > MAX_OFFSET || data[candidate..candidate + 4] != data[i..i + 4] {
+            i += 1;
+            continue;
+        }
+        let extra = data[i + MIN_MATCH..]
+            .iter()
+            .zip(&data[candidate + MIN_MATCH..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        put_varint(out, (i - literal_start) as u64);
+        out.extend_from_slice(&data[literal_start..i]);
+        put_varint(out, (i - candidate) as u64);
+        put_varint(out, extra as u64);
+        i += MIN_MATCH + extra;
+        literal_start = i;
+    }
+    put_varint(out, (data.len() - literal_start) as u64);
+    out.extend_from_slice(&data[literal_start..]);
+}
+
+fn lz_decompress(cursor: &mut Cursor, raw_len: u64) -> io::Result<Vec<u8>> {
+    let corrupt = || invalid("corrupt SSF compressed column");
+    let raw_len = usize::try_from(raw_len).map_err(|_| corrupt())?;
+    let mut out = Vec::with_capacity(raw_len.min(cursor.data.len().saturating_mul(64)));
+    loop {
+        let literals = cursor.varint()? as usize;
+        out.extend_from_slice(cursor.bytes(literals)?);
+        if out.len() > raw_len {
+            return Err(corrupt());
+        }
+        if cursor.pos == cursor.data.len() {
+            break;
+        }
+        let offset = cursor.varint()? as usize;
+        let len = (cursor.varint()? as usize).saturating_add(MIN_MATCH);
+        if offset == 0 || offset > out.len() || len > raw_len - out.len() {
+            return Err(corrupt());
+        }
+        // Byte by byte, since a match may overlap the bytes it produces
+        let start = out.len() - offset;
+        for k in 0..len {
+            out.push(out[start + k]);
+        }
+    }
+    if out.len() != raw_len {
+        return Err(corrupt());
+    }
+    Ok(out)
+}
+
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn unzigzag(v: u64) -> i64 {
+    (v >> 1) as i64 ^ -((v & 1) as i64)
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+/// Read position within a decoded column
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        let b = *self.data.get(self.pos).ok_or_else(|| invalid("truncated SSF column"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len());
+        let end = end.ok_or_else(|| invalid("truncated SSF column"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn f64(&mut self) -> io::Result<f64> {
+        let bytes = self.bytes(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().unwrap_or_default()))
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            value |= ((b & 0x7F) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("SSF varint too long"))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::survey::{Acoustics, Sensors};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn decimal(rng: &mut StdRng, lo: f64, hi: f64, places: i32) -> f64 {
+        let scale = 10f64.powi(places);
+        (rng.gen_range(lo..hi) * scale).round() / scale
+    }
+
+    fn random_pings(n: usize, seed: u64) -> Vec<Ping> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n)
+            .map(|i| Ping {
+                timestamp: 1_700_000_000.0 + i as f64 * 0.125,
+                channel_id: (i % 3) as u16,
+                lat: decimal(&mut rng, 44.0, 44.01, 7),
+                lon: decimal(&mut rng, -83.01, -83.0, 7),
+                heading_deg: rng.gen_range(0.0..360.0),
+                depth_m: decimal(&mut rng, 0.5, 30.0, 2),
+                range_m: [10.0, 20.0, 40.0][i % 3],
+                samples: (0..rng.gen_range(0..300)).map(|_| rng.gen()).collect(),
+                cog_deg: rng.gen_bool(0.7).then(|| rng.gen_range(0.0..360.0)),
+                sog_knots: rng.gen_bool(0.7).then(|| decimal(&mut rng, 0.0, 8.0, 1)),
+                stw_knots: None,
+                acoustics: Acoustics {
+                    frequency_khz: Some(200.0),
+                    chirp_khz: rng.gen_bool(0.5).then_some((150.0, 250.0)),
+                    gain_pct: rng.gen_bool(0.1).then(|| rng.gen_range(0.0..100.0)),
+                    ..Default::default()
+                },
+                sensors: Sensors::default(),
+                flags: rng.gen_range(0..4),
+            })
+            .collect()
+    }
+
+    fn channels() -> Vec<ChannelInfo> {
+        vec![
+            ChannelInfo {
+                channel_id: 0,
+                name: "Primary".to_string(),
+                kind: ChannelKind::Traditional,
+                frequency_khz: Some(200.0),
+                beam_angle_deg: None,
+            },
+            ChannelInfo {
+                channel_id: 2,
+                name: "Side scan ←".to_string(),
+                kind: ChannelKind::SideScanPort,
+                frequency_khz: None,
+                beam_angle_deg: Some(1.5),
+            },
+        ]
+    }
+
+    fn encode(channels: &[ChannelInfo], pings: &[Ping]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_ssf_to(&mut buf, channels, pings).unwrap();
+        buf
+    }
+
+    #[test]
+    fn round_trips_bit_for_bit() {
+        let mut pings = random_pings(2000, 417);
+        pings[5].depth_m = -0.0;
+        pings[6].heading_deg = f64::INFINITY;
+        pings[7].lat = 1e-300;
+        pings[8].acoustics.pulse_length_us = Some(f64::MIN_POSITIVE);
+        pings[9].depth_m = f64::NAN;
+        let (channels_back, back) = read_ssf_from(&mut encode(&channels(), &pings).as_slice()).unwrap();
+        assert_eq!(channels_back, channels());
+        assert!(back[9].depth_m.is_nan());
+        assert_eq!(back[5].depth_m.to_bits(), (-0.0f64).to_bits());
+        pings[9].depth_m = 0.0;
+        let mut back = back;
+        back[9].depth_m = 0.0;
+        assert_eq!(back, pings);
+
+        let path = std::env::temp_dir().join(format!("sonar_ssf_{}_round_trip.ssf", std::process::id()));
+        write_ssf(&path, &[], &pings[..10]).unwrap();
+        assert_eq!(read_ssf(&path).unwrap().1.len(), 10);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            read_ssf_from(&mut encode(&[], &[]).as_slice()).unwrap(),
+            (vec![], vec![])
+        );
+    }
+
+    #[test]
+    fn decimal_columns_are_quantized() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut lat = 44.0;
+        let track: Vec<f64> = (0..1000)
+            .map(|_| {
+                lat = ((lat + rng.gen_range(0.0..0.00002)) * 1e7f64).round() / 1e7;
+                lat
+            })
+            .collect();
+        let column = float_column(track.iter().copied());
+        assert_eq!(column[0], 7);
+        assert!(column.len() < 3 * track.len(), "{} bytes", column.len());
+        assert!(column.len() * 2 < float_column(track.iter().map(|v| v + 1e-12)).len());
+
+        assert_eq!(float_column([0.5, 0.25].into_iter())[0], 2);
+        assert_eq!(float_column([3.0, -7.0].into_iter())[0], 0);
+        assert_eq!(float_column([0.1, 1.0 / 3.0].into_iter())[0], RAW_FLOATS);
+        assert_eq!(float_column([1.5, -0.0].into_iter())[0], RAW_FLOATS);
+        assert_eq!(float_column([1e17].into_iter())[0], RAW_FLOATS);
+    }
+
+    #[test]
+    fn reads_version_1_files() {
+        // One ping on channel 4 with no channel table, written the version 1 way
+        let mut v1 = MAGIC.to_vec();
+        v1.extend_from_slice(&1u32.to_le_bytes());
+        put_column(&mut v1, &[0], 1).unwrap();
+        v1.extend_from_slice(&1u64.to_le_bytes());
+        put_column(&mut v1, &[zigzag(4) as u8], 1).unwrap();
+        let values: [f64; 6] = [1_700_000_000.5, 44.1, -83.2, 90.0, 3.25, 20.0];
+        for v in values {
+            let mut column = Vec::new();
+            put_varint(&mut column, zigzag(v.to_bits() as i64));
+            put_column(&mut v1, &column, 1).unwrap();
+        }
+        put_column(&mut v1, &[1], 1).unwrap();
+        put_column(&mut v1, &[0], 1).unwrap();
+        for _ in 0..OPTIONAL_FIELDS {
+            put_column(&mut v1, &[], 1).unwrap();
+        }
+        put_column(&mut v1, &[2], 1).unwrap();
+        put_column(&mut v1, &[5, 3], 1).unwrap();
+
+        let (channels, pings) = read_ssf_from(&mut v1.as_slice()).unwrap();
+        assert!(channels.is_empty());
+        let p = &pings[0];
+        assert_eq!((p.channel_id, p.flags, p.samples.clone()), (4, 1, vec![5, 8]));
+        assert_eq!([p.timestamp, p.lat, p.lon, p.heading_deg, p.depth_m, p.range_m], values);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let pings = random_pings(20, 2);
+        let good = encode(&channels(), &pings);
+        let error = |data: &[u8]| read_ssf_from(&mut &data[..]).unwrap_err().kind();
+
+        let mut magic = good.clone();
+        magic[0] = b'X';
+        assert_eq!(error(&magic), io::ErrorKind::InvalidData);
+        for version in [0u32, 4] {
+            let mut other = good.clone();
+            other[4..8].copy_from_slice(&version.to_le_bytes());
+            assert_eq!(error(&other), io::ErrorKind::InvalidData);
+        }
+        for len in 0..good.len() {
+            assert!(read_ssf_from(&mut &good[..len]).is_err(), "truncated to {}", len);
+        }
+
+        // First float column: timestamps, after the header, channel table, count and channel ids
+        let table_len = u64::from_le_bytes(good[8..16].try_into().unwrap()) as usize;
+        let ids_at = 16 + table_len + 8;
+        let ids_len = u64::from_le_bytes(good[ids_at..ids_at + 8].try_into().unwrap()) as usize;
+        let mut tag = good.clone();
+        tag[ids_at + 8 + ids_len + 8] = 42;
+        assert_eq!(error(&tag), io::ErrorKind::InvalidData);
+
+        // One sample more than the lengths column accounts for
+        let sample_count = pings.iter().map(|p| p.samples.len()).sum::<usize>();
+        let len_at = good.len() - sample_count - 8;
+        let mut samples = good.clone();
+        samples[len_at..len_at + 8].copy_from_slice(&(sample_count as u64 + 1).to_le_bytes());
+        samples.push(0);
+        assert_eq!(error(&samples), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn lz_round_trips_and_rejects_bad_references() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let noise: Vec<u8> = (0..5000).map(|_| rng.gen()).collect();
+        let runs: Vec<u8> = (0..5000).map(|i| (i / 700) as u8).collect();
+        let repeated: Vec<u8> = b"abcabcabcab".repeat(40);
+        for data in [&[][..], b"abc", b"aaaaaaaa", &noise, &runs, &repeated] {
+            let mut packed = Vec::new();
+            lz_compress(data, &mut packed);
+            let back = lz_decompress(&mut Cursor::new(&packed), data.len() as u64).unwrap();
+            assert_eq!(back, data);
+        }
+        let mut packed = Vec::new();
+        lz_compress(&runs, &mut packed);
+        assert!(packed.len() * 20 < runs.len(), "{} bytes", packed.len());
+
+        // Literal "ab", then a match reaching back 3 bytes
+        let decode = |data: &[u8], len: u64| lz_decompress(&mut Cursor::new(data), len).map_err(|e| e.kind());
+        assert_eq!(decode(&[2, b'a', b'b', 2, 0, 0], 6), Ok(b"ababab".to_vec()));
+        assert_eq!(decode(&[2, b'a', b'b', 3, 0, 0], 6), Err(io::ErrorKind::InvalidData));
+        assert_eq!(decode(&[2, b'a', b'b', 0, 0, 0], 6), Err(io::ErrorKind::InvalidData));
+        assert_eq!(decode(&[2, b'a', b'b', 2, 0, 0], 5), Err(io::ErrorKind::InvalidData));
+        assert_eq!(decode(&[2, b'a', b'b'], 3), Err(io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn compressed_files_round_trip_and_are_smaller() {
+        let mut pings = random_pings(3000, 5);
+        for (i, p) in pings.iter_mut().enumerate() {
+            // Smooth returns with a bottom band, like real sonar rows
+            p.samples = (0..400).map(|k| if (150..170).contains(&k) { 200 } else { (k / 40 + i % 3) as u8 }).collect();
+        }
+        let plain = encode(&channels(), &pings);
+        let mut packed = Vec::new();
+        write_ssf_compressed_to(&mut packed, &channels(), &pings).unwrap();
+        assert_eq!(u32::from_le_bytes(packed[4..8].try_into().unwrap()), COMPRESSED_VERSION);
+        assert!(packed.len() * 4 < plain.len(), "{} vs {} bytes", packed.len(), plain.len());
+        assert_eq!(read_ssf_from(&mut packed.as_slice()).unwrap(), read_ssf_from(&mut plain.as_slice()).unwrap());
+
+        let path = std::env::temp_dir().join(format!("sonar_ssf_{}_compressed.ssf", std::process::id()));
+        write_ssf_compressed(&path, &[], &pings[..10]).unwrap();
+        assert_eq!(read_ssf(&path).unwrap().1, pings[..10]);
+        std::fs::remove_file(path).unwrap();
+
+        for len in (0..packed.len()).step_by(97) {
+            assert!(read_ssf_from(&mut &packed[..len]).is_err(), "truncated to {}", len);
+        }
+        // Unknown codec on the channel table column
+        let mut codec = packed.clone();
+        codec[16] = 7;
+        assert_eq!(read_ssf_from(&mut codec.as_slice()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
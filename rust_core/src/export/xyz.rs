@@ -0,0 +1,67 @@
+// Plain-text XYZ point export
+// src/export/xyz.rs
+
+use crate::crs::Crs;
+use crate::gridding::footprint_diameter_m;
+use crate::survey::Sounding;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Write soundings as whitespace-separated `lon lat depth` lines (depth positive down)
+pub fn write_xyz<P: AsRef<Path>>(path: P, soundings: &[Sounding]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_xyz_to(&mut out, soundings)?;
+    out.flush()
+}
+
+pub fn write_xyz_to<W: Write>(out: &mut W, soundings: &[Sounding]) -> io::Result<()> {
+    write_xyz_with_crs_to(out, soundings, Crs::Wgs84)
+}
+
+/// Write `x y depth` lines in `crs` (easting/northing in meters when projected)
+pub fn write_xyz_with_crs<P: AsRef<Path>>(path: P, soundings: &[Sounding], crs: Crs) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_xyz_with_crs_to(&mut out, soundings, crs)?;
+    out.flush()
+}
+
+pub fn write_xyz_with_crs_to<W: Write>(out: &mut W, soundings: &[Sounding], crs: Crs) -> io::Result<()> {
+    let decimals = crs.decimals();
+    for s in soundings {
+        let (x, y) = crs.project(s.lat, s.lon);
+        writeln!(out, "{:.*} {:.*} {:.3}", decimals, x, decimals, y, s.depth_m)?;
+    }
+    Ok(())
+}
+
+/// Write `lon lat depth footprint` lines, with the beam footprint diameter in meters
+pub fn write_xyz_footprint<P: AsRef<Path>>(path: P, soundings: &[Sounding], beam_angle_deg: f64) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_xyz_footprint_to(&mut out, soundings, beam_angle_deg)?;
+    out.flush()
+}
+
+pub fn write_xyz_footprint_to<W: Write>(out: &mut W, soundings: &[Sounding], beam_angle_deg: f64) -> io::Result<()> {
+    for s in soundings {
+        let footprint = footprint_diameter_m(s.depth_m, beam_angle_deg);
+        writeln!(out, "{:.7} {:.7} {:.3} {:.3}", s.lon, s.lat, s.depth_m, footprint)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn footprint_column_follows_depth_and_beam_angle() {
+        let soundings = [Sounding::new(0.0, 44.6, -63.5, 10.0), Sounding::new(1.0, 44.6001, -63.5, 0.5)];
+        let mut out = Vec::new();
+        write_xyz_footprint_to(&mut out, &soundings, 20.0).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "-63.5000000 44.6000000 10.000 3.527\n-63.5000000 44.6001000 0.500 0.176\n"
+        );
+    }
+}
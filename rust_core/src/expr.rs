@@ -0,0 +1,403 @@
+// Filter expressions over ping fields, e.g. "depth_m < 3 && channel_id == 4"
+// src/expr.rs
+
+use crate::survey::Ping;
+use std::io;
+
+/// Deepest nesting of parentheses and negations accepted, so hostile input
+/// cannot exhaust the stack
+const MAX_DEPTH: usize = 256;
+
+/// Ping field usable in a filter expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Timestamp,
+    ChannelId,
+    Lat,
+    Lon,
+    HeadingDeg,
+    DepthM,
+    RangeM,
+    CogDeg,
+    SogKnots,
+    StwKnots,
+    FrequencyKhz,
+    PulseLengthUs,
+    RangeSettingM,
+    GainPct,
+    Flags,
+}
+
+impl Field {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "timestamp" | "time" => Some(Self::Timestamp),
+            "channel_id" | "channel" => Some(Self::ChannelId),
+            "lat" | "latitude" => Some(Self::Lat),
+            "lon" | "longitude" => Some(Self::Lon),
+            "heading_deg" | "heading" => Some(Self::HeadingDeg),
+            "depth_m" | "depth" => Some(Self::DepthM),
+            "range_m" | "range" => Some(Self::RangeM),
+            "cog_deg" | "cog" => Some(Self::CogDeg),
+            "sog_knots" | "sog" => Some(Self::SogKnots),
+            "stw_knots" | "stw" => Some(Self::StwKnots),
+            "frequency_khz" | "frequency" => Some(Self::FrequencyKhz),
+            "pulse_length_us" | "pulse_length" => Some(Self::PulseLengthUs),
+            "range_setting_m" | "range_setting" => Some(Self::RangeSettingM),
+            "gain_pct" | "gain" => Some(Self::GainPct),
+            "flags" => Some(Self::Flags),
+            _ => None,
+        }
+    }
+
+    /// Field value; unset optional fields are NaN and so fail every comparison but `!=`
+    pub fn value(self, ping: &Ping) -> f64 {
+        match self {
+            Self::Timestamp => ping.timestamp,
+            Self::ChannelId => ping.channel_id as f64,
+            Self::Lat => ping.lat,
+            Self::Lon => ping.lon,
+            Self::HeadingDeg => ping.heading_deg,
+            Self::DepthM => ping.depth_m,
+            Self::RangeM => ping.range_m,
+            Self::CogDeg => ping.cog_deg.unwrap_or(f64::NAN),
+            Self::SogKnots => ping.sog_knots.unwrap_or(f64::NAN),
+            Self::StwKnots => ping.stw_knots.unwrap_or(f64::NAN),
+            Self::FrequencyKhz => ping.acoustics.frequency_khz.unwrap_or(f64::NAN),
+            Self::PulseLengthUs => ping.acoustics.pulse_length_us.unwrap_or(f64::NAN),
+            Self::RangeSettingM => ping.acoustics.range_setting_m.unwrap_or(f64::NAN),
+            Self::GainPct => ping.acoustics.gain_pct.unwrap_or(f64::NAN),
+            Self::Flags => ping.flags as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Compare(Field, CmpOp, f64),
+    /// Operands of a chain of `&&`, kept flat so long chains do not nest
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+}
+
+/// Compiled filter over pings
+///
+/// Grammar: comparisons `field op number` (or `number op field`) with
+/// `< <= > >= == !=`, combined with `&&`/`and`, `||`/`or`, `!`/`not` and
+/// parentheses. `&&` binds tighter than `||`. Numbers may use exponents
+/// such as `1e-3`; nesting is limited to 256 levels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterExpr {
+    root: Node,
+}
+
+impl FilterExpr {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0, depth: 0 };
+        let root = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(Self { root }),
+            Some(token) => Err(invalid(format!("unexpected '{}' in filter expression", token))),
+        }
+    }
+
+    pub fn matches(&self, ping: &Ping) -> bool {
+        eval(&self.root, ping)
+    }
+}
+
+fn eval(node: &Node, ping: &Ping) -> bool {
+    match node {
+        Node::Compare(field, op, value) => {
+            let v = field.value(ping);
+            match op {
+                CmpOp::Lt => v < *value,
+                CmpOp::Le => v <= *value,
+                CmpOp::Gt => v > *value,
+                CmpOp::Ge => v >= *value,
+                CmpOp::Eq => v == *value,
+                CmpOp::Ne => v != *value,
+            }
+        }
+        Node::And(nodes) => nodes.iter().all(|n| eval(n, ping)),
+        Node::Or(nodes) => nodes.iter().any(|n| eval(n, ping)),
+        Node::Not(a) => !eval(a, ping),
+    }
+}
+
+fn invalid<S: Into<String>>(msg: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg.into())
+}
+
+fn tokenize(text: &str) -> io::Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_alphanumeric()
+            || c == '_'
+            || c == '.'
+            || (c == '-' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit() || *n == '.'))
+        {
+            let start = i;
+            let numeric = c != '_' && !c.is_ascii_alphabetic();
+            i += 1;
+            while i < chars.len() {
+                let c = chars[i];
+                // The sign of an exponent, as in `1e-3`
+                let exponent_sign = numeric
+                    && (c == '-' || c == '+')
+                    && matches!(chars[i - 1], 'e' | 'E')
+                    && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit());
+                if !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || exponent_sign) {
+                    break;
+                }
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else {
+            let pair: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let op = ["&&", "||", "<=", ">=", "==", "!="]
+                .into_iter()
+                .find(|op| pair == *op)
+                .map(str::to_string)
+                .or_else(|| "()<>!=".contains(c).then(|| c.to_string()))
+                .ok_or_else(|| invalid(format!("unexpected character '{}' in filter expression", c)))?;
+            i += op.len();
+            tokens.push(op);
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+    /// Open parentheses and negations around the current token
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> io::Result<String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| invalid("filter expression ends early"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn or(&mut self) -> io::Result<Node> {
+        let mut nodes = vec![self.and()?];
+        while matches!(self.peek(), Some("||" | "or")) {
+            self.pos += 1;
+            nodes.push(self.and()?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::Or(nodes) })
+    }
+
+    fn and(&mut self) -> io::Result<Node> {
+        let mut nodes = vec![self.unary()?];
+        while matches!(self.peek(), Some("&&" | "and")) {
+            self.pos += 1;
+            nodes.push(self.unary()?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::And(nodes) })
+    }
+
+    fn unary(&mut self) -> io::Result<Node> {
+        match self.peek() {
+            Some("!" | "not") => {
+                self.pos += 1;
+                self.nested(|p| Ok(Node::Not(Box::new(p.unary()?))))
+            }
+            Some("(") => {
+                self.pos += 1;
+                let node = self.nested(Parser::or)?;
+                match self.next()?.as_str() {
+                    ")" => Ok(node),
+                    other => Err(invalid(format!("expected ')' but found '{}'", other))),
+                }
+            }
+            _ => self.comparison(),
+        }
+    }
+
+    /// Parse one level deeper, refusing to go past [`MAX_DEPTH`]
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> io::Result<Node>) -> io::Result<Node> {
+        if self.depth == MAX_DEPTH {
+            return Err(invalid(format!("filter expression nests deeper than {} levels", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let node = parse(self);
+        self.depth -= 1;
+        node
+    }
+
+    fn comparison(&mut self) -> io::Result<Node> {
+        let left = self.next()?;
+        let op = match self.next()?.as_str() {
+            "<" => CmpOp::Lt,
+            "<=" => CmpOp::Le,
+            ">" => CmpOp::Gt,
+            ">=" => CmpOp::Ge,
+            "==" | "=" => CmpOp::Eq,
+            "!=" => CmpOp::Ne,
+            other => return Err(invalid(format!("expected a comparison operator but found '{}'", other))),
+        };
+        let right = self.next()?;
+
+        if let Some(field) = Field::from_name(&left) {
+            return Ok(Node::Compare(field, op, number(&right)?));
+        }
+        let field = Field::from_name(&right).ok_or_else(|| invalid(format!("unknown field '{}'", left)))?;
+        // Mirror `3 > depth_m` into `depth_m < 3`
+        let mirrored = match op {
+            CmpOp::Lt => CmpOp::Gt,
+            CmpOp::Le => CmpOp::Ge,
+            CmpOp::Gt => CmpOp::Lt,
+            CmpOp::Ge => CmpOp::Le,
+            other => other,
+        };
+        Ok(Node::Compare(field, mirrored, number(&left)?))
+    }
+}
+
+fn number(token: &str) -> io::Result<f64> {
+    token
+        .parse()
+        .map_err(|_| invalid(format!("expected a number but found '{}'", token)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::survey::Acoustics;
+
+    fn ping(channel_id: u16, depth_m: f64) -> Ping {
+        Ping {
+            timestamp: 1_700_000_000.0,
+            channel_id,
+            depth_m,
+            ..Default::default()
+        }
+    }
+
+    fn matches(text: &str, ping: &Ping) -> bool {
+        FilterExpr::parse(text).unwrap().matches(ping)
+    }
+
+    fn error(text: &str) -> String {
+        let err = FilterExpr::parse(text).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        err.to_string()
+    }
+
+    #[test]
+    fn parses_comparisons_and_aliases() {
+        let p = ping(4, 2.5);
+        assert!(matches("depth_m < 3 && channel_id == 4", &p));
+        assert!(matches("depth<=2.5 and channel=4", &p));
+        assert!(!matches("depth_m > 3", &p));
+        assert!(matches("timestamp >= 1.7e9 && depth_m != -1", &p));
+        assert!(matches("depth_m < 2.5E+0 || depth_m > 1e-3", &p));
+        assert!(!matches("depth_m < 1e-3", &p));
+        assert!(matches("not (depth_m > 3)", &p));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let p = ping(1, 10.0);
+        // Read as `channel == 1 || (depth < 3 && channel == 2)`
+        assert!(matches("channel_id == 1 || depth_m < 3 && channel_id == 2", &p));
+        assert!(!matches("(channel_id == 1 || depth_m < 3) && channel_id == 2", &p));
+        assert!(matches("!channel_id == 2 && depth_m > 5", &p));
+        assert!(!matches("!(channel_id == 1 && depth_m > 5)", &p));
+    }
+
+    #[test]
+    fn number_first_comparisons_are_mirrored() {
+        let p = ping(0, 2.0);
+        for (text, expected) in [
+            ("3 > depth_m", true),
+            ("3 < depth_m", false),
+            ("2 >= depth_m", true),
+            ("2 <= depth_m", true),
+            ("2 == depth_m", true),
+            ("2 != depth_m", false),
+        ] {
+            assert_eq!(matches(text, &p), expected, "{}", text);
+        }
+        assert_eq!(
+            FilterExpr::parse("3 > depth_m").unwrap(),
+            FilterExpr::parse("depth_m < 3").unwrap()
+        );
+    }
+
+    #[test]
+    fn unset_fields_fail_all_but_not_equal() {
+        let p = ping(0, 2.0);
+        for text in ["sog_knots < 1", "sog_knots >= 0", "sog_knots == 0", "frequency_khz > 0"] {
+            assert!(!matches(text, &p), "{}", text);
+        }
+        assert!(matches("sog_knots != 0", &p));
+        let tuned = Ping {
+            sog_knots: Some(0.5),
+            acoustics: Acoustics {
+                frequency_khz: Some(200.0),
+                ..Default::default()
+            },
+            ..p
+        };
+        assert!(matches("sog_knots < 1 && frequency_khz == 200", &tuned));
+    }
+
+    #[test]
+    fn errors_name_the_problem() {
+        assert_eq!(error("speed > 3"), "unknown field 'speed'");
+        assert_eq!(error("depth_m ~ 3"), "unexpected character '~' in filter expression");
+        assert_eq!(error("depth_m < shallow"), "expected a number but found 'shallow'");
+        assert_eq!(error("depth_m 3"), "expected a comparison operator but found '3'");
+        assert_eq!(error("depth_m <"), "filter expression ends early");
+        assert_eq!(error("(depth_m < 3"), "filter expression ends early");
+        assert_eq!(error("(depth_m < 3 depth_m"), "expected ')' but found 'depth_m'");
+        assert_eq!(error("depth_m < 3)"), "unexpected ')' in filter expression");
+    }
+
+    #[test]
+    fn deep_nesting_is_refused_without_overflowing() {
+        let p = ping(0, 2.0);
+        let nested = format!("{}depth_m < 3{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+        assert!(matches(&nested, &p));
+        assert!(matches(&format!("{}depth_m < 3", "!".repeat(MAX_DEPTH)), &p));
+
+        let too_deep = format!("{}depth_m < 3", "!".repeat(200_000));
+        assert_eq!(error(&too_deep), "filter expression nests deeper than 256 levels");
+        let too_deep = format!("{}depth_m < 3{}", "(".repeat(MAX_DEPTH + 1), ")".repeat(MAX_DEPTH + 1));
+        assert_eq!(error(&too_deep), "filter expression nests deeper than 256 levels");
+
+        // Long flat chains are fine at any length
+        let chain = vec!["depth_m < 3"; 200_000].join(" && ");
+        assert!(matches(&chain, &p));
+    }
+}
@@ -0,0 +1,78 @@
+// Local-plane geodesy helpers shared by the sonar processing modules
+// src/geo.rs
+
+/// Meters per degree of latitude (same constant as the drift kernel)
+pub const METERS_PER_DEGREE_LAT: f64 = 111320.0;
+
+/// Meters per degree of longitude at the given latitude
+pub fn meters_per_degree_lon(lat: f64) -> f64 {
+    METERS_PER_DEGREE_LAT * lat.to_radians().cos()
+}
+
+/// Shift a position by east/north offsets in meters
+pub fn offset_position(lat: f64, lon: f64, east_m: f64, north_m: f64) -> (f64, f64) {
+    let delta_lat = north_m / METERS_PER_DEGREE_LAT;
+    let delta_lon = east_m / meters_per_degree_lon(lat);
+
+    (lat + delta_lat, lon + delta_lon)
+}
+
+/// East/north offset in meters of a position relative to a reference point
+pub fn local_offset_m(ref_lat: f64, ref_lon: f64, lat: f64, lon: f64) -> (f64, f64) {
+    let east = (lon - ref_lon) * meters_per_degree_lon(ref_lat);
+    let north = (lat - ref_lat) * METERS_PER_DEGREE_LAT;
+
+    (east, north)
+}
+
+/// Flat-earth distance in meters, accurate enough over survey-sized areas
+pub fn distance_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (east, north) = local_offset_m(lat1, lon1, lat2, lon2);
+    east.hypot(north)
+}
+
+/// Rotate a vessel-frame offset (starboard, forward) into east/north by heading
+pub fn vessel_to_local(starboard_m: f64, forward_m: f64, heading_deg: f64) -> (f64, f64) {
+    let (sin_h, cos_h) = heading_deg.to_radians().sin_cos();
+    let east = forward_m * sin_h + starboard_m * cos_h;
+    let north = forward_m * cos_h - starboard_m * sin_h;
+
+    (east, north)
+}
+
+/// Whether a position lies inside a polygon of `(lat, lon)` vertices (even-odd rule)
+pub fn point_in_polygon(lat: f64, lon: f64, polygon: &[(f64, f64)]) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len().wrapping_sub(1);
+    for (i, &(lat_i, lon_i)) in polygon.iter().enumerate() {
+        let (lat_j, lon_j) = polygon[j];
+        if (lat_i > lat) != (lat_j > lat) && lon < (lon_j - lon_i) * (lat - lat_i) / (lat_j - lat_i) + lon_i {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Whether a polygon overlaps a `(min_lat, min_lon, max_lat, max_lon)` box
+pub fn polygon_intersects_bbox(polygon: &[(f64, f64)], bbox: (f64, f64, f64, f64)) -> bool {
+    let (min_lat, min_lon, max_lat, max_lon) = bbox;
+    let in_box = |&(lat, lon): &(f64, f64)| lat >= min_lat && lat <= max_lat && lon >= min_lon && lon <= max_lon;
+    let corners = [(min_lat, min_lon), (min_lat, max_lon), (max_lat, max_lon), (max_lat, min_lon)];
+
+    polygon.iter().any(in_box)
+        || corners.iter().any(|&(lat, lon)| point_in_polygon(lat, lon, polygon))
+        || polygon.iter().zip(polygon.iter().cycle().skip(1)).any(|(&a, &b)| {
+            corners
+                .iter()
+                .zip(corners.iter().cycle().skip(1))
+                .any(|(&c, &d)| segments_cross(a, b, c, d))
+        })
+}
+
+fn segments_cross(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> bool {
+    let orient = |p: (f64, f64), q: (f64, f64), r: (f64, f64)| (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0);
+    let (d1, d2) = (orient(c, d, a), orient(c, d, b));
+    let (d3, d4) = (orient(a, b, c), orient(a, b, d));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
@@ -0,0 +1,825 @@
+// Bathymetric gridding of soundings onto a regular depth grid
+// src/gridding.rs
+
+use crate::geo::{local_offset_m, meters_per_degree_lon, offset_position, METERS_PER_DEGREE_LAT};
+use crate::survey::Sounding;
+use std::collections::HashMap;
+
+/// Largest grid built, about 128 MB of depths; finer cells over a wider
+/// extent are refused rather than allocated
+pub const MAX_GRID_CELLS: usize = 1 << 24;
+
+/// Interpolation method used to fill grid cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GridMethod {
+    /// Depth of the closest sounding within the search radius
+    Nearest,
+    /// Inverse distance weighting with the given power
+    InverseDistance { power: f64 },
+    /// Linear interpolation over a Delaunay triangulation
+    Triangulation,
+}
+
+/// Gridding parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridConfig {
+    pub cell_size_m: f64,
+    pub search_radius_m: f64,
+    pub method: GridMethod,
+    /// Transducer beam angle; when set, inverse distance weights are scaled
+    /// by 1/footprint² so wide deep-water footprints count for less
+    pub beam_angle_deg: Option<f64>,
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        Self {
+            cell_size_m: 5.0,
+            search_radius_m: 15.0,
+            method: GridMethod::InverseDistance { power: 2.0 },
+            beam_angle_deg: None,
+        }
+    }
+}
+
+/// North-up depth grid; row 0 is the northern edge, NaN marks empty cells
+#[derive(Debug, Clone)]
+pub struct DepthGrid {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub cell_size_m: f64,
+    pub rows: usize,
+    pub cols: usize,
+    pub values: Vec<f64>,
+}
+
+impl DepthGrid {
+    /// Empty grid covering `width_m` x `height_m` east/north of the south-west corner
+    ///
+    /// None when `cell_size_m` is not a positive number or the grid would
+    /// have more than [`MAX_GRID_CELLS`] cells.
+    pub fn new(min_lat: f64, min_lon: f64, cell_size_m: f64, width_m: f64, height_m: f64) -> Option<Self> {
+        if !cell_size_m.is_finite() || cell_size_m <= 0.0 {
+            return None;
+        }
+        let cells_along = |extent_m: f64| (extent_m / cell_size_m).ceil().max(1.0);
+        let (cols, rows) = (cells_along(width_m), cells_along(height_m));
+        if cols * rows > MAX_GRID_CELLS as f64 {
+            return None;
+        }
+        let (cols, rows) = (cols as usize, rows as usize);
+
+        Some(Self {
+            min_lat,
+            min_lon,
+            cell_size_m,
+            rows,
+            cols,
+            values: vec![f64::NAN; rows * cols],
+        })
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.values[row * self.cols + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: f64) {
+        self.values[row * self.cols + col] = value;
+    }
+
+    /// Local east/north coordinates in meters of a cell center
+    pub fn cell_center_m(&self, row: usize, col: usize) -> (f64, f64) {
+        let east = (col as f64 + 0.5) * self.cell_size_m;
+        let north = (self.rows - row) as f64 * self.cell_size_m - 0.5 * self.cell_size_m;
+        (east, north)
+    }
+
+    /// Latitude/longitude of a cell center
+    pub fn cell_center(&self, row: usize, col: usize) -> (f64, f64) {
+        let (east, north) = self.cell_center_m(row, col);
+        offset_position(self.min_lat, self.min_lon, east, north)
+    }
+
+    /// Row/column of the cell containing a position, if inside the grid
+    pub fn cell_at(&self, lat: f64, lon: f64) -> Option<(usize, usize)> {
+        let (east, north) = self.to_local(lat, lon);
+        if east < 0.0 || north < 0.0 {
+            return None;
+        }
+        let col = (east / self.cell_size_m) as usize;
+        let row_from_south = (north / self.cell_size_m) as usize;
+        if col >= self.cols || row_from_south >= self.rows {
+            return None;
+        }
+        Some((self.rows - 1 - row_from_south, col))
+    }
+
+    /// Local east/north in meters relative to the south-west corner
+    pub fn to_local(&self, lat: f64, lon: f64) -> (f64, f64) {
+        local_offset_m(self.min_lat, self.min_lon, lat, lon)
+    }
+
+    /// Geographic bounds as (min_lat, min_lon, max_lat, max_lon)
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        let (max_lat, max_lon) = offset_position(
+            self.min_lat,
+            self.min_lon,
+            self.cols as f64 * self.cell_size_m,
+            self.rows as f64 * self.cell_size_m,
+        );
+        (self.min_lat, self.min_lon, max_lat, max_lon)
+    }
+
+    /// GDAL-style affine transform `[west, dlon, 0, north, 0, -dlat]` in degrees
+    ///
+    /// Uses the same flat-earth scaling as [`DepthGrid::cell_at`], so it maps
+    /// pixel corners exactly onto the cells this grid was binned into.
+    pub fn geotransform(&self) -> [f64; 6] {
+        let dlon = self.cell_size_m / meters_per_degree_lon(self.min_lat);
+        let dlat = self.cell_size_m / METERS_PER_DEGREE_LAT;
+        let north = self.min_lat + self.rows as f64 * dlat;
+        [self.min_lon, dlon, 0.0, north, 0.0, -dlat]
+    }
+
+    /// Minimum and maximum of the filled cells
+    pub fn depth_range(&self) -> Option<(f64, f64)> {
+        let filled = self.values.iter().copied().filter(|v| v.is_finite());
+        filled.fold(None, |range, v| match range {
+            None => Some((v, v)),
+            Some((lo, hi)) => Some((lo.min(v), hi.max(v))),
+        })
+    }
+}
+
+/// Interpolate soundings onto a depth grid covering their extent
+///
+/// None without soundings, or when the cell size is unusable, see [`DepthGrid::new`].
+pub fn grid_soundings(soundings: &[Sounding], config: &GridConfig) -> Option<DepthGrid> {
+    let first = soundings.first()?;
+    let min_lat = soundings.iter().map(|s| s.lat).fold(first.lat, f64::min);
+    let max_lat = soundings.iter().map(|s| s.lat).fold(first.lat, f64::max);
+    let min_lon = soundings.iter().map(|s| s.lon).fold(first.lon, f64::min);
+    let max_lon = soundings.iter().map(|s| s.lon).fold(first.lon, f64::max);
+
+    let (width_m, height_m) = local_offset_m(min_lat, min_lon, max_lat, max_lon);
+    let mut grid = DepthGrid::new(min_lat, min_lon, config.cell_size_m, width_m, height_m)?;
+
+    let points: Vec<(f64, f64, f64)> = soundings
+        .iter()
+        .map(|s| {
+            let (x, y) = grid.to_local(s.lat, s.lon);
+            (x, y, s.depth_m)
+        })
+        .collect();
+
+    match config.method {
+        GridMethod::Nearest => fill_nearest(&mut grid, &points, config.search_radius_m),
+        GridMethod::InverseDistance { power } => {
+            fill_idw(&mut grid, &points, config.search_radius_m, power, config.beam_angle_deg)
+        }
+        GridMethod::Triangulation => fill_tin(&mut grid, &points, config.search_radius_m),
+    }
+
+    Some(grid)
+}
+
+/// Statistic computed over the soundings that fall in each cell by [`bin_soundings`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BinStatistic {
+    Mean,
+    #[default]
+    Median,
+    Min,
+    Max,
+    Count,
+    /// Population standard deviation
+    Std,
+}
+
+impl BinStatistic {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "mean" => Some(Self::Mean),
+            "median" => Some(Self::Median),
+            "min" | "shoalest" => Some(Self::Min),
+            "max" | "deepest" => Some(Self::Max),
+            "count" => Some(Self::Count),
+            "std" | "stddev" => Some(Self::Std),
+            _ => None,
+        }
+    }
+
+    fn compute(self, values: &mut [f64]) -> f64 {
+        let n = values.len() as f64;
+        let mean = || values.iter().sum::<f64>() / n;
+        match self {
+            Self::Mean => mean(),
+            Self::Median => {
+                values.sort_by(f64::total_cmp);
+                let mid = values.len() / 2;
+                if values.len().is_multiple_of(2) {
+                    (values[mid - 1] + values[mid]) / 2.0
+                } else {
+                    values[mid]
+                }
+            }
+            Self::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Self::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Self::Count => n,
+            Self::Std => {
+                let m = mean();
+                (values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / n).sqrt()
+            }
+        }
+    }
+}
+
+/// Bin soundings into square cells without interpolation
+///
+/// Each cell holds `statistic` of the depths inside it; cells without
+/// soundings are NaN (for `Count` as well, so empty cells stay masked).
+/// None under the same conditions as [`grid_soundings`].
+pub fn bin_soundings(soundings: &[Sounding], cell_size_m: f64, statistic: BinStatistic) -> Option<DepthGrid> {
+    let first = soundings.first()?;
+    let min_lat = soundings.iter().map(|s| s.lat).fold(first.lat, f64::min);
+    let max_lat = soundings.iter().map(|s| s.lat).fold(first.lat, f64::max);
+    let min_lon = soundings.iter().map(|s| s.lon).fold(first.lon, f64::min);
+    let max_lon = soundings.iter().map(|s| s.lon).fold(first.lon, f64::max);
+
+    let (width_m, height_m) = local_offset_m(min_lat, min_lon, max_lat, max_lon);
+    // One extra cell so soundings on the north and east edges land inside
+    let mut grid = DepthGrid::new(min_lat, min_lon, cell_size_m, width_m + cell_size_m, height_m + cell_size_m)?;
+    let mut cells: Vec<Vec<f64>> = vec![Vec::new(); grid.rows * grid.cols];
+    for s in soundings {
+        if let Some((row, col)) = grid.cell_at(s.lat, s.lon) {
+            cells[row * grid.cols + col].push(s.depth_m);
+        }
+    }
+    for (value, mut depths) in grid.values.iter_mut().zip(cells) {
+        if !depths.is_empty() {
+            *value = statistic.compute(&mut depths);
+        }
+    }
+    Some(grid)
+}
+
+/// Uniform bucket index for radius searches over local points
+struct PointIndex {
+    bucket_size: f64,
+    buckets: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl PointIndex {
+    fn new(points: &[(f64, f64, f64)], bucket_size: f64) -> Self {
+        let mut buckets: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, (x, y, _)) in points.iter().enumerate() {
+            let key = ((x / bucket_size).floor() as i64, (y / bucket_size).floor() as i64);
+            buckets.entry(key).or_default().push(i);
+        }
+        Self { bucket_size, buckets }
+    }
+
+    /// Indices of points within `radius` of (x, y), with squared distances
+    fn within(&self, points: &[(f64, f64, f64)], x: f64, y: f64, radius: f64) -> Vec<(usize, f64)> {
+        let reach = (radius / self.bucket_size).ceil() as i64;
+        let bx = (x / self.bucket_size).floor() as i64;
+        let by = (y / self.bucket_size).floor() as i64;
+        let radius_sq = radius * radius;
+
+        let mut found = Vec::new();
+        for i in (bx - reach)..=(bx + reach) {
+            for j in (by - reach)..=(by + reach) {
+                if let Some(bucket) = self.buckets.get(&(i, j)) {
+                    for &idx in bucket {
+                        let (px, py, _) = points[idx];
+                        let dist_sq = (px - x).powi(2) + (py - y).powi(2);
+                        if dist_sq <= radius_sq {
+                            found.push((idx, dist_sq));
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+fn fill_nearest(grid: &mut DepthGrid, points: &[(f64, f64, f64)], radius: f64) {
+    let index = PointIndex::new(points, radius.max(grid.cell_size_m));
+    for row in 0..grid.rows {
+        for col in 0..grid.cols {
+            let (x, y) = grid.cell_center_m(row, col);
+            let nearest = index
+                .within(points, x, y, radius)
+                .into_iter()
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((idx, _)) = nearest {
+                grid.set(row, col, points[idx].2);
+            }
+        }
+    }
+}
+
+/// Diameter of the seabed area insonified by a conical beam at the given depth
+pub fn footprint_diameter_m(depth_m: f64, beam_angle_deg: f64) -> f64 {
+    2.0 * depth_m.max(0.0) * (beam_angle_deg.to_radians() / 2.0).tan()
+}
+
+fn fill_idw(grid: &mut DepthGrid, points: &[(f64, f64, f64)], radius: f64, power: f64, beam_angle_deg: Option<f64>) {
+    let index = PointIndex::new(points, radius.max(grid.cell_size_m));
+    for row in 0..grid.rows {
+        for col in 0..grid.cols {
+            let (x, y) = grid.cell_center_m(row, col);
+            let mut weight_sum = 0.0;
+            let mut value_sum = 0.0;
+            let mut exact = None;
+
+            for (idx, dist_sq) in index.within(points, x, y, radius) {
+                if dist_sq < 1e-12 {
+                    exact = Some(points[idx].2);
+                    break;
+                }
+                let mut weight = 1.0 / dist_sq.sqrt().powf(power);
+                if let Some(beam) = beam_angle_deg {
+                    // Floor keeps shallow soundings from taking all the weight
+                    weight /= footprint_diameter_m(points[idx].2, beam).max(0.1).powi(2);
+                }
+                weight_sum += weight;
+                value_sum += weight * points[idx].2;
+            }
+
+            if let Some(depth) = exact {
+                grid.set(row, col, depth);
+            } else if weight_sum > 0.0 {
+                grid.set(row, col, value_sum / weight_sum);
+            }
+        }
+    }
+}
+
+/// Marks a missing neighbor (hull edge of the super-triangle)
+const NO_TRIANGLE: usize = usize::MAX;
+/// Bits per axis of the Hilbert grid used to order insertions
+const HILBERT_ORDER: u32 = 16;
+
+/// Counter-clockwise triangle; `nbr[i]` is the triangle across the edge opposite `v[i]`
+#[derive(Clone, Copy)]
+struct Triangle {
+    v: [usize; 3],
+    nbr: [usize; 3],
+}
+
+/// Twice the signed area of (a, b, c); positive when counter-clockwise
+fn orient(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+/// Positive when `d` lies inside the circumcircle of counter-clockwise (a, b, c)
+fn in_circle(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> f64 {
+    let (adx, ady) = (a.0 - d.0, a.1 - d.1);
+    let (bdx, bdy) = (b.0 - d.0, b.1 - d.1);
+    let (cdx, cdy) = (c.0 - d.0, c.1 - d.1);
+    let (ad, bd, cd) = (adx * adx + ady * ady, bdx * bdx + bdy * bdy, cdx * cdx + cdy * cdy);
+    adx * (bdy * cd - bd * cdy) - ady * (bdx * cd - bd * cdx) + ad * (bdx * cdy - bdy * cdx)
+}
+
+/// Distance along a Hilbert curve filling a `2^order` square
+fn hilbert_index(mut x: u32, mut y: u32, order: u32) -> u64 {
+    let last = (1u32 << order) - 1;
+    let mut d = 0u64;
+    let mut s = 1u32 << (order - 1);
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        d += (s as u64) * (s as u64) * ((3 * rx) ^ ry) as u64;
+        if ry == 0 {
+            if rx == 1 {
+                x = last - x;
+                y = last - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s >>= 1;
+    }
+    d
+}
+
+/// Incremental Delaunay triangulation with neighbor links
+///
+/// Each point is located by walking from the last inserted triangle, and
+/// the Bowyer-Watson cavity is grown across neighbors, so insertion costs
+/// are local. Points are inserted in Hilbert order to keep walks short.
+struct Triangulation {
+    pts: Vec<(f64, f64)>,
+    tris: Vec<Triangle>,
+    /// Insertion number that last visited each triangle
+    mark: Vec<usize>,
+    free: Vec<usize>,
+    last: usize,
+}
+
+impl Triangulation {
+    fn new(pts: Vec<(f64, f64)>, super_triangle: [usize; 3]) -> Self {
+        Self {
+            pts,
+            tris: vec![Triangle {
+                v: super_triangle,
+                nbr: [NO_TRIANGLE; 3],
+            }],
+            mark: vec![0],
+            free: Vec::new(),
+            last: 0,
+        }
+    }
+
+    fn contains(&self, t: usize, p: (f64, f64)) -> bool {
+        let [a, b, c] = self.tris[t].v.map(|v| self.pts[v]);
+        orient(a, b, p) >= 0.0 && orient(b, c, p) >= 0.0 && orient(c, a, p) >= 0.0
+    }
+
+    /// Triangle containing `p`, walking from the last insertion
+    fn locate(&self, p: (f64, f64)) -> Option<usize> {
+        let mut t = self.last;
+        'walk: for _ in 0..self.tris.len() {
+            let tri = self.tris[t];
+            for i in 0..3 {
+                let (a, b) = (self.pts[tri.v[(i + 1) % 3]], self.pts[tri.v[(i + 2) % 3]]);
+                if orient(a, b, p) < 0.0 && tri.nbr[i] != NO_TRIANGLE {
+                    t = tri.nbr[i];
+                    continue 'walk;
+                }
+            }
+            return Some(t);
+        }
+        // Numerical trouble stalled the walk; fall back to a scan
+        let alive = |t: &usize| self.mark[*t] != usize::MAX;
+        (0..self.tris.len()).filter(alive).find(|&t| self.contains(t, p))
+    }
+
+    fn insert(&mut self, i: usize, stamp: usize) {
+        let p = self.pts[i];
+        let Some(start) = self.locate(p) else {
+            return;
+        };
+        if self.tris[start].v.iter().any(|&v| self.pts[v] == p) {
+            return; // duplicate position
+        }
+
+        // Cavity: triangles whose circumcircle holds p, connected to `start`
+        let mut cavity = vec![start];
+        self.mark[start] = stamp;
+        let mut k = 0;
+        while k < cavity.len() {
+            let tri = self.tris[cavity[k]];
+            for &n in &tri.nbr {
+                if n != NO_TRIANGLE && self.mark[n] != stamp {
+                    let [a, b, c] = self.tris[n].v.map(|v| self.pts[v]);
+                    if in_circle(a, b, c, p) > 0.0 {
+                        self.mark[n] = stamp;
+                        cavity.push(n);
+                    }
+                }
+            }
+            k += 1;
+        }
+
+        // Boundary edges (a, b) with the triangle outside each
+        let mut boundary = Vec::new();
+        for &t in &cavity {
+            let tri = self.tris[t];
+            for e in 0..3 {
+                if tri.nbr[e] == NO_TRIANGLE || self.mark[tri.nbr[e]] != stamp {
+                    boundary.push((tri.v[(e + 1) % 3], tri.v[(e + 2) % 3], tri.nbr[e]));
+                }
+            }
+        }
+        for &t in &cavity {
+            self.mark[t] = usize::MAX;
+            self.free.push(t);
+        }
+
+        // Fan of new triangles (a, b, p), linked to the outside and to each other
+        let mut by_start: Vec<(usize, usize)> = Vec::with_capacity(boundary.len());
+        for &(a, b, outside) in &boundary {
+            let t = self.alloc(Triangle {
+                v: [a, b, i],
+                nbr: [NO_TRIANGLE, NO_TRIANGLE, outside],
+            });
+            if outside != NO_TRIANGLE {
+                let o = &mut self.tris[outside];
+                if let Some(e) = (0..3).find(|&e| o.v[(e + 1) % 3] == b && o.v[(e + 2) % 3] == a) {
+                    o.nbr[e] = t;
+                }
+            }
+            by_start.push((a, t));
+        }
+        for &(_, t) in &by_start {
+            // Across (b, p) is the fan triangle starting at b, which sees t across (p, b)
+            let b = self.tris[t].v[1];
+            if let Some(&(_, next)) = by_start.iter().find(|(s, _)| *s == b) {
+                self.tris[t].nbr[0] = next;
+                self.tris[next].nbr[1] = t;
+            }
+        }
+        self.last = by_start.last().map_or(self.last, |&(_, t)| t);
+    }
+
+    fn alloc(&mut self, tri: Triangle) -> usize {
+        match self.free.pop() {
+            Some(t) => {
+                self.tris[t] = tri;
+                self.mark[t] = 0;
+                t
+            }
+            None => {
+                self.tris.push(tri);
+                self.mark.push(0);
+                self.tris.len() - 1
+            }
+        }
+    }
+}
+
+/// Delaunay triangulation of local points (Bowyer-Watson), returning vertex triples
+///
+/// Triangles come out counter-clockwise. Repeated positions are inserted once.
+pub fn delaunay(points: &[(f64, f64)]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+
+    let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let max_x = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+    let extent = (max_x - min_x).max(max_y - min_y).max(1.0);
+    let span = extent * 20.0;
+    let mid_x = (min_x + max_x) / 2.0;
+    let mid_y = (min_y + max_y) / 2.0;
+
+    // Centered coordinates, with the super-triangle appended after the real points
+    let n = points.len();
+    let mut pts: Vec<(f64, f64)> = points.iter().map(|p| (p.0 - mid_x, p.1 - mid_y)).collect();
+    pts.push((-span, -span));
+    pts.push((span, -span));
+    pts.push((0.0, span));
+
+    let cells = ((1u64 << HILBERT_ORDER) - 1) as f64;
+    let mut order: Vec<(u64, usize)> = points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let x = ((p.0 - min_x) / extent * cells) as u32;
+            let y = ((p.1 - min_y) / extent * cells) as u32;
+            (hilbert_index(x, y, HILBERT_ORDER), i)
+        })
+        .collect();
+    order.sort_unstable();
+
+    let mut mesh = Triangulation::new(pts, [n, n + 1, n + 2]);
+    for (stamp, (_, i)) in order.into_iter().enumerate() {
+        mesh.insert(i, stamp + 1);
+    }
+
+    mesh.tris
+        .iter()
+        .zip(&mesh.mark)
+        .filter(|(t, &mark)| mark != usize::MAX && t.v.iter().all(|&v| v < n))
+        .map(|(t, _)| t.v)
+        .collect()
+}
+
+fn fill_tin(grid: &mut DepthGrid, points: &[(f64, f64, f64)], max_edge: f64) {
+    let xy: Vec<(f64, f64)> = points.iter().map(|p| (p.0, p.1)).collect();
+    let max_edge_sq = max_edge * max_edge;
+
+    for tri in delaunay(&xy) {
+        let [a, b, c] = tri.map(|i| points[i]);
+
+        // Skip long, thin triangles that bridge unsurveyed gaps
+        let too_long = [(a, b), (b, c), (c, a)]
+            .iter()
+            .any(|(p, q)| (p.0 - q.0).powi(2) + (p.1 - q.1).powi(2) > max_edge_sq);
+        if too_long {
+            continue;
+        }
+
+        let det = (b.1 - c.1) * (a.0 - c.0) + (c.0 - b.0) * (a.1 - c.1);
+        if det.abs() < 1e-12 {
+            continue;
+        }
+
+        let cell = grid.cell_size_m;
+        let col_lo = ((a.0.min(b.0).min(c.0) / cell).floor().max(0.0)) as usize;
+        let col_hi = ((a.0.max(b.0).max(c.0) / cell).ceil() as usize).min(grid.cols);
+        let south_lo = ((a.1.min(b.1).min(c.1) / cell).floor().max(0.0)) as usize;
+        let south_hi = ((a.1.max(b.1).max(c.1) / cell).ceil() as usize).min(grid.rows);
+
+        for south in south_lo..south_hi {
+            let row = grid.rows - 1 - south;
+            for col in col_lo..col_hi {
+                let (x, y) = grid.cell_center_m(row, col);
+                let w1 = ((b.1 - c.1) * (x - c.0) + (c.0 - b.0) * (y - c.1)) / det;
+                let w2 = ((c.1 - a.1) * (x - c.0) + (a.0 - c.0) * (y - c.1)) / det;
+                let w3 = 1.0 - w1 - w2;
+                if w1 >= -1e-9 && w2 >= -1e-9 && w3 >= -1e-9 {
+                    grid.set(row, col, w1 * a.2 + w2 * b.2 + w3 * c.2);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::offset_position;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_points(n: usize, seed: u64) -> Vec<(f64, f64)> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n)
+            .map(|_| (rng.gen_range(0.0..500.0), rng.gen_range(0.0..300.0)))
+            .collect()
+    }
+
+    #[test]
+    fn delaunay_circumcircles_are_empty() {
+        let pts = random_points(400, 7);
+        let tris = delaunay(&pts);
+        assert!(tris.len() > 700, "only {} triangles", tris.len());
+        for t in &tris {
+            let [a, b, c] = t.map(|i| pts[i]);
+            assert!(orient(a, b, c) > 0.0, "triangle not counter-clockwise");
+            for (i, &p) in pts.iter().enumerate() {
+                if !t.contains(&i) {
+                    let inside = in_circle(a, b, c, p);
+                    assert!(inside <= 1e-6, "point {} inside circumcircle of {:?}", i, t);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn delaunay_covers_regular_grid_and_skips_duplicates() {
+        let mut pts: Vec<(f64, f64)> = (0..100).map(|i| ((i % 10) as f64, (i / 10) as f64)).collect();
+        pts.push((3.0, 3.0));
+        let tris = delaunay(&pts);
+        let area: f64 = tris.iter().map(|t| orient(pts[t[0]], pts[t[1]], pts[t[2]]) / 2.0).sum();
+        assert!((area - 81.0).abs() < 1e-6, "area {}", area);
+        assert!(tris.iter().all(|t| !t.contains(&100)));
+    }
+
+    #[test]
+    fn delaunay_handles_degenerate_input() {
+        assert!(delaunay(&[(0.0, 0.0), (1.0, 1.0)]).is_empty());
+        assert!(delaunay(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]).is_empty());
+    }
+
+    #[test]
+    fn delaunay_scales_to_survey_sizes() {
+        let pts = random_points(100_000, 11);
+        let tris = delaunay(&pts);
+        // A triangulation of n points has at most 2n - 5 triangles
+        let count = tris.len();
+        assert!(count > 199_000 && count <= 2 * pts.len() - 5, "{} triangles", count);
+    }
+
+    #[test]
+    fn beam_footprints_weight_shallow_soundings_up() {
+        assert!((footprint_diameter_m(10.0, 20.0) - 3.526_539).abs() < 1e-6);
+        assert_eq!(footprint_diameter_m(-1.0, 20.0), 0.0);
+
+        // One cell with a 2 m and a 20 m sounding at the same distance from its center
+        let (lat, lon) = offset_position(45.0, -63.0, 10.0, 0.0);
+        let soundings = [Sounding::new(0.0, 45.0, -63.0, 2.0), Sounding::new(0.0, lat, lon, 20.0)];
+        let mut config = GridConfig {
+            cell_size_m: 10.0,
+            ..Default::default()
+        };
+        let plain = grid_soundings(&soundings, &config).unwrap();
+        assert_eq!((plain.rows, plain.cols), (1, 1));
+        assert!((plain.get(0, 0) - 11.0).abs() < 1e-3, "{}", plain.get(0, 0));
+
+        // Footprints grow with depth, so the weights differ by (20 / 2)² = 100
+        config.beam_angle_deg = Some(20.0);
+        let weighted = grid_soundings(&soundings, &config).unwrap();
+        let expected = (2.0 * 100.0 + 20.0) / 101.0;
+        assert!((weighted.get(0, 0) - expected).abs() < 1e-3, "{}", weighted.get(0, 0));
+    }
+
+    fn plane_soundings() -> Vec<Sounding> {
+        let mut rng = StdRng::seed_from_u64(3);
+        (0..2000)
+            .map(|_| {
+                let (east, north) = (rng.gen_range(0.0..200.0), rng.gen_range(0.0..200.0));
+                let (lat, lon) = offset_position(45.0, -63.0, east, north);
+                Sounding::new(0.0, lat, lon, 5.0 + 0.01 * east + 0.02 * north)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn triangulation_reproduces_a_plane() {
+        let soundings = plane_soundings();
+        let config = GridConfig {
+            cell_size_m: 10.0,
+            search_radius_m: 50.0,
+            method: GridMethod::Triangulation,
+            beam_angle_deg: None,
+        };
+        let grid = grid_soundings(&soundings, &config).unwrap();
+        let mut filled = 0;
+        for row in 0..grid.rows {
+            for col in 0..grid.cols {
+                let value = grid.get(row, col);
+                if value.is_finite() {
+                    let (lat, lon) = grid.cell_center(row, col);
+                    let (east, north) = local_offset_m(45.0, -63.0, lat, lon);
+                    assert!((value - (5.0 + 0.01 * east + 0.02 * north)).abs() < 0.01);
+                    filled += 1;
+                }
+            }
+        }
+        assert!(filled > grid.rows * grid.cols * 3 / 4);
+    }
+
+    #[test]
+    fn idw_and_nearest_stay_within_data_range() {
+        let soundings = plane_soundings();
+        for method in [GridMethod::Nearest, GridMethod::InverseDistance { power: 2.0 }] {
+            let config = GridConfig {
+                method,
+                ..GridConfig::default()
+            };
+            let grid = grid_soundings(&soundings, &config).unwrap();
+            let (lo, hi) = grid.depth_range().unwrap();
+            assert!(lo >= 5.0 && hi <= 11.0, "{:?}: {}..{}", method, lo, hi);
+        }
+        assert!(grid_soundings(&[], &GridConfig::default()).is_none());
+    }
+
+    #[test]
+    fn binning_takes_the_cell_statistic() {
+        let soundings = [
+            Sounding::new(0.0, 45.0, -63.0, 2.0),
+            Sounding::new(0.0, 45.0, -63.0, 4.0),
+            Sounding::new(0.0, 45.0, -63.0, 9.0),
+        ];
+        let median = bin_soundings(&soundings, 5.0, BinStatistic::Median).unwrap();
+        assert_eq!(median.depth_range(), Some((4.0, 4.0)));
+        let count = bin_soundings(&soundings, 5.0, BinStatistic::Count).unwrap();
+        assert_eq!(count.depth_range(), Some((3.0, 3.0)));
+        assert!(bin_soundings(&soundings, 0.0, BinStatistic::Mean).is_none());
+    }
+
+    #[test]
+    fn binning_places_soundings_by_cell_and_geotransform() {
+        let at = |east: f64, north: f64, depth: f64| {
+            let (lat, lon) = offset_position(45.0, -63.0, east, north);
+            Sounding::new(0.0, lat, lon, depth)
+        };
+        let soundings = [at(0.0, 0.0, 2.0), at(3.0, 3.0, 4.0), at(23.0, 4.0, 6.0), at(4.0, 23.0, 8.0)];
+        let cell = |statistic| bin_soundings(&soundings, 10.0, statistic).unwrap().get(3, 0);
+        assert_eq!(
+            [BinStatistic::Mean, BinStatistic::Median, BinStatistic::Min, BinStatistic::Max, BinStatistic::Count]
+                .map(cell),
+            [3.0, 3.0, 2.0, 4.0, 2.0]
+        );
+        assert!((cell(BinStatistic::Std) - 1.0).abs() < 1e-12);
+
+        // A 23 m extent plus the extra edge cell gives 4 x 4 cells, south-west cell at the bottom left
+        let grid = bin_soundings(&soundings, 10.0, BinStatistic::Mean).unwrap();
+        assert_eq!((grid.rows, grid.cols), (4, 4));
+        assert_eq!((grid.get(3, 2), grid.get(1, 0)), (6.0, 8.0));
+        assert_eq!(grid.values.iter().filter(|v| v.is_nan()).count(), 13);
+
+        // The geotransform maps soundings back into the pixel they were binned into;
+        // the first one sits exactly on the south-west corner, so it is left out
+        let [west, dlon, _, north, _, dlat] = grid.geotransform();
+        assert_eq!((west, north + grid.rows as f64 * dlat), (grid.min_lon, grid.min_lat));
+        let pixel = |s: &Sounding| (((s.lat - north) / dlat) as usize, ((s.lon - west) / dlon) as usize);
+        assert_eq!(soundings[1..].iter().map(pixel).collect::<Vec<_>>(), [(3, 0), (3, 2), (1, 0)]);
+        assert_eq!(BinStatistic::from_name("Shoalest"), Some(BinStatistic::Min));
+        assert_eq!(BinStatistic::from_name("mode"), None);
+    }
+
+    #[test]
+    fn unusable_cell_sizes_give_no_grid() {
+        let soundings = plane_soundings();
+        for cell_size_m in [0.0, -5.0, f64::NAN, f64::INFINITY] {
+            let config = GridConfig {
+                cell_size_m,
+                ..GridConfig::default()
+            };
+            assert!(grid_soundings(&soundings, &config).is_none(), "{}", cell_size_m);
+            assert!(bin_soundings(&soundings, cell_size_m, BinStatistic::Mean).is_none());
+        }
+        // Millimeter cells over a 100 km extent would need 10^16 cells
+        let wide = [Sounding::new(0.0, 45.0, -63.0, 2.0), Sounding::new(0.0, 45.9, -62.0, 4.0)];
+        assert!(bin_soundings(&wide, 0.001, BinStatistic::Mean).is_none());
+        assert!(DepthGrid::new(45.0, -63.0, 1.0, 4000.0, 4000.0).is_some());
+        assert!(DepthGrid::new(45.0, -63.0, 1.0, 5000.0, 5000.0).is_none());
+    }
+}
@@ -0,0 +1,175 @@
+// Multi-frequency channel fusion (e.g. 77/200 kHz or CHIRP bands)
+// src/imaging/fusion.rs
+
+use super::palette::ImagingSettings;
+use super::waterfall::{channel_pings, render_waterfall, Waterfall};
+use crate::survey::Ping;
+
+/// How aligned channel layers are combined into one image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FusionMode {
+    /// Mean intensity of the layers
+    Blend,
+    /// Strongest return of the layers
+    Max,
+    /// Layers placed left to right in channel order
+    SideBySide,
+}
+
+impl FusionMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "blend" | "mean" => Some(Self::Blend),
+            "max" => Some(Self::Max),
+            "side-by-side" | "side_by_side" | "sidebyside" => Some(Self::SideBySide),
+            _ => None,
+        }
+    }
+}
+
+/// Rows of same-time pings, one per requested channel in the given order
+///
+/// The first channel is the reference; each of its pings is paired with the
+/// nearest-in-time ping of every other channel. Rows where any channel has
+/// no ping within `tolerance_s` are dropped.
+pub fn align_channels<'a>(pings: &'a [Ping], channels: &[u16], tolerance_s: f64) -> Vec<Vec<&'a Ping>> {
+    let Some((&reference, others)) = channels.split_first() else {
+        return Vec::new();
+    };
+    let others: Vec<Vec<&Ping>> = others
+        .iter()
+        .map(|&ch| {
+            let mut selected = channel_pings(pings, ch);
+            selected.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+            selected
+        })
+        .collect();
+
+    channel_pings(pings, reference)
+        .into_iter()
+        .filter_map(|base| {
+            let mut row = vec![base];
+            for selected in &others {
+                row.push(nearest(selected, base.timestamp, tolerance_s)?);
+            }
+            Some(row)
+        })
+        .collect()
+}
+
+fn nearest<'a>(sorted: &[&'a Ping], timestamp: f64, tolerance_s: f64) -> Option<&'a Ping> {
+    let i = sorted.partition_point(|p| p.timestamp < timestamp);
+    [i.checked_sub(1), Some(i)]
+        .into_iter()
+        .flatten()
+        .filter_map(|j| sorted.get(j))
+        .min_by(|a, b| (a.timestamp - timestamp).abs().total_cmp(&(b.timestamp - timestamp).abs()))
+        .filter(|p| (p.timestamp - timestamp).abs() <= tolerance_s)
+        .copied()
+}
+
+/// One waterfall per channel over the aligned rows, each with its own levels
+pub fn fusion_layers(aligned: &[Vec<&Ping>], width: usize, settings: &ImagingSettings) -> Vec<Waterfall> {
+    let count = aligned.first().map_or(0, Vec::len);
+    (0..count)
+        .map(|k| {
+            let column: Vec<&Ping> = aligned.iter().map(|row| row[k]).collect();
+            render_waterfall(&column, width, &settings.levels(column[0].channel_id))
+        })
+        .collect()
+}
+
+/// Composite image of aligned channels; side-by-side output is `width` per layer
+pub fn render_fusion(aligned: &[Vec<&Ping>], width: usize, settings: &ImagingSettings, mode: FusionMode) -> Waterfall {
+    let layers = fusion_layers(aligned, width, settings);
+    let height = aligned.len();
+    if layers.is_empty() {
+        return Waterfall {
+            width,
+            height: 0,
+            pixels: Vec::new(),
+        };
+    }
+
+    match mode {
+        FusionMode::SideBySide => {
+            let total = width * layers.len();
+            let mut pixels = Vec::with_capacity(total * height);
+            for row in 0..height {
+                for layer in &layers {
+                    pixels.extend_from_slice(&layer.pixels[row * width..(row + 1) * width]);
+                }
+            }
+            Waterfall {
+                width: total,
+                height,
+                pixels,
+            }
+        }
+        FusionMode::Blend | FusionMode::Max => {
+            let pixels = (0..width * height)
+                .map(|i| {
+                    let values = layers.iter().map(|l| l.pixels[i] as u32);
+                    if mode == FusionMode::Max {
+                        values.max().unwrap_or(0) as u8
+                    } else {
+                        (values.sum::<u32>() as f64 / layers.len() as f64).round() as u8
+                    }
+                })
+                .collect();
+            Waterfall { width, height, pixels }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(channel_id: u16, timestamp: f64, value: u8) -> Ping {
+        Ping {
+            channel_id,
+            timestamp,
+            range_m: 20.0,
+            samples: vec![value; 4],
+            ..Default::default()
+        }
+    }
+
+    fn survey() -> Vec<Ping> {
+        vec![
+            ping(0, 0.0, 40),
+            ping(1, 0.1, 100),
+            ping(0, 1.0, 40),
+            ping(1, 1.2, 100),
+            ping(0, 2.0, 40),
+            ping(1, 2.9, 100),
+        ]
+    }
+
+    #[test]
+    fn aligns_nearest_pings_and_drops_unmatched_rows() {
+        let pings = survey();
+        let rows = align_channels(&pings, &[0, 1], 0.3);
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[1][0].timestamp, rows[1][1].timestamp), (1.0, 1.2));
+        assert_eq!(align_channels(&pings, &[1, 0], 0.3)[0][1].timestamp, 0.0);
+        assert!(align_channels(&pings, &[], 0.3).is_empty());
+    }
+
+    #[test]
+    fn fusion_modes_combine_layers() {
+        let pings = survey();
+        let rows = align_channels(&pings, &[0, 1], 0.3);
+        let settings = ImagingSettings::default();
+
+        let blend = render_fusion(&rows, 4, &settings, FusionMode::Blend);
+        assert_eq!((blend.width, blend.height), (4, 2));
+        assert!(blend.pixels.iter().all(|&v| v == 70));
+        let max = render_fusion(&rows, 4, &settings, FusionMode::Max);
+        assert!(max.pixels.iter().all(|&v| v == 100));
+        let side = render_fusion(&rows, 4, &settings, FusionMode::from_name("side-by-side").unwrap());
+        assert_eq!(side.width, 8);
+        assert_eq!(&side.pixels[..8], &[40, 40, 40, 40, 100, 100, 100, 100]);
+    }
+}
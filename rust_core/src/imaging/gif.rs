@@ -0,0 +1,169 @@
+// Minimal dependency-free animated GIF encoder
+// src/imaging/gif.rs
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+const MIN_CODE_SIZE: u8 = 8;
+const MAX_CODES: u16 = 4096;
+
+/// Animated GIF89a writer with a single global 256-color palette
+pub struct GifEncoder<W: Write> {
+    out: W,
+    width: u16,
+    height: u16,
+}
+
+impl<W: Write> GifEncoder<W> {
+    /// Write the header, global palette and an infinite-loop extension
+    pub fn new(mut out: W, width: u16, height: u16, palette: &[[u8; 3]; 256]) -> io::Result<Self> {
+        out.write_all(b"GIF89a")?;
+        out.write_all(&width.to_le_bytes())?;
+        out.write_all(&height.to_le_bytes())?;
+        out.write_all(&[0xF7, 0, 0])?; // global table, 8 bits per color, 256 entries
+        for color in palette {
+            out.write_all(color)?;
+        }
+        // NETSCAPE2.0 application extension: loop forever
+        out.write_all(&[0x21, 0xFF, 0x0B])?;
+        out.write_all(b"NETSCAPE2.0")?;
+        out.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+
+        Ok(Self { out, width, height })
+    }
+
+    /// Append a full-size frame of palette indices shown for `delay_cs` hundredths of a second
+    pub fn write_frame(&mut self, indices: &[u8], delay_cs: u16) -> io::Result<()> {
+        if indices.len() != self.width as usize * self.height as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame does not match image size"));
+        }
+
+        // Graphic control extension
+        self.out.write_all(&[0x21, 0xF9, 0x04, 0x00])?;
+        self.out.write_all(&delay_cs.to_le_bytes())?;
+        self.out.write_all(&[0x00, 0x00])?;
+
+        // Image descriptor covering the whole canvas
+        self.out.write_all(&[0x2C, 0, 0, 0, 0])?;
+        self.out.write_all(&self.width.to_le_bytes())?;
+        self.out.write_all(&self.height.to_le_bytes())?;
+        self.out.write_all(&[0x00, MIN_CODE_SIZE])?;
+
+        let data = lzw_encode(indices);
+        for block in data.chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+        self.out.write_all(&[0x00])
+    }
+
+    /// Write the trailer and return the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.write_all(&[0x3B])?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// LSB-first variable-width code packer
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn push(&mut self, code: u16, size: u8) {
+        self.acc |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.acc as u8);
+            self.acc >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.acc as u8);
+        }
+        self.bytes
+    }
+}
+
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    let clear: u16 = 1 << MIN_CODE_SIZE;
+    let end = clear + 1;
+    let mut writer = BitWriter { bytes: Vec::new(), acc: 0, bits: 0 };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut size = MIN_CODE_SIZE + 1;
+    let mut next = end + 1;
+
+    writer.push(clear, size);
+    let mut pixels = indices.iter();
+    let Some(&first) = pixels.next() else {
+        writer.push(end, size);
+        return writer.finish();
+    };
+    let mut prefix = first as u16;
+
+    for &k in pixels {
+        if let Some(&code) = table.get(&(prefix, k)) {
+            prefix = code;
+            continue;
+        }
+        writer.push(prefix, size);
+        if next < MAX_CODES {
+            table.insert((prefix, k), next);
+            next += 1;
+            if next > (1 << size) && size < 12 {
+                size += 1;
+            }
+        } else {
+            writer.push(clear, size);
+            table.clear();
+            size = MIN_CODE_SIZE + 1;
+            next = end + 1;
+        }
+        prefix = k as u16;
+    }
+
+    writer.push(prefix, size);
+    writer.push(end, size);
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::decode_gif;
+
+    #[test]
+    fn frames_round_trip_through_lzw() {
+        let mut palette = [[0u8; 3]; 256];
+        palette[7] = [10, 20, 30];
+        // Noisy enough to fill the code table and force clear codes, plus a flat frame
+        let mut state = 12345u32;
+        let noise: Vec<u8> = (0..64 * 64)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        let flat = vec![7u8; 64 * 64];
+
+        let mut encoder = GifEncoder::new(Vec::new(), 64, 64, &palette).unwrap();
+        encoder.write_frame(&noise, 5).unwrap();
+        encoder.write_frame(&flat, 100).unwrap();
+        assert_eq!(encoder.write_frame(&flat[1..], 5).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        let bytes = encoder.finish().unwrap();
+
+        let (size, colors, frames) = decode_gif(&bytes);
+        assert_eq!(size, (64, 64));
+        assert_eq!(colors[7], [10, 20, 30]);
+        assert_eq!(frames.len(), 2);
+        assert_eq!((frames[0].delay_cs, frames[1].delay_cs), (5, 100));
+        assert!(frames[0].indices == noise);
+        assert!(frames[1].indices == flat);
+    }
+}
@@ -0,0 +1,66 @@
+// Raster imaging of sonar data
+// src/imaging/mod.rs
+
+pub mod fusion;
+pub mod gif;
+pub mod palette;
+pub mod plot;
+pub mod png;
+pub mod pyramid;
+pub mod quicklook;
+pub mod tiles;
+pub mod video;
+pub mod waterfall;
+
+/// North-up 8-bit raster spanning a lat/lon bounding box (row 0 is north)
+#[derive(Debug, Clone)]
+pub struct GeoRaster {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<u8>,
+    pub mask: Vec<bool>,
+}
+
+impl GeoRaster {
+    /// Blank (fully masked) raster
+    pub fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64, width: usize, height: usize) -> Self {
+        Self {
+            min_lat,
+            min_lon,
+            max_lat,
+            max_lon,
+            width,
+            height,
+            values: vec![0; width * height],
+            mask: vec![false; width * height],
+        }
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: u8) {
+        let idx = row * self.width + col;
+        self.values[idx] = value;
+        self.mask[idx] = true;
+    }
+
+    /// Nearest-pixel sample at a position, None outside the raster or where masked
+    pub fn sample(&self, lat: f64, lon: f64) -> Option<u8> {
+        if lat < self.min_lat || lat > self.max_lat || lon < self.min_lon || lon > self.max_lon {
+            return None;
+        }
+        let fx = (lon - self.min_lon) / (self.max_lon - self.min_lon);
+        let fy = (self.max_lat - lat) / (self.max_lat - self.min_lat);
+        let col = ((fx * self.width as f64) as usize).min(self.width - 1);
+        let row = ((fy * self.height as f64) as usize).min(self.height - 1);
+        let idx = row * self.width + col;
+
+        if self.mask[idx] {
+            Some(self.values[idx])
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,364 @@
+// Sonar color palettes and per-channel intensity adjustments
+// src/imaging/palette.rs
+
+use crate::survey::Ping;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Built-in palettes matching common chartplotter looks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinPalette {
+    Grayscale,
+    GarminAmber,
+    LowranceBlue,
+    Viridis,
+}
+
+impl BuiltinPalette {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "grayscale" | "gray" | "grey" => Some(Self::Grayscale),
+            "garmin" | "amber" | "garmin_amber" => Some(Self::GarminAmber),
+            "lowrance" | "blue" | "lowrance_blue" => Some(Self::LowranceBlue),
+            "viridis" => Some(Self::Viridis),
+            _ => None,
+        }
+    }
+
+    fn stops(self) -> &'static [[u8; 3]] {
+        match self {
+            Self::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+            Self::GarminAmber => &[[0, 0, 0], [60, 20, 0], [170, 80, 0], [255, 170, 30], [255, 240, 180]],
+            Self::LowranceBlue => &[[0, 0, 40], [0, 40, 140], [40, 120, 220], [150, 210, 255], [255, 255, 255]],
+            Self::Viridis => &[
+                [68, 1, 84],
+                [71, 44, 122],
+                [59, 82, 139],
+                [44, 114, 142],
+                [33, 145, 140],
+                [40, 174, 128],
+                [94, 201, 98],
+                [173, 220, 48],
+                [253, 231, 37],
+            ],
+        }
+    }
+}
+
+/// 256-entry lookup from sample intensity to RGB
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [[u8; 3]; 256],
+}
+
+impl Palette {
+    pub fn builtin(kind: BuiltinPalette) -> Self {
+        Self::from_stops(kind.stops())
+    }
+
+    /// Interpolate evenly spaced color stops into a full lookup table
+    pub fn from_stops(stops: &[[u8; 3]]) -> Self {
+        let mut colors = [[0u8; 3]; 256];
+        match stops.len() {
+            0 => {}
+            1 => colors = [stops[0]; 256],
+            n => {
+                for (i, color) in colors.iter_mut().enumerate() {
+                    let pos = i as f64 / 255.0 * (n - 1) as f64;
+                    let lo = (pos.floor() as usize).min(n - 2);
+                    let t = pos - lo as f64;
+                    for c in 0..3 {
+                        let a = stops[lo][c] as f64;
+                        let b = stops[lo + 1][c] as f64;
+                        color[c] = (a + (b - a) * t).round() as u8;
+                    }
+                }
+            }
+        }
+        Self { colors }
+    }
+
+    /// Load a custom palette: one color stop per line as `#rrggbb` or `r g b`
+    ///
+    /// Blank lines and lines starting with `;` or `//` are ignored. Any
+    /// number of stops is accepted and spread evenly over the intensity range.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut stops = Vec::new();
+
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with("//") {
+                continue;
+            }
+            let color = parse_color(line).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("bad palette color on line {}", line_no + 1))
+            })?;
+            stops.push(color);
+        }
+
+        if stops.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "palette file has no colors"));
+        }
+        Ok(Self::from_stops(&stops))
+    }
+
+    pub fn color(&self, value: u8) -> [u8; 3] {
+        self.colors[value as usize]
+    }
+
+    /// Map 8-bit intensities to packed RGB
+    pub fn colorize(&self, values: &[u8]) -> Vec<u8> {
+        values.iter().flat_map(|&v| self.colors[v as usize]).collect()
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::builtin(BuiltinPalette::Grayscale)
+    }
+}
+
+fn parse_color(text: &str) -> Option<[u8; 3]> {
+    if let Some(hex) = text.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some([channel(0)?, channel(2)?, channel(4)?]);
+    }
+
+    let parts: Vec<u8> = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    match parts[..] {
+        [r, g, b] => Some([r, g, b]),
+        _ => None,
+    }
+}
+
+/// Intensity adjustments applied to a channel's samples before coloring
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Levels {
+    /// Overall gain in dB
+    pub gain_db: f64,
+    /// Contrast multiplier around mid-gray (1.0 = unchanged)
+    pub contrast: f64,
+    /// Linear time-varied gain in dB per meter of range
+    pub tvg_db_per_m: f64,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self {
+            gain_db: 0.0,
+            contrast: 1.0,
+            tvg_db_per_m: 0.0,
+        }
+    }
+}
+
+impl Levels {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Histogram stretch mapping the `low`..`high` percentiles of the samples to 0..255
+    ///
+    /// Zero samples (no return) are ignored. Falls back to identity levels
+    /// when there are too few distinct intensities to stretch.
+    pub fn auto(pings: &[&Ping], low_percentile: f64, high_percentile: f64) -> Self {
+        let mut histogram = [0u64; 256];
+        for ping in pings {
+            for &v in &ping.samples {
+                histogram[v as usize] += 1;
+            }
+        }
+        histogram[0] = 0;
+        let total: u64 = histogram.iter().sum();
+        if total == 0 {
+            return Self::default();
+        }
+
+        let percentile = |p: f64| {
+            let wanted = (p.clamp(0.0, 100.0) / 100.0 * total as f64).ceil().max(1.0) as u64;
+            let mut seen = 0;
+            histogram
+                .iter()
+                .position(|&n| {
+                    seen += n;
+                    seen >= wanted
+                })
+                .unwrap_or(255) as f64
+        };
+        let (lo, hi) = (percentile(low_percentile), percentile(high_percentile));
+        if hi <= lo {
+            return Self::default();
+        }
+        // Solve (v·g - 127.5)·c + 127.5 = (v - lo)·255 / (hi - lo) for g and c
+        Self {
+            gain_db: 20.0 * (255.0 / (hi + lo)).log10(),
+            contrast: (hi + lo) / (hi - lo),
+            tvg_db_per_m: 0.0,
+        }
+    }
+
+    /// Adjusted copy of a ping's samples
+    pub fn apply(&self, ping: &Ping) -> Vec<u8> {
+        if self.is_identity() {
+            return ping.samples.clone();
+        }
+        let spacing = ping.sample_spacing_m();
+
+        ping.samples
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                let gain_db = self.gain_db + self.tvg_db_per_m * i as f64 * spacing;
+                let amplified = v as f64 * 10f64.powf(gain_db / 20.0);
+                let contrasted = (amplified - 127.5) * self.contrast + 127.5;
+                contrasted.round().clamp(0.0, 255.0) as u8
+            })
+            .collect()
+    }
+}
+
+/// Palette plus per-channel levels shared by the PNG, mosaic and video renderers
+#[derive(Debug, Clone, Default)]
+pub struct ImagingSettings {
+    pub palette: Palette,
+    pub default_levels: Levels,
+    pub channel_levels: HashMap<u16, Levels>,
+}
+
+impl ImagingSettings {
+    pub fn with_palette(palette: Palette) -> Self {
+        Self {
+            palette,
+            ..Default::default()
+        }
+    }
+
+    /// Fill in stretched levels for every channel without manually set levels
+    pub fn auto_levels(&mut self, pings: &[Ping]) {
+        let mut channels: Vec<u16> = pings.iter().map(|p| p.channel_id).collect();
+        channels.sort_unstable();
+        channels.dedup();
+        for channel in channels {
+            self.channel_levels.entry(channel).or_insert_with(|| {
+                let selected: Vec<&Ping> = pings.iter().filter(|p| p.channel_id == channel).collect();
+                Levels::auto(&selected, 1.0, 99.0)
+            });
+        }
+    }
+
+    pub fn levels(&self, channel_id: u16) -> Levels {
+        self.channel_levels
+            .get(&channel_id)
+            .copied()
+            .unwrap_or(self.default_levels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(channel_id: u16, samples: Vec<u8>) -> Ping {
+        Ping {
+            channel_id,
+            range_m: 50.0,
+            samples,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stops_interpolate_across_the_table() {
+        let gray = Palette::builtin(BuiltinPalette::from_name("Grey").unwrap());
+        assert_eq!(gray.color(0), [0, 0, 0]);
+        assert_eq!(gray.color(128), [128, 128, 128]);
+        assert_eq!(gray.color(255), [255, 255, 255]);
+        let three = Palette::from_stops(&[[0, 0, 0], [255, 0, 0], [255, 255, 0]]);
+        assert_eq!(three.color(0), [0, 0, 0]);
+        assert_eq!(three.color(255), [255, 255, 0]);
+        assert_eq!(three.color(128)[0], 255);
+        assert_eq!(Palette::from_stops(&[[9, 8, 7]]).color(200), [9, 8, 7]);
+        assert_eq!(gray.colorize(&[0, 255]), vec![0, 0, 0, 255, 255, 255]);
+        assert_eq!(BuiltinPalette::from_name("sepia"), None);
+    }
+
+    #[test]
+    fn loads_custom_palette_files() {
+        let path = crate::testutil::scratch("palette", "custom.pal");
+        fs::write(&path, "; ramp\n#000000\n\n// middle\n0 0 255\n255, 255, 255\n").unwrap();
+        let palette = Palette::load(&path).unwrap();
+        assert_eq!(palette.color(0), [0, 0, 0]);
+        assert_eq!(palette.color(255), [255, 255, 255]);
+        fs::write(&path, "#00000\n").unwrap();
+        assert_eq!(Palette::load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        fs::write(&path, "; nothing\n").unwrap();
+        assert!(Palette::load(&path).is_err());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn levels_apply_gain_contrast_and_tvg() {
+        let p = ping(0, vec![100; 4]);
+        assert_eq!(Levels::default().apply(&p), p.samples);
+        let gain = Levels {
+            gain_db: 20.0 * 2f64.log10(),
+            ..Default::default()
+        };
+        assert_eq!(gain.apply(&p), vec![200; 4]);
+        let contrast = Levels {
+            contrast: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(contrast.apply(&ping(0, vec![100, 127, 200, 250])), vec![73, 127, 255, 255]);
+        let tvg = Levels {
+            tvg_db_per_m: 0.5,
+            ..Default::default()
+        };
+        // 12.5 m sample spacing: 0, 6.25, 12.5 and 18.75 dB
+        assert_eq!(tvg.apply(&p), vec![100, 205, 255, 255]);
+    }
+
+    #[test]
+    fn auto_levels_stretch_the_percentile_range_to_full_scale() {
+        let pings = [ping(0, (50..=150).collect())];
+        let refs: Vec<&Ping> = pings.iter().collect();
+        let levels = Levels::auto(&refs, 0.0, 100.0);
+        let out = levels.apply(&pings[0]);
+        assert_eq!(out[0], 0);
+        assert!(out[50].abs_diff(128) <= 1);
+        assert_eq!(out[100], 255);
+        assert!(out.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn auto_levels_fall_back_to_identity_without_contrast() {
+        let silent = [ping(0, vec![0; 32])];
+        assert!(Levels::auto(&silent.iter().collect::<Vec<_>>(), 1.0, 99.0).is_identity());
+        let flat = [ping(0, vec![0, 80, 80, 80])];
+        assert!(Levels::auto(&flat.iter().collect::<Vec<_>>(), 1.0, 99.0).is_identity());
+    }
+
+    #[test]
+    fn auto_levels_keep_manually_set_channels() {
+        let manual = Levels {
+            gain_db: 6.0,
+            ..Default::default()
+        };
+        let mut settings = ImagingSettings::default();
+        settings.channel_levels.insert(1, manual);
+        settings.auto_levels(&[ping(0, (10..=200).collect()), ping(1, (10..=200).collect())]);
+        assert_eq!(settings.levels(1), manual);
+        assert!(!settings.levels(0).is_identity());
+        assert!(settings.levels(7).is_identity());
+    }
+}
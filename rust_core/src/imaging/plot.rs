@@ -0,0 +1,163 @@
+// Plotting-library-neutral image and track data
+// src/imaging/plot.rs
+
+use super::palette::ImagingSettings;
+use super::quicklook::Quicklook;
+use super::waterfall::{range_segments, render_waterfall};
+use crate::survey::Ping;
+
+/// RGB image plus the data coordinates of its outer pixel edges
+///
+/// Follows the `imshow(origin="upper")` convention: row 0 is drawn at the
+/// top and `extent` is `(left, right, bottom, top)`, so a plot only needs
+/// the pixels, the extent and the axis labels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotImage {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major RGB triplets
+    pub rgb: Vec<u8>,
+    pub extent: [f64; 4],
+    pub x_label: &'static str,
+    pub y_label: &'static str,
+}
+
+impl PlotImage {
+    /// Image in pixel coordinates, for previews without a data axis
+    pub fn from_quicklook(image: Quicklook) -> Self {
+        Self {
+            width: image.width,
+            height: image.height,
+            extent: [0.0, image.width as f64, image.height as f64, 0.0],
+            rgb: image.rgb,
+            x_label: "pixel",
+            y_label: "pixel",
+        }
+    }
+}
+
+/// Waterfall of one channel's pings with range across and time down
+///
+/// x is meters from the transducer when the channel keeps one range
+/// setting; after range changes each segment is scaled to its own range,
+/// so x becomes the fraction of range (0..1). y runs from the first ping
+/// time at the top to the last at the bottom, in epoch seconds.
+pub fn waterfall_plot(pings: &[&Ping], width: usize, settings: &ImagingSettings) -> PlotImage {
+    let channel = pings.first().map_or(0, |p| p.channel_id);
+    let waterfall = render_waterfall(pings, width.max(1), &settings.levels(channel));
+    let segments = range_segments(pings);
+    let (right, x_label) = match segments[..] {
+        [single] => (single.range_m, "range_m"),
+        _ => (1.0, "range_fraction"),
+    };
+    let (first, last) = match (pings.first(), pings.last()) {
+        (Some(first), Some(last)) => (first.timestamp, last.timestamp),
+        _ => (0.0, 0.0),
+    };
+    PlotImage {
+        width: waterfall.width,
+        height: waterfall.height,
+        rgb: settings.palette.colorize(&waterfall.pixels),
+        extent: [0.0, right, last, first],
+        x_label,
+        y_label: "time",
+    }
+}
+
+/// Track as parallel arrays of equal length
+///
+/// Only positioned pings of the lowest channel are used, so multi-channel
+/// recordings do not repeat each position. `depth_m` is NaN where the ping
+/// has no depth.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlotTrack {
+    pub time: Vec<f64>,
+    pub lon: Vec<f64>,
+    pub lat: Vec<f64>,
+    pub depth_m: Vec<f64>,
+}
+
+impl PlotTrack {
+    pub fn from_pings(pings: &[Ping]) -> Self {
+        let mut track = Self::default();
+        let Some(channel) = pings.iter().map(|p| p.channel_id).min() else {
+            return track;
+        };
+        for p in pings
+            .iter()
+            .filter(|p| p.channel_id == channel && (p.lat != 0.0 || p.lon != 0.0))
+        {
+            track.time.push(p.timestamp);
+            track.lon.push(p.lon);
+            track.lat.push(p.lat);
+            track.depth_m.push(if p.depth_m > 0.0 { p.depth_m } else { f64::NAN });
+        }
+        track
+    }
+
+    /// `(west, east, south, north)` in degrees, matching the image extent order
+    pub fn extent(&self) -> Option<[f64; 4]> {
+        if self.lon.is_empty() {
+            return None;
+        }
+        let min = |v: &[f64]| v.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = |v: &[f64]| v.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Some([min(&self.lon), max(&self.lon), min(&self.lat), max(&self.lat)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(channel_id: u16, timestamp: f64, range_m: f64, lat: f64, depth_m: f64) -> Ping {
+        Ping {
+            channel_id,
+            timestamp,
+            range_m,
+            lat,
+            lon: if lat == 0.0 { 0.0 } else { -63.5 + timestamp * 1e-4 },
+            depth_m,
+            samples: vec![timestamp as u8 * 10; 4],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn waterfall_extent_follows_range_and_time() {
+        let settings = ImagingSettings::default();
+        let pings = [ping(0, 1.0, 20.0, 0.0, 0.0), ping(0, 2.0, 20.0, 0.0, 0.0), ping(0, 4.0, 20.0, 0.0, 0.0)];
+        let refs: Vec<&Ping> = pings.iter().collect();
+        let plot = waterfall_plot(&refs, 4, &settings);
+        assert_eq!((plot.width, plot.height), (4, 3));
+        assert_eq!(plot.extent, [0.0, 20.0, 4.0, 1.0]);
+        assert_eq!((plot.x_label, plot.y_label), ("range_m", "time"));
+        // Row 1 is the second ping, colored through the palette
+        assert_eq!(&plot.rgb[12..15], &settings.palette.color(20));
+
+        let mut zoomed = pings.clone();
+        zoomed[2].range_m = 40.0;
+        let refs: Vec<&Ping> = zoomed.iter().collect();
+        let plot = waterfall_plot(&refs, 4, &settings);
+        assert_eq!((plot.extent, plot.x_label), ([0.0, 1.0, 4.0, 1.0], "range_fraction"));
+    }
+
+    #[test]
+    fn track_uses_positioned_pings_of_the_lowest_channel() {
+        let pings = [
+            ping(2, 1.0, 20.0, 44.6, 3.0),
+            ping(1, 1.0, 20.0, 44.6, 3.0),
+            ping(1, 2.0, 20.0, 0.0, 3.5),
+            ping(1, 3.0, 20.0, 44.7, 0.0),
+        ];
+        let track = PlotTrack::from_pings(&pings);
+        assert_eq!((track.time.clone(), track.lat.clone()), (vec![1.0, 3.0], vec![44.6, 44.7]));
+        assert_eq!(track.depth_m[0], 3.0);
+        assert!(track.depth_m[1].is_nan());
+        assert_eq!(track.extent(), Some([-63.4999, -63.4997, 44.6, 44.7]));
+        assert_eq!(PlotTrack::from_pings(&[]).extent(), None);
+
+        let image = PlotImage::from_quicklook(Quicklook::blank(3, 2));
+        assert_eq!((image.extent, image.rgb.len()), ([0.0, 3.0, 2.0, 0.0], 18));
+    }
+}
@@ -0,0 +1,300 @@
+// Multi-resolution waterfall overviews for fast zoomed-out rendering
+// src/imaging/pyramid.rs
+
+use super::palette::Levels;
+use super::waterfall::{channel_pings, render_waterfall};
+use crate::batch::read_recording;
+use crate::parsers::FormatRegistry;
+use crate::survey::Ping;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"SSWP";
+const VERSION: u32 = 1;
+const PYRAMID_SUFFIX: &str = ".pyramid";
+/// Levels stop halving once they have no more rows than this
+const MIN_ROWS: usize = 256;
+
+/// One resolution of a channel's raw intensity matrix
+///
+/// Rows run in ping order and columns across range; each cell is the mean
+/// of `factor` × `factor` cells of the full-resolution level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PyramidLevel {
+    pub factor: usize,
+    pub rows: usize,
+    pub cols: usize,
+    pub values: Vec<u8>,
+}
+
+impl PyramidLevel {
+    /// Half-size copy, averaging 2×2 blocks (edge blocks may be partial)
+    fn halve(&self) -> Self {
+        let (rows, cols) = (self.rows.div_ceil(2), self.cols.div_ceil(2).max(1));
+        let mut values = Vec::with_capacity(rows * cols);
+        for r in 0..rows {
+            for c in 0..cols {
+                let (mut sum, mut n) = (0u32, 0u32);
+                for y in (2 * r)..(2 * r + 2).min(self.rows) {
+                    for x in (2 * c)..(2 * c + 2).min(self.cols) {
+                        sum += self.values[y * self.cols + x] as u32;
+                        n += 1;
+                    }
+                }
+                values.push((sum as f64 / n.max(1) as f64).round() as u8);
+            }
+        }
+        Self {
+            factor: self.factor * 2,
+            rows,
+            cols,
+            values,
+        }
+    }
+}
+
+/// Overviews of one channel from full resolution down to about [`MIN_ROWS`] rows
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelPyramid {
+    pub channel_id: u16,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// Finest first
+    pub levels: Vec<PyramidLevel>,
+}
+
+impl ChannelPyramid {
+    /// Coarsest level that still has at least `rows` rows, for drawing into that many pixels
+    pub fn level_for(&self, rows: usize) -> Option<&PyramidLevel> {
+        self.levels
+            .iter()
+            .rev()
+            .find(|l| l.rows >= rows)
+            .or(self.levels.first())
+    }
+}
+
+/// Pyramids of every channel with samples in a recording
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WaterfallPyramid {
+    pub channels: Vec<ChannelPyramid>,
+}
+
+impl WaterfallPyramid {
+    /// Build from raw samples (no levels applied) at `width` columns per ping
+    pub fn build(pings: &[Ping], width: usize) -> Self {
+        let mut ids: Vec<u16> = pings
+            .iter()
+            .filter(|p| !p.samples.is_empty())
+            .map(|p| p.channel_id)
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let channels = ids
+            .into_iter()
+            .map(|channel_id| {
+                let selected = channel_pings(pings, channel_id);
+                let base = render_waterfall(&selected, width.max(1), &Levels::default());
+                let mut levels = vec![PyramidLevel {
+                    factor: 1,
+                    rows: base.height,
+                    cols: base.width,
+                    values: base.pixels,
+                }];
+                while let Some(last) = levels.last().filter(|l| l.rows > MIN_ROWS) {
+                    levels.push(last.halve());
+                }
+                ChannelPyramid {
+                    channel_id,
+                    start_time: selected.first().map_or(0.0, |p| p.timestamp),
+                    end_time: selected.last().map_or(0.0, |p| p.timestamp),
+                    levels,
+                }
+            })
+            .collect();
+        Self { channels }
+    }
+
+    pub fn channel(&self, channel_id: u16) -> Option<&ChannelPyramid> {
+        self.channels.iter().find(|c| c.channel_id == channel_id)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(self.channels.len() as u32).to_le_bytes())?;
+        for channel in &self.channels {
+            out.write_all(&channel.channel_id.to_le_bytes())?;
+            out.write_all(&channel.start_time.to_le_bytes())?;
+            out.write_all(&channel.end_time.to_le_bytes())?;
+            out.write_all(&(channel.levels.len() as u32).to_le_bytes())?;
+            for level in &channel.levels {
+                for v in [level.factor, level.rows, level.cols] {
+                    out.write_all(&(v as u64).to_le_bytes())?;
+                }
+                out.write_all(&level.values)?;
+            }
+        }
+        out.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(&mut input)? != VERSION {
+            return Err(invalid("not a waterfall pyramid file"));
+        }
+        let mut channels = Vec::new();
+        for _ in 0..read_u32(&mut input)? {
+            let mut id = [0u8; 2];
+            input.read_exact(&mut id)?;
+            let start_time = f64::from_bits(read_u64(&mut input)?);
+            let end_time = f64::from_bits(read_u64(&mut input)?);
+            let mut levels = Vec::new();
+            for _ in 0..read_u32(&mut input)? {
+                let factor = read_u64(&mut input)? as usize;
+                let rows = read_u64(&mut input)? as usize;
+                let cols = read_u64(&mut input)? as usize;
+                let len = rows
+                    .checked_mul(cols)
+                    .ok_or_else(|| invalid("corrupt waterfall pyramid"))?;
+                let mut values = Vec::new();
+                input.by_ref().take(len as u64).read_to_end(&mut values)?;
+                if values.len() != len {
+                    return Err(invalid("truncated waterfall pyramid"));
+                }
+                levels.push(PyramidLevel {
+                    factor,
+                    rows,
+                    cols,
+                    values,
+                });
+            }
+            channels.push(ChannelPyramid {
+                channel_id: u16::from_le_bytes(id),
+                start_time,
+                end_time,
+                levels,
+            });
+        }
+        Ok(Self { channels })
+    }
+}
+
+/// Pyramid location for a recording, e.g. `survey.sl2` -> `survey.sl2.pyramid`
+pub fn pyramid_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(PYRAMID_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Reuse the pyramid next to a recording, or build and save it
+///
+/// A saved pyramid older than the recording, or with another base width,
+/// is rebuilt. Failure to save is not an error; the pyramid is returned anyway.
+pub fn load_or_build_pyramid<P: AsRef<Path>>(
+    path: P,
+    registry: &FormatRegistry,
+    width: usize,
+) -> io::Result<WaterfallPyramid> {
+    let path = path.as_ref();
+    let cache = pyramid_path(path);
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    if modified(&cache)
+        .zip(modified(path))
+        .is_some_and(|(cache, source)| cache >= source)
+    {
+        if let Ok(pyramid) = WaterfallPyramid::load(&cache) {
+            let base_cols = pyramid.channels.first().and_then(|c| c.levels.first()).map(|l| l.cols);
+            if base_cols.is_none_or(|cols| cols == width.max(1)) {
+                return Ok(pyramid);
+            }
+        }
+    }
+    let (_, pings) = read_recording(path, registry)?;
+    let pyramid = WaterfallPyramid::build(&pings, width);
+    if let Err(err) = pyramid.save(&cache) {
+        tracing::warn!(path = %cache.display(), error = %err, "could not save waterfall pyramid");
+    }
+    Ok(pyramid)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch;
+
+    fn survey(count: usize) -> Vec<Ping> {
+        (0..count)
+            .map(|i| Ping {
+                channel_id: (i % 2) as u16,
+                timestamp: i as f64,
+                range_m: 10.0,
+                samples: vec![if i % 4 < 2 { 100 } else { 50 }; 8],
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn halving_averages_blocks_including_partial_edges() {
+        let level = PyramidLevel {
+            factor: 1,
+            rows: 3,
+            cols: 3,
+            values: vec![10, 20, 30, 30, 40, 50, 90, 90, 90],
+        };
+        let half = level.halve();
+        assert_eq!((half.factor, half.rows, half.cols), (2, 2, 2));
+        assert_eq!(half.values, vec![25, 40, 90, 90]);
+    }
+
+    #[test]
+    fn builds_levels_down_to_the_row_floor() {
+        let pyramid = WaterfallPyramid::build(&survey(2000), 8);
+        assert_eq!(pyramid.channels.len(), 2);
+        let channel = pyramid.channel(1).unwrap();
+        let rows: Vec<usize> = channel.levels.iter().map(|l| l.rows).collect();
+        assert_eq!(rows, vec![1000, 500, 250]);
+        assert_eq!((channel.start_time, channel.end_time), (1.0, 1999.0));
+        // Alternating 100/50 rows of one channel average out one level down
+        assert!(channel.levels[1].values.iter().all(|&v| v == 75));
+        assert_eq!(channel.level_for(400).unwrap().rows, 500);
+        assert_eq!(channel.level_for(5000).unwrap().rows, 1000);
+    }
+
+    #[test]
+    fn saved_pyramids_load_back_and_bad_files_are_rejected() {
+        let pyramid = WaterfallPyramid::build(&survey(600), 16);
+        let path = scratch("pyramid", "survey.sl2.pyramid");
+        pyramid.save(&path).unwrap();
+        assert_eq!(WaterfallPyramid::load(&path).unwrap(), pyramid);
+
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 10]).unwrap();
+        assert!(WaterfallPyramid::load(&path).is_err());
+        fs::write(&path, b"PNG\0\0\0\0\0").unwrap();
+        assert_eq!(WaterfallPyramid::load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let _ = fs::remove_file(&path);
+        assert_eq!(pyramid_path("survey.sl2"), PathBuf::from("survey.sl2.pyramid"));
+    }
+}
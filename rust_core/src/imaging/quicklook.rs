@@ -0,0 +1,298 @@
+// Quick-look preview images of whole recordings
+// src/imaging/quicklook.rs
+
+use super::palette::ImagingSettings;
+use super::png::{write_png, PngColor};
+use super::waterfall::{channel_pings, render_waterfall};
+use crate::parsers::{ChannelInfo, ChannelKind, FormatRegistry};
+use crate::survey::Ping;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const BACKGROUND: [u8; 3] = [24, 24, 28];
+const TRACK: [u8; 3] = [255, 64, 64];
+const START: [u8; 3] = [64, 255, 64];
+const WATER: [u8; 3] = [16, 40, 80];
+const BOTTOM: [u8; 3] = [150, 110, 60];
+
+/// Preview layout settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuicklookConfig {
+    pub width: usize,
+    pub height: usize,
+    /// Channel for the sonar strip; picked from the channel list when unset
+    pub channel_id: Option<u16>,
+}
+
+impl Default for QuicklookConfig {
+    fn default() -> Self {
+        Self {
+            width: 480,
+            height: 240,
+            channel_id: None,
+        }
+    }
+}
+
+/// RGB composite: track mini-map and sonar strip on top, depth profile below
+#[derive(Debug, Clone)]
+pub struct Quicklook {
+    pub width: usize,
+    pub height: usize,
+    pub rgb: Vec<u8>,
+}
+
+impl Quicklook {
+    /// Blank image filled with the background color
+    pub fn blank(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            rgb: BACKGROUND.repeat(width * height),
+        }
+    }
+
+    pub fn write_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write_png(&mut out, self.width, self.height, PngColor::Rgb, &self.rgb)?;
+        out.flush()
+    }
+
+    /// PNG file contents, e.g. for embedding in a report
+    pub fn to_png(&self) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        write_png(&mut out, self.width, self.height, PngColor::Rgb, &self.rgb)?;
+        Ok(out)
+    }
+
+    fn fill(&mut self, x0: usize, y0: usize, w: usize, h: usize, color: [u8; 3]) {
+        for y in y0..(y0 + h).min(self.height) {
+            for x in x0..(x0 + w).min(self.width) {
+                self.put(x, y, color);
+            }
+        }
+    }
+
+    fn put(&mut self, x: usize, y: usize, color: [u8; 3]) {
+        let i = (y * self.width + x) * 3;
+        self.rgb[i..i + 3].copy_from_slice(&color);
+    }
+}
+
+/// Read a recording and write its preview PNG to `out_path`
+pub fn quicklook<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    out_path: Q,
+    registry: &FormatRegistry,
+    config: &QuicklookConfig,
+    settings: &ImagingSettings,
+) -> io::Result<()> {
+    let source = registry.open(path.as_ref())?;
+    let channels = source.channels();
+    let pings = source.collect::<io::Result<Vec<Ping>>>()?;
+    render_quicklook(&pings, &channels, config, settings).write_png(out_path)
+}
+
+/// Channel best suited to a vertical strip: down-scan, then traditional, then any with samples
+pub fn strip_channel(pings: &[Ping], channels: &[ChannelInfo]) -> Option<u16> {
+    [ChannelKind::DownScan, ChannelKind::Traditional]
+        .iter()
+        .find_map(|kind| channels.iter().find(|c| c.kind == *kind).map(|c| c.channel_id))
+        .or_else(|| pings.iter().filter(|p| !p.samples.is_empty()).map(|p| p.channel_id).min())
+}
+
+pub fn render_quicklook(pings: &[Ping], channels: &[ChannelInfo], config: &QuicklookConfig, settings: &ImagingSettings) -> Quicklook {
+    let (width, height) = (config.width.max(8), config.height.max(8));
+    let mut image = Quicklook::blank(width, height);
+    let top = height * 2 / 3;
+    let map_size = top.min(width / 3);
+
+    draw_track(&mut image, pings, map_size);
+    if let Some(channel) = config.channel_id.or_else(|| strip_channel(pings, channels)) {
+        draw_strip(&mut image, &channel_pings(pings, channel), map_size + 1, top, settings, channel);
+    }
+    draw_profile(&mut image, pings, top + 1);
+    image
+}
+
+/// Square track mini-map on its own, start marked in green
+pub fn render_track_map(pings: &[Ping], size: usize) -> Quicklook {
+    let mut image = Quicklook::blank(size.max(8), size.max(8));
+    draw_track(&mut image, pings, size.max(8));
+    image
+}
+
+/// Depth profile on its own, time running left to right
+pub fn render_depth_profile(pings: &[Ping], width: usize, height: usize) -> Quicklook {
+    let mut image = Quicklook::blank(width.max(8), height.max(8));
+    draw_profile(&mut image, pings, 0);
+    image
+}
+
+fn draw_track(image: &mut Quicklook, pings: &[Ping], size: usize) {
+    let track: Vec<(f64, f64)> = pings
+        .iter()
+        .filter(|p| p.lat != 0.0 || p.lon != 0.0)
+        .map(|p| (p.lat, p.lon))
+        .collect();
+    let Some(&first) = track.first() else { return };
+    let min_lat = track.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let max_lat = track.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    let min_lon = track.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let max_lon = track.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+    // Equal scale on both axes, longitude shrunk by latitude so shapes are not stretched
+    let lon_scale = ((min_lat + max_lat) / 2.0).to_radians().cos().max(0.01);
+    let span = (max_lat - min_lat).max((max_lon - min_lon) * lon_scale).max(1e-9);
+    let inner = (size.saturating_sub(4)) as f64;
+
+    let to_pixel = |(lat, lon): (f64, f64)| {
+        let px = ((lon - min_lon) * lon_scale / span * inner) as usize;
+        let py = ((max_lat - lat) / span * inner) as usize;
+        (2 + px, 2 + py)
+    };
+    for &point in &track {
+        let (x, y) = to_pixel(point);
+        image.put(x, y, TRACK);
+    }
+    let (x, y) = to_pixel(first);
+    image.fill(x.saturating_sub(1), y.saturating_sub(1), 3, 3, START);
+}
+
+/// Sonar strip with time running left to right and range running down
+fn draw_strip(image: &mut Quicklook, pings: &[&Ping], x0: usize, height: usize, settings: &ImagingSettings, channel: u16) {
+    let width = image.width.saturating_sub(x0);
+    if pings.is_empty() || width == 0 || height == 0 {
+        return;
+    }
+    let waterfall = render_waterfall(pings, height, &settings.levels(channel));
+    for x in 0..width {
+        let row = x * pings.len() / width;
+        for y in 0..height {
+            let value = waterfall.pixels[row * height + y];
+            image.put(x0 + x, y, settings.palette.color(value));
+        }
+    }
+}
+
+/// Depth against time, water above the bottom line and sediment below
+fn draw_profile(image: &mut Quicklook, pings: &[Ping], y0: usize) {
+    let depths: Vec<(f64, f64)> = pings
+        .iter()
+        .filter(|p| p.depth_m > 0.0)
+        .map(|p| (p.timestamp, p.depth_m))
+        .collect();
+    let height = image.height.saturating_sub(y0);
+    let Some(&(start, _)) = depths.first() else { return };
+    if height == 0 {
+        return;
+    }
+    let end = depths.iter().map(|d| d.0).fold(start, f64::max);
+    let max_depth = depths.iter().map(|d| d.1).fold(0.0, f64::max) * 1.1;
+    let duration = (end - start).max(1e-9);
+
+    // Deepest reading per column so narrow holes stay visible
+    let mut columns = vec![f64::NAN; image.width];
+    for &(t, depth) in &depths {
+        let x = (((t - start) / duration) * (image.width - 1) as f64) as usize;
+        if columns[x].is_nan() || depth > columns[x] {
+            columns[x] = depth;
+        }
+    }
+    for (x, depth) in columns.into_iter().enumerate() {
+        if depth.is_nan() {
+            continue;
+        }
+        let bottom = ((depth / max_depth) * height as f64) as usize;
+        image.fill(x, y0, 1, bottom, WATER);
+        image.fill(x, y0 + bottom, 1, height - bottom.min(height), BOTTOM);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(image: &Quicklook, x: usize, y: usize) -> [u8; 3] {
+        let i = (y * image.width + x) * 3;
+        [image.rgb[i], image.rgb[i + 1], image.rgb[i + 2]]
+    }
+
+    #[test]
+    fn composite_places_track_strip_and_profile() {
+        // Heading due north with a flat 5 m bottom and bright returns on channel 1
+        let pings: Vec<Ping> = (0..5)
+            .map(|i| Ping {
+                channel_id: 1,
+                timestamp: i as f64,
+                lat: 44.6 + i as f64 * 1e-4,
+                lon: -63.5,
+                depth_m: 5.0,
+                range_m: 8.0,
+                samples: vec![200; 8],
+                ..Default::default()
+            })
+            .collect();
+        let settings = ImagingSettings::default();
+        let config = QuicklookConfig {
+            width: 30,
+            height: 12,
+            channel_id: None,
+        };
+        let image = render_quicklook(&pings, &[], &config, &settings);
+        assert_eq!((image.width, image.height, image.rgb.len()), (30, 12, 30 * 12 * 3));
+
+        // 8 px map: start marker at the bottom, track running up to the top margin
+        assert_eq!(pixel(&image, 2, 6), START);
+        assert_eq!(pixel(&image, 1, 7), START);
+        assert_eq!(pixel(&image, 2, 2), TRACK);
+        assert_eq!(pixel(&image, 5, 2), BACKGROUND);
+        // Strip to the right of the map over the top two thirds
+        assert_eq!(pixel(&image, 20, 3), settings.palette.color(200));
+        assert_eq!(pixel(&image, 29, 7), settings.palette.color(200));
+        // Profile rows below: 5 m of a 5.5 m scale over 3 rows
+        assert_eq!(pixel(&image, 0, 9), WATER);
+        assert_eq!(pixel(&image, 0, 10), WATER);
+        assert_eq!(pixel(&image, 0, 11), BOTTOM);
+        assert_eq!(pixel(&image, 1, 9), BACKGROUND);
+
+        let png = image.to_png().unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..24], b"IHDR\0\0\0\x1e\0\0\0\x0c");
+    }
+
+    #[test]
+    fn profile_columns_keep_the_deepest_reading() {
+        let ping = |timestamp: f64, depth_m: f64| Ping {
+            timestamp,
+            depth_m,
+            ..Default::default()
+        };
+        // 11 m is the deepest reading, so the scale runs to 12.1 m over 20 rows
+        let pings = [ping(0.0, 2.2), ping(0.1, 1.0), ping(10.0, 11.0), ping(5.0, 0.0)];
+        let image = render_depth_profile(&pings, 8, 20);
+        let column = |x: usize| (0..20).map(|y| pixel(&image, x, y)).collect::<Vec<_>>();
+        assert_eq!(column(0), [vec![WATER; 3], vec![BOTTOM; 17]].concat());
+        assert_eq!(column(7), [vec![WATER; 18], vec![BOTTOM; 2]].concat());
+        assert_eq!(column(3), vec![BACKGROUND; 20]);
+    }
+
+    #[test]
+    fn strip_prefers_down_scan_channels() {
+        let channel = |channel_id, kind| ChannelInfo {
+            channel_id,
+            name: String::new(),
+            kind,
+            frequency_khz: None,
+            beam_angle_deg: None,
+        };
+        let pings = [
+            Ping { channel_id: 4, samples: vec![1], ..Default::default() },
+            Ping { channel_id: 2, ..Default::default() },
+        ];
+        let channels = [channel(3, ChannelKind::Traditional), channel(5, ChannelKind::DownScan)];
+        assert_eq!(strip_channel(&pings, &channels), Some(5));
+        assert_eq!(strip_channel(&pings, &channels[..1]), Some(3));
+        assert_eq!(strip_channel(&pings, &[]), Some(4));
+    }
+}
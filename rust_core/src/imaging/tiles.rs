@@ -0,0 +1,368 @@
+// Slippy-map tile pyramid export of georeferenced rasters
+// src/imaging/tiles.rs
+
+use super::palette::Palette;
+use super::png::{write_png, PngColor};
+use super::GeoRaster;
+use crate::sqlite::{SqliteWriter, Value};
+use std::f64::consts::PI;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub const TILE_SIZE: usize = 256;
+/// Deepest zoom level written; level 24 pixels are about a centimetre across
+pub const MAX_ZOOM: u8 = 24;
+/// `MPBX`, the application id MBTiles readers check for
+const MBTILES_APPLICATION_ID: u32 = 0x4d50_4258;
+
+/// Row numbering of the tile pyramid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileScheme {
+    /// Google/OSM style, y = 0 at the north (Leaflet default)
+    Xyz,
+    /// TMS style, y = 0 at the south (MBTiles internal layout)
+    Tms,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileConfig {
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    pub scheme: TileScheme,
+}
+
+impl TileConfig {
+    /// Reject zoom ranges that are inverted or deeper than [`MAX_ZOOM`]
+    pub fn validate(&self) -> io::Result<()> {
+        if self.max_zoom > MAX_ZOOM {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("max_zoom {} exceeds the limit of {}", self.max_zoom, MAX_ZOOM),
+            ));
+        }
+        if self.min_zoom > self.max_zoom {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("min_zoom {} is above max_zoom {}", self.min_zoom, self.max_zoom),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for TileConfig {
+    fn default() -> Self {
+        Self {
+            min_zoom: 12,
+            max_zoom: 18,
+            scheme: TileScheme::Xyz,
+        }
+    }
+}
+
+/// Fractional Web Mercator tile coordinates of a position
+pub fn lat_lon_to_tile(lat: f64, lon: f64, zoom: u8) -> (f64, f64) {
+    let n = 2f64.powi(zoom as i32);
+    let x = (lon + 180.0) / 360.0 * n;
+    let lat_rad = lat.to_radians();
+    let y = (1.0 - (lat_rad.tan() + 1.0 / lat_rad.cos()).ln() / PI) / 2.0 * n;
+    (x, y)
+}
+
+/// Position of fractional Web Mercator tile coordinates
+pub fn tile_to_lat_lon(x: f64, y: f64, zoom: u8) -> (f64, f64) {
+    let n = 2f64.powi(zoom as i32);
+    let lon = x / n * 360.0 - 180.0;
+    let lat = (PI * (1.0 - 2.0 * y / n)).sinh().atan().to_degrees();
+    (lat, lon)
+}
+
+/// Cut the raster into 256px PNG tiles under `dir/z/x/y.png`, returning the tile count
+///
+/// Tiles are gray+alpha, or RGBA when a palette is given. Fails with
+/// `InvalidInput` when the zoom range does not pass [`TileConfig::validate`].
+pub fn write_tiles<P: AsRef<Path>>(
+    dir: P,
+    raster: &GeoRaster,
+    config: &TileConfig,
+    palette: Option<&Palette>,
+) -> io::Result<usize> {
+    config.validate()?;
+    let dir = dir.as_ref();
+    let written = for_each_tile(raster, config, palette, |zoom, tx, ty, png| {
+        let row = match config.scheme {
+            TileScheme::Xyz => ty,
+            TileScheme::Tms => (1u64 << zoom) - 1 - ty,
+        };
+        let tile_dir = dir.join(zoom.to_string()).join(tx.to_string());
+        fs::create_dir_all(&tile_dir)?;
+        fs::write(tile_dir.join(format!("{}.png", row)), png)
+    })?;
+
+    write_metadata(dir, raster, config)?;
+    Ok(written)
+}
+
+/// Package the tiles into a single MBTiles 1.3 file, returning the tile count
+///
+/// Rows are stored in TMS order as the spec requires, whatever
+/// `config.scheme` says; the metadata table carries the same fields as
+/// the directory pyramid's `metadata.json`. The zoom range is checked as
+/// in [`write_tiles`] before the file is created.
+pub fn write_mbtiles<P: AsRef<Path>>(
+    path: P,
+    raster: &GeoRaster,
+    config: &TileConfig,
+    palette: Option<&Palette>,
+) -> io::Result<usize> {
+    config.validate()?;
+    let mut db = SqliteWriter::new(BufWriter::new(File::create(path)?));
+    db.set_application_id(MBTILES_APPLICATION_ID);
+    let metadata = db.create_table("metadata", "CREATE TABLE metadata (name text, value text)");
+    let tiles = db.create_table(
+        "tiles",
+        "CREATE TABLE tiles (zoom_level integer, tile_column integer, tile_row integer, tile_data blob)",
+    );
+    db.create_index(
+        "tile_index",
+        tiles,
+        "CREATE UNIQUE INDEX tile_index on tiles (zoom_level, tile_column, tile_row)",
+        &[0, 1, 2],
+    );
+
+    let center_lat = (raster.min_lat + raster.max_lat) / 2.0;
+    let center_lon = (raster.min_lon + raster.max_lon) / 2.0;
+    let bounds = format!(
+        "{},{},{},{}",
+        raster.min_lon, raster.min_lat, raster.max_lon, raster.max_lat
+    );
+    let center = format!("{},{},{}", center_lon, center_lat, config.max_zoom);
+    let (min_zoom, max_zoom) = (config.min_zoom.to_string(), config.max_zoom.to_string());
+    for (name, value) in [
+        ("name", "sidescan mosaic"),
+        ("format", "png"),
+        ("type", "overlay"),
+        ("version", "1.3"),
+        ("minzoom", &min_zoom),
+        ("maxzoom", &max_zoom),
+        ("bounds", &bounds),
+        ("center", &center),
+    ] {
+        db.insert(metadata, &[Value::Text(name), Value::Text(value)])?;
+    }
+
+    let written = for_each_tile(raster, config, palette, |zoom, tx, ty, png| {
+        let row = (1u64 << zoom) - 1 - ty;
+        db.insert(
+            tiles,
+            &[
+                Value::Integer(zoom as i64),
+                Value::Integer(tx as i64),
+                Value::Integer(row as i64),
+                Value::Blob(png),
+            ],
+        )
+        .map(|_| ())
+    })?;
+    db.finish()?.flush()?;
+    Ok(written)
+}
+
+/// Encode every tile that has data as PNG and hand it to `sink` with its XYZ address
+fn for_each_tile<F>(
+    raster: &GeoRaster,
+    config: &TileConfig,
+    palette: Option<&Palette>,
+    mut sink: F,
+) -> io::Result<usize>
+where
+    F: FnMut(u8, u64, u64, &[u8]) -> io::Result<()>,
+{
+    let mut written = 0;
+    let mut png = Vec::new();
+
+    for zoom in config.min_zoom..=config.max_zoom {
+        let (x0, y0) = lat_lon_to_tile(raster.max_lat, raster.min_lon, zoom);
+        let (x1, y1) = lat_lon_to_tile(raster.min_lat, raster.max_lon, zoom);
+        let n = 1u64 << zoom;
+
+        for tx in (x0.floor() as u64)..=(x1.floor() as u64).min(n - 1) {
+            for ty in (y0.floor() as u64)..=(y1.floor() as u64).min(n - 1) {
+                let Some(pixels) = render_tile(raster, tx, ty, zoom) else {
+                    continue;
+                };
+                png.clear();
+                match palette {
+                    Some(palette) => write_png(
+                        &mut png,
+                        TILE_SIZE,
+                        TILE_SIZE,
+                        PngColor::Rgba,
+                        &colorize_gray_alpha(&pixels, palette),
+                    )?,
+                    None => write_png(&mut png, TILE_SIZE, TILE_SIZE, PngColor::GrayAlpha, &pixels)?,
+                }
+                sink(zoom, tx, ty, &png)?;
+                written += 1;
+            }
+        }
+    }
+
+    Ok(written)
+}
+
+/// Gray+alpha pixels for one tile, or None if the tile has no data
+fn render_tile(raster: &GeoRaster, tx: u64, ty: u64, zoom: u8) -> Option<Vec<u8>> {
+    let mut pixels = vec![0u8; TILE_SIZE * TILE_SIZE * 2];
+    let mut any = false;
+
+    for py in 0..TILE_SIZE {
+        let fy = ty as f64 + (py as f64 + 0.5) / TILE_SIZE as f64;
+        for px in 0..TILE_SIZE {
+            let fx = tx as f64 + (px as f64 + 0.5) / TILE_SIZE as f64;
+            let (lat, lon) = tile_to_lat_lon(fx, fy, zoom);
+            if let Some(value) = raster.sample(lat, lon) {
+                let idx = (py * TILE_SIZE + px) * 2;
+                pixels[idx] = value;
+                pixels[idx + 1] = 255;
+                any = true;
+            }
+        }
+    }
+
+    any.then_some(pixels)
+}
+
+fn colorize_gray_alpha(pixels: &[u8], palette: &Palette) -> Vec<u8> {
+    pixels
+        .chunks(2)
+        .flat_map(|px| {
+            let [r, g, b] = palette.color(px[0]);
+            [r, g, b, px[1]]
+        })
+        .collect()
+}
+
+/// MBTiles-style metadata describing the pyramid
+fn write_metadata(dir: &Path, raster: &GeoRaster, config: &TileConfig) -> io::Result<()> {
+    let scheme = match config.scheme {
+        TileScheme::Xyz => "xyz",
+        TileScheme::Tms => "tms",
+    };
+    let center_lat = (raster.min_lat + raster.max_lat) / 2.0;
+    let center_lon = (raster.min_lon + raster.max_lon) / 2.0;
+
+    let mut out = File::create(dir.join("metadata.json"))?;
+    writeln!(
+        out,
+        "{{\"name\":\"sidescan mosaic\",\"format\":\"png\",\"type\":\"overlay\",\"scheme\":\"{}\",\"minzoom\":{},\"maxzoom\":{},\"bounds\":\"{},{},{},{}\",\"center\":\"{},{},{}\"}}",
+        scheme,
+        config.min_zoom,
+        config.max_zoom,
+        raster.min_lon,
+        raster.min_lat,
+        raster.max_lon,
+        raster.max_lat,
+        center_lon,
+        center_lat,
+        config.max_zoom
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch;
+
+    fn raster() -> GeoRaster {
+        let mut raster = GeoRaster::new(44.0, -63.6, 44.01, -63.58, 40, 30);
+        for row in 0..30 {
+            for col in 0..40 {
+                raster.set(row, col, (row * 8) as u8);
+            }
+        }
+        raster
+    }
+
+    #[test]
+    fn tile_coordinates_round_trip() {
+        for zoom in [0, 12, 18] {
+            let (x, y) = lat_lon_to_tile(44.6488, -63.5752, zoom);
+            let (lat, lon) = tile_to_lat_lon(x, y, zoom);
+            assert!((lat - 44.6488).abs() < 1e-9 && (lon + 63.5752).abs() < 1e-9);
+        }
+        assert_eq!(lat_lon_to_tile(0.0, 0.0, 1), (1.0, 1.0));
+    }
+
+    #[test]
+    fn tms_rows_mirror_xyz_rows() {
+        let (dir_xyz, dir_tms) = (scratch("tiles", "xyz"), scratch("tiles", "tms"));
+        let mut config = TileConfig {
+            min_zoom: 14,
+            max_zoom: 15,
+            scheme: TileScheme::Xyz,
+        };
+        let count = write_tiles(&dir_xyz, &raster(), &config, None).unwrap();
+        config.scheme = TileScheme::Tms;
+        assert_eq!(write_tiles(&dir_tms, &raster(), &config, None).unwrap(), count);
+        assert!(count >= 2);
+
+        let (x, y) = lat_lon_to_tile(44.005, -63.59, 15);
+        let (x, y) = (x as u64, y as u64);
+        let xyz = fs::read(dir_xyz.join(format!("15/{}/{}.png", x, y))).unwrap();
+        let tms = fs::read(dir_tms.join(format!("15/{}/{}.png", x, (1 << 15) - 1 - y))).unwrap();
+        assert_eq!(&xyz[1..4], b"PNG");
+        assert_eq!(xyz, tms);
+        assert!(fs::read_to_string(dir_tms.join("metadata.json"))
+            .unwrap()
+            .contains("\"scheme\":\"tms\""));
+        fs::remove_dir_all(dir_xyz).unwrap();
+        fs::remove_dir_all(dir_tms).unwrap();
+    }
+
+    #[test]
+    fn rejects_unusable_zoom_ranges() {
+        let path = scratch("tiles", "rejected.mbtiles");
+        for (min_zoom, max_zoom) in [(16, 14), (0, 25), (63, 64)] {
+            let config = TileConfig {
+                min_zoom,
+                max_zoom,
+                scheme: TileScheme::Xyz,
+            };
+            let err = write_tiles(scratch("tiles", "rejected"), &raster(), &config, None).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            let err = write_mbtiles(&path, &raster(), &config, None).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+        assert!(!path.exists());
+        assert_eq!(lat_lon_to_tile(0.0, 0.0, 64).0, 2f64.powi(63));
+    }
+
+    #[test]
+    fn mbtiles_holds_every_tile() {
+        let (dir, path) = (scratch("tiles", "dir"), scratch("tiles", "pyramid.mbtiles"));
+        let config = TileConfig {
+            min_zoom: 13,
+            max_zoom: 16,
+            scheme: TileScheme::Xyz,
+        };
+        let palette = Palette::default();
+        let count = write_mbtiles(&path, &raster(), &config, Some(&palette)).unwrap();
+        assert_eq!(count, write_tiles(&dir, &raster(), &config, Some(&palette)).unwrap());
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[..16], b"SQLite format 3\0");
+        assert_eq!(&bytes[68..72], &MBTILES_APPLICATION_ID.to_be_bytes());
+        let pages = u32::from_be_bytes(bytes[28..32].try_into().unwrap()) as usize;
+        assert_eq!(bytes.len(), pages * 4096);
+        let contains = |text: &str| bytes.windows(text.len()).any(|w| w == text.as_bytes());
+        assert!(contains(
+            "CREATE UNIQUE INDEX tile_index on tiles (zoom_level, tile_column, tile_row)"
+        ));
+        assert!(contains("minzoom13"));
+        assert!(contains("-63.6,44,-63.58,44.01"));
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+// Scrolling waterfall animation export
+// src/imaging/video.rs
+
+use super::gif::GifEncoder;
+use super::palette::{ImagingSettings, Palette};
+use super::waterfall::{channel_pings, render_waterfall};
+use crate::survey::Ping;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/// Palette index used for the mini-map track line
+pub const TRACK_INDEX: u8 = 254;
+/// Palette index used for the current position marker
+pub const POSITION_INDEX: u8 = 255;
+
+/// Waterfall animation settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoConfig {
+    pub channel_id: u16,
+    pub width: usize,
+    pub height: usize,
+    /// New pings scrolled in per frame
+    pub pings_per_frame: usize,
+    pub frame_delay_cs: u16,
+    pub minimap: bool,
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        Self {
+            channel_id: 0,
+            width: 320,
+            height: 240,
+            pings_per_frame: 4,
+            frame_delay_cs: 4,
+            minimap: true,
+        }
+    }
+}
+
+/// Squeeze a palette into the first 254 entries and add the overlay colors
+pub fn gif_palette(palette: &Palette) -> [[u8; 3]; 256] {
+    let mut colors = [[0u8; 3]; 256];
+    for (i, color) in colors.iter_mut().enumerate().take(TRACK_INDEX as usize) {
+        *color = palette.color((i * 255 / (TRACK_INDEX as usize - 1)) as u8);
+    }
+    colors[TRACK_INDEX as usize] = [255, 64, 64];
+    colors[POSITION_INDEX as usize] = [255, 255, 0];
+    colors
+}
+
+/// Render a scrolling waterfall of one channel as an animated GIF, returning the frame count
+pub fn write_waterfall_gif<P: AsRef<Path>>(
+    path: P,
+    pings: &[Ping],
+    config: &VideoConfig,
+    settings: &ImagingSettings,
+) -> io::Result<usize> {
+    let out = BufWriter::new(File::create(path)?);
+    write_waterfall_gif_to(out, pings, config, settings)
+}
+
+pub fn write_waterfall_gif_to<W: io::Write>(
+    out: W,
+    pings: &[Ping],
+    config: &VideoConfig,
+    settings: &ImagingSettings,
+) -> io::Result<usize> {
+    let selected = channel_pings(pings, config.channel_id);
+    if selected.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no pings for the selected channel"));
+    }
+    let (width, height) = (config.width, config.height);
+    let waterfall = render_waterfall(&selected, width, &settings.levels(config.channel_id));
+    let track: Vec<(f64, f64)> = selected.iter().map(|p| (p.lat, p.lon)).collect();
+
+    // Keep sample values clear of the overlay indices
+    let levels: Vec<u8> = waterfall
+        .pixels
+        .iter()
+        .map(|&v| (v as usize * (TRACK_INDEX as usize - 1) / 255) as u8)
+        .collect();
+
+    let mut encoder = GifEncoder::new(out, width as u16, height as u16, &gif_palette(&settings.palette))?;
+    let step = config.pings_per_frame.max(1);
+    let mut frames = 0;
+    let mut newest = step.min(selected.len());
+
+    loop {
+        // Newest ping at the top, older pings scrolling down
+        let mut frame = vec![0u8; width * height];
+        for row in 0..height.min(newest) {
+            let src = newest - 1 - row;
+            frame[row * width..(row + 1) * width].copy_from_slice(&levels[src * width..(src + 1) * width]);
+        }
+        if config.minimap {
+            draw_minimap(&mut frame, width, height, &track, newest - 1);
+        }
+        encoder.write_frame(&frame, config.frame_delay_cs)?;
+        frames += 1;
+
+        if newest == selected.len() {
+            break;
+        }
+        newest = (newest + step).min(selected.len());
+    }
+
+    encoder.finish()?;
+    Ok(frames)
+}
+
+/// Draw the whole track and the current position into the bottom-right corner
+fn draw_minimap(frame: &mut [u8], width: usize, height: usize, track: &[(f64, f64)], current: usize) {
+    let size = (width.min(height) / 4).max(8);
+    let (x0, y0) = (width - size - 2, height - size - 2);
+    let min_lat = track.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let max_lat = track.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    let min_lon = track.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let max_lon = track.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+    let span = (max_lat - min_lat).max(max_lon - min_lon).max(1e-9);
+
+    let to_pixel = |(lat, lon): (f64, f64)| {
+        let px = ((lon - min_lon) / span * (size - 1) as f64) as usize;
+        let py = ((max_lat - lat) / span * (size - 1) as f64) as usize;
+        (x0 + px, y0 + py)
+    };
+
+    for &point in track {
+        let (x, y) = to_pixel(point);
+        frame[y * width + x] = TRACK_INDEX;
+    }
+    let (cx, cy) = to_pixel(track[current]);
+    for y in cy.saturating_sub(1)..=(cy + 1).min(height - 1) {
+        for x in cx.saturating_sub(1)..=(cx + 1).min(width - 1) {
+            frame[y * width + x] = POSITION_INDEX;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::decode_gif;
+
+    #[test]
+    fn frames_scroll_newest_pings_in_at_the_top() {
+        let pings: Vec<Ping> = (0..10)
+            .map(|i| Ping {
+                timestamp: i as f64,
+                lat: 44.6 + i as f64 * 1e-4,
+                lon: -63.5,
+                range_m: 20.0,
+                samples: vec![(i * 25) as u8; 8],
+                ..Default::default()
+            })
+            .collect();
+        let mut config = VideoConfig {
+            width: 16,
+            height: 12,
+            pings_per_frame: 4,
+            frame_delay_cs: 7,
+            minimap: false,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let count = write_waterfall_gif_to(&mut out, &pings, &config, &ImagingSettings::default()).unwrap();
+        assert_eq!(count, 3);
+
+        let (size, palette, frames) = decode_gif(&out);
+        assert_eq!(size, (16, 12));
+        assert_eq!((palette[TRACK_INDEX as usize], palette[POSITION_INDEX as usize]), ([255, 64, 64], [255, 255, 0]));
+        assert_eq!(frames.len(), 3);
+        assert!(frames.iter().all(|f| f.delay_cs == 7));
+        let row = |frame: usize, row: usize| &frames[frame].indices[row * 16..(row + 1) * 16];
+        // Pings 0..4, newest first, then empty rows; ping i is scaled into 0..=253
+        assert_eq!(row(0, 0), &[(75 * 253 / 255) as u8; 16]);
+        assert_eq!(row(0, 1), &[(50 * 253 / 255) as u8; 16]);
+        assert_eq!(row(0, 4), &[0; 16]);
+        assert_eq!(row(1, 0), &[(175 * 253 / 255) as u8; 16]);
+        assert_eq!(row(2, 0), &[(225 * 253 / 255) as u8; 16]);
+        assert_eq!(row(2, 9), &[0; 16]);
+
+        config.minimap = true;
+        let mut out = Vec::new();
+        write_waterfall_gif_to(&mut out, &pings, &config, &ImagingSettings::default()).unwrap();
+        let (_, _, frames) = decode_gif(&out);
+        assert!(frames.iter().all(|f| f.indices.contains(&TRACK_INDEX) && f.indices.contains(&POSITION_INDEX)));
+        let other_channel = VideoConfig { channel_id: 3, ..config };
+        assert!(write_waterfall_gif_to(Vec::new(), &pings, &other_channel, &ImagingSettings::default()).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+// Waterfall rendering of ping sample data
+// src/imaging/waterfall.rs
+
+use super::palette::{Levels, Palette};
+use super::png::{write_png, PngColor};
+use crate::survey::Ping;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Row-major 8-bit image; one row per ping, newest ping last
+#[derive(Debug, Clone)]
+pub struct Waterfall {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+/// Pings belonging to a single channel, in recording order
+pub fn channel_pings(pings: &[Ping], channel_id: u16) -> Vec<&Ping> {
+    pings.iter().filter(|p| p.channel_id == channel_id).collect()
+}
+
+/// Fractional range difference treated as a change of range setting
+pub const RANGE_CHANGE_TOLERANCE: f64 = 0.02;
+
+/// Run of consecutive pings recorded with one range setting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangeSegment {
+    /// Index of the first ping
+    pub start: usize,
+    /// One past the last ping
+    pub end: usize,
+    /// Largest `range_m` in the segment, which its rows are scaled to
+    pub range_m: f64,
+}
+
+/// Split pings where the operator changed range or zoom
+///
+/// Compares the recorded range setting when the format has one, else
+/// `range_m`; a change beyond [`RANGE_CHANGE_TOLERANCE`] starts a segment.
+/// Pings without a range join the current segment.
+pub fn range_segments(pings: &[&Ping]) -> Vec<RangeSegment> {
+    let setting = |p: &Ping| p.acoustics.range_setting_m.unwrap_or(p.range_m);
+    let mut segments: Vec<RangeSegment> = Vec::new();
+    let mut current_setting = 0.0;
+    for (i, ping) in pings.iter().enumerate() {
+        let value = setting(ping);
+        let changed = value > 0.0
+            && current_setting > 0.0
+            && (value - current_setting).abs() > RANGE_CHANGE_TOLERANCE * current_setting.max(value);
+        match segments.last_mut() {
+            Some(segment) if !changed => {
+                segment.end = i + 1;
+                segment.range_m = segment.range_m.max(ping.range_m);
+            }
+            _ => segments.push(RangeSegment {
+                start: i,
+                end: i + 1,
+                range_m: ping.range_m.max(0.0),
+            }),
+        }
+        if value > 0.0 {
+            current_setting = value;
+        }
+    }
+    segments
+}
+
+/// Resample ping sample arrays to a fixed width and stack them as rows
+///
+/// Each [`range_segments`] segment is scaled to its own largest range, so
+/// rows fill the width across range changes, while shorter-range pings
+/// within a segment leave the far columns black rather than being stretched.
+pub fn render_waterfall(pings: &[&Ping], width: usize, levels: &Levels) -> Waterfall {
+    let mut pixels = vec![0u8; width * pings.len()];
+
+    for segment in range_segments(pings) {
+        if segment.range_m <= 0.0 {
+            continue;
+        }
+        let meters_per_col = segment.range_m / width as f64;
+        for (row, ping) in pings.iter().enumerate().take(segment.end).skip(segment.start) {
+            if ping.samples.is_empty() {
+                continue;
+            }
+            let samples = levels.apply(ping);
+            let spacing = ping.sample_spacing_m();
+            for col in 0..width {
+                let range = (col as f64 + 0.5) * meters_per_col;
+                let idx = (range / spacing) as usize;
+                if idx < samples.len() {
+                    pixels[row * width + col] = samples[idx];
+                }
+            }
+        }
+    }
+
+    Waterfall {
+        width,
+        height: pings.len(),
+        pixels,
+    }
+}
+
+impl Waterfall {
+    /// Write the waterfall as an RGB PNG colored with the given palette
+    pub fn write_png<P: AsRef<Path>>(&self, path: P, palette: &Palette) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write_png(&mut out, self.width, self.height, PngColor::Rgb, &palette.colorize(&self.pixels))?;
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(range_m: f64, value: u8) -> Ping {
+        Ping {
+            range_m,
+            samples: vec![value; 4],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn range_changes_split_segments() {
+        let pings = [ping(30.0, 1), ping(30.3, 1), ping(0.0, 1), ping(60.0, 1), ping(60.0, 1)];
+        let refs: Vec<&Ping> = pings.iter().collect();
+        let segments = range_segments(&refs);
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].start, segments[0].end, segments[0].range_m), (0, 3, 30.3));
+        assert_eq!((segments[1].start, segments[1].end, segments[1].range_m), (3, 5, 60.0));
+
+        // A recorded range setting wins over the per-ping range
+        let mut zoomed = pings.clone();
+        for p in &mut zoomed {
+            p.acoustics.range_setting_m = Some(30.0);
+        }
+        assert_eq!(range_segments(&zoomed.iter().collect::<Vec<_>>()).len(), 1);
+    }
+
+    #[test]
+    fn each_segment_fills_the_width_and_short_pings_stay_black() {
+        let mut pings = [ping(30.0, 50), ping(15.0, 80), ping(60.0, 200)];
+        // Same 30 m setting, but only the near half of the range was recorded
+        pings[1].acoustics.range_setting_m = Some(30.0);
+        let refs: Vec<&Ping> = pings.iter().collect();
+        let image = render_waterfall(&refs, 4, &Levels::default());
+        assert_eq!((image.width, image.height), (4, 3));
+        assert_eq!(&image.pixels[0..4], &[50; 4]);
+        assert_eq!(&image.pixels[4..8], &[80, 80, 0, 0]);
+        assert_eq!(&image.pixels[8..12], &[200; 4]);
+    }
+}
@@ -1,12 +1,53 @@
 // High-performance drift calculation kernel in Rust
 // src/lib.rs
 
+// pyo3 0.19's #[pymethods] expansion of #[new] trips this lint on current compilers
+#![allow(non_local_definitions)]
+// and its create_exception! expansion tests a cfg that current compilers do not know
+#![allow(unexpected_cfgs)]
+
 use pyo3::prelude::*;
 use numpy::{PyArray1, PyArray2};
 use rayon::prelude::*;
-use nalgebra::{Vector2, Vector3};
-use chrono::{DateTime, Utc};
-use std::f64::consts::PI;
+
+pub mod anonymize;
+pub mod batch;
+pub mod bounds;
+pub mod catalog;
+pub mod classification;
+pub mod contours;
+pub mod coverage;
+pub mod crs;
+pub mod datum;
+pub mod dedup;
+pub mod detection;
+pub mod diff;
+pub mod export;
+pub mod expr;
+pub mod geo;
+pub mod gridding;
+pub mod imaging;
+pub mod metadata;
+pub mod motion;
+pub mod parsers;
+pub mod pipeline;
+pub mod profile;
+mod python;
+pub mod qc;
+pub mod query;
+pub mod route;
+pub mod signal;
+pub mod spatial;
+pub mod sqlite;
+pub mod stats;
+pub mod survey;
+#[cfg(test)]
+mod testutil;
+pub mod timezone;
+pub mod track;
+pub mod validate;
+pub mod vessel;
+pub mod watch;
 
 #[derive(Debug, Clone)]
 pub struct DriftPoint {
@@ -34,6 +75,15 @@ pub struct HighPerformanceDriftAnalyzer {
     environmental_data: Vec<EnvironmentalConditions>,
 }
 
+/// (times, lats, lons) arrays of one simulated track
+type Trajectory = (Py<PyArray1<f64>>, Py<PyArray1<f64>>, Py<PyArray1<f64>>);
+
+impl Default for HighPerformanceDriftAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[pymethods]
 impl HighPerformanceDriftAnalyzer {
     #[new]
@@ -53,10 +103,15 @@ impl HighPerformanceDriftAnalyzer {
         velocities_v: &PyArray1<f64>
     ) {
         let timestamps = timestamps.readonly();
+        let timestamps = timestamps.as_array();
         let lats = lats.readonly();
+        let lats = lats.as_array();
         let lons = lons.readonly();
+        let lons = lons.as_array();
         let velocities_u = velocities_u.readonly();
+        let velocities_u = velocities_u.as_array();
         let velocities_v = velocities_v.readonly();
+        let velocities_v = velocities_v.as_array();
 
         self.drift_points = (0..timestamps.len())
             .into_par_iter()
@@ -71,6 +126,7 @@ impl HighPerformanceDriftAnalyzer {
     }
 
     /// Add environmental conditions (vectorized operation)
+    #[allow(clippy::too_many_arguments)]
     pub fn add_environmental_data(&mut self,
         wind_speeds: &PyArray1<f64>,
         wind_directions: &PyArray1<f64>,
@@ -81,12 +137,19 @@ impl HighPerformanceDriftAnalyzer {
         pressures: &PyArray1<f64>
     ) {
         let wind_speeds = wind_speeds.readonly();
+        let wind_speeds = wind_speeds.as_array();
         let wind_directions = wind_directions.readonly();
+        let wind_directions = wind_directions.as_array();
         let currents_u = currents_u.readonly();
+        let currents_u = currents_u.as_array();
         let currents_v = currents_v.readonly();
+        let currents_v = currents_v.as_array();
         let wave_heights = wave_heights.readonly();
+        let wave_heights = wave_heights.as_array();
         let water_temps = water_temps.readonly();
+        let water_temps = water_temps.as_array();
         let pressures = pressures.readonly();
+        let pressures = pressures.as_array();
 
         self.environmental_data = (0..wind_speeds.len())
             .into_par_iter()
@@ -109,7 +172,7 @@ impl HighPerformanceDriftAnalyzer {
         duration_hours: f64,
         time_step_minutes: f64,
         py: Python
-    ) -> PyResult<(Py<PyArray1<f64>>, Py<PyArray1<f64>>, Py<PyArray1<f64>>)> {
+    ) -> PyResult<Trajectory> {
         
         let num_steps = (duration_hours * 60.0 / time_step_minutes) as usize;
         let dt = time_step_minutes * 60.0; // Convert to seconds
@@ -122,7 +185,7 @@ impl HighPerformanceDriftAnalyzer {
         let mut current_lon = start_lon;
         let mut current_time = 0.0;
         
-        for step in 0..num_steps {
+        for _ in 0..num_steps {
             trajectory_times.push(current_time);
             trajectory_lats.push(current_lat);
             trajectory_lons.push(current_lon);
@@ -162,7 +225,8 @@ impl HighPerformanceDriftAnalyzer {
     ) -> PyResult<Py<PyArray2<f64>>> {
         
         let start_positions = start_positions.readonly();
-        let n_scenarios = start_positions.shape()[0];
+        let start_positions = start_positions.as_array();
+        let n_scenarios = start_positions.nrows();
         
         // Parallel processing of multiple drift scenarios
         let results: Vec<Vec<f64>> = (0..n_scenarios)
@@ -198,7 +262,6 @@ impl HighPerformanceDriftAnalyzer {
         
         // Monte Carlo simulation with environmental uncertainty
         let n_simulations = 1000;
-        let mut all_endpoints: Vec<(f64, f64)> = Vec::with_capacity(n_simulations);
         
         // Parallel Monte Carlo simulations
         let endpoints: Vec<(f64, f64)> = (0..n_simulations)
@@ -260,7 +323,7 @@ impl HighPerformanceDriftAnalyzer {
     }
     
     /// Interpolate environmental conditions for given location/time
-    fn interpolate_environmental_conditions(&self, lat: f64, lon: f64, time: f64) -> EnvironmentalConditions {
+    fn interpolate_environmental_conditions(&self, _lat: f64, _lon: f64, _time: f64) -> EnvironmentalConditions {
         // Simplified interpolation - in practice would use spatial-temporal interpolation
         if let Some(env) = self.environmental_data.first() {
             env.clone()
@@ -333,7 +396,7 @@ impl HighPerformanceDriftAnalyzer {
     }
     
     /// Calculate probability density from Monte Carlo results
-    fn calculate_probability_density(&self, endpoints: &[(f64, f64)], confidence_level: f64) -> Vec<Vec<f64>> {
+    fn calculate_probability_density(&self, endpoints: &[(f64, f64)], _confidence_level: f64) -> Vec<Vec<f64>> {
         // Create probability grid (simplified implementation)
         let grid_size = 50;
         let mut grid = vec![vec![0.0; grid_size]; grid_size];
@@ -368,7 +431,8 @@ impl HighPerformanceDriftAnalyzer {
 
 /// Python module definition
 #[pymodule]
-fn cesarops_core(py: Python, m: &PyModule) -> PyResult<()> {
+fn cesarops_core(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<HighPerformanceDriftAnalyzer>()?;
+    python::register(m)?;
     Ok(())
 }
\ No newline at end of file
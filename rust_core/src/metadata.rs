@@ -0,0 +1,326 @@
+// Survey metadata sidecar files that travel with recordings and exports
+// src/metadata.rs
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Suffix appended to a recording or export file name for its sidecar
+pub const SIDECAR_SUFFIX: &str = ".meta.json";
+
+/// Descriptive metadata for a recording, session or export
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SurveyMetadata {
+    pub vessel: String,
+    pub operator: String,
+    pub project: String,
+    pub notes: String,
+    /// Processing steps applied, e.g. `"tvg 20log"` or `"layback 35 m"`
+    pub corrections: Vec<String>,
+    /// Other string fields, kept so round trips do not lose them
+    pub extra: BTreeMap<String, String>,
+}
+
+impl SurveyMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Record a processing step unless it is already listed
+    pub fn add_correction<S: Into<String>>(&mut self, step: S) {
+        let step = step.into();
+        if !self.corrections.contains(&step) {
+            self.corrections.push(step);
+        }
+    }
+
+    /// Pretty-printed JSON object
+    pub fn to_json(&self) -> String {
+        let mut fields = vec![
+            ("vessel", json_string(&self.vessel)),
+            ("operator", json_string(&self.operator)),
+            ("project", json_string(&self.project)),
+            ("notes", json_string(&self.notes)),
+        ];
+        let corrections: Vec<String> = self.corrections.iter().map(|c| json_string(c)).collect();
+        fields.push(("corrections", format!("[{}]", corrections.join(", "))));
+        fields.extend(self.extra.iter().map(|(k, v)| (k.as_str(), json_string(v))));
+
+        let body: Vec<String> = fields.iter().map(|(k, v)| format!("  {}: {}", json_string(k), v)).collect();
+        format!("{{\n{}\n}}\n", body.join(",\n"))
+    }
+
+    /// Parse a sidecar JSON object; unknown non-string values are ignored
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        let mut parser = JsonParser { text, pos: 0 };
+        let Json::Object(fields) = parser.value()? else {
+            return Err(invalid("metadata sidecar must be a JSON object"));
+        };
+        let mut meta = Self::default();
+        for (key, value) in fields {
+            match (key.as_str(), value) {
+                ("vessel", Json::String(s)) => meta.vessel = s,
+                ("operator", Json::String(s)) => meta.operator = s,
+                ("project", Json::String(s)) => meta.project = s,
+                ("notes", Json::String(s)) => meta.notes = s,
+                ("corrections", Json::Array(items)) => {
+                    meta.corrections = items
+                        .into_iter()
+                        .filter_map(|v| match v {
+                            Json::String(s) => Some(s),
+                            _ => None,
+                        })
+                        .collect()
+                }
+                (_, Json::String(s)) => {
+                    meta.extra.insert(key, s);
+                }
+                _ => {}
+            }
+        }
+        Ok(meta)
+    }
+
+    /// One-line summary for formats with a single description field
+    pub fn description(&self) -> String {
+        let mut parts = Vec::new();
+        for (label, value) in [("Vessel", &self.vessel), ("Operator", &self.operator), ("Project", &self.project)] {
+            if !value.is_empty() {
+                parts.push(format!("{}: {}", label, value));
+            }
+        }
+        if !self.corrections.is_empty() {
+            parts.push(format!("Corrections: {}", self.corrections.join(", ")));
+        }
+        if !self.notes.is_empty() {
+            parts.push(self.notes.clone());
+        }
+        parts.join("; ")
+    }
+}
+
+/// Sidecar location for a file, e.g. `survey.sl2` -> `survey.sl2.meta.json`
+pub fn sidecar_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(SIDECAR_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Metadata stored next to `path`, or None when it has no sidecar
+pub fn read_sidecar<P: AsRef<Path>>(path: P) -> io::Result<Option<SurveyMetadata>> {
+    match fs::read_to_string(sidecar_path(path)) {
+        Ok(text) => SurveyMetadata::from_json(&text).map(Some),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Write `metadata` next to `path`, e.g. alongside an export so it travels with it
+pub fn write_sidecar<P: AsRef<Path>>(path: P, metadata: &SurveyMetadata) -> io::Result<()> {
+    fs::write(sidecar_path(path), metadata.to_json())
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn invalid<S: Into<String>>(msg: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+enum Json {
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+    /// Numbers, booleans and null
+    Scalar,
+}
+
+/// Minimal JSON reader covering what sidecar files contain
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn value(&mut self) -> io::Result<Json> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => self.string().map(Json::String),
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                while !self.close(']')? {
+                    items.push(self.value()?);
+                }
+                Ok(Json::Array(items))
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                while !self.close('}')? {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    if self.peek() != Some(':') {
+                        return Err(invalid(format!("expected ':' at byte {}", self.pos)));
+                    }
+                    self.pos += 1;
+                    fields.push((key, self.value()?));
+                }
+                Ok(Json::Object(fields))
+            }
+            Some(_) => {
+                let len = self.text[self.pos..]
+                    .find(|c: char| c == ',' || c == ']' || c == '}' || c.is_whitespace())
+                    .unwrap_or(self.text.len() - self.pos);
+                if len == 0 {
+                    return Err(invalid(format!("unexpected character at byte {}", self.pos)));
+                }
+                self.pos += len;
+                Ok(Json::Scalar)
+            }
+            None => Err(invalid("unexpected end of JSON")),
+        }
+    }
+
+    /// Consume a separating comma, returning true at the closing bracket
+    fn close(&mut self, bracket: char) -> io::Result<bool> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == bracket => {
+                self.pos += 1;
+                Ok(true)
+            }
+            Some(',') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(_) => Ok(false),
+            None => Err(invalid("unexpected end of JSON")),
+        }
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        if self.peek() != Some('"') {
+            return Err(invalid(format!("expected string at byte {}", self.pos)));
+        }
+        let mut out = String::new();
+        let mut chars = self.text[self.pos + 1..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 2;
+                    return Ok(out);
+                }
+                '\\' => match chars.next().map(|(_, e)| e) {
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some('b') => out.push('\u{8}'),
+                    Some('f') => out.push('\u{c}'),
+                    Some('u') => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, h)| h).collect();
+                        let code = u32::from_str_radix(&hex, 16).map_err(|_| invalid("bad \\u escape"))?;
+                        out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    }
+                    Some(other) => out.push(other),
+                    None => break,
+                },
+                c => out.push(c),
+            }
+        }
+        Err(invalid("unterminated JSON string"))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch;
+
+    fn sample() -> SurveyMetadata {
+        let mut meta = SurveyMetadata {
+            vessel: "Osprey".to_string(),
+            operator: "J. Doe".to_string(),
+            project: "Harbour \"East\"".to_string(),
+            notes: "calm\tline 2\nrepeat".to_string(),
+            ..Default::default()
+        };
+        meta.add_correction("tvg 20log");
+        meta.add_correction("layback 35 m");
+        meta.add_correction("tvg 20log");
+        meta.extra.insert("client".to_string(), "Port Authority".to_string());
+        meta
+    }
+
+    #[test]
+    fn json_is_stable_and_round_trips() {
+        let meta = sample();
+        assert_eq!(meta.corrections, ["tvg 20log", "layback 35 m"]);
+        assert_eq!(
+            meta.to_json(),
+            "{\n  \"vessel\": \"Osprey\",\n  \"operator\": \"J. Doe\",\n  \"project\": \"Harbour \\\"East\\\"\",\n  \
+             \"notes\": \"calm\\tline 2\\nrepeat\",\n  \"corrections\": [\"tvg 20log\", \"layback 35 m\"],\n  \
+             \"client\": \"Port Authority\"\n}\n"
+        );
+        assert_eq!(SurveyMetadata::from_json(&meta.to_json()).unwrap(), meta);
+        assert_eq!(
+            meta.description(),
+            "Vessel: Osprey; Operator: J. Doe; Project: Harbour \"East\"; \
+             Corrections: tvg 20log, layback 35 m; calm\tline 2\nrepeat"
+        );
+    }
+
+    #[test]
+    fn reading_skips_non_string_values() {
+        let meta = SurveyMetadata::from_json(
+            "{\"vessel\": \"Tern\", \"depth_offset\": 0.4, \"checked\": true, \"tags\": [\"a\"],\
+             \"corrections\": [\"draft 0.5 m\", 3, null], \"note\": \"caf\\u00e9\"}",
+        )
+        .unwrap();
+        assert_eq!(meta.vessel, "Tern");
+        assert_eq!(meta.corrections, ["draft 0.5 m"]);
+        assert_eq!(meta.extra.len(), 1);
+        assert_eq!(meta.extra["note"], "café");
+
+        assert!(SurveyMetadata::from_json("[\"vessel\"]").is_err());
+        assert!(SurveyMetadata::from_json("{\"vessel\": \"Tern\"").is_err());
+        assert!(SurveyMetadata::from_json("{\"vessel\" \"Tern\"}").is_err());
+    }
+
+    #[test]
+    fn sidecars_sit_next_to_their_file() {
+        let recording = scratch("metadata", "survey.sl2");
+        assert_eq!(sidecar_path("/data/survey.sl2"), Path::new("/data/survey.sl2.meta.json"));
+        assert_eq!(read_sidecar(&recording).unwrap(), None);
+
+        write_sidecar(&recording, &sample()).unwrap();
+        assert_eq!(read_sidecar(&recording).unwrap(), Some(sample()));
+        fs::remove_file(sidecar_path(&recording)).unwrap();
+    }
+}
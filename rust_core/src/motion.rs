@@ -0,0 +1,468 @@
+// Course and speed over ground from position deltas, and water current estimates
+// src/motion.rs
+
+use crate::geo::local_offset_m;
+use crate::survey::Ping;
+use std::collections::VecDeque;
+use std::io;
+
+const METERS_PER_SECOND_PER_KNOT: f64 = 0.514444;
+
+/// Smoothing settings for computed course and speed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionConfig {
+    /// Positions this far apart in time are differenced; longer windows smooth more.
+    /// Negative or NaN windows act as zero
+    pub window_s: f64,
+    /// Below this speed position noise dominates and course is left unknown
+    pub min_speed_knots: f64,
+}
+
+impl MotionConfig {
+    /// Half the window, never negative
+    fn half_window_s(&self) -> f64 {
+        (self.window_s / 2.0).max(0.0)
+    }
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            window_s: 5.0,
+            min_speed_knots: 0.5,
+        }
+    }
+}
+
+/// Course and speed at each `(timestamp, lat, lon)` position (sorted by time)
+///
+/// Uses the displacement between the earliest and latest positions within
+/// half a window either side, so a single bad fix cannot swing the course.
+pub fn course_over_ground(track: &[(f64, f64, f64)], config: &MotionConfig) -> Vec<(Option<f64>, Option<f64>)> {
+    let half = config.half_window_s();
+    let (mut lo, mut hi) = (0, 0);
+
+    track
+        .iter()
+        .enumerate()
+        .map(|(i, &(t, _, _))| {
+            while lo < i && track[lo].0 < t - half {
+                lo += 1;
+            }
+            while hi + 1 < track.len() && track[hi + 1].0 <= t + half {
+                hi += 1;
+            }
+            displacement_motion(track[lo], track[hi], config)
+        })
+        .collect()
+}
+
+/// Course and speed from the displacement between two `(timestamp, lat, lon)` positions
+fn displacement_motion(a: (f64, f64, f64), b: (f64, f64, f64), config: &MotionConfig) -> (Option<f64>, Option<f64>) {
+    let dt = b.0 - a.0;
+    if dt <= 0.0 {
+        return (None, None);
+    }
+    let (east, north) = local_offset_m(a.1, a.2, b.1, b.2);
+    let sog = east.hypot(north) / dt / METERS_PER_SECOND_PER_KNOT;
+    let cog = (sog >= config.min_speed_knots).then(|| east.atan2(north).to_degrees().rem_euclid(360.0));
+    (cog, Some(sog))
+}
+
+fn is_positioned(ping: &Ping) -> bool {
+    ping.lat != 0.0 || ping.lon != 0.0
+}
+
+/// Fill `cog_deg` and `sog_knots` on pings from their positions
+///
+/// Pings from different channels at one timestamp share a position and
+/// receive the same values. Unpositioned pings are left unchanged.
+pub fn compute_motion(pings: &mut [Ping], config: &MotionConfig) {
+    let mut order: Vec<usize> = (0..pings.len()).filter(|&i| is_positioned(&pings[i])).collect();
+    order.sort_by(|&a, &b| pings[a].timestamp.total_cmp(&pings[b].timestamp));
+
+    let mut track: Vec<(f64, f64, f64)> = Vec::new();
+    let mut slot = Vec::with_capacity(order.len());
+    for &i in &order {
+        let p = &pings[i];
+        if track.last().is_none_or(|last| last.0 != p.timestamp) {
+            track.push((p.timestamp, p.lat, p.lon));
+        }
+        slot.push(track.len() - 1);
+    }
+
+    let motion = course_over_ground(&track, config);
+    for (&i, &k) in order.iter().zip(&slot) {
+        (pings[i].cog_deg, pings[i].sog_knots) = motion[k];
+    }
+}
+
+/// Ping stream adapter that fills `cog_deg` and `sog_knots` as it reads
+///
+/// Each positioned ping is held back until a position more than half a
+/// window after it has been read, so time-ordered input gets the same values
+/// as [`compute_motion`] without loading the whole recording. A read error is
+/// returned after the pings read before it, and reading resumes on the next call.
+pub struct MotionStream<I> {
+    inner: I,
+    config: MotionConfig,
+    /// Read but not yet returned
+    pending: VecDeque<Ping>,
+    /// Distinct-timestamp positions from half a window before the oldest pending ping
+    track: VecDeque<(f64, f64, f64)>,
+    /// Returned once `pending` drains
+    error: Option<io::Error>,
+    done: bool,
+}
+
+impl<I: Iterator<Item = io::Result<Ping>>> MotionStream<I> {
+    pub fn new(inner: I, config: MotionConfig) -> Self {
+        Self {
+            inner,
+            config,
+            pending: VecDeque::new(),
+            track: VecDeque::new(),
+            error: None,
+            done: false,
+        }
+    }
+
+    /// True once every position within the front ping's window has been read
+    fn front_ready(&self) -> bool {
+        match self.pending.front() {
+            None => false,
+            Some(p) if !is_positioned(p) => true,
+            Some(p) => self
+                .track
+                .back()
+                .is_some_and(|last| last.0 > p.timestamp + self.config.half_window_s()),
+        }
+    }
+}
+
+impl<I: Iterator<Item = io::Result<Ping>>> Iterator for MotionStream<I> {
+    type Item = io::Result<Ping>;
+
+    fn next(&mut self) -> Option<io::Result<Ping>> {
+        while !self.done && self.error.is_none() && !self.front_ready() {
+            match self.inner.next() {
+                Some(Ok(ping)) => {
+                    if is_positioned(&ping) && self.track.back().is_none_or(|last| last.0 != ping.timestamp) {
+                        self.track.push_back((ping.timestamp, ping.lat, ping.lon));
+                    }
+                    self.pending.push_back(ping);
+                }
+                Some(Err(err)) => self.error = Some(err),
+                None => self.done = true,
+            }
+        }
+
+        let Some(mut ping) = self.pending.pop_front() else {
+            return self.error.take().map(Err);
+        };
+        if is_positioned(&ping) {
+            let half = self.config.half_window_s();
+            let t = ping.timestamp;
+            while self.track.front().is_some_and(|first| first.0 < t - half) {
+                self.track.pop_front();
+            }
+            let hi = self.track.iter().rposition(|p| p.0 <= t + half);
+            (ping.cog_deg, ping.sog_knots) = match (self.track.front(), hi) {
+                (Some(&first), Some(hi)) => displacement_motion(first, self.track[hi], &self.config),
+                _ => (None, None),
+            };
+        }
+        Some(Ok(ping))
+    }
+}
+
+/// Fill `stw_knots` from a `(timestamp, knots)` water speed log
+///
+/// Each ping takes the nearest reading no more than `max_gap_s` away;
+/// pings without one are left unchanged. Returns the number updated.
+pub fn fuse_water_speed(pings: &mut [Ping], speeds: &[(f64, f64)], max_gap_s: f64) -> usize {
+    let mut sorted: Vec<(f64, f64)> = speeds
+        .iter()
+        .copied()
+        .filter(|(t, v)| t.is_finite() && v.is_finite())
+        .collect();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut updated = 0;
+    for p in pings.iter_mut() {
+        let idx = sorted.partition_point(|s| s.0 < p.timestamp);
+        let nearest = [idx.checked_sub(1), Some(idx)]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| sorted.get(i))
+            .min_by(|a, b| (a.0 - p.timestamp).abs().total_cmp(&(b.0 - p.timestamp).abs()));
+        if let Some(&(_, knots)) = nearest.filter(|s| (s.0 - p.timestamp).abs() <= max_gap_s) {
+            p.stw_knots = Some(knots);
+            updated += 1;
+        }
+    }
+    updated
+}
+
+/// Water current at one point along the track
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurrentEstimate {
+    pub timestamp: f64,
+    pub lat: f64,
+    pub lon: f64,
+    /// Direction the current flows towards, degrees true
+    pub set_deg: f64,
+    pub drift_knots: f64,
+}
+
+/// Current set and drift from the difference between ground and water velocity
+///
+/// Ground velocity is `sog_knots` along `cog_deg`; water velocity is
+/// `stw_knots` along `heading_deg` (leeway is ignored). Vector components are
+/// averaged over `window_s` to suppress heading and paddlewheel noise. Only
+/// pings carrying all four values contribute, one per timestamp.
+pub fn estimate_current(pings: &[Ping], window_s: f64) -> Vec<CurrentEstimate> {
+    let mut samples: Vec<(f64, f64, f64, f64, f64)> = pings
+        .iter()
+        .filter_map(|p| {
+            let (cog, sog, stw) = (p.cog_deg?, p.sog_knots?, p.stw_knots?);
+            let (ground_e, ground_n) = polar(sog, cog);
+            let (water_e, water_n) = polar(stw, p.heading_deg);
+            Some((p.timestamp, p.lat, p.lon, ground_e - water_e, ground_n - water_n))
+        })
+        .collect();
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    samples.dedup_by(|a, b| a.0 == b.0);
+
+    let half = window_s.max(0.0) / 2.0;
+    let (mut lo, mut hi) = (0, 0);
+    let (mut sum_e, mut sum_n) = (0.0, 0.0);
+    samples
+        .iter()
+        .map(|&(t, lat, lon, _, _)| {
+            while hi < samples.len() && samples[hi].0 <= t + half {
+                sum_e += samples[hi].3;
+                sum_n += samples[hi].4;
+                hi += 1;
+            }
+            while samples[lo].0 < t - half {
+                sum_e -= samples[lo].3;
+                sum_n -= samples[lo].4;
+                lo += 1;
+            }
+            let n = (hi - lo) as f64;
+            let (east, north) = (sum_e / n, sum_n / n);
+            CurrentEstimate {
+                timestamp: t,
+                lat,
+                lon,
+                set_deg: east.atan2(north).to_degrees().rem_euclid(360.0),
+                drift_knots: east.hypot(north),
+            }
+        })
+        .collect()
+}
+
+/// East/north components of a speed along a bearing
+fn polar(speed: f64, bearing_deg: f64) -> (f64, f64) {
+    let (sin, cos) = bearing_deg.to_radians().sin_cos();
+    (speed * sin, speed * cos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::offset_position;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const KNOT: f64 = METERS_PER_SECOND_PER_KNOT;
+
+    /// One ping per second per channel along a constant-velocity track
+    fn track(seconds: usize, channels: u16, east_ms: f64, north_ms: f64) -> Vec<Ping> {
+        (0..seconds)
+            .flat_map(|i| {
+                let t = i as f64;
+                let (lat, lon) = offset_position(45.0, -83.0, east_ms * t, north_ms * t);
+                (0..channels).map(move |channel_id| Ping {
+                    timestamp: t,
+                    channel_id,
+                    lat,
+                    lon,
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    fn close(a: f64, b: f64, tol: f64) -> bool {
+        (a - b).abs() <= tol
+    }
+
+    #[test]
+    fn constant_course_and_speed() {
+        for (east, north, course) in [(0.0, 5.0, 0.0), (5.0, 0.0, 90.0), (-3.0, -3.0, 225.0)] {
+            let mut pings = track(30, 2, east * KNOT, north * KNOT);
+            compute_motion(&mut pings, &MotionConfig::default());
+            let speed = f64::hypot(east, north);
+            for p in &pings {
+                let cog = p.cog_deg.unwrap();
+                assert!(
+                    close(cog, course, 0.1) || close(cog, course + 360.0, 0.1),
+                    "{} vs {}",
+                    cog,
+                    course
+                );
+                assert!(close(p.sog_knots.unwrap(), speed, 0.01));
+            }
+            for pair in pings.chunks(2) {
+                assert_eq!(pair[0].cog_deg, pair[1].cog_deg);
+                assert_eq!(pair[0].sog_knots, pair[1].sog_knots);
+            }
+        }
+    }
+
+    #[test]
+    fn slow_and_unpositioned_pings() {
+        let mut pings = track(10, 1, 0.1 * KNOT, 0.0);
+        pings[4].lat = 0.0;
+        pings[4].lon = 0.0;
+        pings[4].cog_deg = Some(12.0);
+        compute_motion(&mut pings, &MotionConfig::default());
+        assert_eq!(pings[4].cog_deg, Some(12.0));
+        assert_eq!(pings[4].sog_knots, None);
+        for p in pings.iter().filter(|p| p.lat != 0.0) {
+            assert_eq!(p.cog_deg, None);
+            assert!(close(p.sog_knots.unwrap(), 0.1, 0.01));
+        }
+
+        let mut single = track(1, 3, 1.0, 1.0);
+        compute_motion(&mut single, &MotionConfig::default());
+        assert!(single.iter().all(|p| p.cog_deg.is_none() && p.sog_knots.is_none()));
+        compute_motion(&mut [], &MotionConfig::default());
+    }
+
+    #[test]
+    fn stream_matches_compute_motion() {
+        let mut rng = StdRng::seed_from_u64(371);
+        let mut pings = Vec::new();
+        let (mut t, mut lat, mut lon) = (0.0, 45.0, -83.0);
+        for _ in 0..500 {
+            t += rng.gen_range(0.0..2.0_f64);
+            (lat, lon) = offset_position(lat, lon, rng.gen_range(-3.0..3.0), rng.gen_range(-3.0..3.0));
+            for channel_id in 0..rng.gen_range(1..4) {
+                let positioned = rng.gen_bool(0.9);
+                pings.push(Ping {
+                    timestamp: t,
+                    channel_id,
+                    lat: if positioned { lat } else { 0.0 },
+                    lon: if positioned { lon } else { 0.0 },
+                    ..Default::default()
+                });
+            }
+        }
+        for config in [
+            MotionConfig::default(),
+            MotionConfig {
+                window_s: 0.0,
+                min_speed_knots: 0.0,
+            },
+            MotionConfig {
+                window_s: -5.0,
+                min_speed_knots: 0.0,
+            },
+        ] {
+            let mut expected = pings.clone();
+            compute_motion(&mut expected, &config);
+            let streamed: Vec<Ping> = MotionStream::new(pings.iter().cloned().map(Ok), config)
+                .collect::<io::Result<_>>()
+                .unwrap();
+            assert_eq!(streamed, expected);
+        }
+    }
+
+    #[test]
+    fn negative_and_nan_windows_act_as_zero() {
+        let pings = track(3, 1, 2.0, 0.0);
+        let zero = MotionConfig {
+            window_s: 0.0,
+            ..MotionConfig::default()
+        };
+        let mut expected = pings.clone();
+        compute_motion(&mut expected, &zero);
+        for window_s in [-5.0, f64::NAN] {
+            let config = MotionConfig { window_s, ..zero };
+            let mut computed = pings.clone();
+            compute_motion(&mut computed, &config);
+            assert_eq!(computed, expected);
+            let streamed: Vec<Ping> = MotionStream::new(pings.iter().cloned().map(Ok), config)
+                .collect::<io::Result<_>>()
+                .unwrap();
+            assert_eq!(streamed, expected);
+        }
+    }
+
+    #[test]
+    fn stream_errors_keep_order_and_resume() {
+        let pings = track(20, 1, 2.0, 0.0);
+        let mut input: Vec<io::Result<Ping>> = pings.iter().cloned().map(Ok).collect();
+        input.insert(10, Err(io::Error::new(io::ErrorKind::InvalidData, "bad record")));
+
+        let mut stream = MotionStream::new(input.into_iter(), MotionConfig::default());
+        let before: Vec<Ping> = stream.by_ref().take(10).map(Result::unwrap).collect();
+        assert_eq!(
+            before.iter().map(|p| p.timestamp).collect::<Vec<_>>(),
+            (0..10).map(f64::from).collect::<Vec<_>>()
+        );
+        assert!(before.iter().all(|p| p.sog_knots.is_some()));
+        assert_eq!(stream.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let after: Vec<Ping> = stream.map(Result::unwrap).collect();
+        assert_eq!(after.len(), 10);
+        assert_eq!(after[0].timestamp, 10.0);
+        assert!(after.iter().all(|p| close(p.sog_knots.unwrap(), 2.0 / KNOT, 0.01)));
+    }
+
+    #[test]
+    fn water_speed_takes_the_nearest_reading_within_the_gap() {
+        let mut pings: Vec<Ping> = [0.0, 10.0, 20.0]
+            .into_iter()
+            .map(|timestamp| Ping {
+                timestamp,
+                stw_knots: Some(9.0),
+                ..Default::default()
+            })
+            .collect();
+        let speeds = [(11.0, 4.0), (-0.5, 3.0), (f64::NAN, 1.0), (9.5, 5.0)];
+        assert_eq!(fuse_water_speed(&mut pings, &speeds, 1.0), 2);
+        assert_eq!(pings.iter().map(|p| p.stw_knots).collect::<Vec<_>>(), [Some(3.0), Some(5.0), Some(9.0)]);
+    }
+
+    #[test]
+    fn current_is_ground_minus_water_velocity() {
+        // Heading north at 5 kn through the water, set east by 1 kn, with
+        // alternating heading noise that the window averages out
+        let pings: Vec<Ping> = (0..60)
+            .map(|i| {
+                let heading = if i % 2 == 0 { 2.0 } else { -2.0f64 };
+                let (water_e, water_n) = polar(5.0, heading);
+                let (east, north) = (water_e + 1.0, water_n);
+                Ping {
+                    timestamp: i as f64,
+                    heading_deg: heading.rem_euclid(360.0),
+                    cog_deg: Some(east.atan2(north).to_degrees().rem_euclid(360.0)),
+                    sog_knots: Some(east.hypot(north)),
+                    stw_knots: Some(5.0),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let currents = estimate_current(&pings, 10.0);
+        assert_eq!(currents.len(), 60);
+        for c in &currents {
+            assert!((c.set_deg - 90.0).abs() < 1e-6, "{:?}", c);
+            assert!((c.drift_knots - 1.0).abs() < 1e-9, "{:?}", c);
+        }
+        let mut no_stw = pings.clone();
+        no_stw.iter_mut().for_each(|p| p.stw_knots = None);
+        assert!(estimate_current(&no_stw, 10.0).is_empty());
+    }
+}
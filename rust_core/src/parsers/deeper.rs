@@ -0,0 +1,119 @@
+// Deeper smart sonar CSV import
+// src/parsers/deeper.rs
+
+use super::{parse_timestamp, ParseMode};
+use crate::survey::Sounding;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const LAT_NAMES: &[&str] = &["latitude", "lat"];
+const LON_NAMES: &[&str] = &["longitude", "lon", "lng", "long"];
+const DEPTH_NAMES: &[&str] = &["depth", "depth_m", "depth (m)", "depth(m)"];
+const TIME_NAMES: &[&str] = &["time", "timestamp", "datetime", "date"];
+
+/// Read soundings from a Deeper CSV export
+///
+/// Columns are located by header name, so exports from different app
+/// versions (and comma or semicolon separated files) load the same way.
+/// Rows without a position or depth are skipped in lenient mode and rejected
+/// in strict mode. The binary app logs are not supported.
+pub fn read_deeper_csv<P: AsRef<Path>>(path: P, mode: ParseMode) -> io::Result<Vec<Sounding>> {
+    let _span = tracing::info_span!("read_deeper_csv", path = %path.as_ref().display()).entered();
+    parse_deeper_csv(&fs::read_to_string(path)?, mode)
+}
+
+pub fn parse_deeper_csv(text: &str, mode: ParseMode) -> io::Result<Vec<Sounding>> {
+    let mut lines = text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+    let (_, header) = lines
+        .next()
+        .ok_or_else(|| invalid("empty Deeper CSV file"))?;
+    let delimiter = if header.matches(';').count() > header.matches(',').count() { ';' } else { ',' };
+    let columns: Vec<String> = header
+        .split(delimiter)
+        .map(|c| c.trim().trim_matches('"').to_lowercase())
+        .collect();
+
+    let find = |names: &[&str]| columns.iter().position(|c| names.contains(&c.as_str()));
+    let lat_col = find(LAT_NAMES).ok_or_else(|| invalid("no latitude column"))?;
+    let lon_col = find(LON_NAMES).ok_or_else(|| invalid("no longitude column"))?;
+    let depth_col = find(DEPTH_NAMES).ok_or_else(|| invalid("no depth column"))?;
+    let time_col = find(TIME_NAMES);
+
+    let mut soundings = Vec::new();
+    for (line_no, line) in lines {
+        let fields: Vec<&str> = line.split(delimiter).map(|f| f.trim().trim_matches('"')).collect();
+        let number = |col: usize| fields.get(col).and_then(|f| f.parse::<f64>().ok());
+
+        let (Some(lat), Some(lon), Some(depth_m)) = (number(lat_col), number(lon_col), number(depth_col)) else {
+            mode.check(line_no, "row without position or depth")?;
+            continue;
+        };
+        let timestamp = time_col
+            .and_then(|col| fields.get(col))
+            .and_then(|f| parse_timestamp(f))
+            .unwrap_or(0.0);
+
+        soundings.push(Sounding::new(timestamp, lat, lon, depth_m));
+    }
+    tracing::debug!(soundings = soundings.len(), "parsed Deeper CSV");
+    Ok(soundings)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_columns_by_header_name() {
+        let text = "Time,Depth (m),Latitude,Longitude,Temperature\n\
+                    1700000000000,4.25,44.6488,-63.5752,12.5\n\
+                    \n\
+                    1700000001000,4.5,44.6489,-63.5753,12.4\n";
+        let soundings = parse_deeper_csv(text, ParseMode::Strict).unwrap();
+        assert_eq!(soundings.len(), 2);
+        assert_eq!(soundings[0].timestamp, 1_700_000_000.0);
+        let first = &soundings[0];
+        assert_eq!((first.lat, first.lon, first.depth_m), (44.6488, -63.5752, 4.25));
+        assert_eq!(soundings[1].timestamp, 1_700_000_001.0);
+    }
+
+    #[test]
+    fn reads_semicolon_files_with_quoted_fields_and_dates() {
+        let text = "\"lat\";\"lng\";\"depth\";\"datetime\"\n\"44.5\";\"-63.5\";\"7.0\";\"2023-11-14 22:13:20\"\n";
+        let soundings = parse_deeper_csv(text, ParseMode::Strict).unwrap();
+        assert_eq!(soundings.len(), 1);
+        assert_eq!(soundings[0].timestamp, 1_700_000_000.0);
+        assert_eq!(soundings[0].depth_m, 7.0);
+    }
+
+    #[test]
+    fn missing_time_column_leaves_timestamps_at_zero() {
+        let soundings = parse_deeper_csv("lat,lon,depth_m\n44.5,-63.5,3\n", ParseMode::Strict).unwrap();
+        assert_eq!(soundings[0].timestamp, 0.0);
+    }
+
+    #[test]
+    fn incomplete_rows_are_skipped_leniently_and_rejected_strictly() {
+        let text = "lat,lon,depth\n44.5,-63.5,3\n44.5,,3\n44.5,-63.5,n/a\n44.6,-63.6\n44.7,-63.7,5\n";
+        let soundings = parse_deeper_csv(text, ParseMode::Lenient).unwrap();
+        assert_eq!(soundings.iter().map(|s| s.depth_m).collect::<Vec<_>>(), vec![3.0, 5.0]);
+        let err = parse_deeper_csv(text, ParseMode::Strict).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 3:"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_files_without_required_columns() {
+        assert!(parse_deeper_csv("", ParseMode::Lenient).is_err());
+        assert!(parse_deeper_csv("\n\n", ParseMode::Lenient).is_err());
+        for header in ["lon,depth", "lat,depth", "lat,lon,temperature", "\u{0}\u{1}binary"] {
+            let err = parse_deeper_csv(&format!("{}\n1,2\n", header), ParseMode::Lenient).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+// GPX track/route import and navigation substitution
+// src/parsers/gpx.rs
+
+use super::nmea0183::{fuse_pings, NavFix};
+use super::parse_timestamp;
+use crate::survey::Ping;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How recording time is matched to the GPX track
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeMatch {
+    /// Recording and track share a clock; `offset_s` is added to record times
+    Absolute { offset_s: f64 },
+    /// Elapsed time from the first record is matched to elapsed time from the
+    /// first track point, plus `offset_s` (for units whose clock never set)
+    Elapsed { offset_s: f64 },
+}
+
+/// Track points (`<trkpt>`) of a GPX file as navigation fixes, sorted by time
+pub fn read_gpx_track<P: AsRef<Path>>(path: P) -> io::Result<Vec<NavFix>> {
+    let _span = tracing::info_span!("read_gpx_track", path = %path.as_ref().display()).entered();
+    Ok(parse_points(&fs::read_to_string(path)?, "trkpt"))
+}
+
+/// Route points (`<rtept>`) in file order; timestamps are 0 when absent
+pub fn read_gpx_route<P: AsRef<Path>>(path: P) -> io::Result<Vec<NavFix>> {
+    let _span = tracing::info_span!("read_gpx_route", path = %path.as_ref().display()).entered();
+    Ok(parse_points(&fs::read_to_string(path)?, "rtept"))
+}
+
+/// Each `<rte>` of a GPX file as its name and route points, e.g. a survey line plan
+pub fn read_gpx_routes<P: AsRef<Path>>(path: P) -> io::Result<Vec<(String, Vec<NavFix>)>> {
+    let _span = tracing::info_span!("read_gpx_routes", path = %path.as_ref().display()).entered();
+    Ok(parse_routes(&fs::read_to_string(path)?))
+}
+
+/// Split a GPX document into named routes; unnamed routes are numbered from 1
+pub fn parse_routes(xml: &str) -> Vec<(String, Vec<NavFix>)> {
+    let mut routes = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find("<rte>").or_else(|| rest.find("<rte ")) {
+        rest = &rest[start + 4..];
+        let end = rest.find("</rte>").unwrap_or(rest.len());
+        let body = &rest[..end];
+        let header = &body[..body.find("<rtept").unwrap_or(body.len())];
+        let name = child_text(header, "name").map_or_else(|| format!("Route {}", routes.len() + 1), str::to_string);
+        routes.push((name, parse_points(body, "rtept")));
+        rest = &rest[end..];
+    }
+    routes
+}
+
+/// Parse `<trkpt>`, `<rtept>` or `<wpt>` elements with their `<time>` and `<ele>` children
+pub fn parse_points(xml: &str, element: &str) -> Vec<NavFix> {
+    let open = format!("<{}", element);
+    let close = format!("</{}>", element);
+    let mut fixes = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(tag_end) = rest.find('>') else { break };
+        let tag = &rest[..tag_end];
+        let self_closing = tag.ends_with('/');
+        let body = if self_closing {
+            ""
+        } else {
+            let body = &rest[tag_end + 1..];
+            &body[..body.find(&close).unwrap_or(body.len())]
+        };
+
+        if let (Some(lat), Some(lon)) = (attr(tag, "lat"), attr(tag, "lon")) {
+            fixes.push(NavFix {
+                timestamp: child_text(body, "time").and_then(parse_timestamp).unwrap_or(0.0),
+                lat,
+                lon,
+                heading_deg: child_text(body, "course").and_then(|c| c.parse().ok()),
+                sog_knots: None,
+                altitude_m: child_text(body, "ele").and_then(|e| e.parse().ok()),
+            });
+        }
+        rest = &rest[tag_end..];
+    }
+
+    if element == "trkpt" {
+        fixes.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    }
+    fixes
+}
+
+fn attr(tag: &str, name: &str) -> Option<f64> {
+    let key = format!(" {}=", name);
+    let start = tag.find(&key)? + key.len();
+    let quote = tag[start..].chars().next()?;
+    let value = &tag[start + quote.len_utf8()..];
+    value[..value.find(quote)?].parse().ok()
+}
+
+fn child_text<'a>(body: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let start = body.find(&open)? + open.len();
+    let end = body[start..].find(&close)? + start;
+    Some(body[start..end].trim())
+}
+
+/// Georeference pings from a GPX track, returning how many were positioned
+pub fn georeference_pings(pings: &mut [Ping], track: &[NavFix], matching: TimeMatch, max_gap_s: f64) -> usize {
+    let offset = match matching {
+        TimeMatch::Absolute { offset_s } => offset_s,
+        TimeMatch::Elapsed { offset_s } => {
+            let (Some(first_ping), Some(first_fix)) = (
+                pings.iter().map(|p| p.timestamp).reduce(f64::min),
+                track.first(),
+            ) else {
+                return 0;
+            };
+            first_fix.timestamp - first_ping + offset_s
+        }
+    };
+    fuse_pings(pings, track, offset, max_gap_s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::gpx::write_track_to;
+    use crate::survey::Sounding;
+    use crate::track::Simplify;
+
+    #[test]
+    fn track_round_trips_through_the_exporter() {
+        let soundings: Vec<Sounding> = (0..20)
+            .map(|i| {
+                Sounding::new(
+                    1_700_000_000.0 + i as f64 * 1.5,
+                    44.5 + i as f64 * 1e-5,
+                    -63.5,
+                    3.0 + i as f64,
+                )
+            })
+            .collect();
+        let mut buf = Vec::new();
+        write_track_to(&mut buf, "Line <1>", &soundings, &Simplify::None).unwrap();
+        let fixes = parse_points(&String::from_utf8(buf).unwrap(), "trkpt");
+        assert_eq!(fixes.len(), soundings.len());
+        for (fix, s) in fixes.iter().zip(&soundings) {
+            assert!((fix.timestamp - s.timestamp).abs() < 1e-3);
+            assert!((fix.lat - s.lat).abs() < 1e-7 && (fix.lon - s.lon).abs() < 1e-7);
+            assert_eq!(fix.altitude_m, Some(-s.depth_m));
+        }
+    }
+
+    #[test]
+    fn track_points_are_sorted_and_accept_either_quote() {
+        let xml = "<gpx><trk><trkseg>\
+                   <trkpt lon='-63.5' lat='44.6'><time>2023-11-14T22:13:21Z</time><course>12.5</course></trkpt>\
+                   <trkpt lat=\"44.5\" lon=\"-63.4\"><time>2023-11-14T22:13:20Z</time></trkpt>\
+                   <trkpt lat=\"bad\" lon=\"-63.4\"/>\
+                   <trkpt lat=\"44.7\"/>\
+                   </trkseg></trk></gpx>";
+        let fixes = parse_points(xml, "trkpt");
+        assert_eq!(fixes.len(), 2);
+        assert_eq!(
+            (fixes[0].timestamp, fixes[0].lat, fixes[0].lon),
+            (1_700_000_000.0, 44.5, -63.4)
+        );
+        assert_eq!((fixes[1].lat, fixes[1].heading_deg), (44.6, Some(12.5)));
+    }
+
+    #[test]
+    fn routes_keep_file_order_and_names() {
+        let xml = "<gpx>\
+                   <rte><name>North line</name><rtept lat=\"44.6\" lon=\"-63.5\"/><rtept lat=\"44.5\" lon=\"-63.5\"/></rte>\
+                   <rte number=\"2\"><rtept lat=\"44.4\" lon=\"-63.4\"><name>WP1</name></rtept></rte>\
+                   </gpx>";
+        let routes = parse_routes(xml);
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].0, "North line");
+        assert_eq!(routes[0].1.iter().map(|f| f.lat).collect::<Vec<_>>(), vec![44.6, 44.5]);
+        assert_eq!(routes[0].1[0].timestamp, 0.0);
+        assert_eq!(routes[1].0, "Route 2");
+        assert_eq!(routes[1].1.len(), 1);
+    }
+
+    #[test]
+    fn malformed_documents_yield_what_can_be_read() {
+        assert!(parse_points("", "trkpt").is_empty());
+        assert!(parse_points("<gpx><trkpt lat=\"44.5\" lon=\"-63.5\"", "trkpt").is_empty());
+        let truncated = parse_points("<trkpt lat=\"44.5\" lon=\"-63.5\"><ele>3", "trkpt");
+        assert_eq!(truncated.len(), 1);
+        assert_eq!(truncated[0].altitude_m, None);
+        assert!(parse_routes("<rte>").iter().all(|(_, points)| points.is_empty()));
+    }
+
+    fn pings() -> Vec<Ping> {
+        (0..3)
+            .map(|i| Ping {
+                timestamp: 100.0 + i as f64,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn track() -> Vec<NavFix> {
+        (0..10)
+            .map(|i| NavFix {
+                timestamp: 1_700_000_000.0 + i as f64,
+                lat: 44.0 + i as f64 * 0.001,
+                lon: -63.0,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn elapsed_matching_aligns_first_record_with_first_point() {
+        let mut p = pings();
+        let matching = TimeMatch::Elapsed { offset_s: 2.0 };
+        assert_eq!(georeference_pings(&mut p, &track(), matching, 5.0), 3);
+        assert!((p[0].lat - 44.002).abs() < 1e-12 && (p[2].lat - 44.004).abs() < 1e-12);
+        assert_eq!(georeference_pings(&mut p, &[], matching, 5.0), 0);
+    }
+
+    #[test]
+    fn absolute_matching_uses_the_shared_clock() {
+        let mut p = pings();
+        let matching = TimeMatch::Absolute {
+            offset_s: 1_700_000_000.0 - 100.0 + 4.0,
+        };
+        assert_eq!(georeference_pings(&mut p, &track(), matching, 5.0), 3);
+        assert!((p[1].lat - 44.005).abs() < 1e-12);
+        let mut p = pings();
+        let unmatched = TimeMatch::Absolute { offset_s: 0.0 };
+        assert_eq!(georeference_pings(&mut p, &track(), unmatched, 5.0), 0);
+        assert_eq!(p[0].lat, 0.0);
+    }
+}
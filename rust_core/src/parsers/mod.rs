@@ -0,0 +1,103 @@
+// Readers for external sonar and navigation logs
+// src/parsers/mod.rs
+
+pub mod deeper;
+pub mod gpx;
+pub mod multipart;
+pub mod nmea0183;
+pub mod nmea2000;
+pub mod registry;
+pub mod warnings;
+
+pub use registry::{open_with, ChannelInfo, ChannelKind, FormatRegistry, SonarFormatParser, SonarSource};
+
+use chrono::{DateTime, NaiveDateTime};
+use std::io;
+
+/// How readers treat malformed input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Fail on the first structural problem (for validating files we wrote)
+    Strict,
+    /// Skip what cannot be parsed and keep going
+    #[default]
+    Lenient,
+}
+
+impl ParseMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "strict" => Some(Self::Strict),
+            "lenient" => Some(Self::Lenient),
+            _ => None,
+        }
+    }
+
+    /// In strict mode turn a skipped input into an error; in lenient mode allow it
+    pub fn check(self, line_no: usize, problem: &str) -> io::Result<()> {
+        let message = format!("line {}: {}", line_no + 1, problem);
+        match self {
+            ParseMode::Strict => Err(io::Error::new(io::ErrorKind::InvalidData, message)),
+            ParseMode::Lenient => {
+                warnings::report(warnings::ParseWarning {
+                    kind: warnings::WarningKind::SkippedInput,
+                    line: Some(line_no + 1),
+                    timestamp: None,
+                    message,
+                });
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Parse a timestamp as unix seconds: epoch seconds/milliseconds or an ISO-8601-like date (UTC)
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if let Ok(value) = text.parse::<f64>() {
+        // Values this large are epoch milliseconds
+        return Some(if value > 1e11 { value / 1000.0 } else { value });
+    }
+    if let Ok(t) = DateTime::parse_from_rfc3339(text) {
+        return Some(t.timestamp_micros() as f64 / 1e6);
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y/%m/%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(text, fmt).ok())
+        .map(|t| t.and_utc().timestamp_micros() as f64 / 1e6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_modes_by_name() {
+        assert_eq!(ParseMode::from_name("Strict"), Some(ParseMode::Strict));
+        assert_eq!(ParseMode::from_name("lenient"), Some(ParseMode::Lenient));
+        assert_eq!(ParseMode::from_name("loose"), None);
+    }
+
+    #[test]
+    fn strict_mode_turns_skips_into_errors() {
+        let (result, warnings) = warnings::collect_warnings(|| ParseMode::Lenient.check(4, "bad row"));
+        assert!(result.is_ok());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line, Some(5));
+        let err = ParseMode::Strict.check(4, "bad row").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 5: bad row");
+    }
+
+    #[test]
+    fn timestamps_in_every_supported_form() {
+        assert_eq!(parse_timestamp("1700000000"), Some(1_700_000_000.0));
+        assert_eq!(parse_timestamp(" 1700000000500 "), Some(1_700_000_000.5));
+        assert_eq!(parse_timestamp("2023-11-14T22:13:20.250Z"), Some(1_700_000_000.25));
+        assert_eq!(parse_timestamp("2023-11-15T00:13:20+02:00"), Some(1_700_000_000.0));
+        assert_eq!(parse_timestamp("2023-11-14 22:13:20"), Some(1_700_000_000.0));
+        assert_eq!(parse_timestamp("2023/11/14 22:13:20.5"), Some(1_700_000_000.5));
+        assert_eq!(parse_timestamp("14/11/2023"), None);
+        assert_eq!(parse_timestamp(""), None);
+    }
+}
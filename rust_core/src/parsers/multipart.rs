@@ -0,0 +1,215 @@
+// Recordings split into numbered parts (survey.rsd.001, survey.rsd.002, ...)
+// src/parsers/multipart.rs
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Part number and digit count when the extension is all digits, e.g. `.002` -> (2, 3)
+fn part_number(path: &Path) -> Option<(u64, usize)> {
+    let ext = path.extension()?.to_str()?;
+    if ext.is_empty() || !ext.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((ext.parse().ok()?, ext.len()))
+}
+
+fn numbered(path: &Path, number: u64, width: usize) -> PathBuf {
+    path.with_extension(format!("{:0width$}", number, width = width))
+}
+
+/// All parts of the sequence `path` belongs to, in order, or `None` for an ordinary file
+///
+/// Any part may be given; the sequence runs from the lowest to the highest
+/// consecutive number present next to it. A numbered file with no numbered
+/// neighbour (`soundings.2024`) is an ordinary file.
+pub fn part_paths(path: &Path) -> Option<Vec<PathBuf>> {
+    let (number, width) = part_number(path)?;
+    let mut first = number;
+    while first > 0 && numbered(path, first - 1, width).is_file() {
+        first -= 1;
+    }
+    let mut parts = vec![numbered(path, first, width)];
+    let mut next = first + 1;
+    while numbered(path, next, width).is_file() {
+        parts.push(numbered(path, next, width));
+        next += 1;
+    }
+    (parts.len() >= 2).then_some(parts)
+}
+
+/// Whether `path` is a later part whose sequence starts at another file
+///
+/// Directory scans skip these so a split recording is processed once.
+pub fn is_continuation(path: &Path) -> bool {
+    part_number(path).is_some_and(|(number, width)| number > 0 && numbered(path, number - 1, width).is_file())
+}
+
+/// Name used to pick a parser by extension: `survey.rsd.001` -> `survey.rsd`
+pub fn logical_path(path: &Path) -> PathBuf {
+    match part_paths(path) {
+        Some(_) => path.with_extension(""),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Parts read back to back as one stream
+///
+/// Records that straddle a part boundary come out whole because the
+/// boundary is invisible to the reader.
+pub struct PartReader {
+    parts: std::vec::IntoIter<PathBuf>,
+    current: Option<File>,
+}
+
+impl PartReader {
+    pub fn new(parts: Vec<PathBuf>) -> Self {
+        Self {
+            parts: parts.into_iter(),
+            current: None,
+        }
+    }
+}
+
+impl Read for PartReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.current.is_none() {
+                match self.parts.next() {
+                    Some(path) => self.current = Some(File::open(path)?),
+                    None => return Ok(0),
+                }
+            }
+            let n = self.current.as_mut().map_or(Ok(0), |file| file.read(buf))?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            self.current = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::discover_files;
+    use crate::parsers::registry::VecSource;
+    use crate::parsers::{open_with, FormatRegistry, ParseMode, SonarFormatParser, SonarSource};
+    use crate::survey::Ping;
+    use crate::testutil::scratch_dir;
+
+    /// Parser that cannot stream; each ping's depth is the opened file's length
+    struct WholeFile;
+
+    impl SonarFormatParser for WholeFile {
+        fn name(&self) -> &str {
+            "whole-file"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &[]
+        }
+
+        fn probe(&self, _header: &[u8]) -> bool {
+            false
+        }
+
+        fn open(&self, path: &Path, _mode: ParseMode) -> io::Result<Box<dyn SonarSource>> {
+            let ping = Ping {
+                depth_m: std::fs::metadata(path)?.len() as f64,
+                ..Default::default()
+            };
+            Ok(Box::new(VecSource::new(Vec::new(), vec![ping])))
+        }
+    }
+
+    #[test]
+    fn finds_sequences_of_two_or_more_parts() {
+        let dir = scratch_dir("multipart", "sequences");
+        for name in [
+            "survey.csv.001",
+            "survey.csv.002",
+            "survey.csv.003",
+            "log.2024",
+            "plain.csv",
+        ] {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+        let parts: Vec<PathBuf> = ["001", "002", "003"]
+            .iter()
+            .map(|n| dir.join(format!("survey.csv.{}", n)))
+            .collect();
+        assert_eq!(part_paths(&parts[1]), Some(parts.clone()));
+        assert_eq!(part_paths(&parts[2]), Some(parts.clone()));
+        assert!(!is_continuation(&parts[0]));
+        assert!(is_continuation(&parts[2]));
+        assert_eq!(logical_path(&parts[1]), dir.join("survey.csv"));
+
+        let lone = dir.join("log.2024");
+        assert_eq!(part_paths(&lone), None);
+        assert!(!is_continuation(&lone));
+        assert_eq!(logical_path(&lone), lone);
+        assert_eq!(part_paths(&dir.join("plain.csv")), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parts_read_as_one_stream() {
+        let dir = scratch_dir("multipart", "stream");
+        let text = "Time,Depth (m),Latitude,Longitude\n\
+                    1700000000000,4.25,44.6488,-63.5752\n\
+                    1700000001000,4.5,44.6489,-63.5753\n\
+                    1700000002000,4.75,44.6490,-63.5754\n";
+        // Split mid-row, with an empty part in between
+        let cuts = [0, 50, 50, 90, text.len()];
+        let parts: Vec<PathBuf> = (0..4).map(|i| dir.join(format!("track.csv.{:02}", i + 1))).collect();
+        for (part, range) in parts.iter().zip(cuts.windows(2)) {
+            std::fs::write(part, &text[range[0]..range[1]]).unwrap();
+        }
+
+        let mut joined = String::new();
+        PartReader::new(parts.clone()).read_to_string(&mut joined).unwrap();
+        assert_eq!(joined, text);
+
+        let registry = FormatRegistry::default();
+        let pings: Vec<Ping> = registry.open(&parts[2]).unwrap().collect::<io::Result<_>>().unwrap();
+        assert_eq!(
+            pings.iter().map(|p| p.depth_m).collect::<Vec<_>>(),
+            vec![4.25, 4.5, 4.75]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn formats_without_stream_support_refuse_split_recordings() {
+        let dir = scratch_dir("multipart", "fallback");
+        let (first, second) = (dir.join("dump.bin.1"), dir.join("dump.bin.2"));
+        std::fs::write(&first, [0u8; 10]).unwrap();
+        std::fs::write(&second, [0u8; 4]).unwrap();
+        let err = open_with(&WholeFile, &second, ParseMode::Lenient).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(err.to_string().contains("join the 2 parts"), "{}", err);
+
+        // A lone numbered file is not split, so it opens as usual
+        std::fs::remove_file(&first).unwrap();
+        let ping = open_with(&WholeFile, &second, ParseMode::Lenient)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(ping.depth_m, 4.0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn directory_scans_match_split_recordings_by_joined_name() {
+        let dir = scratch_dir("multipart", "discover");
+        for name in ["track.csv.001", "track.csv.002", "notes.txt", "lone.csv", "log.2024"] {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+        let found = discover_files(&dir, "*.csv", false).unwrap();
+        assert_eq!(found, vec![dir.join("lone.csv"), dir.join("track.csv.001")]);
+        assert_eq!(discover_files(&dir, "*.2024", false).unwrap(), vec![dir.join("log.2024")]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,413 @@
+// NMEA 0183 log ingestion and navigation fusion
+// src/parsers/nmea0183.rs
+
+use super::ParseMode;
+use crate::survey::{Ping, Sounding};
+use chrono::{NaiveDate, NaiveTime};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// One navigation fix assembled from NMEA sentences
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NavFix {
+    pub timestamp: f64,
+    pub lat: f64,
+    pub lon: f64,
+    pub heading_deg: Option<f64>,
+    pub sog_knots: Option<f64>,
+    pub altitude_m: Option<f64>,
+}
+
+/// Parsed NMEA log: position fixes plus any depth/temperature sentences
+#[derive(Debug, Clone, Default)]
+pub struct NmeaLog {
+    pub fixes: Vec<NavFix>,
+    pub depths: Vec<(f64, f64)>,
+    pub water_temps: Vec<(f64, f64)>,
+    /// Speed through water in knots (VHW)
+    pub water_speeds: Vec<(f64, f64)>,
+    /// Sentences dropped for bad checksums or malformed fields
+    pub rejected: usize,
+}
+
+pub fn read_nmea0183<P: AsRef<Path>>(path: P, mode: ParseMode) -> io::Result<NmeaLog> {
+    let _span = tracing::info_span!("read_nmea0183", path = %path.as_ref().display()).entered();
+    let bytes = fs::read(path)?;
+    parse_nmea0183(&String::from_utf8_lossy(&bytes), mode)
+}
+
+/// Parse GGA/RMC/HDT/HDG/VTG/DBT/DPT/MTW/VHW sentences into fixes
+///
+/// RMC supplies the date; GGA-only logs are timed from midnight of the last
+/// RMC date seen (or the epoch). Heading sentences apply to the next fix.
+/// Strict mode fails on the first sentence with a bad checksum or fields.
+pub fn parse_nmea0183(text: &str, mode: ParseMode) -> io::Result<NmeaLog> {
+    let mut log = NmeaLog::default();
+    let mut date: Option<NaiveDate> = None;
+    let mut heading: Option<f64> = None;
+    let mut sog: Option<f64> = None;
+    let mut last_time: Option<f64> = None;
+
+    for (line_no, line) in text.lines().enumerate() {
+        let Some(fields) = sentence_fields(line) else {
+            if line.trim_start().starts_with('$') {
+                mode.check(line_no, "bad checksum or empty sentence")?;
+                log.rejected += 1;
+            }
+            continue;
+        };
+        let Some(kind) = fields[0].get(fields[0].len() - 3..) else {
+            continue;
+        };
+
+        match kind {
+            "RMC" if fields.len() > 9 => {
+                date = parse_date(fields[9]).or(date);
+                let (Some(time), Some(lat), Some(lon)) = (
+                    timestamp(date, fields[1]),
+                    coordinate(fields[3], fields[4]),
+                    coordinate(fields[5], fields[6]),
+                ) else {
+                    mode.check(line_no, "malformed position sentence")?;
+                    log.rejected += 1;
+                    continue;
+                };
+                if fields[2] != "A" {
+                    continue;
+                }
+                let course = fields[8].parse().ok();
+                push_fix(&mut log, NavFix {
+                    timestamp: time,
+                    lat,
+                    lon,
+                    heading_deg: heading.or(course),
+                    sog_knots: fields[7].parse().ok().or(sog),
+                    altitude_m: None,
+                });
+                last_time = Some(time);
+            }
+            "GGA" if fields.len() > 9 => {
+                let (Some(time), Some(lat), Some(lon)) = (
+                    timestamp(date, fields[1]),
+                    coordinate(fields[2], fields[3]),
+                    coordinate(fields[4], fields[5]),
+                ) else {
+                    mode.check(line_no, "malformed position sentence")?;
+                    log.rejected += 1;
+                    continue;
+                };
+                if fields[6] == "0" {
+                    continue;
+                }
+                push_fix(&mut log, NavFix {
+                    timestamp: time,
+                    lat,
+                    lon,
+                    heading_deg: heading,
+                    sog_knots: sog,
+                    altitude_m: fields[9].parse().ok(),
+                });
+                last_time = Some(time);
+            }
+            "HDT" | "HDG" | "HDM" if fields.len() > 1 => heading = fields[1].parse().ok().or(heading),
+            "VTG" if fields.len() > 5 => sog = fields[5].parse().ok().or(sog),
+            "DBT" if fields.len() > 3 => {
+                if let (Some(t), Ok(d)) = (last_time, fields[3].parse()) {
+                    log.depths.push((t, d));
+                }
+            }
+            "DPT" if fields.len() > 1 => {
+                if let (Some(t), Ok(d)) = (last_time, fields[1].parse::<f64>()) {
+                    let offset = fields.get(2).and_then(|f| f.parse::<f64>().ok()).unwrap_or(0.0);
+                    log.depths.push((t, d + offset));
+                }
+            }
+            "VHW" if fields.len() > 5 => {
+                if let (Some(t), Ok(knots)) = (last_time, fields[5].parse()) {
+                    log.water_speeds.push((t, knots));
+                }
+            }
+            "MTW" if fields.len() > 1 => {
+                if let (Some(t), Ok(c)) = (last_time, fields[1].parse()) {
+                    log.water_temps.push((t, c));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    log.fixes.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    tracing::debug!(fixes = log.fixes.len(), depths = log.depths.len(), rejected = log.rejected, "parsed NMEA 0183 log");
+    Ok(log)
+}
+
+/// Merge RMC and GGA fixes for the same second instead of duplicating them
+fn push_fix(log: &mut NmeaLog, fix: NavFix) {
+    if let Some(last) = log.fixes.last_mut() {
+        if (last.timestamp - fix.timestamp).abs() < 1e-6 {
+            last.heading_deg = last.heading_deg.or(fix.heading_deg);
+            last.sog_knots = last.sog_knots.or(fix.sog_knots);
+            last.altitude_m = last.altitude_m.or(fix.altitude_m);
+            return;
+        }
+    }
+    log.fixes.push(fix);
+}
+
+/// Comma-separated fields of a sentence with a valid checksum (or none)
+fn sentence_fields(line: &str) -> Option<Vec<&str>> {
+    let line = line.trim();
+    let start = line.find(['$', '!'])?;
+    let body = &line[start + 1..];
+    let (data, checksum) = match body.split_once('*') {
+        Some((data, checksum)) => (data, Some(checksum)),
+        None => (body, None),
+    };
+    if let Some(checksum) = checksum {
+        let expected = u8::from_str_radix(checksum.get(..2)?, 16).ok()?;
+        if data.bytes().fold(0u8, |acc, b| acc ^ b) != expected {
+            return None;
+        }
+    }
+    let fields: Vec<&str> = data.split(',').collect();
+    (fields[0].len() >= 3).then_some(fields)
+}
+
+/// ddmm.mmmm + hemisphere to signed decimal degrees
+fn coordinate(value: &str, hemisphere: &str) -> Option<f64> {
+    let raw: f64 = value.parse().ok()?;
+    let degrees = (raw / 100.0).trunc();
+    let decimal = degrees + (raw - degrees * 100.0) / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(decimal),
+        "S" | "W" => Some(-decimal),
+        _ => None,
+    }
+}
+
+fn parse_date(ddmmyy: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(ddmmyy, "%d%m%y").ok()
+}
+
+fn timestamp(date: Option<NaiveDate>, hhmmss: &str) -> Option<f64> {
+    let time = NaiveTime::parse_from_str(hhmmss, "%H%M%S%.f").ok()?;
+    let date = date.unwrap_or_default();
+    Some(date.and_time(time).and_utc().timestamp_micros() as f64 / 1e6)
+}
+
+/// Interpolated fix at a time, or None outside the log or across gaps longer than `max_gap_s`
+pub fn interpolate_fix(fixes: &[NavFix], timestamp: f64, max_gap_s: f64) -> Option<NavFix> {
+    let after = fixes.partition_point(|f| f.timestamp < timestamp);
+    if after < fixes.len() && (fixes[after].timestamp - timestamp).abs() < 1e-9 {
+        return Some(fixes[after]);
+    }
+    if after == 0 || after == fixes.len() {
+        return None;
+    }
+    let (a, b) = (&fixes[after - 1], &fixes[after]);
+    if b.timestamp - a.timestamp > max_gap_s {
+        return None;
+    }
+    let t = (timestamp - a.timestamp) / (b.timestamp - a.timestamp);
+    let lerp = |x: f64, y: f64| x + (y - x) * t;
+    let heading = match (a.heading_deg, b.heading_deg) {
+        (Some(h1), Some(h2)) => {
+            let delta = (h2 - h1 + 540.0).rem_euclid(360.0) - 180.0;
+            Some((h1 + delta * t).rem_euclid(360.0))
+        }
+        (h1, h2) => h1.or(h2),
+    };
+
+    Some(NavFix {
+        timestamp,
+        lat: lerp(a.lat, b.lat),
+        lon: lerp(a.lon, b.lon),
+        heading_deg: heading,
+        sog_knots: a.sog_knots.zip(b.sog_knots).map(|(s1, s2)| lerp(s1, s2)).or(a.sog_knots),
+        altitude_m: a.altitude_m.zip(b.altitude_m).map(|(z1, z2)| lerp(z1, z2)).or(a.altitude_m),
+    })
+}
+
+/// Replace sounding navigation with the external log, shifted by `time_offset_s`
+///
+/// Records outside the log's coverage keep their internal navigation.
+/// Returns how many records were updated.
+pub fn fuse_soundings(soundings: &mut [Sounding], fixes: &[NavFix], time_offset_s: f64, max_gap_s: f64) -> usize {
+    let mut updated = 0;
+    for s in soundings.iter_mut() {
+        if let Some(fix) = interpolate_fix(fixes, s.timestamp + time_offset_s, max_gap_s) {
+            s.lat = fix.lat;
+            s.lon = fix.lon;
+            if let Some(heading) = fix.heading_deg {
+                s.heading_deg = heading;
+            }
+            updated += 1;
+        }
+    }
+    updated
+}
+
+/// Replace ping navigation with the external log; see [`fuse_soundings`]
+pub fn fuse_pings(pings: &mut [Ping], fixes: &[NavFix], time_offset_s: f64, max_gap_s: f64) -> usize {
+    let mut updated = 0;
+    for p in pings.iter_mut() {
+        if let Some(fix) = interpolate_fix(fixes, p.timestamp + time_offset_s, max_gap_s) {
+            p.lat = fix.lat;
+            p.lon = fix.lon;
+            if let Some(heading) = fix.heading_deg {
+                p.heading_deg = heading;
+            }
+            updated += 1;
+        }
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sentence with a correct checksum
+    fn sentence(body: &str) -> String {
+        format!("${}*{:02X}", body, body.bytes().fold(0u8, |acc, b| acc ^ b))
+    }
+
+    fn log(bodies: &[&str]) -> String {
+        bodies.iter().map(|b| sentence(b) + "\r\n").collect()
+    }
+
+    const RMC: &str = "GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W";
+    const GGA: &str = "GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,";
+
+    #[test]
+    fn merges_rmc_and_gga_of_the_same_second() {
+        let parsed = parse_nmea0183(&log(&[RMC, GGA]), ParseMode::Strict).unwrap();
+        assert_eq!(parsed.fixes.len(), 1);
+        let fix = parsed.fixes[0];
+        // 23 March 1994 12:35:19 UTC
+        assert_eq!(fix.timestamp, 764_426_119.0);
+        assert!((fix.lat - (48.0 + 7.038 / 60.0)).abs() < 1e-12);
+        assert!((fix.lon - (11.0 + 31.0 / 60.0)).abs() < 1e-12);
+        assert_eq!(fix.sog_knots, Some(22.4));
+        assert_eq!(fix.heading_deg, Some(84.4));
+        assert_eq!(fix.altitude_m, Some(545.4));
+        assert_eq!(parsed.rejected, 0);
+    }
+
+    #[test]
+    fn heading_and_speed_sentences_apply_to_later_fixes() {
+        let text = log(&[
+            "GPRMC,000000,A,4400.000,S,06300.000,W,,,010124,,",
+            "HEHDT,271.5,T",
+            "GPVTG,90.0,T,,M,5.5,N,10.2,K",
+            "GPGGA,000001,4400.060,S,06300.000,W,1,08,0.9,,M,,M,,",
+        ]);
+        let parsed = parse_nmea0183(&text, ParseMode::Strict).unwrap();
+        assert_eq!(parsed.fixes.len(), 2);
+        assert_eq!((parsed.fixes[0].lat, parsed.fixes[0].lon), (-44.0, -63.0));
+        assert_eq!(parsed.fixes[0].heading_deg, None);
+        assert_eq!(parsed.fixes[1].timestamp, 1_704_067_201.0);
+        assert_eq!(parsed.fixes[1].heading_deg, Some(271.5));
+        assert_eq!(parsed.fixes[1].sog_knots, Some(5.5));
+        assert!((parsed.fixes[1].lat + 44.001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn environment_sentences_take_the_last_fix_time() {
+        let text = log(&[
+            "SDDBT,10.0,f,3.0,M,1.6,F",
+            RMC,
+            "SDDBT,10.0,f,3.0,M,1.6,F",
+            "SDDPT,4.2,0.5",
+            "YXMTW,14.5,C",
+            "VWVHW,,T,,M,3.2,N,5.9,K",
+        ]);
+        let parsed = parse_nmea0183(&text, ParseMode::Strict).unwrap();
+        let t = parsed.fixes[0].timestamp;
+        assert_eq!(parsed.depths, vec![(t, 3.0), (t, 4.7)]);
+        assert_eq!(parsed.water_temps, vec![(t, 14.5)]);
+        assert_eq!(parsed.water_speeds, vec![(t, 3.2)]);
+    }
+
+    #[test]
+    fn invalid_fixes_are_ignored() {
+        let text = log(&[
+            "GPRMC,123519,V,4807.038,N,01131.000,E,,,230394,,",
+            "GPGGA,123520,4807.038,N,01131.000,E,0,00,,,M,,M,,",
+        ]);
+        let parsed = parse_nmea0183(&text, ParseMode::Strict).unwrap();
+        assert!(parsed.fixes.is_empty());
+        assert_eq!(parsed.rejected, 0);
+    }
+
+    #[test]
+    fn corrupt_sentences_are_counted_or_rejected() {
+        let mut text = log(&[RMC]);
+        text.push_str("$GPGGA,123520,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*00\n");
+        let bad_hemisphere = "GPGGA,123521,4807.038,Q,01131.000,E,1,08,0.9,545.4,M,46.9,M,,";
+        text.push_str(&sentence(bad_hemisphere));
+        text.push_str("\nnot nmea at all\n$\n");
+        let parsed = parse_nmea0183(&text, ParseMode::Lenient).unwrap();
+        assert_eq!(parsed.fixes.len(), 1);
+        assert_eq!(parsed.rejected, 3);
+
+        let err = parse_nmea0183(&text, ParseMode::Strict).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2:"), "{}", err);
+    }
+
+    #[test]
+    fn sentences_without_checksums_are_accepted() {
+        let parsed = parse_nmea0183(&format!("${}\n", GGA), ParseMode::Strict).unwrap();
+        assert_eq!(parsed.fixes.len(), 1);
+    }
+
+    fn fix(timestamp: f64, lat: f64, heading: f64) -> NavFix {
+        NavFix {
+            timestamp,
+            lat,
+            lon: -63.0,
+            heading_deg: Some(heading),
+            sog_knots: Some(4.0),
+            altitude_m: None,
+        }
+    }
+
+    #[test]
+    fn interpolation_wraps_heading_and_respects_gaps() {
+        let fixes = [fix(0.0, 44.0, 350.0), fix(2.0, 44.002, 10.0), fix(100.0, 44.1, 20.0)];
+        let mid = interpolate_fix(&fixes, 1.0, 5.0).unwrap();
+        assert!((mid.lat - 44.001).abs() < 1e-12);
+        assert!(mid.heading_deg.unwrap().abs() < 1e-9 || (mid.heading_deg.unwrap() - 360.0).abs() < 1e-9);
+        assert_eq!(interpolate_fix(&fixes, 2.0, 5.0), Some(fixes[1]));
+        assert_eq!(interpolate_fix(&fixes, 50.0, 5.0), None);
+        assert_eq!(interpolate_fix(&fixes, -1.0, 5.0), None);
+        assert_eq!(interpolate_fix(&fixes, 101.0, 5.0), None);
+    }
+
+    #[test]
+    fn fusion_replaces_navigation_inside_coverage_only() {
+        let fixes = [fix(10.0, 44.0, 90.0), fix(12.0, 44.002, 90.0)];
+        let mut pings = vec![
+            Ping {
+                timestamp: 1.0,
+                lat: 1.0,
+                ..Default::default()
+            },
+            Ping {
+                timestamp: 50.0,
+                lat: 1.0,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(fuse_pings(&mut pings, &fixes, 10.0, 5.0), 1);
+        assert!((pings[0].lat - 44.001).abs() < 1e-12);
+        assert_eq!((pings[0].lon, pings[0].heading_deg), (-63.0, 90.0));
+        assert_eq!(pings[1].lat, 1.0);
+
+        let mut soundings = vec![Sounding::new(11.0, 0.0, 0.0, 5.0)];
+        assert_eq!(fuse_soundings(&mut soundings, &fixes, 0.0, 5.0), 1);
+        assert!((soundings[0].lat - 44.001).abs() < 1e-12);
+    }
+}
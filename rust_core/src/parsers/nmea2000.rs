@@ -0,0 +1,684 @@
+// NMEA 2000 (CAN) log ingestion from candump captures
+// src/parsers/nmea2000.rs
+
+use super::nmea0183::NavFix;
+use super::ParseMode;
+use crate::survey::{Ping, Sensors};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const PGN_ENGINE_RAPID: u32 = 127488;
+pub const PGN_BATTERY_STATUS: u32 = 127508;
+pub const PGN_VESSEL_HEADING: u32 = 127250;
+pub const PGN_ATTITUDE: u32 = 127257;
+pub const PGN_MAGNETIC_VARIATION: u32 = 127258;
+pub const PGN_SPEED: u32 = 128259;
+pub const PGN_WATER_DEPTH: u32 = 128267;
+pub const PGN_POSITION_RAPID: u32 = 129025;
+pub const PGN_COG_SOG_RAPID: u32 = 129026;
+pub const PGN_TEMPERATURE: u32 = 130312;
+
+const MS_TO_KNOTS: f64 = 1.943_844;
+
+/// Heading reference codes in the low bits of PGN 127250 byte 7
+const HEADING_TRUE: u8 = 0;
+const HEADING_MAGNETIC: u8 = 1;
+
+/// Single CAN frame with its decoded PGN
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanFrame {
+    pub timestamp: f64,
+    pub pgn: u32,
+    pub source: u8,
+    pub data: Vec<u8>,
+}
+
+/// Vessel attitude sample in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attitude {
+    pub timestamp: f64,
+    pub yaw_deg: Option<f64>,
+    pub pitch_deg: Option<f64>,
+    pub roll_deg: Option<f64>,
+}
+
+/// Value from one numbered device, e.g. engine 0 or battery 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstanceReading {
+    pub timestamp: f64,
+    pub instance: u8,
+    pub value: f64,
+}
+
+/// Optional engine and environment series; empty when the bus does not carry them
+///
+/// [`decode_frames`] leaves each series sorted as [`Extras::at`] expects;
+/// call [`Extras::sort`] after building or editing one by hand.
+#[derive(Debug, Clone, Default)]
+pub struct Extras {
+    /// Battery voltage in volts, by instance then time
+    pub battery_volts: Vec<InstanceReading>,
+    /// Engine speed in revolutions per minute, by instance then time
+    pub engine_rpm: Vec<InstanceReading>,
+    /// Outside air temperature in degrees Celsius, by time
+    pub air_temps: Vec<(f64, f64)>,
+}
+
+/// Extras values nearest a ping time, see [`Extras::at`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExtrasSample {
+    pub battery_volts: Option<f64>,
+    pub engine_rpm: Option<f64>,
+    pub air_temp_c: Option<f64>,
+}
+
+impl Extras {
+    pub fn is_empty(&self) -> bool {
+        self.battery_volts.is_empty() && self.engine_rpm.is_empty() && self.air_temps.is_empty()
+    }
+
+    /// Order every series for the binary searches in [`Extras::at`]
+    pub fn sort(&mut self) {
+        for readings in [&mut self.battery_volts, &mut self.engine_rpm] {
+            readings.sort_by(|a, b| a.instance.cmp(&b.instance).then(a.timestamp.total_cmp(&b.timestamp)));
+        }
+        self.air_temps.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+
+    /// Nearest value of each series within `max_gap_s`, from the lowest
+    /// instance present (normally 0) for per-device readings
+    pub fn at(&self, timestamp: f64, max_gap_s: f64) -> ExtrasSample {
+        ExtrasSample {
+            battery_volts: nearest_instance(&self.battery_volts, timestamp, max_gap_s),
+            engine_rpm: nearest_instance(&self.engine_rpm, timestamp, max_gap_s),
+            air_temp_c: nearest(&self.air_temps, |r| r.0, timestamp, max_gap_s).map(|r| r.1),
+        }
+    }
+}
+
+/// Readings sorted by instance, so the lowest instance is the leading run
+fn nearest_instance(readings: &[InstanceReading], timestamp: f64, max_gap_s: f64) -> Option<f64> {
+    let instance = readings.first()?.instance;
+    let run = &readings[..readings.partition_point(|r| r.instance == instance)];
+    nearest(run, |r| r.timestamp, timestamp, max_gap_s).map(|r| r.value)
+}
+
+/// Entry of a time-sorted series closest to `timestamp`, if within `max_gap_s`
+fn nearest<T>(series: &[T], time: impl Fn(&T) -> f64, timestamp: f64, max_gap_s: f64) -> Option<&T> {
+    let idx = series.partition_point(|s| time(s) < timestamp);
+    [idx.checked_sub(1), Some(idx)]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| series.get(i))
+        .min_by(|a, b| (time(a) - timestamp).abs().total_cmp(&(time(b) - timestamp).abs()))
+        .filter(|s| (time(s) - timestamp).abs() <= max_gap_s)
+}
+
+/// Fill each ping's engine, battery and air readings from the nearest extras within `max_gap_s`
+///
+/// Values with no reading near a ping are left unchanged. Returns the
+/// number of pings that received at least one value.
+pub fn fuse_extras(pings: &mut [Ping], extras: &Extras, max_gap_s: f64) -> usize {
+    let mut updated = 0;
+    for p in pings.iter_mut() {
+        if merge_extras(&mut p.sensors, extras.at(p.timestamp, max_gap_s)) {
+            updated += 1;
+        }
+    }
+    updated
+}
+
+/// Copy the values present in `sample`; false when it has none
+fn merge_extras(sensors: &mut Sensors, sample: ExtrasSample) -> bool {
+    sensors.battery_volts = sample.battery_volts.or(sensors.battery_volts);
+    sensors.engine_rpm = sample.engine_rpm.or(sensors.engine_rpm);
+    sensors.air_temp_c = sample.air_temp_c.or(sensors.air_temp_c);
+    sample != ExtrasSample::default()
+}
+
+/// Decoded series from an NMEA 2000 log
+///
+/// `fixes` use the same type as the NMEA 0183 reader, so they fuse into
+/// records through [`super::nmea0183::fuse_pings`] and `fuse_soundings`.
+#[derive(Debug, Clone, Default)]
+pub struct N2kLog {
+    pub fixes: Vec<NavFix>,
+    pub attitude: Vec<Attitude>,
+    /// Headings in degrees true; magnetic readings are corrected by their
+    /// deviation and the magnetic variation when one is known
+    pub headings: Vec<(f64, f64)>,
+    /// Magnetic headings left uncorrected because no variation was on the bus
+    pub magnetic_headings: Vec<(f64, f64)>,
+    pub depths: Vec<(f64, f64)>,
+    pub water_temps: Vec<(f64, f64)>,
+    /// Speed through water in knots
+    pub water_speeds: Vec<(f64, f64)>,
+    pub extras: Extras,
+    pub frames: usize,
+}
+
+pub fn read_candump<P: AsRef<Path>>(path: P, mode: ParseMode) -> io::Result<N2kLog> {
+    let _span = tracing::info_span!("read_candump", path = %path.as_ref().display()).entered();
+    Ok(decode_frames(&parse_candump(&fs::read_to_string(path)?, mode)?))
+}
+
+/// Parse `candump -l` lines (`(ts) can0 ID#DATA`) and timestamped interactive
+/// lines (`(ts) can0 ID [n] AA BB ...`)
+pub fn parse_candump(text: &str, mode: ParseMode) -> io::Result<Vec<CanFrame>> {
+    let mut frames = Vec::new();
+    for (line_no, line) in text.lines().enumerate() {
+        match parse_candump_line(line) {
+            Some(frame) => frames.push(frame),
+            None if line.trim().is_empty() => {}
+            None => mode.check(line_no, "unrecognized candump line")?,
+        }
+    }
+    Ok(frames)
+}
+
+fn parse_candump_line(line: &str) -> Option<CanFrame> {
+    let line = line.trim();
+    let rest = line.strip_prefix('(')?;
+    let (ts, rest) = rest.split_once(')')?;
+    let timestamp: f64 = ts.trim().parse().ok()?;
+    let mut parts = rest.split_whitespace().skip(1); // interface name
+    let frame = parts.next()?;
+
+    let (id, data) = match frame.split_once('#') {
+        Some((id, hex)) => (id, hex_bytes(hex)?),
+        None => {
+            let len_field = parts.next()?;
+            let len: usize = len_field.trim_matches(['[', ']']).parse().ok()?;
+            let data: Option<Vec<u8>> = parts.take(len).map(|b| u8::from_str_radix(b, 16).ok()).collect();
+            (frame, data?)
+        }
+    };
+    let id = u32::from_str_radix(id, 16).ok()?;
+
+    Some(CanFrame {
+        timestamp,
+        pgn: pgn_from_id(id),
+        source: (id & 0xFF) as u8,
+        data,
+    })
+}
+
+fn hex_bytes(hex: &str) -> Option<Vec<u8>> {
+    if !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// PGN from a 29-bit extended CAN identifier
+pub fn pgn_from_id(id: u32) -> u32 {
+    let pgn = (id >> 8) & 0x3FFFF;
+    let pdu_format = (pgn >> 8) & 0xFF;
+    if pdu_format < 240 {
+        pgn & 0x3FF00
+    } else {
+        pgn
+    }
+}
+
+/// Decode the single-frame navigation, attitude, engine and environment PGNs
+///
+/// Fast-packet PGNs are ignored. Position fixes take the most recent true
+/// heading from PGN 127250 and SOG from PGN 129026 seen before them; course
+/// over ground is never used as heading. Magnetic headings are converted
+/// with the variation in the same frame or the last PGN 127258.
+pub fn decode_frames(frames: &[CanFrame]) -> N2kLog {
+    let mut log = N2kLog {
+        frames: frames.len(),
+        ..Default::default()
+    };
+    let mut heading: Option<f64> = None;
+    let mut sog: Option<f64> = None;
+    let mut variation: Option<f64> = None;
+
+    for frame in frames {
+        let d = &frame.data;
+        let t = frame.timestamp;
+        match frame.pgn {
+            PGN_VESSEL_HEADING if d.len() >= 8 => {
+                let Some(sensor) = angle_u16(d, 1) else {
+                    continue;
+                };
+                variation = angle_i16(d, 5).or(variation);
+                let true_heading = match d[7] & 0x03 {
+                    HEADING_TRUE => Some(sensor),
+                    HEADING_MAGNETIC => {
+                        let magnetic = (sensor + angle_i16(d, 3).unwrap_or(0.0)).rem_euclid(360.0);
+                        if variation.is_none() {
+                            log.magnetic_headings.push((t, magnetic));
+                        }
+                        variation.map(|v| (magnetic + v).rem_euclid(360.0))
+                    }
+                    _ => None,
+                };
+                if let Some(h) = true_heading {
+                    heading = Some(h);
+                    log.headings.push((t, h));
+                }
+            }
+            PGN_MAGNETIC_VARIATION if d.len() >= 6 => variation = angle_i16(d, 4).or(variation),
+            PGN_ATTITUDE if d.len() >= 7 => log.attitude.push(Attitude {
+                timestamp: t,
+                yaw_deg: angle_i16(d, 1),
+                pitch_deg: angle_i16(d, 3),
+                roll_deg: angle_i16(d, 5),
+            }),
+            PGN_SPEED if d.len() >= 3 => {
+                if let Some(v) = u16_field(d, 1) {
+                    log.water_speeds.push((t, v as f64 * 0.01 * MS_TO_KNOTS));
+                }
+            }
+            PGN_WATER_DEPTH if d.len() >= 7 => {
+                let depth = u32::from_le_bytes([d[1], d[2], d[3], d[4]]);
+                if depth != u32::MAX {
+                    let offset = i16::from_le_bytes([d[5], d[6]]);
+                    let offset_m = if offset == i16::MAX { 0.0 } else { offset as f64 * 0.001 };
+                    log.depths.push((t, depth as f64 * 0.01 + offset_m.max(0.0)));
+                }
+            }
+            PGN_TEMPERATURE if d.len() >= 5 => {
+                // Source 0 is sea temperature, 1 outside air
+                if let Some(k) = u16_field(d, 3) {
+                    match d[2] {
+                        0 => log.water_temps.push((t, k as f64 * 0.01 - 273.15)),
+                        1 => log.extras.air_temps.push((t, k as f64 * 0.01 - 273.15)),
+                        _ => {}
+                    }
+                }
+            }
+            PGN_ENGINE_RAPID if d.len() >= 3 => {
+                if let Some(v) = u16_field(d, 1) {
+                    log.extras.engine_rpm.push(InstanceReading {
+                        timestamp: t,
+                        instance: d[0],
+                        value: v as f64 * 0.25,
+                    });
+                }
+            }
+            PGN_BATTERY_STATUS if d.len() >= 3 => {
+                let v = i16::from_le_bytes([d[1], d[2]]);
+                if v < 0x7FFD {
+                    log.extras.battery_volts.push(InstanceReading {
+                        timestamp: t,
+                        instance: d[0],
+                        value: v as f64 * 0.01,
+                    });
+                }
+            }
+            PGN_COG_SOG_RAPID if d.len() >= 6 => {
+                sog = u16_field(d, 4).map(|v| v as f64 * 0.01 * MS_TO_KNOTS).or(sog);
+            }
+            PGN_POSITION_RAPID if d.len() >= 8 => {
+                let lat = i32::from_le_bytes([d[0], d[1], d[2], d[3]]);
+                let lon = i32::from_le_bytes([d[4], d[5], d[6], d[7]]);
+                if lat != i32::MAX && lon != i32::MAX {
+                    log.fixes.push(NavFix {
+                        timestamp: t,
+                        lat: lat as f64 * 1e-7,
+                        lon: lon as f64 * 1e-7,
+                        heading_deg: heading,
+                        sog_knots: sog,
+                        altitude_m: None,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    log.fixes.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    log.attitude.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    log.water_temps.sort_by(|a, b| a.0.total_cmp(&b.0));
+    log.water_speeds.sort_by(|a, b| a.0.total_cmp(&b.0));
+    log.extras.sort();
+    tracing::debug!(frames = log.frames, fixes = log.fixes.len(), "decoded NMEA 2000 frames");
+    log
+}
+
+impl N2kLog {
+    /// Nearest attitude sample within `max_gap_s` of a time
+    pub fn attitude_at(&self, timestamp: f64, max_gap_s: f64) -> Option<Attitude> {
+        nearest(&self.attitude, |a| a.timestamp, timestamp, max_gap_s).copied()
+    }
+
+    /// Fill each ping's pitch, roll, water temperature, speed through water
+    /// and [`Extras`] from the nearest samples within `max_gap_s`
+    ///
+    /// Heading and position are fused separately by
+    /// [`super::nmea0183::fuse_pings`]. Values with no sample near a ping are
+    /// left unchanged. Returns the number of pings that received any value.
+    pub fn fuse_sensors(&self, pings: &mut [Ping], max_gap_s: f64) -> usize {
+        let mut updated = 0;
+        for p in pings.iter_mut() {
+            let mut found = merge_extras(&mut p.sensors, self.extras.at(p.timestamp, max_gap_s));
+            let sensors = &mut p.sensors;
+            if let Some(att) = self.attitude_at(p.timestamp, max_gap_s) {
+                sensors.pitch_deg = att.pitch_deg.or(sensors.pitch_deg);
+                sensors.roll_deg = att.roll_deg.or(sensors.roll_deg);
+                found = true;
+            }
+            if let Some(&(_, celsius)) = nearest(&self.water_temps, |r| r.0, p.timestamp, max_gap_s) {
+                sensors.water_temp_c = Some(celsius);
+                found = true;
+            }
+            if let Some(&(_, knots)) = nearest(&self.water_speeds, |r| r.0, p.timestamp, max_gap_s) {
+                p.stw_knots = Some(knots);
+                found = true;
+            }
+            if found {
+                updated += 1;
+            }
+        }
+        updated
+    }
+}
+
+fn u16_field(d: &[u8], offset: usize) -> Option<u16> {
+    let v = u16::from_le_bytes([d[offset], d[offset + 1]]);
+    (v < 0xFFFD).then_some(v)
+}
+
+/// Unsigned angle in 1e-4 rad, as degrees
+fn angle_u16(d: &[u8], offset: usize) -> Option<f64> {
+    u16_field(d, offset).map(|v| (v as f64 * 1e-4).to_degrees())
+}
+
+/// Signed angle in 1e-4 rad, as degrees
+fn angle_i16(d: &[u8], offset: usize) -> Option<f64> {
+    let v = i16::from_le_bytes([d[offset], d[offset + 1]]);
+    (v < 0x7FFD).then(|| (v as f64 * 1e-4).to_degrees())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1e-4 rad units of an angle in degrees
+    fn rad4(degrees: f64) -> i32 {
+        (degrees.to_radians() * 1e4).round() as i32
+    }
+
+    fn frame(timestamp: f64, pgn: u32, data: Vec<u8>) -> CanFrame {
+        CanFrame {
+            timestamp,
+            pgn,
+            source: 0x23,
+            data,
+        }
+    }
+
+    fn heading_frame(
+        timestamp: f64,
+        sensor_deg: f64,
+        deviation: Option<f64>,
+        variation: Option<f64>,
+        reference: u8,
+    ) -> CanFrame {
+        let mut d = vec![0u8];
+        d.extend((rad4(sensor_deg) as u16).to_le_bytes());
+        d.extend(deviation.map_or(0x7fff, |v| rad4(v) as i16).to_le_bytes());
+        d.extend(variation.map_or(0x7fff, |v| rad4(v) as i16).to_le_bytes());
+        d.push(0xfc | reference);
+        frame(timestamp, PGN_VESSEL_HEADING, d)
+    }
+
+    fn cog_sog_frame(timestamp: f64, cog_deg: f64, sog_ms: f64) -> CanFrame {
+        let mut d = vec![0, 0xfc];
+        d.extend((rad4(cog_deg) as u16).to_le_bytes());
+        d.extend(((sog_ms * 100.0).round() as u16).to_le_bytes());
+        d.extend([0xff, 0xff]);
+        frame(timestamp, PGN_COG_SOG_RAPID, d)
+    }
+
+    fn position_frame(timestamp: f64, lat: f64, lon: f64) -> CanFrame {
+        let mut d = ((lat * 1e7).round() as i32).to_le_bytes().to_vec();
+        d.extend(((lon * 1e7).round() as i32).to_le_bytes());
+        frame(timestamp, PGN_POSITION_RAPID, d)
+    }
+
+    fn temperature_frame(timestamp: f64, source: u8, celsius: f64) -> CanFrame {
+        let mut d = vec![0, 0, source];
+        d.extend((((celsius + 273.15) * 100.0).round() as u16).to_le_bytes());
+        frame(timestamp, PGN_TEMPERATURE, d)
+    }
+
+    fn close(a: Option<f64>, b: f64) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 0.01)
+    }
+
+    #[test]
+    fn parses_both_candump_line_forms() {
+        let text = "(1700000000.100000) can0 09F80123#3BE0A61A5C9E1BDA\n\
+                    \n\
+                    (1700000000.200000) can0  09F11223   [8]  00 10 27 FF 7F FF 7F FC\n";
+        let frames = parse_candump(text, ParseMode::Strict).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!((frames[0].pgn, frames[0].source), (PGN_POSITION_RAPID, 0x23));
+        assert_eq!(frames[0].data, vec![0x3b, 0xe0, 0xa6, 0x1a, 0x5c, 0x9e, 0x1b, 0xda]);
+        assert_eq!(frames[1].timestamp, 1_700_000_000.2);
+        assert_eq!(frames[1].pgn, PGN_VESSEL_HEADING);
+        assert_eq!(frames[1].data.len(), 8);
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped_or_rejected() {
+        let text = "(1.0) can0 09F80123#3BE0A61A5C9E1BD\n(x) can0 09F80123#00\ncan0 09F80123#00\n(2.0) can0 1#00\n";
+        let frames = parse_candump(text, ParseMode::Lenient).unwrap();
+        assert_eq!(frames.len(), 1);
+        let err = parse_candump(text, ParseMode::Strict).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn pdu1_identifiers_drop_the_destination() {
+        assert_eq!(pgn_from_id(0x18EAFF00), 59904);
+        assert_eq!(pgn_from_id(0x09F80123), PGN_POSITION_RAPID);
+    }
+
+    #[test]
+    fn course_over_ground_is_not_taken_as_heading() {
+        let log = decode_frames(&[
+            cog_sog_frame(0.0, 45.0, 2.0),
+            position_frame(0.5, 44.5, -63.5),
+            heading_frame(1.0, 90.0, None, None, HEADING_TRUE),
+            cog_sog_frame(1.5, 180.0, 3.0),
+            position_frame(2.0, 44.5001, -63.5),
+        ]);
+        assert_eq!(log.fixes.len(), 2);
+        assert_eq!(log.fixes[0].heading_deg, None);
+        assert!(close(log.fixes[0].sog_knots, 2.0 * MS_TO_KNOTS));
+        assert!(close(log.fixes[1].heading_deg, 90.0));
+        assert!(close(log.fixes[1].sog_knots, 3.0 * MS_TO_KNOTS));
+        assert!((log.fixes[1].lat - 44.5001).abs() < 1e-9);
+    }
+
+    #[test]
+    fn magnetic_headings_are_corrected_to_true() {
+        let log = decode_frames(&[
+            heading_frame(0.0, 100.0, Some(2.0), Some(-17.0), HEADING_MAGNETIC),
+            heading_frame(1.0, 10.0, None, None, HEADING_MAGNETIC),
+            position_frame(1.5, 44.5, -63.5),
+        ]);
+        assert_eq!(log.headings.len(), 2);
+        assert!((log.headings[0].1 - 85.0).abs() < 0.01);
+        // The second frame reuses the variation carried by the first
+        assert!((log.headings[1].1 - 353.0).abs() < 0.01);
+        assert!(close(log.fixes[0].heading_deg, 353.0));
+        assert!(log.magnetic_headings.is_empty());
+    }
+
+    #[test]
+    fn variation_pgn_corrects_later_magnetic_headings() {
+        let mut variation = vec![0, 0xf0, 0xff, 0xff];
+        variation.extend((rad4(12.0) as i16).to_le_bytes());
+        let log = decode_frames(&[
+            heading_frame(0.0, 200.0, None, None, HEADING_MAGNETIC),
+            position_frame(0.5, 44.5, -63.5),
+            frame(1.0, PGN_MAGNETIC_VARIATION, variation),
+            heading_frame(2.0, 200.0, None, None, HEADING_MAGNETIC),
+            position_frame(2.5, 44.5, -63.5),
+        ]);
+        assert_eq!(log.magnetic_headings.len(), 1);
+        assert!((log.magnetic_headings[0].1 - 200.0).abs() < 0.01);
+        assert_eq!(log.fixes[0].heading_deg, None);
+        assert!(close(log.fixes[1].heading_deg, 212.0));
+    }
+
+    #[test]
+    fn headings_without_a_valid_reference_are_ignored() {
+        let log = decode_frames(&[
+            heading_frame(0.0, 30.0, None, None, 3),
+            position_frame(1.0, 44.5, -63.5),
+        ]);
+        assert!(log.headings.is_empty() && log.magnetic_headings.is_empty());
+        assert_eq!(log.fixes[0].heading_deg, None);
+    }
+
+    #[test]
+    fn decodes_environment_attitude_and_extras() {
+        let mut depth = vec![0];
+        depth.extend(1234u32.to_le_bytes());
+        depth.extend(500i16.to_le_bytes());
+        let mut attitude = vec![0];
+        for degrees in [10.0, -2.0, 5.0] {
+            attitude.extend((rad4(degrees) as i16).to_le_bytes());
+        }
+        let log = decode_frames(&[
+            frame(1.0, PGN_WATER_DEPTH, depth),
+            frame(1.0, PGN_WATER_DEPTH, vec![0, 0xff, 0xff, 0xff, 0xff, 0, 0]),
+            temperature_frame(2.0, 0, 16.0),
+            temperature_frame(2.0, 1, 17.9),
+            frame(3.0, PGN_SPEED, vec![0, 0xc8, 0x00]),
+            frame(4.0, PGN_ATTITUDE, attitude),
+            frame(5.0, PGN_ENGINE_RAPID, vec![1, 0x40, 0x1f]),
+            frame(5.5, PGN_ENGINE_RAPID, vec![0, 0x80, 0x3e]),
+            frame(6.0, PGN_BATTERY_STATUS, vec![0, 0xd6, 0x04]),
+            frame(7.0, 60928, vec![0; 8]),
+        ]);
+        assert_eq!(log.frames, 10);
+        assert_eq!(log.depths, vec![(1.0, 12.34 + 0.5)]);
+        assert!((log.water_temps[0].1 - 16.0).abs() < 0.01);
+        assert!((log.extras.air_temps[0].1 - 17.9).abs() < 0.01);
+        assert!((log.water_speeds[0].1 - 2.0 * MS_TO_KNOTS).abs() < 1e-9);
+        let att = log.attitude_at(4.2, 1.0).unwrap();
+        assert!(close(att.yaw_deg, 10.0) && close(att.pitch_deg, -2.0) && close(att.roll_deg, 5.0));
+        assert_eq!(log.attitude_at(10.0, 1.0), None);
+
+        let sample = log.extras.at(5.6, 4.0);
+        assert_eq!(sample.engine_rpm, Some(4000.0));
+        assert_eq!(sample.battery_volts, Some(12.38));
+        assert!(close(sample.air_temp_c, 17.9));
+        assert_eq!(log.extras.at(60.0, 2.0), ExtrasSample::default());
+    }
+
+    #[test]
+    fn extras_are_fused_into_nearby_pings() {
+        let mut frames: Vec<CanFrame> = (0..20)
+            .flat_map(|i| {
+                let t = i as f64;
+                let rpm = ((1000 + 100 * i) * 4) as u16;
+                [
+                    frame(t, PGN_ENGINE_RAPID, [vec![1], 0x40u16.to_le_bytes().to_vec()].concat()),
+                    frame(t, PGN_ENGINE_RAPID, [vec![0], rpm.to_le_bytes().to_vec()].concat()),
+                ]
+            })
+            .collect();
+        // Out of order on the bus, e.g. from two gateways
+        frames.reverse();
+        frames.push(temperature_frame(3.0, 1, 20.0));
+        let log = decode_frames(&frames);
+
+        let mut pings: Vec<Ping> = [2.9, 10.4, 40.0]
+            .into_iter()
+            .map(|timestamp| Ping {
+                timestamp,
+                ..Default::default()
+            })
+            .collect();
+        pings[2].sensors.engine_rpm = Some(1.0);
+        assert_eq!(fuse_extras(&mut pings, &log.extras, 0.5), 2);
+        assert_eq!(pings[0].sensors.engine_rpm, Some(1300.0));
+        assert!(close(pings[0].sensors.air_temp_c, 20.0));
+        assert_eq!(pings[1].sensors.engine_rpm, Some(2000.0));
+        assert_eq!(pings[1].sensors.air_temp_c, None);
+        // Nothing within reach: earlier values stay
+        assert_eq!(pings[2].sensors.engine_rpm, Some(1.0));
+    }
+
+    #[test]
+    fn attitude_and_water_temperature_are_fused_into_pings() {
+        let attitude = |pitch: f64, roll: f64| {
+            let mut d = vec![0, 0xff, 0x7f];
+            d.extend((rad4(pitch) as i16).to_le_bytes());
+            d.extend((rad4(roll) as i16).to_le_bytes());
+            d
+        };
+        let log = decode_frames(&[
+            frame(2.0, PGN_ATTITUDE, attitude(3.0, -4.0)),
+            frame(1.0, PGN_ATTITUDE, attitude(1.0, 2.0)),
+            temperature_frame(1.5, 0, 12.5),
+            frame(1.8, PGN_ENGINE_RAPID, vec![0, 0x40, 0x1f]),
+        ]);
+        let mut pings: Vec<Ping> = [1.1, 1.9, 30.0]
+            .into_iter()
+            .map(|timestamp| Ping {
+                timestamp,
+                ..Default::default()
+            })
+            .collect();
+        assert_eq!(log.fuse_sensors(&mut pings, 0.5), 2);
+        let first = pings[0].sensors;
+        assert!(close(first.pitch_deg, 1.0) && close(first.roll_deg, 2.0));
+        assert!(close(first.water_temp_c, 12.5));
+        assert_eq!(first.engine_rpm, None);
+        let second = pings[1].sensors;
+        assert!(close(second.pitch_deg, 3.0) && close(second.roll_deg, -4.0));
+        assert_eq!(second.engine_rpm, Some(2000.0));
+        assert_eq!(pings[2].sensors, Sensors::default());
+    }
+
+    #[test]
+    fn water_speed_is_fused_within_the_gap() {
+        // 2.00 and 3.00 m/s
+        let log = decode_frames(&[
+            frame(5.0, PGN_SPEED, vec![0, 0x2c, 0x01]),
+            frame(1.0, PGN_SPEED, vec![0, 0xc8, 0x00]),
+        ]);
+        let mut pings: Vec<Ping> = [1.4, 4.2, 3.0]
+            .into_iter()
+            .map(|timestamp| Ping {
+                timestamp,
+                ..Default::default()
+            })
+            .collect();
+        assert_eq!(log.fuse_sensors(&mut pings, 1.0), 2);
+        assert!(close(pings[0].stw_knots, 2.0 * MS_TO_KNOTS));
+        assert!(close(pings[1].stw_knots, 3.0 * MS_TO_KNOTS));
+        // Two seconds from either reading
+        assert_eq!(pings[2].stw_knots, None);
+    }
+
+    #[test]
+    fn short_frames_are_ignored() {
+        let frames: Vec<CanFrame> = [
+            PGN_VESSEL_HEADING,
+            PGN_POSITION_RAPID,
+            PGN_WATER_DEPTH,
+            PGN_COG_SOG_RAPID,
+        ]
+        .into_iter()
+        .map(|pgn| frame(0.0, pgn, vec![0; 2]))
+        .collect();
+        let log = decode_frames(&frames);
+        assert!(log.fixes.is_empty() && log.headings.is_empty() && log.depths.is_empty());
+    }
+}
@@ -0,0 +1,328 @@
+// Pluggable sonar format parsers and format detection
+// src/parsers/registry.rs
+
+use super::deeper::{parse_deeper_csv, read_deeper_csv};
+use super::multipart::{logical_path, part_paths, PartReader};
+use super::ParseMode;
+use crate::export::ssf::{self, read_ssf, read_ssf_from};
+use crate::survey::{Ping, Sounding};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Bytes read from the start of a file for format probing
+pub const PROBE_LEN: usize = 4096;
+
+/// Role of a sonar channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelKind {
+    Traditional,
+    DownScan,
+    SideScanPort,
+    SideScanStarboard,
+    Other,
+}
+
+/// Description of one channel in a recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelInfo {
+    pub channel_id: u16,
+    pub name: String,
+    pub kind: ChannelKind,
+    pub frequency_khz: Option<f64>,
+    /// Full cone angle of the beam, when known
+    pub beam_angle_deg: Option<f64>,
+}
+
+/// Open recording yielding pings in file order
+pub trait SonarSource: Iterator<Item = io::Result<Ping>> + Send {
+    fn channels(&self) -> Vec<ChannelInfo>;
+}
+
+/// A sonar file format that can be detected and read into pings
+///
+/// Implement this in a downstream crate and add it to a [`FormatRegistry`]
+/// to make every exporter and processing stage in this crate available for
+/// that format.
+pub trait SonarFormatParser: Send + Sync {
+    /// Short identifier such as `"deeper-csv"`
+    fn name(&self) -> &str;
+
+    /// Lowercase file extensions without the dot, used when probing is inconclusive
+    fn extensions(&self) -> &[&str];
+
+    /// Whether the first bytes of a file look like this format
+    fn probe(&self, header: &[u8]) -> bool;
+
+    fn open(&self, path: &Path, mode: ParseMode) -> io::Result<Box<dyn SonarSource>>;
+
+    /// Read from a stream instead of a file; used for multi-part recordings
+    ///
+    /// Formats that need a seekable file keep the default, which refuses.
+    fn open_reader(&self, _input: &mut dyn Read, _mode: ParseMode) -> io::Result<Box<dyn SonarSource>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} does not support multi-part recordings", self.name()),
+        ))
+    }
+}
+
+/// Open `path` with `parser`, joining numbered parts into one stream
+///
+/// Formats that cannot read from a stream fail with `Unsupported` rather
+/// than read one part, since directory scans only list the first part.
+pub fn open_with(parser: &dyn SonarFormatParser, path: &Path, mode: ParseMode) -> io::Result<Box<dyn SonarSource>> {
+    match part_paths(path) {
+        Some(parts) => {
+            tracing::debug!(parts = parts.len(), "opening multi-part recording");
+            let count = parts.len();
+            parser.open_reader(&mut PartReader::new(parts), mode).map_err(|err| {
+                if err.kind() != io::ErrorKind::Unsupported {
+                    return err;
+                }
+                io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("{}; join the {} parts of {} into one file first", err, count, path.display()),
+                )
+            })
+        }
+        None => parser.open(path, mode),
+    }
+}
+
+/// In-memory source, convenient for parsers that decode a whole file up front
+pub struct VecSource {
+    channels: Vec<ChannelInfo>,
+    pings: std::vec::IntoIter<Ping>,
+}
+
+impl VecSource {
+    /// Pings without a recorded frequency take their channel's nominal one
+    pub fn new(channels: Vec<ChannelInfo>, mut pings: Vec<Ping>) -> Self {
+        for ping in &mut pings {
+            if ping.acoustics.frequency_khz.is_none() {
+                ping.acoustics.frequency_khz = channels
+                    .iter()
+                    .find(|c| c.channel_id == ping.channel_id)
+                    .and_then(|c| c.frequency_khz);
+            }
+        }
+        Self {
+            channels,
+            pings: pings.into_iter(),
+        }
+    }
+}
+
+impl Iterator for VecSource {
+    type Item = io::Result<Ping>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pings.next().map(Ok)
+    }
+}
+
+impl SonarSource for VecSource {
+    fn channels(&self) -> Vec<ChannelInfo> {
+        self.channels.clone()
+    }
+}
+
+/// Ordered set of format parsers; the first parser whose probe matches wins
+pub struct FormatRegistry {
+    parsers: Vec<Box<dyn SonarFormatParser>>,
+    mode: ParseMode,
+}
+
+impl FormatRegistry {
+    pub fn empty() -> Self {
+        Self {
+            parsers: Vec::new(),
+            mode: ParseMode::default(),
+        }
+    }
+
+    /// Parse mode passed to parsers opened through this registry
+    pub fn with_mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn mode(&self) -> ParseMode {
+        self.mode
+    }
+
+    /// Registry with the formats built into this crate
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Box::new(DeeperCsvFormat));
+        registry.register(Box::new(SsfFormat));
+        registry
+    }
+
+    /// Add a parser; later registrations take priority over earlier ones
+    pub fn register(&mut self, parser: Box<dyn SonarFormatParser>) {
+        self.parsers.insert(0, parser);
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.parsers.iter().map(|p| p.name()).collect()
+    }
+
+    /// Find the parser for a file by content probe, then by extension
+    ///
+    /// Parts of a split recording are probed from the start of the first
+    /// part and matched by the extension before the part number.
+    pub fn detect(&self, path: &Path) -> io::Result<Option<&dyn SonarFormatParser>> {
+        let mut header = Vec::with_capacity(PROBE_LEN);
+        match part_paths(path) {
+            Some(parts) => PartReader::new(parts).take(PROBE_LEN as u64).read_to_end(&mut header)?,
+            None => File::open(path)?.take(PROBE_LEN as u64).read_to_end(&mut header)?,
+        };
+
+        if let Some(parser) = self.parsers.iter().find(|p| p.probe(&header)) {
+            return Ok(Some(parser.as_ref()));
+        }
+        let extension = logical_path(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+        Ok(self
+            .parsers
+            .iter()
+            .find(|p| p.extensions().contains(&extension.as_str()))
+            .map(|p| p.as_ref()))
+    }
+
+    /// Detect the format and open the file
+    pub fn open(&self, path: &Path) -> io::Result<Box<dyn SonarSource>> {
+        let _span = tracing::info_span!("open_recording", path = %path.display()).entered();
+        match self.detect(path)? {
+            Some(parser) => {
+                tracing::debug!(format = parser.name(), mode = ?self.mode, "detected format");
+                open_with(parser, path, self.mode)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unrecognized sonar format: {}", path.display()),
+            )),
+        }
+    }
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        Self::with_builtin()
+    }
+}
+
+/// Deeper CSV exports, yielded as depth-only pings on channel 0
+pub struct DeeperCsvFormat;
+
+impl SonarFormatParser for DeeperCsvFormat {
+    fn name(&self) -> &str {
+        "deeper-csv"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv"]
+    }
+
+    fn probe(&self, header: &[u8]) -> bool {
+        let first_line = header.split(|&b| b == b'\n').next().unwrap_or_default();
+        let text = String::from_utf8_lossy(first_line).to_lowercase();
+        text.contains("lat") && text.contains("lon") && text.contains("depth")
+    }
+
+    fn open(&self, path: &Path, mode: ParseMode) -> io::Result<Box<dyn SonarSource>> {
+        Ok(deeper_source(read_deeper_csv(path, mode)?))
+    }
+
+    fn open_reader(&self, input: &mut dyn Read, mode: ParseMode) -> io::Result<Box<dyn SonarSource>> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        Ok(deeper_source(parse_deeper_csv(&text, mode)?))
+    }
+}
+
+fn deeper_source(soundings: Vec<Sounding>) -> Box<dyn SonarSource> {
+    let pings = soundings
+        .into_iter()
+        .map(|s| Ping {
+            timestamp: s.timestamp,
+            lat: s.lat,
+            lon: s.lon,
+            depth_m: s.depth_m,
+            ..Default::default()
+        })
+        .collect();
+    let channels = vec![ChannelInfo {
+        channel_id: 0,
+        name: "Deeper".to_string(),
+        kind: ChannelKind::Traditional,
+        frequency_khz: None,
+        beam_angle_deg: None,
+    }];
+    Box::new(VecSource::new(channels, pings))
+}
+
+/// Compact archives written by [`crate::export::ssf::write_ssf`]
+pub struct SsfFormat;
+
+impl SonarFormatParser for SsfFormat {
+    fn name(&self) -> &str {
+        "ssf"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ssf"]
+    }
+
+    fn probe(&self, header: &[u8]) -> bool {
+        header.starts_with(ssf::MAGIC)
+    }
+
+    fn open(&self, path: &Path, _mode: ParseMode) -> io::Result<Box<dyn SonarSource>> {
+        let (channels, pings) = read_ssf(path)?;
+        Ok(Box::new(VecSource::new(channels, pings)))
+    }
+
+    fn open_reader(&self, input: &mut dyn Read, _mode: ParseMode) -> io::Result<Box<dyn SonarSource>> {
+        let (channels, pings) = read_ssf_from(input)?;
+        Ok(Box::new(VecSource::new(channels, pings)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::survey::Acoustics;
+
+    #[test]
+    fn pings_inherit_their_channel_frequency() {
+        let channel = |channel_id, frequency_khz| ChannelInfo {
+            channel_id,
+            name: format!("ch{}", channel_id),
+            kind: ChannelKind::Traditional,
+            frequency_khz,
+            beam_angle_deg: None,
+        };
+        let ping = |channel_id, frequency_khz| Ping {
+            channel_id,
+            acoustics: Acoustics {
+                frequency_khz,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let source = VecSource::new(
+            vec![channel(0, Some(200.0)), channel(1, None)],
+            vec![ping(0, None), ping(0, Some(83.0)), ping(1, None), ping(2, None)],
+        );
+        assert_eq!(source.channels().len(), 2);
+        let frequencies: Vec<Option<f64>> = source.map(|p| p.unwrap().acoustics.frequency_khz).collect();
+        // A recorded frequency wins; channels without a nominal one leave pings unset
+        assert_eq!(frequencies, [Some(200.0), Some(83.0), None, None]);
+    }
+}
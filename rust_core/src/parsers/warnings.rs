@@ -0,0 +1,176 @@
+// Collection of recoverable anomalies found while reading recordings
+// src/parsers/warnings.rs
+
+use crate::survey::Ping;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Category of a recoverable anomaly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// Input skipped in lenient mode (bad checksum, malformed row, ...)
+    SkippedInput,
+    /// A record timestamp earlier than the previous one on the same channel
+    ClockRegression,
+    /// A decoded value outside its plausible range
+    SuspiciousValue,
+}
+
+impl WarningKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SkippedInput => "skipped_input",
+            Self::ClockRegression => "clock_regression",
+            Self::SuspiciousValue => "suspicious_value",
+        }
+    }
+}
+
+/// One anomaly, located by input line or record time where known
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseWarning {
+    pub kind: WarningKind,
+    /// 1-based line for text formats
+    pub line: Option<usize>,
+    pub timestamp: Option<f64>,
+    pub message: String,
+}
+
+thread_local! {
+    static COLLECTOR: RefCell<Option<Vec<ParseWarning>>> = const { RefCell::new(None) };
+}
+
+/// Run `f` and return the warnings reported by readers on this thread meanwhile
+///
+/// Outside of a collection, warnings only go to the tracing log.
+pub fn collect_warnings<T, F: FnOnce() -> T>(f: F) -> (T, Vec<ParseWarning>) {
+    let outer = COLLECTOR.with(|c| c.borrow_mut().replace(Vec::new()));
+    let result = f();
+    let collected = COLLECTOR.with(|c| std::mem::replace(&mut *c.borrow_mut(), outer));
+    (result, collected.unwrap_or_default())
+}
+
+/// Hand a warning to the active collection, if any
+pub fn report(warning: ParseWarning) {
+    tracing::warn!(kind = ?warning.kind, "{}", warning.message);
+    COLLECTOR.with(|c| {
+        if let Some(list) = c.borrow_mut().as_mut() {
+            list.push(warning);
+        }
+    });
+}
+
+/// Tracks the last time per channel to spot clock regressions in a ping stream
+#[derive(Debug, Clone, Default)]
+pub struct ClockMonitor {
+    /// Backward steps up to this size are accepted (jitter)
+    pub tolerance_s: f64,
+    last: HashMap<u16, f64>,
+}
+
+impl ClockMonitor {
+    pub fn new(tolerance_s: f64) -> Self {
+        Self {
+            tolerance_s,
+            last: HashMap::new(),
+        }
+    }
+
+    /// Note a ping, reporting a warning and returning true if its clock went back
+    pub fn observe(&mut self, ping: &Ping) -> bool {
+        let previous = self.last.insert(ping.channel_id, ping.timestamp);
+        match previous {
+            Some(previous) if ping.timestamp < previous - self.tolerance_s => {
+                report(ParseWarning {
+                    kind: WarningKind::ClockRegression,
+                    line: None,
+                    timestamp: Some(ping.timestamp),
+                    message: format!(
+                        "channel {} clock went back {:.3} s",
+                        ping.channel_id,
+                        previous - ping.timestamp
+                    ),
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Report every clock regression in a recording, returning how many were found
+pub fn check_clock(pings: &[Ping], tolerance_s: f64) -> usize {
+    let mut monitor = ClockMonitor::new(tolerance_s);
+    pings.iter().filter(|p| monitor.observe(p)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::deeper::parse_deeper_csv;
+    use crate::parsers::ParseMode;
+
+    #[test]
+    fn lenient_readers_report_skipped_rows() {
+        let csv = "time,lat,lon,depth\n1700000000,44.6,-63.5,3.2\n1700000001,,-63.5,3.1\n1700000002,44.6,-63.5,3.0\n";
+        let (soundings, warnings) = collect_warnings(|| parse_deeper_csv(csv, ParseMode::Lenient).unwrap());
+        assert_eq!(soundings.len(), 2);
+        assert_eq!(
+            warnings,
+            [ParseWarning {
+                kind: WarningKind::SkippedInput,
+                line: Some(3),
+                timestamp: None,
+                message: "line 3: row without position or depth".to_string(),
+            }]
+        );
+
+        // Strict mode fails instead, and nothing is collected outside a collection
+        let (result, warnings) = collect_warnings(|| parse_deeper_csv(csv, ParseMode::Strict));
+        assert_eq!(result.unwrap_err().to_string(), "line 3: row without position or depth");
+        assert!(warnings.is_empty());
+        report(ParseWarning {
+            kind: WarningKind::SuspiciousValue,
+            line: None,
+            timestamp: None,
+            message: "ignored".to_string(),
+        });
+        assert!(collect_warnings(|| ()).1.is_empty());
+    }
+
+    #[test]
+    fn nested_collections_keep_their_own_warnings() {
+        let skipped = |line| ParseWarning {
+            kind: WarningKind::SkippedInput,
+            line: Some(line),
+            timestamp: None,
+            message: String::new(),
+        };
+        let ((_, inner), outer) = collect_warnings(|| {
+            report(skipped(1));
+            let inner = collect_warnings(|| report(skipped(2)));
+            report(skipped(3));
+            inner
+        });
+        assert_eq!(inner, [skipped(2)]);
+        assert_eq!(outer, [skipped(1), skipped(3)]);
+    }
+
+    #[test]
+    fn clock_regressions_are_per_channel_beyond_the_tolerance() {
+        let ping = |channel_id, timestamp| Ping {
+            channel_id,
+            timestamp,
+            ..Default::default()
+        };
+        // Channel 1 interleaved behind channel 0 is fine; 0.05 s of jitter is tolerated
+        let pings = [ping(0, 10.0), ping(1, 9.0), ping(0, 9.95), ping(0, 12.0), ping(0, 8.5), ping(1, 9.5)];
+        let (count, warnings) = collect_warnings(|| check_clock(&pings, 0.1));
+        assert_eq!(count, 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, WarningKind::ClockRegression);
+        assert_eq!(warnings[0].timestamp, Some(8.5));
+        assert_eq!(warnings[0].message, "channel 0 clock went back 3.500 s");
+        assert_eq!(WarningKind::ClockRegression.name(), "clock_regression");
+    }
+}
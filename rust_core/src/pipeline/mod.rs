@@ -0,0 +1,947 @@
+// Declarative processing pipelines loaded from a config file
+// src/pipeline/mod.rs
+
+pub mod toml;
+
+use self::toml::{Table, Value};
+use crate::anonymize::{anonymize_survey, AnonymizeConfig, PositionRedaction, TimeRedaction};
+use crate::batch::{discover_files, summarize_file, RecordingSummary};
+use crate::contours::generate_contours;
+use crate::crs::Crs;
+use crate::datum::{TideTable, VerticalModel};
+use crate::dedup::{reconcile, DedupConfig};
+use crate::detection::{detect_targets, DetectionConfig};
+use crate::export::{geojson, gpx, kml, las, segy, xyz};
+use crate::expr::FilterExpr;
+use crate::gridding::{grid_soundings, GridConfig, MAX_GRID_CELLS};
+use crate::imaging::palette::{BuiltinPalette, Levels, Palette};
+use crate::imaging::waterfall::{channel_pings, render_waterfall};
+use crate::parsers::FormatRegistry;
+use crate::query::SoundingQuery;
+use crate::signal::filters::{apply_filters, FilterStep};
+use crate::signal::stack::stack_pings;
+use crate::signal::tvg::{SpreadingLaw, Tvg};
+use crate::metadata::{write_sidecar, SurveyMetadata};
+use crate::motion::{compute_motion, MotionConfig};
+use crate::survey::{Ping, Sounding, VerticalReference};
+use crate::vessel::{Layback, VesselConfig};
+use rayon::prelude::*;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Product written by one `[[output]]` entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Xyz,
+    Las,
+    GeojsonContours,
+    KmlContours,
+    Segy,
+    WaterfallPng,
+    TargetsGpx,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "xyz" => Some(Self::Xyz),
+            "las" => Some(Self::Las),
+            "geojson" | "geojson-contours" => Some(Self::GeojsonContours),
+            "kml" | "kml-contours" => Some(Self::KmlContours),
+            "segy" | "sgy" => Some(Self::Segy),
+            "waterfall" | "waterfall-png" => Some(Self::WaterfallPng),
+            "gpx" | "targets-gpx" => Some(Self::TargetsGpx),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputSpec {
+    pub format: OutputFormat,
+    pub path: PathBuf,
+    /// Channel for ping-based products; the lowest channel when unset
+    pub channel: Option<u16>,
+    pub contour_interval_m: f64,
+    pub grid: GridConfig,
+    pub width: usize,
+    pub palette: BuiltinPalette,
+    /// Manual waterfall levels; a histogram stretch is used when unset
+    pub levels: Option<Levels>,
+    pub detection: DetectionConfig,
+    /// Coordinate system of XYZ, LAS and GeoJSON products; WGS84, Web Mercator or WGS84 UTM
+    pub crs: Crs,
+}
+
+impl OutputSpec {
+    pub fn new<P: AsRef<Path>>(format: OutputFormat, path: P) -> Self {
+        Self {
+            format,
+            path: path.as_ref().to_path_buf(),
+            channel: None,
+            contour_interval_m: 1.0,
+            grid: GridConfig::default(),
+            width: 512,
+            palette: BuiltinPalette::Grayscale,
+            levels: None,
+            detection: DetectionConfig::default(),
+            crs: Crs::Wgs84,
+        }
+    }
+}
+
+const SECTIONS: [&str; 11] = [
+    "input", "filter", "vessel", "layback", "vertical", "dedup", "tvg", "imaging", "motion", "anonymize", "output",
+];
+
+/// Inputs, filters, corrections and outputs of one processing run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    /// A recording, or a directory searched for recordings
+    pub input: PathBuf,
+    pub pattern: String,
+    pub recursive: bool,
+    /// Keep only pings from these channels; all channels when empty
+    pub channels: Vec<u16>,
+    pub filter: SoundingQuery,
+    /// Ping filter expression applied as each recording is read
+    pub ping_filter: Option<FilterExpr>,
+    /// Course and speed are computed from positions before `ping_filter` runs
+    pub motion: MotionConfig,
+    pub vessel: Option<VesselConfig>,
+    /// Towfish placement applied to each recording's pings before soundings are derived
+    pub layback: Option<Layback>,
+    /// Reduce depths to this reference; outputs are then tagged with it in a sidecar
+    pub vertical_reference: Option<VerticalReference>,
+    /// Water level above chart datum, required for `ChartDatum`
+    pub tide: Option<TideTable>,
+    pub dedup: Option<DedupConfig>,
+    /// Sample gain correction applied to each recording before imaging
+    pub tvg: Option<Tvg>,
+    /// Re-estimate the TVG gain per recording so its median echo lands here
+    pub tvg_auto_target: Option<u8>,
+    /// Sample filters run per recording after TVG
+    pub sample_filters: Vec<FilterStep>,
+    /// Average this many consecutive pings per channel before filtering; 1 disables
+    pub stack: usize,
+    /// Redact positions and times of every product, after all corrections
+    pub anonymize: Option<AnonymizeConfig>,
+    pub outputs: Vec<OutputSpec>,
+}
+
+impl Pipeline {
+    /// Load a pipeline file; relative paths are resolved against its directory
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let base = path.parent().unwrap_or(Path::new(""));
+        Self::from_toml(&fs::read_to_string(path)?, base)
+    }
+
+    /// Parse pipeline TOML, resolving relative paths against `base_dir`
+    ///
+    /// ```toml
+    /// [input]
+    /// path = "sdcard"
+    /// pattern = "*.csv"
+    ///
+    /// [filter]
+    /// depth_gt = 0.5
+    /// expr = "sog_knots > 1 && flags == 0"
+    /// channels = [1]
+    ///
+    /// [vessel]
+    /// draft_m = 0.4
+    ///
+    /// [layback]
+    /// cable_out_m = 25.0
+    /// towfish_depth_m = 3.0
+    ///
+    /// [motion]
+    /// window_s = 5.0
+    ///
+    /// [vertical]
+    /// reference = "chart_datum"
+    /// tide = "tides.csv"
+    ///
+    /// [anonymize]
+    /// positions = "fuzz"
+    /// rotate_deg = 40.0
+    /// jitter_m = 5.0
+    ///
+    /// [[output]]
+    /// format = "geojson-contours"
+    /// path = "out/contours.geojson"
+    /// interval_m = 2.0
+    /// crs = "EPSG:32617"
+    /// ```
+    pub fn from_toml(text: &str, base_dir: &Path) -> io::Result<Self> {
+        let doc = toml::parse(text)?;
+        if let Some(key) = doc.root.keys().next() {
+            return Err(invalid(format!("top-level key '{}' must be inside a table", key)));
+        }
+        for name in doc.tables.keys().chain(doc.arrays.keys()) {
+            if !SECTIONS.contains(&name.as_str()) {
+                return Err(invalid(format!("unknown section [{}]", name)));
+            }
+        }
+
+        let empty = Table::new();
+        let input = Section::new("input", doc.tables.get("input").unwrap_or(&empty));
+        input.check(&["path", "pattern", "recursive"])?;
+        let filter = Section::new("filter", doc.tables.get("filter").unwrap_or(&empty));
+        filter.check(&["depth_gt", "depth_lt", "time_from", "time_to", "bbox", "channels", "expr"])?;
+
+        let bbox = match filter.numbers("bbox")? {
+            None => None,
+            Some(v) if v.len() == 4 => Some((v[0], v[1], v[2], v[3])),
+            Some(_) => return Err(invalid("[filter] bbox needs [min_lat, min_lon, max_lat, max_lon]")),
+        };
+
+        let vessel = match doc.tables.get("vessel") {
+            Some(table) => {
+                let s = Section::new("vessel", table);
+                s.check(&["x_m", "y_m", "z_m", "draft_m"])?;
+                Some(VesselConfig::new(
+                    s.number("x_m")?.unwrap_or(0.0),
+                    s.number("y_m")?.unwrap_or(0.0),
+                    s.number("z_m")?.unwrap_or(0.0),
+                    s.number("draft_m")?.unwrap_or(0.0),
+                ))
+            }
+            None => None,
+        };
+
+        let layback = match doc.tables.get("layback") {
+            Some(table) => {
+                let s = Section::new("layback", table);
+                s.check(&[
+                    "cable_out_m",
+                    "towfish_depth_m",
+                    "tow_point_y_m",
+                    "tow_point_height_m",
+                    "catenary_factor",
+                    "follow_track",
+                ])?;
+                let defaults = Layback::default();
+                Some(Layback {
+                    cable_out_m: s.positive("cable_out_m")?.ok_or_else(|| invalid("[layback] cable_out_m is required"))?,
+                    towfish_depth_m: s.number("towfish_depth_m")?.unwrap_or(defaults.towfish_depth_m),
+                    tow_point_y_m: s.number("tow_point_y_m")?.unwrap_or(defaults.tow_point_y_m),
+                    tow_point_height_m: s.number("tow_point_height_m")?.unwrap_or(defaults.tow_point_height_m),
+                    catenary_factor: s.positive("catenary_factor")?.unwrap_or(defaults.catenary_factor),
+                    follow_track: s.bool("follow_track")?.unwrap_or(defaults.follow_track),
+                })
+            }
+            None => None,
+        };
+
+        let vertical = Section::new("vertical", doc.tables.get("vertical").unwrap_or(&empty));
+        vertical.check(&["reference", "tide", "tide_max_gap_s"])?;
+        let vertical_reference = match vertical.string("reference")? {
+            Some(name) => Some(
+                VerticalReference::from_name(name)
+                    .ok_or_else(|| invalid(format!("unknown vertical reference '{}'", name)))?,
+            ),
+            None => None,
+        };
+        let tide = match vertical.string("tide")? {
+            Some(path) => {
+                let mut table = TideTable::load(base_dir.join(path))?;
+                table.max_gap_s = vertical.number("tide_max_gap_s")?.unwrap_or(table.max_gap_s);
+                Some(table)
+            }
+            None => None,
+        };
+        if vertical_reference == Some(VerticalReference::ChartDatum) && tide.is_none() {
+            return Err(invalid("[vertical] reference = \"chart_datum\" needs a tide file"));
+        }
+
+        let dedup = match doc.tables.get("dedup") {
+            Some(table) => {
+                let s = Section::new("dedup", table);
+                s.check(&["cell_size_m", "pass_gap_s", "conflict_std_m"])?;
+                let defaults = DedupConfig::default();
+                Some(DedupConfig {
+                    cell_size_m: s.positive("cell_size_m")?.unwrap_or(defaults.cell_size_m),
+                    pass_gap_s: s.number("pass_gap_s")?.unwrap_or(defaults.pass_gap_s),
+                    conflict_std_m: s.number("conflict_std_m")?.unwrap_or(defaults.conflict_std_m),
+                })
+            }
+            None => None,
+        };
+
+        let mut tvg_auto_target = None;
+        let tvg = match doc.tables.get("tvg") {
+            Some(table) => {
+                let s = Section::new("tvg", table);
+                s.check(&["law", "absorption_db_per_m", "reference_m", "gain_db", "auto_target"])?;
+                let defaults = Tvg::default();
+                let law = match s.string("law")? {
+                    Some(name) => SpreadingLaw::from_name(name)
+                        .ok_or_else(|| invalid(format!("unknown TVG law '{}'", name)))?,
+                    None => defaults.law,
+                };
+                tvg_auto_target = s.number("auto_target")?.map(|t| t.clamp(1.0, 255.0) as u8);
+                Some(Tvg {
+                    law,
+                    absorption_db_per_m: s.number("absorption_db_per_m")?.unwrap_or(defaults.absorption_db_per_m),
+                    reference_m: s.number("reference_m")?.unwrap_or(defaults.reference_m),
+                    gain_db: s.number("gain_db")?.unwrap_or(defaults.gain_db),
+                })
+            }
+            None => None,
+        };
+
+        let motion = Section::new("motion", doc.tables.get("motion").unwrap_or(&empty));
+        motion.check(&["window_s", "min_speed_knots"])?;
+        let motion_defaults = MotionConfig::default();
+
+        let imaging = Section::new("imaging", doc.tables.get("imaging").unwrap_or(&empty));
+        imaging.check(&["stack", "filters"])?;
+        let sample_filters = imaging
+            .strings("filters")?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|spec| FilterStep::from_name(spec).ok_or_else(|| invalid(format!("unknown sample filter '{}'", spec))))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let anonymize = match doc.tables.get("anonymize") {
+            Some(table) => Some(parse_anonymize(&Section::new("anonymize", table))?),
+            None => None,
+        };
+
+        let mut outputs = Vec::new();
+        for table in doc.arrays.get("output").into_iter().flatten() {
+            outputs.push(parse_output(&Section::new("output", table), base_dir)?);
+        }
+        if outputs.is_empty() {
+            return Err(invalid("pipeline has no [[output]] entries"));
+        }
+
+        Ok(Self {
+            input: base_dir.join(input.string("path")?.ok_or_else(|| invalid("[input] path is required"))?),
+            pattern: input.string("pattern")?.unwrap_or("*").to_string(),
+            recursive: input.bool("recursive")?.unwrap_or(true),
+            channels: filter.channels("channels")?.unwrap_or_default(),
+            filter: SoundingQuery {
+                depth_gt: filter.number("depth_gt")?,
+                depth_lt: filter.number("depth_lt")?,
+                time_from: filter.number("time_from")?,
+                time_to: filter.number("time_to")?,
+                bbox,
+            },
+            ping_filter: filter.string("expr")?.map(FilterExpr::parse).transpose()?,
+            motion: MotionConfig {
+                window_s: motion.positive("window_s")?.unwrap_or(motion_defaults.window_s),
+                min_speed_knots: motion
+                    .number("min_speed_knots")?
+                    .unwrap_or(motion_defaults.min_speed_knots),
+            },
+            vessel,
+            layback,
+            vertical_reference,
+            tide,
+            dedup,
+            tvg,
+            tvg_auto_target,
+            sample_filters,
+            stack: imaging.count("stack")?.unwrap_or(1),
+            anonymize,
+            outputs,
+        })
+    }
+}
+
+/// Positions `keep`, `relocate` or `fuzz`; times `keep`, `zero` or `shift`
+fn parse_anonymize(s: &Section) -> io::Result<AnonymizeConfig> {
+    s.check(&[
+        "positions",
+        "offset_east_m",
+        "offset_north_m",
+        "rotate_deg",
+        "jitter_m",
+        "times",
+        "time_shift_s",
+        "seed",
+    ])?;
+    let defaults = AnonymizeConfig::default();
+    let positions = match s.string("positions")?.unwrap_or("relocate") {
+        "keep" => PositionRedaction::Keep,
+        "relocate" => PositionRedaction::Relocate,
+        "fuzz" => PositionRedaction::Fuzz {
+            offset_east_m: s.number("offset_east_m")?.unwrap_or(0.0),
+            offset_north_m: s.number("offset_north_m")?.unwrap_or(0.0),
+            rotate_deg: s.number("rotate_deg")?.unwrap_or(0.0),
+            jitter_m: s.number("jitter_m")?.unwrap_or(0.0),
+        },
+        other => return Err(invalid(format!("unknown [anonymize] positions '{}'", other))),
+    };
+    let times = match s.string("times")?.unwrap_or("zero") {
+        "keep" => TimeRedaction::Keep,
+        "zero" => TimeRedaction::ZeroBased,
+        "shift" => TimeRedaction::Shift(
+            s.number("time_shift_s")?
+                .ok_or_else(|| invalid("[anonymize] times = \"shift\" needs time_shift_s"))?,
+        ),
+        other => return Err(invalid(format!("unknown [anonymize] times '{}'", other))),
+    };
+    Ok(AnonymizeConfig {
+        positions,
+        times,
+        seed: s.whole("seed", MAX_EXACT_INTEGER)?.unwrap_or(defaults.seed),
+    })
+}
+
+fn parse_output(s: &Section, base_dir: &Path) -> io::Result<OutputSpec> {
+    s.check(&[
+        "format",
+        "path",
+        "channel",
+        "interval_m",
+        "cell_size_m",
+        "search_radius_m",
+        "beam_angle_deg",
+        "width",
+        "palette",
+        "gain_db",
+        "contrast",
+        "threshold",
+        "crs",
+    ])?;
+    let name = s.string("format")?.ok_or_else(|| invalid("[[output]] format is required"))?;
+    let format = OutputFormat::from_name(name).ok_or_else(|| invalid(format!("unknown output format '{}'", name)))?;
+    let path = s.string("path")?.ok_or_else(|| invalid("[[output]] path is required"))?;
+
+    let mut spec = OutputSpec::new(format, base_dir.join(path));
+    spec.channel = s.whole("channel", u16::MAX as f64)?.map(|c| c as u16);
+    spec.contour_interval_m = s.positive("interval_m")?.unwrap_or(spec.contour_interval_m);
+    spec.grid.cell_size_m = s.positive("cell_size_m")?.unwrap_or(spec.grid.cell_size_m);
+    spec.grid.search_radius_m = s.positive("search_radius_m")?.unwrap_or(spec.grid.search_radius_m);
+    spec.grid.beam_angle_deg = s.number("beam_angle_deg")?;
+    spec.width = s.count("width")?.unwrap_or(spec.width);
+    if let Some(name) = s.string("palette")? {
+        spec.palette = BuiltinPalette::from_name(name).ok_or_else(|| invalid(format!("unknown palette '{}'", name)))?;
+    }
+    let (gain_db, contrast) = (s.number("gain_db")?, s.number("contrast")?);
+    if gain_db.is_some() || contrast.is_some() {
+        let defaults = Levels::default();
+        spec.levels = Some(Levels {
+            gain_db: gain_db.unwrap_or(defaults.gain_db),
+            contrast: contrast.unwrap_or(defaults.contrast),
+            ..defaults
+        });
+    }
+    if let Some(threshold) = s.number("threshold")? {
+        spec.detection.threshold = threshold.clamp(0.0, 255.0) as u8;
+    }
+    spec.crs = match s.table.get("crs") {
+        None => Crs::Wgs84,
+        Some(Value::Number(code)) => Crs::from_epsg(*code as u32)?,
+        Some(Value::String(name)) => Crs::parse(name)?,
+        Some(other) => return Err(s.mismatch("crs", "EPSG code", other)),
+    };
+    let projectable = matches!(format, OutputFormat::Xyz | OutputFormat::Las | OutputFormat::GeojsonContours);
+    if !spec.crs.is_geographic() && !projectable {
+        return Err(invalid(format!("[[output]] {} is always written in WGS84 and takes no crs", name)));
+    }
+    Ok(spec)
+}
+
+/// Largest integer a TOML number holds exactly once read as `f64`
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+fn invalid<S: Into<String>>(msg: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Typed access to one table with section-qualified error messages
+struct Section<'a> {
+    name: &'a str,
+    table: &'a Table,
+}
+
+impl<'a> Section<'a> {
+    fn new(name: &'a str, table: &'a Table) -> Self {
+        Self { name, table }
+    }
+
+    fn check(&self, allowed: &[&str]) -> io::Result<()> {
+        match self.table.keys().find(|k| !allowed.contains(&k.as_str())) {
+            Some(key) => Err(invalid(format!("unknown key '{}' in [{}]", key, self.name))),
+            None => Ok(()),
+        }
+    }
+
+    fn mismatch(&self, key: &str, expected: &str, found: &Value) -> io::Error {
+        invalid(format!(
+            "[{}] {} must be a {}, found {}",
+            self.name,
+            key,
+            expected,
+            found.type_name()
+        ))
+    }
+
+    fn number(&self, key: &str) -> io::Result<Option<f64>> {
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(Value::Number(n)) => Ok(Some(*n)),
+            Some(other) => Err(self.mismatch(key, "number", other)),
+        }
+    }
+
+    /// A number that must be finite and above zero
+    fn positive(&self, key: &str) -> io::Result<Option<f64>> {
+        match self.number(key)? {
+            Some(n) if !n.is_finite() || n <= 0.0 => {
+                Err(invalid(format!("[{}] {} must be positive, found {}", self.name, key, n)))
+            }
+            other => Ok(other),
+        }
+    }
+
+    /// A whole number of at least one
+    fn count(&self, key: &str) -> io::Result<Option<usize>> {
+        match self.number(key)? {
+            None => Ok(None),
+            Some(n) if n >= 1.0 && n.fract() == 0.0 && n <= u32::MAX as f64 => Ok(Some(n as usize)),
+            Some(n) => Err(invalid(format!(
+                "[{}] {} must be a whole number of at least 1, found {}",
+                self.name, key, n
+            ))),
+        }
+    }
+
+    /// A whole number from zero to `max`
+    fn whole(&self, key: &str, max: f64) -> io::Result<Option<u64>> {
+        self.number(key)?.map(|n| self.whole_value(key, n, max)).transpose()
+    }
+
+    /// An array of channel numbers
+    fn channels(&self, key: &str) -> io::Result<Option<Vec<u16>>> {
+        self.numbers(key)?
+            .map(|items| items.into_iter().map(|n| Ok(self.whole_value(key, n, u16::MAX as f64)? as u16)).collect())
+            .transpose()
+    }
+
+    fn whole_value(&self, key: &str, n: f64, max: f64) -> io::Result<u64> {
+        if n >= 0.0 && n.fract() == 0.0 && n <= max {
+            Ok(n as u64)
+        } else {
+            Err(invalid(format!(
+                "[{}] {} must be a whole number from 0 to {}, found {}",
+                self.name, key, max, n
+            )))
+        }
+    }
+
+    fn string(&self, key: &str) -> io::Result<Option<&'a str>> {
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s)),
+            Some(other) => Err(self.mismatch(key, "string", other)),
+        }
+    }
+
+    fn bool(&self, key: &str) -> io::Result<Option<bool>> {
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(Value::Bool(b)) => Ok(Some(*b)),
+            Some(other) => Err(self.mismatch(key, "boolean", other)),
+        }
+    }
+
+    fn strings(&self, key: &str) -> io::Result<Option<Vec<&'a str>>> {
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|v| match v {
+                    Value::String(s) => Ok(s.as_str()),
+                    other => Err(self.mismatch(key, "string array", other)),
+                })
+                .collect::<io::Result<Vec<&str>>>()
+                .map(Some),
+            Some(other) => Err(self.mismatch(key, "string array", other)),
+        }
+    }
+
+    fn numbers(&self, key: &str) -> io::Result<Option<Vec<f64>>> {
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|v| match v {
+                    Value::Number(n) => Ok(*n),
+                    other => Err(self.mismatch(key, "number array", other)),
+                })
+                .collect::<io::Result<Vec<f64>>>()
+                .map(Some),
+            Some(other) => Err(self.mismatch(key, "number array", other)),
+        }
+    }
+}
+
+/// Summaries of the recordings read plus every file written
+#[derive(Debug, Clone, Default)]
+pub struct PipelineReport {
+    pub recordings: Vec<RecordingSummary>,
+    pub sounding_count: usize,
+    /// Soundings dropped because no tide was known at their time
+    pub unreduced_count: usize,
+    pub written: Vec<PathBuf>,
+}
+
+/// Read the pipeline's inputs, apply its filters and corrections, and write its outputs
+pub fn run_pipeline(pipeline: &Pipeline, registry: &FormatRegistry) -> io::Result<PipelineReport> {
+    let _span = tracing::info_span!("run_pipeline", input = %pipeline.input.display()).entered();
+    let files = if pipeline.input.is_dir() {
+        discover_files(&pipeline.input, &pipeline.pattern, pipeline.recursive)?
+    } else {
+        vec![pipeline.input.clone()]
+    };
+
+    let results: Vec<(RecordingSummary, Vec<Ping>)> = files.par_iter().map(|f| summarize_file(f, registry)).collect();
+    let mut report = PipelineReport::default();
+    let mut pings = Vec::new();
+    for (summary, mut recording) in results {
+        report.recordings.push(summary);
+        compute_motion(&mut recording, &pipeline.motion);
+        if let Some(layback) = &pipeline.layback {
+            layback.apply_pings(&mut recording);
+        }
+        if let Some(expr) = &pipeline.ping_filter {
+            recording.retain(|p| expr.matches(p));
+        }
+        if let Some(tvg) = &pipeline.tvg {
+            let tvg = match pipeline.tvg_auto_target {
+                Some(target) => tvg.with_auto_gain(&recording, target),
+                None => *tvg,
+            };
+            tvg.apply(&mut recording);
+        }
+        if pipeline.stack > 1 {
+            recording = stack_pings(&recording, pipeline.stack);
+        }
+        if !pipeline.sample_filters.is_empty() {
+            apply_filters(&mut recording, &pipeline.sample_filters);
+        }
+        pings.extend(
+            recording
+                .into_iter()
+                .filter(|p| pipeline.channels.is_empty() || pipeline.channels.contains(&p.channel_id)),
+        );
+    }
+    pings.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    // Ping products (SEG-Y, waterfalls, targets) are placed at the transducer too
+    if let Some(vessel) = &pipeline.vessel {
+        vessel.apply_pings(&mut pings);
+    }
+
+    let mut soundings: Vec<Sounding> = pings.iter().filter(|p| p.depth_m > 0.0).map(Ping::to_sounding).collect();
+    if let Some(vessel) = &pipeline.vessel {
+        vessel.draft_only().apply_all(&mut soundings);
+    }
+    if let Some(target) = pipeline.vertical_reference {
+        let model = VerticalModel {
+            draft_m: pipeline.vessel.map_or(0.0, |v| v.draft_m),
+            tide: pipeline.tide.clone(),
+        };
+        report.unreduced_count = model.reduce_all(&mut soundings, target);
+    }
+    soundings.retain(|s| pipeline.filter.matches(s));
+    if let Some(dedup) = &pipeline.dedup {
+        soundings = reconcile(&soundings, dedup).iter().map(|r| r.to_sounding()).collect();
+    }
+    report.sounding_count = soundings.len();
+
+    let mut metadata = SurveyMetadata::default();
+    if let Some(layback) = &pipeline.layback {
+        metadata.add_correction(format!("layback {:.1} m", layback.horizontal_m()));
+    }
+    if let Some(reference) = pipeline.vertical_reference {
+        metadata.extra.insert("vertical_reference".to_string(), reference.name().to_string());
+    }
+    if let Some(config) = &pipeline.anonymize {
+        anonymize_survey(&mut pings, &mut soundings, config);
+        metadata.add_correction("anonymized");
+    }
+
+    for output in &pipeline.outputs {
+        if let Some(dir) = output.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if write_output(output, &pings, &soundings)? {
+            if !metadata.is_empty() {
+                write_sidecar(&output.path, &metadata)?;
+            }
+            report.written.push(output.path.clone());
+        }
+    }
+    Ok(report)
+}
+
+/// Write one product; false when there was nothing to write
+fn write_output(output: &OutputSpec, pings: &[Ping], soundings: &[Sounding]) -> io::Result<bool> {
+    let channel = output
+        .channel
+        .or_else(|| pings.iter().map(|p| p.channel_id).min())
+        .unwrap_or(0);
+    let channel_pings = || channel_pings(pings, channel);
+
+    match output.format {
+        OutputFormat::Xyz => xyz::write_xyz_with_crs(&output.path, soundings, output.crs)?,
+        OutputFormat::Las => las::write_las_with_crs(&output.path, soundings, output.crs)?,
+        OutputFormat::GeojsonContours | OutputFormat::KmlContours => {
+            let Some(grid) = grid_soundings(soundings, &output.grid) else {
+                if soundings.is_empty() {
+                    return Ok(false);
+                }
+                return Err(invalid(format!(
+                    "{} m cells over these soundings would exceed {} grid cells",
+                    output.grid.cell_size_m, MAX_GRID_CELLS
+                )));
+            };
+            let contours = generate_contours(&grid, output.contour_interval_m);
+            if output.format == OutputFormat::GeojsonContours {
+                geojson::write_contours_with_crs(&output.path, &contours, output.crs)?;
+            } else {
+                kml::write_contours(&output.path, &contours)?;
+            }
+        }
+        OutputFormat::Segy => {
+            let selected = channel_pings();
+            if selected.is_empty() {
+                return Ok(false);
+            }
+            segy::write_segy(&output.path, &selected)?;
+        }
+        OutputFormat::WaterfallPng => {
+            let selected = channel_pings();
+            if selected.is_empty() {
+                return Ok(false);
+            }
+            let levels = output.levels.unwrap_or_else(|| Levels::auto(&selected, 1.0, 99.0));
+            render_waterfall(&selected, output.width, &levels)
+                .write_png(&output.path, &Palette::builtin(output.palette))?;
+        }
+        OutputFormat::TargetsGpx => {
+            let waypoints: Vec<_> = detect_targets(&channel_pings(), &output.detection)
+                .iter()
+                .enumerate()
+                .map(|(i, t)| t.to_waypoint(&format!("Target {}", i + 1)))
+                .collect();
+            gpx::write_waypoints(&output.path, &waypoints)?;
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch_dir;
+
+    const OUTPUT: &str = "[[output]]\nformat = \"xyz\"\npath = \"out/points.xyz\"\n";
+
+    fn parse(text: &str) -> io::Result<Pipeline> {
+        Pipeline::from_toml(text, Path::new("/data"))
+    }
+
+    fn error(text: &str) -> String {
+        let err = parse(text).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        err.to_string()
+    }
+
+    #[test]
+    fn parses_sections_and_resolves_paths() {
+        let pipeline = parse(&format!(
+            "[input]\npath = \"card\"\npattern = \"*.csv\"\nrecursive = false\n\
+             [filter]\ndepth_gt = 0.5\nchannels = [1, 2]\nexpr = \"sog_knots > 1\"\n\
+             [motion]\nwindow_s = 8\n[imaging]\nstack = 3\n{}",
+            OUTPUT
+        ))
+        .unwrap();
+        assert_eq!(pipeline.input, Path::new("/data/card"));
+        assert_eq!((pipeline.pattern.as_str(), pipeline.recursive), ("*.csv", false));
+        assert_eq!(pipeline.channels, vec![1, 2]);
+        assert_eq!(pipeline.filter.depth_gt, Some(0.5));
+        assert!(pipeline.ping_filter.is_some());
+        assert_eq!((pipeline.motion.window_s, pipeline.stack), (8.0, 3));
+        assert_eq!(pipeline.outputs.len(), 1);
+        assert_eq!(pipeline.outputs[0].format, OutputFormat::Xyz);
+        assert_eq!(pipeline.outputs[0].path, Path::new("/data/out/points.xyz"));
+    }
+
+    #[test]
+    fn rejects_unknown_sections_and_keys() {
+        let input = "[input]\npath = \"card\"\n";
+        assert_eq!(error(&format!("[inputs]\npath = \"card\"\n{}", OUTPUT)), "unknown section [inputs]");
+        assert_eq!(
+            error(&format!("path = \"card\"\n{}", OUTPUT)),
+            "top-level key 'path' must be inside a table"
+        );
+        assert_eq!(
+            error(&format!("{}[filter]\ndepth_above = 1\n{}", input, OUTPUT)),
+            "unknown key 'depth_above' in [filter]"
+        );
+        assert_eq!(
+            error(&format!("{}{}colour = \"red\"\n", input, OUTPUT)),
+            "unknown key 'colour' in [output]"
+        );
+        assert_eq!(error(input), "pipeline has no [[output]] entries");
+        assert_eq!(error(OUTPUT), "[input] path is required");
+        assert_eq!(
+            error(&format!("{}[filter]\ndepth_gt = \"1\"\n{}", input, OUTPUT)),
+            "[filter] depth_gt must be a number, found string"
+        );
+    }
+
+    #[test]
+    fn chart_datum_needs_a_tide_file() {
+        assert_eq!(
+            error(&format!("[input]\npath = \"card\"\n[vertical]\nreference = \"chart_datum\"\n{}", OUTPUT)),
+            "[vertical] reference = \"chart_datum\" needs a tide file"
+        );
+        let waterline = parse(&format!("[input]\npath = \"card\"\n[vertical]\nreference = \"waterline\"\n{}", OUTPUT));
+        assert_eq!(waterline.unwrap().vertical_reference, Some(VerticalReference::Waterline));
+    }
+
+    #[test]
+    fn crs_is_refused_on_products_written_in_wgs84() {
+        let output = |format: &str| {
+            format!(
+                "[input]\npath = \"card\"\n[[output]]\nformat = \"{}\"\npath = \"out\"\ncrs = \"EPSG:32617\"\n",
+                format
+            )
+        };
+        assert_eq!(error(&output("gpx")), "[[output]] gpx is always written in WGS84 and takes no crs");
+        assert_eq!(error(&output("kml")), "[[output]] kml is always written in WGS84 and takes no crs");
+        assert!(!parse(&output("xyz")).unwrap().outputs[0].crs.is_geographic());
+    }
+
+    #[test]
+    fn rejects_settings_that_cannot_be_used() {
+        let input = "[input]\npath = \"card\"\n";
+        for (setting, message) in [
+            ("cell_size_m = 0.0", "[output] cell_size_m must be positive, found 0"),
+            ("search_radius_m = -1", "[output] search_radius_m must be positive, found -1"),
+            ("interval_m = nan", "[output] interval_m must be positive, found NaN"),
+            ("width = 0", "[output] width must be a whole number of at least 1, found 0"),
+            ("width = 12.5", "[output] width must be a whole number of at least 1, found 12.5"),
+            ("channel = 1.5", "[output] channel must be a whole number from 0 to 65535, found 1.5"),
+            ("channel = 70000", "[output] channel must be a whole number from 0 to 65535, found 70000"),
+        ] {
+            assert_eq!(error(&format!("{}{}{}\n", input, OUTPUT, setting)), message);
+        }
+        assert_eq!(
+            error(&format!("{}[motion]\nwindow_s = -5\n{}", input, OUTPUT)),
+            "[motion] window_s must be positive, found -5"
+        );
+        assert_eq!(
+            error(&format!("{}[imaging]\nstack = 0\n{}", input, OUTPUT)),
+            "[imaging] stack must be a whole number of at least 1, found 0"
+        );
+        assert_eq!(
+            error(&format!("{}[filter]\nchannels = [1, -2]\n{}", input, OUTPUT)),
+            "[filter] channels must be a whole number from 0 to 65535, found -2"
+        );
+        assert_eq!(
+            error(&format!("{}[anonymize]\nseed = 7.25\n{}", input, OUTPUT)),
+            "[anonymize] seed must be a whole number from 0 to 9007199254740992, found 7.25"
+        );
+        assert_eq!(
+            error(&format!("{}[anonymize]\nseed = -1\n{}", input, OUTPUT)),
+            "[anonymize] seed must be a whole number from 0 to 9007199254740992, found -1"
+        );
+        let pipeline = parse(&format!("{}[anonymize]\nseed = 42\n{}channel = 65535\n", input, OUTPUT)).unwrap();
+        assert_eq!(pipeline.outputs[0].channel, Some(65535));
+        assert_eq!(pipeline.anonymize.unwrap().seed, 42);
+    }
+
+    #[test]
+    fn parses_anonymize_options() {
+        let input = "[input]\npath = \"card\"\n";
+        let pipeline = parse(&format!(
+            "{}[anonymize]\npositions = \"fuzz\"\nrotate_deg = 40\njitter_m = 5\ntimes = \"shift\"\n\
+             time_shift_s = -3600\nseed = 99\n{}",
+            input, OUTPUT
+        ))
+        .unwrap();
+        let config = pipeline.anonymize.unwrap();
+        assert_eq!(config.times, TimeRedaction::Shift(-3600.0));
+        assert_eq!(config.seed, 99);
+        let PositionRedaction::Fuzz { rotate_deg, jitter_m, .. } = config.positions else {
+            panic!("{:?}", config.positions)
+        };
+        assert_eq!((rotate_deg, jitter_m), (40.0, 5.0));
+        assert_eq!(
+            error(&format!("{}[anonymize]\ntimes = \"shift\"\n{}", input, OUTPUT)),
+            "[anonymize] times = \"shift\" needs time_shift_s"
+        );
+        assert_eq!(
+            error(&format!("{}[anonymize]\npositions = \"blur\"\n{}", input, OUTPUT)),
+            "unknown [anonymize] positions 'blur'"
+        );
+        assert!(parse(&format!("{}{}", input, OUTPUT)).unwrap().anonymize.is_none());
+    }
+
+    #[test]
+    fn parses_layback() {
+        let input = "[input]\npath = \"card\"\n";
+        let pipeline = parse(&format!(
+            "{}[layback]\ncable_out_m = 25\ntowfish_depth_m = 3\nfollow_track = false\n{}",
+            input, OUTPUT
+        ))
+        .unwrap();
+        let layback = pipeline.layback.unwrap();
+        assert_eq!((layback.cable_out_m, layback.towfish_depth_m), (25.0, 3.0));
+        assert_eq!((layback.catenary_factor, layback.follow_track), (1.0, false));
+        assert_eq!(
+            error(&format!("{}[layback]\ntowfish_depth_m = 3\n{}", input, OUTPUT)),
+            "[layback] cable_out_m is required"
+        );
+    }
+
+    #[test]
+    fn runs_over_a_directory_of_recordings() {
+        let dir = scratch_dir("pipeline", "run");
+        fs::create_dir_all(dir.join("card")).unwrap();
+        let mut csv = String::from("Time,Depth (m),Latitude,Longitude\n");
+        for i in 0..50 {
+            let depth = if i == 10 { 0.2 } else { 2.0 + i as f64 * 0.1 };
+            csv.push_str(&format!("{},{},{},{}\n", 1_700_000_000 + i, depth, 44.6 + i as f64 * 1e-5, -63.5));
+        }
+        fs::write(dir.join("card/track.csv"), csv).unwrap();
+        fs::write(dir.join("card/notes.txt"), "not a recording").unwrap();
+        let text = "[input]\npath = \"card\"\npattern = \"*.csv\"\n\
+                    [filter]\ndepth_gt = 0.5\n\
+                    [vessel]\ndraft_m = 0.3\ny_m = 11.1\n\
+                    [vertical]\nreference = \"waterline\"\n\
+                    [[output]]\nformat = \"xyz\"\npath = \"out/points.xyz\"\n\
+                    [[output]]\nformat = \"segy\"\npath = \"out/none.sgy\"\nchannel = 5\n";
+        fs::write(dir.join("pipeline.toml"), text).unwrap();
+
+        let pipeline = Pipeline::load(dir.join("pipeline.toml")).unwrap();
+        let report = run_pipeline(&pipeline, &FormatRegistry::default()).unwrap();
+        assert_eq!(report.recordings.len(), 1);
+        assert_eq!(report.recordings[0].ping_count, 50);
+        assert_eq!(report.sounding_count, 49);
+        // Nothing was recorded on channel 5, so no SEG-Y is written
+        assert_eq!(report.written, vec![dir.join("out/points.xyz")]);
+
+        let xyz = fs::read_to_string(dir.join("out/points.xyz")).unwrap();
+        assert_eq!(xyz.lines().count(), 49);
+        // Waterline depth is the transducer depth plus the draft
+        // 11.1 m forward on a north heading, applied once; the draft is added once too
+        assert_eq!(xyz.lines().next(), Some("-63.5000000 44.6000997 2.300"));
+        let sidecar = fs::read_dir(dir.join("out"))
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .any(|e| fs::read_to_string(e.path()).is_ok_and(|t| t.contains("waterline")));
+        assert!(sidecar);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
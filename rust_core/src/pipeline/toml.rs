@@ -0,0 +1,597 @@
+// Minimal TOML reader for pipeline configuration files
+// src/pipeline/toml.rs
+//
+// Covers TOML 1.0 apart from dates and times: `[table]` and `[[array]]`
+// headers, bare, quoted and dotted keys, basic and literal strings (single
+// and multi-line, with every escape), integers in any radix, floats
+// including inf/nan, booleans, arrays and inline tables. Header names are
+// kept whole, so `[a.b]` is the table "a.b" rather than "b" nested in "a";
+// dotted keys and inline tables inside a table become `Value::Table`.
+
+use std::collections::BTreeMap;
+use std::io;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Number(_) => "number",
+            Value::Bool(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+pub type Table = BTreeMap<String, Value>;
+
+/// Parsed document: top-level keys, named tables and arrays of tables
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    pub root: Table,
+    pub tables: BTreeMap<String, Table>,
+    pub arrays: BTreeMap<String, Vec<Table>>,
+}
+
+enum Target {
+    Root,
+    Table(String),
+    Array(String),
+}
+
+pub fn parse(text: &str) -> io::Result<Document> {
+    let mut doc = Document::default();
+    let mut target = Target::Root;
+    let mut p = Parser { text, pos: 0, line: 1 };
+
+    loop {
+        p.skip_blank();
+        let Some(c) = p.peek() else { break };
+        let line = p.line;
+
+        if c == '[' {
+            let array = p.text[p.pos..].starts_with("[[");
+            p.pos += if array { 2 } else { 1 };
+            let name = p.key()?.join(".");
+            p.skip_ws();
+            let close = if array { "]]" } else { "]" };
+            if !p.eat(close) {
+                return Err(p.error(&format!("expected '{}' after table name", close)));
+            }
+            if array {
+                if doc.tables.contains_key(&name) {
+                    return Err(error(line, &format!("[[{}]] conflicts with table [{}]", name, name)));
+                }
+                doc.arrays.entry(name.clone()).or_default().push(Table::new());
+                target = Target::Array(name);
+            } else {
+                if doc.arrays.contains_key(&name) {
+                    return Err(error(line, &format!("[{}] conflicts with array [[{}]]", name, name)));
+                }
+                if doc.tables.insert(name.clone(), Table::new()).is_some() {
+                    return Err(error(line, &format!("table [{}] defined twice", name)));
+                }
+                target = Target::Table(name);
+            }
+        } else {
+            let key = p.key()?;
+            p.skip_ws();
+            if !p.eat("=") {
+                return Err(p.error("expected `key = value`"));
+            }
+            let value = p.value()?;
+            let table = match &target {
+                Target::Root => &mut doc.root,
+                Target::Table(name) => doc.tables.get_mut(name).expect("current table exists"),
+                Target::Array(name) => doc
+                    .arrays
+                    .get_mut(name)
+                    .and_then(|a| a.last_mut())
+                    .expect("current array entry exists"),
+            };
+            insert(table, &key, value).map_err(|msg| error(line, &msg))?;
+        }
+        p.end_of_line()?;
+    }
+    Ok(doc)
+}
+
+fn error(line: usize, problem: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, problem))
+}
+
+/// Insert `value` at a dotted key path, creating intermediate tables
+fn insert(table: &mut Table, key: &[String], value: Value) -> Result<(), String> {
+    let (last, parents) = key.split_last().expect("keys have at least one part");
+    let mut table = table;
+    for (i, part) in parents.iter().enumerate() {
+        let entry = table.entry(part.clone()).or_insert_with(|| Value::Table(Table::new()));
+        table = match entry {
+            Value::Table(inner) => inner,
+            _ => return Err(format!("key '{}' is not a table", key[..=i].join("."))),
+        };
+    }
+    if table.insert(last.clone(), value).is_some() {
+        return Err(format!("key '{}' defined twice", key.join(".")));
+    }
+    Ok(())
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, problem: &str) -> io::Error {
+        error(self.line, problem)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.text[self.pos..].starts_with(token) {
+            for _ in token.chars() {
+                self.bump();
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skip whitespace, comments and newlines, as between statements or array items
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_ws();
+            self.skip_comment();
+            if !(self.eat("\n") || self.eat("\r\n")) {
+                break;
+            }
+        }
+    }
+
+    /// Require the rest of the line to be blank or a comment
+    fn end_of_line(&mut self) -> io::Result<()> {
+        self.skip_ws();
+        self.skip_comment();
+        if self.peek().is_none() || self.eat("\n") || self.eat("\r\n") {
+            Ok(())
+        } else {
+            let rest = self.text[self.pos..].lines().next().unwrap_or("");
+            Err(self.error(&format!("unexpected trailing text '{}'", rest.trim())))
+        }
+    }
+
+    /// A possibly dotted key: bare or quoted parts joined by `.`
+    fn key(&mut self) -> io::Result<Vec<String>> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_ws();
+            let part = match self.peek() {
+                Some('"') => {
+                    self.bump();
+                    self.basic_string()?
+                }
+                Some('\'') => {
+                    self.bump();
+                    self.literal_string()?
+                }
+                _ => {
+                    let start = self.pos;
+                    while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        self.bump();
+                    }
+                    if start == self.pos {
+                        return Err(self.error("expected a key"));
+                    }
+                    self.text[start..self.pos].to_string()
+                }
+            };
+            parts.push(part);
+            self.skip_ws();
+            if !self.eat(".") {
+                return Ok(parts);
+            }
+        }
+    }
+
+    fn value(&mut self) -> io::Result<Value> {
+        self.skip_ws();
+        match self.peek() {
+            Some('"') if self.eat("\"\"\"") => self.multiline_basic_string().map(Value::String),
+            Some('"') => {
+                self.bump();
+                self.basic_string().map(Value::String)
+            }
+            Some('\'') if self.eat("'''") => self.multiline_literal_string().map(Value::String),
+            Some('\'') => {
+                self.bump();
+                self.literal_string().map(Value::String)
+            }
+            Some('[') => {
+                self.bump();
+                self.array()
+            }
+            Some('{') => {
+                self.bump();
+                self.inline_table()
+            }
+            Some(_) => self.scalar(),
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    fn array(&mut self) -> io::Result<Value> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.eat("]") {
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank();
+            if !self.eat(",") && self.peek() != Some(']') {
+                return Err(self.error(if self.peek().is_none() {
+                    "unterminated array"
+                } else {
+                    "expected ',' or ']' in array"
+                }));
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> io::Result<Value> {
+        let mut table = Table::new();
+        self.skip_ws();
+        if self.eat("}") {
+            return Ok(Value::Table(table));
+        }
+        loop {
+            let key = self.key()?;
+            self.skip_ws();
+            if !self.eat("=") {
+                return Err(self.error("expected `key = value` in inline table"));
+            }
+            let value = self.value()?;
+            insert(&mut table, &key, value).map_err(|msg| self.error(&msg))?;
+            self.skip_ws();
+            if self.eat("}") {
+                return Ok(Value::Table(table));
+            }
+            if !self.eat(",") {
+                return Err(self.error("expected ',' or '}' in inline table"));
+            }
+        }
+    }
+
+    /// Number or boolean up to the next delimiter
+    fn scalar(&mut self) -> io::Result<Value> {
+        let start = self.pos;
+        while !matches!(
+            self.peek(),
+            None | Some(' ' | '\t' | '\r' | '\n' | ',' | ']' | '}' | '#')
+        ) {
+            self.bump();
+        }
+        let token = &self.text[start..self.pos];
+        parse_scalar(token).ok_or_else(|| self.error(&format!("invalid value '{}'", token)))
+    }
+
+    /// Body of a `"` string, after the opening quote
+    fn basic_string(&mut self) -> io::Result<String> {
+        let mut out = String::new();
+        loop {
+            match self.peek() {
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some('"') => {
+                    self.bump();
+                    return Ok(out);
+                }
+                Some('\\') => {
+                    self.bump();
+                    out.push(self.escape()?);
+                }
+                Some(c) => {
+                    self.bump();
+                    out.push(c);
+                }
+            }
+        }
+    }
+
+    /// Body of a `"""` string, after the opening quotes
+    fn multiline_basic_string(&mut self) -> io::Result<String> {
+        let start_line = self.line;
+        // A newline straight after the opening quotes is not part of the string
+        let _ = self.eat("\n") || self.eat("\r\n");
+        let mut out = String::new();
+        loop {
+            if self.eat("\"\"\"") {
+                // Up to two quotes may directly precede the closing delimiter
+                while self.peek() == Some('"') && !self.text[self.pos..].starts_with("\"\"\"") {
+                    self.bump();
+                    out.push('"');
+                }
+                return Ok(out);
+            }
+            match self.bump() {
+                Some('\\') => {
+                    // Line-ending backslash trims the newline and leading whitespace
+                    let save = (self.pos, self.line);
+                    self.skip_ws();
+                    if matches!(self.peek(), Some('\n' | '\r')) {
+                        while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                            self.bump();
+                        }
+                    } else {
+                        (self.pos, self.line) = save;
+                        out.push(self.escape()?);
+                    }
+                }
+                Some(c) => out.push(c),
+                None => return Err(error(start_line, "unterminated multi-line string")),
+            }
+        }
+    }
+
+    /// Body of a `'` string, after the opening quote
+    fn literal_string(&mut self) -> io::Result<String> {
+        let rest = &self.text[self.pos..];
+        match rest.find(['\'', '\n']) {
+            Some(end) if rest[end..].starts_with('\'') => {
+                self.pos += end + 1;
+                Ok(rest[..end].to_string())
+            }
+            _ => Err(self.error("unterminated string")),
+        }
+    }
+
+    /// Body of a `'''` string, after the opening quotes
+    fn multiline_literal_string(&mut self) -> io::Result<String> {
+        let start_line = self.line;
+        let _ = self.eat("\n") || self.eat("\r\n");
+        let start = self.pos;
+        let end = self.text[start..]
+            .find("'''")
+            .ok_or_else(|| error(start_line, "unterminated multi-line string"))?;
+        // Up to two quotes may directly precede the closing delimiter
+        let end = start
+            + end
+            + self.text[start + end + 3..]
+                .chars()
+                .take_while(|&c| c == '\'')
+                .count()
+                .min(2);
+        while self.pos < end {
+            self.bump();
+        }
+        let body = self.text[start..end].to_string();
+        self.eat("'''");
+        Ok(body)
+    }
+
+    /// Character for the escape after a `\`
+    fn escape(&mut self) -> io::Result<char> {
+        let c = match self.bump() {
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(u @ ('u' | 'U')) => {
+                let digits = if u == 'u' { 4 } else { 8 };
+                let hex = self.text.get(self.pos..self.pos + digits).unwrap_or("");
+                let code = u32::from_str_radix(hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == digits && hex.chars().all(|c| c.is_ascii_hexdigit()))
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error(&format!("invalid unicode escape '\\{}{}'", u, hex)))?;
+                self.pos += digits;
+                code
+            }
+            other => {
+                let shown = other.map(String::from).unwrap_or_default();
+                return Err(self.error(&format!("unsupported escape '\\{}'", shown)));
+            }
+        };
+        Ok(c)
+    }
+}
+
+fn parse_scalar(token: &str) -> Option<Value> {
+    match token {
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        "inf" | "+inf" => return Some(Value::Number(f64::INFINITY)),
+        "-inf" => return Some(Value::Number(f64::NEG_INFINITY)),
+        "nan" | "+nan" | "-nan" => return Some(Value::Number(f64::NAN)),
+        _ => {}
+    }
+    // Underscores are only allowed between digits
+    let bytes = token.as_bytes();
+    let digit_around = |i: usize| {
+        i > 0 && i + 1 < bytes.len() && bytes[i - 1].is_ascii_alphanumeric() && bytes[i + 1].is_ascii_alphanumeric()
+    };
+    if (0..bytes.len()).any(|i| bytes[i] == b'_' && !digit_around(i)) {
+        return None;
+    }
+    let clean = token.replace('_', "");
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = clean.strip_prefix(prefix) {
+            return i64::from_str_radix(digits, radix).ok().map(|n| Value::Number(n as f64));
+        }
+    }
+    let unsigned = clean.trim_start_matches(['+', '-']);
+    let valid = unsigned.starts_with(|c: char| c.is_ascii_digit())
+        && unsigned
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+        && !unsigned.ends_with('.');
+    // Leading zeros are not allowed on integer parts
+    let integer_part = unsigned.split(['.', 'e', 'E']).next().unwrap_or("");
+    if !valid || (integer_part.len() > 1 && integer_part.starts_with('0')) {
+        return None;
+    }
+    clean.parse().ok().map(Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(text: &str) -> Value {
+        parse(&format!("v = {}", text)).unwrap().root.remove("v").unwrap()
+    }
+
+    fn string(text: &str) -> String {
+        match value(text) {
+            Value::String(s) => s,
+            other => panic!("expected a string, got {:?}", other),
+        }
+    }
+
+    fn line_of(err: io::Error) -> String {
+        err.to_string().split(':').next().unwrap().to_string()
+    }
+
+    #[test]
+    fn parses_tables_and_arrays_of_tables() {
+        let doc = parse(
+            "title = 'survey'  # trailing comment\n\
+             [input]\npath = \"logs/a.sl2\"\nrecursive = true\n\n\
+             [[output]]\nformat = \"xyz\"\n[[output]]\nformat = \"las\"\n\
+             [\"quoted.name\"]\n[a . b]\nx = 1\n",
+        )
+        .unwrap();
+        assert_eq!(doc.root["title"], Value::String("survey".into()));
+        assert_eq!(doc.tables["input"]["path"], Value::String("logs/a.sl2".into()));
+        assert_eq!(doc.tables["input"]["recursive"], Value::Bool(true));
+        let formats: Vec<_> = doc.arrays["output"].iter().map(|t| t["format"].clone()).collect();
+        assert_eq!(formats, [Value::String("xyz".into()), Value::String("las".into())]);
+        assert!(doc.tables.contains_key("quoted.name"));
+        assert_eq!(doc.tables["a.b"]["x"], Value::Number(1.0));
+    }
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(value("42"), Value::Number(42.0));
+        assert_eq!(value("-1_000"), Value::Number(-1000.0));
+        assert_eq!(value("+3.5e2"), Value::Number(350.0));
+        assert_eq!(value("0xff"), Value::Number(255.0));
+        assert_eq!(value("0o17"), Value::Number(15.0));
+        assert_eq!(value("0b101"), Value::Number(5.0));
+        assert_eq!(value("-inf"), Value::Number(f64::NEG_INFINITY));
+        assert!(matches!(value("nan"), Value::Number(n) if n.is_nan()));
+        for bad in ["1__0", "_1", "1_", "01", "1.", "abc", "1979-05-27"] {
+            assert!(parse(&format!("v = {}", bad)).is_err(), "{} accepted", bad);
+        }
+    }
+
+    #[test]
+    fn parses_string_escapes() {
+        assert_eq!(string(r#""a\tb\n\"c\" \\ \u00e9 \U0001F600""#), "a\tb\n\"c\" \\ é 😀");
+        assert_eq!(string(r##""# not a comment""##), "# not a comment");
+        assert_eq!(string(r"'C:\no\escapes'"), r"C:\no\escapes");
+        assert!(parse(r#"v = "\q""#).is_err());
+        assert!(parse(r#"v = "\uD800""#).is_err());
+        assert!(parse(r#"v = "\u12""#).is_err());
+    }
+
+    #[test]
+    fn parses_multiline_strings() {
+        assert_eq!(string("\"\"\"\nfirst\nsecond\"\"\""), "first\nsecond");
+        assert_eq!(string("\"\"\"one \\\n     two\"\"\""), "one two");
+        assert_eq!(string("\"\"\"say \"hi\"\"\"\""), "say \"hi\"");
+        assert_eq!(string("'''\nraw \\n [x] # y\n'''"), "raw \\n [x] # y\n");
+        assert_eq!(string("'''it's'''''"), "it's''");
+        let doc = parse("a = \"\"\"\nx\ny\n\"\"\"\nb = 1\n").unwrap();
+        assert_eq!(doc.root["b"], Value::Number(1.0));
+    }
+
+    #[test]
+    fn parses_nested_arrays_and_inline_tables() {
+        let doc = parse("bbox = [\n  44.0, # south\n  -64.0,\n  45.0, -63.0,\n]\nmixed = [[1, 2], ['a]']]\n").unwrap();
+        let bbox = [44.0, -64.0, 45.0, -63.0].map(Value::Number).to_vec();
+        assert_eq!(doc.root["bbox"], Value::Array(bbox));
+        let inner = vec![
+            Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]),
+            Value::Array(vec![Value::String("a]".into())]),
+        ];
+        assert_eq!(doc.root["mixed"], Value::Array(inner));
+
+        let doc = parse("point = { lat = 44.5, lon = -63.5, tag.name = \"x\" }\nsite.depth = 3\n").unwrap();
+        let Value::Table(point) = &doc.root["point"] else {
+            panic!("not a table")
+        };
+        assert_eq!(point["lat"], Value::Number(44.5));
+        assert_eq!(
+            point["tag"],
+            Value::Table(Table::from([("name".into(), Value::String("x".into()))]))
+        );
+        assert_eq!(
+            doc.root["site"],
+            Value::Table(Table::from([("depth".into(), Value::Number(3.0))]))
+        );
+    }
+
+    #[test]
+    fn rejects_duplicates_and_conflicts() {
+        assert!(parse("a = 1\na = 2").is_err());
+        assert!(parse("a.b = 1\na.b = 2").is_err());
+        assert!(parse("a = 1\na.b = 2").is_err());
+        assert!(parse("[t]\n[t]").is_err());
+        assert!(parse("[t]\n[[t]]").is_err());
+        assert!(parse("[[t]]\n[t]").is_err());
+    }
+
+    #[test]
+    fn errors_report_the_offending_line() {
+        assert_eq!(line_of(parse("[input]\npath = 'a'\nbad line\n").unwrap_err()), "line 3");
+        assert_eq!(line_of(parse("a = [\n1,\n2\n3]\n").unwrap_err()), "line 4");
+        assert_eq!(line_of(parse("a = [\n1,\n").unwrap_err()), "line 3");
+        assert_eq!(line_of(parse("\n\na = \"open\nb = 1").unwrap_err()), "line 3");
+        assert_eq!(line_of(parse("s = '''\nnever closed").unwrap_err()), "line 1");
+        assert_eq!(line_of(parse("a = 1 2\n").unwrap_err()), "line 1");
+        assert_eq!(line_of(parse("[input\n").unwrap_err()), "line 1");
+        assert!(parse("a = { b = 1,\n c = 2 }").is_err());
+        assert_eq!(parse("").unwrap(), Document::default());
+    }
+}
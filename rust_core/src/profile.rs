@@ -0,0 +1,381 @@
+// Depth, temperature and navigation series resampled onto a regular time base
+// src/profile.rs
+
+use crate::survey::Ping;
+use std::io;
+
+/// Regularly spaced series ready for plotting
+///
+/// Values are NaN where no reading lies within the allowed gap; `gap`
+/// marks those depth samples so plots can break the line there.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DepthProfile {
+    pub time: Vec<f64>,
+    pub depth_m: Vec<f64>,
+    pub temperature_c: Vec<f64>,
+    pub gap: Vec<bool>,
+}
+
+impl DepthProfile {
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+
+    /// Profile of ping depths and water temperatures, one reading per timestamp
+    pub fn from_pings(pings: &[Ping], interval_s: f64, max_gap_s: f64) -> Self {
+        let depths: Vec<(f64, f64)> = pings
+            .iter()
+            .filter(|p| p.depth_m > 0.0)
+            .map(|p| (p.timestamp, p.depth_m))
+            .collect();
+        let temps: Vec<(f64, f64)> = pings
+            .iter()
+            .filter_map(|p| Some((p.timestamp, p.sensors.water_temp_c?)))
+            .collect();
+        depth_profile(&depths, &temps, interval_s, max_gap_s)
+    }
+}
+
+/// Resample `(timestamp, depth)` and `(timestamp, temperature)` readings every `interval_s`
+///
+/// The time base spans the depth readings. Values are linearly
+/// interpolated between neighbouring readings no more than `max_gap_s` apart.
+pub fn depth_profile(depths: &[(f64, f64)], temps: &[(f64, f64)], interval_s: f64, max_gap_s: f64) -> DepthProfile {
+    let depths = sorted_finite(depths);
+    let temps = sorted_finite(temps);
+    let (Some(first), Some(last)) = (depths.first(), depths.last()) else {
+        return DepthProfile::default();
+    };
+    if interval_s <= 0.0 {
+        return DepthProfile::default();
+    }
+
+    let count = ((last.0 - first.0) / interval_s).floor() as usize + 1;
+    let time: Vec<f64> = (0..count).map(|i| first.0 + i as f64 * interval_s).collect();
+    let depth_m: Vec<f64> = time.iter().map(|&t| interpolate(&depths, t, max_gap_s)).collect();
+    DepthProfile {
+        temperature_c: time.iter().map(|&t| interpolate(&temps, t, max_gap_s)).collect(),
+        gap: depth_m.iter().map(|d| d.is_nan()).collect(),
+        depth_m,
+        time,
+    }
+}
+
+fn sorted_finite(series: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut sorted: Vec<(f64, f64)> = series
+        .iter()
+        .copied()
+        .filter(|(t, v)| t.is_finite() && v.is_finite())
+        .collect();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    sorted
+}
+
+fn interpolate(series: &[(f64, f64)], t: f64, max_gap_s: f64) -> f64 {
+    let idx = series.partition_point(|s| s.0 <= t);
+    if idx == 0 {
+        return f64::NAN;
+    }
+    let a = series[idx - 1];
+    if a.0 == t {
+        return a.1;
+    }
+    match series.get(idx) {
+        Some(&b) if b.0 - a.0 <= max_gap_s => a.1 + (b.1 - a.1) * (t - a.0) / (b.0 - a.0),
+        _ => f64::NAN,
+    }
+}
+
+/// How the readings falling in one resampling bin are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregation {
+    #[default]
+    Mean,
+    Median,
+    Min,
+    Max,
+    First,
+    Last,
+}
+
+impl Aggregation {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "mean" | "avg" => Some(Self::Mean),
+            "median" => Some(Self::Median),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            "first" => Some(Self::First),
+            "last" => Some(Self::Last),
+            _ => None,
+        }
+    }
+
+    /// Combine readings given in time order; NaN when there are none
+    fn combine(self, values: &[f64]) -> f64 {
+        let (Some(&first), Some(&last)) = (values.first(), values.last()) else {
+            return f64::NAN;
+        };
+        match self {
+            Self::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Self::Median => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let mid = sorted.len() / 2;
+                if sorted.len().is_multiple_of(2) {
+                    (sorted[mid - 1] + sorted[mid]) / 2.0
+                } else {
+                    sorted[mid]
+                }
+            }
+            Self::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Self::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Self::First => first,
+            Self::Last => last,
+        }
+    }
+
+    /// Like [`Self::combine`] for bearings in degrees; the mean is taken on the circle
+    fn combine_angles(self, values: &[f64]) -> f64 {
+        if self != Self::Mean || values.is_empty() {
+            return self.combine(values);
+        }
+        let (sin, cos) = values.iter().fold((0.0, 0.0), |(s, c), v| {
+            (s + v.to_radians().sin(), c + v.to_radians().cos())
+        });
+        sin.atan2(cos).to_degrees().rem_euclid(360.0)
+    }
+}
+
+/// Navigation and environment fields on a uniform time base
+///
+/// `time` is the start of each bin. Bins without any ping have `gap` set,
+/// a zero `count` and NaN values; a field is also NaN when no ping in the
+/// bin carried it (e.g. unpositioned pings or a missing speed).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResampledSeries {
+    pub time: Vec<f64>,
+    pub lat: Vec<f64>,
+    pub lon: Vec<f64>,
+    pub depth_m: Vec<f64>,
+    pub heading_deg: Vec<f64>,
+    pub cog_deg: Vec<f64>,
+    pub sog_knots: Vec<f64>,
+    /// Pings that fell in each bin
+    pub count: Vec<usize>,
+    pub gap: Vec<bool>,
+}
+
+impl ResampledSeries {
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+}
+
+/// Bin pings every `interval_s` from the first ping and combine each bin with `aggregation`
+pub fn resample(pings: &[Ping], interval_s: f64, aggregation: Aggregation) -> ResampledSeries {
+    let mut sorted: Vec<&Ping> = pings.iter().filter(|p| p.timestamp.is_finite()).collect();
+    sorted.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    let (Some(first), Some(last)) = (sorted.first(), sorted.last()) else {
+        return ResampledSeries::default();
+    };
+    if interval_s <= 0.0 {
+        return ResampledSeries::default();
+    }
+    let start = first.timestamp;
+    let count = ((last.timestamp - start) / interval_s).floor() as usize + 1;
+
+    let mut series = ResampledSeries::default();
+    let mut rest = &sorted[..];
+    for i in 0..count {
+        let end = start + (i + 1) as f64 * interval_s;
+        let split = rest.partition_point(|p| p.timestamp < end);
+        let (bin, tail) = rest.split_at(split);
+        rest = tail;
+
+        let positioned: Vec<&&Ping> = bin.iter().filter(|p| p.lat != 0.0 || p.lon != 0.0).collect();
+        let field = |values: Vec<f64>| aggregation.combine(&values);
+        series.time.push(start + i as f64 * interval_s);
+        series.lat.push(field(positioned.iter().map(|p| p.lat).collect()));
+        series.lon.push(field(positioned.iter().map(|p| p.lon).collect()));
+        series
+            .depth_m
+            .push(field(bin.iter().map(|p| p.depth_m).filter(|&d| d > 0.0).collect()));
+        series
+            .heading_deg
+            .push(aggregation.combine_angles(&bin.iter().map(|p| p.heading_deg).collect::<Vec<_>>()));
+        series
+            .cog_deg
+            .push(aggregation.combine_angles(&bin.iter().filter_map(|p| p.cog_deg).collect::<Vec<_>>()));
+        series
+            .sog_knots
+            .push(field(bin.iter().filter_map(|p| p.sog_knots).collect()));
+        series.count.push(bin.len());
+        series.gap.push(bin.is_empty());
+    }
+    series
+}
+
+/// Seconds in an interval such as `"1s"`, `"500ms"`, `"2min"` or `"1h"`
+pub fn parse_interval(text: &str) -> io::Result<f64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let scale = match unit.trim() {
+        "" | "s" | "sec" => 1.0,
+        "ms" => 0.001,
+        "m" | "min" => 60.0,
+        "h" => 3600.0,
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown interval unit '{}'", other),
+            ))
+        }
+    };
+    match number.parse::<f64>() {
+        Ok(value) if value > 0.0 => Ok(value * scale),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid interval '{}'", text))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::survey::Sensors;
+
+    fn ping(timestamp: f64, depth_m: f64, water_temp_c: Option<f64>) -> Ping {
+        Ping {
+            timestamp,
+            depth_m,
+            sensors: Sensors {
+                water_temp_c,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn profile_interpolates_and_flags_gaps() {
+        // Readings at 0, 2 and 4 s, then a 6 s silence before 10 s
+        let pings = [
+            ping(4.0, 14.0, None),
+            ping(0.0, 10.0, Some(12.0)),
+            ping(2.0, 12.0, None),
+            ping(10.0, 20.0, Some(14.0)),
+            ping(3.0, 0.0, None),
+        ];
+        let profile = DepthProfile::from_pings(&pings, 1.0, 3.0);
+        assert_eq!(profile.time, (0..=10).map(f64::from).collect::<Vec<_>>());
+        assert_eq!(&profile.depth_m[..5], &[10.0, 11.0, 12.0, 13.0, 14.0]);
+        assert_eq!(profile.depth_m[10], 20.0);
+        assert!(profile.depth_m[5..10].iter().all(|d| d.is_nan()));
+        assert_eq!(profile.gap, (0..=10).map(|i| (5..10).contains(&i)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn temperatures_come_from_the_ping_sensors() {
+        let pings = [
+            ping(0.0, 10.0, Some(12.0)),
+            ping(2.0, 10.0, Some(13.0)),
+            ping(4.0, 10.0, None),
+            ping(6.0, 10.0, None),
+            ping(8.0, 10.0, Some(20.0)),
+        ];
+        let profile = DepthProfile::from_pings(&pings, 1.0, 2.0);
+        assert_eq!(&profile.temperature_c[..3], &[12.0, 12.5, 13.0]);
+        // Depths continue, but 2 s to 8 s is wider than the allowed temperature gap
+        assert!(profile.temperature_c[3..8].iter().all(|t| t.is_nan()));
+        assert_eq!(profile.temperature_c[8], 20.0);
+        assert!(!profile.gap.iter().any(|&g| g));
+        assert!(DepthProfile::from_pings(&[], 1.0, 2.0).is_empty());
+    }
+
+    /// Bins of 2 s from 100 s: four pings, one, none, one; input out of order
+    fn uneven_pings() -> Vec<Ping> {
+        let nav = |timestamp: f64, depth_m: f64, lat: f64, heading_deg: f64, sog_knots: Option<f64>| Ping {
+            timestamp,
+            depth_m,
+            lat,
+            lon: if lat == 0.0 { 0.0 } else { -63.5 },
+            heading_deg,
+            sog_knots,
+            ..Default::default()
+        };
+        vec![
+            nav(101.9, 9.0, 44.4, 20.0, Some(4.0)),
+            nav(100.0, 4.0, 44.0, 350.0, Some(3.0)),
+            nav(106.5, 7.0, 44.6, 180.0, None),
+            nav(101.0, 0.0, 0.0, 0.0, Some(5.0)),
+            nav(102.1, 6.0, 44.5, 90.0, None),
+            nav(f64::NAN, 1.0, 44.9, 0.0, None),
+            nav(100.4, 2.0, 44.2, 10.0, None),
+        ]
+    }
+
+    #[test]
+    fn resampling_bins_uneven_pings_and_marks_gaps() {
+        let series = resample(&uneven_pings(), 2.0, Aggregation::Last);
+        assert_eq!(series.time, [100.0, 102.0, 104.0, 106.0]);
+        assert_eq!(series.count, [4, 1, 0, 1]);
+        assert_eq!(series.gap, [false, false, true, false]);
+        assert_eq!(series.depth_m[1], 6.0);
+        assert_eq!(series.depth_m[3], 7.0);
+        // The empty bin and fields no ping carried are NaN
+        for field in [&series.lat, &series.lon, &series.depth_m, &series.heading_deg, &series.sog_knots] {
+            assert!(field[2].is_nan());
+        }
+        assert!(series.sog_knots[1].is_nan());
+        assert!(series.cog_deg.iter().all(|c| c.is_nan()));
+
+        assert!(resample(&uneven_pings(), 0.0, Aggregation::Mean).is_empty());
+        assert!(resample(&[], 1.0, Aggregation::Mean).is_empty());
+    }
+
+    #[test]
+    fn resampling_applies_each_aggregation() {
+        // First bin: depths 4, 2, 9 (the 0 m reading is unset), latitudes 44.0, 44.2, 44.4
+        // (one ping is unpositioned), headings 350, 10, 0, 20 and speeds 3, 5, 4 in time order
+        let cases = [
+            ("mean", 5.0, 44.2, 5.0, 4.0),
+            ("median", 4.0, 44.2, 15.0, 4.0),
+            ("min", 2.0, 44.0, 0.0, 3.0),
+            ("max", 9.0, 44.4, 350.0, 5.0),
+            ("first", 4.0, 44.0, 350.0, 3.0),
+            ("last", 9.0, 44.4, 20.0, 4.0),
+        ];
+        for (name, depth_m, lat, heading_deg, sog_knots) in cases {
+            let series = resample(&uneven_pings(), 2.0, Aggregation::from_name(name).unwrap());
+            let got = [series.depth_m[0], series.lat[0], series.heading_deg[0], series.sog_knots[0]];
+            for (got, expected) in got.into_iter().zip([depth_m, lat, heading_deg, sog_knots]) {
+                assert!((got - expected).abs() < 0.05, "{}: {:?}", name, got);
+            }
+            assert_eq!(series.lon[0], -63.5);
+        }
+        assert_eq!(Aggregation::from_name("AVG"), Some(Aggregation::Mean));
+        assert_eq!(Aggregation::from_name("mode"), None);
+    }
+
+    #[test]
+    fn parses_interval_units() {
+        assert_eq!(parse_interval("1s").unwrap(), 1.0);
+        assert_eq!(parse_interval(" 500ms ").unwrap(), 0.5);
+        assert_eq!(parse_interval("2min").unwrap(), 120.0);
+        assert_eq!(parse_interval("1.5h").unwrap(), 5400.0);
+        assert_eq!(parse_interval("3").unwrap(), 3.0);
+        for bad in ["", "0s", "-1s", "1 fortnight", "s"] {
+            assert_eq!(parse_interval(bad).unwrap_err().kind(), io::ErrorKind::InvalidInput, "{}", bad);
+        }
+    }
+}
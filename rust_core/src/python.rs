@@ -0,0 +1,1524 @@
+// Python bindings for reading sonar recordings
+// src/python.rs
+
+use crate::anonymize::{anonymize_channels, anonymize_pings, AnonymizeConfig, PositionRedaction, TimeRedaction};
+use crate::batch::{
+    detect_sessions as find_sessions, process_directory as process_batch, BatchOptions, RecordingSummary, SessionConfig,
+};
+use crate::catalog::{
+    catalog_directory as scan_catalog, query_catalog as filter_catalog, read_catalog_db, write_catalog_db,
+    CatalogEntry, CatalogQuery,
+};
+use crate::export::arrow::write_arrow;
+use crate::export::gpx::escape_xml;
+use crate::export::report::summary_card;
+use crate::export::ssf::{write_ssf, write_ssf_compressed};
+use crate::expr::{Field, FilterExpr};
+use crate::gridding::{bin_soundings, BinStatistic, MAX_GRID_CELLS};
+use crate::imaging::palette::ImagingSettings;
+use crate::imaging::plot::{waterfall_plot, PlotImage, PlotTrack};
+use crate::imaging::png::{write_png, PngColor};
+use crate::imaging::quicklook::{render_quicklook, QuicklookConfig};
+use crate::imaging::waterfall::{channel_pings, range_segments as split_range_segments};
+use crate::motion::{compute_motion, estimate_current, MotionConfig, MotionStream};
+use crate::parsers::nmea2000::read_candump;
+use crate::parsers::warnings::{collect_warnings, ClockMonitor, ParseWarning};
+use crate::parsers::{open_with, ChannelInfo, FormatRegistry, ParseMode, SonarSource};
+use crate::pipeline::{run_pipeline as execute_pipeline, Pipeline};
+use crate::profile::{parse_interval, resample as resample_pings, Aggregation, DepthProfile};
+use crate::query::{records_where_indexed, shallowest as shallowest_soundings, SoundingQuery};
+use crate::spatial::SoundingIndex;
+use crate::stats::{StreamingStats, MAX_BINS};
+use crate::survey::Ping;
+use crate::validate::validate as validate_recording;
+use crate::vessel::VesselConfig;
+use numpy::PyArray1;
+use pyo3::create_exception;
+use pyo3::exceptions::{
+    PyBufferError, PyFileNotFoundError, PyIOError, PyPermissionError, PyRuntimeError, PyUserWarning, PyValueError,
+};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use pyo3::{ffi, AsPyPointer};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Write as _};
+use std::io;
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata};
+
+/// Size of the track mini-map in notebook summaries
+const REPR_MAP_SIZE: usize = 200;
+/// Buffer protocol format string for unsigned bytes
+const FORMAT_U8: &[u8] = b"B\0";
+/// Queued log events beyond this many drop the oldest
+const LOG_QUEUE_LIMIT: usize = 10_000;
+
+/// Most verbose tracing level forwarded to Python: 0 off, 1 error through 5 trace
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(0);
+/// Events waiting for the GIL as (logging level, logger name, message)
+static LOG_QUEUE: Mutex<VecDeque<(u8, String, String)>> = Mutex::new(VecDeque::new());
+
+create_exception!(
+    cesarops_core,
+    ParseError,
+    PyIOError,
+    "A recording is malformed or truncated"
+);
+create_exception!(
+    cesarops_core,
+    UnsupportedFormatError,
+    PyIOError,
+    "A file is not in any recognized sonar format, or the format cannot do what was asked"
+);
+
+/// Python exception for a Rust I/O error
+///
+/// Both custom exceptions derive from `IOError`, so existing handlers still
+/// catch them.
+fn io_error(err: io::Error) -> PyErr {
+    let message = err.to_string();
+    match err.kind() {
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => ParseError::new_err(message),
+        io::ErrorKind::Unsupported => UnsupportedFormatError::new_err(message),
+        io::ErrorKind::NotFound => PyFileNotFoundError::new_err(message),
+        io::ErrorKind::PermissionDenied => PyPermissionError::new_err(message),
+        _ => PyIOError::new_err(message),
+    }
+}
+
+fn parse_mode(name: &str) -> PyResult<ParseMode> {
+    ParseMode::from_name(name)
+        .ok_or_else(|| PyValueError::new_err(format!("unknown parse mode '{}' (strict or lenient)", name)))
+}
+
+/// Ping as a dict; `samples` is a bytes object
+fn ping_to_dict<'py>(py: Python<'py>, ping: &Ping) -> PyResult<&'py PyDict> {
+    let dict = PyDict::new(py);
+    dict.set_item("timestamp", ping.timestamp)?;
+    dict.set_item("channel_id", ping.channel_id)?;
+    dict.set_item("latitude", ping.lat)?;
+    dict.set_item("longitude", ping.lon)?;
+    dict.set_item("heading_deg", ping.heading_deg)?;
+    dict.set_item("depth_m", ping.depth_m)?;
+    dict.set_item("range_m", ping.range_m)?;
+    dict.set_item("cog_deg", ping.cog_deg)?;
+    dict.set_item("sog_knots", ping.sog_knots)?;
+    dict.set_item("stw_knots", ping.stw_knots)?;
+    dict.set_item("frequency_khz", ping.acoustics.frequency_khz)?;
+    dict.set_item("chirp_khz", ping.acoustics.chirp_khz)?;
+    dict.set_item("pulse_length_us", ping.acoustics.pulse_length_us)?;
+    dict.set_item("range_setting_m", ping.acoustics.range_setting_m)?;
+    dict.set_item("gain_pct", ping.acoustics.gain_pct)?;
+    dict.set_item("pitch_deg", ping.sensors.pitch_deg)?;
+    dict.set_item("roll_deg", ping.sensors.roll_deg)?;
+    dict.set_item("water_temp_c", ping.sensors.water_temp_c)?;
+    dict.set_item("battery_volts", ping.sensors.battery_volts)?;
+    dict.set_item("engine_rpm", ping.sensors.engine_rpm)?;
+    dict.set_item("air_temp_c", ping.sensors.air_temp_c)?;
+    dict.set_item("flags", ping.flags)?;
+    dict.set_item("samples", PyBytes::new(py, &ping.samples))?;
+    Ok(dict)
+}
+
+/// Raise Python `UserWarning`s for reader anomalies
+fn emit_warnings(py: Python<'_>, warnings: &[ParseWarning]) -> PyResult<()> {
+    for warning in warnings {
+        PyErr::warn(py, py.get_type::<PyUserWarning>(), &warning.message, 1)?;
+    }
+    Ok(())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn level_rank(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 1,
+        Level::WARN => 2,
+        Level::INFO => 3,
+        Level::DEBUG => 4,
+        Level::TRACE => 5,
+    }
+}
+
+/// Python `logging` level number for a tracing level
+fn python_level(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 40,
+        Level::WARN => 30,
+        Level::INFO => 20,
+        Level::DEBUG => 10,
+        Level::TRACE => 5,
+    }
+}
+
+/// Message and ` name=value` fields of a span or event
+#[derive(Default)]
+struct FieldText {
+    message: String,
+    fields: String,
+}
+
+impl tracing::field::Visit for FieldText {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+thread_local! {
+    /// Spans entered on this thread, innermost last
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Tracing subscriber that queues events for Python's `logging`
+///
+/// Events can come from threads that must not take the GIL, such as rayon
+/// workers while the caller holds it, so they are only queued here and
+/// handed over by [`flush_logs`]. Messages are prefixed with the spans they
+/// happened in, e.g. `open_recording{path=a.csv}: detected format`.
+struct PyLogSubscriber {
+    /// Open spans as `name{fields}` text and a reference count
+    spans: Mutex<HashMap<u64, (String, usize)>>,
+    next_id: AtomicU64,
+}
+
+impl tracing::Subscriber for PyLogSubscriber {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // The level can change at any time, so decide per event
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        level_rank(metadata.level()) <= LOG_LEVEL.load(Ordering::Relaxed)
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut text = FieldText::default();
+        span.record(&mut text);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let label = format!("{}{{{}}}", span.metadata().name(), text.fields.trim_start());
+        lock(&self.spans).insert(id, (label, 1));
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut text = FieldText::default();
+        values.record(&mut text);
+        if let Some((label, _)) = lock(&self.spans).get_mut(&span.into_u64()) {
+            label.pop();
+            label.push_str(&text.fields);
+            label.push('}');
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut text = FieldText::default();
+        event.record(&mut text);
+        let mut message = {
+            let spans = lock(&self.spans);
+            ENTERED.with(|entered| {
+                entered
+                    .borrow()
+                    .iter()
+                    .filter_map(|id| spans.get(id))
+                    .map(|(label, _)| format!("{}: ", label))
+                    .collect::<String>()
+            })
+        };
+        message.push_str(&text.message);
+        message.push_str(&text.fields);
+
+        let metadata = event.metadata();
+        let mut queue = lock(&LOG_QUEUE);
+        if queue.len() >= LOG_QUEUE_LIMIT {
+            queue.pop_front();
+        }
+        let logger = metadata.target().replace("::", ".");
+        queue.push_back((python_level(metadata.level()), logger, message));
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(pos) = entered.iter().rposition(|&id| id == span.into_u64()) {
+                entered.remove(pos);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some((_, refs)) = lock(&self.spans).get_mut(&span.into_u64()) {
+            *refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = lock(&self.spans);
+        match spans.get_mut(&span.into_u64()) {
+            Some((_, refs)) if *refs > 1 => {
+                *refs -= 1;
+                false
+            }
+            Some(_) => {
+                spans.remove(&span.into_u64());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Hand queued tracing events to Python's `logging`; logging failures are ignored
+fn flush_logs(py: Python<'_>) {
+    let records: Vec<_> = lock(&LOG_QUEUE).drain(..).collect();
+    if records.is_empty() {
+        return;
+    }
+    let Ok(logging) = py.import("logging") else {
+        return;
+    };
+    for (level, name, message) in records {
+        if let Ok(logger) = logging.call_method1("getLogger", (name,)) {
+            let _ = logger.call_method1("log", (level, message));
+        }
+    }
+}
+
+/// Flushes queued log events when an entry point returns, by any path
+struct LogFlush<'py>(Python<'py>);
+
+impl Drop for LogFlush<'_> {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            flush_logs(self.0);
+        }
+    }
+}
+
+/// Forward the reader's tracing diagnostics to Python's `logging`
+///
+/// Events at `level` (`"error"`, `"warning"`, `"info"`, `"debug"` or
+/// `"trace"`) and above go to loggers named after their Rust module, such
+/// as `cesarops_core.parsers.registry`, when the call that produced them
+/// returns. `None` or `"off"` stops forwarding.
+#[pyfunction]
+pub fn set_log_level(py: Python<'_>, level: Option<&str>) -> PyResult<()> {
+    let rank = match level.map(str::to_lowercase).as_deref() {
+        None | Some("off") => 0,
+        Some("error") => 1,
+        Some("warn" | "warning") => 2,
+        Some("info") => 3,
+        Some("debug") => 4,
+        Some("trace") => 5,
+        Some(other) => return Err(PyValueError::new_err(format!("unknown log level '{}'", other))),
+    };
+    if rank > 0 {
+        static INSTALLED: OnceLock<bool> = OnceLock::new();
+        let installed = *INSTALLED.get_or_init(|| {
+            let subscriber = PyLogSubscriber {
+                spans: Mutex::new(HashMap::new()),
+                next_id: AtomicU64::new(1),
+            };
+            tracing::subscriber::set_global_default(subscriber).is_ok()
+        });
+        if !installed {
+            return Err(PyRuntimeError::new_err("a tracing subscriber is already installed"));
+        }
+    }
+    LOG_LEVEL.store(rank, Ordering::Relaxed);
+    flush_logs(py);
+    Ok(())
+}
+
+/// Iterator yielding lists of up to `batch_size` ping dicts
+#[pyclass]
+pub struct PingBatches {
+    path: String,
+    /// Course and speed are filled in before `filter` sees each ping
+    source: MotionStream<Box<dyn SonarSource>>,
+    batch_size: usize,
+    filter: Option<FilterExpr>,
+    filter_text: Option<String>,
+    pings_read: usize,
+    clock: ClockMonitor,
+    /// Emit anomalies through `warnings.warn` as well as collecting them
+    warn: bool,
+    collected: Vec<ParseWarning>,
+}
+
+impl PingBatches {
+    fn record(&mut self, py: Python<'_>, warnings: Vec<ParseWarning>) -> PyResult<()> {
+        if self.warn {
+            emit_warnings(py, &warnings)?;
+        }
+        self.collected.extend(warnings);
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl PingBatches {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        let _logs = LogFlush(py);
+        let this = &mut *slf;
+        let (pings, warnings) = collect_warnings(|| {
+            let filter = &this.filter;
+            let pings = this
+                .source
+                .by_ref()
+                .filter(|ping| match (ping, filter) {
+                    (Ok(ping), Some(expr)) => expr.matches(ping),
+                    _ => true,
+                })
+                .take(this.batch_size)
+                .collect::<io::Result<Vec<Ping>>>()?;
+            for ping in &pings {
+                this.clock.observe(ping);
+            }
+            Ok::<_, io::Error>(pings)
+        });
+        this.record(py, warnings)?;
+        let pings = pings.map_err(io_error)?;
+        if pings.is_empty() {
+            return Ok(None);
+        }
+        this.pings_read += pings.len();
+        let list = PyList::empty(py);
+        for ping in &pings {
+            list.append(ping_to_dict(py, ping)?)?;
+        }
+        Ok(Some(list.to_object(py)))
+    }
+
+    /// Anomalies seen so far as `(kind, line, timestamp, message)` tuples
+    #[getter]
+    fn warnings(&self) -> Vec<(&'static str, Option<usize>, Option<f64>, String)> {
+        self.collected
+            .iter()
+            .map(|w| (w.kind.name(), w.line, w.timestamp, w.message.clone()))
+            .collect()
+    }
+
+    /// Progress table for notebooks; the batches themselves are not read ahead
+    fn _repr_html_(&self) -> String {
+        let rows = [
+            ("Path", self.path.clone()),
+            ("Batch size", self.batch_size.to_string()),
+            ("Filter", self.filter_text.clone().unwrap_or_else(|| "-".to_string())),
+            ("Pings read", self.pings_read.to_string()),
+            ("Warnings", self.collected.len().to_string()),
+        ];
+        html_table("PingBatches", &rows)
+    }
+}
+
+/// Open a recording and iterate over its pings `batch_size` at a time
+///
+/// Skipped input and clock regressions are collected on the iterator's
+/// `warnings` attribute and, unless `warn` is false, raised as `UserWarning`.
+/// `filter` is an expression such as `"depth_m < 3 && channel_id == 4"`;
+/// pings that do not match are dropped before conversion to dicts. `mode`
+/// is `"lenient"` (skip malformed input) or `"strict"` (raise on it).
+#[pyfunction]
+#[pyo3(signature = (path, batch_size = 1000, warn = true, filter = None, mode = "lenient"))]
+pub fn parse_batches(
+    py: Python<'_>,
+    path: &str,
+    batch_size: usize,
+    warn: bool,
+    filter: Option<&str>,
+    mode: &str,
+) -> PyResult<PingBatches> {
+    let _logs = LogFlush(py);
+    let filter_text = filter;
+    let filter = filter
+        .map(FilterExpr::parse)
+        .transpose()
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    let registry = FormatRegistry::default().with_mode(parse_mode(mode)?);
+    let (source, warnings) = collect_warnings(|| registry.open(Path::new(path)));
+    let mut batches = PingBatches {
+        path: path.to_string(),
+        source: MotionStream::new(source.map_err(io_error)?, MotionConfig::default()),
+        batch_size: batch_size.max(1),
+        filter,
+        filter_text: filter_text.map(str::to_string),
+        pings_read: 0,
+        clock: ClockMonitor::default(),
+        warn,
+        collected: Vec::new(),
+    };
+    batches.record(py, warnings)?;
+    Ok(batches)
+}
+
+/// Walk a recording and return its validation report as a dict
+#[pyfunction]
+pub fn validate(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+    let _logs = LogFlush(py);
+    let report = validate_recording(Path::new(path), &FormatRegistry::default());
+    let dict = PyDict::new(py);
+    dict.set_item("path", path)?;
+    dict.set_item("format", &report.format)?;
+    dict.set_item("valid", report.is_valid())?;
+    dict.set_item("records", report.records)?;
+    dict.set_item("skipped_inputs", report.skipped_inputs)?;
+    dict.set_item("clock_regressions", report.clock_regressions)?;
+    dict.set_item("invalid_positions", report.invalid_positions)?;
+    dict.set_item("suspicious_values", report.suspicious_values)?;
+    dict.set_item("unpositioned", report.unpositioned)?;
+    dict.set_item("error", &report.error)?;
+    let issues: Vec<_> = report
+        .issues
+        .iter()
+        .map(|w| (w.kind.name(), w.line, w.timestamp, w.message.clone()))
+        .collect();
+    dict.set_item("issues", issues)?;
+    Ok(dict.to_object(py))
+}
+
+/// Count, min, max, mean, stddev, percentiles and histogram of one ping field
+///
+/// The recording is streamed in fixed memory rather than loaded; unset
+/// values (e.g. a missing `sog_knots`) are skipped. `p5`, `median` and `p95`
+/// are exact up to a few thousand values and P² estimates beyond that. The histogram is returned as
+/// `(edges, counts)` with `bins` equal-width bins, at most 65,536; more
+/// raises ValueError.
+#[pyfunction]
+#[pyo3(signature = (path, field, bins = 32, filter = None))]
+pub fn field_stats(py: Python<'_>, path: &str, field: &str, bins: usize, filter: Option<&str>) -> PyResult<PyObject> {
+    let _logs = LogFlush(py);
+    if bins > MAX_BINS {
+        return Err(PyValueError::new_err(format!("bins {} exceeds the limit of {}", bins, MAX_BINS)));
+    }
+    let field = Field::from_name(field).ok_or_else(|| PyValueError::new_err(format!("unknown field '{}'", field)))?;
+    let filter = filter
+        .map(FilterExpr::parse)
+        .transpose()
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    let source = FormatRegistry::default().open(Path::new(path)).map_err(io_error)?;
+    let source = MotionStream::new(source, MotionConfig::default());
+    let mut stats = StreamingStats::new(bins);
+    for ping in source {
+        let ping = ping.map_err(io_error)?;
+        if filter.as_ref().is_none_or(|expr| expr.matches(&ping)) {
+            stats.push(field.value(&ping));
+        }
+    }
+    let stats = stats.finish();
+
+    let dict = PyDict::new(py);
+    dict.set_item("count", stats.count)?;
+    dict.set_item("min", stats.min)?;
+    dict.set_item("max", stats.max)?;
+    dict.set_item("mean", stats.mean)?;
+    dict.set_item("stddev", stats.stddev)?;
+    dict.set_item("p5", stats.p5)?;
+    dict.set_item("median", stats.median)?;
+    dict.set_item("p95", stats.p95)?;
+    dict.set_item("histogram", (stats.histogram.edges, stats.histogram.counts))?;
+    Ok(dict.to_object(py))
+}
+
+/// Resample a recording's navigation fields every `interval_ms`
+///
+/// Returns a dict of equal-length lists (`time`, `lat`, `lon`, `depth_m`,
+/// `heading_deg`, `cog_deg`, `sog_knots`, `count`, `gap`). Empty bins are
+/// kept with `gap` set and NaN values so the time base stays uniform.
+#[pyfunction]
+#[pyo3(signature = (path, interval_ms, aggregation = "mean", filter = None))]
+pub fn resample(
+    py: Python<'_>,
+    path: &str,
+    interval_ms: f64,
+    aggregation: &str,
+    filter: Option<&str>,
+) -> PyResult<PyObject> {
+    let _logs = LogFlush(py);
+    let aggregation = Aggregation::from_name(aggregation)
+        .ok_or_else(|| PyValueError::new_err(format!("unknown aggregation '{}'", aggregation)))?;
+    if interval_ms <= 0.0 {
+        return Err(PyValueError::new_err("interval_ms must be positive"));
+    }
+    let filter = filter
+        .map(FilterExpr::parse)
+        .transpose()
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    let mut pings = FormatRegistry::default()
+        .open(Path::new(path))
+        .and_then(|source| source.collect::<io::Result<Vec<Ping>>>())
+        .map_err(io_error)?;
+    compute_motion(&mut pings, &MotionConfig::default());
+    if let Some(expr) = &filter {
+        pings.retain(|p| expr.matches(p));
+    }
+    let series = resample_pings(&pings, interval_ms / 1000.0, aggregation);
+
+    let dict = PyDict::new(py);
+    dict.set_item("time", series.time)?;
+    dict.set_item("lat", series.lat)?;
+    dict.set_item("lon", series.lon)?;
+    dict.set_item("depth_m", series.depth_m)?;
+    dict.set_item("heading_deg", series.heading_deg)?;
+    dict.set_item("cog_deg", series.cog_deg)?;
+    dict.set_item("sog_knots", series.sog_knots)?;
+    dict.set_item("count", series.count)?;
+    dict.set_item("gap", series.gap)?;
+    Ok(dict.to_object(py))
+}
+
+/// Depth and water temperature of a recording resampled for plotting
+///
+/// `resample` and `max_gap` are intervals such as `"1s"`, `"500ms"` or
+/// `"2min"`. Returns a dict of equal-length lists (`time`, `depth_m`,
+/// `temperature_c`, `gap`); values are NaN, and `gap` set, where no
+/// readings lie within `max_gap` of each other.
+#[pyfunction]
+#[pyo3(signature = (path, resample = "1s", max_gap = "5s"))]
+pub fn depth_profile(py: Python<'_>, path: &str, resample: &str, max_gap: &str) -> PyResult<PyObject> {
+    let _logs = LogFlush(py);
+    let interval = parse_interval(resample).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    let max_gap = parse_interval(max_gap).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    let pings = FormatRegistry::default()
+        .open(Path::new(path))
+        .and_then(|source| source.collect::<io::Result<Vec<Ping>>>())
+        .map_err(io_error)?;
+    let profile = DepthProfile::from_pings(&pings, interval, max_gap);
+
+    let dict = PyDict::new(py);
+    dict.set_item("time", profile.time)?;
+    dict.set_item("depth_m", profile.depth_m)?;
+    dict.set_item("temperature_c", profile.temperature_c)?;
+    dict.set_item("gap", profile.gap)?;
+    Ok(dict.to_object(py))
+}
+
+/// GDAL-style `(west, dlon, 0, north, 0, -dlat)` geotransform in degrees
+type GridTransform = (f64, f64, f64, f64, f64, f64);
+
+/// Bin a recording's soundings into `cell_size_m` cells in one call
+///
+/// Returns `(grid, transform)`: a 2-D float64 array, north row first, with
+/// NaN in empty cells, and its GDAL-style geotransform
+/// `(west, dlon, 0, north, 0, -dlat)` in degrees. `transducer_offset_m` is the
+/// transducer's `(starboard, forward, down)` offset from the GPS antenna and
+/// `draft_m` its depth below the waterline; with either set, soundings are
+/// moved to the transducer and depths are below the waterline.
+#[pyfunction]
+#[pyo3(signature = (path, cell_size_m, statistic = "median", filter = None, transducer_offset_m = None, draft_m = 0.0))]
+pub fn grid_depths(
+    py: Python<'_>,
+    path: &str,
+    cell_size_m: f64,
+    statistic: &str,
+    filter: Option<&str>,
+    transducer_offset_m: Option<(f64, f64, f64)>,
+    draft_m: f64,
+) -> PyResult<(PyObject, GridTransform)> {
+    let _logs = LogFlush(py);
+    let statistic = BinStatistic::from_name(statistic)
+        .ok_or_else(|| PyValueError::new_err(format!("unknown statistic '{}'", statistic)))?;
+    if !cell_size_m.is_finite() || cell_size_m <= 0.0 {
+        return Err(PyValueError::new_err("cell_size_m must be positive"));
+    }
+    let (x_m, y_m, z_m) = transducer_offset_m.unwrap_or_default();
+    if ![x_m, y_m, z_m, draft_m].iter().all(|v| v.is_finite()) {
+        return Err(PyValueError::new_err("transducer_offset_m and draft_m must be finite"));
+    }
+    let filter = filter
+        .map(FilterExpr::parse)
+        .transpose()
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    let source = FormatRegistry::default().open(Path::new(path)).map_err(io_error)?;
+    let source = MotionStream::new(source, MotionConfig::default());
+    let mut soundings = Vec::new();
+    for ping in source {
+        let ping = ping.map_err(io_error)?;
+        let positioned = ping.lat != 0.0 || ping.lon != 0.0;
+        if positioned && ping.depth_m > 0.0 && filter.as_ref().is_none_or(|expr| expr.matches(&ping)) {
+            soundings.push(ping.to_sounding());
+        }
+    }
+    if transducer_offset_m.is_some() || draft_m != 0.0 {
+        VesselConfig::new(x_m, y_m, z_m, draft_m).apply_all(&mut soundings);
+    }
+    let grid = py
+        .allow_threads(|| bin_soundings(&soundings, cell_size_m, statistic))
+        .ok_or_else(|| {
+            if soundings.is_empty() {
+                PyValueError::new_err("recording has no positioned soundings")
+            } else {
+                PyValueError::new_err(format!(
+                    "{} m cells over this recording would exceed {} cells",
+                    cell_size_m, MAX_GRID_CELLS
+                ))
+            }
+        })?;
+
+    let [west, dlon, row_rot, north, col_rot, dlat] = grid.geotransform();
+    let array = PyArray1::from_vec(py, grid.values).reshape([grid.rows, grid.cols])?;
+    Ok((array.to_object(py), (west, dlon, row_rot, north, col_rot, dlat)))
+}
+
+/// Range-setting changes of one channel as a list of segment dicts
+///
+/// Each dict has `start` and `end` ping indices within the channel (end
+/// exclusive), `start_time`, `end_time` and the `range_m` its waterfall rows
+/// are scaled to.
+#[pyfunction]
+#[pyo3(signature = (path, channel_id, filter = None))]
+pub fn range_segments(py: Python<'_>, path: &str, channel_id: u16, filter: Option<&str>) -> PyResult<PyObject> {
+    let _logs = LogFlush(py);
+    let filter = filter
+        .map(FilterExpr::parse)
+        .transpose()
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    let mut pings = FormatRegistry::default()
+        .open(Path::new(path))
+        .and_then(|source| source.collect::<io::Result<Vec<Ping>>>())
+        .map_err(io_error)?;
+    compute_motion(&mut pings, &MotionConfig::default());
+    if let Some(expr) = &filter {
+        pings.retain(|p| expr.matches(p));
+    }
+    let selected = channel_pings(&pings, channel_id);
+
+    let list = PyList::empty(py);
+    for segment in split_range_segments(&selected) {
+        let dict = PyDict::new(py);
+        dict.set_item("start", segment.start)?;
+        dict.set_item("end", segment.end)?;
+        dict.set_item("start_time", selected[segment.start].timestamp)?;
+        dict.set_item("end_time", selected[segment.end - 1].timestamp)?;
+        dict.set_item("range_m", segment.range_m)?;
+        list.append(dict)?;
+    }
+    Ok(list.to_object(py))
+}
+
+/// Recording summary as a dict; `error` is set for files that could not be read
+fn summary_to_dict<'py>(py: Python<'py>, s: &RecordingSummary) -> PyResult<&'py PyDict> {
+    let dict = PyDict::new(py);
+    dict.set_item("path", s.path.to_string_lossy())?;
+    dict.set_item("format", &s.format)?;
+    dict.set_item("ping_count", s.ping_count)?;
+    dict.set_item("channels", &s.channels)?;
+    dict.set_item("start_time", s.start_time)?;
+    dict.set_item("end_time", s.end_time)?;
+    dict.set_item("bbox", s.bbox)?;
+    dict.set_item("min_depth_m", s.min_depth_m)?;
+    dict.set_item("max_depth_m", s.max_depth_m)?;
+    dict.set_item("error", &s.error)?;
+    Ok(dict)
+}
+
+/// Catalog entry as a dict of its summary fields plus `size` and `sha256`
+fn catalog_entry_to_dict<'py>(py: Python<'py>, entry: &CatalogEntry) -> PyResult<&'py PyDict> {
+    let dict = summary_to_dict(py, &entry.summary)?;
+    dict.set_item("size", entry.size)?;
+    dict.set_item("sha256", &entry.sha256)?;
+    Ok(dict)
+}
+
+/// Hash and summarize every recording under `directory` as a list of dicts
+///
+/// With `db`, the catalog is also saved there as an SQLite database for
+/// [`query_catalog`] or any SQLite client.
+#[pyfunction]
+#[pyo3(signature = (directory, db = None, pattern = "*", recursive = true))]
+pub fn catalog_directory(
+    py: Python<'_>,
+    directory: &str,
+    db: Option<&str>,
+    pattern: &str,
+    recursive: bool,
+) -> PyResult<PyObject> {
+    let _logs = LogFlush(py);
+    let options = BatchOptions {
+        pattern: pattern.to_string(),
+        recursive,
+        ..BatchOptions::default()
+    };
+    let entries = py
+        .allow_threads(|| scan_catalog(Path::new(directory), &FormatRegistry::default(), &options))
+        .map_err(io_error)?;
+    if let Some(db) = db {
+        write_catalog_db(db, &entries).map_err(io_error)?;
+    }
+    let list = PyList::empty(py);
+    for entry in &entries {
+        list.append(catalog_entry_to_dict(py, entry)?)?;
+    }
+    Ok(list.to_object(py))
+}
+
+/// Parse and summarize every recording under `directory`
+///
+/// Returns a dict with `summaries`, one dict per file (`error` is set for
+/// files that could not be read), and `track`, every `track_decimation`-th
+/// positioned ping across all files as time-ordered `(timestamp, latitude,
+/// longitude)` tuples.
+#[pyfunction]
+#[pyo3(signature = (directory, pattern = "*", recursive = true, track_decimation = 10))]
+pub fn process_directory(
+    py: Python<'_>,
+    directory: &str,
+    pattern: &str,
+    recursive: bool,
+    track_decimation: usize,
+) -> PyResult<PyObject> {
+    let _logs = LogFlush(py);
+    let options = BatchOptions {
+        pattern: pattern.to_string(),
+        recursive,
+        track_decimation,
+        ..BatchOptions::default()
+    };
+    let result = py
+        .allow_threads(|| process_batch(Path::new(directory), &FormatRegistry::default(), &options))
+        .map_err(io_error)?;
+    let summaries = PyList::empty(py);
+    for summary in &result.summaries {
+        summaries.append(summary_to_dict(py, summary)?)?;
+    }
+    let dict = PyDict::new(py);
+    dict.set_item("summaries", summaries)?;
+    dict.set_item("track", result.track)?;
+    Ok(dict.to_object(py))
+}
+
+/// Group the recordings under `directory` into outings
+///
+/// Consecutive files join one session when the next starts within
+/// `max_gap_s` of the previous file's end and within `max_jump_m` of its
+/// last position. Returns a list of dicts with `files`, in time order, and
+/// `summary`, the combined summary. Unreadable files are left out.
+#[pyfunction]
+#[pyo3(signature = (directory, pattern = "*", recursive = true, max_gap_s = 1800.0, max_jump_m = 1000.0))]
+pub fn detect_sessions(
+    py: Python<'_>,
+    directory: &str,
+    pattern: &str,
+    recursive: bool,
+    max_gap_s: f64,
+    max_jump_m: f64,
+) -> PyResult<PyObject> {
+    let _logs = LogFlush(py);
+    let options = BatchOptions {
+        pattern: pattern.to_string(),
+        recursive,
+        ..BatchOptions::default()
+    };
+    let config = SessionConfig { max_gap_s, max_jump_m };
+    let sessions = py
+        .allow_threads(|| find_sessions(Path::new(directory), &FormatRegistry::default(), &options, &config))
+        .map_err(io_error)?;
+    let list = PyList::empty(py);
+    for session in &sessions {
+        let dict = PyDict::new(py);
+        let files: Vec<String> = session.files.iter().map(|f| f.display().to_string()).collect();
+        dict.set_item("files", files)?;
+        dict.set_item("summary", summary_to_dict(py, &session.summary)?)?;
+        list.append(dict)?;
+    }
+    Ok(list.to_object(py))
+}
+
+/// Entries of a saved catalog matching every given condition
+///
+/// Recordings overlapping `time_from..time_to` whose bounding box meets
+/// `polygon`, a list of `(lat, lon)` vertices, and that contain
+/// `channel_id`. Unreadable files are left out unless `include_failed`.
+#[pyfunction]
+#[pyo3(signature = (db, time_from = None, time_to = None, polygon = None, channel_id = None, include_failed = false))]
+pub fn query_catalog(
+    py: Python<'_>,
+    db: &str,
+    time_from: Option<f64>,
+    time_to: Option<f64>,
+    polygon: Option<Vec<(f64, f64)>>,
+    channel_id: Option<u16>,
+    include_failed: bool,
+) -> PyResult<PyObject> {
+    let entries = read_catalog_db(db).map_err(io_error)?;
+    let query = CatalogQuery {
+        time_from,
+        time_to,
+        polygon,
+        channel_id,
+        include_failed,
+    };
+    let list = PyList::empty(py);
+    for entry in filter_catalog(&entries, &query) {
+        list.append(catalog_entry_to_dict(py, entry)?)?;
+    }
+    Ok(list.to_object(py))
+}
+
+/// Whole recording held in memory; shows a mini-map and summary in notebooks
+#[pyclass]
+pub struct Recording {
+    summary: RecordingSummary,
+    channels: Vec<ChannelInfo>,
+    pings: Vec<Ping>,
+    /// R-tree over positioned pings with a depth, and each sounding's ping index
+    index: OnceLock<(SoundingIndex, Vec<usize>)>,
+    /// Sample matrices already handed out, by channel
+    sample_blocks: Mutex<HashMap<u16, Arc<SampleMatrix>>>,
+}
+
+impl Recording {
+    fn sounding_index(&self) -> &(SoundingIndex, Vec<usize>) {
+        self.index.get_or_init(|| {
+            let (soundings, ping_indices): (Vec<_>, Vec<_>) = self
+                .pings
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| (p.lat != 0.0 || p.lon != 0.0) && p.depth_m > 0.0)
+                .map(|(i, p)| (p.to_sounding(), i))
+                .unzip();
+            (SoundingIndex::build(&soundings), ping_indices)
+        })
+    }
+}
+
+#[pymethods]
+impl Recording {
+    fn __len__(&self) -> usize {
+        self.pings.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Recording('{}', format='{}', pings={})",
+            self.summary.path.display(),
+            self.summary.format,
+            self.pings.len()
+        )
+    }
+
+    /// Overview as a dict: format, counts, time span, bounds and depth range
+    #[getter]
+    fn summary(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let s = &self.summary;
+        let dict = PyDict::new(py);
+        dict.set_item("path", s.path.to_string_lossy())?;
+        dict.set_item("format", &s.format)?;
+        dict.set_item("ping_count", s.ping_count)?;
+        dict.set_item("channels", &s.channels)?;
+        dict.set_item("start_time", s.start_time)?;
+        dict.set_item("end_time", s.end_time)?;
+        dict.set_item("bbox", s.bbox)?;
+        dict.set_item("min_depth_m", s.min_depth_m)?;
+        dict.set_item("max_depth_m", s.max_depth_m)?;
+        Ok(dict.to_object(py))
+    }
+
+    /// Pings of one channel, or all pings, as a list of dicts
+    #[pyo3(signature = (channel_id = None))]
+    fn pings(&self, py: Python<'_>, channel_id: Option<u16>) -> PyResult<PyObject> {
+        let list = PyList::empty(py);
+        for ping in self
+            .pings
+            .iter()
+            .filter(|p| channel_id.is_none_or(|c| p.channel_id == c))
+        {
+            list.append(ping_to_dict(py, ping)?)?;
+        }
+        Ok(list.to_object(py))
+    }
+
+    /// Indices into `pings()` of soundings within every given bound
+    ///
+    /// Only positioned pings with a depth are considered. `bbox` is
+    /// `(min_lat, min_lon, max_lat, max_lon)` and is answered from an R-tree
+    /// built on first use.
+    #[pyo3(signature = (depth_lt = None, depth_gt = None, time_from = None, time_to = None, bbox = None))]
+    fn records_where(
+        &self,
+        py: Python<'_>,
+        depth_lt: Option<f64>,
+        depth_gt: Option<f64>,
+        time_from: Option<f64>,
+        time_to: Option<f64>,
+        bbox: Option<(f64, f64, f64, f64)>,
+    ) -> Vec<usize> {
+        let query = SoundingQuery {
+            depth_lt,
+            depth_gt,
+            time_from,
+            time_to,
+            bbox,
+        };
+        py.allow_threads(|| {
+            let (index, ping_indices) = self.sounding_index();
+            records_where_indexed(index, &query)
+                .into_iter()
+                .map(|i| ping_indices[i])
+                .collect()
+        })
+    }
+
+    /// Indices into `pings()` of the `n` shallowest soundings, shallowest first
+    fn shallowest(&self, py: Python<'_>, n: usize) -> Vec<usize> {
+        py.allow_threads(|| {
+            let (index, ping_indices) = self.sounding_index();
+            shallowest_soundings(index.soundings(), n)
+                .into_iter()
+                .map(|i| ping_indices[i])
+                .collect()
+        })
+    }
+
+    /// One channel's samples as a matrix that numpy can wrap without copying
+    ///
+    /// The matrix is built on the first call per channel; later calls and
+    /// every buffer exported from them share it.
+    fn samples(&self, py: Python<'_>, channel_id: u16) -> SampleBlock {
+        let matrix = py.allow_threads(|| {
+            lock(&self.sample_blocks)
+                .entry(channel_id)
+                .or_insert_with(|| Arc::new(SampleMatrix::from_pings(&channel_pings(&self.pings, channel_id))))
+                .clone()
+        });
+        SampleBlock { matrix }
+    }
+
+    /// Archive the loaded pings as a compact SSF file, readable by `load`
+    ///
+    /// `compress=True` also LZ-compresses each column (SSF version 3).
+    #[pyo3(signature = (path, compress = false))]
+    fn save_ssf(&self, path: PathBuf, compress: bool) -> PyResult<()> {
+        let write = if compress { write_ssf_compressed } else { write_ssf };
+        write(path, &self.channels, &self.pings).map_err(io_error)
+    }
+
+    /// Write the loaded pings as an Arrow IPC stream of `batch_size`-row record batches
+    ///
+    /// Open it with `pyarrow.ipc.open_stream` to get columns without per-ping objects.
+    #[pyo3(signature = (path, batch_size = 10000))]
+    fn save_arrow(&self, path: PathBuf, batch_size: usize) -> PyResult<()> {
+        write_arrow(path, &self.pings, batch_size).map_err(io_error)
+    }
+
+    /// Copy with attitude, water temperature, water speed and engine readings from an NMEA 2000 candump log
+    ///
+    /// Each ping takes the nearest reading no more than `max_gap_s` away; the
+    /// values appear in `pings()` as `pitch_deg`, `roll_deg`, `water_temp_c`,
+    /// `stw_knots`, `engine_rpm`, `battery_volts` and `air_temp_c`.
+    #[pyo3(signature = (path, max_gap_s = 2.0, mode = "lenient"))]
+    fn with_nmea2000(&self, py: Python<'_>, path: PathBuf, max_gap_s: f64, mode: &str) -> PyResult<Recording> {
+        let mode = parse_mode(mode)?;
+        let _logs = LogFlush(py);
+        let log = py.allow_threads(|| read_candump(&path, mode)).map_err(io_error)?;
+        let mut pings = self.pings.clone();
+        log.fuse_sensors(&mut pings, max_gap_s);
+        Ok(Recording {
+            summary: self.summary.clone(),
+            channels: self.channels.clone(),
+            pings,
+            index: OnceLock::new(),
+            sample_blocks: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Current set and drift along the track, e.g. after `with_nmea2000`
+    ///
+    /// Compares ground velocity (`cog_deg`, `sog_knots`) with water velocity
+    /// (`heading_deg`, `stw_knots`) averaged over `window_s`. Returns a dict
+    /// of equal-length lists (`time`, `lat`, `lon`, `set_deg`, `drift_knots`);
+    /// pings without speed through water are left out.
+    #[pyo3(signature = (window_s = 60.0))]
+    fn estimate_current(&self, py: Python<'_>, window_s: f64) -> PyResult<PyObject> {
+        let currents = py.allow_threads(|| estimate_current(&self.pings, window_s));
+        let dict = PyDict::new(py);
+        dict.set_item("time", currents.iter().map(|c| c.timestamp).collect::<Vec<_>>())?;
+        dict.set_item("lat", currents.iter().map(|c| c.lat).collect::<Vec<_>>())?;
+        dict.set_item("lon", currents.iter().map(|c| c.lon).collect::<Vec<_>>())?;
+        dict.set_item("set_deg", currents.iter().map(|c| c.set_deg).collect::<Vec<_>>())?;
+        dict.set_item("drift_knots", currents.iter().map(|c| c.drift_knots).collect::<Vec<_>>())?;
+        Ok(dict.to_object(py))
+    }
+
+    /// Copy safe to share: positions and times redacted, channel names replaced
+    ///
+    /// `positions` is `"keep"`, `"relocate"` (centroid moved to 0°N 0°E) or
+    /// `"fuzz"` (shifted, rotated and jittered by the given amounts);
+    /// `times` is `"keep"`, `"zero"` or `"shift"` by `time_shift_s`. The
+    /// jitter seed is random unless `seed` is given. Save the result with
+    /// `save_ssf` or export it as usual.
+    #[pyo3(signature = (
+        positions = "relocate",
+        times = "zero",
+        offset_east_m = 0.0,
+        offset_north_m = 0.0,
+        rotate_deg = 0.0,
+        jitter_m = 0.0,
+        time_shift_s = None,
+        seed = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn anonymized(
+        &self,
+        positions: &str,
+        times: &str,
+        offset_east_m: f64,
+        offset_north_m: f64,
+        rotate_deg: f64,
+        jitter_m: f64,
+        time_shift_s: Option<f64>,
+        seed: Option<u64>,
+    ) -> PyResult<Recording> {
+        let positions = match positions {
+            "keep" => PositionRedaction::Keep,
+            "relocate" => PositionRedaction::Relocate,
+            "fuzz" => PositionRedaction::Fuzz {
+                offset_east_m,
+                offset_north_m,
+                rotate_deg,
+                jitter_m,
+            },
+            other => return Err(PyValueError::new_err(format!("unknown positions '{}'", other))),
+        };
+        let times = match (times, time_shift_s) {
+            ("keep", _) => TimeRedaction::Keep,
+            ("zero", _) => TimeRedaction::ZeroBased,
+            ("shift", Some(seconds)) => TimeRedaction::Shift(seconds),
+            ("shift", None) => return Err(PyValueError::new_err("times=\"shift\" needs time_shift_s")),
+            (other, _) => return Err(PyValueError::new_err(format!("unknown times '{}'", other))),
+        };
+        let config = AnonymizeConfig {
+            positions,
+            times,
+            seed: seed.unwrap_or_else(|| AnonymizeConfig::default().seed),
+        };
+        let pings = anonymize_pings(&self.pings, &config);
+        Ok(Recording {
+            summary: RecordingSummary::from_pings(&self.summary.path, &self.summary.format, &pings),
+            channels: anonymize_channels(&self.channels),
+            pings,
+            index: OnceLock::new(),
+            sample_blocks: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Track mini-map, sonar strip and depth profile in one image
+    #[pyo3(signature = (width = 480, height = 240))]
+    fn quicklook(&self, width: usize, height: usize) -> SonarImage {
+        let config = QuicklookConfig {
+            width,
+            height,
+            ..Default::default()
+        };
+        let image = render_quicklook(&self.pings, &self.channels, &config, &auto_settings(&self.pings));
+        SonarImage {
+            image: PlotImage::from_quicklook(image),
+        }
+    }
+
+    /// Waterfall of one channel, one row per ping, with stretched levels
+    #[pyo3(signature = (channel_id, width = 512))]
+    fn waterfall(&self, channel_id: u16, width: usize) -> SonarImage {
+        let selected = channel_pings(&self.pings, channel_id);
+        SonarImage {
+            image: waterfall_plot(&selected, width, &auto_settings(&self.pings)),
+        }
+    }
+
+    /// Track arrays ready for any plotting library
+    ///
+    /// Returns a dict with float64 arrays `time`, `lon`, `lat` and `depth_m`
+    /// (NaN where unknown) of one channel's positioned pings, plus `extent`
+    /// as `(west, east, south, north)` or None without positions. Plot
+    /// `lon` as x and `lat` as y.
+    fn plot_data(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let track = PlotTrack::from_pings(&self.pings);
+        let dict = PyDict::new(py);
+        dict.set_item("extent", track.extent().map(|[w, e, s, n]| (w, e, s, n)))?;
+        dict.set_item("time", PyArray1::from_vec(py, track.time))?;
+        dict.set_item("lon", PyArray1::from_vec(py, track.lon))?;
+        dict.set_item("lat", PyArray1::from_vec(py, track.lat))?;
+        dict.set_item("depth_m", PyArray1::from_vec(py, track.depth_m))?;
+        Ok(dict.to_object(py))
+    }
+
+    /// Summary card with a track mini-map, shown by notebooks
+    fn _repr_html_(&self) -> PyResult<String> {
+        summary_card(&self.summary, &self.pings, REPR_MAP_SIZE).map_err(io_error)
+    }
+
+    /// Default-size `quicklook` as PNG, for frontends that do not render HTML
+    fn _repr_png_(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.quicklook(480, 240)._repr_png_(py)
+    }
+}
+
+/// One channel's samples as a read-only `(pings, samples)` uint8 matrix
+///
+/// Exposes the buffer protocol, so `numpy.asarray(block)` and
+/// `memoryview(block)` share this memory rather than copying it. Pings with
+/// fewer samples are zero-padded to the longest.
+#[pyclass]
+pub struct SampleBlock {
+    matrix: Arc<SampleMatrix>,
+}
+
+/// Padded samples behind a [`SampleBlock`], shared with its recording
+struct SampleMatrix {
+    data: Vec<u8>,
+    /// `[rows, cols]`; exported buffers point at these
+    shape: [isize; 2],
+    strides: [isize; 2],
+    timestamps: Vec<f64>,
+    ranges: Vec<f64>,
+}
+
+impl SampleMatrix {
+    fn from_pings(pings: &[&Ping]) -> Self {
+        let cols = pings.iter().map(|p| p.samples.len()).max().unwrap_or(0);
+        let mut data = vec![0u8; pings.len() * cols];
+        for (row, ping) in pings.iter().enumerate() {
+            data[row * cols..row * cols + ping.samples.len()].copy_from_slice(&ping.samples);
+        }
+        Self {
+            data,
+            shape: [pings.len() as isize, cols as isize],
+            strides: [cols as isize, 1],
+            timestamps: pings.iter().map(|p| p.timestamp).collect(),
+            ranges: pings.iter().map(|p| p.range_m).collect(),
+        }
+    }
+}
+
+#[pymethods]
+impl SampleBlock {
+    fn __len__(&self) -> usize {
+        self.matrix.timestamps.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SampleBlock({}x{})", self.matrix.shape[0], self.matrix.shape[1])
+    }
+
+    /// `(pings, samples)`
+    #[getter]
+    fn shape(&self) -> (usize, usize) {
+        (self.matrix.shape[0] as usize, self.matrix.shape[1] as usize)
+    }
+
+    /// Ping times, one per row
+    #[getter]
+    fn timestamps(&self, py: Python<'_>) -> PyObject {
+        PyArray1::from_slice(py, &self.matrix.timestamps).to_object(py)
+    }
+
+    /// Range covered by each row's samples; padding lies beyond it
+    #[getter]
+    fn range_m(&self, py: Python<'_>) -> PyObject {
+        PyArray1::from_slice(py, &self.matrix.ranges).to_object(py)
+    }
+
+    unsafe fn __getbuffer__(slf: &PyCell<Self>, view: *mut ffi::Py_buffer, flags: c_int) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("View is null"));
+        }
+        if (flags & ffi::PyBUF_WRITABLE) == ffi::PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("sample blocks are read-only"));
+        }
+        // The matrix is never mutated after construction, and the view keeps
+        // a reference to the block, which holds the matrix, so the pointers stay valid
+        let block = &slf.borrow().matrix;
+        (*view).obj = ffi::_Py_NewRef(slf.as_ptr());
+        (*view).buf = block.data.as_ptr() as *mut c_void;
+        (*view).len = block.data.len() as isize;
+        (*view).readonly = 1;
+        (*view).itemsize = 1;
+        (*view).format = if (flags & ffi::PyBUF_FORMAT) == ffi::PyBUF_FORMAT {
+            FORMAT_U8.as_ptr() as *mut c_char
+        } else {
+            ptr::null_mut()
+        };
+        if (flags & ffi::PyBUF_ND) == ffi::PyBUF_ND {
+            (*view).ndim = 2;
+            (*view).shape = block.shape.as_ptr() as *mut isize;
+        } else {
+            (*view).ndim = 1;
+            (*view).shape = ptr::null_mut();
+        }
+        (*view).strides = if (flags & ffi::PyBUF_STRIDES) == ffi::PyBUF_STRIDES {
+            block.strides.as_ptr() as *mut isize
+        } else {
+            ptr::null_mut()
+        };
+        (*view).suboffsets = ptr::null_mut();
+        (*view).internal = ptr::null_mut();
+        Ok(())
+    }
+
+    unsafe fn __releasebuffer__(&self, _view: *mut ffi::Py_buffer) {
+        // Nothing was allocated for the view
+    }
+}
+
+/// Rendered RGB image; displays inline in notebooks
+#[pyclass]
+pub struct SonarImage {
+    image: PlotImage,
+}
+
+#[pymethods]
+impl SonarImage {
+    fn __repr__(&self) -> String {
+        format!("SonarImage({}x{})", self.image.width, self.image.height)
+    }
+
+    /// Width in pixels
+    #[getter]
+    fn width(&self) -> usize {
+        self.image.width
+    }
+
+    /// Height in pixels
+    #[getter]
+    fn height(&self) -> usize {
+        self.image.height
+    }
+
+    /// Pixels and axes ready for any plotting library
+    ///
+    /// Returns a dict with `image`, a `(height, width, 3)` uint8 array whose
+    /// row 0 is the top; `extent` as `(left, right, bottom, top)` in data
+    /// units; `origin` (always `"upper"`); and `xlabel`/`ylabel` naming the
+    /// units. With matplotlib this is `imshow(d["image"], extent=d["extent"],
+    /// origin=d["origin"])`.
+    fn plot_data(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let image = &self.image;
+        let pixels = PyArray1::from_vec(py, image.rgb.clone()).reshape([image.height, image.width, 3])?;
+        let [left, right, bottom, top] = image.extent;
+        let dict = PyDict::new(py);
+        dict.set_item("image", pixels)?;
+        dict.set_item("extent", (left, right, bottom, top))?;
+        dict.set_item("origin", "upper")?;
+        dict.set_item("xlabel", image.x_label)?;
+        dict.set_item("ylabel", image.y_label)?;
+        Ok(dict.to_object(py))
+    }
+
+    /// PNG file contents as bytes
+    fn to_png(&self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(PyBytes::new(py, &self.encode_png()?).to_object(py))
+    }
+
+    /// Write the image to `path` as a PNG file
+    fn save(&self, path: PathBuf) -> PyResult<()> {
+        std::fs::write(path, self.encode_png()?).map_err(io_error)
+    }
+
+    /// Same as `to_png`, so notebooks display the image inline
+    fn _repr_png_(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.to_png(py)
+    }
+}
+
+impl SonarImage {
+    fn encode_png(&self) -> PyResult<Vec<u8>> {
+        let mut png = Vec::new();
+        let image = &self.image;
+        write_png(&mut png, image.width, image.height, PngColor::Rgb, &image.rgb).map_err(io_error)?;
+        Ok(png)
+    }
+}
+
+/// Read a whole recording into memory for interactive use
+///
+/// Reader anomalies are raised as `UserWarning` unless `warn` is false.
+/// `mode="strict"` raises `ParseError` on the first malformed record instead
+/// of skipping it; files in no known format raise `UnsupportedFormatError`.
+#[pyfunction]
+#[pyo3(signature = (path, filter = None, warn = true, mode = "lenient"))]
+pub fn load(py: Python<'_>, path: &str, filter: Option<&str>, warn: bool, mode: &str) -> PyResult<Recording> {
+    let _logs = LogFlush(py);
+    let filter = filter
+        .map(FilterExpr::parse)
+        .transpose()
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    let registry = FormatRegistry::default().with_mode(parse_mode(mode)?);
+    let path = Path::new(path);
+    let (result, warnings) = collect_warnings(|| {
+        let parser = registry
+            .detect(path)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "unrecognized sonar format"))?;
+        let source = open_with(parser, path, registry.mode())?;
+        let channels = source.channels();
+        let pings = source.collect::<io::Result<Vec<Ping>>>()?;
+        Ok::<_, io::Error>((parser.name().to_string(), channels, pings))
+    });
+    if warn {
+        emit_warnings(py, &warnings)?;
+    }
+    let (format, channels, mut pings) = result.map_err(io_error)?;
+    compute_motion(&mut pings, &MotionConfig::default());
+    if let Some(expr) = &filter {
+        pings.retain(|p| expr.matches(p));
+    }
+    Ok(Recording {
+        summary: RecordingSummary::from_pings(path, &format, &pings),
+        channels,
+        pings,
+        index: OnceLock::new(),
+        sample_blocks: Mutex::new(HashMap::new()),
+    })
+}
+
+/// Run the processing pipeline described by a TOML file
+///
+/// Paths in the file are relative to it. Returns a dict with `recordings`
+/// (one summary dict per file read), `sounding_count`, `unreduced_count`
+/// and `written`, the paths of the products written. An invalid pipeline
+/// file raises `ParseError`.
+#[pyfunction]
+#[pyo3(signature = (path, mode = "lenient"))]
+pub fn run_pipeline(py: Python<'_>, path: &str, mode: &str) -> PyResult<PyObject> {
+    let _logs = LogFlush(py);
+    let registry = FormatRegistry::default().with_mode(parse_mode(mode)?);
+    let pipeline = Pipeline::load(path).map_err(io_error)?;
+    let report = py
+        .allow_threads(|| execute_pipeline(&pipeline, &registry))
+        .map_err(io_error)?;
+
+    let recordings = PyList::empty(py);
+    for summary in &report.recordings {
+        recordings.append(summary_to_dict(py, summary)?)?;
+    }
+    let dict = PyDict::new(py);
+    dict.set_item("recordings", recordings)?;
+    dict.set_item("sounding_count", report.sounding_count)?;
+    dict.set_item("unreduced_count", report.unreduced_count)?;
+    let written: Vec<_> = report.written.iter().map(|p| p.to_string_lossy()).collect();
+    dict.set_item("written", written)?;
+    Ok(dict.to_object(py))
+}
+
+/// Palette defaults with histogram-stretched levels per channel
+fn auto_settings(pings: &[Ping]) -> ImagingSettings {
+    let mut settings = ImagingSettings::default();
+    settings.auto_levels(pings);
+    settings
+}
+
+fn html_table(title: &str, rows: &[(&str, String)]) -> String {
+    let body: String = rows
+        .iter()
+        .map(|(label, value)| format!("<tr><th>{}</th><td>{}</td></tr>", label, escape_xml(value)))
+        .collect();
+    format!(
+        "<table><tr><th colspan=\"2\">{}</th></tr>{}</table>",
+        escape_xml(title),
+        body
+    )
+}
+
+/// Add the sonar classes and functions to the extension module
+pub fn register(m: &PyModule) -> PyResult<()> {
+    m.add("ParseError", m.py().get_type::<ParseError>())?;
+    m.add("UnsupportedFormatError", m.py().get_type::<UnsupportedFormatError>())?;
+    m.add_class::<PingBatches>()?;
+    m.add_class::<Recording>()?;
+    m.add_class::<SampleBlock>()?;
+    m.add_class::<SonarImage>()?;
+    m.add_function(wrap_pyfunction!(load, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_batches, m)?)?;
+    m.add_function(wrap_pyfunction!(validate, m)?)?;
+    m.add_function(wrap_pyfunction!(field_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(resample, m)?)?;
+    m.add_function(wrap_pyfunction!(depth_profile, m)?)?;
+    m.add_function(wrap_pyfunction!(grid_depths, m)?)?;
+    m.add_function(wrap_pyfunction!(range_segments, m)?)?;
+    m.add_function(wrap_pyfunction!(set_log_level, m)?)?;
+    m.add_function(wrap_pyfunction!(catalog_directory, m)?)?;
+    m.add_function(wrap_pyfunction!(process_directory, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_sessions, m)?)?;
+    m.add_function(wrap_pyfunction!(query_catalog, m)?)?;
+    m.add_function(wrap_pyfunction!(run_pipeline, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(timestamp: f64, samples: Vec<u8>) -> Ping {
+        Ping {
+            timestamp,
+            range_m: samples.len() as f64,
+            samples,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sample_matrices_pad_short_pings() {
+        let pings = [ping(1.0, vec![1, 2, 3]), ping(2.0, vec![4]), ping(3.0, vec![5, 6, 7, 8])];
+        let block = SampleMatrix::from_pings(&pings.iter().collect::<Vec<_>>());
+        assert_eq!((block.shape, block.strides), ([3, 4], [4, 1]));
+        assert_eq!(block.data, [1, 2, 3, 0, 4, 0, 0, 0, 5, 6, 7, 8]);
+        assert_eq!((block.timestamps, block.ranges), (vec![1.0, 2.0, 3.0], vec![3.0, 1.0, 4.0]));
+
+        let empty = SampleMatrix::from_pings(&[]);
+        assert_eq!((empty.shape, empty.data.len()), ([0, 0], 0));
+    }
+
+    // Embeds an interpreter, which needs libpython linked in
+    #[cfg(not(feature = "extension-module"))]
+    #[test]
+    fn sample_blocks_export_a_read_only_matrix() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let pings = [ping(1.0, vec![1, 2]), ping(2.0, vec![3])];
+            let matrix = Arc::new(SampleMatrix::from_pings(&pings.iter().collect::<Vec<_>>()));
+            let block = Py::new(py, SampleBlock { matrix }).unwrap();
+            let locals = PyDict::new(py);
+            locals.set_item("block", block).unwrap();
+            py.run("m = memoryview(block)", None, Some(locals)).unwrap();
+            let eval = |expr: &str| py.eval(expr, None, Some(locals)).unwrap();
+            let layout: ((usize, usize), (usize, usize)) = eval("(m.shape, m.strides)").extract().unwrap();
+            assert_eq!(layout, ((2, 2), (2, 1)));
+            assert_eq!(eval("m.format").extract::<String>().unwrap(), "B");
+            assert!(eval("m.readonly").extract::<bool>().unwrap());
+            assert_eq!(eval("m.tobytes()").extract::<Vec<u8>>().unwrap(), [1, 2, 3, 0]);
+            // Writable views are refused
+            assert!(py.run("import ctypes; (ctypes.c_char * 4).from_buffer(block)", None, Some(locals)).is_err());
+        });
+    }
+}
@@ -0,0 +1,174 @@
+// Quality checks comparing recorded depth with the water surface level
+// src/qc.rs
+
+use crate::dedup::{bin_soundings_at, median, south_west};
+use crate::parsers::nmea0183::NavFix;
+use crate::survey::Sounding;
+use std::collections::BTreeMap;
+
+/// Settings for the depth vs. water level consistency check
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelCheckConfig {
+    /// Cell size used to pair soundings between sessions
+    pub cell_size_m: f64,
+    /// Largest accepted gap between depth change and level change
+    pub tolerance_m: f64,
+    /// Fewest shared cells needed to judge a session
+    pub min_shared_cells: usize,
+}
+
+impl Default for LevelCheckConfig {
+    fn default() -> Self {
+        Self {
+            cell_size_m: 10.0,
+            tolerance_m: 0.5,
+            min_shared_cells: 5,
+        }
+    }
+}
+
+/// One survey session of a reservoir with its water surface elevation
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelSession {
+    pub name: String,
+    pub soundings: Vec<Sounding>,
+    /// Surface elevation from GPS altitude or a gauge reading
+    pub water_level_m: f64,
+}
+
+/// Result of comparing a session with the reference (first) session
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelCheck {
+    pub name: String,
+    pub water_level_m: f64,
+    /// Level change relative to the reference session
+    pub level_change_m: f64,
+    /// Median depth change over cells covered by both sessions
+    pub depth_change_m: Option<f64>,
+    pub shared_cells: usize,
+    /// Depth change minus level change; the bottom itself should not move
+    pub discrepancy_m: Option<f64>,
+    /// Depth did not follow the level change, e.g. tracking a false bottom
+    pub flagged: bool,
+}
+
+/// Water surface elevation of a session from the median GPS altitude
+pub fn water_level_from_fixes(fixes: &[NavFix]) -> Option<f64> {
+    let mut altitudes: Vec<f64> = fixes.iter().filter_map(|f| f.altitude_m).collect();
+    if altitudes.is_empty() {
+        None
+    } else {
+        Some(median(&mut altitudes))
+    }
+}
+
+/// Check that depth changes between sessions match their water level changes
+///
+/// A rising reservoir should read deeper by the same amount everywhere.
+/// Sessions whose median depth change disagrees by more than the tolerance
+/// are flagged; sessions sharing too few cells with the reference are
+/// reported without a verdict.
+pub fn check_level_consistency(sessions: &[LevelSession], config: &LevelCheckConfig) -> Vec<LevelCheck> {
+    let Some(reference) = sessions.first() else {
+        return Vec::new();
+    };
+    let all: Vec<Sounding> = sessions.iter().flat_map(|s| s.soundings.iter().copied()).collect();
+    let Some(anchor) = south_west(&all) else {
+        return Vec::new();
+    };
+    let cell_depths = |soundings: &[Sounding]| {
+        bin_soundings_at(soundings, anchor, config.cell_size_m)
+            .into_iter()
+            .map(|(key, indices)| {
+                let mut depths: Vec<f64> = indices.iter().map(|&i| soundings[i].depth_m).collect();
+                (key, median(&mut depths))
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let reference_cells = cell_depths(&reference.soundings);
+
+    sessions
+        .iter()
+        .map(|session| {
+            let cells = cell_depths(&session.soundings);
+            let mut changes: Vec<f64> = cells
+                .iter()
+                .filter_map(|(key, depth)| Some(depth - reference_cells.get(key)?))
+                .collect();
+            let level_change_m = session.water_level_m - reference.water_level_m;
+            let depth_change_m = (changes.len() >= config.min_shared_cells).then(|| median(&mut changes));
+            let discrepancy_m = depth_change_m.map(|d| d - level_change_m);
+
+            LevelCheck {
+                name: session.name.clone(),
+                water_level_m: session.water_level_m,
+                level_change_m,
+                depth_change_m,
+                shared_cells: changes.len(),
+                discrepancy_m,
+                flagged: discrepancy_m.is_some_and(|d| d.abs() > config.tolerance_m),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::offset_position;
+
+    /// Eight 10 m cells along a line, sloping from 4 m to 7.5 m, shifted by `change_m`
+    fn line(change_m: f64) -> Vec<Sounding> {
+        (0..8)
+            .map(|i| {
+                let (lat, lon) = offset_position(44.0, -63.0, i as f64 * 10.0 + 5.0, 15.0);
+                Sounding::new(i as f64, lat, lon, 4.0 + i as f64 * 0.5 + change_m)
+            })
+            .collect()
+    }
+
+    fn session(name: &str, water_level_m: f64, soundings: Vec<Sounding>) -> LevelSession {
+        LevelSession {
+            name: name.to_string(),
+            soundings,
+            water_level_m,
+        }
+    }
+
+    #[test]
+    fn flags_depths_that_ignore_a_level_change() {
+        // A lone sounding at the origin anchors the cells so the line runs through their centres
+        let mut reference = line(0.0);
+        reference.push(Sounding::new(0.0, 44.0, -63.0, 1.0));
+        let sessions = [
+            session("spring", 100.0, reference),
+            session("risen", 101.0, line(1.0)),
+            session("false bottom", 101.0, line(0.0)),
+            session("elsewhere", 99.0, line(-1.0)[..2].to_vec()),
+        ];
+        let checks = check_level_consistency(&sessions, &LevelCheckConfig::default());
+        assert_eq!(checks.len(), 4);
+
+        assert_eq!((checks[0].shared_cells, checks[0].discrepancy_m, checks[0].flagged), (9, Some(0.0), false));
+        let risen = &checks[1];
+        assert_eq!((risen.level_change_m, risen.shared_cells, risen.flagged), (1.0, 8, false));
+        assert!(risen.discrepancy_m.unwrap().abs() < 1e-9);
+        let stuck = &checks[2];
+        assert!(stuck.flagged);
+        assert!((stuck.discrepancy_m.unwrap() + 1.0).abs() < 1e-9);
+        // Too few shared cells for a verdict
+        let elsewhere = &checks[3];
+        assert_eq!((elsewhere.shared_cells, elsewhere.depth_change_m, elsewhere.flagged), (2, None, false));
+    }
+
+    #[test]
+    fn water_level_is_the_median_altitude() {
+        let fix = |altitude_m| NavFix {
+            altitude_m,
+            ..Default::default()
+        };
+        let fixes = [fix(Some(100.2)), fix(None), fix(Some(99.8)), fix(Some(140.0))];
+        assert_eq!(water_level_from_fixes(&fixes), Some(100.2));
+        assert_eq!(water_level_from_fixes(&[fix(None)]), None);
+    }
+}
@@ -0,0 +1,153 @@
+// Hazard-style queries over soundings
+// src/query.rs
+
+use crate::spatial::SoundingIndex;
+use crate::survey::Sounding;
+use rayon::prelude::*;
+
+/// Conjunction of optional bounds; unset bounds match everything
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SoundingQuery {
+    pub depth_lt: Option<f64>,
+    pub depth_gt: Option<f64>,
+    pub time_from: Option<f64>,
+    pub time_to: Option<f64>,
+    /// (min_lat, min_lon, max_lat, max_lon)
+    pub bbox: Option<(f64, f64, f64, f64)>,
+}
+
+impl SoundingQuery {
+    pub fn depth_lt(depth_m: f64) -> Self {
+        Self {
+            depth_lt: Some(depth_m),
+            ..Default::default()
+        }
+    }
+
+    pub fn matches(&self, s: &Sounding) -> bool {
+        self.depth_lt.is_none_or(|d| s.depth_m < d)
+            && self.depth_gt.is_none_or(|d| s.depth_m > d)
+            && self.time_from.is_none_or(|t| s.timestamp >= t)
+            && self.time_to.is_none_or(|t| s.timestamp <= t)
+            && self.bbox.is_none_or(|(min_lat, min_lon, max_lat, max_lon)| {
+                s.lat >= min_lat && s.lat <= max_lat && s.lon >= min_lon && s.lon <= max_lon
+            })
+    }
+}
+
+/// Indices of soundings matching the query, in input order
+pub fn records_where(soundings: &[Sounding], query: &SoundingQuery) -> Vec<usize> {
+    soundings
+        .par_iter()
+        .enumerate()
+        .filter(|(_, s)| query.matches(s))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Indices of indexed soundings matching the query, ascending
+///
+/// A bounding box is answered by the index's R-tree, so only soundings in
+/// nodes overlapping it are tested against the other bounds.
+pub fn records_where_indexed(index: &SoundingIndex, query: &SoundingQuery) -> Vec<usize> {
+    match query.bbox {
+        Some(bbox) => index
+            .within_bbox(bbox)
+            .into_iter()
+            .filter(|&i| query.matches(&index.soundings()[i]))
+            .collect(),
+        None => records_where(index.soundings(), query),
+    }
+}
+
+/// Indices of the `n` shallowest valid soundings, shallowest first
+pub fn shallowest(soundings: &[Sounding], n: usize) -> Vec<usize> {
+    let mut valid: Vec<usize> = (0..soundings.len())
+        .filter(|&i| soundings[i].depth_m.is_finite() && soundings[i].depth_m > 0.0)
+        .collect();
+    let by_depth = |a: &usize, b: &usize| soundings[*a].depth_m.total_cmp(&soundings[*b].depth_m);
+
+    if n < valid.len() {
+        if n == 0 {
+            return Vec::new();
+        }
+        valid.select_nth_unstable_by(n - 1, by_depth);
+        valid.truncate(n);
+    }
+    valid.sort_by(by_depth);
+    valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn soundings(n: usize) -> Vec<Sounding> {
+        let mut rng = StdRng::seed_from_u64(5);
+        (0..n)
+            .map(|i| {
+                let lat = 44.5 + rng.gen_range(0.0..0.02);
+                let lon = -63.5 + rng.gen_range(0.0..0.03);
+                Sounding::new(i as f64, lat, lon, rng.gen_range(0.5..30.0))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unset_bounds_match_everything() {
+        let data = soundings(50);
+        assert_eq!(
+            records_where(&data, &SoundingQuery::default()),
+            (0..50).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn bounds_combine_as_a_conjunction() {
+        let data = soundings(500);
+        let query = SoundingQuery {
+            depth_lt: Some(10.0),
+            time_from: Some(100.0),
+            bbox: Some((44.505, -63.49, 44.515, -63.48)),
+            ..Default::default()
+        };
+        let found = records_where(&data, &query);
+        assert!(!found.is_empty());
+        for (i, s) in data.iter().enumerate() {
+            let inside = s.lat >= 44.505 && s.lat <= 44.515 && s.lon >= -63.49 && s.lon <= -63.48;
+            assert_eq!(found.contains(&i), s.depth_m < 10.0 && s.timestamp >= 100.0 && inside);
+        }
+    }
+
+    #[test]
+    fn indexed_queries_agree_with_the_scan() {
+        let data = soundings(5000);
+        let index = SoundingIndex::build(&data);
+        let mut rng = StdRng::seed_from_u64(9);
+        for _ in 0..50 {
+            let (lat, lon) = (44.5 + rng.gen_range(0.0..0.02), -63.5 + rng.gen_range(0.0..0.03));
+            let query = SoundingQuery {
+                depth_gt: Some(rng.gen_range(0.0..20.0)),
+                bbox: Some((lat, lon, lat + 0.004, lon + 0.006)),
+                ..SoundingQuery::depth_lt(25.0)
+            };
+            assert_eq!(records_where_indexed(&index, &query), records_where(&data, &query));
+        }
+        let query = SoundingQuery::depth_lt(3.0);
+        assert_eq!(records_where_indexed(&index, &query), records_where(&data, &query));
+    }
+
+    #[test]
+    fn shallowest_skips_invalid_depths() {
+        let mut data = soundings(100);
+        data[3].depth_m = f64::NAN;
+        data[4].depth_m = 0.0;
+        data[7].depth_m = 0.1;
+        data[8].depth_m = 0.2;
+        assert_eq!(shallowest(&data, 2), vec![7, 8]);
+        assert_eq!(shallowest(&data, 500).len(), 98);
+        assert!(shallowest(&data, 0).is_empty());
+    }
+}
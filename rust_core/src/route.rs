@@ -0,0 +1,225 @@
+// Planned survey line comparison and cross-track error reporting
+// src/route.rs
+
+use crate::geo::local_offset_m;
+use crate::parsers::nmea0183::NavFix;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// One planned survey line as `(lat, lon)` vertices in running direction
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedLine {
+    pub name: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+impl PlannedLine {
+    /// Lines from named GPX routes, see [`crate::parsers::gpx::read_gpx_routes`]
+    pub fn from_routes(routes: &[(String, Vec<NavFix>)]) -> Vec<Self> {
+        routes
+            .iter()
+            .filter(|(_, fixes)| fixes.len() >= 2)
+            .map(|(name, fixes)| Self {
+                name: name.clone(),
+                points: fixes.iter().map(|f| (f.lat, f.lon)).collect(),
+            })
+            .collect()
+    }
+
+    pub fn length_m(&self) -> f64 {
+        self.segments().map(|(_, _, len)| len).sum()
+    }
+
+    /// Signed cross-track error (positive to starboard) and distance along the line
+    pub fn cross_track(&self, lat: f64, lon: f64) -> Option<(f64, f64)> {
+        let mut best: Option<(f64, f64)> = None;
+        let mut start_m = 0.0;
+        for (a, b, len) in self.segments() {
+            let (pe, pn) = local_offset_m(a.0, a.1, lat, lon);
+            let (de, dn) = local_offset_m(a.0, a.1, b.0, b.1);
+            let t = if len > 0.0 { ((pe * de + pn * dn) / (len * len)).clamp(0.0, 1.0) } else { 0.0 };
+            let distance = (pe - t * de).hypot(pn - t * dn);
+            let side = if de * pn - dn * pe > 0.0 { -1.0 } else { 1.0 };
+            if best.is_none_or(|(xte, _)| distance < xte.abs()) {
+                best = Some((side * distance, start_m + t * len));
+            }
+            start_m += len;
+        }
+        best
+    }
+
+    fn segments(&self) -> impl Iterator<Item = ((f64, f64), (f64, f64), f64)> + '_ {
+        self.points.windows(2).map(|w| {
+            let (e, n) = local_offset_m(w[0].0, w[0].1, w[1].0, w[1].1);
+            (w[0], w[1], e.hypot(n))
+        })
+    }
+}
+
+/// Thresholds for assigning the track to lines and judging compliance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RouteCheckConfig {
+    /// Track points further than this from every line are not assigned
+    pub corridor_m: f64,
+    /// Largest accepted 95th percentile cross-track error
+    pub tolerance_m: f64,
+    /// Along-line bin used to measure how much of a line was run
+    pub coverage_bin_m: f64,
+    pub min_coverage_percent: f64,
+}
+
+impl Default for RouteCheckConfig {
+    fn default() -> Self {
+        Self {
+            corridor_m: 50.0,
+            tolerance_m: 5.0,
+            coverage_bin_m: 10.0,
+            min_coverage_percent: 90.0,
+        }
+    }
+}
+
+/// Cross-track statistics of the recording along one planned line
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineReport {
+    pub name: String,
+    pub length_m: f64,
+    pub samples: usize,
+    pub mean_xte_m: f64,
+    pub rms_xte_m: f64,
+    pub max_abs_xte_m: f64,
+    pub p95_abs_xte_m: f64,
+    /// Share of the line length with track points alongside
+    pub coverage_percent: f64,
+    pub compliant: bool,
+}
+
+/// Compare track positions `(lat, lon)` against planned lines
+///
+/// Each position is assigned to the nearest line within the corridor.
+pub fn compare_to_plan(track: &[(f64, f64)], lines: &[PlannedLine], config: &RouteCheckConfig) -> Vec<LineReport> {
+    let mut assigned: Vec<Vec<(f64, f64)>> = vec![Vec::new(); lines.len()];
+    for &(lat, lon) in track {
+        let nearest = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| Some((i, line.cross_track(lat, lon)?)))
+            .min_by(|a, b| a.1 .0.abs().total_cmp(&b.1 .0.abs()));
+        if let Some((i, (xte, along))) = nearest {
+            if xte.abs() <= config.corridor_m {
+                assigned[i].push((xte, along));
+            }
+        }
+    }
+
+    lines
+        .iter()
+        .zip(assigned)
+        .map(|(line, errors)| line_report(line, &errors, config))
+        .collect()
+}
+
+fn line_report(line: &PlannedLine, errors: &[(f64, f64)], config: &RouteCheckConfig) -> LineReport {
+    let length_m = line.length_m();
+    let n = errors.len() as f64;
+    let mut abs: Vec<f64> = errors.iter().map(|e| e.0.abs()).collect();
+    abs.sort_by(|a, b| a.total_cmp(b));
+
+    let bins = ((length_m / config.coverage_bin_m).ceil() as usize).max(1);
+    let mut visited = vec![false; bins];
+    for &(_, along) in errors {
+        visited[((along / config.coverage_bin_m) as usize).min(bins - 1)] = true;
+    }
+    let coverage_percent = if errors.is_empty() {
+        0.0
+    } else {
+        100.0 * visited.iter().filter(|&&v| v).count() as f64 / bins as f64
+    };
+    let p95_abs_xte_m = abs.get(((n * 0.95).ceil() as usize).saturating_sub(1)).copied().unwrap_or(0.0);
+
+    LineReport {
+        name: line.name.clone(),
+        length_m,
+        samples: errors.len(),
+        mean_xte_m: if errors.is_empty() { 0.0 } else { errors.iter().map(|e| e.0).sum::<f64>() / n },
+        rms_xte_m: if errors.is_empty() { 0.0 } else { (abs.iter().map(|e| e * e).sum::<f64>() / n).sqrt() },
+        max_abs_xte_m: abs.last().copied().unwrap_or(0.0),
+        p95_abs_xte_m,
+        coverage_percent,
+        compliant: !errors.is_empty()
+            && p95_abs_xte_m <= config.tolerance_m
+            && coverage_percent >= config.min_coverage_percent,
+    }
+}
+
+/// Write the per-line compliance report as CSV
+pub fn write_route_report_csv<P: AsRef<Path>>(path: P, reports: &[LineReport]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "line,length_m,samples,mean_xte_m,rms_xte_m,max_abs_xte_m,p95_abs_xte_m,coverage_percent,compliant")?;
+    for r in reports {
+        writeln!(
+            out,
+            "{},{:.1},{},{:.2},{:.2},{:.2},{:.2},{:.1},{}",
+            r.name.replace(',', " "),
+            r.length_m,
+            r.samples,
+            r.mean_xte_m,
+            r.rms_xte_m,
+            r.max_abs_xte_m,
+            r.p95_abs_xte_m,
+            r.coverage_percent,
+            r.compliant
+        )?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::offset_position;
+
+    fn local(east: f64, north: f64) -> (f64, f64) {
+        offset_position(44.0, -63.0, east, north)
+    }
+
+    /// 200 m line running north at `east`
+    fn line(name: &str, east: f64) -> PlannedLine {
+        PlannedLine {
+            name: name.to_string(),
+            points: vec![local(east, 0.0), local(east, 100.0), local(east, 200.0)],
+        }
+    }
+
+    #[test]
+    fn cross_track_is_positive_to_starboard() {
+        let line = line("A", 0.0);
+        assert!((line.length_m() - 200.0).abs() < 1e-6);
+        let (lat, lon) = local(3.0, 150.0);
+        let (xte, along) = line.cross_track(lat, lon).unwrap();
+        assert!((xte - 3.0).abs() < 1e-3 && (along - 150.0).abs() < 1e-3);
+        let (lat, lon) = local(-4.0, 20.0);
+        assert!((line.cross_track(lat, lon).unwrap().0 + 4.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn reports_compliance_per_line() {
+        let lines = [line("A", 0.0), line("B", 100.0)];
+        let mut track: Vec<(f64, f64)> = (0..=40).map(|i| local(2.0, i as f64 * 5.0)).collect();
+        // Line B only run for its first half, 8 m to port
+        track.extend((0..20).map(|i| local(92.0, i as f64 * 5.0)));
+        // Transit between lines, outside both corridors
+        track.push(local(300.0, 0.0));
+
+        let reports = compare_to_plan(&track, &lines, &RouteCheckConfig::default());
+        let (a, b) = (&reports[0], &reports[1]);
+        assert_eq!((a.samples, b.samples), (41, 20));
+        assert!((a.mean_xte_m - 2.0).abs() < 1e-3 && (a.p95_abs_xte_m - 2.0).abs() < 1e-3);
+        assert_eq!(a.coverage_percent, 100.0);
+        assert!(a.compliant);
+        assert!((b.mean_xte_m + 8.0).abs() < 1e-3);
+        assert!((b.coverage_percent - 50.0).abs() < 3.0, "{}", b.coverage_percent);
+        assert!(!b.compliant);
+    }
+}
@@ -0,0 +1,198 @@
+// Clock offset estimation between channels by cross-correlating bottom returns
+// src/signal/align.rs
+
+use crate::survey::Ping;
+use std::collections::BTreeMap;
+
+/// Search settings for channel time alignment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlignConfig {
+    /// Resampling interval of the bottom series
+    pub step_s: f64,
+    /// Largest offset searched in either direction
+    pub max_lag_s: f64,
+    /// Fraction of the strongest sample that marks the first bottom return
+    pub bottom_threshold: f64,
+}
+
+impl Default for AlignConfig {
+    fn default() -> Self {
+        Self {
+            step_s: 0.1,
+            max_lag_s: 5.0,
+            bottom_threshold: 0.5,
+        }
+    }
+}
+
+/// Estimated clock offset of one channel relative to the reference channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelOffset {
+    /// Seconds to add to this channel's timestamps
+    pub offset_s: f64,
+    /// Pearson correlation of the bottom series at that offset
+    pub correlation: f64,
+}
+
+/// Bottom range of a ping: the recorded depth, or the first strong return
+///
+/// Side-scan channels usually carry no depth, so their first return above
+/// `threshold` times the ping maximum (the altitude) is used instead.
+pub fn bottom_range_m(ping: &Ping, threshold: f64) -> Option<f64> {
+    if ping.depth_m > 0.0 {
+        return Some(ping.depth_m);
+    }
+    let peak = *ping.samples.iter().max()? as f64;
+    if peak <= 0.0 {
+        return None;
+    }
+    let i = ping.samples.iter().position(|&v| v as f64 >= peak * threshold)?;
+    Some((i as f64 + 0.5) * ping.sample_spacing_m())
+}
+
+/// Offset per non-reference channel that best lines its bottom up with the reference
+///
+/// Channels with too little overlap or a flat bottom are left out.
+pub fn estimate_channel_offsets(pings: &[Ping], reference: u16, config: &AlignConfig) -> BTreeMap<u16, ChannelOffset> {
+    let mut series: BTreeMap<u16, Vec<(f64, f64)>> = BTreeMap::new();
+    for p in pings {
+        if let Some(range) = bottom_range_m(p, config.bottom_threshold) {
+            series.entry(p.channel_id).or_default().push((p.timestamp, range));
+        }
+    }
+    for s in series.values_mut() {
+        s.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+    let Some(base) = series.get(&reference) else {
+        return BTreeMap::new();
+    };
+
+    series
+        .iter()
+        .filter(|(&ch, _)| ch != reference)
+        .filter_map(|(&ch, other)| Some((ch, estimate_offset(base, other, config)?)))
+        .collect()
+}
+
+/// Offset to add to `other`'s times so its series best matches `reference`
+///
+/// Both series are `(timestamp, value)` sorted by time.
+pub fn estimate_offset(reference: &[(f64, f64)], other: &[(f64, f64)], config: &AlignConfig) -> Option<ChannelOffset> {
+    const MIN_OVERLAP: usize = 10;
+    let (start, end) = (reference.first()?.0, reference.last()?.0);
+    let step = config.step_s.max(1e-3);
+    let grid: Vec<(f64, f64)> = (0..=((end - start) / step) as usize)
+        .map(|k| start + k as f64 * step)
+        .filter_map(|t| Some((t, interpolate(reference, t)?)))
+        .collect();
+
+    let max_k = (config.max_lag_s / step).round() as i64;
+    let mut best: Option<ChannelOffset> = None;
+    for k in -max_k..=max_k {
+        let lag = k as f64 * step;
+        let pairs: Vec<(f64, f64)> = grid
+            .iter()
+            .filter_map(|&(t, v)| Some((v, interpolate(other, t + lag)?)))
+            .collect();
+        if pairs.len() < MIN_OVERLAP {
+            continue;
+        }
+        let Some(correlation) = pearson(&pairs) else { continue };
+        if best.is_none_or(|b| correlation > b.correlation) {
+            best = Some(ChannelOffset {
+                offset_s: -lag,
+                correlation,
+            });
+        }
+    }
+    best
+}
+
+fn interpolate(series: &[(f64, f64)], t: f64) -> Option<f64> {
+    let i = series.partition_point(|s| s.0 < t);
+    let after = series.get(i)?;
+    if after.0 == t {
+        return Some(after.1);
+    }
+    let before = series.get(i.checked_sub(1)?)?;
+    let f = (t - before.0) / (after.0 - before.0);
+    Some(before.1 + (after.1 - before.1) * f)
+}
+
+fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    let n = pairs.len() as f64;
+    let (sx, sy) = pairs.iter().fold((0.0, 0.0), |(x, y), p| (x + p.0, y + p.1));
+    let (mx, my) = (sx / n, sy / n);
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for &(x, y) in pairs {
+        sxy += (x - mx) * (y - my);
+        sxx += (x - mx).powi(2);
+        syy += (y - my).powi(2);
+    }
+    (sxx > 0.0 && syy > 0.0).then(|| sxy / (sxx * syy).sqrt())
+}
+
+/// Shift each channel's timestamps by its estimated offset
+pub fn apply_offsets(pings: &mut [Ping], offsets: &BTreeMap<u16, ChannelOffset>) {
+    for p in pings {
+        if let Some(o) = offsets.get(&p.channel_id) {
+            p.timestamp += o.offset_s;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bottom(t: f64) -> f64 {
+        10.0 + 3.0 * (t * 0.7).sin() + (t * 1.9).sin()
+    }
+
+    fn ping(channel_id: u16, timestamp: f64, depth_m: f64) -> Ping {
+        Ping {
+            channel_id,
+            timestamp,
+            depth_m,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn first_strong_return_stands_in_for_missing_depth() {
+        let mut p = Ping {
+            range_m: 10.0,
+            samples: vec![5, 10, 20, 200, 120, 60, 30, 10, 5, 0],
+            ..Default::default()
+        };
+        assert_eq!(bottom_range_m(&p, 0.5), Some(3.5));
+        p.depth_m = 7.0;
+        assert_eq!(bottom_range_m(&p, 0.5), Some(7.0));
+        assert_eq!(bottom_range_m(&Ping::default(), 0.5), None);
+    }
+
+    #[test]
+    fn recovers_a_known_clock_offset() {
+        let mut pings = Vec::new();
+        for k in 0..120 {
+            let t = k as f64 * 0.5;
+            pings.push(ping(0, t, bottom(t)));
+            // Channel 1's clock runs 1.5 s ahead of the reference
+            pings.push(ping(1, t + 1.5, bottom(t)));
+        }
+        let offsets = estimate_channel_offsets(&pings, 0, &AlignConfig::default());
+        let offset = offsets[&1];
+        assert!((offset.offset_s + 1.5).abs() < 1e-6, "{:?}", offset);
+        assert!(offset.correlation > 0.99);
+
+        apply_offsets(&mut pings, &offsets);
+        assert_eq!(pings[1].timestamp, pings[0].timestamp);
+        assert!(estimate_channel_offsets(&pings, 5, &AlignConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn flat_bottom_gives_no_offset() {
+        let pings: Vec<Ping> = (0..40).flat_map(|k| [ping(0, k as f64, 8.0), ping(1, k as f64, 8.0)]).collect();
+        assert!(estimate_channel_offsets(&pings, 0, &AlignConfig::default()).is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+// Despeckle, interference and striping filters for ping samples
+// src/signal/filters.rs
+
+use crate::dedup::median;
+use crate::survey::Ping;
+use std::collections::BTreeMap;
+
+/// One selectable filter stage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterStep {
+    /// Median over a (2r+1)×(2r+1) window of range bins and pings
+    Despeckle { radius: usize },
+    /// Moving average of each range bin over `window` consecutive pings
+    AlongTrack { window: usize },
+    /// Replace samples exceeding `ratio` times both along-track neighbours
+    Interference { ratio: f64 },
+    /// Equalize the mean level of each range bin across the recording
+    Destripe,
+}
+
+impl FilterStep {
+    /// Parse `despeckle`, `despeckle:2`, `smooth:5`, `interference:3`, `destripe`
+    pub fn from_name(spec: &str) -> Option<Self> {
+        let (name, arg) = match spec.split_once(':') {
+            Some((name, arg)) => (name, Some(arg.trim())),
+            None => (spec, None),
+        };
+        match name.trim().to_lowercase().as_str() {
+            "despeckle" | "median" => Some(Self::Despeckle {
+                radius: arg.map_or(Some(1), |a| a.parse().ok())?,
+            }),
+            "smooth" | "along-track" => Some(Self::AlongTrack {
+                window: arg.map_or(Some(3), |a| a.parse().ok())?,
+            }),
+            "interference" => Some(Self::Interference {
+                ratio: arg.map_or(Some(3.0), |a| a.parse().ok())?,
+            }),
+            "destripe" => Some(Self::Destripe),
+            _ => None,
+        }
+    }
+}
+
+/// Run filter steps in order over each channel of a recording (pings in time order)
+pub fn apply_filters(pings: &mut [Ping], steps: &[FilterStep]) {
+    let mut channels: BTreeMap<u16, Vec<usize>> = BTreeMap::new();
+    for (i, p) in pings.iter().enumerate() {
+        channels.entry(p.channel_id).or_default().push(i);
+    }
+    for rows in channels.values() {
+        for step in steps {
+            let input: Vec<Vec<u8>> = rows.iter().map(|&i| pings[i].samples.clone()).collect();
+            let output = match *step {
+                FilterStep::Despeckle { radius } => despeckle(&input, radius),
+                FilterStep::AlongTrack { window } => along_track(&input, window),
+                FilterStep::Interference { ratio } => reject_interference(&input, ratio),
+                FilterStep::Destripe => destripe(&input),
+            };
+            for (&i, samples) in rows.iter().zip(output) {
+                pings[i].samples = samples;
+            }
+        }
+    }
+}
+
+/// 2D median filter over rows of samples; rows may differ in length
+pub fn despeckle(rows: &[Vec<u8>], radius: usize) -> Vec<Vec<u8>> {
+    let mut window = Vec::with_capacity((2 * radius + 1).pow(2));
+    rows.iter()
+        .enumerate()
+        .map(|(r, row)| {
+            (0..row.len())
+                .map(|c| {
+                    window.clear();
+                    for nr in &rows[r.saturating_sub(radius)..(r + radius + 1).min(rows.len())] {
+                        let end = (c + radius + 1).min(nr.len());
+                        if c.saturating_sub(radius) < end {
+                            window.extend(nr[c.saturating_sub(radius)..end].iter().map(|&v| v as f64));
+                        }
+                    }
+                    median(&mut window).round() as u8
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Centered moving average along track for each range bin
+pub fn along_track(rows: &[Vec<u8>], window: usize) -> Vec<Vec<u8>> {
+    let half = window.max(1) / 2;
+    rows.iter()
+        .enumerate()
+        .map(|(r, row)| {
+            let neighbours = &rows[r.saturating_sub(half)..(r + half + 1).min(rows.len())];
+            (0..row.len())
+                .map(|c| {
+                    let (sum, n) = neighbours
+                        .iter()
+                        .filter_map(|nr| nr.get(c))
+                        .fold((0u32, 0u32), |(s, n), &v| (s + v as u32, n + 1));
+                    ((sum as f64 / n as f64).round()) as u8
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Suppress spikes that appear in one ping but not its neighbours
+///
+/// Trolling motors and other sonars inject energy into single pings; real
+/// echoes persist across consecutive pings at the same range.
+pub fn reject_interference(rows: &[Vec<u8>], ratio: f64) -> Vec<Vec<u8>> {
+    rows.iter()
+        .enumerate()
+        .map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(c, &v)| {
+                    let prev = r.checked_sub(1).and_then(|p| rows[p].get(c)).copied();
+                    let next = rows.get(r + 1).and_then(|n| n.get(c)).copied();
+                    match (prev, next) {
+                        (Some(a), Some(b)) if v as f64 > ratio * (a.max(b) as f64).max(1.0) => {
+                            ((a as u16 + b as u16) / 2) as u8
+                        }
+                        _ => v,
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Scale each range bin so its mean matches a smoothed across-range trend
+///
+/// Removes constant bright or dark stripes running along track while
+/// keeping the overall fall-off with range.
+pub fn destripe(rows: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let cols = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut sums = vec![0f64; cols];
+    let mut counts = vec![0u32; cols];
+    for row in rows {
+        for (c, &v) in row.iter().enumerate() {
+            sums[c] += v as f64;
+            counts[c] += 1;
+        }
+    }
+    let means: Vec<f64> = sums.iter().zip(&counts).map(|(s, &n)| s / n.max(1) as f64).collect();
+    const TREND_HALF_WIDTH: usize = 8;
+    let trend: Vec<f64> = (0..cols)
+        .map(|c| {
+            let span = &means[c.saturating_sub(TREND_HALF_WIDTH)..(c + TREND_HALF_WIDTH + 1).min(cols)];
+            span.iter().sum::<f64>() / span.len() as f64
+        })
+        .collect();
+
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(c, &v)| {
+                    let gain = if means[c] > 0.0 { trend[c] / means[c] } else { 1.0 };
+                    (v as f64 * gain).round().clamp(0.0, 255.0) as u8
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filter_specs() {
+        assert_eq!(FilterStep::from_name("despeckle"), Some(FilterStep::Despeckle { radius: 1 }));
+        assert_eq!(FilterStep::from_name(" smooth : 5"), Some(FilterStep::AlongTrack { window: 5 }));
+        assert_eq!(FilterStep::from_name("interference:2.5"), Some(FilterStep::Interference { ratio: 2.5 }));
+        assert_eq!(FilterStep::from_name("Destripe"), Some(FilterStep::Destripe));
+        assert_eq!(FilterStep::from_name("despeckle:big"), None);
+        assert_eq!(FilterStep::from_name("sharpen"), None);
+    }
+
+    #[test]
+    fn despeckle_removes_a_lone_speck() {
+        let mut rows = vec![vec![10u8; 5]; 5];
+        rows[2][2] = 255;
+        rows[4].truncate(3);
+        let out = despeckle(&rows, 1);
+        assert!(out.iter().flatten().all(|&v| v == 10));
+        assert_eq!(out[4].len(), 3);
+    }
+
+    #[test]
+    fn along_track_averages_neighbouring_pings() {
+        let out = along_track(&[vec![0], vec![30], vec![60, 9]], 3);
+        assert_eq!(out, vec![vec![15], vec![30], vec![45, 9]]);
+    }
+
+    #[test]
+    fn interference_spikes_are_replaced_but_persistent_echoes_kept() {
+        let rows = vec![vec![10, 200], vec![200, 200], vec![12, 200]];
+        assert_eq!(reject_interference(&rows, 3.0), vec![vec![10, 200], vec![11, 200], vec![12, 200]]);
+    }
+
+    #[test]
+    fn destripe_flattens_a_bright_range_bin() {
+        let mut rows = vec![vec![100u8; 40]; 6];
+        for row in &mut rows {
+            row[20] = 150;
+        }
+        let out = destripe(&rows);
+        assert!((100..=104).contains(&out[0][20]), "{}", out[0][20]);
+        assert_eq!(out[3][0], 100);
+        assert_eq!(out[3][39], 100);
+    }
+
+    #[test]
+    fn channels_are_filtered_separately() {
+        // Interleaved channels: a bright side-scan channel must not look like spikes to the other
+        let mut pings: Vec<Ping> = (0..6)
+            .map(|i| Ping {
+                timestamp: i as f64,
+                channel_id: (i % 2) as u16,
+                samples: vec![if i % 2 == 1 { 200 } else { 10 }; 3],
+                ..Default::default()
+            })
+            .collect();
+        pings[2].samples[1] = 250;
+        apply_filters(&mut pings, &[FilterStep::Interference { ratio: 3.0 }]);
+        assert_eq!(pings[2].samples, vec![10, 10, 10]);
+        assert!(pings.iter().filter(|p| p.channel_id == 1).all(|p| p.samples == vec![200; 3]));
+    }
+}
@@ -0,0 +1,7 @@
+// Sample-level processing applied to pings before imaging or classification
+// src/signal/mod.rs
+
+pub mod align;
+pub mod filters;
+pub mod stack;
+pub mod tvg;
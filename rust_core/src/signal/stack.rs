@@ -0,0 +1,108 @@
+// Along-track ping stacking for low signal-to-noise recordings
+// src/signal/stack.rs
+
+use crate::survey::Ping;
+use std::collections::BTreeMap;
+
+/// Average every `count` consecutive pings of each channel into one ping
+///
+/// Groups are cut early when the range or sample count changes, so bins are
+/// only averaged with bins at the same range. Position, time and depth are
+/// the group means; heading and motion are taken from the middle ping.
+/// The result keeps the channels interleaved in time order.
+pub fn stack_pings(pings: &[Ping], count: usize) -> Vec<Ping> {
+    if count <= 1 {
+        return pings.to_vec();
+    }
+    let mut channels: BTreeMap<u16, Vec<&Ping>> = BTreeMap::new();
+    for p in pings {
+        channels.entry(p.channel_id).or_default().push(p);
+    }
+
+    let mut stacked = Vec::with_capacity(pings.len() / count + channels.len());
+    for rows in channels.values() {
+        let mut start = 0;
+        while start < rows.len() {
+            let first = rows[start];
+            let mut end = start + 1;
+            while end < rows.len()
+                && end - start < count
+                && rows[end].range_m == first.range_m
+                && rows[end].samples.len() == first.samples.len()
+            {
+                end += 1;
+            }
+            stacked.push(average(&rows[start..end]));
+            start = end;
+        }
+    }
+    stacked.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    stacked
+}
+
+fn average(group: &[&Ping]) -> Ping {
+    let n = group.len() as f64;
+    let mean = |f: fn(&Ping) -> f64| group.iter().map(|p| f(p)).sum::<f64>() / n;
+    let depths: Vec<f64> = group.iter().map(|p| p.depth_m).filter(|&d| d > 0.0).collect();
+    let middle = group[group.len() / 2];
+
+    let mut sums = vec![0u32; middle.samples.len()];
+    for p in group {
+        for (sum, &v) in sums.iter_mut().zip(&p.samples) {
+            *sum += v as u32;
+        }
+    }
+    Ping {
+        timestamp: mean(|p| p.timestamp),
+        lat: mean(|p| p.lat),
+        lon: mean(|p| p.lon),
+        depth_m: if depths.is_empty() { 0.0 } else { depths.iter().sum::<f64>() / depths.len() as f64 },
+        samples: sums.iter().map(|&s| (s as f64 / n).round() as u8).collect(),
+        ..middle.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(channel_id: u16, timestamp: f64, range_m: f64, value: u8) -> Ping {
+        Ping {
+            channel_id,
+            timestamp,
+            range_m,
+            lat: 45.0 + timestamp * 1e-5,
+            depth_m: if timestamp == 1.0 { 0.0 } else { 10.0 },
+            samples: vec![value; 8],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stacks_groups_per_channel_and_averages_samples() {
+        let mut pings = Vec::new();
+        for i in 0..4 {
+            pings.push(ping(0, i as f64, 30.0, 10 * (i as u8 + 1)));
+            pings.push(ping(1, i as f64 + 0.5, 30.0, 100));
+        }
+        let stacked = stack_pings(&pings, 2);
+        assert_eq!(stacked.len(), 4);
+        let port: Vec<&Ping> = stacked.iter().filter(|p| p.channel_id == 0).collect();
+        assert_eq!(port[0].samples, vec![15; 8]);
+        assert_eq!(port[1].samples, vec![35; 8]);
+        assert!((port[0].timestamp - 0.5).abs() < 1e-9);
+        // The zero depth of the second ping is left out of the mean
+        assert_eq!(port[0].depth_m, 10.0);
+        assert!(stacked.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+        assert_eq!(stack_pings(&pings, 1).len(), pings.len());
+    }
+
+    #[test]
+    fn range_changes_cut_a_group_short() {
+        let pings = vec![ping(0, 0.0, 30.0, 20), ping(0, 1.0, 30.0, 40), ping(0, 2.0, 60.0, 90)];
+        let stacked = stack_pings(&pings, 3);
+        assert_eq!(stacked.len(), 2);
+        assert_eq!(stacked[0].samples, vec![30; 8]);
+        assert_eq!((stacked[1].range_m, stacked[1].samples[0]), (60.0, 90));
+    }
+}
@@ -0,0 +1,190 @@
+// Time-varied gain normalization of ping samples
+// src/signal/tvg.rs
+
+use crate::survey::Ping;
+
+/// Geometric spreading compensation, `k·log10(R)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadingLaw {
+    None,
+    /// 20logR: cylindrical spreading, typical for side-scan over flat bottom
+    TwentyLog,
+    /// 30logR: a common compromise for mixed targets
+    ThirtyLog,
+    /// 40logR: spherical spreading both ways, point targets in the water column
+    FortyLog,
+}
+
+impl SpreadingLaw {
+    pub fn factor(self) -> f64 {
+        match self {
+            Self::None => 0.0,
+            Self::TwentyLog => 20.0,
+            Self::ThirtyLog => 30.0,
+            Self::FortyLog => 40.0,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace(' ', "").as_str() {
+            "none" | "0" => Some(Self::None),
+            "20logr" | "20" => Some(Self::TwentyLog),
+            "30logr" | "30" => Some(Self::ThirtyLog),
+            "40logr" | "40" => Some(Self::FortyLog),
+            _ => None,
+        }
+    }
+}
+
+/// TVG curve: spreading plus two-way absorption, with an overall gain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tvg {
+    pub law: SpreadingLaw,
+    /// One-way absorption; doubled for the round trip
+    pub absorption_db_per_m: f64,
+    /// Range at which spreading gain is 0 dB; closer samples are not attenuated further
+    pub reference_m: f64,
+    pub gain_db: f64,
+}
+
+impl Default for Tvg {
+    fn default() -> Self {
+        Self {
+            law: SpreadingLaw::TwentyLog,
+            absorption_db_per_m: 0.0,
+            reference_m: 1.0,
+            gain_db: 0.0,
+        }
+    }
+}
+
+impl Tvg {
+    /// Gain in dB applied at a range
+    pub fn gain_at(&self, range_m: f64) -> f64 {
+        let r = range_m.max(self.reference_m).max(1e-3);
+        self.law.factor() * (r / self.reference_m.max(1e-3)).log10() + 2.0 * self.absorption_db_per_m * r + self.gain_db
+    }
+
+    /// Corrected copy of a ping's samples
+    pub fn correct(&self, ping: &Ping) -> Vec<u8> {
+        let spacing = ping.sample_spacing_m();
+        ping.samples
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                let gain = self.gain_at((i as f64 + 0.5) * spacing);
+                (v as f64 * 10f64.powf(gain / 20.0)).round().clamp(0.0, 255.0) as u8
+            })
+            .collect()
+    }
+
+    /// Correct samples in place
+    pub fn apply(&self, pings: &mut [Ping]) {
+        for ping in pings.iter_mut() {
+            ping.samples = self.correct(ping);
+        }
+    }
+
+    /// Copy of this curve with `gain_db` chosen so the corrected median echo lands on `target`
+    ///
+    /// Zero samples (no return) are ignored. Returns the curve unchanged
+    /// when the pings hold no echoes.
+    pub fn with_auto_gain(&self, pings: &[Ping], target: u8) -> Self {
+        let base = Self { gain_db: 0.0, ..*self };
+        let mut histogram = [0u64; 256];
+        for ping in pings {
+            let spacing = ping.sample_spacing_m();
+            for (i, &v) in ping.samples.iter().enumerate() {
+                if v == 0 {
+                    continue;
+                }
+                // Unclamped amplitude, so saturation does not hide the level
+                let amplitude = v as f64 * 10f64.powf(base.gain_at((i as f64 + 0.5) * spacing) / 20.0);
+                histogram[log_bin(amplitude)] += 1;
+            }
+        }
+        let total: u64 = histogram.iter().sum();
+        if total == 0 {
+            return *self;
+        }
+        let mut seen = 0;
+        let median_bin = histogram
+            .iter()
+            .position(|&n| {
+                seen += n;
+                seen * 2 >= total
+            })
+            .unwrap_or(0);
+        let median = bin_amplitude(median_bin);
+        Self {
+            gain_db: 20.0 * (target.max(1) as f64 / median).log10(),
+            ..*self
+        }
+    }
+}
+
+/// Log-spaced histogram bin for amplitudes 1..10^6, about 0.47 dB wide
+fn log_bin(amplitude: f64) -> usize {
+    ((amplitude.max(1.0).log10() / 6.0) * 255.0).round().clamp(0.0, 255.0) as usize
+}
+
+fn bin_amplitude(bin: usize) -> f64 {
+    10f64.powf(bin as f64 / 255.0 * 6.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(samples: Vec<u8>, range_m: f64) -> Ping {
+        Ping {
+            range_m,
+            samples,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn gain_follows_spreading_and_absorption() {
+        let tvg = Tvg {
+            absorption_db_per_m: 0.05,
+            ..Default::default()
+        };
+        assert!((tvg.gain_at(10.0) - 21.0).abs() < 1e-9);
+        // Nothing closer than the reference range is attenuated
+        assert_eq!(tvg.gain_at(0.2), tvg.gain_at(1.0));
+        let forty = Tvg {
+            law: SpreadingLaw::from_name("40 log R").unwrap(),
+            ..Default::default()
+        };
+        assert!((forty.gain_at(100.0) - 80.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn twenty_log_r_scales_samples_with_range() {
+        let tvg = Tvg {
+            gain_db: -20.0,
+            ..Default::default()
+        };
+        // Sample centres at 5, 15, 25 and 35 m
+        let mut pings = vec![ping(vec![50; 4], 40.0)];
+        tvg.apply(&mut pings);
+        assert_eq!(pings[0].samples, vec![25, 75, 125, 175]);
+        assert_eq!(Tvg { gain_db: 40.0, ..tvg }.correct(&pings[0]), vec![255; 4]);
+    }
+
+    #[test]
+    fn auto_gain_puts_the_median_echo_on_target() {
+        let flat = Tvg {
+            law: SpreadingLaw::None,
+            gain_db: 12.0,
+            ..Default::default()
+        };
+        let pings = vec![ping(vec![0, 100, 100, 100, 30], 10.0), ping(vec![100, 0, 220], 10.0)];
+        let tuned = flat.with_auto_gain(&pings, 50);
+        assert!((tuned.gain_db + 6.02).abs() < 0.5, "{}", tuned.gain_db);
+        assert_eq!(tuned.correct(&pings[0])[1..4], [50, 50, 50]);
+        // Silent pings leave the curve as it was
+        assert_eq!(flat.with_auto_gain(&[ping(vec![0; 8], 10.0)], 50), flat);
+    }
+}
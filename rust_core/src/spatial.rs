@@ -0,0 +1,599 @@
+// Packed R-tree over soundings for nearest-depth and area queries
+// src/spatial.rs
+
+use crate::geo::{point_in_polygon, METERS_PER_DEGREE_LAT};
+use crate::survey::{Sounding, VerticalReference};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const NODE_CAPACITY: usize = 16;
+const MAGIC: &[u8; 4] = b"SSRT";
+/// Version 2 added each sounding's vertical reference byte; version 1 files still load
+const VERSION: u32 = 2;
+
+/// Bounding box in index space: `[min_x, min_y, max_x, max_y]`
+type Rect = [f64; 4];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Node {
+    rect: Rect,
+    /// First child node, or first item for leaves
+    first: u32,
+    count: u32,
+    leaf: bool,
+}
+
+/// Static R-tree built with Sort-Tile-Recursive packing
+///
+/// Longitude is scaled by the cosine of the mean latitude so distances in
+/// index space are proportional to meters across survey-sized areas.
+/// Query results are indices into the slice the index was built from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundingIndex {
+    soundings: Vec<Sounding>,
+    /// Sounding indices in leaf order
+    order: Vec<u32>,
+    nodes: Vec<Node>,
+    lon_scale: f64,
+}
+
+impl SoundingIndex {
+    pub fn build(soundings: &[Sounding]) -> Self {
+        let mean_lat = if soundings.is_empty() {
+            0.0
+        } else {
+            soundings.iter().map(|s| s.lat).sum::<f64>() / soundings.len() as f64
+        };
+        let lon_scale = mean_lat.to_radians().cos().max(0.01);
+
+        let point = |i: &u32| {
+            let s = &soundings[*i as usize];
+            [s.lon * lon_scale, s.lat]
+        };
+        let mut order: Vec<u32> = (0..soundings.len() as u32).collect();
+        str_order(&mut order, point);
+
+        let mut nodes: Vec<Node> = order
+            .chunks(NODE_CAPACITY)
+            .enumerate()
+            .map(|(i, chunk)| Node {
+                rect: chunk.iter().map(point).fold(EMPTY, |r, p| union(r, [p[0], p[1], p[0], p[1]])),
+                first: (i * NODE_CAPACITY) as u32,
+                count: chunk.len() as u32,
+                leaf: true,
+            })
+            .collect();
+
+        // Pack each level's nodes under parents until a single root remains
+        let mut level_start = 0;
+        while nodes.len() - level_start > 1 {
+            let mut level = nodes.split_off(level_start);
+            str_order(&mut level, |n| center(n.rect));
+            nodes.extend_from_slice(&level);
+            let parents: Vec<Node> = level
+                .chunks(NODE_CAPACITY)
+                .enumerate()
+                .map(|(i, chunk)| Node {
+                    rect: chunk.iter().fold(EMPTY, |r, n| union(r, n.rect)),
+                    first: (level_start + i * NODE_CAPACITY) as u32,
+                    count: chunk.len() as u32,
+                    leaf: false,
+                })
+                .collect();
+            level_start = nodes.len();
+            nodes.extend(parents);
+        }
+
+        Self {
+            soundings: soundings.to_vec(),
+            order,
+            nodes,
+            lon_scale,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.soundings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.soundings.is_empty()
+    }
+
+    pub fn sounding(&self, index: usize) -> Option<&Sounding> {
+        self.soundings.get(index)
+    }
+
+    /// The indexed soundings, in the order they were given to [`SoundingIndex::build`]
+    pub fn soundings(&self) -> &[Sounding] {
+        &self.soundings
+    }
+
+    /// Closest sounding and its distance in meters
+    pub fn nearest(&self, lat: f64, lon: f64) -> Option<(usize, f64)> {
+        self.nearest_n(lat, lon, 1).into_iter().next()
+    }
+
+    /// Depth of the closest sounding
+    pub fn nearest_depth(&self, lat: f64, lon: f64) -> Option<f64> {
+        self.nearest(lat, lon).map(|(i, _)| self.soundings[i].depth_m)
+    }
+
+    /// Up to `n` closest soundings with distances in meters, nearest first
+    pub fn nearest_n(&self, lat: f64, lon: f64, n: usize) -> Vec<(usize, f64)> {
+        let mut found = Vec::new();
+        let Some(root) = self.nodes.len().checked_sub(1).filter(|_| n > 0) else { return found };
+        let q = [lon * self.lon_scale, lat];
+        let mut heap = BinaryHeap::new();
+        heap.push(Candidate {
+            dist: rect_distance(self.nodes[root].rect, q),
+            entry: Entry::Node(root),
+        });
+
+        while let Some(Candidate { dist, entry }) = heap.pop() {
+            match entry {
+                Entry::Item(i) => {
+                    found.push((i, dist.sqrt() * METERS_PER_DEGREE_LAT));
+                    if found.len() == n {
+                        break;
+                    }
+                }
+                Entry::Node(id) => {
+                    let node = self.nodes[id];
+                    for child in node.first as usize..(node.first + node.count) as usize {
+                        let (dist, entry) = if node.leaf {
+                            let i = self.order[child] as usize;
+                            let s = &self.soundings[i];
+                            let p = [s.lon * self.lon_scale, s.lat];
+                            (rect_distance([p[0], p[1], p[0], p[1]], q), Entry::Item(i))
+                        } else {
+                            (rect_distance(self.nodes[child].rect, q), Entry::Node(child))
+                        };
+                        heap.push(Candidate { dist, entry });
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Soundings within `radius_m` of a position, in index order
+    pub fn within_radius(&self, lat: f64, lon: f64, radius_m: f64) -> Vec<usize> {
+        let r = radius_m / METERS_PER_DEGREE_LAT;
+        let q = [lon * self.lon_scale, lat];
+        self.search([q[0] - r, q[1] - r, q[0] + r, q[1] + r], |s| {
+            let p = [s.lon * self.lon_scale, s.lat];
+            (p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2) <= r * r
+        })
+    }
+
+    /// Soundings inside a `(min_lat, min_lon, max_lat, max_lon)` box
+    pub fn within_bbox(&self, bbox: (f64, f64, f64, f64)) -> Vec<usize> {
+        let (min_lat, min_lon, max_lat, max_lon) = bbox;
+        self.search([min_lon * self.lon_scale, min_lat, max_lon * self.lon_scale, max_lat], |_| true)
+    }
+
+    /// Soundings inside a polygon of `(lat, lon)` vertices
+    pub fn within_polygon(&self, polygon: &[(f64, f64)]) -> Vec<usize> {
+        let rect = polygon.iter().fold(EMPTY, |r, &(lat, lon)| {
+            let x = lon * self.lon_scale;
+            union(r, [x, lat, x, lat])
+        });
+        self.search(rect, |s| point_in_polygon(s.lat, s.lon, polygon))
+    }
+
+    fn search<F: Fn(&Sounding) -> bool>(&self, rect: Rect, accept: F) -> Vec<usize> {
+        let mut found = Vec::new();
+        let Some(root) = self.nodes.len().checked_sub(1) else { return found };
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            let node = self.nodes[id];
+            if !intersects(node.rect, rect) {
+                continue;
+            }
+            let children = node.first as usize..(node.first + node.count) as usize;
+            if node.leaf {
+                for &i in &self.order[children] {
+                    let s = &self.soundings[i as usize];
+                    let p = [s.lon * self.lon_scale, s.lat];
+                    if intersects([p[0], p[1], p[0], p[1]], rect) && accept(s) {
+                        found.push(i as usize);
+                    }
+                }
+            } else {
+                stack.extend(children);
+            }
+        }
+        found.sort_unstable();
+        found
+    }
+
+    /// Persist the packed tree so it can be reloaded without rebuilding
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&self.lon_scale.to_le_bytes())?;
+        out.write_all(&(self.soundings.len() as u64).to_le_bytes())?;
+        for s in &self.soundings {
+            for v in [s.timestamp, s.lat, s.lon, s.depth_m, s.heading_deg] {
+                out.write_all(&v.to_le_bytes())?;
+            }
+            out.write_all(&[s.reference.code()])?;
+        }
+        for i in &self.order {
+            out.write_all(&i.to_le_bytes())?;
+        }
+        out.write_all(&(self.nodes.len() as u64).to_le_bytes())?;
+        for node in &self.nodes {
+            for v in node.rect {
+                out.write_all(&v.to_le_bytes())?;
+            }
+            out.write_all(&node.first.to_le_bytes())?;
+            out.write_all(&node.count.to_le_bytes())?;
+            out.write_all(&[node.leaf as u8])?;
+        }
+        out.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a sounding index file"));
+        }
+        let version = read_u32(&mut input)?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported index version {}, rebuild the index", version),
+            ));
+        }
+        let lon_scale = read_f64(&mut input)?;
+
+        let count = read_u64(&mut input)?;
+        let mut soundings = Vec::new();
+        for _ in 0..count {
+            let mut v = [0f64; 5];
+            for x in &mut v {
+                *x = read_f64(&mut input)?;
+            }
+            // Version 1 predates vertical references; its depths were all below the transducer
+            let mut code = [VerticalReference::Transducer.code()];
+            if version >= 2 {
+                input.read_exact(&mut code)?;
+            }
+            let s = Sounding {
+                timestamp: v[0],
+                lat: v[1],
+                lon: v[2],
+                depth_m: v[3],
+                heading_deg: v[4],
+                reference: VerticalReference::from_code(code[0] as u64)
+                    .ok_or_else(|| invalid("unknown vertical reference in sounding index"))?,
+            };
+            soundings.push(s);
+        }
+        let mut order = Vec::new();
+        for _ in 0..count {
+            order.push(read_u32(&mut input)?);
+        }
+
+        let node_count = read_u64(&mut input)?;
+        let mut nodes = Vec::new();
+        for _ in 0..node_count {
+            let mut rect = EMPTY;
+            for x in &mut rect {
+                *x = read_f64(&mut input)?;
+            }
+            let first = read_u32(&mut input)?;
+            let count = read_u32(&mut input)?;
+            let mut leaf = [0u8];
+            input.read_exact(&mut leaf)?;
+            nodes.push(Node {
+                rect,
+                first,
+                count,
+                leaf: leaf[0] != 0,
+            });
+        }
+
+        // Reject files whose child ranges point outside the arrays, or at
+        // later nodes: parents always follow their children, so a forward
+        // reference could only form a cycle
+        let valid = nodes.iter().enumerate().all(|(id, n)| {
+            let end = n.first as u64 + n.count as u64;
+            if n.leaf {
+                end <= order.len() as u64
+            } else {
+                end <= id as u64
+            }
+        });
+        if !valid || order.iter().any(|&i| i as u64 >= count) {
+            return Err(invalid("corrupt sounding index"));
+        }
+        Ok(Self {
+            soundings,
+            order,
+            nodes,
+            lon_scale,
+        })
+    }
+}
+
+const EMPTY: Rect = [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY];
+
+fn union(a: Rect, b: Rect) -> Rect {
+    [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])]
+}
+
+fn center(r: Rect) -> [f64; 2] {
+    [(r[0] + r[2]) / 2.0, (r[1] + r[3]) / 2.0]
+}
+
+fn intersects(a: Rect, b: Rect) -> bool {
+    a[0] <= b[2] && b[0] <= a[2] && a[1] <= b[3] && b[1] <= a[3]
+}
+
+/// Squared distance from a point to the nearest edge of a rectangle (0 inside)
+fn rect_distance(r: Rect, p: [f64; 2]) -> f64 {
+    let dx = (r[0] - p[0]).max(0.0).max(p[0] - r[2]);
+    let dy = (r[1] - p[1]).max(0.0).max(p[1] - r[3]);
+    dx * dx + dy * dy
+}
+
+/// Sort-Tile-Recursive order: vertical slices by x, each sorted by y
+fn str_order<T, F: Fn(&T) -> [f64; 2]>(items: &mut [T], key: F) {
+    let leaves = items.len().div_ceil(NODE_CAPACITY);
+    let slices = (leaves as f64).sqrt().ceil().max(1.0) as usize;
+    items.sort_by(|a, b| key(a)[0].total_cmp(&key(b)[0]));
+    for slice in items.chunks_mut(slices * NODE_CAPACITY) {
+        slice.sort_by(|a, b| key(a)[1].total_cmp(&key(b)[1]));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Entry {
+    Node(usize),
+    Item(usize),
+}
+
+/// Min-heap entry ordered by distance
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    dist: f64,
+    entry: Entry,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.dist.total_cmp(&self.dist)
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_f64<R: Read>(input: &mut R) -> io::Result<f64> {
+    Ok(f64::from_bits(read_u64(input)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_soundings(n: usize, seed: u64) -> Vec<Sounding> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n)
+            .map(|i| {
+                let mut s = Sounding::new(
+                    i as f64,
+                    rng.gen_range(60.0..60.05),
+                    rng.gen_range(-150.1..-150.0),
+                    rng.gen_range(1.0..40.0),
+                );
+                if i % 5 == 0 {
+                    s.reference = VerticalReference::ChartDatum;
+                }
+                s
+            })
+            .collect()
+    }
+
+    /// Meters between a sounding and a position, measured the way the index does
+    fn distance(index: &SoundingIndex, s: &Sounding, lat: f64, lon: f64) -> f64 {
+        ((s.lon - lon) * index.lon_scale).hypot(s.lat - lat) * METERS_PER_DEGREE_LAT
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let soundings = random_soundings(5000, 369);
+        let index = SoundingIndex::build(&soundings);
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..200 {
+            let (lat, lon) = (rng.gen_range(59.99..60.06), rng.gen_range(-150.11..-149.99));
+            let mut expected: Vec<(usize, f64)> = soundings
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (i, distance(&index, s, lat, lon)))
+                .collect();
+            expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+            let (i, d) = index.nearest(lat, lon).unwrap();
+            assert!((d - expected[0].1).abs() < 1e-6);
+            assert_eq!(index.nearest_depth(lat, lon), Some(soundings[i].depth_m));
+            let found = index.nearest_n(lat, lon, 10);
+            assert_eq!(found.len(), 10);
+            for (got, want) in found.iter().zip(&expected) {
+                assert!((got.1 - want.1).abs() < 1e-6);
+                assert!((distance(&index, &soundings[got.0], lat, lon) - got.1).abs() < 1e-6);
+            }
+        }
+        assert!(index.nearest_n(60.0, -150.0, 0).is_empty());
+        assert_eq!(index.nearest_n(60.0, -150.0, 10_000).len(), 5000);
+    }
+
+    #[test]
+    fn area_queries_match_brute_force() {
+        let soundings = random_soundings(5000, 370);
+        let index = SoundingIndex::build(&soundings);
+        let all = |keep: &dyn Fn(&Sounding) -> bool| -> Vec<usize> {
+            (0..soundings.len()).filter(|&i| keep(&soundings[i])).collect()
+        };
+
+        let (lat, lon) = (60.02, -150.05);
+        let found = index.within_radius(lat, lon, 500.0);
+        assert!(found.len() > 50);
+        assert_eq!(found, all(&|s| distance(&index, s, lat, lon) <= 500.0));
+
+        let bbox = (60.01, -150.08, 60.03, -150.02);
+        let found = index.within_bbox(bbox);
+        assert!(found.len() > 50);
+        let inside = |s: &Sounding| s.lat >= bbox.0 && s.lat <= bbox.2 && s.lon >= bbox.1 && s.lon <= bbox.3;
+        assert_eq!(found, all(&inside));
+
+        let triangle = [(60.0, -150.1), (60.05, -150.05), (60.0, -150.0)];
+        let found = index.within_polygon(&triangle);
+        assert!(found.len() > 50);
+        assert_eq!(found, all(&|s| point_in_polygon(s.lat, s.lon, &triangle)));
+        assert!(index.within_bbox((10.0, 10.0, 11.0, 11.0)).is_empty());
+    }
+
+    #[test]
+    fn handles_empty_and_degenerate_input() {
+        let empty = SoundingIndex::build(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.nearest(60.0, -150.0), None);
+        assert!(empty.within_radius(60.0, -150.0, 1e6).is_empty());
+
+        // Every sounding at the same spot
+        let same = vec![Sounding::new(0.0, 60.0, -150.0, 5.0); 100];
+        let index = SoundingIndex::build(&same);
+        assert_eq!(index.within_radius(60.0, -150.0, 0.0).len(), 100);
+        assert_eq!(index.nearest(60.0, -150.0).unwrap().1, 0.0);
+        assert_eq!(index.sounding(99), Some(&same[99]));
+        assert_eq!(index.sounding(100), None);
+    }
+
+    #[test]
+    fn saved_index_round_trips() {
+        let soundings = random_soundings(1000, 371);
+        let index = SoundingIndex::build(&soundings);
+        let path = scratch("spatial", "round_trip.idx");
+        index.save(&path).unwrap();
+        let loaded = SoundingIndex::load(&path).unwrap();
+        assert_eq!(loaded, index);
+        assert_eq!(loaded.soundings(), &soundings[..]);
+        assert_eq!(
+            loaded.within_radius(60.02, -150.05, 300.0),
+            index.within_radius(60.02, -150.05, 300.0)
+        );
+
+        SoundingIndex::build(&[]).save(&path).unwrap();
+        assert!(SoundingIndex::load(&path).unwrap().is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_reads_version_1_and_names_newer_versions() {
+        let path = scratch("spatial", "versions.idx");
+        let mut soundings = random_soundings(500, 406);
+        for s in &mut soundings {
+            s.reference = VerticalReference::Transducer;
+        }
+        let index = SoundingIndex::build(&soundings);
+        index.save(&path).unwrap();
+        let v2 = std::fs::read(&path).unwrap();
+
+        // Version 1 is version 2 without the reference byte after each sounding
+        let header = 4 + 4 + 8 + 8;
+        let mut v1 = v2[..header].to_vec();
+        v1[4..8].copy_from_slice(&1u32.to_le_bytes());
+        for record in v2[header..header + 500 * 41].chunks(41) {
+            v1.extend_from_slice(&record[..40]);
+        }
+        v1.extend_from_slice(&v2[header + 500 * 41..]);
+        std::fs::write(&path, &v1).unwrap();
+        let loaded = SoundingIndex::load(&path).unwrap();
+        assert_eq!(loaded.soundings, index.soundings);
+        assert_eq!(loaded.nodes, index.nodes);
+        assert_eq!(loaded.nearest(60.02, -150.05), index.nearest(60.02, -150.05));
+
+        for version in [0u32, 3] {
+            let mut other = v2.clone();
+            other[4..8].copy_from_slice(&version.to_le_bytes());
+            std::fs::write(&path, &other).unwrap();
+            let err = SoundingIndex::load(&path).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+            assert_eq!(err.to_string(), format!("unsupported index version {}, rebuild the index", version));
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn load_rejects_malformed_files() {
+        let path = scratch("spatial", "malformed.idx");
+        let index = SoundingIndex::build(&random_soundings(300, 372));
+        index.save(&path).unwrap();
+        let good = std::fs::read(&path).unwrap();
+        let load = |data: &[u8]| {
+            std::fs::write(&path, data).unwrap();
+            SoundingIndex::load(&path)
+        };
+
+        let mut magic = good.clone();
+        magic[..4].copy_from_slice(b"NOPE");
+        assert_eq!(load(&magic).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            load(&good[..good.len() - 3]).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        assert_eq!(load(&good[..2]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        // Offsets of the node table and of the root node's `first` field
+        let nodes_at = 4 + 4 + 8 + 8 + 300 * 41 + 300 * 4;
+        let root = index.nodes.len() - 1;
+        let first_at = nodes_at + 8 + root * 41 + 32;
+        let mut cycle = good.clone();
+        cycle[first_at..first_at + 4].copy_from_slice(&(root as u32).to_le_bytes());
+        assert_eq!(load(&cycle).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut reference = good.clone();
+        reference[4 + 4 + 8 + 8 + 40] = 9;
+        assert_eq!(load(&reference).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut order = good.clone();
+        let order_at = 4 + 4 + 8 + 8 + 300 * 41;
+        order[order_at..order_at + 4].copy_from_slice(&300u32.to_le_bytes());
+        assert_eq!(load(&order).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,1107 @@
+// Minimal SQLite 3 database encoder and table reader
+// src/sqlite.rs
+
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+const PAGE_SIZE: usize = 4096;
+/// Bytes before the b-tree header on page 1
+const FILE_HEADER_SIZE: usize = 100;
+/// Version number recorded as the last writer (3.45.0)
+const SQLITE_VERSION: u32 = 3_045_000;
+
+const INTERIOR_INDEX: u8 = 0x02;
+const INTERIOR_TABLE: u8 = 0x05;
+const LEAF_INDEX: u8 = 0x0a;
+const LEAF_TABLE: u8 = 0x0d;
+
+/// Largest payload kept on a table leaf page before spilling to overflow pages
+const TABLE_MAX_LOCAL: usize = PAGE_SIZE - 35;
+const INDEX_MAX_LOCAL: usize = (PAGE_SIZE - 12) * 64 / 255 - 23;
+const MIN_LOCAL: usize = (PAGE_SIZE - 12) * 32 / 255 - 23;
+
+/// A column value in an inserted row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value<'a> {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(&'a str),
+    Blob(&'a [u8]),
+}
+
+/// Column value with its own storage, as read back or held as an index key
+///
+/// Sorts in SQLite's BINARY collation order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl OwnedValue {
+    fn from_value(value: &Value) -> Self {
+        match *value {
+            Value::Null => Self::Null,
+            Value::Integer(v) => Self::Integer(v),
+            Value::Real(v) => Self::Real(v),
+            Value::Text(v) => Self::Text(v.to_string()),
+            Value::Blob(v) => Self::Blob(v.to_vec()),
+        }
+    }
+
+    pub fn as_value(&self) -> Value<'_> {
+        match self {
+            Self::Null => Value::Null,
+            Self::Integer(v) => Value::Integer(*v),
+            Self::Real(v) => Value::Real(*v),
+            Self::Text(v) => Value::Text(v),
+            Self::Blob(v) => Value::Blob(v),
+        }
+    }
+
+    fn class(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Integer(_) | Self::Real(_) => 1,
+            Self::Text(_) => 2,
+            Self::Blob(_) => 3,
+        }
+    }
+
+    fn compare(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Integer(a), Self::Integer(b)) => a.cmp(b),
+            (Self::Integer(a), Self::Real(b)) => (*a as f64).total_cmp(b),
+            (Self::Real(a), Self::Integer(b)) => a.total_cmp(&(*b as f64)),
+            (Self::Real(a), Self::Real(b)) => a.total_cmp(b),
+            (Self::Text(a), Self::Text(b)) => a.as_bytes().cmp(b.as_bytes()),
+            (Self::Blob(a), Self::Blob(b)) => a.cmp(b),
+            _ => self.class().cmp(&other.class()),
+        }
+    }
+}
+
+struct Table {
+    name: String,
+    sql: String,
+    next_rowid: i64,
+    /// Cells of the leaf page being filled
+    cells: Vec<Vec<u8>>,
+    used: usize,
+    /// Written leaf pages with the largest rowid on each
+    leaves: Vec<(u32, i64)>,
+}
+
+struct Index {
+    name: String,
+    table: usize,
+    sql: String,
+    columns: Vec<usize>,
+    /// Key columns followed by the rowid
+    entries: Vec<Vec<OwnedValue>>,
+}
+
+/// Table handle returned by [`SqliteWriter::create_table`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableId(usize);
+
+/// Streams rows into a new SQLite database file
+///
+/// Rows are appended in rowid order and leaf pages are written as they
+/// fill, so memory stays flat however large the blobs are. Index keys are
+/// held until [`SqliteWriter::finish`], which sorts them and writes the
+/// index b-trees and the schema. The writer produces a fresh database only;
+/// it cannot open or update an existing one.
+pub struct SqliteWriter<W: Write + Seek> {
+    out: W,
+    pages: u32,
+    tables: Vec<Table>,
+    indexes: Vec<Index>,
+    application_id: u32,
+}
+
+impl<W: Write + Seek> SqliteWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            // Page 1 holds the file header and schema and is written last
+            pages: 1,
+            tables: Vec::new(),
+            indexes: Vec::new(),
+            application_id: 0,
+        }
+    }
+
+    /// Application id stored in the file header, e.g. `0x4d504258` for MBTiles
+    pub fn set_application_id(&mut self, id: u32) {
+        self.application_id = id;
+    }
+
+    /// Declare a table; `sql` is the `CREATE TABLE` statement stored in the schema
+    pub fn create_table(&mut self, name: &str, sql: &str) -> TableId {
+        self.tables.push(Table {
+            name: name.to_string(),
+            sql: sql.to_string(),
+            next_rowid: 1,
+            cells: Vec::new(),
+            used: 0,
+            leaves: Vec::new(),
+        });
+        TableId(self.tables.len() - 1)
+    }
+
+    /// Declare an index over column positions of `table`
+    ///
+    /// Rows must already satisfy a `UNIQUE` constraint in `sql`; the writer
+    /// does not check it.
+    pub fn create_index(&mut self, name: &str, table: TableId, sql: &str, columns: &[usize]) {
+        self.indexes.push(Index {
+            name: name.to_string(),
+            table: table.0,
+            sql: sql.to_string(),
+            columns: columns.to_vec(),
+            entries: Vec::new(),
+        });
+    }
+
+    /// Append a row, returning its rowid
+    pub fn insert(&mut self, table: TableId, row: &[Value]) -> io::Result<i64> {
+        let rowid = self.tables[table.0].next_rowid;
+        for index in self.indexes.iter_mut().filter(|index| index.table == table.0) {
+            let mut key = Vec::with_capacity(index.columns.len() + 1);
+            for &column in &index.columns {
+                let value = row.get(column).ok_or_else(|| invalid("index column outside the row"))?;
+                key.push(OwnedValue::from_value(value));
+            }
+            key.push(OwnedValue::Integer(rowid));
+            index.entries.push(key);
+        }
+
+        let payload = record(row);
+        let local = local_size(payload.len(), TABLE_MAX_LOCAL);
+        let mut cell = Vec::with_capacity(local + 18);
+        put_varint(&mut cell, payload.len() as u64);
+        put_varint(&mut cell, rowid as u64);
+        cell.extend_from_slice(&payload[..local]);
+        if local < payload.len() {
+            let first = self.write_overflow(&payload[local..])?;
+            cell.extend_from_slice(&first.to_be_bytes());
+        }
+
+        if self.tables[table.0].used + cell.len() + 2 > PAGE_SIZE - 8 {
+            self.flush_leaf(table.0)?;
+        }
+        let t = &mut self.tables[table.0];
+        t.used += cell.len() + 2;
+        t.cells.push(cell);
+        t.next_rowid += 1;
+        Ok(rowid)
+    }
+
+    /// Write the remaining pages and the schema, returning the output
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut schema = Vec::new();
+        for t in 0..self.tables.len() {
+            if !self.tables[t].cells.is_empty() || self.tables[t].leaves.is_empty() {
+                self.flush_leaf(t)?;
+            }
+            let leaves = std::mem::take(&mut self.tables[t].leaves);
+            let root = self.build_table(leaves)?;
+            let table = &self.tables[t];
+            schema.push(("table", table.name.clone(), table.name.clone(), root, table.sql.clone()));
+        }
+        for i in 0..self.indexes.len() {
+            let mut entries = std::mem::take(&mut self.indexes[i].entries);
+            entries.sort_by(|a, b| {
+                a.iter()
+                    .zip(b)
+                    .map(|(x, y)| x.compare(y))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+            let records: Vec<Vec<u8>> = entries
+                .iter()
+                .map(|key| record(&key.iter().map(OwnedValue::as_value).collect::<Vec<_>>()))
+                .collect();
+            let root = self.build_index(records)?;
+            let index = &self.indexes[i];
+            let table = self.tables[index.table].name.clone();
+            schema.push(("index", index.name.clone(), table, root, index.sql.clone()));
+        }
+
+        let mut cells = Vec::new();
+        for (rowid, (kind, name, table, root, sql)) in schema.iter().enumerate() {
+            let payload = record(&[
+                Value::Text(kind),
+                Value::Text(name),
+                Value::Text(table),
+                Value::Integer(*root as i64),
+                Value::Text(sql),
+            ]);
+            if payload.len() > TABLE_MAX_LOCAL {
+                return Err(invalid("schema statement too long"));
+            }
+            let mut cell = Vec::new();
+            put_varint(&mut cell, payload.len() as u64);
+            put_varint(&mut cell, rowid as u64 + 1);
+            cell.extend_from_slice(&payload);
+            cells.push(cell);
+        }
+        if cells.iter().map(|cell| cell.len() + 2).sum::<usize>() > PAGE_SIZE - FILE_HEADER_SIZE - 8 {
+            return Err(invalid("schema does not fit on the first page"));
+        }
+
+        let mut page = btree_page(LEAF_TABLE, &cells, None, FILE_HEADER_SIZE);
+        page[..FILE_HEADER_SIZE].copy_from_slice(&self.file_header());
+        self.write_page(1, &page)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn file_header(&self) -> [u8; FILE_HEADER_SIZE] {
+        let mut h = [0u8; FILE_HEADER_SIZE];
+        h[..16].copy_from_slice(b"SQLite format 3\0");
+        h[16..18].copy_from_slice(&(PAGE_SIZE as u16).to_be_bytes());
+        h[18] = 1; // legacy journal write/read versions
+        h[19] = 1;
+        h[21] = 64; // payload fractions, fixed by the format
+        h[22] = 32;
+        h[23] = 32;
+        h[24..28].copy_from_slice(&1u32.to_be_bytes()); // change counter
+        h[28..32].copy_from_slice(&self.pages.to_be_bytes());
+        h[40..44].copy_from_slice(&1u32.to_be_bytes()); // schema cookie
+        h[44..48].copy_from_slice(&4u32.to_be_bytes()); // schema format
+        h[56..60].copy_from_slice(&1u32.to_be_bytes()); // UTF-8
+        h[68..72].copy_from_slice(&self.application_id.to_be_bytes());
+        h[92..96].copy_from_slice(&1u32.to_be_bytes()); // version-valid-for
+        h[96..100].copy_from_slice(&SQLITE_VERSION.to_be_bytes());
+        h
+    }
+
+    fn allocate(&mut self) -> u32 {
+        self.pages += 1;
+        self.pages
+    }
+
+    fn write_page(&mut self, number: u32, page: &[u8]) -> io::Result<()> {
+        self.out.seek(SeekFrom::Start((number as u64 - 1) * PAGE_SIZE as u64))?;
+        self.out.write_all(page)
+    }
+
+    /// Write a chain of overflow pages, returning the first page number
+    fn write_overflow(&mut self, mut data: &[u8]) -> io::Result<u32> {
+        let first = self.allocate();
+        let mut number = first;
+        loop {
+            let take = data.len().min(PAGE_SIZE - 4);
+            let next = if take < data.len() { self.allocate() } else { 0 };
+            let mut page = vec![0u8; PAGE_SIZE];
+            page[..4].copy_from_slice(&next.to_be_bytes());
+            page[4..4 + take].copy_from_slice(&data[..take]);
+            self.write_page(number, &page)?;
+            data = &data[take..];
+            if next == 0 {
+                return Ok(first);
+            }
+            number = next;
+        }
+    }
+
+    fn flush_leaf(&mut self, table: usize) -> io::Result<()> {
+        let cells = std::mem::take(&mut self.tables[table].cells);
+        self.tables[table].used = 0;
+        let number = self.allocate();
+        self.write_page(number, &btree_page(LEAF_TABLE, &cells, None, 0))?;
+        let last = self.tables[table].next_rowid - 1;
+        self.tables[table].leaves.push((number, last));
+        Ok(())
+    }
+
+    /// Interior levels above a table's leaves, returning the root page
+    fn build_table(&mut self, mut level: Vec<(u32, i64)>) -> io::Result<u32> {
+        while level.len() > 1 {
+            let cells: Vec<Vec<u8>> = level
+                .iter()
+                .map(|&(child, key)| {
+                    let mut cell = child.to_be_bytes().to_vec();
+                    put_varint(&mut cell, key as u64);
+                    cell
+                })
+                .collect();
+            // Each page takes cells for its left children; the child after them is its right pointer
+            let ranges = partition(&cells[..cells.len() - 1], PAGE_SIZE - 12);
+            let mut parent = Vec::with_capacity(ranges.len());
+            for range in ranges {
+                let (right, key) = level[range.end];
+                let number = self.allocate();
+                self.write_page(number, &btree_page(INTERIOR_TABLE, &cells[range], Some(right), 0))?;
+                parent.push((number, key));
+            }
+            level = parent;
+        }
+        Ok(level[0].0)
+    }
+
+    /// Index b-tree over sorted records, returning the root page
+    ///
+    /// Interior cells hold keys of their own, so the record after each full
+    /// page moves up a level rather than being stored twice.
+    fn build_index(&mut self, records: Vec<Vec<u8>>) -> io::Result<u32> {
+        let mut cells = Vec::with_capacity(records.len());
+        for payload in &records {
+            if payload.len() > INDEX_MAX_LOCAL {
+                return Err(invalid("index key too long"));
+            }
+            let mut cell = Vec::with_capacity(payload.len() + 2);
+            put_varint(&mut cell, payload.len() as u64);
+            cell.extend_from_slice(payload);
+            cells.push(cell);
+        }
+
+        let mut children = Vec::new();
+        let mut keys = Vec::new();
+        let ranges = partition(&cells, PAGE_SIZE - 8);
+        for range in ranges {
+            let number = self.allocate();
+            self.write_page(number, &btree_page(LEAF_INDEX, &cells[range.clone()], None, 0))?;
+            children.push(number);
+            if range.end < cells.len() {
+                keys.push(cells[range.end].clone());
+            }
+        }
+
+        while children.len() > 1 {
+            let cells: Vec<Vec<u8>> = keys
+                .iter()
+                .zip(&children)
+                .map(|(key, child)| {
+                    let mut cell = child.to_be_bytes().to_vec();
+                    cell.extend_from_slice(key);
+                    cell
+                })
+                .collect();
+            let (mut parent, mut parent_keys) = (Vec::new(), Vec::new());
+            for range in partition(&cells, PAGE_SIZE - 12) {
+                let number = self.allocate();
+                let right = children[range.end];
+                self.write_page(
+                    number,
+                    &btree_page(INTERIOR_INDEX, &cells[range.clone()], Some(right), 0),
+                )?;
+                parent.push(number);
+                if range.end < cells.len() {
+                    parent_keys.push(keys[range.end].clone());
+                }
+            }
+            children = parent;
+            keys = parent_keys;
+        }
+        Ok(children[0])
+    }
+}
+
+/// Split cells into pages of at most `space` bytes
+///
+/// The cell just past each range (except the last) separates that page from
+/// the next and is not part of either. Every page gets at least one cell.
+fn partition(cells: &[Vec<u8>], space: usize) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    loop {
+        let (mut end, mut used) = (start, 0);
+        while end < cells.len() && used + cells[end].len() + 2 <= space {
+            used += cells[end].len() + 2;
+            end += 1;
+        }
+        if end == cells.len() {
+            ranges.push(start..end);
+            return ranges;
+        }
+        // Leave a cell for the last page rather than ending on an empty one
+        if end + 1 == cells.len() && end - start > 1 {
+            end -= 1;
+        }
+        ranges.push(start..end);
+        start = end + 1;
+    }
+}
+
+/// A b-tree page with `cells` packed against its end
+fn btree_page(kind: u8, cells: &[Vec<u8>], right: Option<u32>, offset: usize) -> Vec<u8> {
+    let mut page = vec![0u8; PAGE_SIZE];
+    let header = if right.is_some() { 12 } else { 8 };
+    let mut content = PAGE_SIZE;
+    for (i, cell) in cells.iter().enumerate() {
+        content -= cell.len();
+        page[content..content + cell.len()].copy_from_slice(cell);
+        let pointer = offset + header + 2 * i;
+        page[pointer..pointer + 2].copy_from_slice(&(content as u16).to_be_bytes());
+    }
+    page[offset] = kind;
+    page[offset + 3..offset + 5].copy_from_slice(&(cells.len() as u16).to_be_bytes());
+    page[offset + 5..offset + 7].copy_from_slice(&(content as u16).to_be_bytes());
+    if let Some(right) = right {
+        page[offset + 8..offset + 12].copy_from_slice(&right.to_be_bytes());
+    }
+    page
+}
+
+/// Bytes of a payload stored on the b-tree page itself
+fn local_size(payload: usize, max_local: usize) -> usize {
+    if payload <= max_local {
+        return payload;
+    }
+    let spill = MIN_LOCAL + (payload - MIN_LOCAL) % (PAGE_SIZE - 4);
+    if spill <= max_local {
+        spill
+    } else {
+        MIN_LOCAL
+    }
+}
+
+/// Record format: a header of serial types followed by the values
+fn record(values: &[Value]) -> Vec<u8> {
+    let mut types = Vec::new();
+    let mut body = Vec::new();
+    for value in values {
+        let serial = match *value {
+            Value::Null => 0,
+            Value::Integer(0) => 8,
+            Value::Integer(1) => 9,
+            Value::Integer(v) => {
+                let (serial, width) = match v {
+                    -0x80..=0x7f => (1, 1),
+                    -0x8000..=0x7fff => (2, 2),
+                    -0x80_0000..=0x7f_ffff => (3, 3),
+                    -0x8000_0000..=0x7fff_ffff => (4, 4),
+                    -0x8000_0000_0000..=0x7fff_ffff_ffff => (5, 6),
+                    _ => (6, 8),
+                };
+                body.extend_from_slice(&v.to_be_bytes()[8 - width..]);
+                serial
+            }
+            Value::Real(v) => {
+                body.extend_from_slice(&v.to_be_bytes());
+                7
+            }
+            Value::Text(v) => {
+                body.extend_from_slice(v.as_bytes());
+                13 + 2 * v.len() as u64
+            }
+            Value::Blob(v) => {
+                body.extend_from_slice(v);
+                12 + 2 * v.len() as u64
+            }
+        };
+        put_varint(&mut types, serial);
+    }
+    // The header length counts its own varint
+    let mut header_len = types.len() + 1;
+    if varint_len(header_len as u64) > 1 {
+        header_len += varint_len(header_len as u64 + 1) - 1;
+    }
+    let mut out = Vec::with_capacity(header_len + body.len());
+    put_varint(&mut out, header_len as u64);
+    out.extend_from_slice(&types);
+    out.extend_from_slice(&body);
+    out
+}
+
+fn varint_len(value: u64) -> usize {
+    let mut bytes = Vec::new();
+    put_varint(&mut bytes, value);
+    bytes.len()
+}
+
+/// Big-endian base-128 varint; the ninth byte, when needed, carries 8 bits
+fn put_varint(out: &mut Vec<u8>, value: u64) {
+    if value >> 56 != 0 {
+        for shift in (1..=8).rev() {
+            out.push(((value >> (shift * 7 + 1)) as u8 & 0x7f) | 0x80);
+        }
+        out.push(value as u8);
+        return;
+    }
+    let mut groups = [0u8; 8];
+    let mut n = 0;
+    let mut v = value;
+    loop {
+        groups[n] = (v & 0x7f) as u8;
+        n += 1;
+        v >>= 7;
+        if v == 0 {
+            break;
+        }
+    }
+    for i in (0..n).rev() {
+        out.push(groups[i] | if i > 0 { 0x80 } else { 0 });
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Reads table rows back from an SQLite database file
+///
+/// The whole file is held in memory, which suits catalogs and other small
+/// databases. Only table b-trees are walked: indexes are ignored, and a
+/// database in WAL mode must be checkpointed first. Malformed files give
+/// `InvalidData` errors rather than panics.
+pub struct SqliteReader {
+    data: Vec<u8>,
+    page_size: usize,
+    /// Page size less the reserved bytes at the end of each page
+    usable: usize,
+}
+
+impl SqliteReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> io::Result<Self> {
+        if data.len() < FILE_HEADER_SIZE || !data.starts_with(b"SQLite format 3\0") {
+            return Err(corrupt("not an SQLite database"));
+        }
+        let page_size = match u16::from_be_bytes([data[16], data[17]]) {
+            1 => 65536,
+            n if n >= 512 && n.is_power_of_two() => n as usize,
+            n => return Err(corrupt(&format!("invalid page size {}", n))),
+        };
+        let usable = page_size - data[20] as usize;
+        if usable < 480 {
+            return Err(corrupt("too many reserved bytes per page"));
+        }
+        let pages = u32::from_be_bytes([data[28], data[29], data[30], data[31]]) as usize;
+        if data.len() < pages.saturating_mul(page_size) {
+            return Err(corrupt("file is shorter than its header says"));
+        }
+        if u32::from_be_bytes([data[56], data[57], data[58], data[59]]) > 1 {
+            return Err(corrupt("UTF-16 databases are not supported"));
+        }
+        Ok(Self {
+            data,
+            page_size,
+            usable,
+        })
+    }
+
+    /// Application id from the file header
+    pub fn application_id(&self) -> u32 {
+        u32::from_be_bytes([self.data[68], self.data[69], self.data[70], self.data[71]])
+    }
+
+    /// `CREATE` statement of table `name`, matched case-insensitively
+    pub fn table_sql(&self, name: &str) -> io::Result<Option<String>> {
+        Ok(self.schema_entry(name)?.map(|(_, sql)| sql))
+    }
+
+    /// `(rowid, columns)` of every row of table `name`, in rowid order
+    pub fn rows(&self, name: &str) -> io::Result<Vec<(i64, Vec<OwnedValue>)>> {
+        let (root, _) = self
+            .schema_entry(name)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no table named '{}'", name)))?;
+        self.table_rows(root)
+    }
+
+    /// Root page and SQL of a table in the schema
+    fn schema_entry(&self, name: &str) -> io::Result<Option<(u32, String)>> {
+        for (_, row) in self.table_rows(1)? {
+            if let [OwnedValue::Text(kind), OwnedValue::Text(table), _, OwnedValue::Integer(root), sql, ..] = &row[..] {
+                if kind == "table" && table.eq_ignore_ascii_case(name) {
+                    let root = u32::try_from(*root).map_err(|_| corrupt("invalid root page"))?;
+                    let sql = match sql {
+                        OwnedValue::Text(sql) => sql.clone(),
+                        _ => String::new(),
+                    };
+                    return Ok(Some((root, sql)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn page_count(&self) -> usize {
+        self.data.len() / self.page_size
+    }
+
+    fn page(&self, number: u32) -> io::Result<&[u8]> {
+        let start = (number as usize)
+            .checked_sub(1)
+            .map(|n| n * self.page_size)
+            .ok_or_else(|| corrupt("page number 0"))?;
+        self.data
+            .get(start..start + self.page_size)
+            .ok_or_else(|| corrupt("page number past the end of the file"))
+    }
+
+    fn table_rows(&self, root: u32) -> io::Result<Vec<(i64, Vec<OwnedValue>)>> {
+        let mut rows = Vec::new();
+        let mut visited = vec![false; self.page_count() + 1];
+        self.walk(root, &mut visited, &mut rows)?;
+        Ok(rows)
+    }
+
+    /// Collect the rows under one table b-tree page, depth first
+    fn walk(&self, number: u32, visited: &mut [bool], rows: &mut Vec<(i64, Vec<OwnedValue>)>) -> io::Result<()> {
+        // A page reached twice means the tree has a cycle
+        match visited.get_mut(number as usize) {
+            Some(seen) if !*seen => *seen = true,
+            Some(_) => return Err(corrupt("b-tree page referenced twice")),
+            None => return Err(corrupt("page number past the end of the file")),
+        }
+        let page = self.page(number)?;
+        let offset = if number == 1 { FILE_HEADER_SIZE } else { 0 };
+        let kind = page[offset];
+        let count = u16::from_be_bytes([page[offset + 3], page[offset + 4]]) as usize;
+        let cell = |header: usize, i: usize| -> io::Result<&[u8]> {
+            let at = offset + header + 2 * i;
+            let pointer = page
+                .get(at..at + 2)
+                .ok_or_else(|| corrupt("cell pointer outside the page"))?;
+            let start = u16::from_be_bytes([pointer[0], pointer[1]]) as usize;
+            page.get(start..self.usable)
+                .ok_or_else(|| corrupt("cell outside the page"))
+        };
+        match kind {
+            INTERIOR_TABLE => {
+                for i in 0..count {
+                    let child = cell(12, i)?.get(..4).ok_or_else(|| corrupt("truncated cell"))?;
+                    self.walk(u32::from_be_bytes(child.try_into().expect("4 bytes")), visited, rows)?;
+                }
+                let right = u32::from_be_bytes(page[offset + 8..offset + 12].try_into().expect("4 bytes"));
+                self.walk(right, visited, rows)
+            }
+            LEAF_TABLE => {
+                for i in 0..count {
+                    let cell = cell(8, i)?;
+                    let (size, n) = read_varint(cell)?;
+                    let (rowid, m) = read_varint(&cell[n..])?;
+                    let payload = self.payload(&cell[n + m..], size, visited)?;
+                    rows.push((rowid as i64, decode_record(&payload)?));
+                }
+                Ok(())
+            }
+            _ => Err(corrupt(&format!("page {} is not a table b-tree page", number))),
+        }
+    }
+
+    /// Payload of `size` bytes whose local part starts at `cell`, following overflow pages
+    fn payload(&self, cell: &[u8], size: u64, visited: &mut [bool]) -> io::Result<Vec<u8>> {
+        let size = usize::try_from(size).map_err(|_| corrupt("payload too large"))?;
+        if size > self.data.len() {
+            return Err(corrupt("payload larger than the file"));
+        }
+        let max_local = self.usable - 35;
+        let min_local = (self.usable - 12) * 32 / 255 - 23;
+        let local = if size <= max_local {
+            size
+        } else {
+            let spill = min_local + (size - min_local) % (self.usable - 4);
+            if spill <= max_local {
+                spill
+            } else {
+                min_local
+            }
+        };
+        let mut out = cell.get(..local).ok_or_else(|| corrupt("truncated cell"))?.to_vec();
+        if local < size {
+            let pointer = cell.get(local..local + 4).ok_or_else(|| corrupt("truncated cell"))?;
+            let mut next = u32::from_be_bytes(pointer.try_into().expect("4 bytes"));
+            while out.len() < size {
+                match visited.get_mut(next as usize) {
+                    Some(seen) if !*seen && next != 0 => *seen = true,
+                    _ => return Err(corrupt("broken overflow chain")),
+                }
+                let page = self.page(next)?;
+                let take = (size - out.len()).min(self.usable - 4);
+                out.extend_from_slice(&page[4..4 + take]);
+                next = u32::from_be_bytes(page[..4].try_into().expect("4 bytes"));
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Varint at the start of `bytes` and its length
+fn read_varint(bytes: &[u8]) -> io::Result<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &byte) in bytes.iter().enumerate().take(9) {
+        if i == 8 {
+            return Ok(((value << 8) | byte as u64, 9));
+        }
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err(corrupt("truncated varint"))
+}
+
+/// Column values of a record
+fn decode_record(record: &[u8]) -> io::Result<Vec<OwnedValue>> {
+    let (header_len, mut at) = read_varint(record)?;
+    let header_len = usize::try_from(header_len)
+        .ok()
+        .filter(|&n| n >= at && n <= record.len())
+        .ok_or_else(|| corrupt("record header runs past the record"))?;
+    let mut body = header_len;
+    let mut values = Vec::new();
+    while at < header_len {
+        let (serial, n) = read_varint(&record[at..header_len])?;
+        at += n;
+        let width = match serial {
+            0 | 8 | 9 => 0,
+            1..=4 => serial as usize,
+            5 => 6,
+            6 | 7 => 8,
+            10 | 11 => return Err(corrupt("reserved serial type")),
+            s => usize::try_from((s - 12) / 2).map_err(|_| corrupt("value too large"))?,
+        };
+        let bytes = record
+            .get(body..body.saturating_add(width))
+            .ok_or_else(|| corrupt("record value runs past the record"))?;
+        body += width;
+        values.push(match serial {
+            0 => OwnedValue::Null,
+            8 => OwnedValue::Integer(0),
+            9 => OwnedValue::Integer(1),
+            7 => OwnedValue::Real(f64::from_be_bytes(bytes.try_into().expect("8 bytes"))),
+            1..=6 => {
+                let fill = if bytes[0] & 0x80 != 0 { 0xff } else { 0 };
+                let mut be = [fill; 8];
+                be[8 - width..].copy_from_slice(bytes);
+                OwnedValue::Integer(i64::from_be_bytes(be))
+            }
+            s if s % 2 == 1 => OwnedValue::Text(String::from_utf8_lossy(bytes).into_owned()),
+            _ => OwnedValue::Blob(bytes.to_vec()),
+        });
+    }
+    Ok(values)
+}
+
+fn corrupt(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn decode(record: &[u8]) -> Vec<OwnedValue> {
+        decode_record(record).unwrap()
+    }
+
+    /// Just enough of a reader to walk what the writer produced
+    struct Db(Vec<u8>);
+
+    impl Db {
+        fn page(&self, number: u32) -> &[u8] {
+            &self.0[(number as usize - 1) * PAGE_SIZE..number as usize * PAGE_SIZE]
+        }
+
+        fn cells(&self, number: u32) -> (u8, Vec<&[u8]>, u32) {
+            let page = self.page(number);
+            let offset = if number == 1 { FILE_HEADER_SIZE } else { 0 };
+            let kind = page[offset];
+            let count = u16::from_be_bytes([page[offset + 3], page[offset + 4]]) as usize;
+            let interior = kind == INTERIOR_INDEX || kind == INTERIOR_TABLE;
+            let header = if interior { 12 } else { 8 };
+            let right = if interior {
+                u32::from_be_bytes(page[offset + 8..offset + 12].try_into().unwrap())
+            } else {
+                0
+            };
+            let cells = (0..count)
+                .map(|i| {
+                    let at = offset + header + 2 * i;
+                    &page[u16::from_be_bytes([page[at], page[at + 1]]) as usize..]
+                })
+                .collect();
+            (kind, cells, right)
+        }
+
+        /// Payload starting at `cell`, following the overflow chain
+        fn payload(&self, cell: &[u8], max_local: usize) -> Vec<u8> {
+            let (size, n) = read_varint(cell).unwrap();
+            let size = size as usize;
+            let local = local_size(size, max_local);
+            let mut out = cell[n..n + local].to_vec();
+            let mut next = if local < size {
+                u32::from_be_bytes(cell[n + local..n + local + 4].try_into().unwrap())
+            } else {
+                0
+            };
+            while next != 0 {
+                let page = self.page(next);
+                let take = (size - out.len()).min(PAGE_SIZE - 4);
+                out.extend_from_slice(&page[4..4 + take]);
+                next = u32::from_be_bytes(page[..4].try_into().unwrap());
+            }
+            assert_eq!(out.len(), size);
+            out
+        }
+
+        fn rows(&self, root: u32, out: &mut Vec<(i64, Vec<OwnedValue>)>) {
+            let (kind, cells, right) = self.cells(root);
+            if kind == INTERIOR_TABLE {
+                for cell in cells {
+                    self.rows(u32::from_be_bytes(cell[..4].try_into().unwrap()), out);
+                }
+                self.rows(right, out);
+            } else {
+                assert_eq!(kind, LEAF_TABLE);
+                for cell in cells {
+                    let (_, n) = read_varint(cell).unwrap();
+                    let (rowid, m) = read_varint(&cell[n..]).unwrap();
+                    let mut rest = cell[..n].to_vec();
+                    rest.extend_from_slice(&cell[n + m..]);
+                    out.push((rowid as i64, decode(&self.payload(&rest, TABLE_MAX_LOCAL))));
+                }
+            }
+        }
+
+        fn keys(&self, root: u32, out: &mut Vec<Vec<OwnedValue>>) {
+            let (kind, cells, right) = self.cells(root);
+            if kind == INTERIOR_INDEX {
+                for cell in cells {
+                    self.keys(u32::from_be_bytes(cell[..4].try_into().unwrap()), out);
+                    out.push(decode(&self.payload(&cell[4..], INDEX_MAX_LOCAL)));
+                }
+                self.keys(right, out);
+            } else {
+                assert_eq!(kind, LEAF_INDEX);
+                for cell in cells {
+                    out.push(decode(&self.payload(cell, INDEX_MAX_LOCAL)));
+                }
+            }
+        }
+
+        /// Schema rows: (type, name, root page)
+        fn schema(&self) -> Vec<(String, String, u32)> {
+            let mut rows = Vec::new();
+            self.rows(1, &mut rows);
+            rows.into_iter()
+                .map(|(_, row)| match (&row[0], &row[1], &row[3]) {
+                    (OwnedValue::Text(kind), OwnedValue::Text(name), OwnedValue::Integer(root)) => {
+                        (kind.clone(), name.clone(), *root as u32)
+                    }
+                    _ => panic!("bad schema row {:?}", row),
+                })
+                .collect()
+        }
+    }
+
+    fn blob_for(i: i64) -> Vec<u8> {
+        (0..(i * 37 % 9000)).map(|b| (b ^ i) as u8).collect()
+    }
+
+    fn sample_database(rows: i64) -> Db {
+        let mut db = SqliteWriter::new(Cursor::new(Vec::new()));
+        db.set_application_id(0x1234_5678);
+        let t = db.create_table("t", "CREATE TABLE t (a integer, b text, c real, d blob)");
+        db.create_index("t_b", t, "CREATE INDEX t_b on t (b)", &[1]);
+        for i in 0..rows {
+            let name = format!("n{}", (i * 7919) % 5000);
+            let a = if i % 3 == 0 {
+                Value::Null
+            } else {
+                Value::Integer(i * (1 << 33) - 5)
+            };
+            let rowid = db
+                .insert(
+                    t,
+                    &[
+                        a,
+                        Value::Text(&name),
+                        Value::Real(i as f64 / 4.0),
+                        Value::Blob(&blob_for(i)),
+                    ],
+                )
+                .unwrap();
+            assert_eq!(rowid, i + 1);
+        }
+        Db(db.finish().unwrap().into_inner())
+    }
+
+    #[test]
+    fn varints_follow_the_sqlite_encoding() {
+        for (value, bytes) in [
+            (0u64, vec![0x00]),
+            (0x7f, vec![0x7f]),
+            (0x80, vec![0x81, 0x00]),
+            (0x3fff, vec![0xff, 0x7f]),
+            (1 << 56, vec![0x80, 0xc0, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00]),
+        ] {
+            let mut out = Vec::new();
+            put_varint(&mut out, value);
+            assert_eq!(out, bytes, "{:#x}", value);
+            assert_eq!(read_varint(&out).unwrap(), (value, bytes.len()));
+        }
+        let mut out = Vec::new();
+        put_varint(&mut out, u64::MAX);
+        assert_eq!(read_varint(&out).unwrap(), (u64::MAX, 9));
+    }
+
+    #[test]
+    fn records_round_trip_every_value_type() {
+        let blob = [1u8, 2, 3];
+        let values = [
+            Value::Null,
+            Value::Integer(0),
+            Value::Integer(1),
+            Value::Integer(-129),
+            Value::Integer(1 << 40),
+            Value::Integer(i64::MIN),
+            Value::Real(-2.5),
+            Value::Text("zoom"),
+            Value::Blob(&blob),
+        ];
+        let encoded = record(&values);
+        // Header: length, then serial types
+        assert_eq!(&encoded[..10], &[10, 0, 8, 9, 2, 5, 6, 7, 21, 18]);
+        let decoded = decode(&encoded);
+        assert_eq!(decoded.iter().map(OwnedValue::as_value).collect::<Vec<_>>(), values);
+        let wide: Vec<Value> = (0..200).map(|_| Value::Null).collect();
+        assert_eq!(decode(&record(&wide)).len(), 200);
+    }
+
+    #[test]
+    fn header_declares_every_page() {
+        let db = sample_database(300);
+        assert_eq!(&db.0[..16], b"SQLite format 3\0");
+        assert_eq!(u16::from_be_bytes([db.0[16], db.0[17]]) as usize, PAGE_SIZE);
+        let pages = u32::from_be_bytes(db.0[28..32].try_into().unwrap()) as usize;
+        assert_eq!(db.0.len(), pages * PAGE_SIZE);
+        assert_eq!(&db.0[68..72], &0x1234_5678u32.to_be_bytes());
+    }
+
+    #[test]
+    fn rows_round_trip_through_overflow_pages() {
+        let db = sample_database(3000);
+        let schema = db.schema();
+        assert_eq!(schema[0].0, "table");
+        let mut rows = Vec::new();
+        db.rows(schema[0].2, &mut rows);
+        assert_eq!(rows.len(), 3000);
+        for (n, (rowid, row)) in rows.iter().enumerate() {
+            let i = n as i64;
+            assert_eq!(*rowid, i + 1);
+            let a = if i % 3 == 0 {
+                OwnedValue::Null
+            } else {
+                OwnedValue::Integer(i * (1 << 33) - 5)
+            };
+            assert_eq!(row[0], a);
+            assert_eq!(row[1], OwnedValue::Text(format!("n{}", (i * 7919) % 5000)));
+            assert_eq!(row[2], OwnedValue::Real(i as f64 / 4.0));
+            assert_eq!(row[3], OwnedValue::Blob(blob_for(i)));
+        }
+
+        let reader = SqliteReader::from_bytes(db.0).unwrap();
+        assert_eq!(reader.application_id(), 0x1234_5678);
+        assert_eq!(
+            reader.table_sql("T").unwrap().unwrap(),
+            "CREATE TABLE t (a integer, b text, c real, d blob)"
+        );
+        assert_eq!(reader.rows("t").unwrap(), rows);
+        assert_eq!(reader.rows("t_b").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn index_holds_every_row_in_key_order() {
+        let db = sample_database(20_000);
+        let schema = db.schema();
+        assert_eq!((schema[1].0.as_str(), schema[1].1.as_str()), ("index", "t_b"));
+        let mut keys = Vec::new();
+        db.keys(schema[1].2, &mut keys);
+        assert_eq!(keys.len(), 20_000);
+        for pair in keys.windows(2) {
+            let order = pair[0][0].compare(&pair[1][0]).then(pair[0][1].compare(&pair[1][1]));
+            assert_eq!(order, Ordering::Less);
+        }
+        let mut rowids: Vec<_> = keys
+            .iter()
+            .map(|key| match key[1] {
+                OwnedValue::Integer(rowid) => rowid,
+                _ => panic!("rowid missing"),
+            })
+            .collect();
+        rowids.sort_unstable();
+        assert!(rowids.iter().copied().eq(1..=20_000));
+    }
+
+    #[test]
+    fn empty_tables_get_a_root_page() {
+        let mut db = SqliteWriter::new(Cursor::new(Vec::new()));
+        let t = db.create_table("empty", "CREATE TABLE empty (a)");
+        db.create_index("empty_a", t, "CREATE INDEX empty_a on empty (a)", &[0]);
+        let db = Db(db.finish().unwrap().into_inner());
+        let schema = db.schema();
+        assert_eq!(schema.len(), 2);
+        assert_eq!(db.cells(schema[0].2), (LEAF_TABLE, Vec::new(), 0));
+        assert_eq!(db.cells(schema[1].2), (LEAF_INDEX, Vec::new(), 0));
+    }
+
+    #[test]
+    fn rejects_rows_missing_index_columns_and_long_keys() {
+        let mut db = SqliteWriter::new(Cursor::new(Vec::new()));
+        let t = db.create_table("t", "CREATE TABLE t (a, b)");
+        db.create_index("t_b", t, "CREATE INDEX t_b on t (b)", &[1]);
+        assert!(db.insert(t, &[Value::Integer(1)]).is_err());
+        let long = "x".repeat(2000);
+        db.insert(t, &[Value::Integer(1), Value::Text(&long)]).unwrap();
+        assert!(db.finish().is_err());
+    }
+
+    #[test]
+    fn reader_rejects_malformed_files() {
+        let db = sample_database(400).0;
+        let error = |data: Vec<u8>| {
+            SqliteReader::from_bytes(data)
+                .and_then(|r| r.rows("t"))
+                .unwrap_err()
+                .kind()
+        };
+        assert_eq!(error(b"not a database".to_vec()), io::ErrorKind::InvalidData);
+        let mut bad_page_size = db.clone();
+        bad_page_size[16..18].copy_from_slice(&1000u16.to_be_bytes());
+        assert_eq!(error(bad_page_size), io::ErrorKind::InvalidData);
+        assert_eq!(error(db[..db.len() - PAGE_SIZE].to_vec()), io::ErrorKind::InvalidData);
+
+        // Point the table root's first child back at the root
+        let reader = SqliteReader::from_bytes(db.clone()).unwrap();
+        let (root, _) = reader.schema_entry("t").unwrap().unwrap();
+        let page = (root as usize - 1) * PAGE_SIZE;
+        assert_eq!(db[page], INTERIOR_TABLE);
+        let first = u16::from_be_bytes([db[page + 12], db[page + 13]]) as usize;
+        let mut cycle = db.clone();
+        cycle[page + first..page + first + 4].copy_from_slice(&root.to_be_bytes());
+        assert_eq!(error(cycle), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reader_survives_corruption() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let db = sample_database(200).0;
+        let mut rng = StdRng::seed_from_u64(368);
+        for _ in 0..300 {
+            let mut data = db.clone();
+            for _ in 0..rng.gen_range(1..20) {
+                let at = rng.gen_range(0..data.len());
+                data[at] = rng.gen();
+            }
+            // Either outcome is fine as long as nothing panics
+            let _ = SqliteReader::from_bytes(data).and_then(|r| r.rows("t"));
+        }
+    }
+}
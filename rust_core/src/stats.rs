@@ -0,0 +1,419 @@
+// Summary statistics and histograms of ping fields
+// src/stats.rs
+
+use crate::expr::Field;
+use crate::survey::Ping;
+
+/// Values kept verbatim before switching to fixed-memory estimates
+const EXACT_LIMIT: usize = 4096;
+/// Working histogram bins per requested bin once estimating
+const FINE_PER_BIN: usize = 32;
+/// Largest histogram bin count; larger requests are clamped to it
+pub const MAX_BINS: usize = 65_536;
+
+/// Equal-width histogram over `[min, max]`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Histogram {
+    /// `counts.len() + 1` bin edges
+    pub edges: Vec<f64>,
+    pub counts: Vec<u64>,
+}
+
+/// Count, range, moments, percentiles and histogram of one field
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Population standard deviation
+    pub stddev: f64,
+    pub p5: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub histogram: Histogram,
+}
+
+impl FieldStats {
+    /// Statistics of the finite values; NaN (unset) values are skipped
+    pub fn from_values<I: IntoIterator<Item = f64>>(values: I, bins: usize) -> Self {
+        let mut stats = StreamingStats::new(bins);
+        for v in values {
+            stats.push(v);
+        }
+        stats.finish()
+    }
+}
+
+/// One-pass accumulator behind [`FieldStats`]
+///
+/// Moments use Welford's update. The first few thousand values are kept,
+/// so small inputs get exact percentiles and histograms; past that, memory
+/// is fixed: percentiles come from P² estimators and the histogram from a
+/// finer working histogram that doubles its bin width as the range grows.
+#[derive(Debug, Clone)]
+pub struct StreamingStats {
+    bins: usize,
+    count: usize,
+    min: f64,
+    max: f64,
+    mean: f64,
+    m2: f64,
+    /// Every value until `EXACT_LIMIT` is passed, then empty
+    exact: Vec<f64>,
+    quantiles: [P2Quantile; 3],
+    fine: FineHistogram,
+}
+
+impl StreamingStats {
+    pub fn new(bins: usize) -> Self {
+        let bins = bins.clamp(1, MAX_BINS);
+        Self {
+            bins,
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+            m2: 0.0,
+            exact: Vec::new(),
+            quantiles: [P2Quantile::new(0.05), P2Quantile::new(0.5), P2Quantile::new(0.95)],
+            fine: FineHistogram::new(bins * FINE_PER_BIN),
+        }
+    }
+
+    /// Add one value; non-finite values are ignored
+    pub fn push(&mut self, v: f64) {
+        if !v.is_finite() {
+            return;
+        }
+        self.count += 1;
+        self.min = self.min.min(v);
+        self.max = self.max.max(v);
+        let delta = v - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (v - self.mean);
+        for q in &mut self.quantiles {
+            q.push(v);
+        }
+
+        if self.count <= EXACT_LIMIT {
+            self.exact.push(v);
+            return;
+        }
+        if !self.exact.is_empty() {
+            self.fine.start(self.min, self.max);
+            for x in std::mem::take(&mut self.exact) {
+                self.fine.push(x);
+            }
+        }
+        self.fine.push(v);
+    }
+
+    pub fn finish(&self) -> FieldStats {
+        if self.count == 0 {
+            return FieldStats::default();
+        }
+        let bins = self.bins;
+        let width = (self.max - self.min) / bins as f64;
+        let bin_of = |v: f64| {
+            let bin = if width > 0.0 {
+                ((v - self.min) / width) as usize
+            } else {
+                0
+            };
+            bin.min(bins - 1)
+        };
+        let mut counts = vec![0u64; bins];
+        let [p5, median, p95] = if self.count <= EXACT_LIMIT {
+            for &v in &self.exact {
+                counts[bin_of(v)] += 1;
+            }
+            let mut sorted = self.exact.clone();
+            sorted.sort_by(f64::total_cmp);
+            [0.05, 0.5, 0.95].map(|p| interpolate(&sorted, p))
+        } else {
+            for (center, n) in self.fine.centers() {
+                counts[bin_of(center.clamp(self.min, self.max))] += n;
+            }
+            self.quantiles.each_ref().map(|q| q.value().clamp(self.min, self.max))
+        };
+        FieldStats {
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            mean: self.mean,
+            stddev: (self.m2 / self.count as f64).sqrt(),
+            p5,
+            median,
+            p95,
+            histogram: Histogram {
+                edges: (0..=bins).map(|i| self.min + width * i as f64).collect(),
+                counts,
+            },
+        }
+    }
+}
+
+/// Linearly interpolated quantile of sorted, non-empty values
+fn interpolate(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let (lo, frac) = (rank.floor() as usize, rank.fract());
+    match sorted.get(lo + 1) {
+        Some(&next) => sorted[lo] + frac * (next - sorted[lo]),
+        None => sorted[lo],
+    }
+}
+
+/// Jain and Chlamtac's P² estimate of one quantile in constant memory
+#[derive(Debug, Clone)]
+struct P2Quantile {
+    p: f64,
+    /// Marker heights; the first `seen` hold raw values until five arrive
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+    seen: usize,
+}
+
+impl P2Quantile {
+    fn new(p: f64) -> Self {
+        Self {
+            p,
+            heights: [0.0; 5],
+            positions: [0.0, 1.0, 2.0, 3.0, 4.0],
+            desired: [0.0, 2.0 * p, 4.0 * p, 2.0 + 2.0 * p, 4.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+            seen: 0,
+        }
+    }
+
+    fn push(&mut self, v: f64) {
+        if self.seen < 5 {
+            self.heights[self.seen] = v;
+            self.seen += 1;
+            if self.seen == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        let q = &mut self.heights;
+        let cell = if v < q[0] {
+            q[0] = v;
+            0
+        } else if v >= q[4] {
+            q[4] = v;
+            3
+        } else {
+            (1..5).find(|&i| v < q[i]).unwrap() - 1
+        };
+        for n in &mut self.positions[cell + 1..] {
+            *n += 1.0;
+        }
+        for (d, inc) in self.desired.iter_mut().zip(self.increments) {
+            *d += inc;
+        }
+
+        let n = &mut self.positions;
+        for i in 1..4 {
+            let offset = self.desired[i] - n[i];
+            if (offset >= 1.0 && n[i + 1] - n[i] > 1.0) || (offset <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = offset.signum();
+                let parabolic = q[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]));
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
+        }
+    }
+
+    fn value(&self) -> f64 {
+        if self.seen < 5 {
+            let mut sorted = self.heights[..self.seen].to_vec();
+            sorted.sort_by(f64::total_cmp);
+            return interpolate(&sorted, self.p);
+        }
+        self.heights[2]
+    }
+}
+
+/// Fixed-size histogram whose bins double in width to cover new values
+#[derive(Debug, Clone)]
+struct FineHistogram {
+    counts: Vec<u64>,
+    lo: f64,
+    /// Zero while every value seen is `lo`
+    width: f64,
+}
+
+impl FineHistogram {
+    fn new(bins: usize) -> Self {
+        Self {
+            counts: vec![0; bins.max(2) & !1],
+            lo: 0.0,
+            width: 0.0,
+        }
+    }
+
+    /// Cover `[min, max]` before the first value is added
+    fn start(&mut self, min: f64, max: f64) {
+        self.lo = min;
+        self.width = (max - min) / (self.counts.len() - 1) as f64;
+    }
+
+    fn push(&mut self, v: f64) {
+        let last = self.counts.len() - 1;
+        if self.width == 0.0 && v != self.lo {
+            self.width = (v - self.lo).abs() / last as f64;
+            if v < self.lo {
+                self.counts.swap(0, last);
+                self.lo = v;
+            }
+        }
+        while self.width > 0.0 && (v < self.lo || v >= self.lo + self.counts.len() as f64 * self.width) {
+            self.double(v < self.lo);
+        }
+        let bin = if self.width > 0.0 {
+            ((v - self.lo) / self.width) as usize
+        } else {
+            0
+        };
+        self.counts[bin.min(last)] += 1;
+    }
+
+    /// Merge bin pairs into one half, extending the range down or up
+    fn double(&mut self, downwards: bool) {
+        let half = self.counts.len() / 2;
+        let merged: Vec<u64> = self.counts.chunks(2).map(|pair| pair[0] + pair[1]).collect();
+        self.counts.fill(0);
+        let start = if downwards { half } else { 0 };
+        self.counts[start..start + half].copy_from_slice(&merged);
+        if downwards {
+            self.lo -= self.counts.len() as f64 * self.width;
+        }
+        self.width *= 2.0;
+    }
+
+    /// `(bin center, count)` of the occupied bins
+    fn centers(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &n)| n > 0)
+            .map(|(i, &n)| (self.lo + (i as f64 + 0.5) * self.width, n))
+    }
+}
+
+/// Statistics of `field` over `pings`
+pub fn field_stats<'a, I: IntoIterator<Item = &'a Ping>>(pings: I, field: Field, bins: usize) -> FieldStats {
+    FieldStats::from_values(pings.into_iter().map(|p| field.value(p)), bins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn exact_histogram(values: &[f64], bins: usize) -> Vec<u64> {
+        let (min, max) = values.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+        let width = (max - min) / bins as f64;
+        let mut counts = vec![0; bins];
+        for &v in values {
+            counts[(((v - min) / width) as usize).min(bins - 1)] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn small_inputs_are_exact() {
+        let stats = FieldStats::from_values((1..=100).map(f64::from).chain([f64::NAN, f64::INFINITY]), 10);
+        assert_eq!(stats.count, 100);
+        assert_eq!((stats.min, stats.max, stats.mean), (1.0, 100.0, 50.5));
+        assert!((stats.stddev - 833.25f64.sqrt()).abs() < 1e-9);
+        assert!((stats.p5 - 5.95).abs() < 1e-9);
+        assert!((stats.median - 50.5).abs() < 1e-9);
+        assert!((stats.p95 - 95.05).abs() < 1e-9);
+        assert_eq!(stats.histogram.counts, vec![10; 10]);
+        assert_eq!(stats.histogram.edges.len(), 11);
+        assert_eq!(stats.histogram.edges[10], 100.0);
+
+        assert_eq!(FieldStats::from_values([], 8), FieldStats::default());
+        assert_eq!(FieldStats::from_values([f64::NAN], 8), FieldStats::default());
+        let constant = FieldStats::from_values([3.0; 7], 0);
+        assert_eq!(
+            (constant.min, constant.max, constant.stddev, constant.median),
+            (3.0, 3.0, 0.0, 3.0)
+        );
+        assert_eq!(constant.histogram.counts, vec![7]);
+    }
+
+    #[test]
+    fn large_inputs_match_exact_statistics() {
+        let mut rng = StdRng::seed_from_u64(402);
+        let uniform: Vec<f64> = (0..100_000).map(|_| rng.gen_range(-5.0..40.0)).collect();
+        let skewed: Vec<f64> = (0..100_000)
+            .map(|_| rng.gen_range(0.0f64..1.0).powi(3) * 30.0)
+            .collect();
+        let rising: Vec<f64> = (0..50_000).map(|i| i as f64 * 0.01).collect();
+        let falling: Vec<f64> = rising.iter().rev().map(|v| -v).collect();
+
+        for values in [uniform, skewed, rising, falling] {
+            let bins = 16;
+            let stats = FieldStats::from_values(values.iter().copied(), bins);
+            let mut sorted = values.clone();
+            sorted.sort_by(f64::total_cmp);
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let stddev = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+            let span = sorted[sorted.len() - 1] - sorted[0];
+
+            assert_eq!(stats.count, values.len());
+            assert_eq!((stats.min, stats.max), (sorted[0], sorted[sorted.len() - 1]));
+            assert!((stats.mean - mean).abs() < 1e-9 * span);
+            assert!((stats.stddev - stddev).abs() < 1e-9 * span);
+            for (estimate, p) in [(stats.p5, 0.05), (stats.median, 0.5), (stats.p95, 0.95)] {
+                assert!(
+                    (estimate - interpolate(&sorted, p)).abs() < 0.01 * span,
+                    "p{} {}",
+                    p,
+                    estimate
+                );
+            }
+            let exact = exact_histogram(&values, bins);
+            assert_eq!(stats.histogram.counts.iter().sum::<u64>(), values.len() as u64);
+            for (got, want) in stats.histogram.counts.iter().zip(&exact) {
+                assert!(
+                    got.abs_diff(*want) <= values.len() as u64 / 100,
+                    "{:?} vs {:?}",
+                    stats.histogram.counts,
+                    exact
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn constant_run_then_new_values() {
+        let values: Vec<f64> = std::iter::repeat_n(2.0, EXACT_LIMIT + 10).chain([-6.0, 10.0]).collect();
+        let stats = FieldStats::from_values(values, 4);
+        assert_eq!((stats.min, stats.max), (-6.0, 10.0));
+        assert_eq!(stats.histogram.counts, vec![1, 0, EXACT_LIMIT as u64 + 10, 1]);
+        assert_eq!(stats.median, 2.0);
+    }
+
+    #[test]
+    fn bin_count_is_clamped() {
+        assert_eq!(FieldStats::from_values([1.0, 2.0], 0).histogram.counts.len(), 1);
+        assert_eq!(FieldStats::from_values([1.0, 2.0], usize::MAX).histogram.counts.len(), MAX_BINS);
+    }
+}
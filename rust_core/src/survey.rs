@@ -0,0 +1,172 @@
+// Core survey data types shared by sonar processing and export
+// src/survey.rs
+
+/// Vertical reference a depth is measured from, see [`crate::datum`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum VerticalReference {
+    /// Raw depth below the transducer face
+    #[default]
+    Transducer,
+    /// Depth below the water surface (transducer depth plus draft)
+    Waterline,
+    /// Depth below chart datum (waterline depth minus the tide)
+    ChartDatum,
+}
+
+impl VerticalReference {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "transducer" | "raw" => Some(Self::Transducer),
+            "waterline" | "surface" => Some(Self::Waterline),
+            "chart_datum" | "chart-datum" | "chartdatum" | "datum" => Some(Self::ChartDatum),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Transducer => "transducer",
+            Self::Waterline => "waterline",
+            Self::ChartDatum => "chart_datum",
+        }
+    }
+
+    /// Stable small code used in binary formats
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Transducer => 0,
+            Self::Waterline => 1,
+            Self::ChartDatum => 2,
+        }
+    }
+
+    pub fn from_code(code: u64) -> Option<Self> {
+        match code {
+            0 => Some(Self::Transducer),
+            1 => Some(Self::Waterline),
+            2 => Some(Self::ChartDatum),
+            _ => None,
+        }
+    }
+}
+
+/// Single depth sounding with navigation at the time of the ping
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sounding {
+    pub timestamp: f64,
+    pub lat: f64,
+    pub lon: f64,
+    pub depth_m: f64,
+    pub heading_deg: f64,
+    /// What `depth_m` is measured from
+    pub reference: VerticalReference,
+}
+
+impl Sounding {
+    pub fn new(timestamp: f64, lat: f64, lon: f64, depth_m: f64) -> Self {
+        Self {
+            timestamp,
+            lat,
+            lon,
+            depth_m,
+            ..Default::default()
+        }
+    }
+}
+
+/// Transmit and receive settings of one ping, when the format records them
+///
+/// Echo strength depends on these, so intensities are only comparable
+/// between pings with equal settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Acoustics {
+    /// Center frequency; for CHIRP pings the middle of `chirp_khz`
+    pub frequency_khz: Option<f64>,
+    /// CHIRP sweep as (start, end) frequency
+    pub chirp_khz: Option<(f64, f64)>,
+    pub pulse_length_us: Option<f64>,
+    /// Range chosen on the unit, which may differ from the recorded `range_m`
+    pub range_setting_m: Option<f64>,
+    /// Receiver gain setting in the unit's own 0-100 scale
+    pub gain_pct: Option<f64>,
+}
+
+impl Acoustics {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Attitude, engine, power and environment readings logged beside the sonar
+///
+/// Filled from an NMEA 2000 log by [`crate::parsers::nmea2000::N2kLog::fuse_sensors`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sensors {
+    /// Bow up positive, degrees
+    pub pitch_deg: Option<f64>,
+    /// Starboard down positive, degrees
+    pub roll_deg: Option<f64>,
+    pub water_temp_c: Option<f64>,
+    pub battery_volts: Option<f64>,
+    pub engine_rpm: Option<f64>,
+    pub air_temp_c: Option<f64>,
+}
+
+/// One sonar ping: navigation at the time of the ping plus the echo samples of one channel
+///
+/// `samples` span `0..range_m` away from the transducer (down for 2D/down-scan
+/// channels, across track for side-scan channels).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ping {
+    pub timestamp: f64,
+    pub channel_id: u16,
+    pub lat: f64,
+    pub lon: f64,
+    pub heading_deg: f64,
+    pub depth_m: f64,
+    pub range_m: f64,
+    pub samples: Vec<u8>,
+    /// Course over ground computed from position deltas, see [`crate::motion`]
+    pub cog_deg: Option<f64>,
+    pub sog_knots: Option<f64>,
+    /// Speed through water from a paddlewheel log, as opposed to GPS `sog_knots`
+    pub stw_knots: Option<f64>,
+    pub acoustics: Acoustics,
+    pub sensors: Sensors,
+    /// Implausible-field bits set by [`crate::bounds::BoundsConfig::check`]
+    pub flags: u8,
+}
+
+impl Ping {
+    /// Range in meters represented by a single sample
+    pub fn sample_spacing_m(&self) -> f64 {
+        if self.samples.is_empty() {
+            0.0
+        } else {
+            self.range_m / self.samples.len() as f64
+        }
+    }
+
+    /// Depth-only view of this ping
+    pub fn to_sounding(&self) -> Sounding {
+        Sounding {
+            timestamp: self.timestamp,
+            lat: self.lat,
+            lon: self.lon,
+            depth_m: self.depth_m,
+            heading_deg: self.heading_deg,
+            reference: VerticalReference::Transducer,
+        }
+    }
+}
+
+/// Named mark at a position, e.g. a detected target or a user waypoint
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Waypoint {
+    pub name: String,
+    pub timestamp: f64,
+    pub lat: f64,
+    pub lon: f64,
+    pub depth_m: Option<f64>,
+    pub comment: String,
+}
@@ -0,0 +1,108 @@
+// Helpers shared by unit tests
+// src/testutil.rs
+
+use std::fs;
+use std::path::PathBuf;
+
+/// Path in the temp directory unique to this test process
+///
+/// `tag` names the module so tests running in parallel never share a file.
+pub fn scratch(tag: &str, name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("sonar_{}_{}_{}", tag, std::process::id(), name))
+}
+
+/// Like [`scratch`], but an existing directory that starts out empty
+pub fn scratch_dir(tag: &str, name: &str) -> PathBuf {
+    let dir = scratch(tag, name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// One decoded GIF frame: delay in hundredths of a second and palette indices
+pub struct GifFrame {
+    pub delay_cs: u16,
+    pub indices: Vec<u8>,
+}
+
+/// Decode a GIF89a stream as written by `imaging::gif`
+///
+/// Returns the canvas size, global palette and frames; panics on anything
+/// that stream layout does not produce.
+pub fn decode_gif(bytes: &[u8]) -> ((u16, u16), Vec<[u8; 3]>, Vec<GifFrame>) {
+    let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+    assert_eq!(&bytes[..6], b"GIF89a");
+    let size = (u16_at(6), u16_at(8));
+    assert_eq!(bytes[10], 0xF7);
+    let palette = bytes[13..13 + 768].chunks(3).map(|c| [c[0], c[1], c[2]]).collect();
+    let mut pos = 13 + 768;
+    assert_eq!(&bytes[pos..pos + 3], &[0x21, 0xFF, 0x0B]);
+    assert_eq!(&bytes[pos + 3..pos + 14], b"NETSCAPE2.0");
+    pos += 19;
+
+    let mut frames = Vec::new();
+    while bytes[pos] != 0x3B {
+        assert_eq!(&bytes[pos..pos + 4], &[0x21, 0xF9, 0x04, 0x00]);
+        let delay_cs = u16_at(pos + 4);
+        pos += 8;
+        assert_eq!(bytes[pos], 0x2C);
+        assert_eq!((u16_at(pos + 5), u16_at(pos + 7)), size);
+        let min_code_size = bytes[pos + 10];
+        pos += 11;
+        let mut data = Vec::new();
+        while bytes[pos] != 0 {
+            let len = bytes[pos] as usize;
+            data.extend_from_slice(&bytes[pos + 1..pos + 1 + len]);
+            pos += 1 + len;
+        }
+        pos += 1;
+        frames.push(GifFrame {
+            delay_cs,
+            indices: lzw_decode(&data, min_code_size),
+        });
+    }
+    assert_eq!(pos, bytes.len() - 1, "data after the trailer");
+    (size, palette, frames)
+}
+
+fn lzw_decode(data: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1usize << min_code_size;
+    let reset = || ((0..clear).map(|i| vec![i as u8]).chain([vec![], vec![]]).collect::<Vec<_>>(), min_code_size + 1);
+    let (mut table, mut size) = reset();
+    let (mut out, mut prev): (Vec<u8>, Option<Vec<u8>>) = (Vec::new(), None);
+    let (mut acc, mut bits, mut bytes) = (0u32, 0u8, data.iter());
+
+    loop {
+        while bits < size {
+            acc |= (*bytes.next().expect("missing end code") as u32) << bits;
+            bits += 8;
+        }
+        let code = (acc & ((1 << size) - 1)) as usize;
+        acc >>= size;
+        bits -= size;
+
+        if code == clear {
+            (table, size) = reset();
+            prev = None;
+            continue;
+        }
+        if code == clear + 1 {
+            return out;
+        }
+        let entry = match (table.get(code), &prev) {
+            (Some(entry), _) => entry.clone(),
+            (None, Some(p)) if code == table.len() => [p.as_slice(), &p[..1]].concat(),
+            _ => panic!("invalid LZW code {code}"),
+        };
+        out.extend_from_slice(&entry);
+        if let Some(p) = prev {
+            if table.len() < 4096 {
+                table.push([p.as_slice(), &entry[..1]].concat());
+            }
+        }
+        if table.len() >= 1 << size && size < 12 {
+            size += 1;
+        }
+        prev = Some(entry);
+    }
+}
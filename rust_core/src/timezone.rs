@@ -0,0 +1,647 @@
+// Time zones for local-time timestamps in deliverables
+// src/timezone.rs
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, SecondsFormat};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Searched in order for IANA zone files after `$TZDIR`
+const ZONEINFO_DIRS: &[&str] = &["/usr/share/zoneinfo", "/usr/lib/zoneinfo", "/usr/share/lib/zoneinfo"];
+/// Largest fixed offset accepted, in hours
+const MAX_OFFSET_H: i32 = 18;
+
+/// Current POSIX TZ rules of common zones, for hosts without a zoneinfo database
+///
+/// Strings are the footers of tzdata 2025b; they carry no history, so times
+/// before a zone's last rule change get today's offsets.
+const BUNDLED_ZONES: &[(&str, &str)] = &[
+    ("Africa/Cairo", "EET-2EEST,M4.5.5/0,M10.5.4/24"),
+    ("Africa/Johannesburg", "SAST-2"),
+    ("Africa/Lagos", "WAT-1"),
+    ("Africa/Nairobi", "EAT-3"),
+    ("America/Anchorage", "AKST9AKDT,M3.2.0,M11.1.0"),
+    ("America/Argentina/Buenos_Aires", "<-03>3"),
+    ("America/Chicago", "CST6CDT,M3.2.0,M11.1.0"),
+    ("America/Denver", "MST7MDT,M3.2.0,M11.1.0"),
+    ("America/Detroit", "EST5EDT,M3.2.0,M11.1.0"),
+    ("America/Edmonton", "MST7MDT,M3.2.0,M11.1.0"),
+    ("America/Halifax", "AST4ADT,M3.2.0,M11.1.0"),
+    ("America/Los_Angeles", "PST8PDT,M3.2.0,M11.1.0"),
+    ("America/Mexico_City", "CST6"),
+    ("America/New_York", "EST5EDT,M3.2.0,M11.1.0"),
+    ("America/Phoenix", "MST7"),
+    ("America/Puerto_Rico", "AST4"),
+    ("America/Regina", "CST6"),
+    ("America/Santiago", "<-04>4<-03>,M9.1.6/24,M4.1.6/24"),
+    ("America/Sao_Paulo", "<-03>3"),
+    ("America/St_Johns", "NST3:30NDT,M3.2.0,M11.1.0"),
+    ("America/Toronto", "EST5EDT,M3.2.0,M11.1.0"),
+    ("America/Vancouver", "PST8PDT,M3.2.0,M11.1.0"),
+    ("America/Winnipeg", "CST6CDT,M3.2.0,M11.1.0"),
+    ("Asia/Bangkok", "<+07>-7"),
+    ("Asia/Dhaka", "<+06>-6"),
+    ("Asia/Dubai", "<+04>-4"),
+    ("Asia/Hong_Kong", "HKT-8"),
+    ("Asia/Jakarta", "WIB-7"),
+    ("Asia/Karachi", "PKT-5"),
+    ("Asia/Kolkata", "IST-5:30"),
+    ("Asia/Manila", "PST-8"),
+    ("Asia/Seoul", "KST-9"),
+    ("Asia/Shanghai", "CST-8"),
+    ("Asia/Singapore", "<+08>-8"),
+    ("Asia/Tokyo", "JST-9"),
+    ("Atlantic/Reykjavik", "GMT0"),
+    ("Australia/Adelaide", "ACST-9:30ACDT,M10.1.0,M4.1.0/3"),
+    ("Australia/Brisbane", "AEST-10"),
+    ("Australia/Darwin", "ACST-9:30"),
+    ("Australia/Hobart", "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+    ("Australia/Melbourne", "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+    ("Australia/Perth", "AWST-8"),
+    ("Australia/Sydney", "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+    ("Etc/UTC", "UTC0"),
+    ("Europe/Amsterdam", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Athens", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Berlin", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Brussels", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Copenhagen", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Dublin", "IST-1GMT0,M10.5.0,M3.5.0/1"),
+    ("Europe/Helsinki", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Istanbul", "<+03>-3"),
+    ("Europe/Kyiv", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Europe/Lisbon", "WET0WEST,M3.5.0/1,M10.5.0"),
+    ("Europe/London", "GMT0BST,M3.5.0/1,M10.5.0"),
+    ("Europe/Madrid", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Moscow", "MSK-3"),
+    ("Europe/Oslo", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Paris", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Rome", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Stockholm", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Europe/Warsaw", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Pacific/Auckland", "NZST-12NZDT,M9.5.0,M4.1.0/3"),
+    ("Pacific/Honolulu", "HST10"),
+];
+
+/// Zone used to format timestamps; times are always UTC internally
+///
+/// IANA zones are read from the system zoneinfo database (TZif files), so
+/// they follow the host's tzdata including future daylight-saving rules.
+/// Without one (e.g. on Windows) common zones fall back to bundled rules.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TimeZone {
+    #[default]
+    Utc,
+    /// Seconds east of UTC
+    Fixed(i32),
+    Zone(ZoneRules),
+}
+
+impl TimeZone {
+    /// Parse `"UTC"`, an offset such as `"+05:30"`, `"-0800"` or `"UTC-3"`, or an IANA name like `"America/Halifax"`
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let text = text.trim();
+        let upper = text.to_uppercase();
+        if ["UTC", "GMT", "Z"].contains(&upper.as_str()) {
+            return Ok(Self::Utc);
+        }
+        let offset = upper
+            .strip_prefix("UTC")
+            .or_else(|| upper.strip_prefix("GMT"))
+            .unwrap_or(&upper);
+        if offset.starts_with(['+', '-']) {
+            return parse_offset(offset)
+                .filter(|s| s.abs() <= MAX_OFFSET_H * 3600)
+                .map(Self::Fixed)
+                .ok_or_else(|| invalid_input(format!("invalid UTC offset '{}'", text)));
+        }
+        ZoneRules::load(text).map(Self::Zone)
+    }
+
+    /// Seconds east of UTC in effect at `timestamp`
+    pub fn offset_at(&self, timestamp: f64) -> i32 {
+        match self {
+            Self::Utc => 0,
+            Self::Fixed(offset) => *offset,
+            Self::Zone(rules) => rules.offset_at(timestamp.floor() as i64),
+        }
+    }
+
+    /// RFC 3339 with milliseconds and the local offset, e.g. `2024-06-01T09:30:00.000-03:00`
+    ///
+    /// UTC keeps the `Z` suffix used by the UTC-only exports.
+    pub fn format_time(&self, timestamp: f64) -> Option<String> {
+        let secs = timestamp.floor();
+        let nanos = ((timestamp - secs) * 1e9) as u32;
+        let offset = FixedOffset::east_opt(self.offset_at(timestamp))?;
+        DateTime::from_timestamp(secs as i64, nanos)
+            .map(|t| t.with_timezone(&offset).to_rfc3339_opts(SecondsFormat::Millis, true))
+    }
+}
+
+/// Transitions and trailing rule of one IANA zone
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneRules {
+    pub name: String,
+    /// (UTC time of transition, offset from then on), ascending
+    transitions: Vec<(i64, i32)>,
+    /// Offset before the first transition
+    initial: i32,
+    /// POSIX TZ rule for times after the last transition
+    rule: Option<PosixRule>,
+}
+
+impl ZoneRules {
+    /// Read a zone such as `"Europe/Oslo"` from `$TZDIR` or the system zoneinfo directories,
+    /// falling back to the bundled rules
+    pub fn load(name: &str) -> io::Result<Self> {
+        if name.is_empty() || name.starts_with('/') || name.split('/').any(|part| part == "..") {
+            return Err(invalid_input(format!("invalid time zone name '{}'", name)));
+        }
+        let dirs = env::var_os("TZDIR")
+            .map(PathBuf::from)
+            .into_iter()
+            .chain(ZONEINFO_DIRS.iter().map(PathBuf::from));
+        for dir in dirs {
+            if let Ok(data) = fs::read(dir.join(name)) {
+                return Self::from_tzif(name, &data);
+            }
+        }
+        Self::bundled(name).ok_or_else(|| invalid_input(format!("unknown time zone '{}'", name)))
+    }
+
+    /// A zone from the bundled table, with today's rules applied to every year
+    pub fn bundled(name: &str) -> Option<Self> {
+        let (_, tz) = BUNDLED_ZONES.iter().find(|(zone, _)| *zone == name)?;
+        let rule = PosixRule::parse(tz)?;
+        Some(Self {
+            name: name.to_string(),
+            transitions: Vec::new(),
+            initial: rule.std_offset,
+            rule: Some(rule),
+        })
+    }
+
+    /// Parse TZif data (RFC 8536), preferring the 64-bit block of version 2+ files
+    pub fn from_tzif(name: &str, data: &[u8]) -> io::Result<Self> {
+        let header = TzifHeader::read(data)?;
+        let (header, body, time_size) = if header.version >= b'2' {
+            let rest = data
+                .get(header.block_end(4)..)
+                .ok_or_else(|| invalid_data("truncated TZif data"))?;
+            (TzifHeader::read(rest)?, rest, 8)
+        } else {
+            (header, data, 4)
+        };
+        let mut pos = 44;
+        let mut take = |len: usize| {
+            let bytes = body
+                .get(pos..pos + len)
+                .ok_or_else(|| invalid_data("truncated TZif data"));
+            pos += len;
+            bytes
+        };
+        let times = take(header.timecnt * time_size)?;
+        let indices = take(header.timecnt)?;
+        let types: Vec<i32> = take(header.typecnt * 6)?
+            .chunks(6)
+            .map(|t| i32::from_be_bytes([t[0], t[1], t[2], t[3]]))
+            .collect();
+        let offset_of = |index: u8| {
+            types
+                .get(index as usize)
+                .copied()
+                .ok_or_else(|| invalid_data("bad TZif type index"))
+        };
+        let transitions = times
+            .chunks(time_size)
+            .zip(indices)
+            .map(|(t, &index)| {
+                let time = match time_size {
+                    8 => i64::from_be_bytes(t.try_into().unwrap_or_default()),
+                    _ => i32::from_be_bytes(t.try_into().unwrap_or_default()) as i64,
+                };
+                offset_of(index).map(|offset| (time, offset))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let initial = offset_of(0)?;
+
+        let rule = if time_size == 8 {
+            let footer = body.get(header.block_end(8)..).unwrap_or_default();
+            PosixRule::parse(String::from_utf8_lossy(footer).trim_matches(|c: char| c == '\n' || c == '\0'))
+        } else {
+            None
+        };
+        Ok(Self {
+            name: name.to_string(),
+            transitions,
+            initial,
+            rule,
+        })
+    }
+
+    pub fn offset_at(&self, time: i64) -> i32 {
+        let after = self.transitions.partition_point(|&(t, _)| t <= time);
+        match (after, &self.rule) {
+            (0, Some(rule)) if self.transitions.is_empty() => rule.offset_at(time),
+            (0, _) => self.initial,
+            (n, Some(rule)) if n == self.transitions.len() => rule.offset_at(time),
+            (n, _) => self.transitions[n - 1].1,
+        }
+    }
+}
+
+/// Counts from a TZif header
+struct TzifHeader {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl TzifHeader {
+    fn read(data: &[u8]) -> io::Result<Self> {
+        if data.len() < 44 || &data[..4] != b"TZif" {
+            return Err(invalid_data("not a TZif time zone file"));
+        }
+        let count = |i: usize| {
+            let at = 20 + 4 * i;
+            u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]) as usize
+        };
+        Ok(Self {
+            version: data[4],
+            isutcnt: count(0),
+            isstdcnt: count(1),
+            leapcnt: count(2),
+            timecnt: count(3),
+            typecnt: count(4),
+            charcnt: count(5),
+        })
+    }
+
+    /// Offset just past this header's data block for `time_size`-byte times
+    fn block_end(&self, time_size: usize) -> usize {
+        44 + self.timecnt * (time_size + 1)
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+/// Standard and daylight offsets with the dates daylight time starts and ends
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PosixRule {
+    std_offset: i32,
+    dst: Option<(i32, DayRule, DayRule)>,
+}
+
+/// Local date and time of a daylight-saving change
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DayRule {
+    date: RuleDate,
+    /// Seconds after local midnight; may be negative or past 24 h
+    time: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RuleDate {
+    /// `Mm.w.d`: weekday `d` (0 = Sunday) of week `w` (5 = last) of month `m`
+    MonthWeekDay(u32, u32, u32),
+    /// `Jn`: day 1..=365, February 29 never counted
+    Julian(u32),
+    /// `n`: day 0..=365 counting February 29
+    DayOfYear(u32),
+}
+
+impl PosixRule {
+    /// Parse a rule such as `"CET-1CEST,M3.5.0,M10.5.0/3"` or `"<-03>3"`
+    fn parse(text: &str) -> Option<Self> {
+        let rest = skip_name(text)?;
+        let (std, rest) = split_offset(rest)?;
+        let std_offset = -std;
+        if rest.is_empty() {
+            return Some(Self { std_offset, dst: None });
+        }
+        let rest = skip_name(rest)?;
+        let (dst_offset, rest) = match split_offset(rest) {
+            Some((offset, rest)) => (-offset, rest),
+            None => (std_offset + 3600, rest),
+        };
+        let mut rules = rest.strip_prefix(',')?.split(',');
+        let start = DayRule::parse(rules.next()?)?;
+        let end = DayRule::parse(rules.next()?)?;
+        Some(Self {
+            std_offset,
+            dst: Some((dst_offset, start, end)),
+        })
+    }
+
+    fn offset_at(&self, time: i64) -> i32 {
+        let Some((dst_offset, start, end)) = self.dst else {
+            return self.std_offset;
+        };
+        let Some(year) = DateTime::from_timestamp(time + self.std_offset as i64, 0).map(|t| t.year()) else {
+            return self.std_offset;
+        };
+        let (Some(start), Some(end)) = (
+            start.local_time(year).map(|t| t - self.std_offset as i64),
+            end.local_time(year).map(|t| t - dst_offset as i64),
+        ) else {
+            return self.std_offset;
+        };
+        let in_dst = if start < end {
+            start <= time && time < end
+        } else {
+            !(end <= time && time < start)
+        };
+        if in_dst {
+            dst_offset
+        } else {
+            self.std_offset
+        }
+    }
+}
+
+impl DayRule {
+    fn parse(text: &str) -> Option<Self> {
+        let (date, time) = match text.split_once('/') {
+            Some((date, time)) => (date, parse_offset(time)? as i64),
+            None => (text, 2 * 3600),
+        };
+        let date = if let Some(mwd) = date.strip_prefix('M') {
+            let mut parts = mwd.split('.').map(|p| p.parse::<u32>().ok());
+            let (m, w, d) = (parts.next()??, parts.next()??, parts.next()??);
+            ((1..=12).contains(&m) && (1..=5).contains(&w) && d <= 6).then_some(RuleDate::MonthWeekDay(m, w, d))?
+        } else if let Some(day) = date.strip_prefix('J') {
+            RuleDate::Julian(day.parse().ok().filter(|d| (1..=365).contains(d))?)
+        } else {
+            RuleDate::DayOfYear(date.parse().ok().filter(|d| *d <= 365)?)
+        };
+        Some(Self { date, time })
+    }
+
+    /// Seconds since the epoch of this change in `year`, read as if local time were UTC
+    fn local_time(&self, year: i32) -> Option<i64> {
+        let date = match self.date {
+            RuleDate::MonthWeekDay(month, week, weekday) => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                let first_match = 1 + (weekday + 7 - first.weekday().num_days_from_sunday()) % 7;
+                let mut day = first_match + (week - 1) * 7;
+                while NaiveDate::from_ymd_opt(year, month, day).is_none() {
+                    day -= 7;
+                }
+                NaiveDate::from_ymd_opt(year, month, day)?
+            }
+            RuleDate::Julian(day) => {
+                let date = NaiveDate::from_yo_opt(year, day)?;
+                if date.leap_year() && day >= 60 {
+                    date.succ_opt()?
+                } else {
+                    date
+                }
+            }
+            RuleDate::DayOfYear(day) => NaiveDate::from_yo_opt(year, day + 1)?,
+        };
+        Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp() + self.time)
+    }
+}
+
+/// Skip a zone abbreviation: letters, or any text in angle brackets
+fn skip_name(text: &str) -> Option<&str> {
+    if let Some(quoted) = text.strip_prefix('<') {
+        return quoted.split_once('>').map(|(_, rest)| rest);
+    }
+    let len = text.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(text.len());
+    (len >= 3).then(|| &text[len..])
+}
+
+/// Leading `[+-]hh[:mm[:ss]]` as seconds, and the text after it
+fn split_offset(text: &str) -> Option<(i32, &str)> {
+    let len = text
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == ':' || (i == 0 && (c == '+' || c == '-'))))
+        .map_or(text.len(), |(i, _)| i);
+    parse_offset(&text[..len]).map(|offset| (offset, &text[len..]))
+}
+
+/// `[+-]hh[:mm[:ss]]` or `[+-]hhmm` as signed seconds
+fn parse_offset(text: &str) -> Option<i32> {
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let mut parts: Vec<&str> = digits.split(':').collect();
+    if let [compact] = parts[..] {
+        if compact.len() == 4 {
+            parts = vec![&compact[..2], &compact[2..]];
+        }
+    }
+    if parts.len() > 3
+        || parts
+            .iter()
+            .any(|p| p.is_empty() || p.len() > 3 || !p.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return None;
+    }
+    let mut seconds = 0;
+    for (part, scale) in parts.iter().zip([3600, 60, 1]) {
+        let value: i32 = part.parse().ok()?;
+        if scale != 3600 && value >= 60 {
+            return None;
+        }
+        seconds += value * scale;
+    }
+    Some(sign * seconds)
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn utc(text: &str) -> i64 {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_utc()
+            .timestamp()
+    }
+
+    fn bundled(name: &str) -> ZoneRules {
+        ZoneRules::bundled(name).unwrap()
+    }
+
+    /// Version 2 TZif with an empty 32-bit block, the given transitions and types, and a footer
+    fn tzif(transitions: &[(i64, u8)], types: &[i32], footer: &str) -> Vec<u8> {
+        let header = |timecnt: usize, typecnt: usize, charcnt: usize| {
+            let mut bytes = b"TZif2".to_vec();
+            bytes.resize(20, 0);
+            for count in [0, 0, 0, timecnt, typecnt, charcnt] {
+                bytes.extend((count as u32).to_be_bytes());
+            }
+            bytes
+        };
+        let mut data = header(0, 0, 0);
+        data.extend(header(transitions.len(), types.len(), 4));
+        for (time, _) in transitions {
+            data.extend(time.to_be_bytes());
+        }
+        data.extend(transitions.iter().map(|&(_, index)| index));
+        for offset in types {
+            data.extend(offset.to_be_bytes());
+            data.extend([0, 0]);
+        }
+        data.extend(b"LMT\0");
+        data.extend(format!("\n{}\n", footer).bytes());
+        data
+    }
+
+    #[test]
+    fn every_bundled_zone_parses() {
+        for (name, tz) in BUNDLED_ZONES {
+            assert!(
+                PosixRule::parse(tz).is_some(),
+                "{} has an unreadable rule '{}'",
+                name,
+                tz
+            );
+        }
+        assert!(ZoneRules::bundled("Mars/Olympus_Mons").is_none());
+    }
+
+    #[test]
+    fn bundled_zones_match_the_host_database() {
+        let start = utc("2025-01-01 00:00:00");
+        for (name, _) in BUNDLED_ZONES {
+            let Some(data) = ZONEINFO_DIRS
+                .iter()
+                .find_map(|dir| fs::read(PathBuf::from(dir).join(name)).ok())
+            else {
+                continue;
+            };
+            let system = ZoneRules::from_tzif(name, &data).unwrap();
+            let bundled = bundled(name);
+            for hour in (0..3 * 366 * 24).step_by(3) {
+                let time = start + hour * 3600;
+                assert_eq!(bundled.offset_at(time), system.offset_at(time), "{} at {}", name, time);
+            }
+        }
+    }
+
+    #[test]
+    fn northern_daylight_saving_changes() {
+        let new_york = bundled("America/New_York");
+        assert_eq!(new_york.offset_at(utc("2024-03-10 06:59:59")), -5 * 3600);
+        assert_eq!(new_york.offset_at(utc("2024-03-10 07:00:00")), -4 * 3600);
+        assert_eq!(new_york.offset_at(utc("2024-11-03 05:59:59")), -4 * 3600);
+        assert_eq!(new_york.offset_at(utc("2024-11-03 06:00:00")), -5 * 3600);
+
+        // Explicit change times: London switches at 01:00 UTC both ways
+        let london = bundled("Europe/London");
+        assert_eq!(london.offset_at(utc("2024-03-31 00:59:59")), 0);
+        assert_eq!(london.offset_at(utc("2024-03-31 01:00:00")), 3600);
+        assert_eq!(london.offset_at(utc("2024-10-27 00:59:59")), 3600);
+        assert_eq!(london.offset_at(utc("2024-10-27 01:00:00")), 0);
+
+        // Dublin's standard time is summer time, so its "daylight" offset is the winter one
+        let dublin = bundled("Europe/Dublin");
+        assert_eq!(dublin.offset_at(utc("2024-01-15 12:00:00")), 0);
+        assert_eq!(dublin.offset_at(utc("2024-03-31 01:00:00")), 3600);
+        assert_eq!(dublin.offset_at(utc("2024-10-27 01:00:00")), 0);
+    }
+
+    #[test]
+    fn southern_daylight_saving_wraps_the_new_year() {
+        let sydney = bundled("Australia/Sydney");
+        assert_eq!(sydney.offset_at(utc("2024-01-15 00:00:00")), 11 * 3600);
+        assert_eq!(sydney.offset_at(utc("2024-04-06 15:59:59")), 11 * 3600);
+        assert_eq!(sydney.offset_at(utc("2024-04-06 16:00:00")), 10 * 3600);
+        assert_eq!(sydney.offset_at(utc("2024-10-05 15:59:59")), 10 * 3600);
+        assert_eq!(sydney.offset_at(utc("2024-10-05 16:00:00")), 11 * 3600);
+        assert_eq!(sydney.offset_at(utc("2024-12-31 23:00:00")), 11 * 3600);
+
+        // Santiago changes at 24:00 local Saturday
+        let santiago = bundled("America/Santiago");
+        assert_eq!(santiago.offset_at(utc("2024-04-07 02:59:59")), -3 * 3600);
+        assert_eq!(santiago.offset_at(utc("2024-04-07 03:00:00")), -4 * 3600);
+        assert_eq!(santiago.offset_at(utc("2024-09-08 03:59:59")), -4 * 3600);
+        assert_eq!(santiago.offset_at(utc("2024-09-08 04:00:00")), -3 * 3600);
+    }
+
+    #[test]
+    fn tzif_transitions_then_footer_rule() {
+        let data = tzif(
+            &[(-1000, 1), (1000, 2)],
+            &[1800, 3600, 7200],
+            "CET-1CEST,M3.5.0,M10.5.0/3",
+        );
+        let zone = ZoneRules::from_tzif("Test/Zone", &data).unwrap();
+        assert_eq!(zone.offset_at(-1001), 1800);
+        assert_eq!(zone.offset_at(-1000), 3600);
+        assert_eq!(zone.offset_at(999), 3600);
+        // After the last transition the footer decides
+        assert_eq!(zone.offset_at(utc("2024-01-15 12:00:00")), 3600);
+        assert_eq!(zone.offset_at(utc("2024-07-15 12:00:00")), 7200);
+
+        let fixed = ZoneRules::from_tzif("Test/Fixed", &tzif(&[], &[-10800], "<-03>3")).unwrap();
+        assert_eq!(fixed.offset_at(utc("2024-07-15 12:00:00")), -10800);
+    }
+
+    #[test]
+    fn malformed_tzif_is_invalid_data() {
+        let data = tzif(&[(0, 1)], &[0, 3600], "UTC0");
+        let mut bad_magic = data.clone();
+        bad_magic[0] = b'X';
+        let bad_index = tzif(&[(0, 5)], &[0, 3600], "UTC0");
+        for broken in [&bad_magic[..], &data[..60], &data[..20], &bad_index[..]] {
+            let err = ZoneRules::from_tzif("Test/Broken", broken).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn parse_names_and_offsets() {
+        assert_eq!(TimeZone::parse(" utc ").unwrap(), TimeZone::Utc);
+        assert_eq!(TimeZone::parse("+05:30").unwrap(), TimeZone::Fixed(19800));
+        assert_eq!(TimeZone::parse("-0800").unwrap(), TimeZone::Fixed(-28800));
+        assert_eq!(TimeZone::parse("UTC-3").unwrap(), TimeZone::Fixed(-10800));
+        assert!(matches!(TimeZone::parse("America/Halifax").unwrap(), TimeZone::Zone(_)));
+        for bad in [
+            "+25:00",
+            "+05:75",
+            "../etc/passwd",
+            "/etc/localtime",
+            "Mars/Olympus_Mons",
+            "",
+        ] {
+            let err = TimeZone::parse(bad).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn format_with_local_offsets() {
+        let time = utc("2024-06-01 12:30:00") as f64 + 0.25;
+        let halifax = TimeZone::Zone(bundled("America/Halifax"));
+        assert_eq!(halifax.format_time(time).unwrap(), "2024-06-01T09:30:00.250-03:00");
+        let st_johns = TimeZone::Zone(bundled("America/St_Johns"));
+        assert_eq!(st_johns.format_time(time).unwrap(), "2024-06-01T10:00:00.250-02:30");
+        assert_eq!(
+            TimeZone::Fixed(19800).format_time(time).unwrap(),
+            "2024-06-01T18:00:00.250+05:30"
+        );
+        assert_eq!(TimeZone::Utc.format_time(time).unwrap(), "2024-06-01T12:30:00.250Z");
+    }
+}
@@ -0,0 +1,211 @@
+// Track simplification for export
+// src/track.rs
+
+use crate::geo::local_offset_m;
+use crate::survey::Sounding;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// How far to thin a track before export
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Simplify {
+    /// Keep every point
+    #[default]
+    None,
+    /// Douglas-Peucker: drop points closer than this to the simplified line
+    Tolerance { meters: f64 },
+    /// Keep the most significant points up to this count
+    MaxPoints(usize),
+}
+
+/// Retained track point plus the depth range of the points folded into it
+///
+/// Each dropped point is attached to the nearer retained neighbour, so
+/// `min_depth_m` keeps shoals visible even when their vertex was removed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackVertex {
+    /// Index of the point in the input track
+    pub index: usize,
+    pub timestamp: f64,
+    pub lat: f64,
+    pub lon: f64,
+    pub depth_m: f64,
+    pub min_depth_m: f64,
+    pub max_depth_m: f64,
+}
+
+/// Simplify a track of soundings (in time order)
+pub fn simplify_track(track: &[Sounding], simplify: &Simplify) -> Vec<TrackVertex> {
+    let points: Vec<(f64, f64)> = track.iter().map(|s| (s.lat, s.lon)).collect();
+    let keep = match *simplify {
+        Simplify::None => (0..track.len()).collect(),
+        Simplify::Tolerance { meters } => simplify_indices(&points, meters, usize::MAX),
+        Simplify::MaxPoints(n) => simplify_indices(&points, 0.0, n),
+    };
+
+    let mut vertices: Vec<TrackVertex> = keep
+        .iter()
+        .map(|&i| TrackVertex {
+            index: i,
+            timestamp: track[i].timestamp,
+            lat: track[i].lat,
+            lon: track[i].lon,
+            depth_m: track[i].depth_m,
+            min_depth_m: track[i].depth_m,
+            max_depth_m: track[i].depth_m,
+        })
+        .collect();
+
+    for k in 0..keep.len().saturating_sub(1) {
+        let (a, b) = (keep[k], keep[k + 1]);
+        for (i, s) in track.iter().enumerate().take(b).skip(a + 1) {
+            let target = if i - a <= b - i { k } else { k + 1 };
+            let v = &mut vertices[target];
+            v.min_depth_m = v.min_depth_m.min(s.depth_m);
+            v.max_depth_m = v.max_depth_m.max(s.depth_m);
+        }
+    }
+    vertices
+}
+
+/// Indices kept by Douglas-Peucker, refined most-significant point first
+///
+/// Stops when every dropped point lies within `tolerance_m` of the line or
+/// `max_points` are kept. The first and last points are always kept.
+pub fn simplify_indices(points: &[(f64, f64)], tolerance_m: f64, max_points: usize) -> Vec<usize> {
+    if points.len() <= 2 || (max_points >= points.len() && tolerance_m <= 0.0) {
+        return (0..points.len()).collect();
+    }
+    let (ref_lat, ref_lon) = points[0];
+    let local: Vec<(f64, f64)> = points.iter().map(|&(lat, lon)| local_offset_m(ref_lat, ref_lon, lat, lon)).collect();
+
+    let mut keep = vec![0, points.len() - 1];
+    let mut heap = BinaryHeap::new();
+    if let Some(split) = farthest(&local, 0, points.len() - 1) {
+        heap.push(split);
+    }
+    while keep.len() < max_points.max(2) {
+        let Some(split) = heap.pop() else { break };
+        if split.distance <= tolerance_m {
+            break;
+        }
+        keep.push(split.index);
+        heap.extend(farthest(&local, split.start, split.index));
+        heap.extend(farthest(&local, split.index, split.end));
+    }
+    keep.sort_unstable();
+    keep
+}
+
+/// Segment `start..end` split at its point farthest from the chord
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Split {
+    distance: f64,
+    index: usize,
+    start: usize,
+    end: usize,
+}
+
+impl Eq for Split {}
+
+impl Ord for Split {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance)
+    }
+}
+
+impl PartialOrd for Split {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn farthest(points: &[(f64, f64)], start: usize, end: usize) -> Option<Split> {
+    (start + 1..end)
+        .map(|i| Split {
+            distance: segment_distance(points[i], points[start], points[end]),
+            index: i,
+            start,
+            end,
+        })
+        .max()
+}
+
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::offset_position;
+
+    /// Soundings at `(east, north)` metres from a fixed origin
+    fn track(points: &[(f64, f64, f64)]) -> Vec<Sounding> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, &(east, north, depth))| {
+                let (lat, lon) = offset_position(45.0, -83.0, east, north);
+                Sounding::new(i as f64, lat, lon, depth)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tolerance_keeps_only_significant_corners() {
+        // East 100 m with 0.5 m wiggles, then a corner and 100 m north
+        let points = [
+            (0.0, 0.0, 5.0),
+            (25.0, 0.5, 5.0),
+            (50.0, -0.5, 5.0),
+            (100.0, 0.0, 5.0),
+            (100.0, 50.0, 5.0),
+            (100.0, 100.0, 5.0),
+        ];
+        let latlon: Vec<(f64, f64)> = track(&points).iter().map(|s| (s.lat, s.lon)).collect();
+        assert_eq!(simplify_indices(&latlon, 2.0, usize::MAX), vec![0, 3, 5]);
+        assert_eq!(simplify_indices(&latlon, 0.1, usize::MAX), vec![0, 1, 2, 3, 5]);
+        assert_eq!(simplify_indices(&latlon[..2], 50.0, usize::MAX), vec![0, 1]);
+    }
+
+    #[test]
+    fn max_points_caps_the_track_most_significant_first() {
+        let points: Vec<(f64, f64, f64)> = (0..50).map(|i| (i as f64 * 10.0, (i % 7) as f64 * i as f64, 5.0)).collect();
+        let soundings = track(&points);
+        for n in [2, 5, 20] {
+            let vertices = simplify_track(&soundings, &Simplify::MaxPoints(n));
+            assert_eq!(vertices.len(), n);
+            assert_eq!((vertices[0].index, vertices[n - 1].index), (0, 49));
+        }
+        assert_eq!(simplify_track(&soundings, &Simplify::None).len(), 50);
+        let corner = track(&[(0.0, 0.0, 5.0), (50.0, 1.0, 5.0), (100.0, 0.0, 5.0), (100.0, 80.0, 5.0)]);
+        let kept: Vec<usize> = simplify_track(&corner, &Simplify::MaxPoints(3)).iter().map(|v| v.index).collect();
+        assert_eq!(kept, vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn dropped_shoals_fold_into_the_nearest_kept_vertex() {
+        let soundings = track(&[
+            (0.0, 0.0, 10.0),
+            (10.0, 0.0, 2.5),
+            (20.0, 0.0, 11.0),
+            (30.0, 0.0, 12.0),
+            (40.0, 0.0, 14.0),
+            (50.0, 0.0, 9.0),
+        ]);
+        let vertices = simplify_track(&soundings, &Simplify::Tolerance { meters: 1.0 });
+        assert_eq!(vertices.len(), 2);
+        let (first, last) = (vertices[0], vertices[1]);
+        assert_eq!(first.depth_m, 10.0);
+        assert_eq!((first.min_depth_m, first.max_depth_m), (2.5, 11.0));
+        assert_eq!((last.min_depth_m, last.max_depth_m), (9.0, 14.0));
+    }
+}
@@ -0,0 +1,167 @@
+// Dry-run validation of recordings for QC gates after copying cards
+// src/validate.rs
+
+use crate::bounds::{BoundsConfig, FLAG_LAT, FLAG_LON};
+use crate::parsers::warnings::{collect_warnings, ClockMonitor, ParseWarning, WarningKind};
+use crate::parsers::{open_with, FormatRegistry, ParseMode};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Issues kept in a report; counts keep going past this
+const MAX_LISTED_ISSUES: usize = 100;
+
+/// Outcome of walking one recording
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub path: PathBuf,
+    /// Detected format, empty when no parser recognized the file
+    pub format: String,
+    pub records: usize,
+    pub skipped_inputs: usize,
+    pub clock_regressions: usize,
+    /// Records with latitude or longitude outside the valid range
+    pub invalid_positions: usize,
+    /// Implausible field values, positions included
+    pub suspicious_values: usize,
+    /// Records without a position fix (0, 0)
+    pub unpositioned: usize,
+    /// Fatal error that stopped the walk
+    pub error: Option<String>,
+    /// First issues in file order
+    pub issues: Vec<ParseWarning>,
+}
+
+impl ValidationReport {
+    /// Nothing was skipped, out of order or implausible
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none() && self.skipped_inputs == 0 && self.clock_regressions == 0 && self.suspicious_values == 0
+    }
+
+    /// Single-line JSON object with the counts and listed issues
+    pub fn to_json(&self) -> String {
+        let text = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        let option = |v: Option<String>| v.unwrap_or_else(|| "null".to_string());
+        let issues: Vec<String> = self
+            .issues
+            .iter()
+            .map(|w| {
+                format!(
+                    "{{\"kind\":\"{}\",\"line\":{},\"timestamp\":{},\"message\":{}}}",
+                    w.kind.name(),
+                    option(w.line.map(|l| l.to_string())),
+                    option(w.timestamp.filter(|t| t.is_finite()).map(|t| t.to_string())),
+                    text(&w.message)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"path\":{},\"format\":{},\"valid\":{},\"records\":{},\"skipped_inputs\":{},\"clock_regressions\":{},\"invalid_positions\":{},\"suspicious_values\":{},\"unpositioned\":{},\"error\":{},\"issues\":[{}]}}",
+            text(&self.path.to_string_lossy()),
+            text(&self.format),
+            self.is_valid(),
+            self.records,
+            self.skipped_inputs,
+            self.clock_regressions,
+            self.invalid_positions,
+            self.suspicious_values,
+            self.unpositioned,
+            option(self.error.as_deref().map(text)),
+            issues.join(",")
+        )
+    }
+}
+
+/// Walk a recording end to end and report problems without keeping its pings
+///
+/// The file is read leniently so one bad record does not hide the rest;
+/// only failures to open or detect the file end up in `error`.
+pub fn validate(path: &Path, registry: &FormatRegistry) -> ValidationReport {
+    let mut report = ValidationReport {
+        path: path.to_path_buf(),
+        ..Default::default()
+    };
+    let (result, warnings) = collect_warnings(|| walk(path, registry, &mut report));
+    if let Err(err) = result {
+        report.error = Some(err.to_string());
+    }
+    for warning in warnings {
+        match warning.kind {
+            WarningKind::SkippedInput => report.skipped_inputs += 1,
+            WarningKind::ClockRegression => report.clock_regressions += 1,
+            WarningKind::SuspiciousValue => report.suspicious_values += 1,
+        }
+        if report.issues.len() < MAX_LISTED_ISSUES {
+            report.issues.push(warning);
+        }
+    }
+    report
+}
+
+fn walk(path: &Path, registry: &FormatRegistry, report: &mut ValidationReport) -> io::Result<()> {
+    let parser = registry
+        .detect(path)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "unrecognized sonar format"))?;
+    report.format = parser.name().to_string();
+
+    let mut clock = ClockMonitor::default();
+    let bounds = BoundsConfig::default();
+    for ping in open_with(parser, path, ParseMode::Lenient)? {
+        let mut ping = ping?;
+        report.records += 1;
+        clock.observe(&ping);
+        if ping.lat == 0.0 && ping.lon == 0.0 {
+            report.unpositioned += 1;
+        }
+        if bounds.check(&mut ping) & (FLAG_LAT | FLAG_LON) != 0 {
+            report.invalid_positions += 1;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch;
+    use std::fs;
+
+    #[test]
+    fn counts_problems_in_a_recording() {
+        let path = scratch("validate", "track.csv");
+        fs::write(
+            &path,
+            "Time,Depth (m),Latitude,Longitude\n\
+             1700000000,2.0,44.6,-63.5\n\
+             1700000001,2.1,95.0,-63.5\n\
+             1700000002,2.2,0,0\n\
+             not,a,row\n\
+             1700000001,2.3,44.6,-63.5\n",
+        )
+        .unwrap();
+        let report = validate(&path, &FormatRegistry::default());
+        fs::remove_file(&path).unwrap();
+        assert_eq!((report.format.as_str(), report.records, report.error.clone()), ("deeper-csv", 4, None));
+        assert_eq!((report.skipped_inputs, report.clock_regressions, report.suspicious_values), (1, 1, 1));
+        assert_eq!((report.invalid_positions, report.unpositioned), (1, 1));
+        assert!(!report.is_valid());
+        let kinds: Vec<WarningKind> = report.issues.iter().map(|w| w.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![WarningKind::SkippedInput, WarningKind::SuspiciousValue, WarningKind::ClockRegression]
+        );
+        let json = report.to_json();
+        assert!(json.contains("\"valid\":false,\"records\":4,\"skipped_inputs\":1"));
+        assert!(json.contains("\"kind\":\"skipped_input\",\"line\":5"), "{}", json);
+    }
+
+    #[test]
+    fn unreadable_files_report_an_error() {
+        let path = scratch("validate", "notes.bin");
+        fs::write(&path, [0u8, 1, 2, 3]).unwrap();
+        let report = validate(&path, &FormatRegistry::default());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(report.error.as_deref(), Some("unrecognized sonar format"));
+        assert!(!report.is_valid());
+        assert!(report.to_json().contains("\"format\":\"\",\"valid\":false"));
+    }
+}
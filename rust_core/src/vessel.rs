@@ -0,0 +1,297 @@
+// Vessel geometry corrections applied to soundings
+// src/vessel.rs
+
+use crate::geo::{distance_m, offset_position, vessel_to_local};
+use crate::survey::{Ping, Sounding, VerticalReference};
+
+/// Transducer mounting relative to the GPS antenna, plus draft
+///
+/// Offsets follow the vessel frame: `x_m` positive to starboard, `y_m`
+/// positive forward, `z_m` positive down from the antenna. `draft_m` is
+/// the transducer depth below the waterline.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VesselConfig {
+    pub x_m: f64,
+    pub y_m: f64,
+    pub z_m: f64,
+    pub draft_m: f64,
+}
+
+impl VesselConfig {
+    pub fn new(x_m: f64, y_m: f64, z_m: f64, draft_m: f64) -> Self {
+        Self { x_m, y_m, z_m, draft_m }
+    }
+
+    /// Move the sounding from the antenna to the transducer and reference depth to the waterline
+    ///
+    /// Draft is only added to transducer-referenced depths, so depths that
+    /// were already reduced are not corrected twice.
+    pub fn apply(&self, sounding: &Sounding) -> Sounding {
+        let (east, north) = vessel_to_local(self.x_m, self.y_m, sounding.heading_deg);
+        let (lat, lon) = offset_position(sounding.lat, sounding.lon, east, north);
+        let (depth_m, reference) = match sounding.reference {
+            VerticalReference::Transducer => (sounding.depth_m + self.draft_m, VerticalReference::Waterline),
+            other => (sounding.depth_m, other),
+        };
+
+        Sounding {
+            lat,
+            lon,
+            depth_m,
+            reference,
+            ..*sounding
+        }
+    }
+
+    /// Correct a batch of soundings in place
+    pub fn apply_all(&self, soundings: &mut [Sounding]) {
+        for sounding in soundings.iter_mut() {
+            *sounding = self.apply(sounding);
+        }
+    }
+
+    /// Move ping positions from the antenna to the transducer
+    ///
+    /// Depths are left transducer-referenced; draft is added when soundings
+    /// are derived. Unpositioned pings are left where they are.
+    pub fn apply_pings(&self, pings: &mut [Ping]) {
+        if self.x_m == 0.0 && self.y_m == 0.0 {
+            return;
+        }
+        for p in pings.iter_mut().filter(|p| p.lat != 0.0 || p.lon != 0.0) {
+            let (east, north) = vessel_to_local(self.x_m, self.y_m, p.heading_deg);
+            (p.lat, p.lon) = offset_position(p.lat, p.lon, east, north);
+        }
+    }
+
+    /// Only the draft, for soundings derived from pings already moved by [`Self::apply_pings`]
+    pub fn draft_only(&self) -> Self {
+        Self {
+            draft_m: self.draft_m,
+            ..Default::default()
+        }
+    }
+
+    /// Transducer elevation given the GPS antenna altitude
+    pub fn transducer_altitude(&self, antenna_altitude_m: f64) -> f64 {
+        antenna_altitude_m - self.z_m
+    }
+}
+
+/// Towed transducer geometry
+///
+/// The tow point is given in the vessel frame relative to the GPS antenna
+/// (`tow_point_y_m` negative aft) and its height above the waterline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layback {
+    pub cable_out_m: f64,
+    pub towfish_depth_m: f64,
+    pub tow_point_y_m: f64,
+    pub tow_point_height_m: f64,
+    /// Fraction of the straight-cable layback actually achieved (cable sag); 1.0 = taut
+    pub catenary_factor: f64,
+    /// Place the fish on the vessel's own track rather than straight astern
+    pub follow_track: bool,
+}
+
+impl Default for Layback {
+    fn default() -> Self {
+        Self {
+            cable_out_m: 0.0,
+            towfish_depth_m: 0.0,
+            tow_point_y_m: 0.0,
+            tow_point_height_m: 0.0,
+            catenary_factor: 1.0,
+            follow_track: true,
+        }
+    }
+}
+
+impl Layback {
+    /// Horizontal distance from the antenna back to the towfish
+    pub fn horizontal_m(&self) -> f64 {
+        let vertical = self.towfish_depth_m + self.tow_point_height_m;
+        let cable = (self.cable_out_m.powi(2) - vertical.powi(2)).max(0.0).sqrt();
+        cable * self.catenary_factor - self.tow_point_y_m
+    }
+
+    /// Move ping positions from the antenna to the towfish
+    ///
+    /// Along-track placement uses where the antenna was `horizontal_m()`
+    /// earlier on the track; before the track is long enough, and in
+    /// straight-astern mode, the offset follows course (or heading) instead.
+    pub fn apply_pings(&self, pings: &mut [Ping]) {
+        let layback = self.horizontal_m();
+        if layback == 0.0 {
+            return;
+        }
+        let mut order: Vec<usize> = (0..pings.len()).filter(|&i| pings[i].lat != 0.0 || pings[i].lon != 0.0).collect();
+        order.sort_by(|&a, &b| pings[a].timestamp.total_cmp(&pings[b].timestamp));
+
+        // Antenna track with cumulative distance, one entry per timestamp
+        let mut track: Vec<(f64, f64, f64, f64)> = Vec::new();
+        for &i in &order {
+            let p = &pings[i];
+            match track.last() {
+                Some(last) if last.0 == p.timestamp => {}
+                Some(&(_, lat, lon, dist)) => track.push((p.timestamp, p.lat, p.lon, dist + distance_m(lat, lon, p.lat, p.lon))),
+                None => track.push((p.timestamp, p.lat, p.lon, 0.0)),
+            }
+        }
+
+        for &i in &order {
+            let p = &pings[i];
+            let k = track.partition_point(|e| e.0 < p.timestamp);
+            let target = track[k].3 - layback;
+            let along = if self.follow_track && track.len() > 1 && (0.0..=track[k].3).contains(&target) {
+                let j = track.partition_point(|e| e.3 <= target).clamp(1, track.len() - 1);
+                let (a, b) = (track[j - 1], track[j]);
+                let f = if b.3 > a.3 { (target - a.3) / (b.3 - a.3) } else { 0.0 };
+                Some((a.1 + (b.1 - a.1) * f, a.2 + (b.2 - a.2) * f))
+            } else {
+                None
+            };
+            let (lat, lon) = along.unwrap_or_else(|| {
+                let (east, north) = vessel_to_local(0.0, -layback, p.cog_deg.unwrap_or(p.heading_deg));
+                offset_position(p.lat, p.lon, east, north)
+            });
+            pings[i].lat = lat;
+            pings[i].lon = lon;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::local_offset_m;
+
+    const ORIGIN: (f64, f64) = (44.0, -63.0);
+
+    fn sounding(heading_deg: f64, reference: VerticalReference) -> Sounding {
+        Sounding {
+            heading_deg,
+            reference,
+            ..Sounding::new(0.0, ORIGIN.0, ORIGIN.1, 10.0)
+        }
+    }
+
+    #[test]
+    fn transducer_offset_rotates_with_heading() {
+        // 2 m to starboard and 5 m forward of the antenna
+        let vessel = VesselConfig::new(2.0, 5.0, 1.5, 0.4);
+        for (heading, east, north) in [(0.0, 2.0, 5.0), (90.0, 5.0, -2.0), (180.0, -2.0, -5.0)] {
+            let moved = vessel.apply(&sounding(heading, VerticalReference::Transducer));
+            let (e, n) = local_offset_m(ORIGIN.0, ORIGIN.1, moved.lat, moved.lon);
+            assert!((e - east).abs() < 0.01 && (n - north).abs() < 0.01, "{} {} {}", heading, e, n);
+        }
+        assert_eq!(vessel.transducer_altitude(10.0), 8.5);
+    }
+
+    #[test]
+    fn ping_positions_move_to_the_transducer() {
+        let vessel = VesselConfig::new(2.0, 5.0, 1.5, 0.4);
+        let ping = |heading_deg, lat, lon| Ping {
+            heading_deg,
+            lat,
+            lon,
+            depth_m: 10.0,
+            ..Default::default()
+        };
+        let mut pings = vec![ping(90.0, ORIGIN.0, ORIGIN.1), ping(90.0, 0.0, 0.0)];
+        vessel.apply_pings(&mut pings);
+        let (e, n) = local_offset_m(ORIGIN.0, ORIGIN.1, pings[0].lat, pings[0].lon);
+        assert!((e - 5.0).abs() < 0.01 && (n + 2.0).abs() < 0.01, "{} {}", e, n);
+        assert_eq!(pings[0].depth_m, 10.0);
+        assert_eq!((pings[1].lat, pings[1].lon), (0.0, 0.0));
+
+        // Soundings from moved pings take the draft once and stay put
+        let moved = vessel.draft_only().apply(&pings[0].to_sounding());
+        assert_eq!((moved.lat, moved.lon, moved.depth_m), (pings[0].lat, pings[0].lon, 10.4));
+    }
+
+    #[test]
+    fn draft_is_added_to_transducer_depths_only() {
+        let vessel = VesselConfig::new(0.0, 0.0, 0.0, 0.4);
+        let raw = vessel.apply(&sounding(0.0, VerticalReference::Transducer));
+        assert_eq!((raw.depth_m, raw.reference), (10.4, VerticalReference::Waterline));
+        // Applying again is a no-op for depth, as is any already reduced reference
+        assert_eq!(vessel.apply(&raw).depth_m, 10.4);
+        let charted = vessel.apply(&sounding(0.0, VerticalReference::ChartDatum));
+        assert_eq!((charted.depth_m, charted.reference), (10.0, VerticalReference::ChartDatum));
+    }
+
+    /// One ping per meter: 100 m north, then 100 m east, heading along each leg
+    fn turn() -> Vec<Ping> {
+        (0..=200)
+            .map(|i| {
+                let (east, north, heading_deg) = if i <= 100 {
+                    (0.0, i as f64, 0.0)
+                } else {
+                    ((i - 100) as f64, 100.0, 90.0)
+                };
+                let (lat, lon) = offset_position(ORIGIN.0, ORIGIN.1, east, north);
+                Ping {
+                    timestamp: i as f64,
+                    lat,
+                    lon,
+                    heading_deg,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn local(p: &Ping) -> (f64, f64) {
+        local_offset_m(ORIGIN.0, ORIGIN.1, p.lat, p.lon)
+    }
+
+    fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 0.05 && (a.1 - b.1).abs() < 0.05
+    }
+
+    #[test]
+    fn horizontal_layback_from_cable_geometry() {
+        let layback = Layback {
+            cable_out_m: 10.0,
+            towfish_depth_m: 5.0,
+            tow_point_y_m: -2.0,
+            tow_point_height_m: 1.0,
+            ..Default::default()
+        };
+        assert!((layback.horizontal_m() - 10.0).abs() < 1e-9);
+        let sagging = Layback { catenary_factor: 0.5, ..layback };
+        assert!((sagging.horizontal_m() - 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn straight_astern_offsets_along_heading() {
+        let layback = Layback {
+            cable_out_m: 30.0,
+            follow_track: false,
+            ..Default::default()
+        };
+        let mut pings = turn();
+        layback.apply_pings(&mut pings);
+        // Heading north: 30 m south of the antenna
+        assert!(close(local(&pings[50]), (0.0, 20.0)), "{:?}", local(&pings[50]));
+        // Heading east just after the turn: 30 m west, off the vessel's track
+        assert!(close(local(&pings[120]), (-10.0, 100.0)), "{:?}", local(&pings[120]));
+    }
+
+    #[test]
+    fn follow_track_keeps_the_fish_on_the_track_through_a_turn() {
+        let layback = Layback {
+            cable_out_m: 30.0,
+            ..Default::default()
+        };
+        let mut pings = turn();
+        layback.apply_pings(&mut pings);
+        assert!(close(local(&pings[50]), (0.0, 20.0)), "{:?}", local(&pings[50]));
+        // 20 m past the corner the fish is still 10 m short of it on the first leg
+        assert!(close(local(&pings[120]), (0.0, 90.0)), "{:?}", local(&pings[120]));
+        assert!(close(local(&pings[160]), (30.0, 100.0)), "{:?}", local(&pings[160]));
+        // Before 30 m of track exist the fish falls back to straight astern
+        assert!(close(local(&pings[10]), (0.0, -20.0)), "{:?}", local(&pings[10]));
+    }
+}
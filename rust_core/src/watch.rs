@@ -0,0 +1,211 @@
+// Watch-folder ingestion of newly copied recordings
+// src/watch.rs
+
+use crate::batch::{discover_files, summarize_file, RecordingSummary};
+use crate::parsers::FormatRegistry;
+use crate::pipeline::{run_pipeline, Pipeline, PipelineReport};
+use crate::survey::Ping;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileState {
+    /// Seen with this size and modification time; not yet stable
+    Copying(u64, Option<SystemTime>),
+    Done,
+}
+
+/// Polls a folder for recordings whose copy has finished
+///
+/// A file is reported once its size and modification time are unchanged
+/// between two consecutive polls, so half-copied files are never parsed.
+#[derive(Debug, Clone)]
+pub struct FolderWatcher {
+    pub dir: PathBuf,
+    pub pattern: String,
+    pub recursive: bool,
+    files: HashMap<PathBuf, FileState>,
+}
+
+impl FolderWatcher {
+    pub fn new<P: AsRef<Path>>(dir: P, pattern: &str, recursive: bool) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            pattern: pattern.to_string(),
+            recursive,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Mark everything currently in the folder as already processed
+    pub fn skip_existing(&mut self) -> io::Result<()> {
+        for path in discover_files(&self.dir, &self.pattern, self.recursive)? {
+            self.files.insert(path, FileState::Done);
+        }
+        Ok(())
+    }
+
+    /// Files that became ready since the last poll, sorted by path
+    pub fn poll(&mut self) -> io::Result<Vec<PathBuf>> {
+        let mut ready = Vec::new();
+        for path in discover_files(&self.dir, &self.pattern, self.recursive)? {
+            // The file may vanish between listing and stat; pick it up next time
+            let Ok(meta) = fs::metadata(&path) else { continue };
+            let current = FileState::Copying(meta.len(), meta.modified().ok());
+            match self.files.get(&path) {
+                Some(FileState::Done) => {}
+                Some(previous) if *previous == current => {
+                    self.files.insert(path.clone(), FileState::Done);
+                    ready.push(path);
+                }
+                _ => {
+                    self.files.insert(path, current);
+                }
+            }
+        }
+        Ok(ready)
+    }
+}
+
+/// Poll `watcher` every `interval` and hand each finished recording to `handler`
+///
+/// Recordings that fail to parse are passed with `error` set and no pings.
+/// Runs until `handler` returns false.
+pub fn watch_folder<F>(watcher: &mut FolderWatcher, registry: &FormatRegistry, interval: Duration, mut handler: F) -> io::Result<()>
+where
+    F: FnMut(&RecordingSummary, &[Ping]) -> bool,
+{
+    loop {
+        for path in watcher.poll()? {
+            let _span = tracing::info_span!("ingest", path = %path.display()).entered();
+            let (summary, pings) = summarize_file(&path, registry);
+            if let Some(err) = &summary.error {
+                tracing::warn!(error = %err, "failed to ingest recording");
+            }
+            if !handler(&summary, &pings) {
+                return Ok(());
+            }
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// Run `pipeline` over each recording that finishes copying into its input folder
+///
+/// The pipeline's input, pattern and recursion select the watched files.
+/// Each recording is processed on its own and its outputs are named after it,
+/// so `points.xyz` becomes `points_<recording>.xyz`. Runs until `handler`
+/// returns false; recordings already present when watching starts are skipped.
+pub fn watch_folder_with_pipeline<F>(pipeline: &Pipeline, registry: &FormatRegistry, interval: Duration, mut handler: F) -> io::Result<()>
+where
+    F: FnMut(&Path, io::Result<PipelineReport>) -> bool,
+{
+    let mut watcher = FolderWatcher::new(&pipeline.input, &pipeline.pattern, pipeline.recursive);
+    watcher.skip_existing()?;
+    loop {
+        for path in watcher.poll()? {
+            let _span = tracing::info_span!("ingest", path = %path.display()).entered();
+            let result = run_pipeline(&for_recording(pipeline, &path), registry);
+            if let Err(err) = &result {
+                tracing::warn!(error = %err, "pipeline failed on recording");
+            }
+            if !handler(&path, result) {
+                return Ok(());
+            }
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// `pipeline` narrowed to one recording, with outputs suffixed by its name
+fn for_recording(pipeline: &Pipeline, recording: &Path) -> Pipeline {
+    let name = recording.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+    let mut single = pipeline.clone();
+    single.input = recording.to_path_buf();
+    for output in &mut single.outputs {
+        let stem = output.path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+        let file = match output.path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => format!("{}_{}.{}", stem, name, ext),
+            None => format!("{}_{}", stem, name),
+        };
+        output.path.set_file_name(file);
+    }
+    single
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch_dir;
+
+    fn track(rows: usize) -> String {
+        let mut csv = String::from("Time,Depth (m),Latitude,Longitude\n");
+        for i in 0..rows {
+            csv.push_str(&format!("{},{},{},{}\n", 1_700_000_000 + i, 3.0, 44.6 + i as f64 * 1e-5, -63.5));
+        }
+        csv
+    }
+
+    #[test]
+    fn reports_a_file_after_two_identical_polls() {
+        let dir = scratch_dir("watch", "poll");
+        let mut watcher = FolderWatcher::new(&dir, "*.csv", false);
+        fs::write(dir.join("a.csv"), track(2)).unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        assert!(watcher.poll().unwrap().is_empty());
+
+        // Still growing: the size changed, so it is not ready yet
+        fs::write(dir.join("a.csv"), track(5)).unwrap();
+        assert!(watcher.poll().unwrap().is_empty());
+        assert_eq!(watcher.poll().unwrap(), vec![dir.join("a.csv")]);
+        // Reported once only
+        assert!(watcher.poll().unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn skip_existing_ignores_files_already_present() {
+        let dir = scratch_dir("watch", "skip");
+        fs::write(dir.join("old.csv"), track(2)).unwrap();
+        let mut watcher = FolderWatcher::new(&dir, "*.csv", false);
+        watcher.skip_existing().unwrap();
+        fs::write(dir.join("new.csv"), track(2)).unwrap();
+        assert!(watcher.poll().unwrap().is_empty());
+        assert_eq!(watcher.poll().unwrap(), vec![dir.join("new.csv")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn runs_the_pipeline_on_each_new_recording() {
+        let dir = scratch_dir("watch", "pipeline");
+        fs::create_dir_all(dir.join("card")).unwrap();
+        fs::write(dir.join("card/before.csv"), track(3)).unwrap();
+        let text = "[input]\npath = \"card\"\npattern = \"*.csv\"\n[[output]]\nformat = \"xyz\"\npath = \"out/points.xyz\"\n";
+        let pipeline = Pipeline::from_toml(text, &dir).unwrap();
+
+        let card = dir.join("card");
+        let copier = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            fs::write(card.join("after.csv"), track(4)).unwrap();
+        });
+        let mut reports = Vec::new();
+        watch_folder_with_pipeline(&pipeline, &FormatRegistry::default(), Duration::from_millis(10), |path, report| {
+            reports.push((path.to_path_buf(), report.unwrap()));
+            false
+        })
+        .unwrap();
+        copier.join().unwrap();
+
+        assert_eq!(reports.len(), 1);
+        let (path, report) = &reports[0];
+        assert_eq!(path, &dir.join("card/after.csv"));
+        assert_eq!(report.sounding_count, 4);
+        assert_eq!(report.written, vec![dir.join("out/points_after.xyz")]);
+        assert_eq!(fs::read_to_string(dir.join("out/points_after.xyz")).unwrap().lines().count(), 4);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}